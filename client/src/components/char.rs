@@ -6,30 +6,33 @@ use nphysics2d::object::{
     BodyPartHandle, BodyStatus, ColliderDesc, DefaultBodyHandle, DefaultColliderHandle,
     RigidBodyDesc,
 };
-use rustarok_common::common::{v2, EngineTime, Mat4, Vec2};
-use serde::Deserialize;
-use serde::Serialize;
+use rustarok_common::common::{v2, Mat4, Vec2};
 use specs::prelude::*;
 
 use crate::audio::sound_sys::AudioCommandCollectorComponent;
 use crate::components::controller::{
     CameraComponent, HumanInputComponent, LocalPlayerControllerComponent, SkillKey,
 };
-use crate::components::skills::basic_attack::{BasicAttackType, WeaponType};
+use crate::components::skills::basic_attack::BasicAttackType;
 use crate::components::skills::skills::Skills;
-use crate::components::status::status::Statuses;
 use crate::configs::DevConfig;
 use crate::grf::SpriteResource;
 use crate::render::render_command::RenderCommandCollector;
 use crate::runtime_assets::map::PhysicEngine;
-use crate::systems::{Sprites, SystemVariables};
+use crate::systems::Sprites;
 use crate::ElapsedTime;
 use rustarok_common::components::char::{
-    AuthorizedCharStateComponent, CharDir, CharEntityId, CharOutlook, CharState, CharType,
-    CollisionGroup, ControllerEntityId, EntityTarget, JobId, MonsterId, Sex, Team,
+    AuthorizedCharStateComponent, CastingSkillData, CharDir, CharEntityId, CharOutlook, CharState,
+    CharType, CollisionGroup, ControllerEntityId, EntityTarget, JobId, MonsterId, Sex, Team,
 };
 use rustarok_common::components::controller::ControllerComponent;
 use rustarok_common::components::job_ids::JobSpriteId;
+use rustarok_common::components::status::StatusesComponent;
+
+pub use rustarok_common::char_attr::{
+    percentage, BonusDurations, CharAttributeModifier, CharAttributeModifierCollector,
+    CharAttributes, CharAttributesBonuses, Percentage,
+};
 
 #[derive(Clone, Copy)]
 #[allow(dead_code)]
@@ -351,6 +354,7 @@ impl CharacterEntityBuilder {
                     JobId::CRUSADER | JobId::SWORDMAN | JobId::ARCHER | JobId::RANGER | JobId::ASSASSIN | JobId::ROGUE | JobId::KNIGHT | JobId::WIZARD | JobId::SAGE | JobId::ALCHEMIST | JobId::BLACKSMITH | JobId::PRIEST | JobId::MONK | JobId::GUNSLINGER =>
                         CharType::Player,
                     JobId::Barricade => CharType::Minion,
+                    JobId::Tower | JobId::Inhibitor | JobId::Core => CharType::Structure,
                 },
                 char_builder.outlook,
                 char_builder.job_id,
@@ -408,17 +412,6 @@ impl CharacterEntityBuilder {
 #[derive(Eq, PartialEq, Hash)]
 pub struct ComponentRadius(pub i32);
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct CastingSkillData {
-    pub target_area_pos: Option<Vec2>,
-    pub char_to_skill_dir_when_casted: Vec2,
-    pub target_entity: Option<CharEntityId>,
-    pub cast_started: ElapsedTime,
-    pub cast_ends: ElapsedTime,
-    pub can_move: bool,
-    pub skill: Skills,
-}
-
 #[derive(Clone, Debug, PartialEq)]
 pub enum ClientCharState {
     Idle,
@@ -469,8 +462,8 @@ pub fn get_sprite_index(state: &CharState, is_monster: bool) -> usize {
         //        (CharState::StandBy, false) => CharActionIndex::StandBy as usize,
         //        (CharState::Attacking { .. }, false) => CharActionIndex::Attacking3 as usize,
         //        (CharState::ReceivingDamage, false) => CharActionIndex::ReceivingDamage as usize,
-        //        (CharState::Dead, false) => CharActionIndex::Dead as usize,
-        //        (CharState::CastingSkill { .. }, false) => CharActionIndex::CastingSpell as usize,
+        (CharState::Dead, false) => CharActionIndex::Dead as usize,
+        (CharState::CastingSkill { .. }, false) => CharActionIndex::CastingSpell as usize,
 
         // monster
         (CharState::Idle, true) => MonsterActionIndex::Idle as usize,
@@ -480,8 +473,8 @@ pub fn get_sprite_index(state: &CharState, is_monster: bool) -> usize {
         //        (CharState::ReceivingDamage, true) => {
         //            MonsterActionIndex::ReceivingDamage as usize
         //        }
-        //        (CharState::Dead, true) => MonsterActionIndex::Die as usize,
-        //        (CharState::CastingSkill { .. }, true) => MonsterActionIndex::Attack as usize,
+        (CharState::Dead, true) => MonsterActionIndex::Die as usize,
+        (CharState::CastingSkill { .. }, true) => MonsterActionIndex::Attack as usize,
     }
 }
 
@@ -501,131 +494,6 @@ impl SpriteBoundingRect {
     }
 }
 
-const PERCENTAGE_FACTOR: i32 = 1000;
-
-// able to represent numbers in 0.1% discrete steps
-#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
-#[serde(from = "i32")]
-pub struct Percentage {
-    value: i32,
-}
-
-impl From<i32> for Percentage {
-    fn from(value: i32) -> Self {
-        percentage(value)
-    }
-}
-
-pub fn percentage(value: i32) -> Percentage {
-    Percentage {
-        value: value * PERCENTAGE_FACTOR,
-    }
-}
-
-impl Percentage {
-    pub fn is_not_zero(&self) -> bool {
-        self.value != 0
-    }
-
-    pub fn as_i16(&self) -> i16 {
-        (self.value / PERCENTAGE_FACTOR) as i16
-    }
-
-    pub fn limit(&mut self, min: Percentage, max: Percentage) {
-        self.value = self.value.min(max.value).max(min.value);
-    }
-
-    pub fn apply(&mut self, modifier: &CharAttributeModifier) {
-        match modifier {
-            CharAttributeModifier::AddPercentage(p) => {
-                self.value += p.value;
-            }
-            CharAttributeModifier::AddValue(_v) => panic!(
-                "{:?} += {:?}, you cannot add value to a percentage",
-                self, modifier
-            ),
-            CharAttributeModifier::IncreaseByPercentage(p) => {
-                self.value = self.increase_by(*p).value;
-            }
-        }
-    }
-
-    pub fn as_f32(&self) -> f32 {
-        (self.value as f32 / PERCENTAGE_FACTOR as f32) / 100.0
-    }
-
-    pub fn increase_by(&self, p: Percentage) -> Percentage {
-        let change = self.value / 100 * p.value;
-        Percentage {
-            value: self.value + change / PERCENTAGE_FACTOR,
-        }
-    }
-
-    pub fn add_me_to(&self, num: i32) -> i32 {
-        let f = PERCENTAGE_FACTOR as i64;
-        let change = (num as i64) * f / 100 * (self.value as i64) / f / f;
-        return num + (change as i32);
-    }
-
-    pub fn of(&self, num: i32) -> i32 {
-        let f = PERCENTAGE_FACTOR as i64;
-        let change = (num as i64) * f / 100 * (self.value as i64) / f / f;
-        return change as i32;
-    }
-
-    pub fn subtract_me_from(&self, num: i32) -> i32 {
-        let f = PERCENTAGE_FACTOR as i64;
-        let change = (num as i64) * f / 100 * (self.value as i64) / f / f;
-        return num - (change as i32);
-    }
-
-    #[allow(dead_code)]
-    pub fn div(&self, other: i32) -> Percentage {
-        Percentage {
-            value: self.value / other,
-        }
-    }
-
-    pub fn subtract(&self, other: Percentage) -> Percentage {
-        Percentage {
-            value: self.value - other.value,
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_percentages() {
-        assert_eq!(percentage(70).increase_by(percentage(10)).as_i16(), 77);
-        assert_eq!(percentage(70).increase_by(percentage(0)).as_i16(), 70);
-        assert_eq!(percentage(70).increase_by(percentage(-10)).as_i16(), 63);
-        assert_eq!(percentage(100).increase_by(percentage(200)).as_i16(), 300);
-        assert_eq!(percentage(10).add_me_to(200), 220);
-        assert_eq!(percentage(70).add_me_to(600), 1020);
-        assert_eq!(percentage(70).div(10).add_me_to(600), 642);
-        assert_eq!(percentage(-10).add_me_to(200), 180);
-        assert_eq!(percentage(50).add_me_to(76), 114);
-        assert_eq!(percentage(50).add_me_to(10_000), 15_000);
-        assert_eq!(percentage(10).of(200), 20);
-        assert_eq!(percentage(70).of(600), 420);
-        assert_eq!(percentage(70).div(10).of(600), 42);
-        assert_eq!(percentage(50).of(76), 38);
-        assert_eq!(percentage(50).of(10_000), 5_000);
-        assert_eq!(percentage(10).subtract_me_from(200), 180);
-        assert_eq!(percentage(40).subtract_me_from(10_000), 6_000);
-        assert_eq!(percentage(70).subtract_me_from(600), 180);
-        assert_eq!(percentage(50).subtract_me_from(76), 38);
-        assert_eq!(percentage(100).as_f32(), 1.0);
-        assert_eq!(percentage(50).as_f32(), 0.5);
-        assert_eq!(percentage(5).as_f32(), 0.05);
-        assert_eq!(percentage(5).div(10).as_f32(), 0.005);
-        assert_eq!(percentage(-5).div(10).as_f32(), -0.005);
-    }
-}
-
 pub fn get_sprite_and_action_index<'a>(
     outlook: &CharOutlook,
     sprites: &'a Sprites,
@@ -653,306 +521,6 @@ pub fn get_sprite_and_action_index<'a>(
     };
 }
 
-#[derive(Clone, Debug, Deserialize)]
-pub struct CharAttributes {
-    pub max_hp: i32,
-    pub attack_damage: u16,
-    pub movement_speed: Percentage,
-    pub attack_range: Percentage,
-    pub attack_speed: Percentage,
-    pub armor: Percentage,
-    pub healing: Percentage,
-    pub hp_regen: Percentage,
-    pub mana_regen: Percentage,
-}
-
-#[derive(Clone, Debug)]
-pub struct CharAttributesBonuses {
-    pub attrs: CharAttributes,
-    pub durations: BonusDurations,
-}
-
-impl CharAttributes {
-    pub fn zero() -> CharAttributes {
-        CharAttributes {
-            movement_speed: percentage(0),
-            attack_range: percentage(0),
-            attack_speed: percentage(0),
-            attack_damage: 0,
-            armor: percentage(0),
-            healing: percentage(0),
-            hp_regen: percentage(0),
-            max_hp: 0,
-            mana_regen: percentage(0),
-        }
-    }
-
-    pub fn differences(
-        &self,
-        other: &CharAttributes,
-        collector: &CharAttributeModifierCollector,
-    ) -> CharAttributesBonuses {
-        return CharAttributesBonuses {
-            attrs: CharAttributes {
-                max_hp: self.max_hp - other.max_hp,
-                attack_damage: self.attack_damage - other.attack_damage,
-                movement_speed: self.movement_speed.subtract(other.movement_speed),
-                attack_range: self.attack_range.subtract(other.attack_range),
-                attack_speed: self.attack_speed.subtract(other.attack_speed),
-                armor: (self.armor).subtract(other.armor),
-                healing: self.healing.subtract(other.healing),
-                hp_regen: self.hp_regen.subtract(other.hp_regen),
-                mana_regen: self.mana_regen.subtract(other.mana_regen),
-            },
-            durations: collector.durations.clone(),
-        };
-    }
-
-    pub fn apply(&self, modifiers: &CharAttributeModifierCollector) -> CharAttributes {
-        let mut attr = self.clone();
-        for m in &modifiers.max_hp {
-            match m {
-                CharAttributeModifier::AddPercentage(_p) => {
-                    panic!("max_hp += {:?}, you cannot add percentage to a value", m)
-                }
-                CharAttributeModifier::AddValue(v) => {
-                    attr.max_hp += *v as i32;
-                }
-                CharAttributeModifier::IncreaseByPercentage(p) => {
-                    attr.max_hp = p.add_me_to(attr.max_hp);
-                }
-            }
-        }
-        for m in &modifiers.attack_damage {
-            match m {
-                CharAttributeModifier::AddPercentage(_p) => panic!(
-                    "attack_damage += {:?}, you cannot add percentage to a value",
-                    m
-                ),
-                CharAttributeModifier::AddValue(v) => {
-                    attr.attack_damage += *v as u16;
-                }
-                CharAttributeModifier::IncreaseByPercentage(p) => {
-                    attr.attack_damage = p.add_me_to(attr.attack_damage as i32) as u16;
-                }
-            }
-        }
-
-        for m in &modifiers.movement_speed {
-            attr.movement_speed.apply(m);
-        }
-        for m in &modifiers.attack_range {
-            attr.attack_range.apply(m);
-        }
-        for m in &modifiers.attack_speed {
-            attr.attack_speed.apply(m);
-        }
-        attr.attack_speed.limit(percentage(-300), percentage(500));
-        for m in &modifiers.armor {
-            attr.armor.apply(m);
-        }
-        attr.armor.limit(percentage(-100), percentage(100));
-        for m in &modifiers.healing {
-            attr.healing.apply(m);
-        }
-        for m in &modifiers.hp_regen {
-            attr.hp_regen.apply(m);
-        }
-        for m in &modifiers.mana_regen {
-            attr.mana_regen.apply(m);
-        }
-        return attr;
-    }
-}
-
-#[derive(Clone, Debug)]
-#[allow(dead_code)]
-pub enum CharAttributeModifier {
-    AddPercentage(Percentage),
-    AddValue(f32),
-    IncreaseByPercentage(Percentage),
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct BonusDurations {
-    pub max_hp_bonus_ends_at: ElapsedTime,
-    pub walking_speed_bonus_ends_at: ElapsedTime,
-    pub attack_range_bonus_ends_at: ElapsedTime,
-    pub attack_speed_bonus_ends_at: ElapsedTime,
-    pub attack_damage_bonus_ends_at: ElapsedTime,
-    pub armor_bonus_ends_at: ElapsedTime,
-    pub healing_bonus_ends_at: ElapsedTime,
-    pub hp_regen_bonus_ends_at: ElapsedTime,
-    pub mana_regen_bonus_ends_at: ElapsedTime,
-
-    pub max_hp_bonus_started_at: ElapsedTime,
-    pub walking_speed_bonus_started_at: ElapsedTime,
-    pub attack_range_bonus_started_at: ElapsedTime,
-    pub attack_speed_bonus_started_at: ElapsedTime,
-    pub attack_damage_bonus_started_at: ElapsedTime,
-    pub armor_bonus_started_at: ElapsedTime,
-    pub healing_bonus_started_at: ElapsedTime,
-    pub hp_regen_bonus_started_at: ElapsedTime,
-    pub mana_regen_bonus_started_at: ElapsedTime,
-}
-
-impl BonusDurations {
-    pub fn with_invalid_times() -> BonusDurations {
-        BonusDurations {
-            max_hp_bonus_ends_at: ElapsedTime(std::f32::MAX),
-            walking_speed_bonus_ends_at: ElapsedTime(std::f32::MAX),
-            attack_range_bonus_ends_at: ElapsedTime(std::f32::MAX),
-            attack_speed_bonus_ends_at: ElapsedTime(std::f32::MAX),
-            attack_damage_bonus_ends_at: ElapsedTime(std::f32::MAX),
-            armor_bonus_ends_at: ElapsedTime(std::f32::MAX),
-            healing_bonus_ends_at: ElapsedTime(std::f32::MAX),
-            hp_regen_bonus_ends_at: ElapsedTime(std::f32::MAX),
-            mana_regen_bonus_ends_at: ElapsedTime(std::f32::MAX),
-
-            max_hp_bonus_started_at: ElapsedTime(std::f32::MAX),
-            walking_speed_bonus_started_at: ElapsedTime(std::f32::MAX),
-            attack_range_bonus_started_at: ElapsedTime(std::f32::MAX),
-            attack_speed_bonus_started_at: ElapsedTime(std::f32::MAX),
-            attack_damage_bonus_started_at: ElapsedTime(std::f32::MAX),
-            armor_bonus_started_at: ElapsedTime(std::f32::MAX),
-            healing_bonus_started_at: ElapsedTime(std::f32::MAX),
-            hp_regen_bonus_started_at: ElapsedTime(std::f32::MAX),
-            mana_regen_bonus_started_at: ElapsedTime(std::f32::MAX),
-        }
-    }
-}
-
-#[derive(Clone, Debug)]
-pub struct CharAttributeModifierCollector {
-    max_hp: Vec<CharAttributeModifier>,
-    movement_speed: Vec<CharAttributeModifier>,
-    attack_range: Vec<CharAttributeModifier>,
-    attack_speed: Vec<CharAttributeModifier>,
-    attack_damage: Vec<CharAttributeModifier>,
-    armor: Vec<CharAttributeModifier>,
-    healing: Vec<CharAttributeModifier>,
-    hp_regen: Vec<CharAttributeModifier>,
-    mana_regen: Vec<CharAttributeModifier>,
-    durations: BonusDurations,
-}
-
-impl CharAttributeModifierCollector {
-    pub fn new() -> CharAttributeModifierCollector {
-        CharAttributeModifierCollector {
-            max_hp: Vec::with_capacity(8),
-            movement_speed: Vec::with_capacity(8),
-            attack_range: Vec::with_capacity(8),
-            attack_speed: Vec::with_capacity(8),
-            attack_damage: Vec::with_capacity(8),
-            armor: Vec::with_capacity(8),
-            healing: Vec::with_capacity(8),
-            hp_regen: Vec::with_capacity(8),
-            mana_regen: Vec::with_capacity(8),
-            durations: BonusDurations::with_invalid_times(),
-        }
-    }
-
-    pub fn change_attack_damage(
-        &mut self,
-        modifier: CharAttributeModifier,
-        started: ElapsedTime,
-        until: ElapsedTime,
-    ) {
-        CharAttributeModifierCollector::set_durations(
-            started,
-            until,
-            &mut self.durations.attack_damage_bonus_started_at,
-            &mut self.durations.attack_damage_bonus_ends_at,
-        );
-        self.attack_damage.push(modifier);
-    }
-
-    pub fn change_attack_speed(
-        &mut self,
-        modifier: CharAttributeModifier,
-        started: ElapsedTime,
-        until: ElapsedTime,
-    ) {
-        CharAttributeModifierCollector::set_durations(
-            started,
-            until,
-            &mut self.durations.attack_speed_bonus_started_at,
-            &mut self.durations.attack_speed_bonus_ends_at,
-        );
-        self.attack_speed.push(modifier);
-    }
-
-    pub fn change_armor(
-        &mut self,
-        modifier: CharAttributeModifier,
-        started: ElapsedTime,
-        until: ElapsedTime,
-    ) {
-        CharAttributeModifierCollector::set_durations(
-            started,
-            until,
-            &mut self.durations.armor_bonus_started_at,
-            &mut self.durations.armor_bonus_ends_at,
-        );
-        self.armor.push(modifier);
-    }
-
-    fn set_durations(
-        new_started_at: ElapsedTime,
-        new_ends_at: ElapsedTime,
-        current_started_at: &mut ElapsedTime,
-        current_ends_at: &mut ElapsedTime,
-    ) {
-        if current_ends_at.has_not_passed_yet(new_ends_at) {
-            *current_ends_at = new_ends_at;
-            *current_started_at = new_started_at;
-        }
-    }
-
-    pub fn change_attack_range(
-        &mut self,
-        modifier: CharAttributeModifier,
-        started: ElapsedTime,
-        until: ElapsedTime,
-    ) {
-        CharAttributeModifierCollector::set_durations(
-            started,
-            until,
-            &mut self.durations.attack_range_bonus_started_at,
-            &mut self.durations.attack_range_bonus_ends_at,
-        );
-        self.attack_range.push(modifier);
-    }
-
-    pub fn change_walking_speed(
-        &mut self,
-        modifier: CharAttributeModifier,
-        started: ElapsedTime,
-        until: ElapsedTime,
-    ) {
-        CharAttributeModifierCollector::set_durations(
-            started,
-            until,
-            &mut self.durations.walking_speed_bonus_started_at,
-            &mut self.durations.walking_speed_bonus_ends_at,
-        );
-        self.movement_speed.push(modifier);
-    }
-
-    pub fn clear(&mut self) {
-        self.max_hp.clear();
-        self.movement_speed.clear();
-        self.attack_range.clear();
-        self.attack_speed.clear();
-        self.attack_damage.clear();
-        self.armor.clear();
-        self.healing.clear();
-        self.hp_regen.clear();
-        self.mana_regen.clear();
-        self.durations = BonusDurations::with_invalid_times();
-    }
-}
-
 pub struct TurretControllerComponent;
 
 impl Component for TurretControllerComponent {
@@ -977,7 +545,6 @@ impl Component for NpcComponent {
 pub struct CharacterStateComponent {
     // characters also has names so it is possible to follow them with a camera
     pub name: String,
-    pub basic_attack_type: BasicAttackType,
     y: f32,
     pub team: Team,
     pub target: Option<EntityTarget>,
@@ -992,8 +559,6 @@ pub struct CharacterStateComponent {
     base_attributes: CharAttributes,
     calculated_attribs: CharAttributes,
     attrib_bonuses: CharAttributesBonuses,
-    // TODO: the whole Statuses struct needs for simulation but not for state representation. Extract the array from it for serialization
-    pub statuses: Statuses,
     pub body_handle: DefaultBodyHandle,
     pub collider_handle: DefaultColliderHandle,
 }
@@ -1005,9 +570,13 @@ impl Drop for CharacterStateComponent {
 }
 
 impl CharacterStateComponent {
-    pub fn update_base_attributes(&mut self, dev_configs: &DevConfig) {
-        self.base_attributes = Statuses::get_base_attributes(self.job_id, dev_configs);
-        self.recalc_attribs_based_on_statuses()
+    pub fn update_base_attributes(
+        &mut self,
+        dev_configs: &DevConfig,
+        statuses: Option<&mut StatusesComponent>,
+    ) {
+        self.base_attributes = get_base_attributes(self.job_id, dev_configs);
+        self.recalc_attribs_based_on_statuses(statuses)
     }
 
     pub fn set_noncollidable(&self, physics_world: &mut PhysicEngine) {
@@ -1044,25 +613,9 @@ impl CharacterStateComponent {
         dev_configs: &DevConfig,
         physics_component: (DefaultColliderHandle, DefaultBodyHandle),
     ) -> CharacterStateComponent {
-        let statuses = Statuses::new();
-        let base_attributes = Statuses::get_base_attributes(job_id, dev_configs);
+        let base_attributes = get_base_attributes(job_id, dev_configs);
         let calculated_attribs = base_attributes.clone();
         CharacterStateComponent {
-            basic_attack_type: match job_id {
-                JobId::GUNSLINGER => BasicAttackType::Ranged {
-                    bullet_type: WeaponType::SilverBullet,
-                },
-                JobId::RangedMinion => BasicAttackType::Ranged {
-                    bullet_type: WeaponType::Arrow,
-                },
-                JobId::RANGER => BasicAttackType::Ranged {
-                    bullet_type: WeaponType::Arrow,
-                },
-                JobId::Turret => BasicAttackType::Ranged {
-                    bullet_type: WeaponType::SilverBullet,
-                },
-                _ => BasicAttackType::MeleeSimple,
-            },
             job_id,
             name,
             y,
@@ -1081,7 +634,6 @@ impl CharacterStateComponent {
                 attrs: CharAttributes::zero(),
                 durations: BonusDurations::with_invalid_times(),
             },
-            statuses,
             body_handle: physics_component.1,
             collider_handle: physics_component.0,
         }
@@ -1098,8 +650,13 @@ impl CharacterStateComponent {
         &self.attrib_bonuses
     }
 
-    pub fn recalc_attribs_based_on_statuses(&mut self) {
-        let modifier_collector = self.statuses.calc_attributes();
+    /// The statuses are simulated by the server, the client only reflects their effects
+    pub fn recalc_attribs_based_on_statuses(&mut self, statuses: Option<&mut StatusesComponent>) {
+        let empty_collector = CharAttributeModifierCollector::new();
+        let modifier_collector = match statuses {
+            Some(statuses) => statuses.calc_attributes(),
+            None => &empty_collector,
+        };
         self.calculated_attribs = self.base_attributes.apply(modifier_collector);
 
         self.attrib_bonuses = self
@@ -1107,47 +664,6 @@ impl CharacterStateComponent {
             .differences(&self.base_attributes, modifier_collector);
     }
 
-    // for tests
-    #[allow(dead_code)]
-    pub fn get_status_count(&self) -> usize {
-        self.statuses.count()
-    }
-
-    pub fn update_statuses(
-        &mut self,
-        self_char_id: CharEntityId,
-        sys_vars: &mut SystemVariables,
-        time: &EngineTime,
-        entities: &Entities,
-        updater: &mut LazyUpdate,
-        phyisics_world: &mut PhysicEngine,
-    ) {
-        // TODO: refactor this
-        //                 a hack so statuses and self can be mut at the same time
-        let mut mut_statuses = std::mem::replace(&mut self.statuses, Statuses::new());
-        let bit_indices_of_changed_statuses = mut_statuses.update(
-            self_char_id,
-            self,
-            phyisics_world,
-            sys_vars,
-            time,
-            entities,
-            updater,
-        );
-        std::mem::replace(&mut self.statuses, mut_statuses);
-        if bit_indices_of_changed_statuses > 0 {
-            self.statuses
-                .remove_statuses(bit_indices_of_changed_statuses);
-            self.recalc_attribs_based_on_statuses();
-            log::trace!(
-                "Status expired. Attributes({:?}): mod: {:?}, attribs: {:?}",
-                self_char_id,
-                self.attrib_bonuses(),
-                self.calculated_attribs()
-            );
-        }
-    }
-
     pub fn set_y(&mut self, y: f32) {
         self.y = y;
     }
@@ -1171,8 +687,7 @@ impl CharacterStateComponent {
     pub fn went_from_casting_to_idle(&self, current_state: &CharState) -> bool {
         match current_state {
             CharState::Idle => match self.prev_state {
-                // TODO2
-                //                CharState::CastingSkill(_) => true,
+                CharState::CastingSkill(_) => true,
                 _ => false,
             },
             _ => false,
@@ -1180,44 +695,49 @@ impl CharacterStateComponent {
     }
 }
 
+fn get_base_attributes(job_id: JobId, configs: &DevConfig) -> CharAttributes {
+    return rustarok_common::config::get_base_attributes(job_id, &configs.stats, &configs.skills);
+}
+
 pub fn can_char_cast(
     char_state: &CharacterStateComponent,
+    statuses: Option<&StatusesComponent>,
     state: &CharState,
     sys_time: ElapsedTime,
 ) -> bool {
     let can_cast_by_state = match state {
-        // TODO2
-        //        CharState::CastingSkill(_) => false,
+        CharState::CastingSkill(_) => false,
         CharState::Idle => true,
         CharState::Walking(_pos) => true,
         //        CharState::StandBy => true,
         //        CharState::Attacking { .. } => false,
         //        CharState::ReceivingDamage => false,
-        //        CharState::Dead => false,
+        CharState::Dead => false,
     };
     can_cast_by_state
         && char_state.cannot_control_until.has_already_passed(sys_time)
-        && char_state.statuses.can_cast()
+        && statuses.map(|it| it.can_cast()).unwrap_or(true)
 }
 
 pub fn can_char_move(
     char_state: &CharacterStateComponent,
+    statuses: Option<&StatusesComponent>,
     state: &CharState,
     sys_time: ElapsedTime,
 ) -> bool {
     let can_move_by_state = match state {
-        //        CharState::CastingSkill(casting_info) => casting_info.can_move,
+        CharState::CastingSkill(casting_info) => casting_info.can_move,
         CharState::Idle => true,
         CharState::Walking(_pos) => true,
         // TODO2
         //        CharState::StandBy => true,
         //        CharState::Attacking { .. } => false,
         //        CharState::ReceivingDamage => true,
-        //        CharState::Dead => false,
+        CharState::Dead => false,
     };
     can_move_by_state
         && char_state.cannot_control_until.has_already_passed(sys_time)
-        && char_state.statuses.can_move()
+        && statuses.map(|it| it.can_move()).unwrap_or(true)
 }
 
 #[derive(Clone, Copy, Eq, PartialEq)]
@@ -1,15 +1,18 @@
-use nalgebra::Isometry2;
-use nphysics2d::object::DefaultBodyHandle;
 use specs::prelude::*;
 //use websocket::stream::sync::TcpStream;
 
 use crate::audio::sound_sys::SoundId;
 use crate::components::char::ActionPlayMode;
-use crate::components::skills::basic_attack::WeaponType;
 use crate::effect::StrEffectId;
 use crate::ElapsedTime;
 use rustarok_common::common::Vec2;
-use rustarok_common::components::char::CharEntityId;
+use rustarok_common::components::char::{CharEntityId, Team};
+use rustarok_common::scenario::Lane;
+
+pub use rustarok_common::attack::{
+    DamageDisplayType, HpModificationRequest, HpModificationResult, HpModificationResultType,
+    HpModificationType,
+};
 
 pub mod char;
 pub mod controller;
@@ -47,7 +50,20 @@ pub struct StrEffectComponent {
 
 #[derive(Component)]
 pub struct MinionComponent {
-    pub fountain_up: bool,
+    /// index in `Scenario::lanes`
+    pub lane: usize,
+    /// the minion walks towards the `nth` waypoint of the lane, counted from the base of its team
+    pub next_waypoint: usize,
+}
+
+impl MinionComponent {
+    /// The minion continues from the waypoint which is the closest to `pos`
+    pub fn new(lane_index: usize, lane: &Lane, team: Team, pos: &Vec2) -> MinionComponent {
+        MinionComponent {
+            lane: lane_index,
+            next_waypoint: lane.closest_waypoint(team, pos),
+        }
+    }
 }
 
 pub enum FlyingNumberType {
@@ -114,93 +130,3 @@ impl FlyingNumberComponent {
         }
     }
 }
-
-#[derive(Clone, Copy, Eq, PartialEq, Debug)]
-pub enum DamageDisplayType {
-    SingleNumber,
-    Combo(u8),
-}
-
-#[derive(Clone, Copy, Debug)]
-pub enum HpModificationType {
-    BasicDamage(u32, DamageDisplayType, WeaponType),
-    SpellDamage(u32, DamageDisplayType),
-    Heal(u32),
-    Poison(u32),
-}
-
-#[derive(Debug)]
-pub struct HpModificationRequest {
-    pub src_entity: CharEntityId,
-    pub dst_entity: CharEntityId,
-    pub typ: HpModificationType,
-}
-
-impl HpModificationRequest {
-    pub fn allow(self, dmg: u32) -> HpModificationResult {
-        HpModificationResult {
-            src_entity: self.src_entity,
-            dst_entity: self.dst_entity,
-            typ: HpModificationResultType::Ok(match self.typ {
-                HpModificationType::BasicDamage(_, display_type, weapon_type) => {
-                    HpModificationType::BasicDamage(dmg, display_type, weapon_type)
-                }
-                HpModificationType::SpellDamage(_, display_type) => {
-                    HpModificationType::SpellDamage(dmg, display_type)
-                }
-                HpModificationType::Heal(_) => HpModificationType::Heal(dmg),
-                HpModificationType::Poison(_) => HpModificationType::Poison(dmg),
-            }),
-        }
-    }
-
-    pub fn blocked(self) -> HpModificationResult {
-        HpModificationResult {
-            src_entity: self.src_entity,
-            dst_entity: self.dst_entity,
-            typ: HpModificationResultType::Blocked,
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct HpModificationResult {
-    pub src_entity: CharEntityId,
-    pub dst_entity: CharEntityId,
-    pub typ: HpModificationResultType,
-}
-
-impl HpModificationResult {
-    pub fn absorbed(self) -> HpModificationResult {
-        HpModificationResult {
-            src_entity: self.src_entity,
-            dst_entity: self.dst_entity,
-            typ: HpModificationResultType::Absorbed,
-        }
-    }
-}
-
-#[derive(Debug)]
-pub enum HpModificationResultType {
-    Ok(HpModificationType),
-    Blocked,
-    Absorbed,
-}
-
-// TODO: be static types for Cuboid area attack components, Circle, etc
-pub struct AreaAttackComponent {
-    pub area_shape: Box<dyn ncollide2d::shape::Shape<f32>>,
-    pub area_isom: Isometry2<f32>,
-    pub source_entity_id: CharEntityId,
-    pub typ: HpModificationType,
-    pub except: Option<CharEntityId>,
-}
-
-#[derive(Debug)]
-pub struct ApplyForceComponent {
-    pub src_entity: CharEntityId,
-    pub dst_entity: CharEntityId,
-    pub force: Vec2,
-    pub body_handle: DefaultBodyHandle,
-    pub duration: f32,
-}
@@ -1,20 +1,4 @@
-use crate::components::char::{
-    CharacterEntityBuilder, CharacterStateComponent, NpcComponent, TurretComponent,
-    TurretControllerComponent,
-};
-use crate::components::controller::LocalPlayerControllerComponent;
-use crate::components::skills::skills::{
-    FinishCast, SkillDef, SkillManifestation, SkillTargetType,
-};
-use crate::configs::DevConfig;
-use crate::runtime_assets::map::PhysicEngine;
-
-use rustarok_common::components::char::{
-    CharEntityId, CharOutlook, CollisionGroup, ControllerEntityId, JobId, MonsterId,
-};
-use rustarok_common::components::controller::ControllerComponent;
-use specs::prelude::*;
-use specs::LazyUpdate;
+use crate::components::skills::skills::SkillDef;
 
 pub struct GazTurretSkill;
 
@@ -24,52 +8,6 @@ impl SkillDef for GazTurretSkill {
     fn get_icon_path(&self) -> &'static str {
         "data\\texture\\À¯ÀúÀÎÅÍÆäÀÌ½º\\item\\gn_cartcannon.bmp"
     }
-
-    fn finish_cast(
-        &self,
-        params: &FinishCast,
-        ecs_world: &mut World,
-    ) -> Option<Box<dyn SkillManifestation>> {
-        if let Some(caster) = ecs_world
-            .read_storage::<CharacterStateComponent>()
-            .get(params.caster_entity_id.into())
-        {
-            let entities = &ecs_world.entities();
-            let updater = &ecs_world.read_resource::<LazyUpdate>();
-            let char_entity_id = CharEntityId::from(entities.create());
-            updater.insert(char_entity_id.into(), NpcComponent);
-            CharacterEntityBuilder::new(char_entity_id, "turret")
-                .insert_sprite_render_descr_component(updater)
-                .insert_turret_component(params.caster_entity_id, updater)
-                .physics(
-                    params.skill_pos.unwrap(),
-                    &mut ecs_world.write_resource::<PhysicEngine>(),
-                    |builder| builder.collision_group(CollisionGroup::Turret).circle(1.0),
-                )
-                .char_state(
-                    updater,
-                    &ecs_world.read_resource::<DevConfig>(),
-                    params.skill_pos.unwrap(),
-                    |ch| {
-                        ch.outlook(CharOutlook::Monster(MonsterId::Dimik))
-                            .job_id(JobId::Turret)
-                            .team(caster.team)
-                    },
-                );
-
-            let controller_id = ControllerEntityId::new(entities.create());
-            updater.insert(
-                controller_id.into(),
-                ControllerComponent::new(char_entity_id),
-            );
-            updater.insert(controller_id.into(), TurretControllerComponent);
-        }
-        None
-    }
-
-    fn get_skill_target_type(&self) -> SkillTargetType {
-        SkillTargetType::Area
-    }
 }
 
 pub struct GazDestroyTurretSkill;
@@ -79,37 +17,6 @@ impl SkillDef for GazDestroyTurretSkill {
     fn get_icon_path(&self) -> &'static str {
         "data\\texture\\À¯ÀúÀÎÅÍÆäÀÌ½º\\item\\gn_remodeling_cart.bmp"
     }
-
-    fn finish_cast(
-        &self,
-        params: &FinishCast,
-        ecs_world: &mut World,
-    ) -> Option<Box<dyn SkillManifestation>> {
-        if params
-            .target_entity
-            .and_then(|it| {
-                ecs_world
-                    .read_storage::<TurretComponent>()
-                    .get(it.into())
-                    .map(|turret| turret.owner_entity_id == params.caster_entity_id)
-            })
-            .unwrap_or(false)
-        {
-            let target_entity = params.target_entity.unwrap();
-            if let Some(turret) = ecs_world
-                .write_storage::<CharacterStateComponent>()
-                .get_mut(target_entity.into())
-            {
-                turret.hp = 0;
-            }
-        }
-
-        None
-    }
-
-    fn get_skill_target_type(&self) -> SkillTargetType {
-        SkillTargetType::OnlyAllyButNoSelf
-    }
 }
 
 pub struct GazTurretTargetSkill;
@@ -119,22 +26,4 @@ impl SkillDef for GazTurretTargetSkill {
     fn get_icon_path(&self) -> &'static str {
         "data\\texture\\À¯ÀúÀÎÅÍÆäÀÌ½º\\item\\gs_bullseye.bmp"
     }
-
-    fn finish_cast(
-        &self,
-        params: &FinishCast,
-        ecs_world: &mut World,
-    ) -> Option<Box<dyn SkillManifestation>> {
-        for turret in (&mut ecs_world.write_storage::<TurretComponent>()).join() {
-            if turret.owner_entity_id == params.caster_entity_id {
-                turret.preferred_target = params.target_entity;
-            }
-        }
-
-        None
-    }
-
-    fn get_skill_target_type(&self) -> SkillTargetType {
-        SkillTargetType::OnlyEnemy
-    }
 }
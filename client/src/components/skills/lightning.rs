@@ -1,22 +1,15 @@
-use nalgebra::Isometry2;
-use specs::{Entity, ReadStorage};
-
-use crate::audio::sound_sys::AudioCommandCollectorComponent;
-use crate::components::char::{ActionPlayMode, CharacterStateComponent};
-use crate::components::skills::skills::{
-    FinishCast, SkillDef, SkillManifestation, SkillManifestationComponent,
-    SkillManifestationUpdateParam, SkillTargetType,
-};
-use crate::components::{
-    AreaAttackComponent, DamageDisplayType, HpModificationType, StrEffectComponent,
-};
+use crate::components::char::ActionPlayMode;
+use crate::components::skills::skills::SkillDef;
 use crate::configs::DevConfig;
 use crate::effect::StrEffectType;
 use crate::render::render_command::RenderCommandCollector;
-use crate::systems::{AssetResources, SystemVariables};
+use crate::render::render_sys::RenderDesktopClientSystem;
+use crate::systems::AssetResources;
 use crate::ElapsedTime;
-use rustarok_common::common::{EngineTime, Vec2};
-use rustarok_common::components::char::CharEntityId;
+use rustarok_common::common::Vec2;
+use rustarok_common::components::manifestation::{
+    LightningState, LIGHTNING_STRIKE_DISTANCE, LIGHTNING_STRIKE_OFFSETS,
+};
 
 pub struct LightningSkill;
 
@@ -27,25 +20,6 @@ impl SkillDef for LightningSkill {
         "data\\texture\\À¯ÀúÀÎÅÍÆäÀÌ½º\\item\\wl_chainlightning.bmp"
     }
 
-    fn finish_cast(
-        &self,
-        params: &FinishCast,
-        ecs_world: &mut specs::world::World,
-    ) -> Option<Box<dyn SkillManifestation>> {
-        let sys_vars = ecs_world.read_resource::<SystemVariables>();
-        Some(Box::new(LightningManifest::new(
-            params.caster_entity_id,
-            &params.skill_pos.unwrap(),
-            &params.char_to_skill_dir,
-            ecs_world.read_resource::<EngineTime>().now(),
-            &ecs_world.entities(),
-        )))
-    }
-
-    fn get_skill_target_type(&self) -> SkillTargetType {
-        SkillTargetType::Area
-    }
-
     fn render_target_selection(
         &self,
         _is_castable: bool,
@@ -55,7 +29,7 @@ impl SkillDef for LightningSkill {
         _configs: &DevConfig,
     ) {
         for i in 0..3 {
-            let pos = skill_pos + char_to_skill_dir * i as f32 * 2.2;
+            let pos = skill_pos + char_to_skill_dir * i as f32 * LIGHTNING_STRIKE_DISTANCE;
             render_commands
                 .circle_3d()
                 .pos_2d(&pos)
@@ -67,158 +41,40 @@ impl SkillDef for LightningSkill {
     }
 }
 
-pub struct LightningManifest {
-    pub caster_entity_id: CharEntityId,
-    pub effect_id: Entity,
-    pub pos: Vec2,
-    pub dir_vector: Vec2,
-    pub created_at: ElapsedTime,
-    pub next_action_at: ElapsedTime,
-    pub next_damage_at: ElapsedTime,
-    pub last_skill_pos: Vec2,
-    pub action_count: u8,
-}
-
-impl LightningManifest {
-    pub fn new(
-        caster_entity_id: CharEntityId,
-        skill_center: &Vec2,
-        dir_vector: &Vec2,
-        now: ElapsedTime,
-        entities: &specs::Entities,
-    ) -> LightningManifest {
-        LightningManifest {
-            caster_entity_id,
-            effect_id: entities.create(),
-            pos: *skill_center,
-            created_at: now,
-            next_action_at: now,
-            next_damage_at: now,
-            last_skill_pos: *skill_center,
-            action_count: 0,
-            dir_vector: *dir_vector,
-        }
+pub fn render_lightning(
+    pos: &Vec2,
+    state: &LightningState,
+    now: ElapsedTime,
+    assets: &AssetResources,
+    render_commands: &mut RenderCommandCollector,
+) {
+    // the places of the upcoming strikes
+    for offset in LIGHTNING_STRIKE_OFFSETS
+        .iter()
+        .skip(state.action_count as usize)
+    {
+        let strike_pos = pos + state.dir * *offset * LIGHTNING_STRIKE_DISTANCE;
+        render_commands
+            .circle_3d()
+            .pos_2d(&strike_pos)
+            .y(0.0)
+            .radius(1.0)
+            .color(&[0, 255, 0, 255])
+            .add();
     }
-}
-
-impl SkillManifestation for LightningManifest {
-    fn update(&mut self, mut params: SkillManifestationUpdateParam) {
-        let now = params.time().now();
-        if self.created_at.add_seconds(12.0).has_already_passed(now) {
-            params.remove_component::<SkillManifestationComponent>(params.self_entity_id);
-            params.remove_component::<StrEffectComponent>(params.self_entity_id);
-        } else {
-            if self.next_action_at.has_already_passed(now) {
-                params.remove_component::<StrEffectComponent>(self.effect_id);
-                let effect_comp = match self.action_count {
-                    0 => StrEffectComponent {
-                        effect_id: StrEffectType::Lightning.into(),
-                        pos: self.pos,
-                        start_time: now.add_seconds(-0.5),
-                        die_at: Some(now.add_seconds(1.0)),
-                        play_mode: ActionPlayMode::Repeat,
-                    },
-                    1 => {
-                        let pos = self.pos + self.dir_vector * 2.2;
-                        StrEffectComponent {
-                            effect_id: StrEffectType::Lightning.into(),
-                            pos,
-                            start_time: now.add_seconds(-0.5),
-                            die_at: Some(now.add_seconds(1.0)),
-                            play_mode: ActionPlayMode::Repeat,
-                        }
-                    }
-                    2 => {
-                        let pos = self.pos + self.dir_vector * 2.0 * 2.2;
-                        StrEffectComponent {
-                            effect_id: StrEffectType::Lightning.into(),
-                            pos,
-                            start_time: now.add_seconds(-0.5),
-                            die_at: Some(now.add_seconds(1.0)),
-                            play_mode: ActionPlayMode::Repeat,
-                        }
-                    }
-                    3 => {
-                        let pos = self.pos + self.dir_vector * 2.0 * 2.2;
-                        StrEffectComponent {
-                            effect_id: StrEffectType::Lightning.into(),
-                            pos,
-                            start_time: now.add_seconds(-0.5),
-                            die_at: Some(now.add_seconds(1.0)),
-                            play_mode: ActionPlayMode::Repeat,
-                        }
-                    }
-                    4 => {
-                        let pos = self.pos + self.dir_vector * 2.2;
-                        StrEffectComponent {
-                            effect_id: StrEffectType::Lightning.into(),
-                            pos,
-                            start_time: now.add_seconds(-0.5),
-                            die_at: Some(now.add_seconds(1.0)),
-                            play_mode: ActionPlayMode::Repeat,
-                        }
-                    }
-                    5 => StrEffectComponent {
-                        effect_id: StrEffectType::Lightning.into(),
-                        pos: self.pos,
-                        start_time: now.add_seconds(-0.5),
-                        die_at: Some(now.add_seconds(1.0)),
-                        play_mode: ActionPlayMode::Repeat,
-                    },
-                    _ => {
-                        return;
-                    }
-                };
-                self.last_skill_pos = effect_comp.pos.clone();
-                params.insert_comp(self.effect_id, effect_comp);
-                self.action_count += 1;
-                self.next_action_at = now.add_seconds(1.5);
-                self.next_damage_at = now.add_seconds(1.0);
-            }
-            if self.next_damage_at.has_already_passed(now) {
-                params.add_area_hp_mod_request(AreaAttackComponent {
-                    area_shape: Box::new(ncollide2d::shape::Ball::new(1.0)),
-                    area_isom: Isometry2::new(self.last_skill_pos, 0.0),
-                    source_entity_id: self.caster_entity_id,
-                    typ: HpModificationType::SpellDamage(120, DamageDisplayType::SingleNumber),
-                    except: None,
-                });
-                self.next_damage_at = self.next_damage_at.add_seconds(0.6);
-            }
-        }
-    }
-
-    fn render(
-        &self,
-        _char_entity_storage: &ReadStorage<CharacterStateComponent>,
-        _now: ElapsedTime,
-        _tick: u64,
-        _assets: &AssetResources,
-        render_commands: &mut RenderCommandCollector,
-        _audio_commands: &mut AudioCommandCollectorComponent,
-    ) {
-        for i in self.action_count..3 {
-            let pos = self.pos + self.dir_vector * i as f32 * 2.2;
-            render_commands
-                .circle_3d()
-                .pos_2d(&pos)
-                .y(0.0)
-                .radius(1.0)
-                .color(&[0, 255, 0, 255])
-                .add();
-        }
-        // backwards
-        if self.action_count >= 4 {
-            for i in self.action_count..6 {
-                let pos = self.pos + self.dir_vector * (5 - i) as f32 * 2.2;
-                render_commands
-                    .circle_3d()
-                    .pos_2d(&pos)
-                    .y(0.0)
-                    .radius(1.0)
-                    .color(&[0, 255, 0, 255])
-                    .add();
-            }
+    if let Some(last_strike_pos) = state.last_strike_pos {
+        // a new strike comes in every 1.5 seconds, its effect lasts for 1 second
+        let last_strike_at = state.next_action_at.add_seconds(-1.5);
+        if last_strike_at.add_seconds(1.0).has_not_passed_yet(now) {
+            RenderDesktopClientSystem::render_str(
+                StrEffectType::Lightning,
+                last_strike_at.add_seconds(-0.5),
+                &last_strike_pos,
+                assets,
+                now,
+                render_commands,
+                ActionPlayMode::Repeat,
+            );
         }
     }
 }
@@ -1,6 +1,8 @@
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
 
 use crate::strum::IntoEnumIterator;
 use encoding::types::Encoding;
@@ -10,11 +12,12 @@ use std::fmt::Write;
 use crate::components::char::CharActionIndex;
 use crate::consts::{job_name_table, PLAYABLE_CHAR_SPRITES};
 use crate::grf::act::ActionFile;
+use crate::grf::asset_bundle::{self, AssetBundle};
 use crate::grf::asset_loader::GrfEntryLoader;
 use crate::grf::gnd::{Gnd, MeshVertex};
 use crate::grf::rsm::{BoundingBox, Rsm};
 use crate::grf::rsw::RswModelInstance;
-use crate::grf::spr::SpriteFile;
+use crate::grf::spr::{SprFrame, SpriteFile};
 use crate::grf::texture::TextureId;
 use crate::grf::SpriteResource;
 use crate::my_gl::MyGlEnum;
@@ -25,14 +28,29 @@ use rustarok_common::common::{measure_time, v3, Mat4, Vec2, Vec3};
 use rustarok_common::components::char::{JobId, MonsterId};
 use rustarok_common::components::job_ids::JobSpriteId;
 use rustarok_common::grf::asset_loader::CommonAssetLoader;
-use rustarok_common::grf::binary_reader::BinaryReader;
+use rustarok_common::grf::binary_reader::{BinaryReader, ParseError, ParseErrorKind};
 use rustarok_common::grf::gat::{BlockingRectangle, CellType, Gat};
 use sdl2::pixels::PixelFormatEnum;
+use serde::{Deserialize, Serialize};
 
 pub(super) struct BackgroundAssetLoader<'a> {
     to_main_thread: Sender<FromBackgroundAssetLoaderMsg<'a>>,
     from_main_thread: Receiver<ToBackgroundAssetLoaderMsg>,
     asset_loader: CommonAssetLoader,
+    /// by the normalized path without extension, one path can be loaded with many palettes
+    loaded_sprites: RefCell<HashMap<String, Vec<LoadedSprite>>>,
+    /// the parts of the bundle are taken out when they are requested
+    bundle: Option<AssetBundle>,
+    /// their files are not known, so they can not be hot reloaded
+    sprites_from_bundle: bool,
+}
+
+/// What is needed to load a sprite again when its files are changed
+struct LoadedSprite {
+    path: String,
+    palette: Option<(usize, Vec<u8>)>,
+    without_head_rotating_frames: bool,
+    texture_ids: Vec<TextureId>,
 }
 
 pub(super) struct SendableRawSdlSurface<'a>(pub &'a mut sdl2::sys::SDL_Surface);
@@ -40,7 +58,7 @@ pub(super) struct SendableRawSdlSurface<'a>(pub &'a mut sdl2::sys::SDL_Surface);
 unsafe impl<'a> Send for SendableRawSdlSurface<'a> {}
 
 impl<'a> SendableRawSdlSurface<'a> {
-    fn new(sdl_surface: sdl2::surface::Surface<'a>) -> SendableRawSdlSurface<'a> {
+    pub(super) fn new(sdl_surface: sdl2::surface::Surface<'a>) -> SendableRawSdlSurface<'a> {
         let ptr = sdl_surface.raw();
         // prevent drop
         std::mem::forget(sdl_surface);
@@ -71,6 +89,20 @@ pub(super) enum ToBackgroundAssetLoaderMsg {
         water_wave_height: f32,
         colliders: Vec<(Vec2, Vec2)>,
     },
+    /// The sprites, the ground and the models are taken from this bundle if it is up to date
+    LoadBundle(PathBuf),
+    /// These files were changed in a directory source, the entries of the VFS must be updated
+    /// before the reload messages are processed
+    RefreshFiles(Vec<PathBuf>),
+    ReloadSprite {
+        path: String,
+        texture_id_pool: Vec<TextureId>,
+    },
+    ReloadModel {
+        model_name: String,
+        model_id: usize,
+        texture_id_pool: Vec<TextureId>,
+    },
 }
 
 pub(super) enum FromBackgroundAssetLoaderMsg<'a> {
@@ -87,7 +119,7 @@ pub(super) enum FromBackgroundAssetLoaderMsg<'a> {
     LoadTextureResponse {
         texture_id: TextureId,
         minmag: MyGlEnum,
-        content: Vec<u8>,
+        content: Arc<Vec<u8>>,
         filename: String,
     },
     LoadModelsResponse {
@@ -97,6 +129,20 @@ pub(super) enum FromBackgroundAssetLoaderMsg<'a> {
         texture_id_pool: Vec<TextureId>,
         model_id_pool: Vec<usize>,
     },
+    ReloadSpriteResponse {
+        /// the first texture of the old sprite, it identifies the sprite resources to be replaced
+        sprites: Vec<(TextureId, SpriteResource)>,
+        reserved_textures: Vec<ReservedTexturedata<'a>>,
+        texture_id_pool: Vec<TextureId>,
+    },
+    ReloadModelResponse {
+        model_name: String,
+        model: ModelLoadingData,
+        reserved_textures: Vec<ReservedTexturedata<'a>>,
+        texture_id_pool: Vec<TextureId>,
+    },
+    /// The texture slots of the failed reload are given back
+    ReloadModelFailed { texture_id_pool: Vec<TextureId> },
 }
 
 pub(super) struct ReservedTexturedata<'a> {
@@ -106,6 +152,7 @@ pub(super) struct ReservedTexturedata<'a> {
     pub minmag: MyGlEnum,
 }
 
+#[derive(Serialize, Deserialize)]
 pub(super) struct AsyncGroundLoadResult {
     pub ground_vertex_array: Vec<MeshVertex>,
     pub ground_walkability_mesh: Vec<Point3<f32>>,
@@ -118,6 +165,7 @@ pub(super) struct AsyncGroundLoadResult {
     pub lightmap_texture: TextureId,
 }
 
+#[derive(Serialize, Deserialize)]
 pub(super) struct ModelLoadingData {
     pub model_id: usize,
     pub data_for_rendering_full_model: Vec<Vec<SameTextureNodeFacesRaw>>,
@@ -135,10 +183,13 @@ impl<'a> BackgroundAssetLoader<'a> {
             to_main_thread,
             from_main_thread,
             asset_loader,
+            loaded_sprites: RefCell::new(HashMap::new()),
+            bundle: None,
+            sprites_from_bundle: false,
         }
     }
 
-    pub fn run(self) {
+    pub fn run(mut self) {
         loop {
             let msg = self.from_main_thread.recv();
             if msg.is_err() {
@@ -168,70 +219,33 @@ impl<'a> BackgroundAssetLoader<'a> {
                     map_height,
                 } => {
                     let mut reserved_textures = Vec::<ReservedTexturedata>::with_capacity(1000);
-                    let mut texture_map = HashMap::<String, TextureId>::with_capacity(128);
-                    let model_names: HashSet<_> = rsw_model_instances
-                        .iter()
-                        .map(|m| m.filename.clone())
-                        .collect();
-                    let models: HashMap<String, ModelLoadingData> = model_names
-                        .into_iter()
-                        .map(|model_name| {
-                            let file_name = format!("data\\model\\{}", model_name);
-                            let content = self.asset_loader.get_content(&file_name).unwrap();
-                            let model_id = model_id_pool.pop().unwrap();
-                            let rsm = Rsm::load(BinaryReader::from_vec(content));
-                            let textures: Vec<(String, TextureId)> = rsm
-                                .texture_names
-                                .iter()
-                                .map(|texture_name| {
-                                    let texture_id = texture_map
-                                        .entry(texture_name.to_string())
-                                        .or_insert_with(|| {
-                                            let path = format!("data\\texture\\{}", texture_name);
-                                            self.load_texture(
-                                                &path,
-                                                MyGlEnum::NEAREST,
-                                                &mut texture_id_pool,
-                                                &mut reserved_textures,
-                                            )
-                                            .unwrap()
-                                        });
-                                    (texture_name.to_string(), *texture_id)
+                    let baked = self
+                        .bundle
+                        .as_mut()
+                        .and_then(|bundle| bundle.models.take())
+                        .and_then(|baked| {
+                            baked
+                                .unpack(
+                                    &mut model_id_pool,
+                                    &mut texture_id_pool,
+                                    &mut reserved_textures,
+                                )
+                                .map_err(|e| {
+                                    log::warn!("The models of the bundle are not used: {}", e)
                                 })
-                                .collect::<Vec<_>>();
-                            let (data_for_rendering_full_model, bbox): (
-                                Vec<Vec<SameTextureNodeFacesRaw>>,
-                                BoundingBox,
-                            ) = Rsm::generate_meshes_by_texture_id(
-                                &rsm.bounding_box,
-                                rsm.shade_type,
-                                rsm.nodes.len() == 1,
-                                &rsm.nodes,
-                                &textures,
-                            );
-                            (
-                                model_name,
-                                ModelLoadingData {
-                                    model_id,
-                                    data_for_rendering_full_model,
-                                    bbox,
-                                    alpha: rsm.alpha,
-                                },
-                            )
-                        })
-                        .collect();
-                    //
-                    let model_instances = rsw_model_instances
-                        .into_iter()
-                        .map(|rsw_model_instance| {
-                            BackgroundAssetLoader::to_model_instance(
-                                rsw_model_instance,
-                                &models,
-                                map_width,
-                                map_height,
-                            )
-                        })
-                        .collect();
+                                .ok()
+                        });
+                    let (models, model_instances) = match baked {
+                        Some(baked) => (baked.models, baked.model_instances),
+                        None => self.load_models(
+                            rsw_model_instances,
+                            &mut model_id_pool,
+                            &mut texture_id_pool,
+                            &mut reserved_textures,
+                            map_width,
+                            map_height,
+                        ),
+                    };
                     self.to_main_thread
                         .send(FromBackgroundAssetLoaderMsg::LoadModelsResponse {
                             models,
@@ -242,9 +256,92 @@ impl<'a> BackgroundAssetLoader<'a> {
                         })
                         .expect("");
                 }
+                ToBackgroundAssetLoaderMsg::LoadBundle(bundle_path) => {
+                    self.bundle = asset_bundle::load(&bundle_path, &self.asset_loader);
+                }
+                ToBackgroundAssetLoaderMsg::RefreshFiles(paths) => {
+                    for path in paths {
+                        self.asset_loader.refresh_file(&path);
+                    }
+                }
+                ToBackgroundAssetLoaderMsg::ReloadSprite {
+                    path,
+                    mut texture_id_pool,
+                } => {
+                    let mut reserved_textures = Vec::<ReservedTexturedata>::with_capacity(256);
+                    let sprites =
+                        self.reload_sprite(&path, &mut texture_id_pool, &mut reserved_textures);
+                    self.to_main_thread
+                        .send(FromBackgroundAssetLoaderMsg::ReloadSpriteResponse {
+                            sprites,
+                            reserved_textures,
+                            texture_id_pool,
+                        })
+                        .expect("");
+                }
+                ToBackgroundAssetLoaderMsg::ReloadModel {
+                    model_name,
+                    model_id,
+                    mut texture_id_pool,
+                } => {
+                    let mut reserved_textures = Vec::<ReservedTexturedata>::with_capacity(32);
+                    match self.load_model(
+                        &model_name,
+                        model_id,
+                        &mut HashMap::new(),
+                        &mut texture_id_pool,
+                        &mut reserved_textures,
+                    ) {
+                        Ok(model) => self
+                            .to_main_thread
+                            .send(FromBackgroundAssetLoaderMsg::ReloadModelResponse {
+                                model_name,
+                                model,
+                                reserved_textures,
+                                texture_id_pool,
+                            })
+                            .expect(""),
+                        Err(e) => {
+                            log::warn!("Could not reload '{}': {}", model_name, e);
+                            // the textures loaded before the error are not needed
+                            for reserved_texture in reserved_textures {
+                                std::mem::drop(unsafe {
+                                    sdl2::surface::Surface::from_ll(
+                                        &mut *reserved_texture.raw_sdl_surface.0,
+                                    )
+                                });
+                                texture_id_pool.push(reserved_texture.texture_id);
+                            }
+                            self.to_main_thread
+                                .send(FromBackgroundAssetLoaderMsg::ReloadModelFailed {
+                                    texture_id_pool,
+                                })
+                                .expect("");
+                        }
+                    }
+                }
                 ToBackgroundAssetLoaderMsg::StartLoadingSprites(mut texture_id_pool) => {
                     let mut reserved_textures = Vec::<ReservedTexturedata>::with_capacity(8_000);
-                    let sprites = self.load_sprites(&mut texture_id_pool, &mut reserved_textures);
+                    let baked = self
+                        .bundle
+                        .as_mut()
+                        .and_then(|bundle| bundle.sprites.take())
+                        .and_then(|baked| {
+                            baked
+                                .unpack(&mut texture_id_pool, &mut reserved_textures)
+                                .map_err(|e| {
+                                    log::warn!("The sprites of the bundle are not used: {}", e)
+                                })
+                                .ok()
+                        });
+                    let sprites = match baked {
+                        Some(sprites) => {
+                            log::info!("The sprites of the bundle are not hot reloaded");
+                            self.sprites_from_bundle = true;
+                            sprites
+                        }
+                        None => self.load_sprites(&mut texture_id_pool, &mut reserved_textures),
+                    };
                     self.to_main_thread
                         .send(FromBackgroundAssetLoaderMsg::StartLoadingSpritesResponse {
                             sprites: Box::new(sprites),
@@ -263,16 +360,31 @@ impl<'a> BackgroundAssetLoader<'a> {
                     colliders,
                 } => {
                     let mut reserved_textures = Vec::<ReservedTexturedata>::with_capacity(3);
-                    let result = self.load_ground(
-                        &map_name,
-                        &gat,
-                        rectangles,
-                        water_level,
-                        water_height,
-                        &colliders,
-                        &mut texture_id_pool,
-                        &mut reserved_textures,
-                    );
+                    let baked = self
+                        .bundle
+                        .as_mut()
+                        .and_then(|bundle| bundle.ground.take())
+                        .and_then(|baked| {
+                            baked
+                                .unpack(&mut texture_id_pool, &mut reserved_textures)
+                                .map_err(|e| {
+                                    log::warn!("The ground of the bundle is not used: {}", e)
+                                })
+                                .ok()
+                        });
+                    let result = match baked {
+                        Some(ground) => ground,
+                        None => self.load_ground(
+                            &map_name,
+                            &gat,
+                            rectangles,
+                            water_level,
+                            water_height,
+                            &colliders,
+                            &mut texture_id_pool,
+                            &mut reserved_textures,
+                        ),
+                    };
                     self.to_main_thread
                         .send(FromBackgroundAssetLoaderMsg::StartLoadingGroundResponse {
                             ground_result: result,
@@ -285,6 +397,124 @@ impl<'a> BackgroundAssetLoader<'a> {
         }
     }
 
+    pub(super) fn load_models(
+        &self,
+        rsw_model_instances: Vec<RswModelInstance>,
+        model_id_pool: &mut Vec<usize>,
+        texture_id_pool: &mut Vec<TextureId>,
+        reserved_textures: &mut Vec<ReservedTexturedata>,
+        map_width: u32,
+        map_height: u32,
+    ) -> (HashMap<String, ModelLoadingData>, Vec<ModelInstance>) {
+        let mut texture_map = HashMap::<String, TextureId>::with_capacity(128);
+        let model_names: HashSet<_> = rsw_model_instances
+            .iter()
+            .map(|m| m.filename.clone())
+            .collect();
+        let models: HashMap<String, ModelLoadingData> = model_names
+            .into_iter()
+            .map(|model_name| {
+                let model = self
+                    .load_model(
+                        &model_name,
+                        model_id_pool.pop().unwrap(),
+                        &mut texture_map,
+                        texture_id_pool,
+                        reserved_textures,
+                    )
+                    .unwrap();
+                (model_name, model)
+            })
+            .collect();
+        //
+        let model_instances = rsw_model_instances
+            .into_iter()
+            .map(|rsw_model_instance| {
+                BackgroundAssetLoader::to_model_instance(
+                    rsw_model_instance,
+                    &models,
+                    map_width,
+                    map_height,
+                )
+            })
+            .collect();
+        return (models, model_instances);
+    }
+
+    fn load_model(
+        &self,
+        model_name: &str,
+        model_id: usize,
+        texture_map: &mut HashMap<String, TextureId>,
+        texture_id_pool: &mut Vec<TextureId>,
+        reserved_textures: &mut Vec<ReservedTexturedata>,
+    ) -> Result<ModelLoadingData, String> {
+        let file_name = format!("data\\model\\{}", model_name);
+        let content = self.asset_loader.get_content(&file_name)?;
+        let rsm = match Rsm::load(BinaryReader::from_shared(content, &file_name)) {
+            Ok(rsm) => rsm,
+            Err(e) => {
+                log::warn!("{}, the model is not rendered", e);
+                return Ok(ModelLoadingData {
+                    model_id,
+                    data_for_rendering_full_model: vec![],
+                    bbox: BoundingBox {
+                        min: v3(0.0, 0.0, 0.0),
+                        max: v3(0.0, 0.0, 0.0),
+                        range: v3(0.0, 0.0, 0.0),
+                        center: v3(0.0, 0.0, 0.0),
+                    },
+                    alpha: 255,
+                });
+            }
+        };
+        let textures: Vec<(String, TextureId)> = rsm
+            .texture_names
+            .iter()
+            .map(|texture_name| {
+                let texture_id = match texture_map.get(texture_name) {
+                    Some(texture_id) => *texture_id,
+                    None => {
+                        let path = format!("data\\texture\\{}", texture_name);
+                        let texture_id = self.load_texture(
+                            &path,
+                            MyGlEnum::NEAREST,
+                            texture_id_pool,
+                            reserved_textures,
+                        )?;
+                        texture_map.insert(texture_name.to_string(), texture_id);
+                        texture_id
+                    }
+                };
+                Ok((texture_name.to_string(), texture_id))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let (meshes_by_texture_index, bbox) = rsm.generate_meshes_by_texture_index();
+        let data_for_rendering_full_model: Vec<Vec<SameTextureNodeFacesRaw>> =
+            meshes_by_texture_index
+                .into_iter()
+                .map(|node_meshes| {
+                    node_meshes
+                        .into_iter()
+                        .map(|(texture_index, mesh)| {
+                            let (name, gl_tex) = &textures[texture_index];
+                            SameTextureNodeFacesRaw {
+                                mesh,
+                                texture: gl_tex.clone(),
+                                texture_name: name.to_owned(),
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+        return Ok(ModelLoadingData {
+            model_id,
+            data_for_rendering_full_model,
+            bbox,
+            alpha: rsm.alpha,
+        });
+    }
+
     fn to_model_instance(
         model_instance: RswModelInstance,
         models: &HashMap<String, ModelLoadingData>,
@@ -363,7 +593,7 @@ impl<'a> BackgroundAssetLoader<'a> {
         }
     }
 
-    fn load_ground(
+    pub(super) fn load_ground(
         &self,
         map_name: &str,
         gat: &Gat,
@@ -577,13 +807,13 @@ impl<'a> BackgroundAssetLoader<'a> {
             .map(|texture_name| {
                 let path = format!("data\\texture\\{}", texture_name);
                 let surface = GrfEntryLoader::load_sdl_surface2(
-                    self.asset_loader.get_content(&path).unwrap(),
+                    &self.asset_loader.get_content(&path).unwrap(),
                     path.ends_with(".tga"),
                 );
                 surface.unwrap()
             })
             .collect();
-        let surface_atlas = Gnd::create_texture_atlas(texture_surfaces);
+        let surface_atlas = create_texture_atlas(texture_surfaces);
         let texture_id = texture_id_pool.pop().unwrap();
         reserved_textures.push(ReservedTexturedata {
             texture_id,
@@ -602,14 +832,15 @@ impl<'a> BackgroundAssetLoader<'a> {
     ) -> Result<Gnd, String> {
         let file_name = format!("data\\{}.gnd", map_name);
         let content = self.asset_loader.get_content(&file_name)?;
-        return Ok(Gnd::load(
-            BinaryReader::from_vec(content),
+        return Gnd::load(
+            BinaryReader::from_shared(content, &file_name),
             water_level,
             water_height,
-        ));
+        )
+        .map_err(|e| e.to_string());
     }
 
-    fn load_sprites(
+    pub(super) fn load_sprites(
         &self,
         texture_id_pool: &mut Vec<TextureId>,
         reserved_textures: &mut Vec<ReservedTexturedata>,
@@ -628,10 +859,7 @@ impl<'a> BackgroundAssetLoader<'a> {
                         reserved_textures,
                     )
                     .unwrap();
-                // for Idle action, character sprites contains head rotating animations, we don't need them
-                exoskeleton
-                    .action
-                    .remove_frames_in_every_direction(CharActionIndex::Idle as usize, 1..);
+                self.remove_head_rotating_frames(&mut exoskeleton);
                 exoskeleton
             },
             ginseng_bullet: self
@@ -721,9 +949,7 @@ impl<'a> BackgroundAssetLoader<'a> {
                 let mut male = self
                     .load_spr_and_act(&male_file_name, texture_id_pool, reserved_textures)
                     .expect(&format!("Failed loading {:?}", JobSpriteId::CRUSADER2));
-                // for Idle action, character sprites contains head rotating animations, we don't need them
-                male.action
-                    .remove_frames_in_every_direction(CharActionIndex::Idle as usize, 1..);
+                self.remove_head_rotating_frames(&mut male);
                 let female = male.clone();
                 mounted_sprites.insert(JobId::CRUSADER, [male, female]);
                 log::info!("<<< load mounted_character_sprites");
@@ -786,8 +1012,7 @@ impl<'a> BackgroundAssetLoader<'a> {
         reserved_textures: &mut Vec<ReservedTexturedata>,
     ) -> Result<TextureId, String> {
         if let Ok(content) = self.asset_loader.get_content(texture_path) {
-            let surface =
-                GrfEntryLoader::load_sdl_surface2(content, texture_path.ends_with(".tga")).unwrap();
+            let surface = GrfEntryLoader::load_sdl_surface_or_backup(&content, texture_path);
             let texture_id = texture_id_pool.pop().unwrap();
             reserved_textures.push(ReservedTexturedata {
                 texture_id,
@@ -807,7 +1032,99 @@ impl<'a> BackgroundAssetLoader<'a> {
         texture_id_pool: &mut Vec<TextureId>,
         reserved_textures: &mut Vec<ReservedTexturedata>,
     ) -> Result<SpriteResource, String> {
-        self.load_spr_and_act_inner(path, None, None, texture_id_pool, reserved_textures)
+        let sprite =
+            self.load_spr_and_act_inner(path, None, None, texture_id_pool, reserved_textures)?;
+        self.register_loaded_sprite(path, None, &sprite);
+        return Ok(sprite);
+    }
+
+    fn register_loaded_sprite(
+        &self,
+        path: &str,
+        palette: Option<(usize, Vec<u8>)>,
+        sprite: &SpriteResource,
+    ) {
+        self.loaded_sprites
+            .borrow_mut()
+            .entry(CommonAssetLoader::entry_name(path))
+            .or_insert_with(Vec::new)
+            .push(LoadedSprite {
+                path: path.to_owned(),
+                palette,
+                without_head_rotating_frames: false,
+                texture_ids: sprite.textures.clone(),
+            });
+    }
+
+    /// For Idle action, character sprites contains head rotating animations, we don't need them
+    fn remove_head_rotating_frames(&self, sprite: &mut SpriteResource) {
+        sprite
+            .action
+            .remove_frames_in_every_direction(CharActionIndex::Idle as usize, 1..);
+        // so it is done again when the sprite is reloaded
+        if let Some(first_texture_id) = sprite.textures.first() {
+            for loaded_sprite in self.loaded_sprites.borrow_mut().values_mut().flatten() {
+                if loaded_sprite.texture_ids.first() == Some(first_texture_id) {
+                    loaded_sprite.without_head_rotating_frames = true;
+                }
+            }
+        }
+    }
+
+    /// Loads every variant of the sprite again. The texture slots of the old frames are reused,
+    /// the new frames get their slots from `texture_id_pool`.
+    fn reload_sprite(
+        &self,
+        path: &str,
+        texture_id_pool: &mut Vec<TextureId>,
+        reserved_textures: &mut Vec<ReservedTexturedata>,
+    ) -> Vec<(TextureId, SpriteResource)> {
+        let mut loaded_sprites = self.loaded_sprites.borrow_mut();
+        let loaded_sprites = match loaded_sprites.get_mut(&CommonAssetLoader::entry_name(path)) {
+            Some(loaded_sprites) => loaded_sprites,
+            None => {
+                if self.sprites_from_bundle {
+                    log::warn!("'{}' is not reloaded, it comes from the bundle", path);
+                }
+                return vec![];
+            }
+        };
+        let mut reloaded_sprites = Vec::with_capacity(loaded_sprites.len());
+        for loaded_sprite in loaded_sprites.iter_mut() {
+            let old_first_texture_id = match loaded_sprite.texture_ids.first() {
+                Some(texture_id) => *texture_id,
+                // a sprite without frames can not be identified, it is not rendered anyway
+                None => continue,
+            };
+            let pool_len = texture_id_pool.len();
+            texture_id_pool.extend(loaded_sprite.texture_ids.iter().rev());
+            let (palette_index, palette) = match &loaded_sprite.palette {
+                Some((palette_index, palette)) => (Some(*palette_index), Some(palette.as_slice())),
+                None => (None, None),
+            };
+            match self.load_spr_and_act_inner(
+                &loaded_sprite.path,
+                palette_index,
+                palette,
+                texture_id_pool,
+                reserved_textures,
+            ) {
+                Ok(mut sprite) => {
+                    if loaded_sprite.without_head_rotating_frames {
+                        sprite
+                            .action
+                            .remove_frames_in_every_direction(CharActionIndex::Idle as usize, 1..);
+                    }
+                    loaded_sprite.texture_ids = sprite.textures.clone();
+                    reloaded_sprites.push((old_first_texture_id, sprite));
+                }
+                Err(e) => {
+                    log::warn!("Could not reload '{}': {}", path, e);
+                    texture_id_pool.truncate(pool_len);
+                }
+            }
+        }
+        return reloaded_sprites;
     }
 
     fn load_spr_and_act_inner(
@@ -818,21 +1135,44 @@ impl<'a> BackgroundAssetLoader<'a> {
         texture_id_pool: &mut Vec<TextureId>,
         reserved_textures: &mut Vec<ReservedTexturedata>,
     ) -> Result<SpriteResource, String> {
-        let content = self.asset_loader.get_content(&format!("{}.spr", path))?;
-        let mut reader = BinaryReader::from_vec(content);
-        let (version, indexed_frame_count, rgba_frame_count) = SpriteFile::read_header(&mut reader);
-        let texture_ids = (0..(indexed_frame_count + rgba_frame_count as usize))
+        let spr_name = format!("{}.spr", path);
+        let act_name = format!("{}.act", path);
+        let spr_content = self.asset_loader.get_content(&spr_name)?;
+        let act_content = self.asset_loader.get_content(&act_name)?;
+        let parsed = BackgroundAssetLoader::parse_spr_and_act(
+            BinaryReader::from_shared(spr_content, &spr_name),
+            BinaryReader::from_shared(act_content, &act_name),
+            palette,
+        );
+        let (frames, action) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                log::warn!("{}, the placeholder sprite is used instead", e);
+                // it gets its own texture, so it can be found when the sprite is reloaded
+                let texture_id = texture_id_pool
+                    .pop()
+                    .ok_or_else(|| format!("Not enough texture slots for '{}'", path))?;
+                reserved_textures.push(ReservedTexturedata {
+                    texture_id,
+                    name: BackgroundAssetLoader::sprite_texture_name(path, palette_index, 0),
+                    raw_sdl_surface: SendableRawSdlSurface::new(
+                        GrfEntryLoader::missing_texture_surface(),
+                    ),
+                    minmag: MyGlEnum::NEAREST,
+                });
+                return Ok(SpriteResource {
+                    textures: vec![texture_id],
+                    ..SpriteResource::placeholder()
+                });
+            }
+        };
+        if texture_id_pool.len() < frames.len() {
+            return Err(format!("Not enough texture slots for '{}'", path));
+        }
+        let texture_ids = (0..frames.len())
             .map(|_it| texture_id_pool.pop().unwrap())
             .collect::<Vec<_>>();
 
-        let frames = SpriteFile::load(
-            reader,
-            palette,
-            version,
-            indexed_frame_count,
-            rgba_frame_count,
-        )
-        .frames;
         frames
             .into_iter()
             .map(|frame| BackgroundAssetLoader::sdl_surface_from_frame(frame))
@@ -840,28 +1180,58 @@ impl<'a> BackgroundAssetLoader<'a> {
             .for_each(|(index, sdl_surface)| {
                 reserved_textures.push(ReservedTexturedata {
                     texture_id: texture_ids[index],
-                    name: format!(
-                        "{}_{}_{}",
-                        &path.to_string(),
-                        palette_index.unwrap_or(0),
-                        index
-                    ),
+                    name: BackgroundAssetLoader::sprite_texture_name(path, palette_index, index),
                     raw_sdl_surface: SendableRawSdlSurface::new(sdl_surface),
                     minmag: MyGlEnum::NEAREST,
                 })
             });
 
-        let content = self.asset_loader.get_content(&format!("{}.act", path))?;
-        let action = ActionFile::load(BinaryReader::from_vec(content));
         return Ok(SpriteResource {
             action,
             textures: texture_ids,
         });
     }
 
-    fn sdl_surface_from_frame(
-        mut frame: crate::grf::spr::SprFrame,
-    ) -> sdl2::surface::Surface<'static> {
+    fn sprite_texture_name(path: &str, palette_index: Option<usize>, index: usize) -> String {
+        format!("{}_{}_{}", path, palette_index.unwrap_or(0), index)
+    }
+
+    fn parse_spr_and_act(
+        mut spr_reader: BinaryReader,
+        act_reader: BinaryReader,
+        palette: Option<&[u8]>,
+    ) -> Result<(Vec<SprFrame>, ActionFile), ParseError> {
+        let (version, indexed_frame_count, rgba_frame_count) =
+            SpriteFile::read_header(&mut spr_reader)?;
+        let frames = SpriteFile::load(
+            spr_reader,
+            palette,
+            version,
+            indexed_frame_count,
+            rgba_frame_count,
+        )?
+        .frames;
+        let act_len = act_reader.len();
+        let act_file_name = act_reader.file_name().to_owned();
+        let action = ActionFile::load(act_reader)?;
+        // the renderer indexes the textures with the frame indices of the layers
+        if let Some(max_index) = action.max_sprite_frame_index() {
+            if max_index >= frames.len() {
+                return Err(ParseError {
+                    file_name: act_file_name,
+                    offset: act_len,
+                    kind: ParseErrorKind::InvalidData(format!(
+                        "sprite frame {} is referenced, but the sprite has only {} frames",
+                        max_index,
+                        frames.len()
+                    )),
+                });
+            }
+        }
+        return Ok((frames, action));
+    }
+
+    fn sdl_surface_from_frame(mut frame: SprFrame) -> sdl2::surface::Surface<'static> {
         let frame_surface = sdl2::surface::Surface::from_data(
             &mut frame.data,
             frame.width as u32,
@@ -928,9 +1298,7 @@ impl<'a> BackgroundAssetLoader<'a> {
                         let mut head = self
                             .load_spr_and_act(male_file_name, texture_id_pool, reserved_textures)
                             .expect(&format!("Failed loading head({})", i));
-                        // for Idle action, character sprites contains head rotating animations, we don't need them
-                        head.action
-                            .remove_frames_in_every_direction(CharActionIndex::Idle as usize, 1..);
+                        self.remove_head_rotating_frames(&mut head);
                         Some(head)
                     } else {
                         None
@@ -958,9 +1326,7 @@ impl<'a> BackgroundAssetLoader<'a> {
                         let mut head = self
                             .load_spr_and_act(female_file_name, texture_id_pool, reserved_textures)
                             .expect(&format!("Failed loading head({})", i));
-                        // for Idle action, character sprites contains head rotating animations, we don't need them
-                        head.action
-                            .remove_frames_in_every_direction(CharActionIndex::Idle as usize, 1..);
+                        self.remove_head_rotating_frames(&mut head);
                         Some(head)
                     } else {
                         None
@@ -1084,19 +1450,14 @@ impl<'a> BackgroundAssetLoader<'a> {
                     let mut male = self
                         .load_spr_and_act(&male_file_path, texture_id_pool, reserved_textures)
                         .expect(&format!("Failed loading {:?}", job_sprite_id));
-                    // for Idle action, character sprites contains head rotating animations, we don't need them
-                    male.action
-                        .remove_frames_in_every_direction(CharActionIndex::Idle as usize, 1..);
+                    self.remove_head_rotating_frames(&mut male);
                     let female = male.clone();
                     (male.clone(), female.clone(), male, female)
                 } else if !self.asset_loader.exists(&format!("{}.act", male_file_path)) {
                     let mut female = self
                         .load_spr_and_act(&female_file_path, texture_id_pool, reserved_textures)
                         .expect(&format!("Failed loading {:?}", job_sprite_id));
-                    // for Idle action, character sprites contains head rotating animations, we don't need them
-                    female
-                        .action
-                        .remove_frames_in_every_direction(CharActionIndex::Idle as usize, 1..);
+                    self.remove_head_rotating_frames(&mut female);
                     let male = female.clone();
                     (male.clone(), female.clone(), male, female)
                 } else {
@@ -1163,10 +1524,7 @@ impl<'a> BackgroundAssetLoader<'a> {
                 reserved_textures,
             )
             .expect(&format!("Failed loading {:?}", job_sprite_id));
-        // for Idle action, character sprites contains head rotating animations, we don't need them
-        sprite_res
-            .action
-            .remove_frames_in_every_direction(CharActionIndex::Idle as usize, 1..);
+        self.remove_head_rotating_frames(&mut sprite_res);
         sprite_res
     }
 
@@ -1178,13 +1536,15 @@ impl<'a> BackgroundAssetLoader<'a> {
         texture_id_pool: &mut Vec<TextureId>,
         reserved_textures: &mut Vec<ReservedTexturedata>,
     ) -> Result<SpriteResource, String> {
-        self.load_spr_and_act_inner(
+        let sprite = self.load_spr_and_act_inner(
             path,
             Some(palette_index),
             Some(palette),
             texture_id_pool,
             reserved_textures,
-        )
+        )?;
+        self.register_loaded_sprite(path, Some((palette_index, palette.to_vec())), &sprite);
+        return Ok(sprite);
     }
 
     fn load_palette(
@@ -1192,7 +1552,7 @@ impl<'a> BackgroundAssetLoader<'a> {
         job_sprite_id: &JobSpriteId,
         job_file_name: &str,
         palette_id: usize,
-    ) -> Vec<u8> {
+    ) -> Arc<Vec<u8>> {
         let palette = {
             // for some jobs, the palette file name is truncated, so this
             // code tries names one by one removing the last char in each
@@ -1219,3 +1579,35 @@ impl<'a> BackgroundAssetLoader<'a> {
         palette
     }
 }
+
+fn create_texture_atlas(
+    texture_surfaces: Vec<sdl2::surface::Surface>,
+) -> sdl2::surface::Surface<'static> {
+    let _width = (texture_surfaces.len() as f32).sqrt().round() as i32;
+    let width = ((_width * 258) as u32).next_power_of_two();
+    let height = ((texture_surfaces.len() as f32).sqrt().ceil() as u32 * 258).next_power_of_two();
+    let mut surface_atlas =
+        sdl2::surface::Surface::new(width, height, PixelFormatEnum::RGB888).unwrap();
+    for (i, texture_surface) in texture_surfaces.iter().enumerate() {
+        let x = (i as i32 % _width) * 258;
+        let y = ((i as i32 / _width) as f32).floor() as i32 * 258;
+        let optimized = texture_surface
+            .convert(&surface_atlas.pixel_format())
+            .unwrap();
+        optimized
+            .blit_scaled(
+                None,
+                &mut surface_atlas,
+                sdl2::rect::Rect::new(x, y, 258, 258),
+            )
+            .unwrap();
+        optimized
+            .blit_scaled(
+                None,
+                &mut surface_atlas,
+                sdl2::rect::Rect::new(x + 1, y + 1, 256, 256),
+            )
+            .unwrap();
+    }
+    surface_atlas
+}
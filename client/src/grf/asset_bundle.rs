@@ -0,0 +1,341 @@
+use crate::grf::asset_async_loader::{
+    AsyncGroundLoadResult, BackgroundAssetLoader, ModelLoadingData, ReservedTexturedata,
+    SendableRawSdlSurface,
+};
+use crate::grf::asset_loader::GrfEntryLoader;
+use crate::grf::texture::TextureId;
+use crate::my_gl::MyGlEnum;
+use crate::runtime_assets::map::{create_collider, ModelInstance, PhysicEngine};
+use crate::systems::Sprites;
+use rustarok_common::common::{measure_time, Vec2};
+use rustarok_common::grf::asset_loader::CommonAssetLoader;
+use rustarok_common::grf::bundle::{self, BakedTexture};
+use sdl2::pixels::PixelFormatEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+
+/// The sprites, the ground and the models of a map decoded in advance, see `bake`.
+/// The ids of the textures and the models in it are local to their part, they are replaced
+/// by the reserved ids when the part is unpacked.
+#[derive(Serialize, Deserialize)]
+pub(super) struct AssetBundle {
+    pub sprites: Option<BakedPart<Sprites>>,
+    pub ground: Option<BakedPart<AsyncGroundLoadResult>>,
+    pub models: Option<BakedPart<BakedModels>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct BakedPart<T> {
+    pub content: T,
+    /// by their local ids
+    pub textures: Vec<(usize, BakedTexture)>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(super) struct BakedModels {
+    pub models: HashMap<String, ModelLoadingData>,
+    pub model_instances: Vec<ModelInstance>,
+}
+
+/// The configured directory (bundle_dir in config.toml) or "bundles"
+pub fn bundle_path(configured_dir: &Option<String>, map_name: &str) -> PathBuf {
+    let dir = match configured_dir {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from("bundles"),
+    };
+    return dir.join(format!("{}.bundle", map_name));
+}
+
+/// Decodes the sprites, the ground and the models of the map the same way as they are decoded
+/// at startup, and writes them into a bundle
+pub fn bake(
+    asset_loader: &GrfEntryLoader,
+    map_name: &str,
+    bundle_path: &Path,
+) -> Result<(), String> {
+    // nothing is sent between the threads, the loading methods are called directly
+    let (to_main_thread, _from_background_loader) = channel();
+    let (_to_background_loader, from_main_thread) = channel();
+    let background_loader = BackgroundAssetLoader::new(
+        to_main_thread,
+        from_main_thread,
+        asset_loader.asset_loader.clone(),
+    );
+    let rsw = asset_loader.load_map(map_name)?;
+    let (gat, rectangles) = asset_loader.load_gat(map_name)?;
+
+    log::info!(">>> bake sprites");
+    let mut reserved_textures = Vec::with_capacity(8_000);
+    let sprites =
+        background_loader.load_sprites(&mut local_texture_id_pool(10_000), &mut reserved_textures);
+    let sprites = BakedPart::pack(sprites, reserved_textures);
+    log::info!("<<< bake sprites: {} textures", sprites.textures.len());
+
+    log::info!(">>> bake ground");
+    let mut physics_world = PhysicEngine::new();
+    let colliders: Vec<(Vec2, Vec2)> = rectangles
+        .iter()
+        .map(|cell| create_collider(&mut physics_world, cell))
+        .collect();
+    let mut reserved_textures = Vec::with_capacity(3);
+    let ground = background_loader.load_ground(
+        map_name,
+        &gat,
+        rectangles,
+        rsw.water.level,
+        rsw.water.wave_height,
+        &colliders,
+        &mut local_texture_id_pool(3),
+        &mut reserved_textures,
+    );
+    let ground = BakedPart::pack(ground, reserved_textures);
+    log::info!("<<< bake ground");
+
+    log::info!(">>> bake models");
+    let mut reserved_textures = Vec::with_capacity(1000);
+    let (models, model_instances) = background_loader.load_models(
+        rsw.models,
+        &mut (0..500).rev().collect::<Vec<usize>>(),
+        &mut local_texture_id_pool(500),
+        &mut reserved_textures,
+        gat.width / 2,
+        gat.height / 2,
+    );
+    let models = BakedPart::pack(
+        BakedModels {
+            models,
+            model_instances,
+        },
+        reserved_textures,
+    );
+    log::info!(
+        "<<< bake models: {} models, {} textures",
+        models.content.models.len(),
+        models.textures.len()
+    );
+
+    let bundle = AssetBundle {
+        sprites: Some(sprites),
+        ground: Some(ground),
+        models: Some(models),
+    };
+    return bundle::save(bundle_path, &asset_loader.asset_loader, &bundle);
+}
+
+/// None if there is no up to date bundle, then everything is decoded from the asset sources
+pub(super) fn load(bundle_path: &Path, asset_loader: &CommonAssetLoader) -> Option<AssetBundle> {
+    let (elapsed, bundle) = measure_time(|| bundle::load(bundle_path, asset_loader));
+    return match bundle {
+        Ok(Some(bundle)) => {
+            log::info!(
+                "'{}' has been loaded: {}ms",
+                bundle_path.display(),
+                elapsed.as_millis()
+            );
+            Some(bundle)
+        }
+        Ok(None) => {
+            log::info!(
+                "'{}' is missing or outdated, the assets are decoded from the asset sources",
+                bundle_path.display()
+            );
+            None
+        }
+        Err(e) => {
+            log::warn!("Could not load the bundle: {}", e);
+            None
+        }
+    };
+}
+
+/// TextureId(0) is the dummy texture, which is used by the placeholder sprites
+fn local_texture_id_pool(count: usize) -> Vec<TextureId> {
+    return (1..=count).rev().map(TextureId).collect();
+}
+
+impl<T> BakedPart<T> {
+    fn pack(content: T, reserved_textures: Vec<ReservedTexturedata>) -> BakedPart<T> {
+        let textures = reserved_textures
+            .into_iter()
+            .map(|reserved_texture| {
+                let sdl_surface = unsafe {
+                    sdl2::surface::Surface::from_ll(&mut *reserved_texture.raw_sdl_surface.0)
+                };
+                let sdl_surface = GrfEntryLoader::to_rgba32_surface(sdl_surface);
+                let row_len = sdl_surface.width() as usize * 4;
+                let pitch = sdl_surface.pitch() as usize;
+                let mut rgba = Vec::with_capacity(row_len * sdl_surface.height() as usize);
+                if row_len > 0 {
+                    for row in sdl_surface
+                        .without_lock()
+                        .unwrap()
+                        .chunks(pitch)
+                        .take(sdl_surface.height() as usize)
+                    {
+                        rgba.extend_from_slice(&row[..row_len]);
+                    }
+                }
+                let baked_texture = BakedTexture {
+                    name: reserved_texture.name,
+                    width: sdl_surface.width(),
+                    height: sdl_surface.height(),
+                    min_mag: reserved_texture.minmag as u32,
+                    rgba,
+                };
+                (reserved_texture.texture_id.0, baked_texture)
+            })
+            .collect();
+        return BakedPart { content, textures };
+    }
+}
+
+/// The unpacking fails without taking anything from the pools, then the part has to be decoded
+/// from the asset sources
+impl BakedPart<Sprites> {
+    pub fn unpack(
+        self,
+        texture_id_pool: &mut Vec<TextureId>,
+        reserved_textures: &mut Vec<ReservedTexturedata>,
+    ) -> Result<Sprites, String> {
+        let texture_ids = unpack_textures(self.textures, texture_id_pool, reserved_textures)?;
+        let mut sprites = self.content;
+        for sprite in sprites.iter_mut() {
+            for texture_id in sprite.textures.iter_mut() {
+                remap_texture_id(&texture_ids, texture_id);
+            }
+        }
+        for texture_id in vec![
+            &mut sprites.numbers,
+            &mut sprites.magic_target,
+            &mut sprites.fire_particle,
+            &mut sprites.clock,
+        ] {
+            remap_texture_id(&texture_ids, texture_id);
+        }
+        return Ok(sprites);
+    }
+}
+
+impl BakedPart<AsyncGroundLoadResult> {
+    pub fn unpack(
+        self,
+        texture_id_pool: &mut Vec<TextureId>,
+        reserved_textures: &mut Vec<ReservedTexturedata>,
+    ) -> Result<AsyncGroundLoadResult, String> {
+        let texture_ids = unpack_textures(self.textures, texture_id_pool, reserved_textures)?;
+        let mut ground = self.content;
+        remap_texture_id(&texture_ids, &mut ground.texture_atlas);
+        remap_texture_id(&texture_ids, &mut ground.tile_color_texture);
+        remap_texture_id(&texture_ids, &mut ground.lightmap_texture);
+        return Ok(ground);
+    }
+}
+
+impl BakedPart<BakedModels> {
+    pub fn unpack(
+        self,
+        model_id_pool: &mut Vec<usize>,
+        texture_id_pool: &mut Vec<TextureId>,
+        reserved_textures: &mut Vec<ReservedTexturedata>,
+    ) -> Result<BakedModels, String> {
+        let mut baked = self.content;
+        if baked.models.len() > model_id_pool.len() {
+            return Err(format!(
+                "{} models do not fit into the {} free model slots",
+                baked.models.len(),
+                model_id_pool.len()
+            ));
+        }
+        if let Some(model_instance) = baked.model_instances.iter().find(|instance| {
+            !baked
+                .models
+                .values()
+                .any(|model| model.model_id == instance.asset_db_model_index)
+        }) {
+            return Err(format!(
+                "unknown model index {}",
+                model_instance.asset_db_model_index
+            ));
+        }
+        let texture_ids = unpack_textures(self.textures, texture_id_pool, reserved_textures)?;
+        let mut model_ids = HashMap::with_capacity(baked.models.len());
+        let reserved_model_ids = model_id_pool.split_off(model_id_pool.len() - baked.models.len());
+        for (model, model_id) in baked.models.values_mut().zip(reserved_model_ids) {
+            model_ids.insert(model.model_id, model_id);
+            model.model_id = model_id;
+            for faces in model.data_for_rendering_full_model.iter_mut().flatten() {
+                remap_texture_id(&texture_ids, &mut faces.texture);
+            }
+        }
+        for model_instance in baked.model_instances.iter_mut() {
+            model_instance.asset_db_model_index = model_ids[&model_instance.asset_db_model_index];
+        }
+        return Ok(baked);
+    }
+}
+
+/// Reserves the textures with the ids of the pool.
+/// The returned map translates the local ids to the reserved ones.
+fn unpack_textures(
+    textures: Vec<(usize, BakedTexture)>,
+    texture_id_pool: &mut Vec<TextureId>,
+    reserved_textures: &mut Vec<ReservedTexturedata>,
+) -> Result<HashMap<TextureId, TextureId>, String> {
+    if textures.len() > texture_id_pool.len() {
+        return Err(format!(
+            "{} textures do not fit into the {} free texture slots",
+            textures.len(),
+            texture_id_pool.len()
+        ));
+    }
+    let reserved_ids = texture_id_pool.split_off(texture_id_pool.len() - textures.len());
+    return Ok(textures
+        .into_iter()
+        .zip(reserved_ids)
+        .map(|((local_id, baked_texture), texture_id)| {
+            let minmag = if baked_texture.min_mag == MyGlEnum::LINEAR as u32 {
+                MyGlEnum::LINEAR
+            } else {
+                MyGlEnum::NEAREST
+            };
+            reserved_textures.push(ReservedTexturedata {
+                texture_id,
+                raw_sdl_surface: SendableRawSdlSurface::new(sdl_surface_from_baked(&baked_texture)),
+                name: baked_texture.name,
+                minmag,
+            });
+            (TextureId(local_id), texture_id)
+        })
+        .collect());
+}
+
+/// The ids which were not reserved by the bundle (e.g. the dummy texture) are left as they are
+fn remap_texture_id(texture_ids: &HashMap<TextureId, TextureId>, texture_id: &mut TextureId) {
+    if let Some(reserved_id) = texture_ids.get(texture_id) {
+        *texture_id = *reserved_id;
+    }
+}
+
+fn sdl_surface_from_baked(baked_texture: &BakedTexture) -> sdl2::surface::Surface<'static> {
+    let mut sdl_surface = sdl2::surface::Surface::new(
+        baked_texture.width,
+        baked_texture.height,
+        PixelFormatEnum::RGBA32,
+    )
+    .unwrap();
+    let row_len = baked_texture.width as usize * 4;
+    let pitch = sdl_surface.pitch() as usize;
+    if row_len > 0 {
+        sdl_surface.with_lock_mut(|pixels| {
+            for (row, baked_row) in pixels
+                .chunks_mut(pitch)
+                .zip(baked_texture.rgba.chunks(row_len))
+            {
+                row[..row_len].copy_from_slice(baked_row);
+            }
+        });
+    }
+    return sdl_surface;
+}
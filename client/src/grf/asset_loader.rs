@@ -1,3 +1,4 @@
+use crate::effect::{StrEffectId, StrEffectType};
 use crate::grf::asset_async_loader::{
     AsyncGroundLoadResult, BackgroundAssetLoader, FromBackgroundAssetLoaderMsg, ModelLoadingData,
     ReservedTexturedata, ToBackgroundAssetLoaderMsg,
@@ -10,6 +11,7 @@ use crate::my_gl::{Gl, MyGlEnum};
 use crate::runtime_assets::map::{
     MapRenderData, ModelInstance, ModelRenderData, SameTextureNodeFaces,
 };
+use crate::strum::IntoEnumIterator;
 use crate::systems::SystemVariables;
 use crate::video::{VertexArray, VertexAttribDefinition};
 use rustarok_common::common::Vec2;
@@ -19,32 +21,39 @@ use rustarok_common::grf::gat::{BlockingRectangle, Gat};
 use sdl2::image::ImageRWops;
 use sdl2::mixer::LoaderRWops;
 use sdl2::pixels::PixelFormatEnum;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::os::raw::c_void;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver, Sender};
 
 pub struct GrfEntryLoader<'a> {
     to_2nd_thread: Sender<ToBackgroundAssetLoaderMsg>,
     from_2nd_thread: Receiver<FromBackgroundAssetLoaderMsg<'a>>,
     pub asset_loader: CommonAssetLoader,
+    // the slots which were reserved but not used by the hot reloads
+    spare_texture_slots: Vec<TextureId>,
 }
 
 impl<'a> GrfEntryLoader<'a> {
     pub fn new<P: AsRef<Path> + Clone>(
         paths: &[P],
+        cache_path: &Path,
+        content_cache_bytes: usize,
     ) -> Result<GrfEntryLoader<'static>, std::io::Error> {
         let (to_main_thread, from_2nd_thread) = channel::<FromBackgroundAssetLoaderMsg>();
         let (to_2nd_thread, from_main_thread) = channel::<ToBackgroundAssetLoaderMsg>();
 
-        let cloned_asset_loader = CommonAssetLoader::new(paths)?;
+        let asset_loader = CommonAssetLoader::new(paths, cache_path, content_cache_bytes)?;
+        // the archives and the decompressed entries are shared between the threads
+        let cloned_asset_loader = asset_loader.clone();
         std::thread::spawn(move || {
             BackgroundAssetLoader::new(to_main_thread, from_main_thread, cloned_asset_loader).run();
         });
         Ok(GrfEntryLoader {
             to_2nd_thread,
-            asset_loader: CommonAssetLoader::new(paths)?,
+            asset_loader,
             from_2nd_thread,
+            spare_texture_slots: Vec::new(),
         })
     }
 
@@ -56,6 +65,14 @@ impl<'a> GrfEntryLoader<'a> {
             .expect("");
     }
 
+    /// The sprites, the ground and the models are taken from the bundle if it is up to date,
+    /// so it has to be loaded before they are requested
+    pub fn load_bundle(&self, bundle_path: PathBuf) {
+        self.to_2nd_thread
+            .send(ToBackgroundAssetLoaderMsg::LoadBundle(bundle_path))
+            .expect("");
+    }
+
     pub fn start_loading_ground(
         &self,
         gl: &Gl,
@@ -81,7 +98,7 @@ impl<'a> GrfEntryLoader<'a> {
     }
 
     pub fn process_async_loading(
-        &self,
+        &mut self,
         gl: &Gl,
         sys_vars: &mut SystemVariables,
         asset_db: &mut AssetDatabase,
@@ -98,8 +115,7 @@ impl<'a> GrfEntryLoader<'a> {
                         filename,
                     } => {
                         let surface =
-                            GrfEntryLoader::load_sdl_surface2(content, filename.ends_with(".tga"))
-                                .unwrap();
+                            GrfEntryLoader::load_sdl_surface_or_backup(&content, &filename);
 
                         let gl_texture =
                             GrfEntryLoader::create_texture_from_surface_inner(gl, surface, minmag);
@@ -131,6 +147,44 @@ impl<'a> GrfEntryLoader<'a> {
                         texture_id_pool,
                         model_id_pool,
                     ),
+                    FromBackgroundAssetLoaderMsg::ReloadSpriteResponse {
+                        sprites,
+                        reserved_textures,
+                        texture_id_pool,
+                    } => {
+                        GrfEntryLoader::replace_reserved_textures(gl, asset_db, reserved_textures);
+                        for (old_first_texture_id, sprite) in sprites {
+                            for sprite_resource in sys_vars
+                                .assets
+                                .sprites
+                                .iter_mut()
+                                .filter(|it| it.textures.first() == Some(&old_first_texture_id))
+                            {
+                                *sprite_resource = sprite.clone();
+                            }
+                        }
+                        self.spare_texture_slots.extend(texture_id_pool);
+                    }
+                    FromBackgroundAssetLoaderMsg::ReloadModelResponse {
+                        model_name,
+                        model,
+                        reserved_textures,
+                        texture_id_pool,
+                    } => {
+                        GrfEntryLoader::replace_reserved_textures(gl, asset_db, reserved_textures);
+                        let model_id = model.model_id;
+                        let model_render_data = GrfEntryLoader::allocate_vbo(gl, model);
+                        asset_db.fill_bulk_reserved_model_slot(
+                            model_id,
+                            model_render_data,
+                            model_name.clone(),
+                        );
+                        self.spare_texture_slots.extend(texture_id_pool);
+                        log::info!("'{}' has been reloaded", model_name);
+                    }
+                    FromBackgroundAssetLoaderMsg::ReloadModelFailed { texture_id_pool } => {
+                        self.spare_texture_slots.extend(texture_id_pool);
+                    }
                     FromBackgroundAssetLoaderMsg::StartLoadingGroundResponse {
                         ground_result,
                         reserved_textures,
@@ -360,24 +414,144 @@ impl<'a> GrfEntryLoader<'a> {
         reserved_textures: Vec<ReservedTexturedata>,
     ) -> () {
         for reserved_texture in reserved_textures.into_iter() {
-            let sdl_surface = unsafe {
-                sdl2::surface::Surface::from_ll(&mut *reserved_texture.raw_sdl_surface.0)
-            };
-            let gl_texture = GrfEntryLoader::create_texture_from_surface_inner(
-                gl,
-                sdl_surface,
-                reserved_texture.minmag,
-            );
-            asset_db.fill_bulk_reserved_texture_slot(
-                reserved_texture.texture_id,
-                gl_texture,
-                reserved_texture.name,
-            );
+            let (texture_id, gl_texture, name) =
+                GrfEntryLoader::create_reserved_texture(gl, reserved_texture);
+            asset_db.fill_bulk_reserved_texture_slot(texture_id, gl_texture, name);
+        }
+    }
+
+    /// The names of the reloaded textures are already registered
+    fn replace_reserved_textures(
+        gl: &Gl,
+        asset_db: &mut AssetDatabase,
+        reserved_textures: Vec<ReservedTexturedata>,
+    ) -> () {
+        for reserved_texture in reserved_textures.into_iter() {
+            let (texture_id, gl_texture, name) =
+                GrfEntryLoader::create_reserved_texture(gl, reserved_texture);
+            asset_db.replace_texture(texture_id, gl_texture, name);
+        }
+    }
+
+    fn create_reserved_texture(
+        gl: &Gl,
+        reserved_texture: ReservedTexturedata,
+    ) -> (TextureId, GlTexture, String) {
+        let sdl_surface =
+            unsafe { sdl2::surface::Surface::from_ll(&mut *reserved_texture.raw_sdl_surface.0) };
+        let gl_texture = GrfEntryLoader::create_texture_from_surface_inner(
+            gl,
+            sdl_surface,
+            reserved_texture.minmag,
+        );
+        return (
+            reserved_texture.texture_id,
+            gl_texture,
+            reserved_texture.name,
+        );
+    }
+
+    /// Reloads the sprites, effects, models and textures whose files were changed in a
+    /// directory source. They keep their ids, so the entities and the map are not touched.
+    pub fn reload_changed_files(
+        &mut self,
+        gl: &Gl,
+        changed_files: Vec<PathBuf>,
+        sys_vars: &mut SystemVariables,
+        asset_db: &mut AssetDatabase,
+    ) {
+        let names: Vec<String> = changed_files
+            .iter()
+            .filter_map(|path| self.asset_loader.refresh_file(path))
+            .collect();
+        self.to_2nd_thread
+            .send(ToBackgroundAssetLoaderMsg::RefreshFiles(changed_files))
+            .expect("");
+        let mut reloaded_sprite_paths = HashSet::new();
+        for name in names {
+            log::info!("'{}' has been changed", name);
+            if name.ends_with(".spr") || name.ends_with(".act") {
+                let path = name[..name.len() - 4].to_owned();
+                // the spr and the act files are usually saved together
+                if reloaded_sprite_paths.insert(path.clone()) {
+                    let texture_id_pool = self.take_texture_slots(gl, asset_db, 1024);
+                    self.to_2nd_thread
+                        .send(ToBackgroundAssetLoaderMsg::ReloadSprite {
+                            path,
+                            texture_id_pool,
+                        })
+                        .expect("");
+                }
+            } else if name.ends_with(".str") {
+                self.reload_effect(gl, &name, sys_vars, asset_db);
+            } else if name.starts_with("data\\model\\") {
+                let model = asset_db.find_model_index_ignore_case(&name["data\\model\\".len()..]);
+                if let Some((model_name, model_id)) = model {
+                    let texture_id_pool = self.take_texture_slots(gl, asset_db, 64);
+                    self.to_2nd_thread
+                        .send(ToBackgroundAssetLoaderMsg::ReloadModel {
+                            model_name,
+                            model_id,
+                            texture_id_pool,
+                        })
+                        .expect("");
+                }
+            } else {
+                for texture_id in asset_db.find_texture_ids_ignore_case(&name) {
+                    self.to_2nd_thread
+                        .send(ToBackgroundAssetLoaderMsg::LoadTexture {
+                            texture_id,
+                            minmag: MyGlEnum::NEAREST,
+                            filename: name.clone(),
+                        })
+                        .expect("");
+                }
+            }
+        }
+    }
+
+    fn reload_effect(
+        &self,
+        gl: &Gl,
+        name: &str,
+        sys_vars: &mut SystemVariables,
+        asset_db: &mut AssetDatabase,
+    ) {
+        let effect_type = StrEffectType::iter().find(|it| {
+            let file_name = format!("data\\texture\\effect\\{}.str", it.get_effect_filename());
+            CommonAssetLoader::entry_name(&file_name) == name
+        });
+        if let Some(effect_type) = effect_type {
+            match self.load_effect(gl, effect_type.get_effect_filename(), asset_db) {
+                Ok(str_file) => {
+                    sys_vars.assets.str_effects[StrEffectId::from(effect_type).0] = str_file;
+                    sys_vars.assets.str_effects_revision += 1;
+                }
+                Err(e) => log::warn!("Could not reload '{}': {}", name, e),
+            }
         }
     }
 
+    fn take_texture_slots(
+        &mut self,
+        gl: &Gl,
+        asset_db: &mut AssetDatabase,
+        count: usize,
+    ) -> Vec<TextureId> {
+        let spare_count = self.spare_texture_slots.len().min(count);
+        let mut texture_slots = self
+            .spare_texture_slots
+            .split_off(self.spare_texture_slots.len() - spare_count);
+        texture_slots.extend(asset_db.reserve_texture_slots(gl, count - spare_count));
+        return texture_slots;
+    }
+
     /// Clones backup surfaces, quite inefficient to share one surface...
     pub fn backup_surface(&self) -> sdl2::surface::Surface {
+        GrfEntryLoader::missing_texture_surface()
+    }
+
+    pub(super) fn missing_texture_surface() -> sdl2::surface::Surface<'static> {
         let mut missing_texture =
             sdl2::surface::Surface::new(256, 256, PixelFormatEnum::RGBA8888).unwrap();
         missing_texture
@@ -394,25 +568,27 @@ impl<'a> GrfEntryLoader<'a> {
     ) -> Result<StrFile, String> {
         let file_name = format!("data\\texture\\effect\\{}.str", effect_name);
         let content = self.asset_loader.get_content(&file_name)?;
-        return Ok(StrFile::load(
+        return StrFile::load(
             gl,
             &self,
             asset_db,
-            BinaryReader::from_vec(content),
+            BinaryReader::from_shared(content, &file_name),
             effect_name,
-        ));
+        );
     }
 
     pub fn load_map(&self, map_name: &str) -> Result<Rsw, String> {
         let file_name = format!("data\\{}.rsw", map_name);
         let content = self.asset_loader.get_content(&file_name)?;
-        return Ok(Rsw::load(BinaryReader::from_vec(content)));
+        return Rsw::load(BinaryReader::from_shared(content, &file_name))
+            .map_err(|e| e.to_string());
     }
 
     pub fn load_gat(&self, map_name: &str) -> Result<(Gat, Vec<BlockingRectangle>), String> {
         let file_name = format!("data\\{}.gat", map_name);
         let content = self.asset_loader.get_content(&file_name)?;
-        return Ok(Gat::load(BinaryReader::from_vec(content), map_name));
+        return Gat::load(BinaryReader::from_shared(content, &file_name), map_name)
+            .map_err(|e| e.to_string());
     }
 
     pub fn start_loading_models(
@@ -442,14 +618,27 @@ impl<'a> GrfEntryLoader<'a> {
 
     pub fn load_sdl_surface(&self, path: &str) -> Result<sdl2::surface::Surface, String> {
         let buffer = self.asset_loader.get_content(path)?;
-        return GrfEntryLoader::load_sdl_surface2(buffer, path.ends_with(".tga"));
+        return GrfEntryLoader::load_sdl_surface2(&buffer, path.ends_with(".tga"));
+    }
+
+    /// Corrupt images are replaced by the pink backup surface
+    pub fn load_sdl_surface_or_backup(
+        buffer: &[u8],
+        path: &str,
+    ) -> sdl2::surface::Surface<'static> {
+        return GrfEntryLoader::load_sdl_surface2(buffer, path.ends_with(".tga")).unwrap_or_else(
+            |e| {
+                log::warn!("Could not load '{}': {}", path, e);
+                GrfEntryLoader::missing_texture_surface()
+            },
+        );
     }
 
     pub fn load_sdl_surface2(
-        buffer: Vec<u8>,
+        buffer: &[u8],
         is_tga: bool,
     ) -> Result<sdl2::surface::Surface<'static>, String> {
-        let rwops = sdl2::rwops::RWops::from_bytes(buffer.as_slice())?;
+        let rwops = sdl2::rwops::RWops::from_bytes(buffer)?;
         let mut surface = if is_tga {
             rwops.load_tga()?
         } else {
@@ -486,10 +675,39 @@ impl<'a> GrfEntryLoader<'a> {
 
     pub fn create_texture_from_surface_inner(
         gl: &Gl,
-        mut surface: sdl2::surface::Surface,
+        surface: sdl2::surface::Surface,
+        min_mag: MyGlEnum,
+    ) -> GlTexture {
+        let surface = GrfEntryLoader::to_rgba32_surface(surface);
+        return GrfEntryLoader::create_gl_texture(
+            gl,
+            surface.width() as i32,
+            surface.height() as i32,
+            surface.without_lock().unwrap().as_ptr() as *const c_void,
+            min_mag,
+        );
+    }
+
+    /// `pixels` has to contain width * height RGBA pixels
+    pub fn create_texture_from_rgba(
+        gl: &Gl,
+        width: i32,
+        height: i32,
+        pixels: &[u8],
         min_mag: MyGlEnum,
     ) -> GlTexture {
-        let surface = if surface.pixel_format_enum() != PixelFormatEnum::RGBA32 {
+        return GrfEntryLoader::create_gl_texture(
+            gl,
+            width,
+            height,
+            pixels.as_ptr() as *const c_void,
+            min_mag,
+        );
+    }
+
+    /// The pixel format of the GL textures
+    pub(super) fn to_rgba32_surface(mut surface: sdl2::surface::Surface) -> sdl2::surface::Surface {
+        return if surface.pixel_format_enum() != PixelFormatEnum::RGBA32 {
             let mut optimized_surf = sdl2::surface::Surface::new(
                 surface.width(),
                 surface.height(),
@@ -504,13 +722,6 @@ impl<'a> GrfEntryLoader<'a> {
         } else {
             surface
         };
-        return GrfEntryLoader::create_gl_texture(
-            gl,
-            surface.width() as i32,
-            surface.height() as i32,
-            surface.without_lock().unwrap().as_ptr() as *const c_void,
-            min_mag,
-        );
     }
 
     fn create_gl_texture(
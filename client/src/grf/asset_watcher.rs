@@ -0,0 +1,70 @@
+use notify::Watcher;
+use rustarok_common::grf::asset_loader::CommonAssetLoader;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Watches the directory sources of the asset loader, so the edited sprites, effects, models and
+/// textures can be reloaded while the game is running, see `GrfEntryLoader::reload_changed_files`
+pub struct AssetWatcher {
+    rx: crossbeam_channel::Receiver<Result<notify::Event, notify::Error>>,
+    // the events are sent only while it is alive
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl AssetWatcher {
+    /// None if there is no directory source
+    pub fn new(asset_loader: &CommonAssetLoader) -> Option<AssetWatcher> {
+        let roots = asset_loader.dir_roots();
+        if roots.is_empty() {
+            return None;
+        }
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let mut watcher = match notify::watcher(tx, Duration::from_secs(1)) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                log::warn!("Could not watch the asset directories: {:?}", e);
+                return None;
+            }
+        };
+        for root in roots {
+            // a directory is watched, so unlike the runtime config, it does not have to be
+            // watched again when an editor replaces a file during saving
+            match watcher.watch(&root, notify::RecursiveMode::Recursive) {
+                Ok(()) => log::info!("Watching '{}' for changes", root.display()),
+                Err(e) => log::warn!("Could not watch '{}': {:?}", root.display(), e),
+            }
+        }
+        return Some(AssetWatcher {
+            rx,
+            _watcher: watcher,
+        });
+    }
+
+    /// The files which were created or modified since the last call
+    pub fn changed_files(&self) -> Vec<PathBuf> {
+        let mut changed_files = Vec::new();
+        for event in self.rx.try_iter() {
+            match event {
+                Ok(notify::Event {
+                    kind: notify::EventKind::Create(..),
+                    paths,
+                    ..
+                })
+                | Ok(notify::Event {
+                    kind: notify::EventKind::Modify(..),
+                    paths,
+                    ..
+                }) => {
+                    for path in paths {
+                        if path.is_file() && !changed_files.contains(&path) {
+                            changed_files.push(path);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Asset watcher error: {:?}", e),
+            }
+        }
+        return changed_files;
+    }
+}
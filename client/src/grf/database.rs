@@ -74,6 +74,27 @@ impl AssetDatabase {
             .insert(AssetDatabase::replace_non_ascii_chars(&name), model_index);
     }
 
+    /// The name of the changed file and the name of the model can differ in case
+    pub fn find_model_index_ignore_case(&self, name: &str) -> Option<(String, usize)> {
+        let key = AssetDatabase::replace_non_ascii_chars(&name);
+        return self
+            .model_name_to_index
+            .iter()
+            .find(|(model_name, _index)| model_name.eq_ignore_ascii_case(&key))
+            .map(|(model_name, index)| (model_name.clone(), *index));
+    }
+
+    pub fn find_texture_ids_ignore_case(&self, path: &str) -> Vec<TextureId> {
+        let key = AssetDatabase::replace_non_ascii_chars(&path);
+        return self
+            .texture_db
+            .entries
+            .iter()
+            .filter(|(texture_name, _texture_id)| texture_name.eq_ignore_ascii_case(&key))
+            .map(|(_texture_name, texture_id)| *texture_id)
+            .collect();
+    }
+
     pub fn get_texture_id(&self, path: &str) -> Option<TextureId> {
         let key = AssetDatabase::replace_non_ascii_chars(&path);
         return self.texture_db.entries.get(&key).map(|it| it.clone());
@@ -143,6 +164,20 @@ impl AssetDatabase {
         self.texture_db.entries.insert(key, texture_id);
     }
 
+    /// Used by the hot reload, the old texture in the slot is deleted and the name is bound
+    /// to the new slot even if it belonged to another one
+    pub(super) fn replace_texture(
+        &mut self,
+        texture_id: TextureId,
+        gl_texture: GlTexture,
+        name: String,
+    ) {
+        self.textures[texture_id.0] = gl_texture;
+        self.texture_db
+            .entries
+            .insert(AssetDatabase::replace_non_ascii_chars(&name), texture_id);
+    }
+
     pub fn replace_non_ascii_chars(name: &str) -> String {
         let mut ret = String::with_capacity(name.len() * 2);
         name.chars().for_each(|it| {
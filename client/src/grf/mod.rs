@@ -1,18 +1,19 @@
 use crate::grf::act::{Action, ActionFile, ActionFrame};
-use crate::grf::texture::{TextureId, DUMMY_TEXTURE_ID_FOR_TEST};
+use crate::grf::texture::{TextureId, DUMMY_TEXTURE_ID, DUMMY_TEXTURE_ID_FOR_TEST};
+use serde::{Deserialize, Serialize};
 
-pub mod act;
 pub mod asset_async_loader;
+pub mod asset_bundle;
 pub mod asset_loader;
+pub mod asset_watcher;
 pub mod database;
-pub mod gnd;
-pub mod rsm;
-pub mod rsw;
-pub mod spr;
 pub mod str;
 pub mod texture;
 
-#[derive(Clone)]
+// the GL independent parsers live in the common crate, so the tools can use them too
+pub use rustarok_common::grf::{act, gnd, rsm, rsw, spr};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SpriteResource {
     pub action: ActionFile,
     pub textures: Vec<TextureId>,
@@ -20,6 +21,15 @@ pub struct SpriteResource {
 
 impl SpriteResource {
     pub fn new_for_test() -> SpriteResource {
+        SpriteResource {
+            textures: vec![DUMMY_TEXTURE_ID_FOR_TEST],
+            ..SpriteResource::placeholder()
+        }
+    }
+
+    /// Used instead of sprites which could not be parsed, it has enough empty actions for
+    /// every character state and direction
+    pub fn placeholder() -> SpriteResource {
         SpriteResource {
             action: ActionFile {
                 actions: (1..80)
@@ -35,7 +45,7 @@ impl SpriteResource {
                     .collect(),
                 sounds: vec![],
             },
-            textures: vec![DUMMY_TEXTURE_ID_FOR_TEST],
+            textures: vec![DUMMY_TEXTURE_ID],
         }
     }
 }
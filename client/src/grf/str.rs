@@ -1,9 +1,9 @@
 use crate::grf::asset_loader::GrfEntryLoader;
 use crate::grf::database::AssetDatabase;
-use crate::grf::texture::TextureId;
+use crate::grf::texture::{TextureId, DUMMY_TEXTURE_ID};
 use crate::my_gl::{Gl, MyGlBlendEnum, MyGlEnum};
 use rustarok_common::grf::binary_reader::BinaryReader;
-use std::collections::HashMap;
+pub use rustarok_common::grf::str::KeyFrameType;
 use std::path::Path;
 
 pub struct StrFile {
@@ -17,26 +17,17 @@ pub struct StrLayer {
     pub key_frames: Vec<StrKeyFrame>,
 }
 
-#[derive(PartialEq, Eq, Debug, Clone, Copy)]
-pub enum KeyFrameType {
-    Start,
-    End,
-}
-
+/// The key frames of `rustarok_common::grf::str::StrFile`, with OpenGL blend modes
 pub struct StrKeyFrame {
     pub frame: i32,
     pub typ: KeyFrameType,
     pub pos: [f32; 2],
-    //    pub uv: [f32; 8], it is not used, don't store it
     pub xy: [f32; 8],
     pub color: [u8; 4],
     pub angle: f32,
     pub src_alpha: MyGlBlendEnum,
     pub dst_alpha: MyGlBlendEnum,
     pub texture_index: usize,
-    //    pub anitype: u32, not used
-    //    pub delay: f32, not used O-O
-    //    pub mtpreset: u32, not used
 }
 
 impl StrFile {
@@ -44,21 +35,10 @@ impl StrFile {
         gl: &Gl,
         asset_loader: &GrfEntryLoader,
         asset_db: &mut AssetDatabase,
-        mut buf: BinaryReader,
+        buf: BinaryReader,
         str_name: &str,
-    ) -> Self {
-        let header = buf.string(4);
-        if header != "STRM" {
-            panic!("Invalig STR header: {}", header);
-        }
-        if buf.next_u32() != 0x94 {
-            panic!("invalid version!");
-        }
-
-        let fps = buf.next_u32();
-        let max_key = buf.next_u32();
-        let layer_num = buf.next_u32();
-        buf.skip(16);
+    ) -> Result<Self, String> {
+        let str_file = rustarok_common::grf::str::StrFile::load(buf).map_err(|e| e.to_string())?;
 
         let d3d_to_gl_blend = [
             MyGlBlendEnum::ZERO, // 0
@@ -77,110 +57,70 @@ impl StrFile {
             MyGlBlendEnum::ONE_MINUS_CONSTANT_ALPHA, // 13
         ];
 
-        let mut texture_names_to_index: HashMap<String, usize> = HashMap::new();
-        let mut textures: Vec<TextureId> = Vec::new();
-
-        let layers = (0..layer_num)
-            .map(|_i| {
-                let texture_names: Vec<String> = (0..buf.next_u32())
-                    .map(|_i| {
-                        let texture_name = buf.string(128);
-                        if !texture_names_to_index.contains_key(&texture_name) {
-                            let base = Path::new("data")
-                                .join("texture")
-                                .join("effect")
-                                .join(str_name);
-                            let root = base.parent().unwrap();
-                            let path = format!(
-                                "{}\\{}",
-                                root.to_str().unwrap().replace("/", "\\"),
-                                texture_name
-                            );
-                            let texture = asset_db.get_texture_id(&path).unwrap_or_else(|| {
-                                asset_loader
-                                    .start_loading_texture(gl, &path, MyGlEnum::NEAREST, asset_db)
-                                    .unwrap()
-                            });
-                            textures.push(texture);
-                            let size = texture_names_to_index.len();
-                            texture_names_to_index.insert(texture_name.clone(), size);
-                        }
-                        texture_name
-                    })
-                    .collect();
-                // TODO: skip layers where key_frames.is_empty()
-                let key_frames: Vec<StrKeyFrame> = (0..buf.next_u32())
-                    .map(|_i| {
-                        let frame = buf.next_i32();
-                        let typ = if buf.next_u32() == 0 {
-                            KeyFrameType::Start
-                        } else {
-                            KeyFrameType::End
-                        };
-                        let pos = [buf.next_f32(), buf.next_f32()];
-                        //                        let uv = [
-                        buf.next_f32();
-                        buf.next_f32();
-                        buf.next_f32();
-                        buf.next_f32();
-                        buf.next_f32();
-                        buf.next_f32();
-                        buf.next_f32();
-                        buf.next_f32();
-                        //                        ];
-                        let xy = [
-                            buf.next_f32(),
-                            buf.next_f32(),
-                            buf.next_f32(),
-                            buf.next_f32(),
-                            buf.next_f32(),
-                            buf.next_f32(),
-                            buf.next_f32(),
-                            buf.next_f32(),
-                        ];
-                        StrKeyFrame {
-                            frame,
-                            typ,
-                            pos,
-                            //                            uv,
-                            xy,
-                            texture_index: texture_names_to_index
-                                [&texture_names[buf.next_f32() as usize]],
-                            //                            anitype: buf.next_u32(),
-                            //                            delay: buf.next_f32(),
-                            angle: {
-                                {
-                                    buf.next_u32(); // anitype
-                                    buf.next_f32(); // delay
-                                }
-                                buf.next_f32() / (1024.0 / 360.0)
-                            },
-                            color: [
-                                buf.next_f32() as u8,
-                                buf.next_f32() as u8,
-                                buf.next_f32() as u8,
-                                buf.next_f32() as u8,
-                            ],
-                            src_alpha: d3d_to_gl_blend[buf.next_u32() as usize],
-                            dst_alpha: {
-                                let ret = d3d_to_gl_blend[buf.next_u32() as usize];
-                                buf.next_u32(); // mtpreset
-                                ret
-                            },
-                            //                            mtpreset: buf.next_u32(),
-                        }
-                    })
-                    .collect();
-
-                StrLayer { key_frames }
+        let base = Path::new("data")
+            .join("texture")
+            .join("effect")
+            .join(str_name);
+        let root = base.parent().unwrap();
+        let textures: Vec<TextureId> = str_file
+            .texture_names
+            .iter()
+            .map(|texture_name| {
+                let path = format!(
+                    "{}\\{}",
+                    root.to_str().unwrap().replace("/", "\\"),
+                    texture_name
+                );
+                asset_db.get_texture_id(&path).unwrap_or_else(|| {
+                    asset_loader
+                        .start_loading_texture(gl, &path, MyGlEnum::NEAREST, asset_db)
+                        .unwrap_or_else(|e| {
+                            log::warn!("{}", e);
+                            DUMMY_TEXTURE_ID
+                        })
+                })
             })
-            .filter(|layer| !layer.key_frames.is_empty())
             .collect();
-        StrFile {
-            max_key,
-            fps,
+
+        let to_gl_blend = |d3d_blend: u32| {
+            d3d_to_gl_blend
+                .get(d3d_blend as usize)
+                .map(|it| *it)
+                .ok_or_else(|| format!("'{}': invalid blend mode {}", str_name, d3d_blend))
+        };
+        let mut layers = Vec::with_capacity(str_file.layers.len());
+        for layer in str_file.layers {
+            let mut key_frames = Vec::with_capacity(layer.key_frames.len());
+            for key_frame in layer.key_frames {
+                key_frames.push(StrKeyFrame {
+                    frame: key_frame.frame,
+                    typ: key_frame.typ,
+                    pos: key_frame.pos,
+                    xy: key_frame.xy,
+                    color: key_frame.color,
+                    angle: key_frame.angle,
+                    src_alpha: to_gl_blend(key_frame.src_alpha)?,
+                    dst_alpha: to_gl_blend(key_frame.dst_alpha)?,
+                    texture_index: key_frame.texture_index,
+                });
+            }
+            layers.push(StrLayer { key_frames });
+        }
+        return Ok(StrFile {
+            max_key: str_file.max_key,
+            fps: str_file.fps,
             layers,
             textures,
+        });
+    }
+
+    /// Used instead of effects which could not be loaded, it renders nothing
+    pub fn placeholder() -> StrFile {
+        StrFile {
+            max_key: 1,
+            fps: 1,
+            layers: vec![],
+            textures: vec![],
         }
     }
 }
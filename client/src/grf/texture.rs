@@ -1,12 +1,14 @@
-use std::os::raw::c_uint;
+use std::os::raw::{c_uint, c_void};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::my_gl::{Gl, MyGlEnum};
 
 pub const DUMMY_TEXTURE_ID_FOR_TEST: TextureId = TextureId(0);
+/// The pink "missing texture" is the first registered texture, see `main`
+pub const DUMMY_TEXTURE_ID: TextureId = TextureId(0);
 
-#[derive(Clone, Copy, Debug, Serialize)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct TextureId(pub(super) usize);
 
 struct GlTextureContext {
@@ -50,6 +52,25 @@ impl GlTexture {
         (self.context.native_id).clone()
     }
 
+    /// Overwrites the whole texture, `pixels` has to contain width * height RGBA pixels
+    pub fn update_rgba(&self, gl: &Gl, pixels: &[u8]) {
+        debug_assert_eq!(pixels.len(), (self.width * self.height * 4) as usize);
+        unsafe {
+            gl.bind_texture(MyGlEnum::TEXTURE_2D, self.context.native_id);
+            gl.tex_sub_image2d(
+                MyGlEnum::TEXTURE_2D,
+                0,
+                0,
+                0,
+                self.width,
+                self.height,
+                MyGlEnum::RGBA,
+                MyGlEnum::UNSIGNED_BYTE,
+                pixels.as_ptr() as *const c_void,
+            );
+        }
+    }
+
     pub fn bind(&self, gl: &Gl, texture_index: MyGlEnum) {
         unsafe {
             gl.active_texture(texture_index);
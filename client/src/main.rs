@@ -25,6 +25,7 @@ use std::time::{Duration, Instant, SystemTime};
 
 //use imgui::ImVec2;
 use log::LevelFilter;
+use nphysics2d::object::BodyStatus;
 use rand::Rng;
 use specs::prelude::*;
 use specs::Builder;
@@ -36,10 +37,12 @@ use crate::components::char::{
     SpriteRenderDescriptorComponent,
 };
 use crate::components::controller::HumanInputComponent;
-use crate::components::skills::skills::SkillManifestationComponent;
-use crate::components::MinionComponent;
+use crate::components::skills::skills::{FinishCast, SkillDefinitionExt};
+use crate::components::{HpModificationResult, MinionComponent};
 use crate::configs::{AppConfig, DevConfig};
+use crate::grf::asset_bundle;
 use crate::grf::asset_loader::GrfEntryLoader;
+use crate::grf::asset_watcher::AssetWatcher;
 use crate::grf::database::AssetDatabase;
 use crate::grf::SpriteResource;
 use crate::my_gl::MyGlEnum;
@@ -53,23 +56,25 @@ use crate::runtime_assets::ecs::create_ecs_world;
 use crate::runtime_assets::effect::load_str_effects;
 use crate::runtime_assets::graphic::{load_skill_icons, load_status_icons, load_texts};
 use crate::runtime_assets::map::{load_map, MapRenderData, PhysicEngine};
-use crate::systems::atk_calc::AttackSystem;
+use crate::systems::atk_calc::AttackCalculation;
 use crate::systems::camera_system::CameraSystem;
+use crate::systems::console_commands::create_guard;
 use crate::systems::console_system::{
     CommandArguments, CommandDefinition, ConsoleComponent, ConsoleSystem,
 };
 use crate::systems::falcon_ai_sys::{FalconAiSystem, FalconComponent};
+use crate::systems::fog_of_war_sys::FogOfWarSystem;
 use crate::systems::frame_cleanup_system::FrameCleanupSystem;
 use crate::systems::frame_client_end_system::FrameClientEndSystem;
 use crate::systems::input_sys::InputConsumerSystem;
 use crate::systems::input_to_next_action::InputToNextActionSystem;
 use crate::systems::intention_sender_sys::IntentionSenderSystem;
+use crate::systems::interpolation_sys::{RemoteCharInterpolation, RemoteCharInterpolationSystem};
 use crate::systems::minion_ai_sys::MinionAiSystem;
 use crate::systems::next_action_applier_sys::{
     SavePreviousCharStateSystem, UpdateCharSpriteBasedOnStateSystem,
 };
 use crate::systems::phys::{FrictionSystem, PhysCollisionCollectorSystem};
-use crate::systems::skill_sys::SkillSystem;
 use crate::systems::snapshot_sys::{GameSnapshots, ServerAckResult, SnapshotSystem};
 use crate::systems::turret_ai_sys::TurretAiSystem;
 use crate::systems::{
@@ -80,16 +85,25 @@ use rustarok_common::common::{
     measure_time, v2, ElapsedTime, EngineTime, Vec2, MAX_DURATION_ALLOWED_FOR_SINGLE_FRAME,
 };
 use rustarok_common::components::char::{
-    AuthorizedCharStateComponent, CharDir, CharEntityId, CharOutlook, CollisionGroup,
+    AuthorizedCharStateComponent, CharDir, CharEntityId, CharOutlook, CharType, CollisionGroup,
     ControllerEntityId, JobId, ServerEntityId, Sex, Team,
 };
 use rustarok_common::components::controller::ControllerComponent;
 use rustarok_common::components::job_ids::JobSpriteId;
-use rustarok_common::packets::from_server::{AckEntry, FromServerPacket};
+use rustarok_common::components::manifestation::SkillManifestationComponent;
+use rustarok_common::components::status::StatusesComponent;
+use rustarok_common::grf::grf_cache;
+use rustarok_common::packets::from_server::{AckEntry, FromServerPacket, MatchPhase};
+use rustarok_common::packets::handshake::{
+    map_hash, skills_config_hash, HandshakeRejectReason, PROTOCOL_VERSION, SUPPORTED_CAPABILITIES,
+};
+use rustarok_common::packets::link_conditioner::NetworkSimulationConfig;
 use rustarok_common::packets::to_server::ToServerPacket;
-use rustarok_common::packets::{PacketHandlerThread, SocketBuffer};
+use rustarok_common::packets::{PacketHandlerThread, SocketBuffer, Transport};
+use rustarok_common::scenario::Scenario;
 use rustarok_common::systems::char_state_sys::CharacterStateUpdateSystem;
 use rustarok_common::systems::intention_applier::NextActionApplierSystem;
+use rustarok_common::vision::TeamVision;
 use std::fs::File;
 use std::io::BufReader;
 use std::io::{BufRead, Read};
@@ -165,24 +179,57 @@ fn main() {
             .expect("Unknown log level. Please set one of the following values for 'log_level' in 'config.toml': \"OFF\", \"ERROR\", \"WARN\", \"INFO\", \"DEBUG\", \"TRACE\"")
     );
     log::info!(">>> Loading GRF files");
-    let (elapsed, asset_loader) = measure_time(|| {
-        GrfEntryLoader::new(config.grf_paths.as_slice())
-            .expect("Could not open grf files. Please configure them in 'config.toml'")
+    let (elapsed, mut asset_loader) = measure_time(|| {
+        GrfEntryLoader::new(
+            config.grf_paths.as_slice(),
+            &grf_cache::cache_path(&config.grf_cache_path),
+            config.asset_cache_size_mb * 1024 * 1024,
+        )
+        .expect("Could not open grf files. Please configure them in 'config.toml'")
     });
     log::info!("<<< GRF loading: {}ms", elapsed.as_millis());
+    // `rustarok-client bake <map_name>` decodes the assets of the map into a bundle and exits
+    if std::env::args().nth(1).as_ref().map(String::as_str) == Some("bake") {
+        let map_name = std::env::args()
+            .nth(2)
+            .expect("Usage: rustarok-client bake <map_name>");
+        let bundle_path = asset_bundle::bundle_path(&config.bundle_dir, &map_name);
+        log::info!(">>> Baking '{}'", bundle_path.display());
+        let (elapsed, result) =
+            measure_time(|| asset_bundle::bake(&asset_loader, &map_name, &bundle_path));
+        match result {
+            Ok(()) => log::info!("<<< Baking: {}ms", elapsed.as_millis()),
+            Err(e) => {
+                log::error!("Could not bake '{}': {}", map_name, e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    let asset_watcher = AssetWatcher::new(&asset_loader.asset_loader);
 
     log::info!("starting packet handler thread");
     let mut packet_handler_thread =
         PacketHandlerThread::<FromServerPacket, ToServerPacket>::start_thread(1);
+    packet_handler_thread.set_network_simulation(config.network_simulation.clone());
 
     log::info!("Connecting to server");
-    let server_socket =
-        packet_handler_thread.handle_socket(TcpStream::connect(config.server_addr).unwrap());
+    let server_socket = match config.transport {
+        Transport::Tcp => packet_handler_thread
+            .handle_socket(TcpStream::connect(config.server_addr.as_str()).unwrap()),
+        Transport::Udp => packet_handler_thread
+            .connect_udp(config.server_addr.parse().unwrap())
+            .unwrap(),
+    };
 
+    let dev_config = DevConfig::new().unwrap();
     log::info!("sending welcome msg");
     packet_handler_thread.send(
         server_socket,
         ToServerPacket::Welcome {
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: SUPPORTED_CAPABILITIES,
+            config_hash: skills_config_hash(&dev_config.skills),
             name: "sharp".to_owned(),
         },
     );
@@ -202,6 +249,9 @@ fn main() {
                         log::info!("answer received!!!");
                         break 'outer1 (map_name, start_x, start_y);
                     }
+                    FromServerPacket::HandshakeRejected { reason } => {
+                        show_handshake_rejection(&reason);
+                    }
                     _ => {}
                 }
             }
@@ -209,6 +259,9 @@ fn main() {
         }
     };
 
+    let scenario = Scenario::load(&map_name)
+        .unwrap_or_else(|e| panic!("Could not load the scenario of the map: {}", e));
+    asset_loader.load_bundle(asset_bundle::bundle_path(&config.bundle_dir, &map_name));
     let mut asset_db = AssetDatabase::new();
 
     let fov = 0.638;
@@ -241,6 +294,9 @@ fn main() {
         config.load_models,
     );
     log::info!("<<< Loading map");
+    for problem in scenario.unwalkable_positions(&map_render_data.gat) {
+        log::warn!("Not walkable: {}", problem);
+    }
 
     let command_defs: HashMap<String, CommandDefinition> = ConsoleSystem::init_commands(
         get_all_effect_names(&asset_loader),
@@ -309,13 +365,19 @@ fn main() {
         asset_loader.load_sprites(&gl, &mut asset_db);
     }
     ecs_world.add_resource(gl.clone());
+    // the characters plan their paths on it, see CharacterStateUpdateSystem
+    ecs_world.add_resource(map_render_data.gat.clone());
+    ecs_world.add_resource(TeamVision::new(&map_render_data.gat));
     ecs_world.add_resource(map_render_data);
-    ecs_world.add_resource(DevConfig::new().unwrap());
+    ecs_world.add_resource(scenario);
+    ecs_world.add_resource(dev_config);
     ecs_world.add_resource(RenderCommandCollector::new());
     ecs_world.add_resource(command_buffer);
     ecs_world.add_resource(EngineTime::new());
     ecs_world.add_resource(GameSnapshots::new());
+    ecs_world.add_resource(RemoteCharInterpolation::new());
     ecs_world.add_resource(Vec::<ToServerPacket>::new());
+    ecs_world.add_resource(config.network_simulation.clone());
 
     ecs_world.add_resource(asset_db);
     ecs_world.add_resource(server_socket);
@@ -373,10 +435,20 @@ fn main() {
     ecs_world.maintain();
     log::info!("<<< create player");
 
+    let guards = ecs_world.read_resource::<Scenario>().guards.clone();
+    for guard in guards {
+        create_guard(&mut ecs_world, guard.pos, guard.team, None, guard.y);
+    }
+
     let mut next_second: SystemTime = std::time::SystemTime::now()
         .checked_add(Duration::from_secs(1))
         .unwrap();
-    let mut next_minion_spawn = ElapsedTime(2.0);
+    let mut next_minion_spawn = ElapsedTime(
+        ecs_world
+            .read_resource::<Scenario>()
+            .minion_waves
+            .first_wave_seconds,
+    );
     let mut fps_counter: u64 = 0;
     let mut fps: u64;
     let mut fps_history: Vec<f32> = Vec::with_capacity(30);
@@ -416,7 +488,14 @@ fn main() {
             desktop_client_controller,
         );
 
-        packet_handler_thread.send(server_socket, ToServerPacket::ReadyForGame);
+        let map_hash = {
+            let gat_content = asset_loader
+                .asset_loader
+                .get_content(&format!("data\\{}.gat", map_name))
+                .unwrap();
+            map_hash(&map_name, &gat_content)
+        };
+        packet_handler_thread.send(server_socket, ToServerPacket::ReadyForGame { map_hash });
         // first ACK packet is for initializing out world state
         let mut snapshots = &mut ecs_world.write_resource::<GameSnapshots>();
         'outer3: loop {
@@ -426,12 +505,16 @@ fn main() {
                     FromServerPacket::Ack {
                         cid: _cid,
                         ack_tick,
+                        baseline_tick: _baseline_tick,
                         entries,
                     } => {
-                        snapshots.init(&entries);
+                        snapshots.init(&entries, desktop_client_char);
                         ecs_world.write_resource::<EngineTime>().tick = ack_tick + 1;
                         break 'outer3;
                     }
+                    FromServerPacket::HandshakeRejected { reason } => {
+                        show_handshake_rejection(&reason);
+                    }
                     _ => {}
                 }
             }
@@ -442,9 +525,9 @@ fn main() {
     console_print(&mut ecs_world, "Start", desktop_client_controller);
     let mut tmp_vec = Vec::with_capacity(64);
 
+    let mut applied_network_simulation = config.network_simulation.clone();
     let mut packet_receiver = DelayedPacketReceiver::new(Duration::from_millis(0));
     let mut client_speed_increaser = Duration::from_millis(0);
-    let mut entities: HashMap<ServerEntityId, CharEntityId> = HashMap::with_capacity(1024);
     'running: loop {
         let start = Instant::now();
         let tick = ecs_world.read_resource::<EngineTime>().tick;
@@ -463,27 +546,157 @@ fn main() {
                 for packet in packet_receiver.get_packets().drain(..) {
                     match packet {
                         FromServerPacket::LocalError(_) => {}
+                        FromServerPacket::HandshakeRejected { reason } => {
+                            show_handshake_rejection(&reason);
+                        }
                         FromServerPacket::Init { .. } => panic!(),
                         FromServerPacket::Pong { .. } => panic!(),
                         FromServerPacket::Ack {
                             cid,
                             ack_tick,
+                            baseline_tick,
                             mut entries,
                         } => {
                             let mut snapshots = &mut ecs_world.write_resource::<GameSnapshots>();
+                            let state_from_server =
+                                match snapshots.decode_ack(ack_tick, baseline_tick, &entries) {
+                                    Some(state_from_server) => state_from_server,
+                                    None => {
+                                        log::error!(
+                                            "Missing baseline ({:?}) for ack_tick {}",
+                                            baseline_tick,
+                                            ack_tick
+                                        );
+                                        continue;
+                                    }
+                                };
+                            ecs_world
+                                .write_resource::<Vec<ToServerPacket>>()
+                                .push(ToServerPacket::SnapshotAck { ack_tick });
+                            {
+                                let mut interpolation =
+                                    ecs_world.write_resource::<RemoteCharInterpolation>();
+                                for (server_id, char_snapshot) in &state_from_server.chars {
+                                    match snapshots.get_local_id(server_id) {
+                                        Some(local_id) if local_id != desktop_client_char => {
+                                            snapshots.mark_interpolated(*server_id);
+                                            interpolation.push(
+                                                local_id,
+                                                ack_tick,
+                                                char_snapshot.state.pos(),
+                                                char_snapshot.state.dir(),
+                                            );
+                                        }
+                                        _ => {}
+                                    }
+                                }
+                            }
                             tmp_ack_tick = ack_tick;
-                            ack_result = snapshots.ack_arrived(tick, cid, ack_tick, &entries);
+                            ack_result =
+                                snapshots.ack_arrived(tick, cid, ack_tick, &state_from_server);
 
                             let auth_state_storage =
                                 &mut ecs_world.write_storage::<AuthorizedCharStateComponent>();
                             for entry in entries.drain(1..) {
                                 match entry {
-                                    AckEntry::EntityState { id, char_snapshot } => {
-                                        let local_id = entities.get(&id).unwrap();
+                                    AckEntry::EntityState { id, .. }
+                                    | AckEntry::EntityDelta { id, .. } => {
+                                        // its NewEntity has not arrived yet
+                                        let local_id = match snapshots.get_local_id(&id) {
+                                            Some(local_id) => local_id,
+                                            None => continue,
+                                        };
                                         let char_state =
-                                            auth_state_storage.get_mut((*local_id).into()).unwrap();
-                                        char_state.target = char_snapshot.state.target;
+                                            auth_state_storage.get_mut(local_id.into()).unwrap();
+                                        char_state.target = state_from_server
+                                            .get_char(id)
+                                            .unwrap()
+                                            .state
+                                            .target
+                                            .clone();
+                                    }
+                                    AckEntry::EntityRemoved { id }
+                                    | AckEntry::EntityOutOfVision { id } => {
+                                        if let Some(local_id) = snapshots.unregister_entity(&id) {
+                                            remove_replicated_entity(&ecs_world, local_id);
+                                        }
+                                    }
+                                    AckEntry::ManifestationState { id, manifestation } => {
+                                        apply_manifestation_state(
+                                            &ecs_world,
+                                            snapshots,
+                                            id,
+                                            manifestation,
+                                        );
+                                    }
+                                    AckEntry::StatusesState { id, mut statuses } => {
+                                        let char_id = match snapshots.get_local_id(&id) {
+                                            Some(char_id) => char_id,
+                                            None => continue,
+                                        };
+                                        if let Some(char_state) = ecs_world
+                                            .write_storage::<CharacterStateComponent>()
+                                            .get_mut(char_id.into())
+                                        {
+                                            char_state.recalc_attribs_based_on_statuses(Some(
+                                                &mut statuses,
+                                            ));
+                                        }
+                                        if let Err(e) = ecs_world
+                                            .write_storage::<StatusesComponent>()
+                                            .insert(char_id.into(), statuses)
+                                        {
+                                            log::warn!("Statuses could not be updated: {:?}", e);
+                                        }
+                                    }
+                                    AckEntry::SkillCast {
+                                        caster,
+                                        skill,
+                                        caster_pos,
+                                        skill_pos,
+                                        char_to_skill_dir,
+                                        target_entity,
+                                    } => {
+                                        let caster_entity_id = match snapshots.get_local_id(&caster)
+                                        {
+                                            Some(id) => id,
+                                            None => continue,
+                                        };
+                                        let caster_team = ecs_world
+                                            .read_storage::<CharacterStateComponent>()
+                                            .get(caster_entity_id.into())
+                                            .map(|it| it.team);
+                                        if let Some(caster_team) = caster_team {
+                                            ecs_world
+                                                .write_resource::<SystemVariables>()
+                                                .just_finished_skill_casts
+                                                .push(FinishCast {
+                                                    skill,
+                                                    caster_entity_id,
+                                                    caster_pos,
+                                                    caster_team,
+                                                    skill_pos,
+                                                    char_to_skill_dir,
+                                                    target_entity: target_entity
+                                                        .and_then(|it| snapshots.get_local_id(&it)),
+                                                });
+                                        }
                                     }
+                                    AckEntry::HpModification { .. } | AckEntry::Respawn { .. } => {
+                                        log::warn!("Gameplay event in an Ack: {:?}", entry);
+                                    }
+                                }
+                            }
+                            // they are sent only when they change, the ones whose caster or
+                            // target was unknown on arrival are created from the snapshot
+                            for (id, manifestation) in &state_from_server.manifestations {
+                                if snapshots.get_local_id(id).is_none() {
+                                    apply_manifestation_state(
+                                        &ecs_world,
+                                        snapshots,
+                                        *id,
+                                        manifestation.clone(),
+                                    );
                                 }
                             }
                         }
@@ -498,6 +711,14 @@ fn main() {
                             state,
                         } => {
                             log::info!(">>> create player");
+                            if ecs_world
+                                .read_resource::<GameSnapshots>()
+                                .get_local_id(&id)
+                                .is_some()
+                            {
+                                log::warn!("Entity {:?} already exists", id);
+                                continue;
+                            }
                             {
                                 let char_entity_id =
                                     CharEntityId::from(ecs_world.create_entity().build());
@@ -509,18 +730,43 @@ fn main() {
                                         state.state.pos(),
                                         &mut ecs_world.write_resource::<PhysicEngine>(),
                                         |builder| {
-                                            builder
-                                                .collision_group(team.get_collision_group())
-                                                .circle(1.0)
+                                            if typ == CharType::Structure {
+                                                // only the enemies are blocked by them
+                                                builder
+                                                    .collision_group(
+                                                        team.get_barricade_collision_group(),
+                                                    )
+                                                    .circle(1.5)
+                                                    .body_status(BodyStatus::Static)
+                                            } else {
+                                                builder
+                                                    .collision_group(team.get_collision_group())
+                                                    .circle(1.0)
+                                            }
                                         },
                                     )
                                     .char_state(updater, dev_configs, state.state.pos(), |ch| {
                                         ch.outlook(outlook).job_id(job_id).team(team)
                                     });
-                                entities.insert(id, char_entity_id);
+                                ecs_world
+                                    .write_resource::<GameSnapshots>()
+                                    .register_entity(id, char_entity_id);
                             }
                             ecs_world.maintain();
                         }
+                        FromServerPacket::GameplayEvents { entries } => {
+                            let snapshots = &ecs_world.read_resource::<GameSnapshots>();
+                            for entry in entries {
+                                apply_gameplay_event(&ecs_world, snapshots, entry);
+                            }
+                        }
+                        FromServerPacket::MatchPhaseChanged(phase) => {
+                            console_print(
+                                &mut ecs_world,
+                                &describe_match_phase(&phase),
+                                desktop_client_controller,
+                            );
+                        }
                     }
                 }
                 (ack_result, tmp_ack_tick)
@@ -535,7 +781,7 @@ fn main() {
                     repredict_this_many_frames,
                 } => {
                     log::debug!("Rollback: {} <-- {}", acked_tick, tick);
-                    load_last_acked_state_into_world(&mut ecs_world, desktop_client_controller);
+                    load_last_acked_state_into_world(&mut ecs_world);
                     let timer: EngineTime =
                         (*ecs_world.read_resource::<EngineTime>().deref()).clone();
                     let reverted_timer = timer.reverted_to(acked_tick + 1);
@@ -598,6 +844,13 @@ fn main() {
             desktop_client_controller,
             &mut video,
         );
+        {
+            let network_simulation = ecs_world.read_resource::<NetworkSimulationConfig>();
+            if *network_simulation != applied_network_simulation {
+                applied_network_simulation = network_simulation.clone();
+                packet_handler_thread.set_network_simulation(applied_network_simulation.clone());
+            }
+        }
         //        ecs_server_dispatcher.dispatch(&mut ecs_world.res);
         run_main_frame(&mut ecs_world, &mut ecs_client_dispatcher);
 
@@ -641,10 +894,27 @@ fn main() {
         if next_minion_spawn.has_already_passed(now)
             && ecs_world.read_resource::<DevConfig>().minions_enabled
         {
-            next_minion_spawn = now.add_seconds(2.0);
+            next_minion_spawn = now.add_seconds(
+                ecs_world
+                    .read_resource::<Scenario>()
+                    .minion_waves
+                    .interval_seconds,
+            );
             spawn_minions(&mut ecs_world)
         }
 
+        if let Some(asset_watcher) = &asset_watcher {
+            let changed_files = asset_watcher.changed_files();
+            if !changed_files.is_empty() {
+                asset_loader.reload_changed_files(
+                    &gl,
+                    changed_files,
+                    &mut ecs_world.write_resource::<SystemVariables>(),
+                    &mut ecs_world.write_resource::<AssetDatabase>(),
+                );
+            }
+        }
+
         // runtime configs
         let ret = reload_configs_if_changed(runtime_conf_watcher_rx, watcher, &mut ecs_world);
         runtime_conf_watcher_rx = ret.0;
@@ -662,18 +932,10 @@ fn main() {
     }
 }
 
-fn load_last_acked_state_into_world(
-    ecs_world: &mut World,
-    desktop_client_controller: ControllerEntityId,
-) {
+fn load_last_acked_state_into_world(ecs_world: &mut World) {
     let snapshots = &ecs_world.read_resource::<GameSnapshots>();
     let auth_state_storage = &mut ecs_world.write_storage::<AuthorizedCharStateComponent>();
-    let controller_storage = &ecs_world.read_storage::<ControllerComponent>();
-    snapshots.load_last_acked_state_into_world(
-        auth_state_storage,
-        controller_storage,
-        desktop_client_controller,
-    );
+    snapshots.load_last_acked_state_into_world(auth_state_storage);
 }
 
 pub fn run_main_frame(mut ecs_world: &mut World, ecs_dispatcher: &mut Dispatcher) {
@@ -687,7 +949,7 @@ pub fn run_main_frame(mut ecs_world: &mut World, ecs_dispatcher: &mut Dispatcher
 //        let mut ecs_dispatcher_builder = specs::DispatcherBuilder::new();
 //        ecs_dispatcher_builder = ecs_dispatcher_builder.with(FrictionSystem, "friction_sys", &[]);
 //        ecs_dispatcher_builder = ecs_dispatcher_builder
-//            .with(MinionAiSystem, "minion_ai_sys", &[])
+//            .with(MinionAiSystem::new(), "minion_ai_sys", &[])
 //            .with(TurretAiSystem, "turret_ai_sys", &[])
 //            .with(FalconAiSystem, "falcon_ai_sys", &[])
 //            .with(NextActionApplierSystem, "char_control", &["friction_sys"]);
@@ -756,7 +1018,7 @@ fn register_systems<'a, 'b>(
         }
         ecs_dispatcher_builder = ecs_dispatcher_builder.with(FrictionSystem, "friction_sys", &[]);
         ecs_dispatcher_builder = ecs_dispatcher_builder
-            .with(MinionAiSystem, "minion_ai_sys", &[])
+            .with(MinionAiSystem::new(), "minion_ai_sys", &[])
             .with(TurretAiSystem, "turret_ai_sys", &[])
             .with(FalconAiSystem, "falcon_ai_sys", &[])
             .with(
@@ -793,9 +1055,24 @@ fn register_systems<'a, 'b>(
                 "collision_collector",
                 &["char_state_update"],
             )
-            .with(SkillSystem, "skill_sys", &["collision_collector"])
-            .with(AttackSystem::new(), "attack_sys", &["collision_collector"])
-            .with(SnapshotSystem::new(), "snapshot_sys", &["attack_sys"]);
+            .with(
+                SnapshotSystem::new(),
+                "snapshot_sys",
+                &["collision_collector"],
+            );
+        if !for_test {
+            // it overwrites the simulated remote chars, so it must run after the snapshot
+            ecs_dispatcher_builder = ecs_dispatcher_builder.with(
+                RemoteCharInterpolationSystem,
+                "interpolation_sys",
+                &["snapshot_sys"],
+            );
+            ecs_dispatcher_builder = ecs_dispatcher_builder.with(
+                FogOfWarSystem,
+                "fog_of_war_sys",
+                &["interpolation_sys"],
+            );
+        }
         if let Some(console_system) = console_system {
             // thread_local to avoid Send fields
             ecs_dispatcher_builder = ecs_dispatcher_builder.with_thread_local(console_system);
@@ -850,33 +1127,25 @@ fn update_desktop_inputs(
 }
 
 fn spawn_minions(ecs_world: &mut World) -> () {
-    {
-        let entity_id = create_random_char_minion(
-            ecs_world,
-            v2(
-                MinionAiSystem::CHECKPOINTS[0][0] as f32,
-                MinionAiSystem::CHECKPOINTS[0][1] as f32,
-            ),
-            Team::Right,
-        );
-        let mut storage = ecs_world.write_storage();
-        storage
-            .insert(entity_id.into(), MinionComponent { fountain_up: false })
-            .unwrap();
-    }
-    {
-        let entity_id = create_random_char_minion(
-            ecs_world,
-            v2(
-                MinionAiSystem::CHECKPOINTS[5][0] as f32,
-                MinionAiSystem::CHECKPOINTS[5][1] as f32,
-            ),
-            Team::Left,
-        );
-        let mut storage = ecs_world.write_storage();
-        storage
-            .insert(entity_id.into(), MinionComponent { fountain_up: false })
-            .unwrap();
+    let (lanes, minions_per_lane) = {
+        let scenario = ecs_world.read_resource::<Scenario>();
+        (
+            scenario.lanes.clone(),
+            scenario.minion_waves.minions_per_lane,
+        )
+    };
+    for (lane_index, lane) in lanes.iter().enumerate() {
+        for team in &[Team::Left, Team::Right] {
+            for _ in 0..minions_per_lane {
+                let pos = lane.start(*team);
+                let char_entity_id = create_random_char_minion(ecs_world, pos, *team);
+                ecs_world
+                    .create_entity()
+                    .with(ControllerComponent::new(char_entity_id))
+                    .with(MinionComponent::new(lane_index, lane, *team, &pos))
+                    .build();
+            }
+        }
     }
 }
 
@@ -896,9 +1165,14 @@ fn reload_configs_if_changed(
                 }
 
                 *ecs_world.write_resource::<DevConfig>() = new_config;
-                for char_state in (&mut ecs_world.write_storage::<CharacterStateComponent>()).join()
+                for (char_state, statuses) in (
+                    &mut ecs_world.write_storage::<CharacterStateComponent>(),
+                    (&mut ecs_world.write_storage::<StatusesComponent>()).maybe(),
+                )
+                    .join()
                 {
-                    char_state.update_base_attributes(&ecs_world.write_resource::<DevConfig>());
+                    char_state
+                        .update_base_attributes(&ecs_world.read_resource::<DevConfig>(), statuses);
                 }
 
                 log::info!("Configs has been reloaded");
@@ -1048,17 +1322,122 @@ fn execute_finished_skill_castings(ecs_world: &mut World) {
         Vec::with_capacity(128),
     );
     for finished_cast in &finished_casts {
-        let manifestation = finished_cast
+        finished_cast
             .skill
             .get_definition()
-            .finish_cast(&finished_cast, ecs_world);
-        if let Some(manifestation) = manifestation {
-            let skill_entity_id = ecs_world.create_entity().build();
-            ecs_world.read_resource::<LazyUpdate>().insert(
-                skill_entity_id,
-                SkillManifestationComponent::new(skill_entity_id, manifestation),
-            );
+            .play_cast_effects(&finished_cast, ecs_world);
+    }
+}
+
+/// Deletes the char or manifestation which does not exist on the server anymore
+fn remove_replicated_entity(ecs_world: &World, char_id: CharEntityId) {
+    if let Some(char_state) = ecs_world
+        .read_storage::<CharacterStateComponent>()
+        .get(char_id.into())
+    {
+        let physics_world = &mut ecs_world.write_resource::<PhysicEngine>();
+        physics_world.colliders.remove(char_state.collider_handle);
+        physics_world.bodies.remove(char_state.body_handle);
+    }
+    ecs_world
+        .write_resource::<RemoteCharInterpolation>()
+        .remove(char_id);
+    if let Err(e) = ecs_world.entities().delete(char_id.into()) {
+        log::warn!("The removed entity does not exist: {:?}", e);
+    }
+}
+
+fn apply_manifestation_state(
+    ecs_world: &World,
+    snapshots: &mut GameSnapshots,
+    id: ServerEntityId,
+    mut manifestation: SkillManifestationComponent,
+) {
+    // the caster or the target is not known yet
+    if manifestation
+        .map_char_ids(|it| snapshots.get_local_id(&ServerEntityId::new(it)))
+        .is_none()
+    {
+        return;
+    }
+    let local_id = match snapshots.get_local_id(&id) {
+        Some(local_id) => local_id,
+        None => {
+            let local_id = CharEntityId::from(ecs_world.entities().create());
+            snapshots.register_entity(id, local_id);
+            local_id
         }
+    };
+    if let Err(e) = ecs_world
+        .write_storage::<SkillManifestationComponent>()
+        .insert(local_id.into(), manifestation)
+    {
+        log::warn!("Manifestation could not be added: {:?}", e);
+    }
+}
+
+fn apply_gameplay_event(ecs_world: &World, snapshots: &GameSnapshots, entry: AckEntry) {
+    match entry {
+        AckEntry::HpModification {
+            src,
+            dst,
+            typ,
+            dst_hp,
+        } => {
+            let (src, dst) = match (snapshots.get_local_id(&src), snapshots.get_local_id(&dst)) {
+                (Some(src), Some(dst)) => (src, dst),
+                _ => return,
+            };
+            if let Some(char_state) = ecs_world
+                .write_storage::<CharacterStateComponent>()
+                .get_mut(dst.into())
+            {
+                char_state.hp = dst_hp;
+            }
+            if let Some(dst_auth_state) = ecs_world
+                .read_storage::<AuthorizedCharStateComponent>()
+                .get(dst.into())
+            {
+                let outcome = HpModificationResult {
+                    src_entity: src,
+                    dst_entity: dst,
+                    dst_hp,
+                    typ,
+                };
+                let now = ecs_world.read_resource::<EngineTime>().now();
+                AttackCalculation::add_flying_damage_entity(
+                    &outcome,
+                    &ecs_world.entities(),
+                    &mut ecs_world.write_resource::<LazyUpdate>(),
+                    src,
+                    dst,
+                    &dst_auth_state.pos(),
+                    now,
+                );
+                AttackCalculation::make_sound(
+                    &ecs_world.entities(),
+                    dst_auth_state.pos(),
+                    dst,
+                    &outcome,
+                    now,
+                    &mut ecs_world.write_resource::<LazyUpdate>(),
+                    &ecs_world.read_resource::<SystemVariables>().assets.sounds,
+                );
+            }
+        }
+        AckEntry::Respawn { id, hp } => {
+            let char_id = match snapshots.get_local_id(&id) {
+                Some(char_id) => char_id,
+                None => return,
+            };
+            if let Some(char_state) = ecs_world
+                .write_storage::<CharacterStateComponent>()
+                .get_mut(char_id.into())
+            {
+                char_state.hp = hp;
+            }
+        }
+        _ => log::warn!("Not a gameplay event: {:?}", entry),
     }
 }
 
@@ -1078,6 +1457,61 @@ fn get_all_effect_names(asset_loader: &GrfEntryLoader) -> Vec<String> {
     all_str_names
 }
 
+fn describe_match_phase(phase: &MatchPhase) -> String {
+    return match phase {
+        MatchPhase::Lobby {
+            players,
+            required_players,
+        } => format!("Waiting for players ({}/{})", players, required_players),
+        MatchPhase::Warmup { remaining_seconds } => {
+            format!("The match starts in {:.0} seconds", remaining_seconds)
+        }
+        MatchPhase::Running {
+            remaining_seconds,
+            left_team_kills,
+            right_team_kills,
+        } => format!(
+            "Fight! Red {} - {} Blue, {:.0} seconds left",
+            left_team_kills, right_team_kills, remaining_seconds
+        ),
+        MatchPhase::Ended {
+            winner,
+            stats,
+            remaining_seconds,
+        } => {
+            let mut text = match winner {
+                Some(Team::Left) => "Red team won!".to_owned(),
+                Some(Team::Right) => "Blue team won!".to_owned(),
+                _ => "Draw!".to_owned(),
+            };
+            for player in stats {
+                text += &format!(
+                    "\n{} ({:?}): {} kills, {} deaths, {} damage, {} healing",
+                    player.name,
+                    player.team,
+                    player.kills,
+                    player.deaths,
+                    player.damage_done,
+                    player.healing_done
+                );
+            }
+            text += &format!("\nNext match in {:.0} seconds", remaining_seconds);
+            text
+        }
+    };
+}
+
+fn show_handshake_rejection(reason: &HandshakeRejectReason) -> ! {
+    log::error!("The server has rejected the connection: {:?}", reason);
+    let _ = sdl2::messagebox::show_simple_message_box(
+        sdl2::messagebox::MessageBoxFlag::ERROR,
+        "Connection rejected",
+        &reason.to_string(),
+        None,
+    );
+    std::process::exit(1);
+}
+
 fn get_all_map_names(asset_loader: &GrfEntryLoader) -> Vec<String> {
     let all_map_names = asset_loader
         .asset_loader
@@ -15,6 +15,7 @@ pub enum MyGlEnum {
     TEXTURE0 = gl::TEXTURE0 as isize,
     TEXTURE1 = gl::TEXTURE1 as isize,
     TEXTURE2 = gl::TEXTURE2 as isize,
+    TEXTURE3 = gl::TEXTURE3 as isize,
     RGBA = gl::RGBA as isize,
     UNSIGNED_BYTE = gl::UNSIGNED_BYTE as isize,
     TEXTURE_2D = gl::TEXTURE_2D as isize,
@@ -206,6 +207,31 @@ impl Gl {
         );
     }
 
+    pub unsafe fn tex_sub_image2d(
+        &self,
+        target: MyGlEnum,
+        level: GLint,
+        xoffset: GLint,
+        yoffset: GLint,
+        width: GLsizei,
+        height: GLsizei,
+        format: MyGlEnum,
+        type_: MyGlEnum,
+        pixels: *const c_void,
+    ) {
+        gl::TexSubImage2D(
+            target as u32,
+            level,
+            xoffset,
+            yoffset,
+            width,
+            height,
+            format as u32,
+            type_ as u32,
+            pixels,
+        );
+    }
+
     pub unsafe fn delete_textures(&self, n: GLsizei, textures: *const GLuint) {
         gl::DeleteTextures(n, textures);
     }
@@ -22,15 +22,19 @@ use crate::shaders::{load_shaders, GroundShaderParameters, Shaders};
 use crate::systems::{SystemFrameDurations, SystemVariables};
 use crate::video::{ShaderProgram, VertexArray, VertexAttribDefinition, Video};
 use rustarok_common::common::{rotate_vec2, v2_to_v3, Mat3, Mat4};
+use rustarok_common::vision::TeamVision;
 
 pub struct StrEffectCache {
     cache: HashMap<EffectFrameCacheKey, Option<EffectFrameCache>>,
+    // see `AssetResources::str_effects_revision`
+    revision: usize,
 }
 
 impl StrEffectCache {
     pub fn new() -> StrEffectCache {
         StrEffectCache {
             cache: HashMap::with_capacity(32),
+            revision: 0,
         }
     }
 
@@ -77,6 +81,9 @@ pub struct OpenGlRenderSystem<'a, 'b> {
 
     shaders: Shaders,
     white_dummy_texture: GlTexture,
+    // one texel per gat cell, it is created when the vision of the local team is first rendered
+    fog_texture: Option<GlTexture>,
+    fog_pixels: Vec<u8>,
 }
 
 pub struct Fonts<'a, 'b> {
@@ -429,6 +436,38 @@ impl<'a, 'b> OpenGlRenderSystem<'a, 'b> {
                     .unwrap();
                 GrfEntryLoader::create_texture_from_surface_inner(&gl, surface, MyGlEnum::LINEAR)
             },
+            fog_texture: None,
+            fog_pixels: Vec::new(),
+        }
+    }
+
+    /// The visible cells are lit, the explored ones are dimmed and the rest is almost dark
+    fn update_fog_texture(&mut self, gl: &Gl, vision: &TeamVision) {
+        self.fog_pixels.clear();
+        for y in 0..vision.height() {
+            for x in 0..vision.width() {
+                let brightness = if vision.is_cell_visible((x, y)) {
+                    255
+                } else if vision.is_cell_explored((x, y)) {
+                    140
+                } else {
+                    50
+                };
+                self.fog_pixels
+                    .extend_from_slice(&[brightness, brightness, brightness, 255]);
+            }
+        }
+        match &self.fog_texture {
+            Some(fog_texture) => fog_texture.update_rgba(gl, &self.fog_pixels),
+            None => {
+                self.fog_texture = Some(GrfEntryLoader::create_texture_from_rgba(
+                    gl,
+                    vision.width() as i32,
+                    vision.height() as i32,
+                    &self.fog_pixels,
+                    MyGlEnum::LINEAR,
+                ));
+            }
         }
     }
 
@@ -541,6 +580,7 @@ impl<'a, 'b> OpenGlRenderSystem<'a, 'b> {
         model_view: &Mat4,
         normal_matrix: &Mat3,
         asset_db: &AssetDatabase,
+        fog_texture: Option<&GlTexture>,
     ) {
         let shader = ground_shader.gl_use(gl);
         shader.params.projection_mat.set(gl, &projection_matrix);
@@ -582,6 +622,18 @@ impl<'a, 'b> OpenGlRenderSystem<'a, 'b> {
             .params
             .use_lighting
             .set(gl, if map_render_data.use_lighting { 1 } else { 0 });
+        match fog_texture {
+            Some(fog_texture) => {
+                shader.params.use_fog.set(gl, 1);
+                shader.params.fog_texture.set(gl, 3);
+                shader
+                    .params
+                    .fog_size
+                    .set(gl, &[fog_texture.width as f32, fog_texture.height as f32]);
+                fog_texture.bind(&gl, MyGlEnum::TEXTURE3);
+            }
+            None => shader.params.use_fog.set(gl, 0),
+        }
 
         asset_db
             .get_texture(map_render_data.texture_atlas)
@@ -761,6 +813,7 @@ impl<'a> System<'a> for OpenGlRenderSystem<'_, '_> {
         ReadExpect<'a, AssetDatabase>,
         ReadExpect<'a, Gl>,
         ReadExpect<'a, MapRenderData>,
+        Option<Read<'a, TeamVision>>,
     );
 
     fn run(
@@ -774,6 +827,7 @@ impl<'a> System<'a> for OpenGlRenderSystem<'_, '_> {
             asset_db,
             gl,
             map_render_data,
+            vision,
         ): Self::SystemData,
     ) {
         unsafe {
@@ -781,6 +835,9 @@ impl<'a> System<'a> for OpenGlRenderSystem<'_, '_> {
         }
 
         let gl = &gl;
+        if let Some(vision) = &vision {
+            self.update_fog_texture(gl, vision);
+        }
         for (render_commands, camera /*_not_browser*/) in (
             &render_commands_storage,
             &camera_storage,
@@ -801,6 +858,7 @@ impl<'a> System<'a> for OpenGlRenderSystem<'_, '_> {
                         &camera.view_matrix,
                         &camera.normal_matrix,
                         &asset_db,
+                        self.fog_texture.as_ref(),
                     );
                 }
             }
@@ -956,6 +1014,11 @@ impl<'a> System<'a> for OpenGlRenderSystem<'_, '_> {
                 }
 
                 let str_effect_cache = &mut self.str_effect_cache;
+                if str_effect_cache.revision != sys_vars.assets.str_effects_revision {
+                    // the frames are prepared again on demand
+                    str_effect_cache.cache.clear();
+                    str_effect_cache.revision = sys_vars.assets.str_effects_revision;
+                }
                 &render_commands
                     .effect_commands
                     .iter()
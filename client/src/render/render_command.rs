@@ -440,6 +440,7 @@ pub enum UiLayer2d {
     SkillBarKey,
     HoveringSkillBarName,
     Minimap,
+    MinimapFog,
     MinimapSimpleEntities,
     MinimapImportantEntities,
     MinimapVisibleRegionRectangle,
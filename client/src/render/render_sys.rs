@@ -1,14 +1,18 @@
 use crate::audio::sound_sys::AudioCommandCollectorComponent;
 use crate::cam::Camera;
 use crate::components::char::{
-    ActionPlayMode, CharacterStateComponent, ClientCharState, NpcComponent, SpriteBoundingRect,
+    ActionPlayMode, CharacterStateComponent, ClientCharState, SpriteBoundingRect,
     SpriteRenderDescriptorComponent,
 };
 use crate::components::controller::{
     CameraComponent, EntitiesBelowCursor, HumanInputComponent, LocalPlayerControllerComponent,
     SkillKey,
 };
-use crate::components::skills::skills::{SkillManifestationComponent, SkillTargetType, Skills};
+use crate::components::skills::assa_blade_dash::render_blade_dash;
+use crate::components::skills::skills::{
+    render_manifestation, SkillDefinitionExt, SkillTargetType, Skills,
+};
+use crate::components::status::status;
 use crate::components::{
     FlyingNumberComponent, FlyingNumberType, SoundEffectComponent, StrEffectComponent,
 };
@@ -18,6 +22,7 @@ use crate::effect::StrEffectId;
 use crate::grf::database::AssetDatabase;
 use crate::render::render_command::{RenderCommandCollector, UiLayer2d};
 use crate::runtime_assets::map::{MapRenderData, PhysicEngine};
+use crate::systems::fog_of_war_sys::FogOfWarSystem;
 use crate::systems::ui::RenderUI;
 use crate::systems::{AssetResources, RenderMatrices, SystemFrameDurations, SystemVariables};
 use crate::{ElapsedTime, SpriteResource};
@@ -28,6 +33,9 @@ use rustarok_common::components::char::{
     ControllerEntityId, EntityTarget, Team, DIRECTION_TABLE,
 };
 use rustarok_common::components::controller::{ControllerComponent, PlayerIntention};
+use rustarok_common::components::manifestation::SkillManifestationComponent;
+use rustarok_common::components::status::{StatusEnum, StatusEnumDiscriminants, StatusesComponent};
+use rustarok_common::vision::TeamVision;
 use specs::prelude::*;
 
 pub const COLOR_WHITE: [u8; 4] = [255, 255, 255, 255];
@@ -55,7 +63,6 @@ impl RenderDesktopClientSystem {
         camera: &CameraComponent,
         input: &HumanInputComponent,
         render_commands: &mut RenderCommandCollector,
-        audio_commands: &mut AudioCommandCollectorComponent,
         physics_world: &ReadExpect<'a, PhysicEngine>,
         assets: &AssetResources,
         time: &EngineTime,
@@ -64,13 +71,15 @@ impl RenderDesktopClientSystem {
         auth_char_state_storage: &ReadStorage<'a, AuthorizedCharStateComponent>,
         entities: &Entities<'a>,
         sprite_storage: &ReadStorage<'a, SpriteRenderDescriptorComponent>,
-        skill_storage: &ReadStorage<'a, SkillManifestationComponent>, // TODO remove me
+        skill_storage: &ReadStorage<'a, SkillManifestationComponent>,
+        status_storage: &ReadStorage<'a, StatusesComponent>,
         str_effect_storage: &ReadStorage<'a, StrEffectComponent>,
         updater: &Write<'a, LazyUpdate>,
         system_benchmark: &mut SystemFrameDurations,
         asset_db: &AssetDatabase,
         map_render_data: &MapRenderData,
         matrices: &RenderMatrices,
+        vision: Option<&TeamVision>,
     ) {
         render_commands.set_view_matrix(&camera.view_matrix, &camera.normal_matrix, camera.yaw);
         {
@@ -84,10 +93,12 @@ impl RenderDesktopClientSystem {
                 dev_configs,
                 char_state_storage,
                 auth_char_state_storage,
+                status_storage,
                 entities,
                 sprite_storage,
                 asset_db,
                 matrices,
+                vision,
                 //                &map_render_data.gat,
             );
         }
@@ -162,14 +173,16 @@ impl RenderDesktopClientSystem {
                     let char_pos = controller.controlled_auth_char.pos();
                     if let Some((_skill_key, skill)) = controller.desktop.select_skill_target {
                         let skill_def = skill.get_definition();
-                        let skill_cast_attr =
-                            skill.get_cast_attributes(&dev_configs, controller.controlled_char);
+                        let skill_cast_attr = skill.get_cast_attributes(
+                            &dev_configs.skills,
+                            status::is_mounted(controller.controlled_statuses),
+                        );
                         let (skill_3d_pos, dir_vector) = Skills::limit_vector_into_range(
                             &char_pos,
                             &input.mouse_world_pos,
                             skill_cast_attr.casting_range,
                         );
-                        if skill_def.get_skill_target_type() != SkillTargetType::Directional {
+                        if skill.get_skill_target_type() != SkillTargetType::Directional {
                             render_commands
                                 .circle_3d()
                                 .pos_2d(&char_pos)
@@ -177,7 +190,7 @@ impl RenderDesktopClientSystem {
                                 .radius(skill_cast_attr.casting_range)
                                 .color(&[0, 255, 0, 255])
                                 .add();
-                            if skill_def.get_skill_target_type() == SkillTargetType::Area {
+                            if skill.get_skill_target_type() == SkillTargetType::Area {
                                 let is_castable = controller
                                     .controlled_char
                                     .skill_cast_allowed_at
@@ -241,14 +254,14 @@ impl RenderDesktopClientSystem {
             }
         }
 
-        for skill in (&skill_storage).join() {
-            skill.render(
-                char_state_storage,
+        for manifestation in skill_storage.join() {
+            render_manifestation(
+                manifestation,
+                auth_char_state_storage,
                 time.now(),
-                time.tick,
                 assets,
+                dev_configs,
                 render_commands,
-                audio_commands,
             );
         }
 
@@ -288,7 +301,7 @@ impl RenderDesktopClientSystem {
         desktop_target: &Option<EntityTarget>,
     ) -> bool {
         return if let Some((_skill_key, skill)) = select_skill_target {
-            match skill.get_definition().get_skill_target_type() {
+            match skill.get_skill_target_type() {
                 SkillTargetType::AnyEntity => entities_below_cursor
                     .get_enemy_or_friend()
                     .map(|it| it == rendering_entity_id)
@@ -333,18 +346,21 @@ impl RenderDesktopClientSystem {
         dev_configs: &DevConfig,
         char_state_storage: &ReadStorage<CharacterStateComponent>,
         auth_char_state_storage: &ReadStorage<AuthorizedCharStateComponent>,
+        status_storage: &ReadStorage<StatusesComponent>,
         entities: &Entities,
         sprite_storage: &ReadStorage<SpriteRenderDescriptorComponent>,
         asset_db: &AssetDatabase,
         matrices: &RenderMatrices,
+        vision: Option<&TeamVision>,
         //        gat: &Gat,
     ) {
         // Draw players
-        for (rendering_entity_id, animated_sprite, char_state, auth_state) in (
+        for (rendering_entity_id, animated_sprite, char_state, auth_state, statuses) in (
             entities,
             sprite_storage,
             char_state_storage,
             auth_char_state_storage,
+            status_storage.maybe(),
         )
             .join()
         {
@@ -354,6 +370,17 @@ impl RenderDesktopClientSystem {
             if !camera.camera.is_visible(pos_2d) {
                 continue;
             }
+            // spectators see everyone
+            if let (Some(vision), Some(controller)) = (vision, controller.as_ref()) {
+                if FogOfWarSystem::is_hidden(
+                    vision,
+                    controller.controlled_char.team,
+                    char_state,
+                    &pos_2d,
+                ) {
+                    continue;
+                }
+            }
 
             // gat height calculation
             //            let w = gat.width as usize;
@@ -372,7 +399,7 @@ impl RenderDesktopClientSystem {
 
             let pos_3d = Vector3::new(pos_2d.x, char_state.get_y(), pos_2d.y);
 
-            let color = char_state.statuses.calc_render_color(time.now());
+            let color = status::calc_render_color(statuses);
             match char_state.outlook {
                 CharOutlook::Player {
                     job_sprite_id,
@@ -384,14 +411,13 @@ impl RenderDesktopClientSystem {
                         .as_ref()
                         .map(|it| it.controlled_char.team)
                         .unwrap_or(Team::Right);
-                    let body_sprite = char_state
-                        .statuses
-                        .calc_body_sprite(assets, char_state.job_id, sex)
-                        .unwrap_or(
-                            &assets.sprites.character_sprites[&job_sprite_id]
-                                [viewer_team.get_palette_index(char_state.team)]
-                                [sex as usize],
-                        );
+                    let body_sprite =
+                        status::calc_body_sprite(statuses, assets, char_state.job_id, sex)
+                            .unwrap_or(
+                                &assets.sprites.character_sprites[&job_sprite_id]
+                                    [viewer_team.get_palette_index(char_state.team)]
+                                    [sex as usize],
+                            );
 
                     let play_mode = if auth_state.state().is_dead() {
                         ActionPlayMode::PlayThenHold
@@ -511,6 +537,7 @@ impl RenderDesktopClientSystem {
                                 .map(|it| it.controlled_char.team.is_ally_to(char_state.team))
                                 .unwrap_or(false),
                             &char_state,
+                            statuses,
                             time.now(),
                             &body_bounding_rect,
                             assets,
@@ -600,6 +627,7 @@ impl RenderDesktopClientSystem {
                                 .map(|it| it.controlled_char.team.is_ally_to(char_state.team))
                                 .unwrap_or(false),
                             &char_state,
+                            statuses,
                             time.now(),
                             &bounding_rect,
                             assets,
@@ -630,9 +658,21 @@ impl RenderDesktopClientSystem {
             //                );
             //            }
 
-            char_state
-                .statuses
-                .render(&char_state, assets, time, render_commands);
+            if let Some(statuses) = statuses {
+                status::render_statuses(statuses, &pos_2d, time.now(), assets, render_commands);
+                if let Some(StatusEnum::AssaBladeDashStatus(blade_dash)) =
+                    statuses.get_status(StatusEnumDiscriminants::AssaBladeDashStatus)
+                {
+                    render_blade_dash(
+                        blade_dash,
+                        char_state,
+                        auth_state,
+                        time.now(),
+                        assets,
+                        render_commands,
+                    );
+                }
+            }
         }
     }
 }
@@ -642,6 +682,7 @@ struct ControllerAndControlled<'a> {
     desktop: &'a mut LocalPlayerControllerComponent,
     controlled_char: &'a CharacterStateComponent,
     controlled_auth_char: &'a AuthorizedCharStateComponent,
+    controlled_statuses: Option<&'a StatusesComponent>,
 }
 
 impl<'a> System<'a> for RenderDesktopClientSystem {
@@ -656,7 +697,8 @@ impl<'a> System<'a> for RenderDesktopClientSystem {
         ReadExpect<'a, SystemVariables>,
         ReadExpect<'a, DevConfig>,
         WriteExpect<'a, SystemFrameDurations>,
-        ReadStorage<'a, SkillManifestationComponent>, // TODO remove me
+        ReadStorage<'a, SkillManifestationComponent>,
+        ReadStorage<'a, StatusesComponent>,
         ReadStorage<'a, StrEffectComponent>,
         ReadStorage<'a, CameraComponent>,
         ReadExpect<'a, PhysicEngine>,
@@ -666,9 +708,9 @@ impl<'a> System<'a> for RenderDesktopClientSystem {
         WriteStorage<'a, RenderCommandCollector>,
         WriteStorage<'a, AudioCommandCollectorComponent>,
         ReadExpect<'a, AssetDatabase>,
-        ReadStorage<'a, NpcComponent>,
         ReadExpect<'a, MapRenderData>,
         ReadExpect<'a, EngineTime>,
+        Option<Read<'a, TeamVision>>,
     );
 
     fn run(
@@ -685,6 +727,7 @@ impl<'a> System<'a> for RenderDesktopClientSystem {
             dev_configs,
             mut system_benchmark,
             skill_storage,
+            status_storage,
             str_effect_storage,
             camera_storage,
             physics_world,
@@ -694,9 +737,9 @@ impl<'a> System<'a> for RenderDesktopClientSystem {
             mut render_commands_storage,
             mut audio_commands_storage,
             asset_db,
-            npc_storage,
             map_render_data,
             time,
+            vision,
         ): Self::SystemData,
     ) {
         let join = {
@@ -723,6 +766,7 @@ impl<'a> System<'a> for RenderDesktopClientSystem {
                         desktop: local_desktop_storage.get_mut(controller_id.into()).unwrap(),
                         controlled_char: char_state_storage.get(entity.into()).unwrap(),
                         controlled_auth_char: auth_char_state_storage.get(entity.into()).unwrap(),
+                        controlled_statuses: status_storage.get(entity.into()),
                     }
                 });
 
@@ -733,7 +777,6 @@ impl<'a> System<'a> for RenderDesktopClientSystem {
                     camera,
                     &mut input,
                     &mut render_commands,
-                    &mut audio_commands,
                     &physics_world,
                     &sys_vars.assets,
                     &time,
@@ -743,12 +786,14 @@ impl<'a> System<'a> for RenderDesktopClientSystem {
                     &entities,
                     &sprite_storage,
                     &skill_storage,
+                    &status_storage,
                     &str_effect_storage,
                     &updater,
                     &mut system_benchmark,
                     &asset_db,
                     &map_render_data,
                     &sys_vars.matrices,
+                    vision.as_ref().map(|it| &**it),
                 );
             }
 
@@ -790,11 +835,11 @@ impl<'a> System<'a> for RenderDesktopClientSystem {
                     &sys_vars,
                     &time,
                     &char_state_storage,
-                    &npc_storage,
-                    &entities,
+                    &auth_char_state_storage,
                     &camera.camera.pos(),
                     &asset_db,
                     &map_render_data,
+                    vision.as_ref().map(|it| &**it),
                 );
             }
         }
@@ -1353,6 +1398,7 @@ impl RenderDesktopClientSystem {
         is_self: bool,
         is_same_team: bool,
         char_state: &CharacterStateComponent,
+        statuses: Option<&StatusesComponent>,
         now: ElapsedTime,
         bounding_rect_2d: &SpriteBoundingRect,
         assets: &AssetResources,
@@ -1361,6 +1407,7 @@ impl RenderDesktopClientSystem {
         let bar_w = match char_state.typ {
             CharType::Player => 80,
             CharType::Minion => 70,
+            CharType::Structure => 140,
             _ => 100,
         };
         let spr_x = bounding_rect_2d.bottom_left[0];
@@ -1403,10 +1450,7 @@ impl RenderDesktopClientSystem {
         };
 
         // draw status remaining time indicator
-        if let Some(perc) = char_state
-            .statuses
-            .calc_largest_remaining_status_time_percent(now)
-        {
+        if let Some(perc) = status::calc_largest_remaining_status_time_percent(statuses, now) {
             let orange = [255, 140, 0, 255];
             let w = bar_w - 4;
             draw_rect(2, bottom_bar_y + 2, w, 2, &[0, 0, 0, 255]); // black bg
@@ -6,15 +6,18 @@ use crate::components::char::{
 use crate::components::controller::{
     CameraComponent, HumanInputComponent, LocalPlayerControllerComponent,
 };
-use crate::components::skills::skills::SkillManifestationComponent;
 use crate::components::{
     FlyingNumberComponent, MinionComponent, SoundEffectComponent, StrEffectComponent,
 };
 use crate::render::render_command::RenderCommandCollector;
 use crate::systems::console_system::ConsoleComponent;
 use crate::systems::falcon_ai_sys::FalconComponent;
-use rustarok_common::components::char::AuthorizedCharStateComponent;
+use rustarok_common::components::char::{
+    AuthorizedCharStateComponent, CharAttributesComponent, WalkingPathComponent,
+};
 use rustarok_common::components::controller::ControllerComponent;
+use rustarok_common::components::manifestation::SkillManifestationComponent;
+use rustarok_common::components::status::StatusesComponent;
 use specs::World;
 
 pub fn create_ecs_world() -> World {
@@ -32,12 +35,15 @@ pub fn create_ecs_world() -> World {
     ecs_world.register::<SoundEffectComponent>();
     ecs_world.register::<StrEffectComponent>();
     ecs_world.register::<SkillManifestationComponent>();
+    ecs_world.register::<StatusesComponent>();
     ecs_world.register::<CameraComponent>();
     ecs_world.register::<LocalPlayerControllerComponent>();
     ecs_world.register::<MinionComponent>();
     ecs_world.register::<ConsoleComponent>();
 
     ecs_world.register::<AuthorizedCharStateComponent>();
+    ecs_world.register::<CharAttributesComponent>();
     ecs_world.register::<ControllerComponent>();
+    ecs_world.register::<WalkingPathComponent>();
     ecs_world
 }
@@ -54,7 +54,12 @@ pub fn load_and_prepare_effect(
     asset_db: &mut AssetDatabase,
     effect_cache: &mut StrEffectCache,
 ) {
-    let str_file = asset_loader.load_effect(gl, name, asset_db).unwrap();
+    let str_file = asset_loader
+        .load_effect(gl, name, asset_db)
+        .unwrap_or_else(|e| {
+            log::warn!("{}, the effect is not rendered", e);
+            StrFile::placeholder()
+        });
     effect_cache.precache_effect(gl, effect_id.into(), &str_file);
     str_effects.push(str_file);
 }
@@ -5,7 +5,7 @@ use sdl2::ttf::Sdl2TtfContext;
 use strum::IntoEnumIterator;
 
 use crate::components::controller::SkillKey;
-use crate::components::skills::skills::Skills;
+use crate::components::skills::skills::{SkillDefinitionExt, Skills};
 use crate::grf::asset_loader::GrfEntryLoader;
 use crate::grf::database::AssetDatabase;
 use crate::grf::texture::TextureId;
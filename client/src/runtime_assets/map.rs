@@ -20,7 +20,9 @@ use rustarok_common::common::{measure_time, Mat4};
 use rustarok_common::common::{v2, Vec2};
 use rustarok_common::components::char::CollisionGroup;
 use rustarok_common::grf::gat::{BlockingRectangle, Gat};
+use serde::{Deserialize, Serialize};
 
+#[derive(Serialize, Deserialize)]
 pub struct ModelInstance {
     pub asset_db_model_index: usize,
     pub matrix: Mat4,
@@ -60,6 +62,7 @@ pub struct ModelRenderData {
 
 pub type DataForRenderingSingleNode = Vec<SameTextureNodeFaces>;
 
+#[derive(Serialize, Deserialize)]
 pub struct SameTextureNodeFacesRaw {
     pub mesh: Vec<RsmNodeVertex>,
     pub texture: TextureId,
@@ -265,7 +268,7 @@ fn load_minimap_texture(
     });
 }
 
-fn create_collider(physics_world: &mut PhysicEngine, cell: &BlockingRectangle) -> (Vec2, Vec2) {
+pub fn create_collider(physics_world: &mut PhysicEngine, cell: &BlockingRectangle) -> (Vec2, Vec2) {
     let rot = Rotation3::<f32>::new(Vector3::new(180f32.to_radians(), 0.0, 0.0));
     let half_w = cell.width as f32 / 2.0;
     let x = cell.start_x as f32 + half_w;
@@ -7,10 +7,14 @@ in vec2 tex_coord;
 uniform sampler2D gnd_texture_atlas;
 uniform sampler2D tile_color_texture;
 uniform sampler2D lightmap_texture;
+// one texel per gat cell, its red channel is the brightness of the cell in the fog of war
+uniform sampler2D fog_texture;
+uniform vec2 fog_size;
 
 uniform bool use_tile_color;
 uniform bool use_lightmap;
 uniform bool use_lighting;
+uniform bool use_fog;
 
 uniform vec3 light_ambient;
 uniform vec3 light_diffuse;
@@ -19,6 +23,7 @@ uniform float light_opacity;
 in vec2 vLightmapCoord;
 in vec2 vTileColorCoord;
 in float vLightWeighting;
+in vec2 vGroundPos;
 
 void main() {
     vec4 texture = texture2D(gnd_texture_atlas, tex_coord);
@@ -39,4 +44,10 @@ void main() {
     } else {
         Color = texture;
     }
+
+    if (use_fog) {
+        // the gat cells are one unit wide, and their y coordinate grows towards -z
+        vec2 fog_coord = vec2(vGroundPos.x, -vGroundPos.y) / fog_size;
+        Color.rgb *= texture2D(fog_texture, fog_coord).r;
+    }
 }
\ No newline at end of file
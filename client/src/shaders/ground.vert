@@ -17,6 +17,7 @@ out vec2 tex_coord;
 out vec2 vLightmapCoord;
 out vec2 vTileColorCoord;
 out float vLightWeighting;
+out vec2 vGroundPos;
 
 void main() {
     gl_Position = projection * model_view * vec4(Position, 1.0);
@@ -24,6 +25,7 @@ void main() {
     tex_coord = aTexCoord;
     vLightmapCoord = aLightmapCoord;
     vTileColorCoord = aTileColorCoord;
+    vGroundPos = Position.xz;
 
     vec4 lDirection  = model_view * vec4( light_dir, 0.0);
     vec3 dirVector   = normalize(lDirection.xyz);
@@ -246,10 +246,13 @@ pub struct GroundShaderParameters {
     pub gnd_texture_atlas: ShaderParam1i,
     pub tile_color_texture: ShaderParam1i,
     pub lightmap_texture: ShaderParam1i,
+    pub fog_texture: ShaderParam1i,
+    pub fog_size: ShaderParam2fv,
 
     pub use_tile_color: ShaderParam1i,
     pub use_lightmap: ShaderParam1i,
     pub use_lighting: ShaderParam1i,
+    pub use_fog: ShaderParam1i,
 }
 
 impl GroundShaderParameters {
@@ -277,9 +280,12 @@ impl GroundShaderParameters {
                 program_id,
                 "lightmap_texture",
             )),
+            fog_texture: ShaderParam1i(Shader::get_location(gl, program_id, "fog_texture")),
+            fog_size: ShaderParam2fv(Shader::get_location(gl, program_id, "fog_size")),
             use_tile_color: ShaderParam1i(Shader::get_location(gl, program_id, "use_tile_color")),
             use_lightmap: ShaderParam1i(Shader::get_location(gl, program_id, "use_lightmap")),
             use_lighting: ShaderParam1i(Shader::get_location(gl, program_id, "use_lighting")),
+            use_fog: ShaderParam1i(Shader::get_location(gl, program_id, "use_fog")),
         }
     }
 }
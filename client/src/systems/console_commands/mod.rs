@@ -1,21 +1,11 @@
 use crate::components::char::{
-    attach_human_player_components, percentage, CharActionIndex, CharacterEntityBuilder,
+    attach_human_player_components, CharActionIndex, CharacterEntityBuilder,
     CharacterStateComponent, ClientCharState, SpriteRenderDescriptorComponent,
 };
 use crate::components::controller::{
     CameraComponent, HumanInputComponent, LocalPlayerControllerComponent,
 };
-use crate::components::skills::absorb_shield::AbsorbStatus;
-use crate::components::skills::basic_attack::WeaponType;
-use crate::components::skills::fire_bomb::FireBombStatus;
-use crate::components::skills::skills::SkillManifestationComponent;
-use crate::components::status::attrib_mod::ArmorModifierStatus;
-use crate::components::status::heal_area::HealApplierArea;
-use crate::components::status::status::{ApplyStatusComponent, PoisonStatus, StatusEnum};
-use crate::components::status::status_applier_area::StatusApplierArea;
-use crate::components::{
-    DamageDisplayType, HpModificationRequest, HpModificationType, MinionComponent,
-};
+use crate::components::MinionComponent;
 use crate::configs::DevConfig;
 use crate::consts::PLAYABLE_CHAR_SPRITES;
 use crate::my_gl::Gl;
@@ -31,14 +21,17 @@ use crate::systems::{RenderMatrices, SystemVariables};
 use crate::{CollisionGroup, ElapsedTime, PhysicEngine};
 use nalgebra::Isometry2;
 use rand::Rng;
-use rustarok_common::common::{v2, v2u, EngineTime, Vec2};
+use rustarok_common::common::{v2, Vec2};
 use rustarok_common::components::char::{
     AuthorizedCharStateComponent, CharDir, CharEntityId, CharOutlook, CharState,
     ControllerEntityId, JobId, MonsterId, Sex, Team,
 };
 use rustarok_common::components::controller::ControllerComponent;
 use rustarok_common::components::job_ids::JobSpriteId;
+use rustarok_common::components::status::StatusesComponent;
 use rustarok_common::grf::gat::CellType;
+use rustarok_common::packets::link_conditioner::{LinkConditionerConfig, NetworkSimulationConfig};
+use rustarok_common::scenario::Scenario;
 use sdl2::keyboard::Scancode;
 use sdl2::pixels::PixelFormatEnum;
 use sdl2::video::{FullscreenType, WindowPos};
@@ -443,8 +436,22 @@ pub(super) fn cmd_spawn_entity() -> CommandDefinition {
                     _ => Team::Right,
                 };
                 let count = args.as_int(2).unwrap_or(1);
+                let is_minion = type_name == "minion_melee" || type_name == "minion_ranged";
+                let lane_index = if is_minion {
+                    let lane_count = ecs_world.read_resource::<Scenario>().lanes.len();
+                    if lane_count == 0 {
+                        return Err("The scenario of the map has no lanes".to_owned());
+                    }
+                    rand::thread_rng().gen_range(0, lane_count)
+                } else {
+                    0
+                };
                 let pos2d = match (args.as_int(3), args.as_int(4)) {
                     (Some(x), Some(y)) => v2(x as f32, y as f32),
+                    // the minions start from the base of their team
+                    _ if is_minion => {
+                        ecs_world.read_resource::<Scenario>().lanes[lane_index].start(team)
+                    }
                     _ => {
                         let map_render_data = &ecs_world.read_resource::<MapRenderData>();
                         let hero_pos = {
@@ -488,10 +495,16 @@ pub(super) fn cmd_spawn_entity() -> CommandDefinition {
                                 job_id,
                                 outlook.clone(),
                             );
+                            let minion = MinionComponent::new(
+                                lane_index,
+                                &ecs_world.read_resource::<Scenario>().lanes[lane_index],
+                                team,
+                                &pos2d,
+                            );
                             ecs_world
                                 .create_entity()
                                 .with(ControllerComponent::new(char_entity_id))
-                                .with(MinionComponent { fountain_up: false })
+                                .with(minion)
                                 .build();
                         }
                         "guard" => {
@@ -520,7 +533,7 @@ pub(super) fn cmd_spawn_entity() -> CommandDefinition {
     }
 }
 
-fn create_guard(
+pub(crate) fn create_guard(
     ecs_world: &mut World,
     pos2d: Vec2,
     team: Team,
@@ -778,6 +791,54 @@ pub(super) fn cmd_set_fullscreen() -> CommandDefinition {
     }
 }
 
+pub(super) fn cmd_set_network_simulation() -> CommandDefinition {
+    CommandDefinition {
+        name: "set_network_simulation".to_string(),
+        arguments: vec![
+            ("in/out/both", CommandParamType::String, true),
+            ("delay_ms", CommandParamType::Int, true),
+            ("jitter_ms", CommandParamType::Int, false),
+            ("loss_percent", CommandParamType::Float, false),
+            ("duplication_percent", CommandParamType::Float, false),
+            ("reorder_percent", CommandParamType::Float, false),
+            ("seed", CommandParamType::Int, false),
+        ],
+        autocompletion: BasicAutocompletionProvider::new(|index| {
+            if index == 0 {
+                Some(vec!["in".to_owned(), "out".to_owned(), "both".to_owned()])
+            } else {
+                None
+            }
+        }),
+        action: Box::new(
+            |_self_controller_id, _self_char_id, args, ecs_world, _video| {
+                let link_config = LinkConditionerConfig {
+                    delay_ms: args.as_int(1).unwrap().max(0) as u32,
+                    jitter_ms: args.as_int(2).unwrap_or(0).max(0) as u32,
+                    loss_percent: args.as_f32(3).unwrap_or(0.0),
+                    duplication_percent: args.as_f32(4).unwrap_or(0.0),
+                    reorder_percent: args.as_f32(5).unwrap_or(0.0),
+                };
+                // the main loop forwards the changed config to the packet handler thread
+                let config = &mut ecs_world.write_resource::<NetworkSimulationConfig>();
+                if let Some(seed) = args.as_int(6) {
+                    config.seed = seed as u64;
+                }
+                match args.as_str(0).unwrap() {
+                    "in" => config.incoming = link_config,
+                    "out" => config.outgoing = link_config,
+                    "both" => {
+                        config.incoming = link_config.clone();
+                        config.outgoing = link_config;
+                    }
+                    _ => return Err("The direction must be 'in', 'out' or 'both'".to_owned()),
+                }
+                Ok(())
+            },
+        ),
+    }
+}
+
 pub(super) fn cmd_clear() -> CommandDefinition {
     CommandDefinition {
         name: "clear".to_string(),
@@ -833,254 +894,8 @@ fn print_console(
         .add_entry(entry);
 }
 
-pub(super) fn cmd_heal() -> CommandDefinition {
-    CommandDefinition {
-        name: "heal".to_string(),
-        arguments: vec![
-            ("value", CommandParamType::Int, true),
-            ("[username]", CommandParamType::String, false),
-        ],
-        autocompletion: AutocompletionProviderWithUsernameCompletion::new(
-            |index, username_completor, input_storage| {
-                if index == 1 {
-                    Some(username_completor(input_storage))
-                } else {
-                    None
-                }
-            },
-        ),
-        action: Box::new(
-            |_self_controller_id, self_char_id, args, ecs_world, _video| {
-                let value = args.as_int(0).unwrap().max(0);
-                let username = args.as_str(1);
-                let entity_id = if let Some(username) = username {
-                    ConsoleSystem::get_char_id_by_name(ecs_world, username)
-                } else {
-                    Some(self_char_id)
-                };
-
-                if let Some(entity_id) = entity_id {
-                    let mut sys_vars = ecs_world.write_resource::<SystemVariables>();
-                    sys_vars.hp_mod_requests.push(HpModificationRequest {
-                        src_entity: self_char_id,
-                        dst_entity: entity_id,
-                        typ: HpModificationType::Heal(value as u32),
-                    });
-                    Ok(())
-                } else {
-                    Err("The user was not found".to_owned())
-                }
-            },
-        ),
-    }
-}
-
-pub(super) fn cmd_spawn_area() -> CommandDefinition {
-    CommandDefinition {
-        name: "spawn_area".to_string(),
-        arguments: vec![
-            ("name", CommandParamType::String, true),
-            ("[value]", CommandParamType::Int, false),
-            ("[width:2]", CommandParamType::Int, false),
-            ("[height:3]", CommandParamType::Int, false),
-            ("[interval(ms):500]", CommandParamType::Int, false),
-            ("[time(ms):500]", CommandParamType::Int, false),
-        ],
-        autocompletion: BasicAutocompletionProvider::new(|index| {
-            if index == 0 {
-                let mut names = STATUS_NAMES
-                    .iter()
-                    .map(|it| (*it).to_owned())
-                    .collect::<Vec<_>>();
-                names.append(&mut vec!["heal".to_owned(), "damage".to_owned()]);
-                Some(names)
-            } else {
-                None
-            }
-        }),
-        action: Box::new(
-            |_self_controller_id, self_char_id, args, ecs_world, _video| {
-                let name = args.as_str(0).unwrap();
-                let value = args.as_int(1).unwrap_or(0);
-                let width = args.as_int(2).unwrap_or(2).max(0) as u16;
-                let height = args.as_int(3).unwrap_or(3).max(0) as u16;
-                let interval = args.as_int(4).unwrap_or(500) as f32 / 1000.0;
-                let time = args.as_int(5).unwrap_or(500);
-                let x = args.as_int(6).map(|it| it as f32);
-                let y = args.as_int(7).map(|it| it as f32);
-
-                let (pos, caster_team) = {
-                    let (hero_pos, team) = {
-                        let storage = ecs_world.read_storage::<AuthorizedCharStateComponent>();
-                        let storage2 = ecs_world.read_storage::<CharacterStateComponent>();
-                        let char_state = storage.get(self_char_id.into()).unwrap();
-                        let char_state2 = storage2.get(self_char_id.into()).unwrap();
-                        (char_state.pos(), char_state2.team)
-                    };
-                    (v2(x.unwrap_or(hero_pos.x), y.unwrap_or(hero_pos.y)), team)
-                };
-                let area_status_id = ecs_world.create_entity().build();
-                ecs_world
-                    .write_storage()
-                    .insert(
-                        area_status_id,
-                        SkillManifestationComponent::new(
-                            area_status_id,
-                            match name {
-                                "heal" => Box::new(HealApplierArea::new(
-                                    "Heal",
-                                    HpModificationType::Heal(value.max(0) as u32),
-                                    &pos,
-                                    v2u(width, height),
-                                    interval,
-                                    self_char_id,
-                                    &mut ecs_world.write_resource::<PhysicEngine>(),
-                                )),
-                                "damage" => Box::new(HealApplierArea::new(
-                                    "Damage",
-                                    HpModificationType::BasicDamage(
-                                        value.max(0) as u32,
-                                        DamageDisplayType::SingleNumber,
-                                        WeaponType::Sword,
-                                    ),
-                                    &pos,
-                                    v2u(width, height),
-                                    interval,
-                                    self_char_id,
-                                    &mut ecs_world.write_resource::<PhysicEngine>(),
-                                )),
-                                _ => {
-                                    let name = name.to_owned();
-                                    Box::new(StatusApplierArea::new(
-                                        name.to_owned(),
-                                        move |now| {
-                                            create_status_payload(
-                                                &name,
-                                                self_char_id,
-                                                now,
-                                                time,
-                                                value,
-                                                caster_team,
-                                            )
-                                            .unwrap()
-                                        },
-                                        &pos,
-                                        v2u(width, height),
-                                        self_char_id,
-                                        &mut ecs_world.write_resource::<PhysicEngine>(),
-                                    ))
-                                }
-                            },
-                        ),
-                    )
-                    .unwrap();
-                Ok(())
-            },
-        ),
-    }
-}
-
-fn create_status_payload(
-    name: &str,
-    self_char_id: CharEntityId,
-    now: ElapsedTime,
-    time: i32,
-    value: i32,
-    caster_team: Team,
-) -> Result<StatusEnum, String> {
-    match name {
-        "absorb" => Ok(StatusEnum::AbsorbStatus(AbsorbStatus::new(
-            self_char_id,
-            now,
-            time as f32 / 1000.0,
-        ))),
-        "firebomb" => Ok(StatusEnum::FireBombStatus(FireBombStatus {
-            caster_entity_id: self_char_id,
-            started: now,
-            until: now.add_seconds(time as f32 / 1000.0),
-            damage: value.max(1) as u32,
-            spread_count: 0,
-            caster_team,
-        })),
-        "poison" => Ok(StatusEnum::PoisonStatus(PoisonStatus {
-            poison_caster_entity_id: self_char_id,
-            started: now,
-            until: now.add_seconds(time as f32 / 1000.0),
-            next_damage_at: now,
-            damage: value.max(1) as u32,
-        })),
-        "armor" => Ok(StatusEnum::ArmorModifierStatus(ArmorModifierStatus::new(
-            now,
-            percentage(value),
-        ))),
-        _ => Err("Status not found".to_owned()),
-    }
-}
-
 pub const STATUS_NAMES: &'static [&'static str] = &["absorb", "poison", "firebomb", "armor"];
 
-pub(super) fn cmd_add_status() -> CommandDefinition {
-    CommandDefinition {
-        name: "add_status".to_string(),
-        arguments: vec![
-            ("status_name", CommandParamType::String, true),
-            ("time(ms)", CommandParamType::Int, true),
-            ("[value]", CommandParamType::Int, false),
-            ("[username]", CommandParamType::String, false),
-        ],
-        autocompletion: AutocompletionProviderWithUsernameCompletion::new(
-            |index, username_completor, input_storage| {
-                if index == 0 {
-                    Some(STATUS_NAMES.iter().map(|it| (*it).to_owned()).collect())
-                } else if index == 3 {
-                    Some(username_completor(input_storage))
-                } else {
-                    None
-                }
-            },
-        ),
-        action: Box::new(
-            |_self_controller_id, self_char_id, args, ecs_world, _video| {
-                let status_name = args.as_str(0).unwrap();
-                let duration = args.as_int(1).unwrap();
-                let value = args.as_int(2).unwrap_or(0);
-
-                let username = args.as_str(3);
-                let entity_id = if let Some(username) = username {
-                    ConsoleSystem::get_char_id_by_name(ecs_world, username)
-                } else {
-                    Some(self_char_id)
-                };
-
-                if let Some(entity_id) = entity_id {
-                    let mut sys_vars = ecs_world.write_resource::<SystemVariables>();
-                    let now = ecs_world.read_resource::<EngineTime>().now();
-                    let team = ecs_world
-                        .read_storage::<CharacterStateComponent>()
-                        .get(self_char_id.into())
-                        .unwrap()
-                        .team;
-                    sys_vars.apply_statuses.push(ApplyStatusComponent {
-                        source_entity_id: self_char_id,
-                        target_entity_id: entity_id,
-                        status: create_status_payload(
-                            status_name,
-                            entity_id,
-                            now,
-                            duration,
-                            value,
-                            team,
-                        )?,
-                    });
-                    Ok(())
-                } else {
-                    Err("The user was not found".to_owned())
-                }
-            },
-        ),
-    }
-}
-
 pub(super) fn cmd_list_statuses() -> CommandDefinition {
     CommandDefinition {
         name: "list_statuses".to_string(),
@@ -1098,17 +913,15 @@ pub(super) fn cmd_list_statuses() -> CommandDefinition {
                 };
 
                 if let Some(entity_id) = entity_id {
-                    let char_storage = ecs_world.read_storage::<CharacterStateComponent>();
-                    if let Some(target_char) = char_storage.get(entity_id.into()) {
-                        for status in target_char.statuses.get_statuses().iter() {
-                            if let Some(status) = status {
-                                print_console(
-                                    &mut ecs_world.write_storage::<ConsoleComponent>(),
-                                    self_controller_id,
-                                    ConsoleEntry::new()
-                                        .add(&format!("{:?}", status), ConsoleWordType::Normal),
-                                )
-                            }
+                    let status_storage = ecs_world.read_storage::<StatusesComponent>();
+                    if let Some(statuses) = status_storage.get(entity_id.into()) {
+                        for status in statuses.iter() {
+                            print_console(
+                                &mut ecs_world.write_storage::<ConsoleComponent>(),
+                                self_controller_id,
+                                ConsoleEntry::new()
+                                    .add(&format!("{:?}", status), ConsoleWordType::Normal),
+                            )
                         }
                     }
                     Ok(())
@@ -1286,7 +1099,12 @@ pub(super) fn cmd_resurrect() -> CommandDefinition {
                         let mut auth_char_storage =
                             ecs_world.write_storage::<AuthorizedCharStateComponent>();
                         let auth_state = auth_char_storage.get_mut(target_char_id.into()).unwrap();
-                        char_state.statuses.remove_all();
+                        if let Some(statuses) = ecs_world
+                            .write_storage::<StatusesComponent>()
+                            .get_mut(target_char_id.into())
+                        {
+                            statuses.remove_all();
+                        }
                         auth_state.set_state(CharState::Idle, auth_state.dir());
 
                         // give him max hp/sp
@@ -4,12 +4,12 @@ use crate::configs::DevConfig;
 use crate::render::opengl_render_sys::{NORMAL_FONT_H, NORMAL_FONT_W};
 use crate::render::render_command::{Font, RenderCommandCollector, UiLayer2d};
 use crate::systems::console_commands::{
-    cmd_add_falcon, cmd_add_status, cmd_bind_key, cmd_clear, cmd_clone_char, cmd_control_char,
-    cmd_disable_collision, cmd_enable_collision, cmd_follow_char, cmd_get_pos, cmd_goto, cmd_heal,
+    cmd_add_falcon, cmd_bind_key, cmd_clear, cmd_clone_char, cmd_control_char,
+    cmd_disable_collision, cmd_enable_collision, cmd_follow_char, cmd_get_pos, cmd_goto,
     cmd_kill_all, cmd_list_entities, cmd_list_players, cmd_list_statuses, cmd_remove_falcon,
-    cmd_resurrect, cmd_set_damping, cmd_set_fullscreen, cmd_set_job, cmd_set_mass, cmd_set_outlook,
-    cmd_set_pos, cmd_set_resolution, cmd_set_team, cmd_spawn_area, cmd_spawn_entity,
-    cmd_toggle_console,
+    cmd_resurrect, cmd_set_damping, cmd_set_fullscreen, cmd_set_job, cmd_set_mass,
+    cmd_set_network_simulation, cmd_set_outlook, cmd_set_pos, cmd_set_resolution, cmd_set_team,
+    cmd_spawn_entity, cmd_toggle_console,
 };
 use crate::systems::SystemVariables;
 use crate::video::Video;
@@ -669,16 +669,14 @@ impl<'a> ConsoleSystem<'a> {
         let mut command_defs: HashMap<String, CommandDefinition> = HashMap::new();
         ConsoleSystem::add_command(&mut command_defs, cmd_set_pos());
         ConsoleSystem::add_command(&mut command_defs, cmd_get_pos());
-        ConsoleSystem::add_command(&mut command_defs, cmd_add_status());
         ConsoleSystem::add_command(&mut command_defs, cmd_list_statuses());
         ConsoleSystem::add_command(&mut command_defs, cmd_list_players());
         ConsoleSystem::add_command(&mut command_defs, cmd_set_resolution(resolutions));
         ConsoleSystem::add_command(&mut command_defs, cmd_set_fullscreen());
+        ConsoleSystem::add_command(&mut command_defs, cmd_set_network_simulation());
         ConsoleSystem::add_command(&mut command_defs, cmd_list_entities());
         //        ConsoleSystem::add_command(&mut command_defs, cmd_spawn_effect(effect_names));
-        ConsoleSystem::add_command(&mut command_defs, cmd_spawn_area());
         ConsoleSystem::add_command(&mut command_defs, cmd_spawn_entity());
-        ConsoleSystem::add_command(&mut command_defs, cmd_heal());
         ConsoleSystem::add_command(&mut command_defs, cmd_kill_all());
         ConsoleSystem::add_command(&mut command_defs, cmd_goto());
         ConsoleSystem::add_command(&mut command_defs, cmd_follow_char());
@@ -2,10 +2,8 @@ use crate::components::char::{
     CharActionIndex, CharacterStateComponent, SpriteRenderDescriptorComponent,
 };
 use crate::components::controller::LocalPlayerControllerComponent;
-use crate::components::skills::falcon_carry::FalconCarryStatus;
-use crate::components::status::status::{ApplyStatusComponent, StatusEnum};
 use crate::runtime_assets::map::PhysicEngine;
-use crate::systems::{SystemFrameDurations, SystemVariables};
+use crate::systems::SystemFrameDurations;
 use nalgebra::{Isometry2, Vector2, Vector3};
 use rustarok_common::common::{v2, v2_to_v3, v3, v3_to_v2, ElapsedTime, EngineTime, Vec2};
 use rustarok_common::components::char::{
@@ -153,7 +151,6 @@ impl<'a> System<'a> for FalconAiSystem {
         ReadStorage<'a, LocalPlayerControllerComponent>,
         WriteExpect<'a, SystemFrameDurations>,
         WriteExpect<'a, PhysicEngine>,
-        WriteExpect<'a, SystemVariables>,
         ReadExpect<'a, EngineTime>,
     );
 
@@ -169,7 +166,6 @@ impl<'a> System<'a> for FalconAiSystem {
             desktop_storage,
             mut system_benchmark,
             mut physics_world,
-            mut sys_vars,
             time,
         ): Self::SystemData,
     ) {
@@ -234,18 +230,6 @@ impl<'a> System<'a> for FalconAiSystem {
                     } else if duration_percentage < 1.0 {
                         if !target_is_caught {
                             sprite.action_index = CharActionIndex::Idle as usize;
-                            sys_vars
-                                .apply_statuses
-                                .push(ApplyStatusComponent::from_status(
-                                    falcon.owner_entity_id,
-                                    falcon.owner_entity_id,
-                                    StatusEnum::FalconCarryStatus(FalconCarryStatus {
-                                        started_at,
-                                        ends_at,
-                                        carry_owner: true,
-                                        end_pos: Vector2::zeros(),
-                                    }),
-                                ));
                             falcon.state = FalconState::CarryOwner {
                                 owner_controller_id,
                                 started_at,
@@ -362,18 +346,6 @@ impl<'a> System<'a> for FalconAiSystem {
                                         end_pos.y,
                                     ),
                                 };
-                                sys_vars
-                                    .apply_statuses
-                                    .push(ApplyStatusComponent::from_status(
-                                        falcon.owner_entity_id,
-                                        target_id,
-                                        StatusEnum::FalconCarryStatus(FalconCarryStatus {
-                                            started_at,
-                                            ends_at,
-                                            carry_owner: false,
-                                            end_pos,
-                                        }),
-                                    ));
                                 falcon.state = FalconState::CarryAlly {
                                     target_id,
                                     start_pos,
@@ -0,0 +1,79 @@
+use crate::components::char::CharacterStateComponent;
+use crate::components::controller::LocalPlayerControllerComponent;
+use crate::configs::DevConfig;
+use crate::systems::SystemFrameDurations;
+use rustarok_common::common::Vec2;
+use rustarok_common::components::char::{AuthorizedCharStateComponent, CharType, Team};
+use rustarok_common::components::controller::ControllerComponent;
+use rustarok_common::config::get_sight_radius;
+use rustarok_common::grf::gat::Gat;
+use rustarok_common::vision::TeamVision;
+use specs::prelude::*;
+
+/// Rebuilds the vision of the local player's team. The enemies outside of it are not rendered
+/// and the ground is darkened where the team does not see.
+/// The server stops sending the enemies which the team can't see, without hiding them they
+/// would stand still at their last known positions.
+pub struct FogOfWarSystem;
+
+impl FogOfWarSystem {
+    /// The enemies outside of the vision of the viewer's team, the structures are always known
+    pub fn is_hidden(
+        vision: &TeamVision,
+        viewer_team: Team,
+        char_state: &CharacterStateComponent,
+        pos: &Vec2,
+    ) -> bool {
+        return char_state.team != viewer_team
+            && char_state.typ != CharType::Structure
+            && !vision.is_visible(pos);
+    }
+}
+
+impl<'a> System<'a> for FogOfWarSystem {
+    type SystemData = (
+        ReadStorage<'a, CharacterStateComponent>,
+        ReadStorage<'a, AuthorizedCharStateComponent>,
+        ReadStorage<'a, ControllerComponent>,
+        ReadStorage<'a, LocalPlayerControllerComponent>,
+        ReadExpect<'a, Gat>,
+        ReadExpect<'a, DevConfig>,
+        WriteExpect<'a, TeamVision>,
+        WriteExpect<'a, SystemFrameDurations>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            char_state_storage,
+            auth_char_state_storage,
+            controller_storage,
+            local_player_storage,
+            gat,
+            dev_configs,
+            mut vision,
+            mut system_benchmark,
+        ): Self::SystemData,
+    ) {
+        let _stopwatch = system_benchmark.start_measurement("FogOfWarSystem");
+        let local_team = (&controller_storage, &local_player_storage)
+            .join()
+            .next()
+            .and_then(|(controller, _local_player)| {
+                char_state_storage.get(controller.controlled_entity.into())
+            })
+            .map(|char_state| char_state.team);
+        let local_team = match local_team {
+            Some(team) => team,
+            None => return,
+        };
+        vision.clear();
+        for (char_state, auth_state) in (&char_state_storage, &auth_char_state_storage).join() {
+            if char_state.team != local_team || auth_state.state().is_dead() {
+                continue;
+            }
+            let sight_radius = get_sight_radius(char_state.job_id, &dev_configs.stats);
+            vision.reveal(&gat, &auth_state.pos(), sight_radius);
+        }
+    }
+}
@@ -254,11 +254,9 @@ impl<'a> System<'a> for InputConsumerSystem {
 impl InputConsumerSystem {
     pub fn target_selection_or_casting(skill: Skills, mouse_pos: Vec2) -> Option<PlayerIntention> {
         // NoTarget skills have to be casted immediately without selecting target
-        if skill.get_definition().get_skill_target_type() == SkillTargetType::NoTarget {
+        if skill.get_skill_target_type() == SkillTargetType::NoTarget {
             log::debug!("Skill '{:?}' is no target, so cast it", skill);
-            // TODO2
-            //Some(PlayerIntention::Casting(skill, false, mouse_pos))
-            None
+            Some(PlayerIntention::Casting(skill, false, mouse_pos))
         } else {
             None
         }
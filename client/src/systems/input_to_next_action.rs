@@ -149,17 +149,9 @@ impl<'a> System<'a> for InputToNextActionSystem {
 
             // in console mode, only moving around is allowed
             if input.is_console_open {
-                if let Some(next_action) = &controller.intention {
-                    match next_action {
-                        PlayerIntention::MoveTo(_) => {}
-                        PlayerIntention::MoveTowardsMouse(_) => {}
-                        PlayerIntention::Attack(_) => {}
-                        PlayerIntention::AttackTowards(_) => {} // TODO2
-                                                                //                        PlayerIntention::Casting(_, _, _) => {
-                                                                //                            log::debug!("...but the console is open");
-                                                                //                            controller.next_action = None;
-                                                                //                        }
-                    }
+                if let Some(PlayerIntention::Casting(..)) = controller.intention {
+                    log::debug!("...but the console is open");
+                    controller.intention = None;
                 }
             }
         }
@@ -185,7 +177,7 @@ impl InputToNextActionSystem {
                 .has_already_passed(now);
             if !is_castable {
                 (CURSOR_STOP, [255, 255, 255])
-            } else if skill.get_definition().get_skill_target_type() != SkillTargetType::Area {
+            } else if skill.get_skill_target_type() != SkillTargetType::Area {
                 (CURSOR_TARGET, [255, 255, 255])
             } else {
                 (CURSOR_CLICK, [255, 255, 255])
@@ -241,13 +233,11 @@ impl InputToNextActionSystem {
                         if input.left_mouse_released {
                             log::debug!("Player wants to cast {:?}", skill);
                             desktop.select_skill_target = None;
-                            // TODO2
-                            //                                Some(PlayerIntention::Casting(
-                            //                                    skill,
-                            //                                    false,
-                            //                                    input.mouse_world_pos,
-                            //                                ))
-                            None
+                            Some(PlayerIntention::Casting(
+                                skill,
+                                false,
+                                input.mouse_world_pos,
+                            ))
                         } else if input.right_mouse_pressed
                             || input.is_key_just_pressed(Scancode::Escape)
                         {
@@ -280,17 +270,14 @@ impl InputToNextActionSystem {
                     CastMode::OnKeyRelease => {
                         if input.is_key_just_released(casting_skill_key.scancode()) {
                             log::debug!("Player wants to cast {:?}", skill);
-                            // TODO2
                             desktop.select_skill_target = None;
-                            //                                Some(
-                            //                                    PlayerIntention::Casting(
-                            //                                        input.get_skill_for_key(casting_skill_key)
-                            //                                            .expect("'is_casting_selection' must be Some only if the casting skill is valid! "),
-                            //                                        false,
-                            //                                        input.mouse_world_pos,
-                            //                                    )
-                            //                                )
-                            None
+                            Some(PlayerIntention::Casting(
+                                input.get_skill_for_key(casting_skill_key).expect(
+                                    "'is_casting_selection' must be Some only if the casting skill is valid! ",
+                                ),
+                                false,
+                                input.mouse_world_pos,
+                            ))
                         } else if input.right_mouse_pressed
                             || input.is_key_just_pressed(Scancode::Escape)
                         {
@@ -337,13 +324,11 @@ impl InputToNextActionSystem {
                 CastMode::OnKeyPress => {
                     log::debug!("Player wants to cast {:?}, alt={:?}", skill, alt_down);
                     desktop.select_skill_target = None;
-                    // TODO2
-                    //                        Some(PlayerIntention::Casting(
-                    //                            skill,
-                    //                            alt_down,
-                    //                            input.mouse_world_pos,
-                    //                        ))
-                    None
+                    Some(PlayerIntention::Casting(
+                        skill,
+                        alt_down,
+                        input.mouse_world_pos,
+                    ))
                 }
             }
         } else if let Some((_skill_key, skill)) = just_released_skill_key.and_then(|skill_key| {
@@ -1,4 +1,5 @@
 use crate::components::controller::LocalPlayerControllerComponent;
+use crate::configs::DevConfig;
 use crate::systems::snapshot_sys::GameSnapshots;
 use rustarok_common::common::EngineTime;
 use rustarok_common::components::controller::ControllerComponent;
@@ -24,11 +25,12 @@ impl<'a> System<'a> for IntentionSenderSystem {
         ReadExpect<'a, EngineTime>,
         WriteExpect<'a, Vec<ToServerPacket>>,
         WriteExpect<'a, GameSnapshots>,
+        ReadExpect<'a, DevConfig>,
     );
 
     fn run(
         &mut self,
-        (mut controller_storage, time, mut to_server, mut snapshots): Self::SystemData,
+        (mut controller_storage, time, mut to_server, mut snapshots, dev_configs): Self::SystemData,
     ) {
         let ok = time.tick % 3 == 0;
         for (controller) in (&controller_storage).join() {
@@ -40,6 +42,7 @@ impl<'a> System<'a> for IntentionSenderSystem {
                     to_server.push(ToServerPacket::Intention {
                         cid: self.cid,
                         client_tick: time.tick,
+                        interpolation_delay_ms: dev_configs.network.interpolation_delay_ms,
                         intention: intention.clone(),
                     });
                 }
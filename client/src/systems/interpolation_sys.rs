@@ -0,0 +1,196 @@
+use crate::configs::DevConfig;
+use rustarok_common::common::{Vec2, MAX_SECONDS_ALLOWED_FOR_SINGLE_FRAME};
+use rustarok_common::components::char::{AuthorizedCharStateComponent, CharDir, CharEntityId};
+use specs::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Clone, Copy, Debug)]
+struct InterpolationSample {
+    server_tick: u64,
+    pos: Vec2,
+    dir: CharDir,
+}
+
+/// The states of the remote chars received from the server. They are rendered
+/// interpolation_delay_ms behind the newest state, so there are usually two states to blend between.
+pub struct RemoteCharInterpolation {
+    buffers: HashMap<CharEntityId, VecDeque<InterpolationSample>>,
+    newest_server_tick: Option<u64>,
+    // in server ticks, it advances one tick per frame
+    render_tick: f32,
+}
+
+impl RemoteCharInterpolation {
+    const MAX_SAMPLES_PER_CHAR: usize = 32;
+
+    pub fn new() -> RemoteCharInterpolation {
+        RemoteCharInterpolation {
+            buffers: HashMap::with_capacity(128),
+            newest_server_tick: None,
+            render_tick: 0.0,
+        }
+    }
+
+    pub fn push(&mut self, char_id: CharEntityId, server_tick: u64, pos: Vec2, dir: CharDir) {
+        let buffer = self.buffers.entry(char_id).or_insert_with(|| {
+            VecDeque::with_capacity(RemoteCharInterpolation::MAX_SAMPLES_PER_CHAR)
+        });
+        // out of order Acks are dropped by the transport, but duplicates might arrive
+        if buffer
+            .back()
+            .map(|it| it.server_tick >= server_tick)
+            .unwrap_or(false)
+        {
+            return;
+        }
+        if buffer.len() == RemoteCharInterpolation::MAX_SAMPLES_PER_CHAR {
+            buffer.pop_front();
+        }
+        buffer.push_back(InterpolationSample {
+            server_tick,
+            pos,
+            dir,
+        });
+        if self
+            .newest_server_tick
+            .map(|it| it < server_tick)
+            .unwrap_or(true)
+        {
+            self.newest_server_tick = Some(server_tick);
+        }
+    }
+
+    pub fn remove(&mut self, char_id: CharEntityId) {
+        self.buffers.remove(&char_id);
+    }
+
+    fn advance_render_tick(&mut self, delay_ticks: f32) {
+        let newest = match self.newest_server_tick {
+            Some(newest) => newest as f32,
+            None => return,
+        };
+        let target = newest - delay_ticks;
+        self.render_tick += 1.0;
+        let drift = self.render_tick - target;
+        if drift.abs() > delay_ticks.max(1.0) * 2.0 {
+            // e.g. the first state or a long freeze, catching up smoothly would take too long
+            self.render_tick = target;
+        } else {
+            // the two clocks run at the same rate, only the jitter has to be absorbed
+            self.render_tick -= drift * 0.1;
+        }
+    }
+
+    /// None if there is no state for the char
+    fn sample_at(
+        buffer: &VecDeque<InterpolationSample>,
+        render_tick: f32,
+        max_extrapolation_ticks: f32,
+    ) -> Option<(Vec2, CharDir)> {
+        let newest = buffer.back()?;
+        let oldest = buffer.front()?;
+        if render_tick <= oldest.server_tick as f32 {
+            return Some((oldest.pos, oldest.dir));
+        }
+        if render_tick >= newest.server_tick as f32 {
+            // the packets are late, continue the last movement for a while
+            let prev = match buffer.len() {
+                0 | 1 => return Some((newest.pos, newest.dir)),
+                len => &buffer[len - 2],
+            };
+            let elapsed_ticks = (newest.server_tick - prev.server_tick) as f32;
+            let velocity = (newest.pos - prev.pos) / elapsed_ticks;
+            let ahead = (render_tick - newest.server_tick as f32).min(max_extrapolation_ticks);
+            return Some((newest.pos + velocity * ahead, newest.dir));
+        }
+        let to_index = buffer
+            .iter()
+            .position(|it| it.server_tick as f32 > render_tick)
+            .unwrap_or(buffer.len() - 1);
+        let from = &buffer[to_index - 1];
+        let to = &buffer[to_index];
+        let t =
+            (render_tick - from.server_tick as f32) / (to.server_tick - from.server_tick) as f32;
+        let pos = from.pos + (to.pos - from.pos) * t;
+        // directions can't be blended, the closer one is used
+        let dir = if t < 0.5 { from.dir } else { to.dir };
+        return Some((pos, dir));
+    }
+}
+
+pub struct RemoteCharInterpolationSystem;
+
+impl<'a> System<'a> for RemoteCharInterpolationSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, AuthorizedCharStateComponent>,
+        WriteExpect<'a, RemoteCharInterpolation>,
+        ReadExpect<'a, DevConfig>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut auth_char_state_storage, mut interpolation, dev_configs): Self::SystemData,
+    ) {
+        let ms_to_ticks = |ms: u32| ms as f32 / 1000.0 / MAX_SECONDS_ALLOWED_FOR_SINGLE_FRAME;
+        let delay_ticks = ms_to_ticks(dev_configs.network.interpolation_delay_ms);
+        let max_extrapolation_ticks = ms_to_ticks(dev_configs.network.max_extrapolation_ms);
+        interpolation.advance_render_tick(delay_ticks);
+
+        let render_tick = interpolation.render_tick;
+        // the removed entities are forgotten
+        interpolation
+            .buffers
+            .retain(|char_id, _buffer| entities.is_alive((*char_id).into()));
+        for (char_id, buffer) in &interpolation.buffers {
+            let auth_state = match auth_char_state_storage.get_mut((*char_id).into()) {
+                Some(auth_state) => auth_state,
+                None => continue,
+            };
+            if let Some((pos, dir)) =
+                RemoteCharInterpolation::sample_at(buffer, render_tick, max_extrapolation_ticks)
+            {
+                auth_state.set_pos(pos);
+                auth_state.set_dir(dir);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustarok_common::common::v2;
+
+    fn buffer() -> VecDeque<InterpolationSample> {
+        let mut buffer = VecDeque::new();
+        buffer.push_back(InterpolationSample {
+            server_tick: 10,
+            pos: v2(0.0, 0.0),
+            dir: CharDir::East,
+        });
+        buffer.push_back(InterpolationSample {
+            server_tick: 12,
+            pos: v2(2.0, 0.0),
+            dir: CharDir::North,
+        });
+        buffer
+    }
+
+    #[test]
+    fn positions_are_blended() {
+        let (pos, dir) = RemoteCharInterpolation::sample_at(&buffer(), 11.5, 0.0).unwrap();
+        assert!((pos.x - 1.5).abs() < 0.0001);
+        assert_eq!(dir, CharDir::North);
+        let (_pos, dir) = RemoteCharInterpolation::sample_at(&buffer(), 10.5, 0.0).unwrap();
+        assert_eq!(dir, CharDir::East);
+    }
+
+    #[test]
+    fn extrapolation_is_limited() {
+        let (pos, _dir) = RemoteCharInterpolation::sample_at(&buffer(), 13.0, 3.0).unwrap();
+        assert!((pos.x - 3.0).abs() < 0.0001);
+        let (pos, _dir) = RemoteCharInterpolation::sample_at(&buffer(), 20.0, 3.0).unwrap();
+        assert!((pos.x - 5.0).abs() < 0.0001);
+    }
+}
@@ -2,25 +2,37 @@ use crate::components::char::CharacterStateComponent;
 use crate::components::controller::LocalPlayerControllerComponent;
 use crate::components::MinionComponent;
 use crate::systems::SystemFrameDurations;
-use rustarok_common::common::{v2, v2_to_p2, Vec2};
+use rustarok_common::common::{measure_time, v2_to_p2, Vec2};
 use rustarok_common::components::char::{
     AuthorizedCharStateComponent, CharEntityId, ControllerEntityId, EntityTarget, Team,
 };
 use rustarok_common::components::controller::{ControllerComponent, PlayerIntention};
+use rustarok_common::flow_field::{self, FlowField};
+use rustarok_common::grf::gat::Gat;
+use rustarok_common::scenario::{Lane, Scenario};
 use specs::prelude::*;
+use std::collections::HashMap;
 
-pub struct MinionAiSystem;
+pub struct MinionAiSystem {
+    /// Towards the waypoints of the lanes by (lane index, waypoint index), computed when the
+    /// first minion needs it. None if the waypoint is outside of the map.
+    flow_fields: HashMap<(usize, usize), Option<FlowField>>,
+}
 
 impl MinionAiSystem {
-    // from left to right
-    pub const CHECKPOINTS: [[i32; 2]; 6] = [
-        [245, -204], // right gate
-        [175, -204], // right side of fountain
-        [156, -220], // top of fountain
-        [156, -188], // bottom of fountain
-        [136, -204], // left side of fountain
-        [64, -204],  // left gate
-    ];
+    /// The minions closer to each other than this are pushed apart
+    const SEPARATION_RADIUS: f32 = 1.0;
+    const SEPARATION_WEIGHT: f32 = 1.5;
+    /// How far ahead the minions aim along the flow field
+    const STEERING_DISTANCE: f32 = 1.5;
+    /// The minions head to the next waypoint of the lane when they are this close to the current
+    const WAYPOINT_REACHED_DISTANCE: f32 = 2.0;
+
+    pub fn new() -> MinionAiSystem {
+        MinionAiSystem {
+            flow_fields: HashMap::with_capacity(16),
+        }
+    }
 
     pub fn get_closest_enemy_in_area(
         entities: &Entities,
@@ -54,6 +66,117 @@ impl MinionAiSystem {
         }
         return ret;
     }
+
+    fn flow_field(
+        &mut self,
+        gat: &Gat,
+        lane_index: usize,
+        waypoint_index: usize,
+        waypoint: &Vec2,
+    ) -> Option<&FlowField> {
+        return self
+            .flow_fields
+            .entry((lane_index, waypoint_index))
+            .or_insert_with(|| {
+                let (elapsed, flow_field) = measure_time(|| FlowField::new(gat, waypoint));
+                if flow_field.is_some() {
+                    log::info!(
+                        "flow field of waypoint {} of lane {}: {}ms",
+                        waypoint_index,
+                        lane_index,
+                        elapsed.as_millis()
+                    );
+                } else {
+                    log::warn!(
+                        "Waypoint {} of lane {} is outside of the map",
+                        waypoint_index,
+                        lane_index
+                    );
+                }
+                flow_field
+            })
+            .as_ref();
+    }
+
+    /// None if the end of the lane was reached or the next waypoint is unreachable
+    fn walk_along_lane(
+        &mut self,
+        gat: Option<&Gat>,
+        lane_index: usize,
+        lane: &Lane,
+        minion: &mut MinionComponent,
+        team: Team,
+        pos: &Vec2,
+        char_positions_by_cell: &HashMap<(i32, i32), Vec<Vec2>>,
+    ) -> Option<PlayerIntention> {
+        minion.next_waypoint = minion.next_waypoint.min(lane.waypoints.len() - 1);
+        while minion.next_waypoint + 1 < lane.waypoints.len() {
+            let waypoint = lane.waypoints[lane.waypoint_index(team, minion.next_waypoint)?];
+            if (waypoint - pos).magnitude() > MinionAiSystem::WAYPOINT_REACHED_DISTANCE {
+                break;
+            }
+            minion.next_waypoint += 1;
+        }
+        let waypoint_index = lane.waypoint_index(team, minion.next_waypoint)?;
+        let waypoint = lane.waypoints[waypoint_index];
+        let gat = match gat {
+            Some(gat) => gat,
+            None => {
+                return Some(PlayerIntention::MoveTo(waypoint));
+            }
+        };
+        let flow_dir = self
+            .flow_field(gat, lane_index, waypoint_index, &waypoint)?
+            .direction_at(pos)?;
+        let push = flow_field::separation(
+            pos,
+            close_char_positions(char_positions_by_cell, pos),
+            MinionAiSystem::SEPARATION_RADIUS,
+        );
+        let dir = flow_dir + push * MinionAiSystem::SEPARATION_WEIGHT;
+        let dir = if dir.magnitude() > 0.01 {
+            dir.normalize()
+        } else {
+            flow_dir
+        };
+        // the flow field already leads around the walls, planning a path in every tick is not
+        // needed
+        return Some(PlayerIntention::Steer(
+            pos + dir * MinionAiSystem::STEERING_DISTANCE,
+        ));
+    }
+}
+
+/// The positions of the characters by the cells they are standing on, so only the close ones
+/// have to be checked for separation
+fn char_positions_by_cell(
+    auth_char_state_storage: &ReadStorage<AuthorizedCharStateComponent>,
+) -> HashMap<(i32, i32), Vec<Vec2>> {
+    let mut cells: HashMap<(i32, i32), Vec<Vec2>> = HashMap::with_capacity(256);
+    for auth_char_state in auth_char_state_storage.join() {
+        if auth_char_state.state().is_dead() {
+            continue;
+        }
+        let pos = auth_char_state.pos();
+        cells.entry(cell_key(&pos)).or_default().push(pos);
+    }
+    return cells;
+}
+
+fn cell_key(pos: &Vec2) -> (i32, i32) {
+    (pos.x.floor() as i32, (-pos.y).floor() as i32)
+}
+
+/// The characters in the cell of `pos` and around it
+fn close_char_positions<'a>(
+    char_positions_by_cell: &'a HashMap<(i32, i32), Vec<Vec2>>,
+    pos: &Vec2,
+) -> impl Iterator<Item = &'a Vec2> {
+    let (x, y) = cell_key(pos);
+    return (-1..=1)
+        .flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+        .filter_map(move |cell| char_positions_by_cell.get(&cell))
+        .flatten();
 }
 
 impl<'a> System<'a> for MinionAiSystem {
@@ -62,8 +185,10 @@ impl<'a> System<'a> for MinionAiSystem {
         WriteStorage<'a, ControllerComponent>,
         ReadStorage<'a, CharacterStateComponent>,
         ReadStorage<'a, AuthorizedCharStateComponent>,
-        ReadStorage<'a, MinionComponent>,
+        WriteStorage<'a, MinionComponent>,
         WriteExpect<'a, SystemFrameDurations>,
+        Option<Read<'a, Gat>>,
+        ReadExpect<'a, Scenario>,
     );
 
     fn run(
@@ -73,13 +198,16 @@ impl<'a> System<'a> for MinionAiSystem {
             mut controller_storage,
             char_state_storage,
             auth_char_state_storage,
-            minion_storage,
+            mut minion_storage,
             mut system_benchmark,
+            gat,
+            scenario,
         ): Self::SystemData,
     ) {
         let _stopwatch = system_benchmark.start_measurement("MinionAiSystem");
-        for (controller_id, controller, _minion) in
-            (&entities, &mut controller_storage, &minion_storage).join()
+        let char_positions_by_cell = char_positions_by_cell(&auth_char_state_storage);
+        for (controller_id, controller, minion) in
+            (&entities, &mut controller_storage, &mut minion_storage).join()
         {
             let controller_id = ControllerEntityId::new(controller_id);
             let char_state = char_state_storage.get(controller.controlled_entity.into());
@@ -121,31 +249,18 @@ impl<'a> System<'a> for MinionAiSystem {
                     );
                     match maybe_enemy {
                         Some(target_id) => Some(PlayerIntention::Attack(target_id)),
-                        None => {
-                            let next_checkpoint = if char_state.team == Team::Right {
-                                let mut next_checkpoint = MinionAiSystem::CHECKPOINTS[5];
-                                for checkpoint in MinionAiSystem::CHECKPOINTS.iter() {
-                                    if checkpoint[0] < auth_char_state.pos().x as i32 {
-                                        next_checkpoint = *checkpoint;
-                                        break;
-                                    }
-                                }
-                                next_checkpoint
-                            } else {
-                                let mut next_checkpoint = MinionAiSystem::CHECKPOINTS[0];
-                                for checkpoint in MinionAiSystem::CHECKPOINTS.iter().rev() {
-                                    if checkpoint[0] > auth_char_state.pos().x as i32 {
-                                        next_checkpoint = *checkpoint;
-                                        break;
-                                    }
-                                }
-                                next_checkpoint
-                            };
-                            Some(PlayerIntention::MoveTo(v2(
-                                next_checkpoint[0] as f32,
-                                next_checkpoint[1] as f32,
-                            )))
-                        }
+                        None => match scenario.lanes.get(minion.lane) {
+                            Some(lane) => self.walk_along_lane(
+                                gat.as_ref().map(|gat| &**gat),
+                                minion.lane,
+                                lane,
+                                minion,
+                                char_state.team,
+                                &auth_char_state.pos(),
+                                &char_positions_by_cell,
+                            ),
+                            None => None,
+                        },
                     }
                 } else {
                     Some(PlayerIntention::Attack(current_target_id.unwrap()))
@@ -1,11 +1,6 @@
 use crate::components::char::ClientCharState;
 use crate::components::skills::skills::{FinishCast, Skills};
-use crate::components::status::status::{
-    ApplyStatusComponent, ApplyStatusInAreaComponent, RemoveStatusComponent,
-};
-use crate::components::{
-    ApplyForceComponent, AreaAttackComponent, HpModificationRequest, HpModificationResult,
-};
+use crate::components::HpModificationResult;
 use crate::consts::PLAYABLE_CHAR_SPRITES;
 use crate::grf::str::StrFile;
 use crate::grf::texture::{TextureId, DUMMY_TEXTURE_ID_FOR_TEST};
@@ -29,21 +24,23 @@ pub mod camera_system;
 pub mod console_commands;
 pub mod console_system;
 pub mod falcon_ai_sys;
+pub mod fog_of_war_sys;
 pub mod frame_cleanup_system;
 pub mod frame_client_end_system;
 pub mod input_sys;
 pub mod input_sys_scancodes;
 pub mod input_to_next_action;
 pub mod intention_sender_sys;
+pub mod interpolation_sys;
 pub mod minion_ai_sys;
 pub mod next_action_applier_sys;
 pub mod phys;
-pub mod skill_sys;
 pub mod snapshot_sys;
 pub mod spawn_entity_system;
 pub mod turret_ai_sys;
 pub mod ui;
 
+#[derive(Serialize, Deserialize)]
 pub struct EffectSprites {
     pub torch: SpriteResource,
     pub fire_wall: SpriteResource,
@@ -51,6 +48,7 @@ pub struct EffectSprites {
     pub plasma: SpriteResource,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Sprites {
     pub cursors: SpriteResource,
     pub numbers: TextureId,
@@ -72,6 +70,36 @@ pub struct Sprites {
 }
 
 impl Sprites {
+    /// Every sprite resource, the ones which were cloned from each other are visited separately
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut SpriteResource> {
+        vec![
+            &mut self.cursors,
+            &mut self.ginseng_bullet,
+            &mut self.exoskeleton,
+            &mut self.arrow,
+            &mut self.falcon,
+            &mut self.stun,
+            &mut self.timefont,
+            &mut self.effect_sprites.torch,
+            &mut self.effect_sprites.fire_wall,
+            &mut self.effect_sprites.fire_ball,
+            &mut self.effect_sprites.plasma,
+        ]
+        .into_iter()
+        .chain(
+            self.character_sprites
+                .values_mut()
+                .flat_map(|it| it.iter_mut().flat_map(|it| it.iter_mut())),
+        )
+        .chain(
+            self.mounted_character_sprites
+                .values_mut()
+                .flat_map(|it| it.iter_mut()),
+        )
+        .chain(self.head_sprites.iter_mut().flat_map(|it| it.iter_mut()))
+        .chain(self.monster_sprites.values_mut())
+    }
+
     pub fn new_for_test() -> Sprites {
         Sprites {
             cursors: SpriteResource::new_for_test(),
@@ -128,6 +156,8 @@ pub struct AssetResources {
     pub status_icons: HashMap<&'static str, TextureId>,
     pub sounds: Sounds,
     pub str_effects: Vec<StrFile>,
+    /// Incremented when an effect is reloaded, so the renderer drops its cached frames
+    pub str_effects_revision: usize,
 }
 
 pub struct RenderMatrices {
@@ -174,13 +204,7 @@ pub enum SystemEvent {
 pub struct SystemVariables {
     pub assets: AssetResources,
     pub matrices: RenderMatrices,
-    pub hp_mod_requests: Vec<HpModificationRequest>,
-    pub area_hp_mod_requests: Vec<AreaAttackComponent>,
-    pub pushes: Vec<ApplyForceComponent>,
-    pub apply_statuses: Vec<ApplyStatusComponent>,
     pub just_finished_skill_casts: Vec<FinishCast>,
-    pub apply_area_statuses: Vec<ApplyStatusInAreaComponent>,
-    pub remove_statuses: Vec<RemoveStatusComponent>,
 }
 
 impl SystemVariables {
@@ -204,15 +228,10 @@ impl SystemVariables {
                 status_icons,
                 sounds,
                 str_effects,
+                str_effects_revision: 0,
             },
             matrices: render_matrices,
-            hp_mod_requests: Vec::with_capacity(128),
-            area_hp_mod_requests: Vec::with_capacity(128),
-            pushes: Vec::with_capacity(128),
-            apply_statuses: Vec::with_capacity(128),
             just_finished_skill_casts: Vec::with_capacity(128),
-            apply_area_statuses: Vec::with_capacity(128),
-            remove_statuses: Vec::with_capacity(128),
         }
     }
 }
@@ -1,5 +1,5 @@
 use crate::components::char::{
-    get_sprite_and_action_index, CastingSkillData, CharacterStateComponent, ClientCharState,
+    get_sprite_and_action_index, CharacterStateComponent, ClientCharState,
     SpriteRenderDescriptorComponent,
 };
 use crate::components::controller::{EntitiesBelowCursor, LocalPlayerControllerComponent};
@@ -9,7 +9,7 @@ use crate::systems::{SystemEvent, SystemFrameDurations, SystemVariables};
 use crate::ElapsedTime;
 use rustarok_common::common::{EngineTime, Vec2};
 use rustarok_common::components::char::{
-    AuthorizedCharStateComponent, CharEntityId, CharState, EntityTarget,
+    AuthorizedCharStateComponent, CastingSkillData, CharEntityId, CharState, EntityTarget,
 };
 use rustarok_common::components::controller::PlayerIntention;
 use specs::prelude::*;
@@ -1,19 +1,20 @@
+use std::collections::{HashMap, HashSet};
 use std::ops::RangeInclusive;
 
 use specs::prelude::*;
 
-use rustarok_common::common::{float_cmp, v2, EngineTime, Vec2};
+use rustarok_common::common::{float_cmp, v2, Vec2};
 use rustarok_common::components::char::{
-    AuthorizedCharStateComponent, CharDir, CharState, ControllerEntityId,
+    AuthorizedCharStateComponent, CharDir, CharEntityId, CharState, ServerEntityId,
+};
+use rustarok_common::components::controller::PlayerIntention;
+use rustarok_common::components::snapshot::{
+    decode_world_snapshot, CharSnapshot, SnapshotHistory, WorldSnapshot,
 };
-use rustarok_common::components::controller::{ControllerComponent, PlayerIntention};
-use rustarok_common::components::snapshot::{CharSnapshot, WorldSnapshot};
 use rustarok_common::packets::from_server::{AckEntry, FromServerPacket};
 use rustarok_common::packets::to_server::ToServerPacket;
 use rustarok_common::packets::{PacketHandlerThread, SocketId};
 
-use crate::components::controller::LocalPlayerControllerComponent;
-
 pub struct GameSnapshots {
     client_last_command_id: u32,
     // TODO: do we need u64?
@@ -23,6 +24,13 @@ pub struct GameSnapshots {
     // last_predicted_index + 1
     snapshots: [CWorldSnapshot; GameSnapshots::SNAPSHOT_COUNT],
     intentions: [(u32, Option<PlayerIntention>); GameSnapshots::SNAPSHOT_COUNT],
+    desktop_id: Option<ServerEntityId>,
+    local_ids: HashMap<ServerEntityId, CharEntityId>,
+    server_ids: HashMap<CharEntityId, ServerEntityId>,
+    // their positions come from RemoteCharInterpolation, so they differ from the acked ones
+    interpolated_ids: HashSet<ServerEntityId>,
+    // the decoded snapshots from the server, the deltas are applied on them
+    server_snapshots: SnapshotHistory,
 }
 
 #[derive(Default)]
@@ -45,6 +53,11 @@ impl GameSnapshots {
             last_acknowledged_index: 0,
             last_acknowledged_tick: 0,
             tail: 1,
+            desktop_id: None,
+            local_ids: HashMap::with_capacity(128),
+            server_ids: HashMap::with_capacity(128),
+            interpolated_ids: HashSet::with_capacity(128),
+            server_snapshots: SnapshotHistory::new(),
             intentions: unsafe {
                 let mut arr: [(u32, Option<PlayerIntention>); GameSnapshots::SNAPSHOT_COUNT] =
                     std::mem::MaybeUninit::zeroed().assume_init();
@@ -64,6 +77,32 @@ impl GameSnapshots {
         }
     }
 
+    pub fn register_entity(&mut self, server_id: ServerEntityId, local_id: CharEntityId) {
+        self.local_ids.insert(server_id, local_id);
+        self.server_ids.insert(local_id, server_id);
+    }
+
+    /// Returns the local id of the entity which has been removed on the server
+    pub fn unregister_entity(&mut self, server_id: &ServerEntityId) -> Option<CharEntityId> {
+        self.interpolated_ids.remove(server_id);
+        let local_id = self.local_ids.remove(server_id)?;
+        self.server_ids.remove(&local_id);
+        return Some(local_id);
+    }
+
+    pub fn get_local_id(&self, server_id: &ServerEntityId) -> Option<CharEntityId> {
+        self.local_ids.get(server_id).cloned()
+    }
+
+    /// The entity is not predicted, so it is left out of the misprediction checks
+    pub fn mark_interpolated(&mut self, server_id: ServerEntityId) {
+        self.interpolated_ids.insert(server_id);
+    }
+
+    pub fn get_server_id(&self, local_id: &CharEntityId) -> Option<ServerEntityId> {
+        self.server_ids.get(local_id).cloned()
+    }
+
     pub fn set_client_last_command_id(&mut self, cid: u32) {
         self.client_last_command_id = dbg!(cid);
     }
@@ -107,21 +146,60 @@ impl GameSnapshots {
         return &mut self.snapshots[GameSnapshots::index(tick)];
     }
 
-    pub fn init(&mut self, entries: &[AckEntry]) {
-        let acked = match &entries[0] {
-            AckEntry::EntityState { id, char_snapshot } => char_snapshot,
+    pub fn init(&mut self, entries: &[AckEntry], desktop_char_id: CharEntityId) {
+        let acked =
+            decode_world_snapshot(None, entries).expect("The first Ack must not contain deltas");
+        match entries[0].entity_id() {
+            Some(id) => {
+                self.desktop_id = Some(id);
+                self.register_entity(id, desktop_char_id);
+            }
+            None => panic!("The first entry must be the state of the controlled char"),
         };
         self.last_acknowledged_index = 0;
         self.tail = 1;
         // so initial Ack packets can compare with something...
-        self.set_state(0, &acked.state);
-        self.set_state(1, &acked.state);
-        self.set_state(2, &acked.state);
-        self.set_state(3, &acked.state);
+        self.set_snapshot(0, &acked);
+        self.set_snapshot(1, &acked);
+        self.set_snapshot(2, &acked);
+        self.set_snapshot(3, &acked);
+    }
+
+    /// Reconstructs the state of the world from the delta encoded Ack entries.
+    /// Returns None if the baseline is not available anymore.
+    pub fn decode_ack(
+        &mut self,
+        ack_tick: u64,
+        baseline_tick: Option<u64>,
+        entries: &[AckEntry],
+    ) -> Option<WorldSnapshot> {
+        let baseline = match baseline_tick {
+            Some(baseline_tick) => Some(self.server_snapshots.get(baseline_tick)?),
+            None => None,
+        };
+        let world_snapshot = decode_world_snapshot(baseline, entries)?;
+        if let Some(baseline_tick) = baseline_tick {
+            // the server never goes back to an older baseline
+            self.server_snapshots.remove_older_than(baseline_tick);
+        }
+        self.server_snapshots.push(ack_tick, world_snapshot.clone());
+        return Some(world_snapshot);
+    }
+
+    fn set_snapshot(&mut self, tick: u64, acked: &WorldSnapshot) {
+        let snapshot = &mut self.get_mut_snapshot(tick).snapshot;
+        // the entities which have been removed on the server must not be predicted anymore
+        snapshot.chars.retain(|id, _| acked.chars.contains_key(id));
+        for (id, char_snapshot) in &acked.chars {
+            snapshot.chars.insert(*id, char_snapshot.clone());
+        }
     }
 
-    fn set_state(&mut self, tick: u64, state: &AuthorizedCharStateComponent) {
-        self.get_mut_snapshot(tick).snapshot.desktop_snapshot.state = state.clone();
+    fn desktop_x(&self, snapshot: &WorldSnapshot) -> f32 {
+        self.desktop_id
+            .and_then(|id| snapshot.get_char(id))
+            .map(|it| it.state.pos().x)
+            .unwrap_or(0.0)
     }
 
     pub fn ack_arrived(
@@ -129,12 +207,8 @@ impl GameSnapshots {
         client_tick: u64,
         acked_cid: u32,
         acked_tick: u64,
-        entries: &[AckEntry],
+        state_from_server: &WorldSnapshot,
     ) -> ServerAckResult {
-        // first entry is always state of self
-        let state_from_server = match &entries[0] {
-            AckEntry::EntityState { id, char_snapshot } => char_snapshot,
-        };
         //        debug h miért van még rollback
         //        limitáld le a input mintavételt 2-3 frame-re? h elkerüld azt h 1 klikk 3 commandot küldjön
         let last_unacknowledged_index = self.last_acknowledged_index + 1;
@@ -156,18 +230,14 @@ impl GameSnapshots {
         }
         let misprediction = if acked_cid < predicted_snapshot.cid {
             log::debug!(
-                "acked_cid < predicted_snapshot.cid: v2({}, {}), acked: v2({}, {})",
-                predicted_snapshot.snapshot.desktop_snapshot.state.pos().x,
-                predicted_snapshot.snapshot.desktop_snapshot.state.pos().y,
-                state_from_server.state.pos().x,
-                state_from_server.state.pos().y
+                "acked_cid < predicted_snapshot.cid: x: {}, acked x: {}",
+                self.desktop_x(&predicted_snapshot.snapshot),
+                self.desktop_x(state_from_server)
             );
             // The server did not get my command yet.
             // Check if my prediction was correct
-            let mut misprediction = !GameSnapshots::compare_snapshots(
-                &state_from_server,
-                &predicted_snapshot.snapshot.desktop_snapshot,
-            );
+            let mut misprediction =
+                !self.compare_snapshots(state_from_server, &predicted_snapshot.snapshot);
             if !misprediction {
                 self.last_acknowledged_index += 1;
             }
@@ -175,13 +245,13 @@ impl GameSnapshots {
             //                // Check if she still think where I should be if I haven't done anything
             //                let prev_snapshot = self.get_snapshot(self.last_acknowledged_index);
             //                log::debug!(
-            //                    "prev_predicted: v2({}, {}), acked: v2({}, {})",
+            //                    "prev_predicted: x: {}, acked x: {}",
             //                    prev_snapshot.snapshot.desktop_snapshot.state.pos().x,
             //                    prev_snapshot.snapshot.desktop_snapshot.state.pos().y,
             //                    state_from_server.state.pos().x,
             //                    state_from_server.state.pos().y
             //                );
-            //                misprediction = !GameSnapshots::compare_snapshots(
+            //                misprediction = !self.compare_snapshots(
             //                    &state_from_server,
             //                    &prev_snapshot.snapshot.desktop_snapshot,
             //                );
@@ -197,16 +267,12 @@ impl GameSnapshots {
         } else if acked_cid > predicted_snapshot.cid {
             // Client might have been too fast and assigned a smaller cid to a prediction than the server
             log::debug!(
-                "cur_predicted2: v2({}, {}), acked: v2({}, {})",
-                predicted_snapshot.snapshot.desktop_snapshot.state.pos().x,
-                predicted_snapshot.snapshot.desktop_snapshot.state.pos().y,
-                state_from_server.state.pos().x,
-                state_from_server.state.pos().y
-            );
-            let mut misprediction = !GameSnapshots::compare_snapshots(
-                &state_from_server,
-                &predicted_snapshot.snapshot.desktop_snapshot,
+                "cur_predicted2: x: {}, acked x: {}",
+                self.desktop_x(&predicted_snapshot.snapshot),
+                self.desktop_x(state_from_server)
             );
+            let mut misprediction =
+                !self.compare_snapshots(state_from_server, &predicted_snapshot.snapshot);
             if misprediction {
                 // Client might have been too fast and generated unnecessary predictions
                 for i in 1..=GameSnapshots::SNAPSHOT_COUNT as u64 {
@@ -214,16 +280,11 @@ impl GameSnapshots {
                     if acked_cid == pred.cid {
                         log::debug!("Found after {}", i);
                         log::debug!(
-                            "found_predicted: v2({}, {}), acked: v2({}, {})",
-                            pred.snapshot.desktop_snapshot.state.pos().x,
-                            pred.snapshot.desktop_snapshot.state.pos().y,
-                            state_from_server.state.pos().x,
-                            state_from_server.state.pos().y
-                        );
-                        misprediction = !GameSnapshots::compare_snapshots(
-                            &state_from_server,
-                            &pred.snapshot.desktop_snapshot,
+                            "found_predicted: x: {}, acked x: {}",
+                            self.desktop_x(&pred.snapshot),
+                            self.desktop_x(state_from_server)
                         );
+                        misprediction = !self.compare_snapshots(state_from_server, &pred.snapshot);
                         if misprediction {
                             self.last_acknowledged_index += 1;
                         } else {
@@ -239,16 +300,12 @@ impl GameSnapshots {
             misprediction
         } else {
             log::debug!(
-                "predicted_snapshot: v2({}, {}), acked: v2({}, {})",
-                predicted_snapshot.snapshot.desktop_snapshot.state.pos().x,
-                predicted_snapshot.snapshot.desktop_snapshot.state.pos().y,
-                state_from_server.state.pos().x,
-                state_from_server.state.pos().y
-            );
-            let misprediction = !GameSnapshots::compare_snapshots(
-                &state_from_server,
-                &predicted_snapshot.snapshot.desktop_snapshot,
+                "predicted_snapshot: x: {}, acked x: {}",
+                self.desktop_x(&predicted_snapshot.snapshot),
+                self.desktop_x(state_from_server)
             );
+            let misprediction =
+                !self.compare_snapshots(state_from_server, &predicted_snapshot.snapshot);
             self.last_acknowledged_index += 1;
             misprediction
         };
@@ -258,11 +315,14 @@ impl GameSnapshots {
             if client_tick > acked_tick {
                 self.print_snapshots(-2, repredict_this_many_frames);
             }
-            self.set_state(self.last_acknowledged_index, &state_from_server.state);
+            self.set_snapshot(self.last_acknowledged_index, state_from_server);
             ServerAckResult::Rollback {
                 repredict_this_many_frames,
             }
         } else {
+            // entities which were not predicted yet (e.g. they have just been created)
+            // are accepted as they are
+            self.set_snapshot(self.last_acknowledged_index, state_from_server);
             ServerAckResult::Ok
         };
         self.last_acknowledged_tick = acked_tick;
@@ -284,7 +344,7 @@ impl GameSnapshots {
         )
         .map(|it| {
             let snapshot = self.get_snapshot(it);
-            let x = snapshot.snapshot.desktop_snapshot.state.pos().x;
+            let x = self.desktop_x(&snapshot.snapshot);
             let (_cid, intention) = &self.intentions[GameSnapshots::index(it)];
             let cid = snapshot.cid;
             if self.last_acknowledged_index == it {
@@ -298,9 +358,24 @@ impl GameSnapshots {
         log::debug!("{}", text);
     }
 
-    fn compare_snapshots(acked: &CharSnapshot, predicted: &CharSnapshot) -> bool {
-        let result = float_cmp(acked.state.pos().x, predicted.state.pos().x);
-        &&float_cmp(acked.state.pos().y, predicted.state.pos().y);
+    /// Every predicted entity is compared, the interpolated ones are skipped
+    fn compare_snapshots(&self, acked: &WorldSnapshot, predicted: &WorldSnapshot) -> bool {
+        return acked
+            .chars
+            .iter()
+            .filter(|(id, _acked_char)| !self.interpolated_ids.contains(id))
+            .all(|(id, acked_char)| match predicted.get_char(*id) {
+                Some(predicted_char) => {
+                    GameSnapshots::compare_char_snapshots(acked_char, predicted_char)
+                }
+                // entities which are missing from the prediction can't be mispredicted
+                None => true,
+            });
+    }
+
+    fn compare_char_snapshots(acked: &CharSnapshot, predicted: &CharSnapshot) -> bool {
+        let result = float_cmp(acked.state.pos().x, predicted.state.pos().x)
+            && float_cmp(acked.state.pos().y, predicted.state.pos().y);
         //        if !result {
         //            log::debug!(
         //                "predicted: v2({}, {}), acked: v2({}, {})",
@@ -322,22 +397,22 @@ impl GameSnapshots {
     pub fn load_last_acked_state_into_world(
         &self,
         auth_storage: &mut WriteStorage<AuthorizedCharStateComponent>,
-        controller_storage: &ReadStorage<ControllerComponent>, // todo singleton
-        desktop_controller_id: ControllerEntityId,
     ) {
         let snapshot = self.get_snapshot(self.last_acknowledged_index);
-        let controller = controller_storage
-            .get(desktop_controller_id.into())
-            .unwrap();
-        let auth_state = auth_storage
-            .get_mut(controller.controlled_entity.into())
-            .unwrap();
-        auth_state.set_pos(snapshot.snapshot.desktop_snapshot.state.pos());
-        auth_state.set_state(
-            snapshot.snapshot.desktop_snapshot.state.state().clone(),
-            snapshot.snapshot.desktop_snapshot.state.dir(),
-        );
-        auth_state.target = snapshot.snapshot.desktop_snapshot.state.target.clone();
+        for (server_id, char_snapshot) in &snapshot.snapshot.chars {
+            let auth_state = self
+                .get_local_id(server_id)
+                .and_then(|local_id| auth_storage.get_mut(local_id.into()));
+            // the entity might have been removed since then
+            if let Some(auth_state) = auth_state {
+                auth_state.set_pos(char_snapshot.state.pos());
+                auth_state.set_state(
+                    char_snapshot.state.state().clone(),
+                    char_snapshot.state.dir(),
+                );
+                auth_state.target = char_snapshot.state.target.clone();
+            }
+        }
     }
 }
 
@@ -352,34 +427,70 @@ impl SnapshotSystem {
 
 impl<'a> System<'a> for SnapshotSystem {
     type SystemData = (
+        Entities<'a>,
         ReadStorage<'a, AuthorizedCharStateComponent>,
-        ReadStorage<'a, ControllerComponent>,
-        ReadStorage<'a, LocalPlayerControllerComponent>,
         WriteExpect<'a, GameSnapshots>,
-        ReadExpect<'a, EngineTime>,
     );
 
-    fn run(
-        &mut self,
-        (
-            auth_char_state_storage,
-            controller_storage,
-            desktop_storage,
-            mut snapshots,
-            time,
-        ): Self::SystemData,
-    ) {
-        for (controller, _desktop) in (&controller_storage, &desktop_storage).join() {
-            if let Some(desktop_char) =
-                auth_char_state_storage.get(controller.controlled_entity.into())
-            {
-                let snapshot = WorldSnapshot {
-                    desktop_snapshot: CharSnapshot {
-                        state: desktop_char.clone(),
-                    },
-                };
-                snapshots.add(snapshot);
+    fn run(&mut self, (entities, auth_char_state_storage, mut snapshots): Self::SystemData) {
+        let mut snapshot = WorldSnapshot::default();
+        for (entity_id, auth_char) in (&entities, &auth_char_state_storage).join() {
+            // only the replicated entities are part of the snapshot
+            if let Some(server_id) = snapshots.get_server_id(&CharEntityId::from(entity_id)) {
+                snapshot.set_char(server_id, auth_char);
             }
         }
+        snapshots.add(snapshot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_entities_are_dropped_from_the_acked_snapshot() {
+        let (player, minion) = (ServerEntityId::from_u64(1), ServerEntityId::from_u64(2));
+        let mut snapshots = GameSnapshots::new();
+        let mut acked = WorldSnapshot::default();
+        acked.set_char(player, &AuthorizedCharStateComponent::new(v2(1.0, 1.0)));
+        acked.set_char(minion, &AuthorizedCharStateComponent::new(v2(2.0, 2.0)));
+        snapshots.set_snapshot(5, &acked);
+
+        acked.chars.remove(&minion);
+        snapshots.set_snapshot(5, &acked);
+        let snapshot = &snapshots.get_snapshot(5).snapshot;
+        assert!(snapshot.get_char(player).is_some());
+        assert!(snapshot.get_char(minion).is_none());
+    }
+
+    #[test]
+    fn every_not_interpolated_entity_is_compared() {
+        let (player, minion, remote_player) = (
+            ServerEntityId::from_u64(1),
+            ServerEntityId::from_u64(2),
+            ServerEntityId::from_u64(3),
+        );
+        let mut snapshots = GameSnapshots::new();
+        let mut acked = WorldSnapshot::default();
+        acked.set_char(player, &AuthorizedCharStateComponent::new(v2(1.0, 1.0)));
+        acked.set_char(minion, &AuthorizedCharStateComponent::new(v2(2.0, 2.0)));
+        acked.set_char(
+            remote_player,
+            &AuthorizedCharStateComponent::new(v2(3.0, 3.0)),
+        );
+        snapshots.mark_interpolated(remote_player);
+
+        let mut predicted = acked.clone();
+        assert!(snapshots.compare_snapshots(&acked, &predicted));
+        predicted.set_char(
+            remote_player,
+            &AuthorizedCharStateComponent::new(v2(5.0, 5.0)),
+        );
+        assert!(snapshots.compare_snapshots(&acked, &predicted));
+        predicted.set_char(minion, &AuthorizedCharStateComponent::new(v2(5.0, 5.0)));
+        assert!(!snapshots.compare_snapshots(&acked, &predicted));
+        predicted.chars.remove(&minion);
+        assert!(snapshots.compare_snapshots(&acked, &predicted));
     }
 }
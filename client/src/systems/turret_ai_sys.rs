@@ -5,13 +5,13 @@ use crate::components::char::{
 };
 use crate::components::controller::LocalPlayerControllerComponent;
 use crate::configs::DevConfig;
-use crate::systems::minion_ai_sys::MinionAiSystem;
 use crate::systems::SystemFrameDurations;
 use rustarok_common::common::v2_to_p2;
 use rustarok_common::components::char::{
-    AuthorizedCharStateComponent, ControllerEntityId, EntityTarget,
+    AuthorizedCharStateComponent, CharEntityId, ControllerEntityId, EntityTarget,
 };
 use rustarok_common::components::controller::{ControllerComponent, PlayerIntention};
+use rustarok_common::systems::structure_ai_sys::StructureAiSystem;
 
 pub struct TurretAiSystem;
 
@@ -87,49 +87,35 @@ impl<'a> System<'a> for TurretAiSystem {
                         }
                     }
                 }
-                // Hack
-                let mut current_target_id = None;
-                // hack end
-                // first check if preferred target is in range
-
-                let current_target_entity = match char_state.target {
-                    Some(EntityTarget::OtherEntity(target_id)) => {
-                        current_target_id = Some(target_id);
-                        auth_char_state_storage.get(target_id.into())
-                    }
+                let current_target_id = match char_state.target {
+                    Some(EntityTarget::OtherEntity(target_id)) => Some(target_id),
                     _ => None,
                 };
-                let no_target_or_dead_or_out_of_range = match current_target_entity {
-                    Some(target) => {
-                        let current_distance = nalgebra::distance(
-                            &v2_to_p2(&target.pos()),
-                            &v2_to_p2(&char_state.pos()),
-                        );
-                        target.state().is_dead() || current_distance > radius
-                    }
-                    None => true,
-                };
-
-                let char_state2 = char_state_storage
+                let self_team = char_state_storage
                     .get(controller.controlled_entity.into())
-                    .unwrap();
-                controller.intention = if no_target_or_dead_or_out_of_range {
-                    let maybe_enemy = MinionAiSystem::get_closest_enemy_in_area(
-                        &entities,
-                        &char_state_storage,
-                        &auth_char_state_storage,
-                        &char_state.pos(),
-                        radius,
-                        char_state2.team,
-                        controller.controlled_entity,
-                    );
-                    match maybe_enemy {
-                        Some(target_id) => Some(PlayerIntention::Attack(target_id)),
-                        None => None,
-                    }
-                } else {
-                    Some(PlayerIntention::Attack(current_target_id.unwrap()))
-                }
+                    .unwrap()
+                    .team;
+                // the same rules as for the towers
+                let living_chars = (&entities, &char_state_storage, &auth_char_state_storage)
+                    .join()
+                    .filter(|(_entity, _other_state, other_auth_state)| {
+                        !other_auth_state.state().is_dead()
+                    })
+                    .map(|(entity, other_state, other_auth_state)| {
+                        (
+                            CharEntityId::from(entity),
+                            other_state.team,
+                            other_auth_state.pos(),
+                        )
+                    });
+                controller.intention = StructureAiSystem::choose_target(
+                    current_target_id,
+                    &char_state.pos(),
+                    radius,
+                    self_team,
+                    living_chars,
+                )
+                .map(PlayerIntention::Attack);
             } else {
                 // the char might have died, remove the controller entity
                 entities.delete(controller_id.into()).expect("");
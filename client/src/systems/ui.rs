@@ -1,5 +1,5 @@
 use crate::components::char::{
-    CharacterStateComponent, ClientCharState, NpcComponent, SpriteRenderDescriptorComponent,
+    CharacterStateComponent, ClientCharState, SpriteRenderDescriptorComponent,
 };
 use crate::components::controller::{
     HumanInputComponent, LocalPlayerControllerComponent, SkillKey,
@@ -8,11 +8,13 @@ use crate::grf::database::AssetDatabase;
 use crate::render::render_command::{RenderCommandCollector, UiLayer2d};
 use crate::runtime_assets::graphic::FONT_SIZE_SKILL_KEY;
 use crate::runtime_assets::map::MapRenderData;
+use crate::systems::fog_of_war_sys::FogOfWarSystem;
 use crate::systems::input_sys::InputConsumerSystem;
 use crate::systems::{AssetResources, RenderMatrices, SystemVariables};
 use crate::{ElapsedTime, SpriteResource};
-use rustarok_common::common::{EngineTime, Vec2i, Vec3};
-use rustarok_common::components::char::CharEntityId;
+use rustarok_common::common::{v2, EngineTime, Vec2, Vec2i, Vec3};
+use rustarok_common::components::char::{AuthorizedCharStateComponent, CharType, JobId};
+use rustarok_common::vision::TeamVision;
 use specs::prelude::*;
 use specs::ReadStorage;
 
@@ -32,11 +34,11 @@ impl RenderUI {
         sys_vars: &SystemVariables,
         time: &EngineTime,
         char_state_storage: &ReadStorage<CharacterStateComponent>,
-        npc_storage: &ReadStorage<NpcComponent>,
-        entities: &Entities,
+        auth_char_state_storage: &ReadStorage<AuthorizedCharStateComponent>,
         camera_pos: &Vec3,
         asset_db: &AssetDatabase,
         map_render_data: &MapRenderData,
+        vision: Option<&TeamVision>,
     ) {
         // Draw casting bar
         // TODO2
@@ -101,18 +103,17 @@ impl RenderUI {
             &time,
         );
 
-        //        self.draw_minimap(
-        //            self_char_state,
-        //            render_commands,
-        //            &sys_vars.matrices,
-        //            &sys_vars.assets,
-        //            char_state_storage,
-        //            npc_storage,
-        //            entities,
-        //            camera_pos,
-        //            asset_db,
-        //            map_render_data,
-        //        );
+        RenderUI::draw_minimap(
+            self_char_state,
+            render_commands,
+            &sys_vars.matrices,
+            char_state_storage,
+            auth_char_state_storage,
+            camera_pos,
+            asset_db,
+            map_render_data,
+            vision,
+        );
 
         render_action_2d(
             time,
@@ -127,155 +128,186 @@ impl RenderUI {
         )
     }
 
-    //    fn draw_minimap(
-    //        &mut self,
-    //        self_char_state: &CharacterStateComponent,
-    //        render_commands: &mut RenderCommandCollector,
-    //        matrices: &RenderMatrices,
-    //        assets: &AssetResources,
-    //        char_state_storage: &ReadStorage<CharacterStateComponent>,
-    //        npc_storage: &ReadStorage<NpcComponent>,
-    //        entities: &Entities,
-    //        camera_pos: &Vec3,
-    //        asset_db: &AssetDatabase,
-    //        map_render_data: &MapRenderData,
-    //    ) {
-    //        // prontera minimaps has empty spaces: left 52, right 45 pixels
-    //        // 6 pixel padding on left and bottom, 7 on top and right
-    //        let minimap_texture = asset_db.get_texture(map_render_data.minimap_texture_id);
-    //        let scale = (matrices.resolution_h as i32 / 4).min(minimap_texture.height) as f32
-    //            / minimap_texture.height as f32;
-    //        let all_minimap_w = (minimap_texture.width as f32 * scale) as i32;
-    //        let minimap_render_x = matrices.resolution_w as i32 - all_minimap_w - 20;
-    //        let offset_x = ((52.0 + 6.0) * scale) as i32;
-    //        let minimap_x = minimap_render_x + offset_x;
-    //        let minimap_h = (minimap_texture.height as f32 * scale) as i32;
-    //        let minimap_y = matrices.resolution_h as i32 - minimap_h - 20;
-    //        render_commands
-    //            .sprite_2d()
-    //            .scale(scale)
-    //            .screen_pos(minimap_render_x, minimap_y)
-    //            .layer(UiLayer2d::Minimap)
-    //            .add(map_render_data.minimap_texture_id);
-    //        let minimap_w = (minimap_texture.width as f32 * scale) as i32
-    //            - ((52.0 + 45.0 + 6.0 + 7.0) * scale) as i32;
-    //        let real_to_map_scale_w = minimap_w as f32 / (map_render_data.ground_width * 2) as f32;
-    //        let real_to_map_scale_h = minimap_h as f32 / (map_render_data.ground_height * 2) as f32;
-    //        for (entity_id, char_state) in (entities, char_state_storage).join() {
-    //            let entity_id = CharEntityId::from(entity_id);
-    //            let head_index = if npc_storage.get(entity_id.into()).is_none() {
-    //                if let CharOutlook::Player {
-    //                    head_index, sex, ..
-    //                } = char_state.outlook
-    //                {
-    //                    Some((sex, head_index))
-    //                } else {
-    //                    None
-    //                }
-    //            } else {
-    //                None
-    //            };
-    //            let color = if self_char_state.team.is_ally_to(char_state.team) {
-    //                &[0, 0, 255, 255]
-    //            } else {
-    //                &[255, 0, 0, 255]
-    //            };
-    //
-    //            let (char_x, char_y) = {
-    //                let char_pos = char_state.pos();
-    //                let char_y = (map_render_data.ground_height * 2) as f32 + char_pos.y;
-    //                (char_pos.x, char_y)
-    //            };
-    //
-    //            if let Some((sex, head_index)) = head_index {
-    //                let head_texture_id = {
-    //                    let sprites = &assets.sprites.head_sprites;
-    //                    sprites[sex as usize][head_index].textures[0]
-    //                };
-    //
-    //                let head_texture = asset_db.get_texture(head_texture_id);
-    //                let center_offset_x = (head_texture.width as f32 * 0.7 / 2.0) as i32;
-    //                let center_offset_y = (head_texture.height as f32 * 0.7 / 2.0) as i32;
-    //                render_commands
-    //                    .sprite_2d()
-    //                    .screen_pos(
-    //                        minimap_x + (char_x * real_to_map_scale_w) as i32 - center_offset_x,
-    //                        minimap_y + (char_y * real_to_map_scale_h) as i32 - center_offset_y,
-    //                    )
-    //                    .color(color)
-    //                    .scale(0.7)
-    //                    .layer(UiLayer2d::MinimapImportantEntities)
-    //                    .add(head_texture_id);
-    //
-    //                let center_offset_x = (head_texture.width as f32 * 0.5 / 2.0) as i32;
-    //                let center_offset_y = (head_texture.height as f32 * 0.5 / 2.0) as i32;
-    //                render_commands
-    //                    .sprite_2d()
-    //                    .screen_pos(
-    //                        minimap_x + (char_x * real_to_map_scale_w) as i32 - center_offset_x,
-    //                        minimap_y + (char_y * real_to_map_scale_h) as i32 - center_offset_y,
-    //                    )
-    //                    .scale(0.5)
-    //                    .layer(UiLayer2d::MinimapImportantEntities)
-    //                    .add(head_texture_id);
-    //            } else {
-    //                render_commands
-    //                    .point_2d()
-    //                    .screen_pos(
-    //                        minimap_x + (char_x * real_to_map_scale_w) as i32,
-    //                        minimap_y + (char_y * real_to_map_scale_h) as i32,
-    //                    )
-    //                    .color(color)
-    //                    .layer(UiLayer2d::MinimapSimpleEntities)
-    //                    .add();
-    //            }
-    //        }
-    //
-    //        // draw camera rectangle
-    //        let right_top = InputConsumerSystem::project_screen_pos_to_world_pos(
-    //            matrices.resolution_w as u16,
-    //            0,
-    //            camera_pos,
-    //            &matrices.projection,
-    //            &render_commands.view_matrix,
-    //            matrices.resolution_w,
-    //            matrices.resolution_h,
-    //        );
-    //        let left_bottom = InputConsumerSystem::project_screen_pos_to_world_pos(
-    //            0,
-    //            matrices.resolution_h as u16,
-    //            camera_pos,
-    //            &matrices.projection,
-    //            &render_commands.view_matrix,
-    //            matrices.resolution_w,
-    //            matrices.resolution_h,
-    //        );
-    //        let right_bottom = InputConsumerSystem::project_screen_pos_to_world_pos(
-    //            matrices.resolution_w as u16,
-    //            matrices.resolution_h as u16,
-    //            camera_pos,
-    //            &matrices.projection,
-    //            &render_commands.view_matrix,
-    //            matrices.resolution_w,
-    //            matrices.resolution_h,
-    //        );
-    //
-    //        let h = right_bottom.y - right_top.y;
-    //        let letf_bottom_y = (map_render_data.ground_height * 2) as f32 + left_bottom.y;
-    //        render_commands
-    //            .rectangle_2d()
-    //            .screen_pos(
-    //                minimap_x + (left_bottom.x * real_to_map_scale_w) as i32,
-    //                minimap_y + ((letf_bottom_y - h) * real_to_map_scale_h) as i32,
-    //            )
-    //            .size(
-    //                ((right_bottom.x - left_bottom.x) * real_to_map_scale_w) as u16,
-    //                (h * real_to_map_scale_h) as u16,
-    //            )
-    //            .color(&[0, 0, 255, 75])
-    //            .layer(UiLayer2d::MinimapVisibleRegionRectangle)
-    //            .add()
-    //    }
+    /// Darkens the parts of the minimap which the team has never seen. The cells are checked in
+    /// blocks, and the neighbouring dark blocks of a row are merged into one rectangle.
+    fn draw_minimap_fog(
+        vision: &TeamVision,
+        render_commands: &mut RenderCommandCollector,
+        to_minimap: &dyn Fn(&Vec2) -> (i32, i32),
+    ) {
+        const BLOCK_SIZE: usize = 4;
+        let is_block_explored = |block_x: usize, block_y: usize| {
+            (block_y..(block_y + BLOCK_SIZE).min(vision.height())).any(|y| {
+                (block_x..(block_x + BLOCK_SIZE).min(vision.width()))
+                    .any(|x| vision.is_cell_explored((x, y)))
+            })
+        };
+        for block_y in (0..vision.height()).step_by(BLOCK_SIZE) {
+            let block_top = (block_y + BLOCK_SIZE).min(vision.height());
+            let mut dark_from: Option<usize> = None;
+            // the end of the row closes the last dark run
+            for block_x in (0..vision.width())
+                .step_by(BLOCK_SIZE)
+                .chain(std::iter::once(vision.width()))
+            {
+                let is_dark = block_x < vision.width() && !is_block_explored(block_x, block_y);
+                match (dark_from, is_dark) {
+                    (None, true) => dark_from = Some(block_x),
+                    (Some(from_x), false) => {
+                        // the y axis of the minimap points upwards
+                        let (left, top) = to_minimap(&v2(from_x as f32, -(block_top as f32)));
+                        let (right, bottom) = to_minimap(&v2(block_x as f32, -(block_y as f32)));
+                        render_commands
+                            .rectangle_2d()
+                            .screen_pos(left, top)
+                            .size((right - left) as u16, (bottom - top) as u16)
+                            .color(&[0, 0, 0, 160])
+                            .layer(UiLayer2d::MinimapFog)
+                            .add();
+                        dark_from = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn draw_minimap(
+        self_char_state: &CharacterStateComponent,
+        render_commands: &mut RenderCommandCollector,
+        matrices: &RenderMatrices,
+        char_state_storage: &ReadStorage<CharacterStateComponent>,
+        auth_char_state_storage: &ReadStorage<AuthorizedCharStateComponent>,
+        camera_pos: &Vec3,
+        asset_db: &AssetDatabase,
+        map_render_data: &MapRenderData,
+        vision: Option<&TeamVision>,
+    ) {
+        // prontera minimaps has empty spaces: left 52, right 45 pixels
+        // 6 pixel padding on left and bottom, 7 on top and right
+        let minimap_texture = asset_db.get_texture(map_render_data.minimap_texture_id);
+        let scale = (matrices.resolution_h as i32 / 4).min(minimap_texture.height) as f32
+            / minimap_texture.height as f32;
+        let all_minimap_w = (minimap_texture.width as f32 * scale) as i32;
+        let minimap_render_x = matrices.resolution_w as i32 - all_minimap_w - 20;
+        let offset_x = ((52.0 + 6.0) * scale) as i32;
+        let minimap_x = minimap_render_x + offset_x;
+        let minimap_h = (minimap_texture.height as f32 * scale) as i32;
+        let minimap_y = matrices.resolution_h as i32 - minimap_h - 20;
+        render_commands
+            .sprite_2d()
+            .scale(scale)
+            .screen_pos(minimap_render_x, minimap_y)
+            .layer(UiLayer2d::Minimap)
+            .add(map_render_data.minimap_texture_id);
+        let minimap_w = (minimap_texture.width as f32 * scale) as i32
+            - ((52.0 + 45.0 + 6.0 + 7.0) * scale) as i32;
+        let real_to_map_scale_w = minimap_w as f32 / (map_render_data.ground_width * 2) as f32;
+        let real_to_map_scale_h = minimap_h as f32 / (map_render_data.ground_height * 2) as f32;
+        let to_minimap = |pos: &Vec2| {
+            let y = (map_render_data.ground_height * 2) as f32 + pos.y;
+            (
+                minimap_x + (pos.x * real_to_map_scale_w) as i32,
+                minimap_y + (y * real_to_map_scale_h) as i32,
+            )
+        };
+        if let Some(vision) = vision {
+            RenderUI::draw_minimap_fog(vision, render_commands, &to_minimap);
+        }
+        for (char_state, auth_state) in (char_state_storage, auth_char_state_storage).join() {
+            if let Some(vision) = vision {
+                let pos = auth_state.pos();
+                if FogOfWarSystem::is_hidden(vision, self_char_state.team, char_state, &pos) {
+                    continue;
+                }
+            }
+            let color = if self_char_state.team.is_ally_to(char_state.team) {
+                [0, 0, 255, 255]
+            } else {
+                [255, 0, 0, 255]
+            };
+            let (x, y) = to_minimap(&auth_state.pos());
+            if char_state.typ == CharType::Structure {
+                // the fallen structures remain on the map, grayed out
+                let color = if auth_state.state().is_dead() {
+                    [128, 128, 128, 255]
+                } else {
+                    color
+                };
+                let size = if char_state.job_id == JobId::Core {
+                    9
+                } else {
+                    6
+                };
+                render_commands
+                    .rectangle_2d()
+                    .screen_pos(x - size / 2 - 1, y - size / 2 - 1)
+                    .size(size as u16 + 2, size as u16 + 2)
+                    .color(&[0, 0, 0, 255])
+                    .layer(UiLayer2d::MinimapSimpleEntities)
+                    .add();
+                render_commands
+                    .rectangle_2d()
+                    .screen_pos(x - size / 2, y - size / 2)
+                    .size(size as u16, size as u16)
+                    .color(&color)
+                    .layer(UiLayer2d::MinimapImportantEntities)
+                    .add();
+            } else if !auth_state.state().is_dead() {
+                render_commands
+                    .point_2d()
+                    .screen_pos(x, y)
+                    .color(&color)
+                    .layer(UiLayer2d::MinimapSimpleEntities)
+                    .add();
+            }
+        }
+
+        // draw camera rectangle
+        let right_top = InputConsumerSystem::project_screen_pos_to_world_pos(
+            matrices.resolution_w as u16,
+            0,
+            camera_pos,
+            &matrices.projection,
+            &render_commands.view_matrix,
+            matrices.resolution_w,
+            matrices.resolution_h,
+        );
+        let left_bottom = InputConsumerSystem::project_screen_pos_to_world_pos(
+            0,
+            matrices.resolution_h as u16,
+            camera_pos,
+            &matrices.projection,
+            &render_commands.view_matrix,
+            matrices.resolution_w,
+            matrices.resolution_h,
+        );
+        let right_bottom = InputConsumerSystem::project_screen_pos_to_world_pos(
+            matrices.resolution_w as u16,
+            matrices.resolution_h as u16,
+            camera_pos,
+            &matrices.projection,
+            &render_commands.view_matrix,
+            matrices.resolution_w,
+            matrices.resolution_h,
+        );
+
+        let h = right_bottom.y - right_top.y;
+        let left_bottom_y = (map_render_data.ground_height * 2) as f32 + left_bottom.y;
+        render_commands
+            .rectangle_2d()
+            .screen_pos(
+                minimap_x + (left_bottom.x * real_to_map_scale_w) as i32,
+                minimap_y + ((left_bottom_y - h) * real_to_map_scale_h) as i32,
+            )
+            .size(
+                ((right_bottom.x - left_bottom.x) * real_to_map_scale_w) as u16,
+                (h * real_to_map_scale_h) as u16,
+            )
+            .color(&[0, 0, 255, 75])
+            .layer(UiLayer2d::MinimapVisibleRegionRectangle)
+            .add()
+    }
 
     fn draw_targeting_skill_name(
         char_state: &CharacterStateComponent,
@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rustarok_common::grf::act::ActionFile;
+use rustarok_common::grf::binary_reader::BinaryReader;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(action) = ActionFile::load(BinaryReader::from_vec(data.to_vec(), "fuzz.act")) {
+        let _ = action.max_sprite_frame_index();
+    }
+});
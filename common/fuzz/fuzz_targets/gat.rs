@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rustarok_common::grf::binary_reader::BinaryReader;
+use rustarok_common::grf::gat::Gat;
+
+fuzz_target!(|data: &[u8]| {
+    // `Gat::load` would read and write the .cel cache files, only the parsing is fuzzed
+    if let Ok(gat) = Gat::parse(BinaryReader::from_vec(data.to_vec(), "fuzz.gat")) {
+        let _ = gat.blocking_rectangles();
+    }
+});
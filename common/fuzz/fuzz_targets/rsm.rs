@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rustarok_common::grf::binary_reader::BinaryReader;
+use rustarok_common::grf::rsm::Rsm;
+
+fuzz_target!(|data: &[u8]| {
+    // the meshes are generated from the validated faces, it must not panic either
+    if let Ok(rsm) = Rsm::load(BinaryReader::from_vec(data.to_vec(), "fuzz.rsm")) {
+        let _ = rsm.generate_meshes_by_texture_index();
+    }
+});
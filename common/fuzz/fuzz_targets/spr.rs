@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rustarok_common::grf::binary_reader::BinaryReader;
+use rustarok_common::grf::spr::SpriteFile;
+
+fuzz_target!(|data: &[u8]| {
+    let mut buf = BinaryReader::from_vec(data.to_vec(), "fuzz.spr");
+    if let Ok((version, indexed_frame_count, rgba_frame_count)) = SpriteFile::read_header(&mut buf)
+    {
+        let _ = SpriteFile::load(buf, None, version, indexed_frame_count, rgba_frame_count);
+    }
+});
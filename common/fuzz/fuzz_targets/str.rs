@@ -0,0 +1,8 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rustarok_common::grf::binary_reader::BinaryReader;
+use rustarok_common::grf::str::StrFile;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = StrFile::load(BinaryReader::from_vec(data.to_vec(), "fuzz.str"));
+});
@@ -0,0 +1,185 @@
+use crate::char_attr::{CharAttributes, Percentage};
+use crate::components::char::{CharEntityId, JobId};
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WeaponType {
+    Sword,
+    Arrow,
+    SilverBullet,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(variant_size_differences)]
+pub enum BasicAttackType {
+    MeleeSimple,
+    #[allow(dead_code)]
+    MeleeCombo {
+        combo_count: u8,
+        base_dmg_percentage_for_each_combo: Percentage,
+    },
+    Ranged {
+        bullet_type: WeaponType,
+    },
+}
+
+impl BasicAttackType {
+    pub fn for_job(job_id: JobId) -> BasicAttackType {
+        return match job_id {
+            JobId::GUNSLINGER => BasicAttackType::Ranged {
+                bullet_type: WeaponType::SilverBullet,
+            },
+            JobId::RangedMinion => BasicAttackType::Ranged {
+                bullet_type: WeaponType::Arrow,
+            },
+            JobId::RANGER => BasicAttackType::Ranged {
+                bullet_type: WeaponType::Arrow,
+            },
+            JobId::Turret => BasicAttackType::Ranged {
+                bullet_type: WeaponType::SilverBullet,
+            },
+            _ => BasicAttackType::MeleeSimple,
+        };
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum DamageDisplayType {
+    SingleNumber,
+    Combo(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum HpModificationType {
+    BasicDamage(u32, DamageDisplayType, WeaponType),
+    SpellDamage(u32, DamageDisplayType),
+    Heal(u32),
+    Poison(u32),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HpModificationRequest {
+    pub src_entity: CharEntityId,
+    pub dst_entity: CharEntityId,
+    pub typ: HpModificationType,
+}
+
+impl HpModificationRequest {
+    pub fn allow(self, dmg: u32) -> HpModificationResult {
+        HpModificationResult {
+            src_entity: self.src_entity,
+            dst_entity: self.dst_entity,
+            dst_hp: 0,
+            typ: HpModificationResultType::Ok(match self.typ {
+                HpModificationType::BasicDamage(_, display_type, weapon_type) => {
+                    HpModificationType::BasicDamage(dmg, display_type, weapon_type)
+                }
+                HpModificationType::SpellDamage(_, display_type) => {
+                    HpModificationType::SpellDamage(dmg, display_type)
+                }
+                HpModificationType::Heal(_) => HpModificationType::Heal(dmg),
+                HpModificationType::Poison(_) => HpModificationType::Poison(dmg),
+            }),
+        }
+    }
+
+    pub fn blocked(self) -> HpModificationResult {
+        HpModificationResult {
+            src_entity: self.src_entity,
+            dst_entity: self.dst_entity,
+            dst_hp: 0,
+            typ: HpModificationResultType::Blocked,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HpModificationResult {
+    pub src_entity: CharEntityId,
+    pub dst_entity: CharEntityId,
+    /// The hp of the target after this modification, set by HpModificationSystem
+    pub dst_hp: i32,
+    pub typ: HpModificationResultType,
+}
+
+impl HpModificationResult {
+    pub fn absorbed(self) -> HpModificationResult {
+        HpModificationResult {
+            src_entity: self.src_entity,
+            dst_entity: self.dst_entity,
+            dst_hp: self.dst_hp,
+            typ: HpModificationResultType::Absorbed,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum HpModificationResultType {
+    Ok(HpModificationType),
+    Blocked,
+    Absorbed,
+}
+
+impl HpModificationRequest {
+    /// Reduces the requested value by the armor of the target.
+    /// The source does not affect the outcome yet, but it will when damage bonuses are added.
+    pub fn apply_armor_calc(self, dst_attribs: &CharAttributes) -> HpModificationResult {
+        return match self.typ {
+            HpModificationType::SpellDamage(base_dmg, _damage_render_type) => {
+                let dmg = dst_attribs.armor.subtract_me_from(base_dmg as i32);
+                if dmg <= 0 {
+                    self.blocked()
+                } else {
+                    self.allow(dmg as u32)
+                }
+            }
+            HpModificationType::BasicDamage(base_dmg, _damage_render_type, _weapon_type) => {
+                let atk = dst_attribs.armor.subtract_me_from(base_dmg as i32);
+                if atk <= 0 {
+                    self.blocked()
+                } else {
+                    self.allow(atk as u32)
+                }
+            }
+            HpModificationType::Heal(healed) => self.allow(healed),
+            HpModificationType::Poison(dmg) => {
+                let atk = dst_attribs.armor.subtract_me_from(dmg as i32);
+                if atk <= 0 {
+                    self.blocked()
+                } else {
+                    self.allow(dmg)
+                }
+            }
+        };
+    }
+}
+
+impl HpModificationResult {
+    pub fn with_dst_hp(self, dst_hp: i32) -> HpModificationResult {
+        HpModificationResult { dst_hp, ..self }
+    }
+
+    /// Returns the new hp of the target after the result is applied on it
+    pub fn apply_on_hp(&self, hp: i32, max_hp: i32) -> i32 {
+        match self.typ {
+            HpModificationResultType::Ok(hp_req_mod_type) => match hp_req_mod_type {
+                HpModificationType::Heal(val) => max_hp.min(hp + val as i32),
+                HpModificationType::BasicDamage(val, _display_type, _weapon_type) => {
+                    hp - val as i32
+                }
+                HpModificationType::Poison(val) => hp - val as i32,
+                HpModificationType::SpellDamage(val, _display_type) => hp - val as i32,
+            },
+            HpModificationResultType::Blocked => hp,
+            HpModificationResultType::Absorbed => hp,
+        }
+    }
+
+    pub fn interrupts_target(&self) -> bool {
+        match self.typ {
+            HpModificationResultType::Ok(HpModificationType::BasicDamage(..))
+            | HpModificationResultType::Ok(HpModificationType::SpellDamage(..)) => true,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,434 @@
+use crate::common::ElapsedTime;
+use serde::{Deserialize, Serialize};
+
+const PERCENTAGE_FACTOR: i32 = 1000;
+
+// able to represent numbers in 0.1% discrete steps
+// serialized as whole percentages like in the configs, the statuses are created from those
+#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(from = "i32", into = "i32")]
+pub struct Percentage {
+    value: i32,
+}
+
+impl From<i32> for Percentage {
+    fn from(value: i32) -> Self {
+        percentage(value)
+    }
+}
+
+impl From<Percentage> for i32 {
+    fn from(value: Percentage) -> Self {
+        value.value / PERCENTAGE_FACTOR
+    }
+}
+
+pub fn percentage(value: i32) -> Percentage {
+    Percentage {
+        value: value * PERCENTAGE_FACTOR,
+    }
+}
+
+impl Percentage {
+    pub fn is_not_zero(&self) -> bool {
+        self.value != 0
+    }
+
+    pub fn as_i16(&self) -> i16 {
+        (self.value / PERCENTAGE_FACTOR) as i16
+    }
+
+    pub fn limit(&mut self, min: Percentage, max: Percentage) {
+        self.value = self.value.min(max.value).max(min.value);
+    }
+
+    pub fn apply(&mut self, modifier: &CharAttributeModifier) {
+        match modifier {
+            CharAttributeModifier::AddPercentage(p) => {
+                self.value += p.value;
+            }
+            CharAttributeModifier::AddValue(_v) => panic!(
+                "{:?} += {:?}, you cannot add value to a percentage",
+                self, modifier
+            ),
+            CharAttributeModifier::IncreaseByPercentage(p) => {
+                self.value = self.increase_by(*p).value;
+            }
+        }
+    }
+
+    pub fn as_f32(&self) -> f32 {
+        (self.value as f32 / PERCENTAGE_FACTOR as f32) / 100.0
+    }
+
+    pub fn increase_by(&self, p: Percentage) -> Percentage {
+        let change = self.value / 100 * p.value;
+        Percentage {
+            value: self.value + change / PERCENTAGE_FACTOR,
+        }
+    }
+
+    pub fn add_me_to(&self, num: i32) -> i32 {
+        let f = PERCENTAGE_FACTOR as i64;
+        let change = (num as i64) * f / 100 * (self.value as i64) / f / f;
+        return num + (change as i32);
+    }
+
+    pub fn of(&self, num: i32) -> i32 {
+        let f = PERCENTAGE_FACTOR as i64;
+        let change = (num as i64) * f / 100 * (self.value as i64) / f / f;
+        return change as i32;
+    }
+
+    pub fn subtract_me_from(&self, num: i32) -> i32 {
+        let f = PERCENTAGE_FACTOR as i64;
+        let change = (num as i64) * f / 100 * (self.value as i64) / f / f;
+        return num - (change as i32);
+    }
+
+    #[allow(dead_code)]
+    pub fn div(&self, other: i32) -> Percentage {
+        Percentage {
+            value: self.value / other,
+        }
+    }
+
+    pub fn subtract(&self, other: Percentage) -> Percentage {
+        Percentage {
+            value: self.value - other.value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentages() {
+        assert_eq!(percentage(70).increase_by(percentage(10)).as_i16(), 77);
+        assert_eq!(percentage(70).increase_by(percentage(0)).as_i16(), 70);
+        assert_eq!(percentage(70).increase_by(percentage(-10)).as_i16(), 63);
+        assert_eq!(percentage(100).increase_by(percentage(200)).as_i16(), 300);
+        assert_eq!(percentage(10).add_me_to(200), 220);
+        assert_eq!(percentage(70).add_me_to(600), 1020);
+        assert_eq!(percentage(70).div(10).add_me_to(600), 642);
+        assert_eq!(percentage(-10).add_me_to(200), 180);
+        assert_eq!(percentage(50).add_me_to(76), 114);
+        assert_eq!(percentage(50).add_me_to(10_000), 15_000);
+        assert_eq!(percentage(10).of(200), 20);
+        assert_eq!(percentage(70).of(600), 420);
+        assert_eq!(percentage(70).div(10).of(600), 42);
+        assert_eq!(percentage(50).of(76), 38);
+        assert_eq!(percentage(50).of(10_000), 5_000);
+        assert_eq!(percentage(10).subtract_me_from(200), 180);
+        assert_eq!(percentage(40).subtract_me_from(10_000), 6_000);
+        assert_eq!(percentage(70).subtract_me_from(600), 180);
+        assert_eq!(percentage(50).subtract_me_from(76), 38);
+        assert_eq!(percentage(100).as_f32(), 1.0);
+        assert_eq!(percentage(50).as_f32(), 0.5);
+        assert_eq!(percentage(5).as_f32(), 0.05);
+        assert_eq!(percentage(5).div(10).as_f32(), 0.005);
+        assert_eq!(percentage(-5).div(10).as_f32(), -0.005);
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CharAttributes {
+    pub max_hp: i32,
+    pub attack_damage: u16,
+    pub movement_speed: Percentage,
+    pub attack_range: Percentage,
+    pub attack_speed: Percentage,
+    pub armor: Percentage,
+    pub healing: Percentage,
+    pub hp_regen: Percentage,
+    pub mana_regen: Percentage,
+}
+
+#[derive(Clone, Debug)]
+pub struct CharAttributesBonuses {
+    pub attrs: CharAttributes,
+    pub durations: BonusDurations,
+}
+
+impl CharAttributes {
+    pub fn zero() -> CharAttributes {
+        CharAttributes {
+            movement_speed: percentage(0),
+            attack_range: percentage(0),
+            attack_speed: percentage(0),
+            attack_damage: 0,
+            armor: percentage(0),
+            healing: percentage(0),
+            hp_regen: percentage(0),
+            max_hp: 0,
+            mana_regen: percentage(0),
+        }
+    }
+
+    pub fn differences(
+        &self,
+        other: &CharAttributes,
+        collector: &CharAttributeModifierCollector,
+    ) -> CharAttributesBonuses {
+        return CharAttributesBonuses {
+            attrs: CharAttributes {
+                max_hp: self.max_hp - other.max_hp,
+                attack_damage: self.attack_damage - other.attack_damage,
+                movement_speed: self.movement_speed.subtract(other.movement_speed),
+                attack_range: self.attack_range.subtract(other.attack_range),
+                attack_speed: self.attack_speed.subtract(other.attack_speed),
+                armor: (self.armor).subtract(other.armor),
+                healing: self.healing.subtract(other.healing),
+                hp_regen: self.hp_regen.subtract(other.hp_regen),
+                mana_regen: self.mana_regen.subtract(other.mana_regen),
+            },
+            durations: collector.durations.clone(),
+        };
+    }
+
+    pub fn apply(&self, modifiers: &CharAttributeModifierCollector) -> CharAttributes {
+        let mut attr = self.clone();
+        for m in &modifiers.max_hp {
+            match m {
+                CharAttributeModifier::AddPercentage(_p) => {
+                    panic!("max_hp += {:?}, you cannot add percentage to a value", m)
+                }
+                CharAttributeModifier::AddValue(v) => {
+                    attr.max_hp += *v as i32;
+                }
+                CharAttributeModifier::IncreaseByPercentage(p) => {
+                    attr.max_hp = p.add_me_to(attr.max_hp);
+                }
+            }
+        }
+        for m in &modifiers.attack_damage {
+            match m {
+                CharAttributeModifier::AddPercentage(_p) => panic!(
+                    "attack_damage += {:?}, you cannot add percentage to a value",
+                    m
+                ),
+                CharAttributeModifier::AddValue(v) => {
+                    attr.attack_damage += *v as u16;
+                }
+                CharAttributeModifier::IncreaseByPercentage(p) => {
+                    attr.attack_damage = p.add_me_to(attr.attack_damage as i32) as u16;
+                }
+            }
+        }
+
+        for m in &modifiers.movement_speed {
+            attr.movement_speed.apply(m);
+        }
+        for m in &modifiers.attack_range {
+            attr.attack_range.apply(m);
+        }
+        for m in &modifiers.attack_speed {
+            attr.attack_speed.apply(m);
+        }
+        attr.attack_speed.limit(percentage(-300), percentage(500));
+        for m in &modifiers.armor {
+            attr.armor.apply(m);
+        }
+        attr.armor.limit(percentage(-100), percentage(100));
+        for m in &modifiers.healing {
+            attr.healing.apply(m);
+        }
+        for m in &modifiers.hp_regen {
+            attr.hp_regen.apply(m);
+        }
+        for m in &modifiers.mana_regen {
+            attr.mana_regen.apply(m);
+        }
+        return attr;
+    }
+}
+
+#[derive(Clone, Debug)]
+#[allow(dead_code)]
+pub enum CharAttributeModifier {
+    AddPercentage(Percentage),
+    AddValue(f32),
+    IncreaseByPercentage(Percentage),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BonusDurations {
+    pub max_hp_bonus_ends_at: ElapsedTime,
+    pub walking_speed_bonus_ends_at: ElapsedTime,
+    pub attack_range_bonus_ends_at: ElapsedTime,
+    pub attack_speed_bonus_ends_at: ElapsedTime,
+    pub attack_damage_bonus_ends_at: ElapsedTime,
+    pub armor_bonus_ends_at: ElapsedTime,
+    pub healing_bonus_ends_at: ElapsedTime,
+    pub hp_regen_bonus_ends_at: ElapsedTime,
+    pub mana_regen_bonus_ends_at: ElapsedTime,
+
+    pub max_hp_bonus_started_at: ElapsedTime,
+    pub walking_speed_bonus_started_at: ElapsedTime,
+    pub attack_range_bonus_started_at: ElapsedTime,
+    pub attack_speed_bonus_started_at: ElapsedTime,
+    pub attack_damage_bonus_started_at: ElapsedTime,
+    pub armor_bonus_started_at: ElapsedTime,
+    pub healing_bonus_started_at: ElapsedTime,
+    pub hp_regen_bonus_started_at: ElapsedTime,
+    pub mana_regen_bonus_started_at: ElapsedTime,
+}
+
+impl BonusDurations {
+    pub fn with_invalid_times() -> BonusDurations {
+        BonusDurations {
+            max_hp_bonus_ends_at: ElapsedTime(std::f32::MAX),
+            walking_speed_bonus_ends_at: ElapsedTime(std::f32::MAX),
+            attack_range_bonus_ends_at: ElapsedTime(std::f32::MAX),
+            attack_speed_bonus_ends_at: ElapsedTime(std::f32::MAX),
+            attack_damage_bonus_ends_at: ElapsedTime(std::f32::MAX),
+            armor_bonus_ends_at: ElapsedTime(std::f32::MAX),
+            healing_bonus_ends_at: ElapsedTime(std::f32::MAX),
+            hp_regen_bonus_ends_at: ElapsedTime(std::f32::MAX),
+            mana_regen_bonus_ends_at: ElapsedTime(std::f32::MAX),
+
+            max_hp_bonus_started_at: ElapsedTime(std::f32::MAX),
+            walking_speed_bonus_started_at: ElapsedTime(std::f32::MAX),
+            attack_range_bonus_started_at: ElapsedTime(std::f32::MAX),
+            attack_speed_bonus_started_at: ElapsedTime(std::f32::MAX),
+            attack_damage_bonus_started_at: ElapsedTime(std::f32::MAX),
+            armor_bonus_started_at: ElapsedTime(std::f32::MAX),
+            healing_bonus_started_at: ElapsedTime(std::f32::MAX),
+            hp_regen_bonus_started_at: ElapsedTime(std::f32::MAX),
+            mana_regen_bonus_started_at: ElapsedTime(std::f32::MAX),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CharAttributeModifierCollector {
+    max_hp: Vec<CharAttributeModifier>,
+    movement_speed: Vec<CharAttributeModifier>,
+    attack_range: Vec<CharAttributeModifier>,
+    attack_speed: Vec<CharAttributeModifier>,
+    attack_damage: Vec<CharAttributeModifier>,
+    armor: Vec<CharAttributeModifier>,
+    healing: Vec<CharAttributeModifier>,
+    hp_regen: Vec<CharAttributeModifier>,
+    mana_regen: Vec<CharAttributeModifier>,
+    durations: BonusDurations,
+}
+
+impl CharAttributeModifierCollector {
+    pub fn new() -> CharAttributeModifierCollector {
+        CharAttributeModifierCollector {
+            max_hp: Vec::with_capacity(8),
+            movement_speed: Vec::with_capacity(8),
+            attack_range: Vec::with_capacity(8),
+            attack_speed: Vec::with_capacity(8),
+            attack_damage: Vec::with_capacity(8),
+            armor: Vec::with_capacity(8),
+            healing: Vec::with_capacity(8),
+            hp_regen: Vec::with_capacity(8),
+            mana_regen: Vec::with_capacity(8),
+            durations: BonusDurations::with_invalid_times(),
+        }
+    }
+
+    pub fn change_attack_damage(
+        &mut self,
+        modifier: CharAttributeModifier,
+        started: ElapsedTime,
+        until: ElapsedTime,
+    ) {
+        CharAttributeModifierCollector::set_durations(
+            started,
+            until,
+            &mut self.durations.attack_damage_bonus_started_at,
+            &mut self.durations.attack_damage_bonus_ends_at,
+        );
+        self.attack_damage.push(modifier);
+    }
+
+    pub fn change_attack_speed(
+        &mut self,
+        modifier: CharAttributeModifier,
+        started: ElapsedTime,
+        until: ElapsedTime,
+    ) {
+        CharAttributeModifierCollector::set_durations(
+            started,
+            until,
+            &mut self.durations.attack_speed_bonus_started_at,
+            &mut self.durations.attack_speed_bonus_ends_at,
+        );
+        self.attack_speed.push(modifier);
+    }
+
+    pub fn change_armor(
+        &mut self,
+        modifier: CharAttributeModifier,
+        started: ElapsedTime,
+        until: ElapsedTime,
+    ) {
+        CharAttributeModifierCollector::set_durations(
+            started,
+            until,
+            &mut self.durations.armor_bonus_started_at,
+            &mut self.durations.armor_bonus_ends_at,
+        );
+        self.armor.push(modifier);
+    }
+
+    fn set_durations(
+        new_started_at: ElapsedTime,
+        new_ends_at: ElapsedTime,
+        current_started_at: &mut ElapsedTime,
+        current_ends_at: &mut ElapsedTime,
+    ) {
+        if current_ends_at.has_not_passed_yet(new_ends_at) {
+            *current_ends_at = new_ends_at;
+            *current_started_at = new_started_at;
+        }
+    }
+
+    pub fn change_attack_range(
+        &mut self,
+        modifier: CharAttributeModifier,
+        started: ElapsedTime,
+        until: ElapsedTime,
+    ) {
+        CharAttributeModifierCollector::set_durations(
+            started,
+            until,
+            &mut self.durations.attack_range_bonus_started_at,
+            &mut self.durations.attack_range_bonus_ends_at,
+        );
+        self.attack_range.push(modifier);
+    }
+
+    pub fn change_walking_speed(
+        &mut self,
+        modifier: CharAttributeModifier,
+        started: ElapsedTime,
+        until: ElapsedTime,
+    ) {
+        CharAttributeModifierCollector::set_durations(
+            started,
+            until,
+            &mut self.durations.walking_speed_bonus_started_at,
+            &mut self.durations.walking_speed_bonus_ends_at,
+        );
+        self.movement_speed.push(modifier);
+    }
+
+    pub fn clear(&mut self) {
+        self.max_hp.clear();
+        self.movement_speed.clear();
+        self.attack_range.clear();
+        self.attack_speed.clear();
+        self.attack_damage.clear();
+        self.armor.clear();
+        self.healing.clear();
+        self.hp_regen.clear();
+        self.mana_regen.clear();
+        self.durations = BonusDurations::with_invalid_times();
+    }
+}
@@ -1,8 +1,15 @@
-use crate::common::{float_cmp, v2, Vec2};
+use crate::char_attr::CharAttributes;
+use crate::common::{float_cmp, v2, ElapsedTime, Vec2};
 use crate::components::job_ids::JobSpriteId;
+use crate::components::skills::Skills;
+use crate::grf::gat::Gat;
+use crate::packets::to_server::PacketReadErr;
 use crate::packets::SocketBuffer;
+use crate::pathfinding;
 use serde::{Deserialize, Serialize};
 use specs::prelude::*;
+use std::collections::{HashMap, HashSet};
+use strum::IntoEnumIterator;
 use strum_macros::Display;
 use strum_macros::EnumCount;
 use strum_macros::EnumIter;
@@ -43,6 +50,26 @@ impl CharEntityId {
     }
 }
 
+impl ServerEntityId {
+    /// The server sends its own entity ids, also inside the replicated components
+    pub fn new(id: CharEntityId) -> ServerEntityId {
+        ServerEntityId(id)
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        (self.0).0
+    }
+
+    /// Only the server can use it, the clients have their own entity ids
+    pub fn to_char_id(&self) -> CharEntityId {
+        self.0
+    }
+
+    pub fn from_u64(id: u64) -> ServerEntityId {
+        ServerEntityId(CharEntityId(id))
+    }
+}
+
 impl Into<specs::Entity> for CharEntityId {
     fn into(self) -> specs::Entity {
         unsafe { std::mem::transmute(self.0) }
@@ -72,8 +99,19 @@ pub enum CharState {
     //        basic_attack: BasicAttackType,
     //    },
     //    ReceivingDamage,
-    //    Dead,
-    //    CastingSkill(CastingSkillData),
+    Dead,
+    CastingSkill(CastingSkillData),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CastingSkillData {
+    pub target_area_pos: Option<Vec2>,
+    pub char_to_skill_dir_when_casted: Vec2,
+    pub target_entity: Option<CharEntityId>,
+    pub cast_started: ElapsedTime,
+    pub cast_ends: ElapsedTime,
+    pub can_move: bool,
+    pub skill: Skills,
 }
 
 impl CharState {
@@ -90,16 +128,14 @@ impl CharState {
 
     pub fn is_alive(&self) -> bool {
         match self {
-            // TODO2
-            //            CharState::Dead => false,
+            CharState::Dead => false,
             _ => true,
         }
     }
 
     pub fn is_dead(&self) -> bool {
         match self {
-            // TODO2
-            //            CharState::Dead => true,
+            CharState::Dead => true,
             _ => false,
         }
     }
@@ -122,6 +158,7 @@ pub enum MonsterId {
     GEFFEN_MAGE_12, // red
     GEFFEN_MAGE_9,  // blue
     Dimik,
+    Emperium,
 }
 
 #[derive(
@@ -150,9 +187,12 @@ pub enum JobId {
     RangedMinion,
     Turret,
     Guard,
+    Tower,
+    Inhibitor,
+    Core,
 }
 
-#[derive(Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub enum CharType {
     Player,
@@ -160,6 +200,7 @@ pub enum CharType {
     Mercenary,
     Boss,
     Guard,
+    Structure,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -174,11 +215,13 @@ pub enum CharOutlook {
     },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum EntityTarget {
     OtherEntity(CharEntityId),
     Pos(Vec2),
     PosWhileAttacking(Vec2, Option<CharEntityId>),
+    /// see PlayerIntention::Steer
+    PosInStraightLine(Vec2),
 }
 
 #[derive(Component, Clone, Debug, Serialize, Deserialize)]
@@ -229,34 +272,68 @@ impl AuthorizedCharStateComponent {
         }
     }
 
-    pub fn from_buffer(buf: &mut SocketBuffer) -> AuthorizedCharStateComponent {
-        let pos = buf.read_v2();
-        let dir = CharDir::from(buf.read_u8() as usize);
-        let state = match buf.read_u8() {
+    pub fn from_buffer(
+        buf: &mut SocketBuffer,
+    ) -> Result<AuthorizedCharStateComponent, PacketReadErr> {
+        let pos = buf.read_v2()?;
+        let dir = buf.read_u8()? as usize;
+        if dir >= 8 {
+            return Err(PacketReadErr::InvalidValues);
+        }
+        let dir = CharDir::from(dir);
+        let state = match buf.read_u8()? {
             0 => CharState::Idle,
-            1 => CharState::Walking(buf.read_v2()),
-            _ => panic!(),
+            1 => CharState::Walking(buf.read_v2()?),
+            2 => CharState::Dead,
+            3 => {
+                let skill = Skills::iter()
+                    .nth(buf.read_u8()? as usize)
+                    .ok_or(PacketReadErr::InvalidValues)?;
+                let target_area_pos = match buf.read_u8()? {
+                    0 => None,
+                    _ => Some(buf.read_v2()?),
+                };
+                let char_to_skill_dir_when_casted = buf.read_v2()?;
+                let target_entity = match buf.read_u8()? {
+                    0 => None,
+                    _ => Some(CharEntityId(buf.read_u64()?)),
+                };
+                let cast_started = ElapsedTime(buf.read_f32()?);
+                let cast_ends = ElapsedTime(buf.read_f32()?);
+                let can_move = buf.read_u8()? != 0;
+                CharState::CastingSkill(CastingSkillData {
+                    target_area_pos,
+                    char_to_skill_dir_when_casted,
+                    target_entity,
+                    cast_started,
+                    cast_ends,
+                    can_move,
+                    skill,
+                })
+            }
+            _ => return Err(PacketReadErr::InvalidValues),
         };
-        let target = match buf.read_u8() {
+        let target = match buf.read_u8()? {
             0 => None,
-            1 => {
-                // TODO3
-                panic!();
-                //                Some(EntityTarget::OtherEntity())
-            }
-            2 => Some(EntityTarget::Pos(buf.read_v2())),
+            1 => Some(EntityTarget::OtherEntity(CharEntityId(buf.read_u64()?))),
+            2 => Some(EntityTarget::Pos(buf.read_v2()?)),
             3 => {
-                // TODO3
-                panic!();
+                let pos = buf.read_v2()?;
+                let target_entity = match buf.read_u8()? {
+                    0 => None,
+                    _ => Some(CharEntityId(buf.read_u64()?)),
+                };
+                Some(EntityTarget::PosWhileAttacking(pos, target_entity))
             }
-            _ => panic!(),
+            4 => Some(EntityTarget::PosInStraightLine(buf.read_v2()?)),
+            _ => return Err(PacketReadErr::InvalidValues),
         };
-        AuthorizedCharStateComponent {
+        Ok(AuthorizedCharStateComponent {
             pos,
             dir,
             state,
             target,
-        }
+        })
     }
 
     pub fn write_into_buffer(&self, buf: &mut SocketBuffer) {
@@ -270,17 +347,59 @@ impl AuthorizedCharStateComponent {
                 buf.write_u8(1);
                 buf.write_v2(&to_pos);
             }
-            _ => panic!(),
+            CharState::Dead => {
+                buf.write_u8(2);
+            }
+            CharState::CastingSkill(ref casting) => {
+                buf.write_u8(3);
+                buf.write_u8(casting.skill as u8);
+                match casting.target_area_pos {
+                    None => buf.write_u8(0),
+                    Some(pos) => {
+                        buf.write_u8(1);
+                        buf.write_v2(&pos);
+                    }
+                }
+                buf.write_v2(&casting.char_to_skill_dir_when_casted);
+                match casting.target_entity {
+                    None => buf.write_u8(0),
+                    Some(target) => {
+                        buf.write_u8(1);
+                        buf.write_u64(target.0);
+                    }
+                }
+                buf.write_f32(casting.cast_started.0);
+                buf.write_f32(casting.cast_ends.0);
+                buf.write_u8(casting.can_move as u8);
+            }
         }
         match self.target {
             None => {
                 buf.write_u8(0);
             }
+            Some(EntityTarget::OtherEntity(target)) => {
+                buf.write_u8(1);
+                buf.write_u64(target.0);
+            }
             Some(EntityTarget::Pos(to_pos)) => {
                 buf.write_u8(2);
                 buf.write_v2(&to_pos)
             }
-            _ => panic!(),
+            Some(EntityTarget::PosWhileAttacking(pos, target)) => {
+                buf.write_u8(3);
+                buf.write_v2(&pos);
+                match target {
+                    None => buf.write_u8(0),
+                    Some(target) => {
+                        buf.write_u8(1);
+                        buf.write_u64(target.0);
+                    }
+                }
+            }
+            Some(EntityTarget::PosInStraightLine(to_pos)) => {
+                buf.write_u8(4);
+                buf.write_v2(&to_pos)
+            }
         }
     }
 
@@ -322,12 +441,161 @@ impl AuthorizedCharStateComponent {
                 //            | CharState::CastingSkill(_) => {
                 //                self.state = CharState::ReceivingDamage;
             } //            CharState::Attacking { .. } | CharState::Dead => {
-              // denied
-              //            }
+            // denied
+            //            }
+            CharState::CastingSkill(_) | CharState::Dead => {}
         };
     }
 }
 
+/// The waypoints towards the `EntityTarget::Pos` of a character, it is planned on the walkable
+/// cells of the map (see `pathfinding`). It is not part of the snapshots, both sides plan it
+/// from the same target.
+#[derive(Component, Clone, Debug)]
+pub struct WalkingPathComponent {
+    /// the target the path was planned for
+    goal: Option<Vec2>,
+    waypoints: Vec<Vec2>,
+    closest_distance_to_waypoint: f32,
+    last_progress_at: ElapsedTime,
+}
+
+impl Default for WalkingPathComponent {
+    fn default() -> Self {
+        WalkingPathComponent {
+            goal: None,
+            waypoints: Vec::new(),
+            closest_distance_to_waypoint: std::f32::MAX,
+            last_progress_at: ElapsedTime(0.0),
+        }
+    }
+}
+
+impl WalkingPathComponent {
+    /// If the character does not get closer to its next waypoint for this long (e.g. a barricade
+    /// or other characters are in the way), the path is planned again around them
+    const STUCK_SECONDS: f32 = 0.5;
+    const WAYPOINT_REACHED_DISTANCE: f32 = 0.2;
+
+    /// The position the character has to walk towards to get to `goal`.
+    /// None if the goal is unreachable and the character is already as close to it as possible.
+    pub fn next_waypoint(
+        &mut self,
+        gat: &Gat,
+        pos: &Vec2,
+        goal: &Vec2,
+        now: ElapsedTime,
+        other_char_positions: &[Vec2],
+    ) -> Option<Vec2> {
+        // e.g. the mouse or the attacked char moves the goal in every tick, but the path has to
+        // be planned again only when it enters another cell
+        let goal_cell_changed = match self.goal {
+            Some(prev_goal) => {
+                pathfinding::cell_of(gat, &prev_goal) != pathfinding::cell_of(gat, goal)
+            }
+            None => true,
+        };
+        if !goal_cell_changed && self.goal != Some(*goal) {
+            // the path ends at the exact goal only if it is reachable
+            if self.waypoints.last() == self.goal.as_ref() {
+                *self.waypoints.last_mut().unwrap() = *goal;
+            }
+            self.goal = Some(*goal);
+        }
+        if goal_cell_changed || self.waypoints.is_empty() {
+            self.plan(gat, pos, goal, now, &HashSet::new());
+        } else if self.is_stuck(pos, now) {
+            let own_cell = pathfinding::cell_of(gat, pos);
+            let goal_cell = pathfinding::cell_of(gat, goal);
+            let blocked_cells = other_char_positions
+                .iter()
+                .filter_map(|other_pos| pathfinding::cell_of(gat, other_pos))
+                .filter(|cell| Some(*cell) != own_cell && Some(*cell) != goal_cell)
+                .collect();
+            self.plan(gat, pos, goal, now, &blocked_cells);
+        }
+        while let Some(waypoint) = self.waypoints.first() {
+            if (waypoint - pos).magnitude() > WalkingPathComponent::WAYPOINT_REACHED_DISTANCE {
+                break;
+            }
+            self.waypoints.remove(0);
+            self.closest_distance_to_waypoint = std::f32::MAX;
+            self.last_progress_at = now;
+        }
+        return self.waypoints.first().cloned();
+    }
+
+    fn plan(
+        &mut self,
+        gat: &Gat,
+        pos: &Vec2,
+        goal: &Vec2,
+        now: ElapsedTime,
+        blocked_cells: &HashSet<pathfinding::Cell>,
+    ) {
+        self.goal = Some(*goal);
+        // outside of the map, walk in a straight line as before
+        self.waypoints =
+            pathfinding::find_path(gat, pos, goal, blocked_cells).unwrap_or_else(|| vec![*goal]);
+        self.closest_distance_to_waypoint = std::f32::MAX;
+        self.last_progress_at = now;
+    }
+
+    fn is_stuck(&mut self, pos: &Vec2, now: ElapsedTime) -> bool {
+        let distance = match self.waypoints.first() {
+            Some(waypoint) => (waypoint - pos).magnitude(),
+            None => return false,
+        };
+        if distance < self.closest_distance_to_waypoint - 0.01 {
+            self.closest_distance_to_waypoint = distance;
+            self.last_progress_at = now;
+            return false;
+        }
+        return now.elapsed_since(self.last_progress_at).as_f32()
+            > WalkingPathComponent::STUCK_SECONDS;
+    }
+}
+
+/// The gameplay values of a character which are decided by the server, e.g. hp or skill
+/// cooldowns. The client only receives the outcomes.
+#[derive(Component, Clone, Debug)]
+pub struct CharAttributesComponent {
+    pub team: Team,
+    pub job_id: JobId,
+    pub hp: i32,
+    pub base_attributes: CharAttributes,
+    pub calculated_attribs: CharAttributes,
+    pub attack_delay_ends_at: ElapsedTime,
+    pub cannot_control_until: ElapsedTime,
+    pub skill_cast_allowed_at: HashMap<Skills, ElapsedTime>,
+}
+
+impl CharAttributesComponent {
+    pub fn new(
+        team: Team,
+        job_id: JobId,
+        base_attributes: CharAttributes,
+    ) -> CharAttributesComponent {
+        CharAttributesComponent {
+            team,
+            job_id,
+            hp: base_attributes.max_hp,
+            calculated_attribs: base_attributes.clone(),
+            base_attributes,
+            attack_delay_ends_at: ElapsedTime(0.0),
+            cannot_control_until: ElapsedTime(0.0),
+            skill_cast_allowed_at: HashMap::new(),
+        }
+    }
+
+    pub fn is_skill_on_cooldown(&self, skill: Skills, now: ElapsedTime) -> bool {
+        self.skill_cast_allowed_at
+            .get(&skill)
+            .map(|it| it.has_not_passed_yet(now))
+            .unwrap_or(false)
+    }
+}
+
 /// The values that should be added to the sprite direction based on the camera
 /// direction (the index is the camera direction, which is floor(angle/45)
 pub const DIRECTION_TABLE: [usize; 8] = [6, 5, 4, 3, 2, 1, 0, 7];
@@ -1,5 +1,6 @@
 use crate::common::Vec2;
 use crate::components::char::CharEntityId;
+use crate::components::skills::Skills;
 use serde::Deserialize;
 use serde::Serialize;
 use specs::prelude::*;
@@ -13,8 +14,11 @@ pub enum PlayerIntention {
     Attack(CharEntityId),
     /// Move to the coordination, attack any enemy on the way.
     AttackTowards(Vec2),
-    //    /// bool = is self cast
-    //    Casting(Skills, bool, Vec2),
+    /// Walk towards the coordination in a straight line, no path is planned around the walls.
+    /// For the AIs which steer along the walkable cells themselves, e.g. with a flow field.
+    Steer(Vec2),
+    /// bool = is self cast
+    Casting(Skills, bool, Vec2),
 }
 
 // It can be a player, an AI, script etc
@@ -22,6 +26,9 @@ pub enum PlayerIntention {
 pub struct ControllerComponent {
     pub intention: Option<PlayerIntention>,
     pub controlled_entity: CharEntityId,
+    /// The tick of the world the player saw when the intention was made, the targets are
+    /// validated against it. None means the present.
+    pub rewind_to_tick: Option<u64>,
 }
 
 impl ControllerComponent {
@@ -29,6 +36,7 @@ impl ControllerComponent {
         ControllerComponent {
             intention: None,
             controlled_entity,
+            rewind_to_tick: None,
         }
     }
 }
@@ -30,6 +30,9 @@ impl JobSpriteId {
             JobId::Turret => panic!(),
             JobId::Guard => panic!(),
             JobId::Barricade => panic!(),
+            JobId::Tower => panic!(),
+            JobId::Inhibitor => panic!(),
+            JobId::Core => panic!(),
         }
     }
 }
@@ -0,0 +1,424 @@
+use crate::attack::{DamageDisplayType, HpModificationRequest, HpModificationType, WeaponType};
+use crate::common::{v2, ElapsedTime, Vec2};
+use crate::components::char::{CharEntityId, Team};
+use crate::components::status::{ApplyStatusInAreaRequest, StatusEnum, StunStatus};
+use nalgebra::{Isometry2, Point2};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::collections::HashMap;
+
+/// The characters are treated as circles with this radius when they are tested against an area
+const CHAR_RADIUS: f32 = 1.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AreaShape {
+    Circle { radius: f32 },
+    Rect { half_extents: Vec2, rotation: f32 },
+}
+
+impl AreaShape {
+    /// Whether the character standing at `char_pos` touches the area placed at `center`
+    pub fn contains_char(&self, center: &Vec2, char_pos: &Vec2) -> bool {
+        match self {
+            AreaShape::Circle { radius } => {
+                return (char_pos - center).magnitude() <= radius + CHAR_RADIUS;
+            }
+            AreaShape::Rect {
+                half_extents,
+                rotation,
+            } => {
+                let local_pos = Isometry2::new(*center, *rotation)
+                    .inverse_transform_point(&Point2::from(*char_pos));
+                let dx = (local_pos.x.abs() - half_extents.x).max(0.0);
+                let dy = (local_pos.y.abs() - half_extents.y).max(0.0);
+                return dx * dx + dy * dy <= CHAR_RADIUS * CHAR_RADIUS;
+            }
+        }
+    }
+}
+
+/// The rotation of a rectangular area which is cast in `dir`
+pub fn area_rotation(dir: &Vec2) -> f32 {
+    let angle = dir.angle(&Vec2::y());
+    return if dir.x > 0.0 { angle } else { -angle };
+}
+
+/// Every character in the area whose team is compatible with `typ` is affected
+pub struct AreaHpModificationRequest {
+    pub shape: AreaShape,
+    pub pos: Vec2,
+    pub src_entity: CharEntityId,
+    pub typ: HpModificationType,
+    pub except: Option<CharEntityId>,
+}
+
+/// The server does not simulate physics, the target is displaced by `force` * PUSH_DISPLACEMENT
+/// immediately and it can't be controlled for `duration` seconds
+pub struct PushRequest {
+    pub src_entity: CharEntityId,
+    pub dst_entity: CharEntityId,
+    pub force: Vec2,
+    pub duration: f32,
+}
+
+pub const PUSH_DISPLACEMENT: f32 = 0.1;
+
+pub enum SkillManifestationUpdateResult {
+    RemoveIt,
+    KeepIt,
+}
+
+pub struct SkillManifestationUpdateParams<'a> {
+    pub now: ElapsedTime,
+    pub dt: f32,
+    /// The positions and teams of the living characters
+    pub chars: &'a HashMap<CharEntityId, (Vec2, Team)>,
+    pub hp_mod_requests: &'a mut Vec<HpModificationRequest>,
+    pub area_hp_mod_requests: &'a mut Vec<AreaHpModificationRequest>,
+    pub area_status_requests: &'a mut Vec<ApplyStatusInAreaRequest>,
+    pub pushes: &'a mut Vec<PushRequest>,
+}
+
+/// An area or a projectile which lives on after a skill cast or a ranged attack finished.
+/// It is simulated by the server, the clients only render it.
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(HashMapStorage)]
+pub struct SkillManifestationComponent {
+    pub caster_entity_id: CharEntityId,
+    pub caster_team: Team,
+    pub pos: Vec2,
+    pub created_at: ElapsedTime,
+    pub die_at: ElapsedTime,
+    pub kind: SkillManifestationKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SkillManifestationKind {
+    Sanctuary(PeriodicArea),
+    BrutalTestSkill(PeriodicArea),
+    Lightning(LightningState),
+    FireWall(FireWallState),
+    PyroBlast(PyroBlastState),
+    BasicAttackProjectile(BasicAttackProjectileState),
+    XplodiumCharge(XplodiumChargeState),
+}
+
+/// Modifies the hp of everyone in the area in every `period_seconds`, first when it is created
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PeriodicArea {
+    pub shape: AreaShape,
+    pub typ: HpModificationType,
+    pub period_seconds: f32,
+    pub next_action_at: ElapsedTime,
+}
+
+/// Strikes 6 times along `dir`, forward then backward, each strike damages for a while
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LightningState {
+    pub dir: Vec2,
+    pub action_count: u8,
+    pub next_action_at: ElapsedTime,
+    pub next_damage_at: ElapsedTime,
+    pub last_strike_pos: Option<Vec2>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FireWallState {
+    pub half_extents: Vec2,
+    pub rotation: f32,
+    pub damage: u32,
+    pub pushback_force: f32,
+    pub force_duration_seconds: f32,
+    pub cannot_damage_until: HashMap<CharEntityId, ElapsedTime>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PyroBlastState {
+    pub target_entity_id: CharEntityId,
+    pub moving_speed: f32,
+    pub damage: u32,
+    pub secondary_damage: u32,
+    pub splash_radius: f32,
+}
+
+/// The bullet of a ranged basic attack, it reaches the target in `arrives_at`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BasicAttackProjectileState {
+    pub target_entity_id: CharEntityId,
+    pub start_pos: Vec2,
+    pub arrives_at: ElapsedTime,
+    pub damage: u32,
+    pub weapon_type: WeaponType,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct XplodiumChargeState {
+    pub start_pos: Vec2,
+    pub end_pos: Vec2,
+    pub lands_at: ElapsedTime,
+    pub detonates_at: ElapsedTime,
+    pub damage: u32,
+    pub stun_duration_seconds: f32,
+    pub explosion_area: f32,
+}
+
+/// The lightning strikes along its direction at these multiples of the distance
+pub const LIGHTNING_STRIKE_DISTANCE: f32 = 2.2;
+pub const LIGHTNING_STRIKE_OFFSETS: [f32; 6] = [0.0, 1.0, 2.0, 2.0, 1.0, 0.0];
+const PROJECTILE_HIT_DISTANCE: f32 = 2.0;
+
+impl SkillManifestationComponent {
+    /// The copy which is sent to the clients, without the bookkeeping only the server uses
+    pub fn without_server_state(&self) -> SkillManifestationComponent {
+        let mut manifestation = self.clone();
+        if let SkillManifestationKind::FireWall(state) = &mut manifestation.kind {
+            state.cannot_damage_until.clear();
+        }
+        return manifestation;
+    }
+
+    /// The manifestations arrive with the ids of the server, the clients map them to their own.
+    /// Returns None if one of the referred chars is unknown to the client.
+    pub fn map_char_ids<F>(&mut self, map: F) -> Option<()>
+    where
+        F: Fn(CharEntityId) -> Option<CharEntityId>,
+    {
+        self.caster_entity_id = map(self.caster_entity_id)?;
+        match &mut self.kind {
+            SkillManifestationKind::PyroBlast(PyroBlastState {
+                target_entity_id, ..
+            })
+            | SkillManifestationKind::BasicAttackProjectile(BasicAttackProjectileState {
+                target_entity_id,
+                ..
+            }) => {
+                *target_entity_id = map(*target_entity_id)?;
+            }
+            SkillManifestationKind::FireWall(state) => {
+                // it is bookkeeping of the server, the client does not need it
+                state.cannot_damage_until.clear();
+            }
+            SkillManifestationKind::Sanctuary(_)
+            | SkillManifestationKind::BrutalTestSkill(_)
+            | SkillManifestationKind::Lightning(_)
+            | SkillManifestationKind::XplodiumCharge(_) => {}
+        }
+        return Some(());
+    }
+
+    pub fn update(
+        &mut self,
+        params: &mut SkillManifestationUpdateParams,
+    ) -> SkillManifestationUpdateResult {
+        if self.die_at.has_already_passed(params.now) {
+            return SkillManifestationUpdateResult::RemoveIt;
+        }
+        let caster = self.caster_entity_id;
+        let caster_team = self.caster_team;
+        let created_at = self.created_at;
+        let pos = &mut self.pos;
+        match &mut self.kind {
+            SkillManifestationKind::Sanctuary(area)
+            | SkillManifestationKind::BrutalTestSkill(area) => {
+                if area.next_action_at.has_already_passed(params.now) {
+                    area.next_action_at = params.now.add_seconds(area.period_seconds);
+                    params.area_hp_mod_requests.push(AreaHpModificationRequest {
+                        shape: area.shape.clone(),
+                        pos: *pos,
+                        src_entity: caster,
+                        typ: area.typ,
+                        except: None,
+                    });
+                }
+            }
+            SkillManifestationKind::Lightning(state) => {
+                if state.action_count < LIGHTNING_STRIKE_OFFSETS.len() as u8
+                    && state.next_action_at.has_already_passed(params.now)
+                {
+                    let offset = LIGHTNING_STRIKE_OFFSETS[state.action_count as usize];
+                    state.last_strike_pos =
+                        Some(*pos + state.dir * offset * LIGHTNING_STRIKE_DISTANCE);
+                    state.action_count += 1;
+                    state.next_action_at = params.now.add_seconds(1.5);
+                    state.next_damage_at = params.now.add_seconds(1.0);
+                }
+                if let Some(strike_pos) = state.last_strike_pos {
+                    if state.next_damage_at.has_already_passed(params.now) {
+                        params.area_hp_mod_requests.push(AreaHpModificationRequest {
+                            shape: AreaShape::Circle { radius: 1.0 },
+                            pos: strike_pos,
+                            src_entity: caster,
+                            typ: HpModificationType::SpellDamage(
+                                120,
+                                DamageDisplayType::SingleNumber,
+                            ),
+                            except: None,
+                        });
+                        state.next_damage_at = state.next_damage_at.add_seconds(0.6);
+                    }
+                }
+            }
+            SkillManifestationKind::FireWall(state) => {
+                let shape = AreaShape::Rect {
+                    half_extents: state.half_extents,
+                    rotation: state.rotation,
+                };
+                for (target_id, (target_pos, target_team)) in params.chars.iter() {
+                    if !caster_team.can_attack(*target_team)
+                        || !shape.contains_char(pos, target_pos)
+                        || state
+                            .cannot_damage_until
+                            .get(target_id)
+                            .map(|it| it.has_not_passed_yet(params.now))
+                            .unwrap_or(false)
+                    {
+                        continue;
+                    }
+                    let push_dir = *pos - target_pos;
+                    let push_dir = if push_dir.x == 0.0 && push_dir.y == 0.0 {
+                        v2(1.0, 0.0) // "random"
+                    } else {
+                        -push_dir.normalize()
+                    };
+                    params.hp_mod_requests.push(HpModificationRequest {
+                        src_entity: caster,
+                        dst_entity: *target_id,
+                        typ: HpModificationType::SpellDamage(
+                            state.damage,
+                            DamageDisplayType::SingleNumber,
+                        ),
+                    });
+                    params.pushes.push(PushRequest {
+                        src_entity: caster,
+                        dst_entity: *target_id,
+                        force: push_dir * state.pushback_force,
+                        duration: state.force_duration_seconds,
+                    });
+                    state.cannot_damage_until.insert(
+                        *target_id,
+                        params.now.add_seconds(state.force_duration_seconds),
+                    );
+                }
+            }
+            SkillManifestationKind::PyroBlast(state) => {
+                let target_pos = match params.chars.get(&state.target_entity_id) {
+                    Some((target_pos, _team)) => *target_pos,
+                    None => return SkillManifestationUpdateResult::RemoveIt,
+                };
+                let dir_vector = target_pos - *pos;
+                if dir_vector.magnitude() > PROJECTILE_HIT_DISTANCE {
+                    *pos += dir_vector.normalize() * params.dt * state.moving_speed;
+                    return SkillManifestationUpdateResult::KeepIt;
+                }
+                params.hp_mod_requests.push(HpModificationRequest {
+                    src_entity: caster,
+                    dst_entity: state.target_entity_id,
+                    typ: HpModificationType::SpellDamage(
+                        state.damage,
+                        DamageDisplayType::SingleNumber,
+                    ),
+                });
+                params.area_hp_mod_requests.push(AreaHpModificationRequest {
+                    shape: AreaShape::Circle {
+                        radius: state.splash_radius,
+                    },
+                    pos: target_pos,
+                    src_entity: caster,
+                    typ: HpModificationType::SpellDamage(
+                        state.secondary_damage,
+                        DamageDisplayType::SingleNumber,
+                    ),
+                    except: Some(state.target_entity_id),
+                });
+                return SkillManifestationUpdateResult::RemoveIt;
+            }
+            SkillManifestationKind::BasicAttackProjectile(state) => {
+                let target_pos = match params.chars.get(&state.target_entity_id) {
+                    Some((target_pos, _team)) => *target_pos,
+                    None => return SkillManifestationUpdateResult::RemoveIt,
+                };
+                if state.arrives_at.has_not_passed_yet(params.now) {
+                    let travel_duration_percentage =
+                        params.now.percentage_between(created_at, state.arrives_at);
+                    *pos = state.start_pos
+                        + (target_pos - state.start_pos) * travel_duration_percentage;
+                    return SkillManifestationUpdateResult::KeepIt;
+                }
+                params.hp_mod_requests.push(HpModificationRequest {
+                    src_entity: caster,
+                    dst_entity: state.target_entity_id,
+                    typ: HpModificationType::BasicDamage(
+                        state.damage,
+                        DamageDisplayType::SingleNumber,
+                        state.weapon_type,
+                    ),
+                });
+                return SkillManifestationUpdateResult::RemoveIt;
+            }
+            SkillManifestationKind::XplodiumCharge(state) => {
+                if state.lands_at.has_not_passed_yet(params.now) {
+                    let travel_duration_percentage =
+                        params.now.percentage_between(created_at, state.lands_at);
+                    *pos = state.start_pos
+                        + (state.end_pos - state.start_pos) * travel_duration_percentage;
+                    return SkillManifestationUpdateResult::KeepIt;
+                }
+                *pos = state.end_pos;
+                if state.detonates_at.has_not_passed_yet(params.now) {
+                    return SkillManifestationUpdateResult::KeepIt;
+                }
+                let shape = AreaShape::Circle {
+                    radius: state.explosion_area,
+                };
+                params.area_hp_mod_requests.push(AreaHpModificationRequest {
+                    shape: shape.clone(),
+                    pos: state.end_pos,
+                    src_entity: caster,
+                    typ: HpModificationType::SpellDamage(
+                        state.damage,
+                        DamageDisplayType::SingleNumber,
+                    ),
+                    except: None,
+                });
+                params.area_status_requests.push(ApplyStatusInAreaRequest {
+                    source_entity_id: caster,
+                    status: StatusEnum::StunStatus(StunStatus::new(
+                        caster,
+                        params.now,
+                        state.stun_duration_seconds,
+                    )),
+                    shape,
+                    pos: state.end_pos,
+                    except: None,
+                    caster_team,
+                });
+                return SkillManifestationUpdateResult::RemoveIt;
+            }
+        }
+        return SkillManifestationUpdateResult::KeepIt;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn rotated_rect_contains_the_chars_touching_it() {
+        let shape = AreaShape::Rect {
+            half_extents: v2(1.5, 0.5),
+            rotation: FRAC_PI_2,
+        };
+        let center = v2(10.0, -10.0);
+        // rotated by 90 degrees, the long side is vertical
+        assert!(shape.contains_char(&center, &v2(10.0, -8.0)));
+        assert!(shape.contains_char(&center, &v2(11.4, -10.0)));
+        assert!(!shape.contains_char(&center, &v2(12.0, -10.0)));
+        assert!(!shape.contains_char(&center, &v2(10.0, -7.0)));
+
+        let circle = AreaShape::Circle { radius: 2.0 };
+        assert!(circle.contains_char(&center, &v2(12.9, -10.0)));
+        assert!(!circle.contains_char(&center, &v2(13.1, -10.0)));
+    }
+}
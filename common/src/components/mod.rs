@@ -1,4 +1,8 @@
 pub mod char;
 pub mod controller;
 pub mod job_ids;
+pub mod manifestation;
+pub mod skills;
 pub mod snapshot;
+pub mod status;
+pub mod structure;
@@ -0,0 +1,459 @@
+use crate::attack::{DamageDisplayType, HpModificationRequest, HpModificationType};
+use crate::char_attr::percentage;
+use crate::common::{v2, ElapsedTime, Vec2};
+use crate::components::char::{CharEntityId, JobId, StatusNature, Team};
+use crate::components::manifestation::{
+    area_rotation, AreaShape, FireWallState, LightningState, PeriodicArea, PyroBlastState,
+    SkillManifestationComponent, SkillManifestationKind, XplodiumChargeState,
+};
+use crate::components::status::{
+    AbsorbStatus, ApplyStatusRequest, AssaBladeDashStatus, ExoSkeletonStatus, FireBombStatus,
+    PoisonStatus, RemoveStatusRequest, RemoveStatusRequestPayload, StatusEnum,
+    StatusEnumDiscriminants,
+};
+use crate::config::{CommonConfigs, SkillsConfig};
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::HashMap;
+use strum_macros::EnumIter;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, EnumIter, Serialize, Deserialize)]
+pub enum Skills {
+    AttackMove,
+    FireWall,
+    BrutalTestSkill,
+    Lightning,
+    Heal,
+    Mounting,
+    Poison,
+    Cure,
+    FireBomb,
+    AbsorbShield,
+    WizPyroBlast,
+    AssaBladeDash,
+    AssaPhasePrism,
+    GazXplodiumCharge,
+    GazTurret,
+    GazBarricade,
+    GazDestroyTurret,
+    GazTurretTarget,
+    FalconCarry,
+    FalconAttack,
+    Sanctuary,
+    ExoSkeleton,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SkillCastingAttributes {
+    pub casting_time: ElapsedTime,
+    pub cast_delay: ElapsedTime,
+    pub casting_range: f32,
+    // in case of Directional skills
+    pub width: Option<f32>,
+}
+
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+#[allow(dead_code)]
+pub enum SkillTargetType {
+    /// casts immediately
+    NoTarget,
+    Area,
+    Directional,
+    AnyEntity,
+    OnlyAllyButNoSelf,
+    OnlyAllyAndSelf,
+    OnlyEnemy,
+}
+
+/// A skill cast which was accepted and finished by the server
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FinishedSkillCast {
+    pub skill: Skills,
+    pub caster_entity_id: CharEntityId,
+    pub caster_pos: Vec2,
+    pub skill_pos: Option<Vec2>,
+    pub char_to_skill_dir: Vec2,
+    pub target_entity: Option<CharEntityId>,
+}
+
+/// What a finished skill cast affects, the requests are consumed by the common systems
+pub struct FinishedSkillCastParams<'a> {
+    pub now: ElapsedTime,
+    pub caster_team: Team,
+    pub caster_job_id: JobId,
+    pub caster_is_mounted: bool,
+    pub configs: &'a CommonConfigs,
+    pub hp_mod_requests: &'a mut Vec<HpModificationRequest>,
+    pub apply_status_requests: &'a mut Vec<ApplyStatusRequest>,
+    pub remove_status_requests: &'a mut Vec<RemoveStatusRequest>,
+}
+
+impl FinishedSkillCast {
+    /// Applies the immediate effects of the skill and returns its manifestation (projectile,
+    /// area etc) if it has one
+    pub fn finish(
+        &self,
+        params: &mut FinishedSkillCastParams,
+    ) -> Option<SkillManifestationComponent> {
+        let now = params.now;
+        let common_configs = params.configs;
+        let configs = &common_configs.skills;
+        let caster = self.caster_entity_id;
+        let caster_team = params.caster_team;
+        let manifestation = |pos: Vec2, die_at: ElapsedTime, kind: SkillManifestationKind| {
+            Some(SkillManifestationComponent {
+                caster_entity_id: caster,
+                caster_team,
+                pos,
+                created_at: now,
+                die_at,
+                kind,
+            })
+        };
+        let skill_pos = self.skill_pos.unwrap_or(self.caster_pos);
+        match self.skill {
+            Skills::AttackMove => {}
+            Skills::Heal => {
+                if let Some(target) = self.target_entity {
+                    params.hp_mod_requests.push(HpModificationRequest {
+                        src_entity: caster,
+                        dst_entity: target,
+                        typ: HpModificationType::Heal(configs.heal.heal),
+                    });
+                }
+            }
+            Skills::Poison => {
+                if let Some(target) = self.target_entity {
+                    params.apply_status_requests.push(ApplyStatusRequest {
+                        source_entity_id: caster,
+                        target_entity_id: target,
+                        status: StatusEnum::PoisonStatus(PoisonStatus {
+                            poison_caster_entity_id: caster,
+                            started: now,
+                            until: now.add_seconds(configs.poison.duration_seconds),
+                            next_damage_at: now,
+                            damage: configs.poison.damage,
+                        }),
+                    });
+                }
+            }
+            Skills::Cure => {
+                if let Some(target) = self.target_entity {
+                    params.remove_status_requests.push(RemoveStatusRequest {
+                        source_entity_id: caster,
+                        target_entity_id: target,
+                        payload: RemoveStatusRequestPayload::ByNature(StatusNature::Harmful),
+                    });
+                }
+            }
+            Skills::FireBomb => {
+                if let Some(target) = self.target_entity {
+                    params.apply_status_requests.push(ApplyStatusRequest {
+                        source_entity_id: caster,
+                        target_entity_id: target,
+                        status: StatusEnum::FireBombStatus(FireBombStatus::new(
+                            caster,
+                            caster_team,
+                            configs.firebomb.damage,
+                            now,
+                        )),
+                    });
+                }
+            }
+            Skills::AbsorbShield => {
+                if let Some(target) = self.target_entity {
+                    params.apply_status_requests.push(ApplyStatusRequest {
+                        source_entity_id: caster,
+                        target_entity_id: target,
+                        status: StatusEnum::AbsorbStatus(AbsorbStatus::new(
+                            caster,
+                            now,
+                            configs.absorb_shield.duration_seconds,
+                        )),
+                    });
+                }
+            }
+            Skills::Mounting => {
+                if params.caster_is_mounted {
+                    params.remove_status_requests.push(RemoveStatusRequest {
+                        source_entity_id: caster,
+                        target_entity_id: caster,
+                        payload: RemoveStatusRequestPayload::ByDiscr(
+                            StatusEnumDiscriminants::MountedStatus,
+                        ),
+                    });
+                } else {
+                    let speedup = match params.caster_job_id {
+                        JobId::CRUSADER => common_configs.stats.player.crusader.mounted_speedup,
+                        _ => percentage(30),
+                    };
+                    params.apply_status_requests.push(ApplyStatusRequest {
+                        source_entity_id: caster,
+                        target_entity_id: caster,
+                        status: StatusEnum::MountedStatus { speedup },
+                    });
+                }
+            }
+            Skills::ExoSkeleton => {
+                let exo = &configs.exoskeleton;
+                params.apply_status_requests.push(ApplyStatusRequest {
+                    source_entity_id: caster,
+                    target_entity_id: caster,
+                    status: StatusEnum::ExoSkeletonStatus(ExoSkeletonStatus {
+                        started: now,
+                        until: now.add_seconds(exo.duration_seconds),
+                        armor: exo.armor,
+                        attack_range: exo.attack_range,
+                        movement_speed: exo.movement_speed,
+                        attack_damage: exo.attack_damage,
+                        attack_speed: exo.attack_speed,
+                    }),
+                });
+            }
+            Skills::AssaBladeDash => {
+                let dash = &configs.assa_blade_dash;
+                let range = dash.attributes.casting_range;
+                params.apply_status_requests.push(ApplyStatusRequest {
+                    source_entity_id: caster,
+                    target_entity_id: caster,
+                    status: StatusEnum::AssaBladeDashStatus(AssaBladeDashStatus {
+                        caster_entity_id: caster,
+                        started_at: now,
+                        ends_at: now.add_seconds(dash.duration_seconds),
+                        start_pos: self.caster_pos,
+                        center: self.caster_pos + self.char_to_skill_dir * (range / 2.0),
+                        rot_radian: area_rotation(&self.char_to_skill_dir),
+                        vector: self.char_to_skill_dir * range,
+                        half_extents: v2(dash.attributes.width.unwrap_or(1.0), range) / 2.0,
+                        first_damage: dash.first_damage,
+                        second_damage: dash.second_damage,
+                        forward_damage_done: false,
+                        backward_damage_done: false,
+                    }),
+                });
+            }
+            Skills::Sanctuary => {
+                let sanctuary = &configs.sanctuary;
+                return manifestation(
+                    skill_pos,
+                    now.add_seconds(sanctuary.duration),
+                    SkillManifestationKind::Sanctuary(PeriodicArea {
+                        shape: AreaShape::Rect {
+                            half_extents: v2(2.5, 2.5),
+                            rotation: 0.0,
+                        },
+                        typ: HpModificationType::Heal(sanctuary.heal),
+                        period_seconds: sanctuary.heal_freq_seconds,
+                        next_action_at: now,
+                    }),
+                );
+            }
+            Skills::BrutalTestSkill => {
+                let brutal = &configs.brutal_test_skill;
+                return manifestation(
+                    skill_pos,
+                    now.add_seconds(30.0),
+                    SkillManifestationKind::BrutalTestSkill(PeriodicArea {
+                        shape: AreaShape::Rect {
+                            half_extents: v2(brutal.width, brutal.height) / 2.0,
+                            rotation: area_rotation(&self.char_to_skill_dir),
+                        },
+                        typ: HpModificationType::SpellDamage(
+                            brutal.damage,
+                            DamageDisplayType::Combo(10),
+                        ),
+                        period_seconds: 0.5,
+                        next_action_at: now,
+                    }),
+                );
+            }
+            Skills::Lightning => {
+                return manifestation(
+                    skill_pos,
+                    now.add_seconds(12.0),
+                    SkillManifestationKind::Lightning(LightningState {
+                        dir: self.char_to_skill_dir,
+                        action_count: 0,
+                        next_action_at: now,
+                        next_damage_at: now,
+                        last_strike_pos: None,
+                    }),
+                );
+            }
+            Skills::FireWall => {
+                let firewall = &configs.firewall;
+                return manifestation(
+                    skill_pos,
+                    now.add_seconds(firewall.duration_seconds),
+                    SkillManifestationKind::FireWall(FireWallState {
+                        half_extents: v2(firewall.width as f32 / 2.0, 0.5),
+                        rotation: area_rotation(&self.char_to_skill_dir),
+                        damage: firewall.damage,
+                        pushback_force: firewall.pushback_force,
+                        force_duration_seconds: firewall.force_duration_seconds,
+                        cannot_damage_until: HashMap::new(),
+                    }),
+                );
+            }
+            Skills::WizPyroBlast => {
+                let pyro = &configs.wiz_pyroblast.inner;
+                if let Some(target) = self.target_entity {
+                    // it is removed when it reaches the target or the target disappears
+                    return manifestation(
+                        self.caster_pos,
+                        now.add_seconds(60.0),
+                        SkillManifestationKind::PyroBlast(PyroBlastState {
+                            target_entity_id: target,
+                            moving_speed: pyro.moving_speed,
+                            damage: pyro.damage,
+                            secondary_damage: pyro.secondary_damage,
+                            splash_radius: pyro.splash_radius,
+                        }),
+                    );
+                }
+            }
+            Skills::GazXplodiumCharge => {
+                let xplodium = &configs.gaz_xplodium_charge.inner;
+                let lands_at = now.add_seconds(xplodium.missile_travel_duration_seconds);
+                let detonates_at = lands_at.add_seconds(xplodium.detonation_duration);
+                return manifestation(
+                    self.caster_pos,
+                    detonates_at.add_seconds(1.0),
+                    SkillManifestationKind::XplodiumCharge(XplodiumChargeState {
+                        start_pos: self.caster_pos,
+                        end_pos: skill_pos,
+                        lands_at,
+                        detonates_at,
+                        damage: xplodium.damage,
+                        stun_duration_seconds: xplodium.stun_duration_seconds,
+                        explosion_area: xplodium.explosion_area,
+                    }),
+                );
+            }
+            Skills::AssaPhasePrism
+            | Skills::GazTurret
+            | Skills::GazBarricade
+            | Skills::GazDestroyTurret
+            | Skills::GazTurretTarget
+            | Skills::FalconCarry
+            | Skills::FalconAttack => {
+                log::warn!("{:?} is not simulated by the server yet", self.skill);
+            }
+        }
+        return None;
+    }
+}
+
+const GAZ_TURRET_TARGET_ATTRIBUTES: SkillCastingAttributes = SkillCastingAttributes {
+    casting_time: ElapsedTime(0.0),
+    cast_delay: ElapsedTime(0.0),
+    casting_range: 999_999_999.0,
+    width: None,
+};
+
+const ATTACK_MOVE_ATTRIBUTES: SkillCastingAttributes = SkillCastingAttributes {
+    casting_time: ElapsedTime(0.0),
+    cast_delay: ElapsedTime(0.0),
+    casting_range: 200_000_000.0,
+    width: None,
+};
+
+impl Skills {
+    pub fn get_skill_target_type(&self) -> SkillTargetType {
+        match self {
+            Skills::AttackMove => SkillTargetType::Area,
+            Skills::FireWall => SkillTargetType::Area,
+            Skills::BrutalTestSkill => SkillTargetType::Area,
+            Skills::Lightning => SkillTargetType::Area,
+            Skills::Heal => SkillTargetType::OnlyAllyAndSelf,
+            Skills::Mounting => SkillTargetType::NoTarget,
+            Skills::Poison => SkillTargetType::OnlyEnemy,
+            Skills::Cure => SkillTargetType::OnlyAllyAndSelf,
+            Skills::FireBomb => SkillTargetType::OnlyEnemy,
+            Skills::AbsorbShield => SkillTargetType::OnlyAllyAndSelf,
+            Skills::WizPyroBlast => SkillTargetType::OnlyEnemy,
+            Skills::AssaBladeDash => SkillTargetType::Directional,
+            Skills::AssaPhasePrism => SkillTargetType::Directional,
+            Skills::GazXplodiumCharge => SkillTargetType::Area,
+            Skills::GazTurret => SkillTargetType::Area,
+            Skills::GazBarricade => SkillTargetType::Area,
+            Skills::GazDestroyTurret => SkillTargetType::OnlyAllyButNoSelf,
+            Skills::GazTurretTarget => SkillTargetType::OnlyEnemy,
+            Skills::FalconCarry => SkillTargetType::OnlyAllyAndSelf,
+            Skills::FalconAttack => SkillTargetType::Directional,
+            Skills::Sanctuary => SkillTargetType::Area,
+            Skills::ExoSkeleton => SkillTargetType::NoTarget,
+        }
+    }
+
+    pub fn get_cast_attributes<'a>(
+        &'a self,
+        configs: &'a SkillsConfig,
+        is_mounted: bool,
+    ) -> &'a SkillCastingAttributes {
+        match self {
+            Skills::WizPyroBlast => &configs.wiz_pyroblast.attributes,
+            Skills::FireWall => &configs.firewall.attributes,
+            Skills::Heal => &configs.heal.attributes,
+            Skills::BrutalTestSkill => &configs.brutal_test_skill.attributes,
+            Skills::Lightning => &configs.lightning.attributes,
+            Skills::Mounting => {
+                if is_mounted {
+                    &configs.unmounting
+                } else {
+                    &configs.mounting
+                }
+            }
+            Skills::Poison => &configs.poison.attributes,
+            Skills::Cure => &configs.cure,
+            Skills::FireBomb => &configs.firebomb.attributes,
+            Skills::AbsorbShield => &configs.absorb_shield.attributes,
+            Skills::AssaBladeDash => &configs.assa_blade_dash.attributes,
+            Skills::AssaPhasePrism => &configs.assa_phase_prism.attributes,
+            Skills::GazXplodiumCharge => &configs.gaz_xplodium_charge.attributes,
+            Skills::GazTurret => &configs.gaz_turret.attributes,
+            Skills::GazDestroyTurret => &configs.gaz_destroy_turret,
+            Skills::GazTurretTarget => &GAZ_TURRET_TARGET_ATTRIBUTES,
+            Skills::FalconCarry => &configs.falcon_carry.attributes,
+            Skills::FalconAttack => &configs.falcon_attack.attributes,
+            Skills::Sanctuary => &configs.sanctuary.attributes,
+            Skills::ExoSkeleton => &configs.exoskeleton.attributes,
+            Skills::AttackMove => &ATTACK_MOVE_ATTRIBUTES,
+            Skills::GazBarricade => &configs.gaz_barricade.attributes,
+        }
+    }
+
+    pub fn limit_vector_into_range(char_pos: &Vec2, mouse_pos: &Vec2, range: f32) -> (Vec2, Vec2) {
+        let dir2d = mouse_pos - char_pos;
+        let dir_vector = dir2d.normalize();
+        let pos = char_pos + dir_vector * dir2d.magnitude().min(range);
+        return (pos, dir_vector);
+    }
+
+    pub fn is_casting_allowed_based_on_target(
+        skill_target_type: SkillTargetType,
+        skill_casting_range: f32,
+        caster_id: CharEntityId,
+        target_entity: Option<CharEntityId>,
+        target_distance: f32,
+    ) -> bool {
+        match skill_target_type {
+            SkillTargetType::Area => true,
+            SkillTargetType::Directional => true,
+            SkillTargetType::NoTarget => true,
+            SkillTargetType::AnyEntity => {
+                target_entity.is_some() && skill_casting_range >= target_distance
+            }
+            SkillTargetType::OnlyAllyButNoSelf => {
+                target_entity.map(|it| it != caster_id).unwrap_or(false)
+                    && skill_casting_range >= target_distance
+            }
+            SkillTargetType::OnlyAllyAndSelf => {
+                target_entity.is_some() && skill_casting_range >= target_distance
+            }
+            SkillTargetType::OnlyEnemy => {
+                target_entity.is_some() && skill_casting_range >= target_distance
+            }
+        }
+    }
+}
@@ -1,8 +1,19 @@
 use crate::common::{v2, Vec2};
-use crate::components::char::{AuthorizedCharStateComponent, CharDir, CharState};
+use crate::components::char::{
+    AuthorizedCharStateComponent, CharDir, CharState, EntityTarget, ServerEntityId,
+};
+use crate::components::manifestation::SkillManifestationComponent;
+use crate::components::status::StatusesComponent;
+use crate::packets::from_server::AckEntry;
+use crate::packets::to_server::PacketReadErr;
 use crate::packets::SocketBuffer;
+use serde::de::{SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
 use serde::Deserialize;
+use serde::Deserializer;
 use serde::Serialize;
+use serde::Serializer;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CharSnapshot {
@@ -16,10 +27,10 @@ impl CharSnapshot {
         }
     }
 
-    pub fn from_buffer(buf: &mut SocketBuffer) -> CharSnapshot {
-        CharSnapshot {
-            state: AuthorizedCharStateComponent::from_buffer(buf),
-        }
+    pub fn from_buffer(buf: &mut SocketBuffer) -> Result<CharSnapshot, PacketReadErr> {
+        Ok(CharSnapshot {
+            state: AuthorizedCharStateComponent::from_buffer(buf)?,
+        })
     }
 
     pub fn write_into_buffer(&self, buf: &mut SocketBuffer) {
@@ -35,19 +46,688 @@ impl Default for CharSnapshot {
     }
 }
 
-#[derive(Default, Debug)]
+/// The state of every replicated entity at a given tick
+#[derive(Default, Debug, Clone)]
 pub struct WorldSnapshot {
-    pub desktop_snapshot: CharSnapshot,
+    pub chars: HashMap<ServerEntityId, CharSnapshot>,
+    /// skill areas and projectiles
+    pub manifestations: HashMap<ServerEntityId, SkillManifestationComponent>,
+    /// indexed by the char they belong to
+    pub statuses: HashMap<ServerEntityId, StatusesComponent>,
 }
 
 impl WorldSnapshot {
-    pub fn from_buffer(buf: &mut SocketBuffer) -> WorldSnapshot {
-        WorldSnapshot {
-            desktop_snapshot: CharSnapshot::from_buffer(buf),
+    pub fn get_char(&self, id: ServerEntityId) -> Option<&CharSnapshot> {
+        self.chars.get(&id)
+    }
+
+    pub fn set_char(&mut self, id: ServerEntityId, state: &AuthorizedCharStateComponent) {
+        self.chars.insert(id, CharSnapshot::from(state));
+    }
+
+    pub fn from_buffer(buf: &mut SocketBuffer) -> Result<WorldSnapshot, PacketReadErr> {
+        let count = buf.read_u16()? as usize;
+        let mut chars = HashMap::with_capacity(count);
+        for _ in 0..count {
+            let id = ServerEntityId::from_u64(buf.read_u64()?);
+            chars.insert(id, CharSnapshot::from_buffer(buf)?);
         }
+        Ok(WorldSnapshot {
+            chars,
+            ..Default::default()
+        })
     }
 
     pub fn write_into_buffer(&self, buf: &mut SocketBuffer) {
-        self.desktop_snapshot.write_into_buffer(buf);
+        buf.write_u16(self.chars.len() as u16);
+        for (id, char_snapshot) in &self.chars {
+            buf.write_u64(id.as_u64());
+            char_snapshot.write_into_buffer(buf);
+        }
+    }
+}
+
+/// Positions in deltas are sent as fixed point numbers with this many steps per unit.
+/// The error (< 0.004) stays below `ALLOWED_F32_DIFF`, so it can't cause mispredictions.
+const POS_QUANTIZATION_STEPS: f32 = 128.0;
+
+fn quantize(value: f32) -> i32 {
+    (value * POS_QUANTIZATION_STEPS).round() as i32
+}
+
+fn dequantize(value: i32) -> f32 {
+    value as f32 / POS_QUANTIZATION_STEPS
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuantizedPos {
+    /// Offset from the baseline position, enough for ±255 units
+    Offset(i16, i16),
+    Absolute(i32, i32),
+}
+
+/// The changed fields of an `AuthorizedCharStateComponent` compared to a baseline snapshot
+/// which the client has already acknowledged.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CharSnapshotDelta {
+    pub pos: Option<QuantizedPos>,
+    pub dir: Option<CharDir>,
+    pub state: Option<CharState>,
+    pub target: Option<Option<EntityTarget>>,
+}
+
+impl CharSnapshotDelta {
+    const POS_OFFSET: u8 = 1 << 0;
+    const POS_ABSOLUTE: u8 = 1 << 1;
+    const DIR: u8 = 1 << 2;
+    const STATE: u8 = 1 << 3;
+    const TARGET: u8 = 1 << 4;
+    const MAX_FIELD_COUNT: usize = 4;
+
+    pub fn diff(baseline: &CharSnapshot, current: &CharSnapshot) -> CharSnapshotDelta {
+        let (base, cur) = (&baseline.state, &current.state);
+        let (base_x, base_y) = (quantize(base.pos().x), quantize(base.pos().y));
+        let (cur_x, cur_y) = (quantize(cur.pos().x), quantize(cur.pos().y));
+        let pos = if base_x == cur_x && base_y == cur_y {
+            None
+        } else {
+            let (dx, dy) = (cur_x - base_x, cur_y - base_y);
+            let fits_into_i16 =
+                |it: i32| it >= i16::min_value() as i32 && it <= i16::max_value() as i32;
+            if fits_into_i16(dx) && fits_into_i16(dy) {
+                Some(QuantizedPos::Offset(dx as i16, dy as i16))
+            } else {
+                Some(QuantizedPos::Absolute(cur_x, cur_y))
+            }
+        };
+        CharSnapshotDelta {
+            pos,
+            dir: if base.dir() != cur.dir() {
+                Some(cur.dir())
+            } else {
+                None
+            },
+            state: if base.state() != cur.state() {
+                Some(cur.state().clone())
+            } else {
+                None
+            },
+            target: if base.target != cur.target {
+                Some(cur.target.clone())
+            } else {
+                None
+            },
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mask() == 0
+    }
+
+    /// Returns the snapshot exactly as the client will see it after applying the delta,
+    /// the server must store this one as a baseline and not its own unquantized state.
+    pub fn apply(&self, baseline: &CharSnapshot) -> CharSnapshot {
+        let mut state = baseline.state.clone();
+        match self.pos {
+            Some(QuantizedPos::Offset(dx, dy)) => {
+                let base_pos = baseline.state.pos();
+                state.set_pos(v2(
+                    dequantize(quantize(base_pos.x) + dx as i32),
+                    dequantize(quantize(base_pos.y) + dy as i32),
+                ));
+            }
+            Some(QuantizedPos::Absolute(x, y)) => {
+                state.set_pos(v2(dequantize(x), dequantize(y)));
+            }
+            None => {}
+        }
+        if let Some(dir) = self.dir {
+            state.set_dir(dir);
+        }
+        if let Some(new_state) = &self.state {
+            let dir = state.dir();
+            state.set_state(new_state.clone(), dir);
+        }
+        if let Some(target) = &self.target {
+            state.target = target.clone();
+        }
+        CharSnapshot { state }
+    }
+
+    fn mask(&self) -> u8 {
+        let mut mask = 0;
+        match self.pos {
+            Some(QuantizedPos::Offset(..)) => mask |= CharSnapshotDelta::POS_OFFSET,
+            Some(QuantizedPos::Absolute(..)) => mask |= CharSnapshotDelta::POS_ABSOLUTE,
+            None => {}
+        }
+        if self.dir.is_some() {
+            mask |= CharSnapshotDelta::DIR;
+        }
+        if self.state.is_some() {
+            mask |= CharSnapshotDelta::STATE;
+        }
+        if self.target.is_some() {
+            mask |= CharSnapshotDelta::TARGET;
+        }
+        mask
+    }
+}
+
+// The delta is serialized as a tuple of the change mask followed by only the changed fields.
+// It relies on bincode not being self describing: the deserializer asks for the maximum
+// number of elements but reads only the ones the mask contains.
+impl Serialize for CharSnapshotDelta {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mask = self.mask();
+        let mut tuple = serializer.serialize_tuple(1 + mask.count_ones() as usize)?;
+        tuple.serialize_element(&mask)?;
+        match self.pos {
+            Some(QuantizedPos::Offset(dx, dy)) => tuple.serialize_element(&(dx, dy))?,
+            Some(QuantizedPos::Absolute(x, y)) => tuple.serialize_element(&(x, y))?,
+            None => {}
+        }
+        if let Some(dir) = &self.dir {
+            tuple.serialize_element(dir)?;
+        }
+        if let Some(state) = &self.state {
+            tuple.serialize_element(state)?;
+        }
+        if let Some(target) = &self.target {
+            tuple.serialize_element(target)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for CharSnapshotDelta {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct DeltaVisitor;
+
+        impl<'de> Visitor<'de> for DeltaVisitor {
+            type Value = CharSnapshotDelta;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a change mask followed by the changed fields")
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<CharSnapshotDelta, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let missing = |index| serde::de::Error::invalid_length(index, &self);
+                let mask: u8 = seq.next_element()?.ok_or_else(|| missing(0))?;
+                let mut delta = CharSnapshotDelta::default();
+                if mask & CharSnapshotDelta::POS_OFFSET != 0 {
+                    let (dx, dy): (i16, i16) = seq.next_element()?.ok_or_else(|| missing(1))?;
+                    delta.pos = Some(QuantizedPos::Offset(dx, dy));
+                } else if mask & CharSnapshotDelta::POS_ABSOLUTE != 0 {
+                    let (x, y): (i32, i32) = seq.next_element()?.ok_or_else(|| missing(1))?;
+                    delta.pos = Some(QuantizedPos::Absolute(x, y));
+                }
+                if mask & CharSnapshotDelta::DIR != 0 {
+                    delta.dir = Some(seq.next_element()?.ok_or_else(|| missing(2))?);
+                }
+                if mask & CharSnapshotDelta::STATE != 0 {
+                    delta.state = Some(seq.next_element()?.ok_or_else(|| missing(3))?);
+                }
+                if mask & CharSnapshotDelta::TARGET != 0 {
+                    delta.target = Some(seq.next_element()?.ok_or_else(|| missing(4))?);
+                }
+                Ok(delta)
+            }
+        }
+
+        deserializer.deserialize_tuple(1 + CharSnapshotDelta::MAX_FIELD_COUNT, DeltaVisitor)
+    }
+}
+
+/// The last few snapshots which were sent to (server) or received from (client) the other side,
+/// indexed by their ack tick. Deltas are encoded and decoded against them.
+pub struct SnapshotHistory {
+    snapshots: VecDeque<(u64, WorldSnapshot)>,
+}
+
+impl SnapshotHistory {
+    const CAPACITY: usize = 32;
+
+    pub fn new() -> SnapshotHistory {
+        SnapshotHistory {
+            snapshots: VecDeque::with_capacity(SnapshotHistory::CAPACITY),
+        }
+    }
+
+    pub fn push(&mut self, tick: u64, snapshot: WorldSnapshot) {
+        if self.snapshots.len() == SnapshotHistory::CAPACITY {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back((tick, snapshot));
+    }
+
+    pub fn get(&self, tick: u64) -> Option<&WorldSnapshot> {
+        self.snapshots
+            .iter()
+            .rev()
+            .find(|(it, _)| *it == tick)
+            .map(|(_, snapshot)| snapshot)
+    }
+
+    /// Snapshots older than the acknowledged one won't be used as baselines anymore
+    pub fn remove_older_than(&mut self, tick: u64) {
+        while self
+            .snapshots
+            .front()
+            .map(|it| it.0 < tick)
+            .unwrap_or(false)
+        {
+            self.snapshots.pop_front();
+        }
+    }
+}
+
+/// Encodes `current` as Ack entries against the baseline. The first entry always belongs
+/// to `first_id` (the char controlled by the receiving client). The entities of the baseline
+/// which are missing from `current` are sent as removed.
+/// Returns the entries and the snapshot which the client will reconstruct from them.
+/// `world` contains every entity, the ones which are missing only from `current` are out of the
+/// vision of the recipient. Without a baseline, only the removal of the `known` entities (the
+/// ones the recipient might still show) is sent, the hidden ones must not be revealed
+pub fn encode_world_snapshot(
+    current: &WorldSnapshot,
+    baseline: Option<&WorldSnapshot>,
+    first_id: ServerEntityId,
+    world: &WorldSnapshot,
+    known: &HashSet<ServerEntityId>,
+) -> (Vec<AckEntry>, WorldSnapshot) {
+    let mut entries = Vec::with_capacity(current.chars.len());
+    let mut reconstructed = WorldSnapshot::default();
+    let ids =
+        std::iter::once(first_id).chain(current.chars.keys().cloned().filter(|it| *it != first_id));
+    for id in ids {
+        let char_snapshot = match current.get_char(id) {
+            Some(it) => it,
+            None => continue,
+        };
+        match baseline.and_then(|it| it.get_char(id)) {
+            Some(base_char) => {
+                let delta = CharSnapshotDelta::diff(base_char, char_snapshot);
+                reconstructed.chars.insert(id, delta.apply(base_char));
+                // unchanged entities are omitted, except the first one
+                if id == first_id || !delta.is_empty() {
+                    entries.push(AckEntry::EntityDelta { id, delta });
+                }
+            }
+            None => {
+                reconstructed.chars.insert(id, char_snapshot.clone());
+                entries.push(AckEntry::EntityState {
+                    id,
+                    char_snapshot: char_snapshot.clone(),
+                });
+            }
+        }
+    }
+    for (id, manifestation) in &current.manifestations {
+        let manifestation = manifestation.without_server_state();
+        if baseline.and_then(|it| it.manifestations.get(id)) != Some(&manifestation) {
+            entries.push(AckEntry::ManifestationState {
+                id: *id,
+                manifestation: manifestation.clone(),
+            });
+        }
+        reconstructed.manifestations.insert(*id, manifestation);
+    }
+    for (id, statuses) in &current.statuses {
+        reconstructed.statuses.insert(*id, statuses.clone());
+        if baseline.and_then(|it| it.statuses.get(id)) != Some(statuses) {
+            entries.push(AckEntry::StatusesState {
+                id: *id,
+                statuses: statuses.clone(),
+            });
+        }
+    }
+    let removed: Vec<&ServerEntityId> = match baseline {
+        Some(baseline) => baseline
+            .chars
+            .keys()
+            .filter(|id| !current.chars.contains_key(id))
+            .chain(
+                baseline
+                    .manifestations
+                    .keys()
+                    .filter(|id| !current.manifestations.contains_key(id)),
+            )
+            .collect(),
+        None => known
+            .iter()
+            .filter(|id| {
+                !current.chars.contains_key(id) && !current.manifestations.contains_key(id)
+            })
+            .collect(),
+    };
+    for id in removed {
+        let still_exists = world.chars.contains_key(id) || world.manifestations.contains_key(id);
+        entries.push(if still_exists {
+            AckEntry::EntityOutOfVision { id: *id }
+        } else {
+            AckEntry::EntityRemoved { id: *id }
+        });
+    }
+    return (entries, reconstructed);
+}
+
+/// Reconstructs the full world snapshot from the baseline and the received Ack entries.
+/// Returns None if a delta arrived for an entity the baseline doesn't contain.
+pub fn decode_world_snapshot(
+    baseline: Option<&WorldSnapshot>,
+    entries: &[AckEntry],
+) -> Option<WorldSnapshot> {
+    let mut world_snapshot = baseline.cloned().unwrap_or_default();
+    for entry in entries {
+        match entry {
+            AckEntry::EntityState { id, char_snapshot } => {
+                world_snapshot.chars.insert(*id, char_snapshot.clone());
+            }
+            AckEntry::EntityDelta { id, delta } => {
+                let char_snapshot = delta.apply(baseline?.get_char(*id)?);
+                world_snapshot.chars.insert(*id, char_snapshot);
+            }
+            AckEntry::EntityRemoved { id } | AckEntry::EntityOutOfVision { id } => {
+                world_snapshot.chars.remove(id);
+                world_snapshot.manifestations.remove(id);
+                world_snapshot.statuses.remove(id);
+            }
+            AckEntry::ManifestationState { id, manifestation } => {
+                world_snapshot
+                    .manifestations
+                    .insert(*id, manifestation.clone());
+            }
+            AckEntry::StatusesState { id, statuses } => {
+                world_snapshot.statuses.insert(*id, statuses.clone());
+            }
+            _ => {}
+        }
+    }
+    return Some(world_snapshot);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attack::HpModificationType;
+    use crate::char_attr::percentage;
+    use crate::common::ElapsedTime;
+    use crate::components::char::{CharEntityId, Team};
+    use crate::components::manifestation::{
+        AreaShape, FireWallState, PeriodicArea, SkillManifestationKind,
+    };
+    use crate::components::status::StatusEnum;
+    use specs::prelude::*;
+
+    fn char_snapshot(x: f32, y: f32, state: CharState) -> CharSnapshot {
+        let mut state_comp = AuthorizedCharStateComponent::new(v2(x, y));
+        state_comp.set_state(state, CharDir::South);
+        CharSnapshot { state: state_comp }
+    }
+
+    #[test]
+    fn unchanged_state_produces_empty_delta() {
+        let base = char_snapshot(238.0, -65.0, CharState::Idle);
+        let delta = CharSnapshotDelta::diff(&base, &base.clone());
+        assert!(delta.is_empty());
+        assert_eq!(bincode::serialize(&delta).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn delta_roundtrip() {
+        let base = char_snapshot(238.0, -65.0, CharState::Idle);
+        let current = char_snapshot(238.31, -65.02, CharState::Walking(v2(245.0, -204.0)));
+        let delta = CharSnapshotDelta::diff(&base, &current);
+        assert_eq!(delta.pos, Some(QuantizedPos::Offset(40, -3)));
+        assert!(delta.dir.is_none());
+        assert!(delta.target.is_none());
+
+        let bytes = bincode::serialize(&delta).unwrap();
+        let decoded: CharSnapshotDelta = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(delta, decoded);
+
+        let applied = decoded.apply(&base);
+        assert_eq!(applied.state, current.state);
+        assert_eq!(applied.state.state(), current.state.state());
+    }
+
+    #[test]
+    fn far_positions_are_sent_absolute() {
+        let base = char_snapshot(0.0, 0.0, CharState::Idle);
+        let current = char_snapshot(300.0, -300.0, CharState::Idle);
+        let delta = CharSnapshotDelta::diff(&base, &current);
+        assert_eq!(delta.pos, Some(QuantizedPos::Absolute(38400, -38400)));
+        assert_eq!(delta.apply(&base).state.pos(), v2(300.0, -300.0));
+    }
+
+    #[test]
+    fn removed_entities_are_dropped_from_the_baseline() {
+        let (player, minion) = (ServerEntityId::from_u64(1), ServerEntityId::from_u64(2));
+        let mut baseline = WorldSnapshot::default();
+        baseline
+            .chars
+            .insert(player, char_snapshot(10.0, 10.0, CharState::Idle));
+        baseline
+            .chars
+            .insert(minion, char_snapshot(20.0, 20.0, CharState::Idle));
+        let mut current = WorldSnapshot::default();
+        current
+            .chars
+            .insert(player, char_snapshot(11.0, 10.0, CharState::Idle));
+
+        let (entries, sent) =
+            encode_world_snapshot(&current, Some(&baseline), player, &current, &HashSet::new());
+        assert!(sent.get_char(minion).is_none());
+        assert!(entries.iter().any(|it| match it {
+            AckEntry::EntityRemoved { id } => *id == minion,
+            _ => false,
+        }));
+
+        let decoded = decode_world_snapshot(Some(&baseline), &entries).unwrap();
+        assert!(decoded.get_char(minion).is_none());
+        assert_eq!(
+            decoded.get_char(player).unwrap().state.pos(),
+            v2(11.0, 10.0)
+        );
+    }
+
+    #[test]
+    fn statuses_are_sent_only_when_changed() {
+        let player = ServerEntityId::from_u64(1);
+        let mut baseline = WorldSnapshot::default();
+        baseline
+            .chars
+            .insert(player, char_snapshot(10.0, 10.0, CharState::Idle));
+        baseline.statuses.insert(player, StatusesComponent::new());
+        let mut current = baseline.clone();
+
+        let (entries, _sent) =
+            encode_world_snapshot(&current, Some(&baseline), player, &current, &HashSet::new());
+        assert!(entries.iter().all(|it| match it {
+            AckEntry::StatusesState { .. } => false,
+            _ => true,
+        }));
+
+        let mut statuses = StatusesComponent::new();
+        statuses.add(StatusEnum::MountedStatus {
+            speedup: percentage(30),
+        });
+        current.statuses.insert(player, statuses);
+        let (entries, sent) =
+            encode_world_snapshot(&current, Some(&baseline), player, &current, &HashSet::new());
+        let bytes = bincode::serialize(&entries).unwrap();
+        let entries: Vec<AckEntry> = bincode::deserialize(&bytes).unwrap();
+        let decoded = decode_world_snapshot(Some(&baseline), &entries).unwrap();
+        assert!(decoded.statuses[&player].is_mounted());
+        assert!(sent.statuses[&player] == decoded.statuses[&player]);
+    }
+
+    #[test]
+    fn manifestations_are_removed_when_they_disappear() {
+        let (player, sanctuary) = (ServerEntityId::from_u64(1), ServerEntityId::from_u64(2));
+        let caster = CharEntityId::new(World::new().create_entity().build());
+        let mut baseline = WorldSnapshot::default();
+        baseline
+            .chars
+            .insert(player, char_snapshot(10.0, 10.0, CharState::Idle));
+        baseline.manifestations.insert(
+            sanctuary,
+            SkillManifestationComponent {
+                caster_entity_id: caster,
+                caster_team: Team::Left,
+                pos: v2(12.0, 10.0),
+                created_at: ElapsedTime(0.0),
+                die_at: ElapsedTime(5.0),
+                kind: SkillManifestationKind::Sanctuary(PeriodicArea {
+                    shape: AreaShape::Circle { radius: 2.0 },
+                    typ: HpModificationType::Heal(100),
+                    period_seconds: 1.0,
+                    next_action_at: ElapsedTime(1.0),
+                }),
+            },
+        );
+        let mut current = baseline.clone();
+        current.manifestations.clear();
+
+        let (entries, sent) =
+            encode_world_snapshot(&current, Some(&baseline), player, &current, &HashSet::new());
+        assert!(sent.manifestations.is_empty());
+        let decoded = decode_world_snapshot(Some(&baseline), &entries).unwrap();
+        assert!(decoded.manifestations.is_empty());
+        assert!(decoded.get_char(player).is_some());
+    }
+
+    #[test]
+    fn manifestations_are_sent_only_when_changed() {
+        let (player, firewall) = (ServerEntityId::from_u64(1), ServerEntityId::from_u64(2));
+        let caster = CharEntityId::new(World::new().create_entity().build());
+        let mut current = WorldSnapshot::default();
+        current
+            .chars
+            .insert(player, char_snapshot(10.0, 10.0, CharState::Idle));
+        current.manifestations.insert(
+            firewall,
+            SkillManifestationComponent {
+                caster_entity_id: caster,
+                caster_team: Team::Left,
+                pos: v2(12.0, 10.0),
+                created_at: ElapsedTime(0.0),
+                die_at: ElapsedTime(5.0),
+                kind: SkillManifestationKind::FireWall(FireWallState {
+                    half_extents: v2(2.5, 0.5),
+                    rotation: 0.0,
+                    damage: 100,
+                    pushback_force: 10.0,
+                    force_duration_seconds: 0.1,
+                    cannot_damage_until: std::iter::once((caster, ElapsedTime(1.0))).collect(),
+                }),
+            },
+        );
+        let is_sent = |entries: &[AckEntry]| {
+            entries.iter().any(|it| match it {
+                AckEntry::ManifestationState { id, .. } => *id == firewall,
+                _ => false,
+            })
+        };
+
+        let (entries, baseline) =
+            encode_world_snapshot(&current, None, player, &current, &HashSet::new());
+        assert!(is_sent(&entries));
+        // the server-only bookkeeping is not sent
+        match &baseline.manifestations[&firewall].kind {
+            SkillManifestationKind::FireWall(state) => {
+                assert!(state.cannot_damage_until.is_empty())
+            }
+            _ => panic!(),
+        }
+
+        // only the bookkeeping has changed
+        if let SkillManifestationKind::FireWall(state) =
+            &mut current.manifestations.get_mut(&firewall).unwrap().kind
+        {
+            state.cannot_damage_until.clear();
+        }
+        let (entries, _sent) =
+            encode_world_snapshot(&current, Some(&baseline), player, &current, &HashSet::new());
+        assert!(!is_sent(&entries));
+        let decoded = decode_world_snapshot(Some(&baseline), &entries).unwrap();
+        assert!(decoded.manifestations.contains_key(&firewall));
+
+        current.manifestations.get_mut(&firewall).unwrap().pos = v2(13.0, 10.0);
+        let (entries, _sent) =
+            encode_world_snapshot(&current, Some(&baseline), player, &current, &HashSet::new());
+        assert!(is_sent(&entries));
+    }
+
+    #[test]
+    fn hidden_entities_are_sent_as_out_of_vision() {
+        let (player, enemy) = (ServerEntityId::from_u64(1), ServerEntityId::from_u64(2));
+        let mut world = WorldSnapshot::default();
+        world
+            .chars
+            .insert(player, char_snapshot(10.0, 10.0, CharState::Idle));
+        world
+            .chars
+            .insert(enemy, char_snapshot(20.0, 20.0, CharState::Idle));
+        let mut visible = world.clone();
+        visible.chars.remove(&enemy);
+        let is_out_of_vision = |entries: &[AckEntry]| {
+            entries.iter().any(|it| match it {
+                AckEntry::EntityOutOfVision { id } => *id == enemy,
+                _ => false,
+            })
+        };
+
+        // the enemy was seen in the baseline
+        let no_known = HashSet::new();
+        let (entries, sent) =
+            encode_world_snapshot(&visible, Some(&world), player, &world, &no_known);
+        assert!(is_out_of_vision(&entries));
+        assert!(sent.get_char(enemy).is_none());
+        let decoded = decode_world_snapshot(Some(&world), &entries).unwrap();
+        assert!(decoded.get_char(enemy).is_none());
+
+        // without a baseline, only if the recipient has seen it before
+        let known = [player, enemy].iter().cloned().collect();
+        let (entries, _sent) = encode_world_snapshot(&visible, None, player, &world, &known);
+        assert!(is_out_of_vision(&entries));
+        let (entries, _sent) = encode_world_snapshot(&visible, None, player, &world, &no_known);
+        assert!(!is_out_of_vision(&entries));
+
+        // already hidden in the baseline
+        let (entries, _sent) =
+            encode_world_snapshot(&visible, Some(&visible), player, &world, &no_known);
+        assert!(!is_out_of_vision(&entries));
+    }
+
+    #[test]
+    fn removal_of_unknown_hidden_entities_is_not_sent_without_baseline() {
+        let (player, enemy) = (ServerEntityId::from_u64(1), ServerEntityId::from_u64(2));
+        let mut visible = WorldSnapshot::default();
+        visible
+            .chars
+            .insert(player, char_snapshot(10.0, 10.0, CharState::Idle));
+        let mut world = visible.clone();
+        world
+            .chars
+            .insert(enemy, char_snapshot(20.0, 20.0, CharState::Idle));
+        let known = std::iter::once(player).collect();
+
+        // the hidden enemy is alive
+        let (entries, _sent) = encode_world_snapshot(&visible, None, player, &world, &known);
+        assert_eq!(entries.len(), 1);
+        // the hidden enemy has been removed
+        let (entries, _sent) = encode_world_snapshot(&visible, None, player, &visible, &known);
+        assert_eq!(entries.len(), 1);
     }
 }
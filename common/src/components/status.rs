@@ -0,0 +1,901 @@
+use crate::attack::{
+    DamageDisplayType, HpModificationRequest, HpModificationResult, HpModificationResultType,
+    HpModificationType, WeaponType,
+};
+use crate::char_attr::{CharAttributeModifier, CharAttributeModifierCollector, Percentage};
+use crate::common::{ElapsedTime, Vec2};
+use crate::components::char::{AuthorizedCharStateComponent, CharEntityId, StatusNature, Team};
+use crate::components::manifestation::{AreaHpModificationRequest, AreaShape};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use strum_macros::EnumDiscriminants;
+
+#[derive(Debug)]
+pub enum StatusStackingResult {
+    DontAddTheNewStatus,
+    AddTheNewStatus,
+    Replace,
+}
+
+pub enum StatusUpdateResult {
+    RemoveIt,
+    KeepIt,
+}
+
+/// What the statuses of a character can affect when they are updated
+pub struct StatusUpdateParams<'a> {
+    pub self_char_id: CharEntityId,
+    pub char_state: &'a mut AuthorizedCharStateComponent,
+    pub now: ElapsedTime,
+    pub hp_mod_requests: &'a mut Vec<HpModificationRequest>,
+    pub area_hp_mod_requests: &'a mut Vec<AreaHpModificationRequest>,
+    pub area_status_requests: &'a mut Vec<ApplyStatusInAreaRequest>,
+}
+
+// the non-stackable statuses are the first ones, their discriminants are their indices in
+// StatusesComponent
+const NONSTACKABLE_STATUS_COUNT: usize = 3;
+
+#[allow(variant_size_differences)]
+#[derive(Clone, Debug, PartialEq, EnumDiscriminants, Serialize, Deserialize)]
+pub enum StatusEnum {
+    MountedStatus { speedup: Percentage },
+    ExoSkeletonStatus(ExoSkeletonStatus),
+    AssaBladeDashStatus(AssaBladeDashStatus),
+
+    // stackable statuses
+    AbsorbStatus(AbsorbStatus),
+    FireBombStatus(FireBombStatus),
+    ArmorModifierStatus(ArmorModifierStatus),
+    WalkingSpeedModifierStatus(WalkingSpeedModifierStatus),
+    PoisonStatus(PoisonStatus),
+    StunStatus(StunStatus),
+}
+
+impl StatusEnum {
+    fn can_target_move(&self) -> bool {
+        match self {
+            StatusEnum::MountedStatus { .. }
+            | StatusEnum::ExoSkeletonStatus(_)
+            | StatusEnum::AbsorbStatus(_)
+            | StatusEnum::FireBombStatus(_)
+            | StatusEnum::ArmorModifierStatus(_)
+            | StatusEnum::WalkingSpeedModifierStatus(_)
+            | StatusEnum::PoisonStatus(_) => true,
+            StatusEnum::AssaBladeDashStatus(_) | StatusEnum::StunStatus(_) => false,
+        }
+    }
+
+    fn can_target_cast(&self) -> bool {
+        match self {
+            StatusEnum::MountedStatus { .. }
+            | StatusEnum::ExoSkeletonStatus(_)
+            | StatusEnum::AssaBladeDashStatus(_)
+            | StatusEnum::AbsorbStatus(_)
+            | StatusEnum::FireBombStatus(_)
+            | StatusEnum::ArmorModifierStatus(_)
+            | StatusEnum::WalkingSpeedModifierStatus(_)
+            | StatusEnum::PoisonStatus(_) => true,
+            StatusEnum::StunStatus(_) => false,
+        }
+    }
+
+    fn can_target_be_controlled(&self) -> bool {
+        match self {
+            StatusEnum::MountedStatus { .. }
+            | StatusEnum::ExoSkeletonStatus(_)
+            | StatusEnum::AbsorbStatus(_)
+            | StatusEnum::FireBombStatus(_)
+            | StatusEnum::ArmorModifierStatus(_)
+            | StatusEnum::WalkingSpeedModifierStatus(_)
+            | StatusEnum::PoisonStatus(_) => true,
+            StatusEnum::AssaBladeDashStatus(_) | StatusEnum::StunStatus(_) => false,
+        }
+    }
+
+    fn allow_push(&self) -> bool {
+        match self {
+            StatusEnum::MountedStatus { .. }
+            | StatusEnum::ExoSkeletonStatus(_)
+            | StatusEnum::FireBombStatus(_)
+            | StatusEnum::ArmorModifierStatus(_)
+            | StatusEnum::WalkingSpeedModifierStatus(_)
+            | StatusEnum::PoisonStatus(_)
+            | StatusEnum::StunStatus(_) => true,
+            StatusEnum::AbsorbStatus(_) | StatusEnum::AssaBladeDashStatus(_) => false,
+        }
+    }
+
+    pub fn nature(&self) -> StatusNature {
+        match self {
+            StatusEnum::MountedStatus { .. }
+            | StatusEnum::ExoSkeletonStatus(_)
+            | StatusEnum::AssaBladeDashStatus(_)
+            | StatusEnum::AbsorbStatus(_) => StatusNature::Supportive,
+            StatusEnum::FireBombStatus(_)
+            | StatusEnum::PoisonStatus(_)
+            | StatusEnum::StunStatus(_) => StatusNature::Harmful,
+            StatusEnum::ArmorModifierStatus(ArmorModifierStatus { modifier, .. })
+            | StatusEnum::WalkingSpeedModifierStatus(WalkingSpeedModifierStatus {
+                modifier, ..
+            }) => {
+                if modifier.as_f32() < 0.0 {
+                    StatusNature::Harmful
+                } else {
+                    StatusNature::Supportive
+                }
+            }
+        }
+    }
+
+    pub fn calc_attribs(&self, modifiers: &mut CharAttributeModifierCollector) {
+        match self {
+            StatusEnum::MountedStatus { speedup } => {
+                // it is applied directly on the base moving speed, since it is called first
+                modifiers.change_walking_speed(
+                    CharAttributeModifier::IncreaseByPercentage(*speedup),
+                    ElapsedTime(0.0),
+                    ElapsedTime(0.0),
+                );
+            }
+            StatusEnum::ExoSkeletonStatus(status) => status.calc_attribs(modifiers),
+            StatusEnum::ArmorModifierStatus(status) => {
+                modifiers.change_armor(
+                    CharAttributeModifier::AddPercentage(status.modifier),
+                    status.started,
+                    status.until,
+                );
+            }
+            StatusEnum::WalkingSpeedModifierStatus(status) => {
+                modifiers.change_walking_speed(
+                    CharAttributeModifier::AddPercentage(status.modifier),
+                    status.started,
+                    status.until,
+                );
+            }
+            StatusEnum::AssaBladeDashStatus(_)
+            | StatusEnum::AbsorbStatus(_)
+            | StatusEnum::FireBombStatus(_)
+            | StatusEnum::PoisonStatus(_)
+            | StatusEnum::StunStatus(_) => {}
+        }
+    }
+
+    pub fn stack(&mut self, _other: &StatusEnum) -> StatusStackingResult {
+        match self {
+            StatusEnum::AbsorbStatus(_)
+            | StatusEnum::FireBombStatus(_)
+            | StatusEnum::ArmorModifierStatus(_)
+            | StatusEnum::WalkingSpeedModifierStatus(_)
+            | StatusEnum::PoisonStatus(_)
+            | StatusEnum::StunStatus(_) => StatusStackingResult::AddTheNewStatus,
+            StatusEnum::MountedStatus { .. }
+            | StatusEnum::ExoSkeletonStatus(_)
+            | StatusEnum::AssaBladeDashStatus(_) => StatusStackingResult::DontAddTheNewStatus,
+        }
+    }
+
+    pub fn update(&mut self, params: &mut StatusUpdateParams) -> StatusUpdateResult {
+        match self {
+            StatusEnum::MountedStatus { .. } => StatusUpdateResult::KeepIt,
+            StatusEnum::AssaBladeDashStatus(status) => status.update(params),
+            StatusEnum::AbsorbStatus(status) => status.update(params),
+            StatusEnum::FireBombStatus(status) => status.update(params),
+            StatusEnum::PoisonStatus(status) => status.update(params),
+            StatusEnum::ExoSkeletonStatus(ExoSkeletonStatus { until, .. })
+            | StatusEnum::ArmorModifierStatus(ArmorModifierStatus { until, .. })
+            | StatusEnum::WalkingSpeedModifierStatus(WalkingSpeedModifierStatus {
+                until, ..
+            })
+            | StatusEnum::StunStatus(StunStatus { until, .. }) => {
+                if until.has_already_passed(params.now) {
+                    StatusUpdateResult::RemoveIt
+                } else {
+                    StatusUpdateResult::KeepIt
+                }
+            }
+        }
+    }
+
+    pub fn hp_mod_is_calculated_but_not_applied_yet(
+        &mut self,
+        outcome: HpModificationResult,
+    ) -> HpModificationResult {
+        match self {
+            StatusEnum::AbsorbStatus(status) => {
+                status.hp_mod_is_calculated_but_not_applied_yet(outcome)
+            }
+            StatusEnum::MountedStatus { .. }
+            | StatusEnum::ExoSkeletonStatus(_)
+            | StatusEnum::AssaBladeDashStatus(_)
+            | StatusEnum::FireBombStatus(_)
+            | StatusEnum::ArmorModifierStatus(_)
+            | StatusEnum::WalkingSpeedModifierStatus(_)
+            | StatusEnum::PoisonStatus(_)
+            | StatusEnum::StunStatus(_) => outcome,
+        }
+    }
+
+    /// When the status started and when it will end, for the ones with a duration
+    pub fn get_duration(&self) -> Option<(ElapsedTime, ElapsedTime)> {
+        match self {
+            StatusEnum::MountedStatus { .. } | StatusEnum::AssaBladeDashStatus(_) => None,
+            StatusEnum::ExoSkeletonStatus(ExoSkeletonStatus { started, until, .. })
+            | StatusEnum::AbsorbStatus(AbsorbStatus { started, until, .. })
+            | StatusEnum::FireBombStatus(FireBombStatus { started, until, .. })
+            | StatusEnum::ArmorModifierStatus(ArmorModifierStatus { started, until, .. })
+            | StatusEnum::WalkingSpeedModifierStatus(WalkingSpeedModifierStatus {
+                started,
+                until,
+                ..
+            })
+            | StatusEnum::PoisonStatus(PoisonStatus { started, until, .. })
+            | StatusEnum::StunStatus(StunStatus { started, until, .. }) => Some((*started, *until)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExoSkeletonStatus {
+    pub started: ElapsedTime,
+    pub until: ElapsedTime,
+    pub armor: Percentage,
+    pub attack_range: Percentage,
+    pub movement_speed: Percentage,
+    pub attack_damage: Percentage,
+    pub attack_speed: Percentage,
+}
+
+impl ExoSkeletonStatus {
+    fn calc_attribs(&self, modifiers: &mut CharAttributeModifierCollector) {
+        modifiers.change_armor(
+            CharAttributeModifier::AddPercentage(self.armor),
+            self.started,
+            self.until,
+        );
+        modifiers.change_walking_speed(
+            CharAttributeModifier::AddPercentage(self.movement_speed),
+            self.started,
+            self.until,
+        );
+        modifiers.change_attack_range(
+            CharAttributeModifier::AddPercentage(self.attack_range),
+            self.started,
+            self.until,
+        );
+        modifiers.change_attack_damage(
+            CharAttributeModifier::IncreaseByPercentage(self.attack_damage),
+            self.started,
+            self.until,
+        );
+        modifiers.change_attack_speed(
+            CharAttributeModifier::AddPercentage(self.attack_speed),
+            self.started,
+            self.until,
+        );
+    }
+}
+
+/// The caster dashes forward along `vector` then back to where it started, damaging the enemies
+/// in the rectangle it went through in both ways
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AssaBladeDashStatus {
+    pub caster_entity_id: CharEntityId,
+    pub started_at: ElapsedTime,
+    pub ends_at: ElapsedTime,
+    pub start_pos: Vec2,
+    pub center: Vec2,
+    pub rot_radian: f32,
+    pub vector: Vec2,
+    pub half_extents: Vec2,
+    pub first_damage: u32,
+    pub second_damage: u32,
+    pub forward_damage_done: bool,
+    pub backward_damage_done: bool,
+}
+
+impl AssaBladeDashStatus {
+    fn update(&mut self, params: &mut StatusUpdateParams) -> StatusUpdateResult {
+        if self.ends_at.has_already_passed(params.now) {
+            params.char_state.set_pos(self.start_pos);
+            return StatusUpdateResult::RemoveIt;
+        }
+        let duration_percentage = params.now.percentage_between(self.started_at, self.ends_at);
+        let travelled = if duration_percentage < 0.5 {
+            duration_percentage * 2.0
+        } else {
+            (1.0 - duration_percentage) * 2.0
+        };
+        params
+            .char_state
+            .set_pos(self.start_pos + self.vector * travelled);
+
+        let damage = if !self.forward_damage_done && duration_percentage > 0.25 {
+            self.forward_damage_done = true;
+            Some(self.first_damage)
+        } else if !self.backward_damage_done && duration_percentage > 0.75 {
+            self.backward_damage_done = true;
+            Some(self.second_damage)
+        } else {
+            None
+        };
+        if let Some(damage) = damage {
+            params.area_hp_mod_requests.push(AreaHpModificationRequest {
+                shape: AreaShape::Rect {
+                    half_extents: self.half_extents,
+                    rotation: self.rot_radian,
+                },
+                pos: self.center,
+                src_entity: self.caster_entity_id,
+                typ: HpModificationType::BasicDamage(
+                    damage,
+                    DamageDisplayType::SingleNumber,
+                    WeaponType::Sword,
+                ),
+                except: None,
+            });
+        }
+        return StatusUpdateResult::KeepIt;
+    }
+}
+
+/// Absorbs every damage while it lasts, then the absorbed amount heals the target
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AbsorbStatus {
+    pub caster_entity_id: CharEntityId,
+    pub started: ElapsedTime,
+    pub until: ElapsedTime,
+    pub absorbed_damage: u32,
+}
+
+impl AbsorbStatus {
+    pub fn new(caster_entity_id: CharEntityId, now: ElapsedTime, duration: f32) -> AbsorbStatus {
+        AbsorbStatus {
+            caster_entity_id,
+            started: now,
+            until: now.add_seconds(duration),
+            absorbed_damage: 0,
+        }
+    }
+
+    fn update(&mut self, params: &mut StatusUpdateParams) -> StatusUpdateResult {
+        if self.until.has_not_passed_yet(params.now) {
+            return StatusUpdateResult::KeepIt;
+        }
+        if self.absorbed_damage > 0 {
+            params.hp_mod_requests.push(HpModificationRequest {
+                src_entity: self.caster_entity_id,
+                dst_entity: params.self_char_id,
+                typ: HpModificationType::Heal(self.absorbed_damage),
+            });
+        }
+        return StatusUpdateResult::RemoveIt;
+    }
+
+    fn hp_mod_is_calculated_but_not_applied_yet(
+        &mut self,
+        outcome: HpModificationResult,
+    ) -> HpModificationResult {
+        match outcome.typ {
+            HpModificationResultType::Ok(hp_mod) => match hp_mod {
+                HpModificationType::BasicDamage(value, _, _)
+                | HpModificationType::SpellDamage(value, _)
+                | HpModificationType::Poison(value) => {
+                    self.absorbed_damage += value;
+                    return outcome.absorbed();
+                }
+                HpModificationType::Heal(_) => return outcome,
+            },
+            HpModificationResultType::Blocked | HpModificationResultType::Absorbed => {
+                return outcome
+            }
+        }
+    }
+}
+
+/// Explodes when it ends, damaging everyone around the target and spreading onto them once
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FireBombStatus {
+    pub caster_entity_id: CharEntityId,
+    pub caster_team: Team,
+    pub damage: u32,
+    pub started: ElapsedTime,
+    pub until: ElapsedTime,
+    pub spread_count: u8,
+}
+
+impl FireBombStatus {
+    const EXPLOSION_RADIUS: f32 = 2.0;
+    const DURATION_SECONDS: f32 = 2.0;
+
+    pub fn new(
+        caster_entity_id: CharEntityId,
+        caster_team: Team,
+        damage: u32,
+        now: ElapsedTime,
+    ) -> FireBombStatus {
+        FireBombStatus {
+            caster_entity_id,
+            caster_team,
+            damage,
+            started: now,
+            until: now.add_seconds(FireBombStatus::DURATION_SECONDS),
+            spread_count: 0,
+        }
+    }
+
+    fn update(&mut self, params: &mut StatusUpdateParams) -> StatusUpdateResult {
+        if self.until.has_not_passed_yet(params.now) {
+            return StatusUpdateResult::KeepIt;
+        }
+        let shape = AreaShape::Circle {
+            radius: FireBombStatus::EXPLOSION_RADIUS,
+        };
+        let pos = params.char_state.pos();
+        params.area_hp_mod_requests.push(AreaHpModificationRequest {
+            shape: shape.clone(),
+            pos,
+            src_entity: self.caster_entity_id,
+            typ: HpModificationType::SpellDamage(self.damage, DamageDisplayType::Combo(10)),
+            except: None,
+        });
+        if self.spread_count < 1 {
+            let mut spread = FireBombStatus::new(
+                self.caster_entity_id,
+                self.caster_team,
+                self.damage,
+                params.now,
+            );
+            spread.spread_count = self.spread_count + 1;
+            params.area_status_requests.push(ApplyStatusInAreaRequest {
+                source_entity_id: self.caster_entity_id,
+                status: StatusEnum::FireBombStatus(spread),
+                shape,
+                pos,
+                except: Some(params.self_char_id),
+                caster_team: self.caster_team,
+            });
+        }
+        return StatusUpdateResult::RemoveIt;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ArmorModifierStatus {
+    pub started: ElapsedTime,
+    pub until: ElapsedTime,
+    pub modifier: Percentage,
+}
+
+impl ArmorModifierStatus {
+    pub fn new(now: ElapsedTime, modifier: Percentage) -> ArmorModifierStatus {
+        ArmorModifierStatus {
+            started: now,
+            until: now.add_seconds(10.0),
+            modifier,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WalkingSpeedModifierStatus {
+    pub started: ElapsedTime,
+    pub until: ElapsedTime,
+    pub modifier: Percentage,
+}
+
+impl WalkingSpeedModifierStatus {
+    pub fn new(
+        now: ElapsedTime,
+        modifier: Percentage,
+        duration: f32,
+    ) -> WalkingSpeedModifierStatus {
+        WalkingSpeedModifierStatus {
+            started: now,
+            until: now.add_seconds(duration),
+            modifier,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PoisonStatus {
+    pub poison_caster_entity_id: CharEntityId,
+    pub started: ElapsedTime,
+    pub until: ElapsedTime,
+    pub next_damage_at: ElapsedTime,
+    pub damage: u32,
+}
+
+impl PoisonStatus {
+    fn update(&mut self, params: &mut StatusUpdateParams) -> StatusUpdateResult {
+        if self.until.has_already_passed(params.now) {
+            return StatusUpdateResult::RemoveIt;
+        }
+        if self.next_damage_at.has_already_passed(params.now) {
+            params.hp_mod_requests.push(HpModificationRequest {
+                src_entity: self.poison_caster_entity_id,
+                dst_entity: params.self_char_id,
+                typ: HpModificationType::Poison(self.damage),
+            });
+            self.next_damage_at = params.now.add_seconds(1.0);
+        }
+        return StatusUpdateResult::KeepIt;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StunStatus {
+    pub caster_entity_id: CharEntityId,
+    pub started: ElapsedTime,
+    pub until: ElapsedTime,
+}
+
+impl StunStatus {
+    pub fn new(caster_entity_id: CharEntityId, now: ElapsedTime, duration: f32) -> StunStatus {
+        StunStatus {
+            caster_entity_id,
+            started: now,
+            until: now.add_seconds(duration),
+        }
+    }
+}
+
+pub struct ApplyStatusRequest {
+    pub source_entity_id: CharEntityId,
+    pub target_entity_id: CharEntityId,
+    pub status: StatusEnum,
+}
+
+/// The status is applied on every character in the area whose team is compatible with its
+/// nature
+pub struct ApplyStatusInAreaRequest {
+    pub source_entity_id: CharEntityId,
+    pub status: StatusEnum,
+    pub shape: AreaShape,
+    pub pos: Vec2,
+    pub except: Option<CharEntityId>,
+    pub caster_team: Team,
+}
+
+pub enum RemoveStatusRequestPayload {
+    ByNature(StatusNature),
+    ByDiscr(StatusEnumDiscriminants),
+}
+
+pub struct RemoveStatusRequest {
+    pub source_entity_id: CharEntityId,
+    pub target_entity_id: CharEntityId,
+    pub payload: RemoveStatusRequestPayload,
+}
+
+// if you change the size, the update function has to be changed as well
+const STATUS_ARRAY_SIZE: usize = 32;
+
+/// The statuses of a character. They are simulated by the server, the clients only render them.
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct StatusesComponent {
+    statuses: [Option<StatusEnum>; STATUS_ARRAY_SIZE],
+    first_free_index: usize,
+    #[serde(skip, default = "CharAttributeModifierCollector::new")]
+    cached_modifier_collector: CharAttributeModifierCollector,
+}
+
+// the cached modifiers are derived from the statuses
+impl PartialEq for StatusesComponent {
+    fn eq(&self, other: &StatusesComponent) -> bool {
+        self.first_free_index == other.first_free_index && self.statuses == other.statuses
+    }
+}
+
+impl StatusesComponent {
+    pub fn new() -> StatusesComponent {
+        StatusesComponent {
+            statuses: Default::default(),
+            first_free_index: NONSTACKABLE_STATUS_COUNT,
+            cached_modifier_collector: CharAttributeModifierCollector::new(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &StatusEnum> {
+        self.statuses
+            .iter()
+            .take(self.first_free_index)
+            .filter_map(|it| it.as_ref())
+    }
+
+    pub fn can_move(&self) -> bool {
+        self.iter().all(|it| it.can_target_move())
+    }
+
+    pub fn can_cast(&self) -> bool {
+        self.iter().all(|it| it.can_target_cast())
+    }
+
+    pub fn can_be_controlled(&self) -> bool {
+        self.iter().all(|it| it.can_target_be_controlled())
+    }
+
+    pub fn allow_push(&self) -> bool {
+        self.iter().all(|it| it.allow_push())
+    }
+
+    pub fn hp_mod_is_calculated_but_not_applied_yet(
+        &mut self,
+        mut outcome: HpModificationResult,
+    ) -> HpModificationResult {
+        for status in self
+            .statuses
+            .iter_mut()
+            .take(self.first_free_index)
+            .filter_map(|it| it.as_mut())
+        {
+            outcome = status.hp_mod_is_calculated_but_not_applied_yet(outcome);
+        }
+        return outcome;
+    }
+
+    /// Returns the bit indices of the statuses which have to be removed
+    pub fn update(&mut self, params: &mut StatusUpdateParams) -> u32 {
+        let mut changed: u32 = 0;
+        for (i, status) in self
+            .statuses
+            .iter_mut()
+            .enumerate()
+            .take(self.first_free_index)
+            .filter(|(_i, it)| it.is_some())
+        {
+            match status.as_mut().unwrap().update(params) {
+                StatusUpdateResult::RemoveIt => {
+                    changed |= 1 << i;
+                }
+                StatusUpdateResult::KeepIt => {}
+            }
+        }
+        return changed;
+    }
+
+    pub fn remove_statuses(&mut self, bit_indices: u32) {
+        for i in 0..STATUS_ARRAY_SIZE {
+            if ((1 << i) & bit_indices) > 0 {
+                self.statuses[i] = None;
+            }
+        }
+        self.move_free_index();
+    }
+
+    pub fn calc_attributes(&mut self) -> &CharAttributeModifierCollector {
+        self.cached_modifier_collector.clear();
+        for status in self
+            .statuses
+            .iter()
+            .take(self.first_free_index)
+            .filter_map(|it| it.as_ref())
+        {
+            status.calc_attribs(&mut self.cached_modifier_collector);
+        }
+        return &self.cached_modifier_collector;
+    }
+
+    pub fn is_mounted(&self) -> bool {
+        self.statuses[StatusEnumDiscriminants::MountedStatus as usize].is_some()
+    }
+
+    pub fn add(&mut self, new_status: StatusEnum) {
+        log::debug!("Try to add status: {:?}", new_status);
+        if self.first_free_index >= STATUS_ARRAY_SIZE {
+            log::error!("There is no more space for new Status!");
+            return;
+        }
+
+        let mut current_index = self.first_free_index;
+        let mut stack_type = StatusStackingResult::AddTheNewStatus;
+        let adding_status_type = StatusEnumDiscriminants::from(&new_status) as usize;
+        for i in 0..self.first_free_index {
+            if i < NONSTACKABLE_STATUS_COUNT {
+                let target_slot_type = i;
+                if target_slot_type == adding_status_type {
+                    stack_type = self.statuses[i]
+                        .as_mut()
+                        .map(|it| it.stack(&new_status))
+                        .unwrap_or(StatusStackingResult::Replace);
+                    current_index = i;
+                    log::trace!(
+                        "Found NONSTACKABLE slot. stack_type: {:?}, index: {}",
+                        stack_type,
+                        current_index
+                    );
+                    break;
+                }
+            } else {
+                if let Some(status) = self.statuses[i].as_mut() {
+                    let target_slot_type = {
+                        let s: &StatusEnum = status;
+                        StatusEnumDiscriminants::from(s) as usize
+                    };
+                    if target_slot_type == adding_status_type {
+                        stack_type = status.stack(&new_status);
+                        current_index = i;
+                        log::trace!(
+                            "Found STACKABLE slot. stack_type: {:?}, index: {}",
+                            stack_type,
+                            current_index
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        log::trace!("stack_type: {:?}, index: {}", stack_type, current_index);
+        match stack_type {
+            StatusStackingResult::Replace => {
+                self.statuses[current_index] = Some(new_status);
+            }
+            StatusStackingResult::AddTheNewStatus => {
+                self.statuses[self.first_free_index] = Some(new_status);
+                self.first_free_index += 1;
+            }
+            StatusStackingResult::DontAddTheNewStatus => {
+                return;
+            }
+        }
+    }
+
+    pub fn remove_all(&mut self) {
+        log::debug!("Remove all status");
+        for status in self.statuses.iter_mut().take(self.first_free_index) {
+            *status = None;
+        }
+        self.first_free_index = NONSTACKABLE_STATUS_COUNT;
+    }
+
+    pub fn remove_by_nature(&mut self, nature: StatusNature) {
+        log::debug!("remove_by_nature: {:?}", nature);
+        self.remove_if(|it| it.nature() == nature);
+    }
+
+    pub fn remove(&mut self, discr: StatusEnumDiscriminants) {
+        log::debug!("remove: {:?}", discr);
+        self.remove_if(|it| StatusEnumDiscriminants::from(it) == discr);
+    }
+
+    fn move_free_index(&mut self) {
+        while self.first_free_index > NONSTACKABLE_STATUS_COUNT
+            && self.statuses[self.first_free_index - 1].is_none()
+        {
+            self.first_free_index -= 1;
+        }
+    }
+
+    pub fn remove_if<F>(&mut self, predicate: F)
+    where
+        F: Fn(&StatusEnum) -> bool,
+    {
+        for status in self.statuses.iter_mut().take(self.first_free_index) {
+            let should_remove = status.as_ref().map(|it| predicate(it)).unwrap_or(false);
+            if should_remove {
+                *status = None;
+            }
+        }
+        self.move_free_index();
+    }
+
+    pub fn get_status(&self, requested_type_id: StatusEnumDiscriminants) -> Option<&StatusEnum> {
+        return self
+            .iter()
+            .find(|status| StatusEnumDiscriminants::from(*status) == requested_type_id);
+    }
+
+    pub fn count(&self) -> usize {
+        let secondary_status_count = self.first_free_index - NONSTACKABLE_STATUS_COUNT;
+        let main_status_count = self
+            .statuses
+            .iter()
+            .take(NONSTACKABLE_STATUS_COUNT)
+            .filter(|it| it.is_some())
+            .count();
+        return main_status_count + secondary_status_count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::char_attr::percentage;
+
+    #[test]
+    fn non_stackable_statuses() {
+        let mut statuses = StatusesComponent::new();
+
+        assert_eq!(statuses.first_free_index, NONSTACKABLE_STATUS_COUNT);
+        assert!(statuses.statuses[0].is_none());
+        statuses.add(StatusEnum::MountedStatus {
+            speedup: percentage(0),
+        });
+        assert_eq!(statuses.first_free_index, NONSTACKABLE_STATUS_COUNT);
+        assert!(statuses.statuses[0].is_some());
+        statuses.add(StatusEnum::MountedStatus {
+            speedup: percentage(0),
+        });
+        assert_eq!(statuses.first_free_index, NONSTACKABLE_STATUS_COUNT);
+        assert!(statuses.statuses[0].is_some());
+
+        statuses.remove(StatusEnumDiscriminants::MountedStatus);
+        assert_eq!(statuses.first_free_index, NONSTACKABLE_STATUS_COUNT);
+        assert!(statuses.statuses[0].is_none());
+    }
+
+    #[test]
+    fn stackable_statuses() {
+        let mut statuses = StatusesComponent::new();
+
+        assert_eq!(statuses.first_free_index, NONSTACKABLE_STATUS_COUNT);
+        assert!(statuses.statuses[NONSTACKABLE_STATUS_COUNT].is_none());
+
+        let status = StatusEnum::WalkingSpeedModifierStatus(WalkingSpeedModifierStatus {
+            started: ElapsedTime(0.0),
+            until: ElapsedTime(0.0),
+            modifier: percentage(0),
+        });
+
+        statuses.add(status.clone());
+        assert!(statuses.statuses[NONSTACKABLE_STATUS_COUNT].is_some());
+        assert_eq!(statuses.first_free_index, NONSTACKABLE_STATUS_COUNT + 1);
+
+        statuses.add(status.clone());
+        assert_eq!(statuses.first_free_index, NONSTACKABLE_STATUS_COUNT + 2);
+        assert!(statuses.statuses[NONSTACKABLE_STATUS_COUNT].is_some());
+        assert!(statuses.statuses[NONSTACKABLE_STATUS_COUNT + 1].is_some());
+
+        statuses.remove(StatusEnumDiscriminants::WalkingSpeedModifierStatus);
+        assert_eq!(statuses.first_free_index, NONSTACKABLE_STATUS_COUNT);
+        assert!(statuses.statuses[NONSTACKABLE_STATUS_COUNT].is_none());
+        assert!(statuses.statuses[NONSTACKABLE_STATUS_COUNT + 1].is_none());
+    }
+
+    #[test]
+    fn absorb_status_absorbs_damages_and_heals_them_back_when_it_ends() {
+        let mut world = World::new();
+        let caster = CharEntityId::new(world.create_entity().build());
+        let target = CharEntityId::new(world.create_entity().build());
+        let mut statuses = StatusesComponent::new();
+        statuses.add(StatusEnum::AbsorbStatus(AbsorbStatus::new(
+            caster,
+            ElapsedTime(0.0),
+            1.0,
+        )));
+
+        let damage = HpModificationRequest {
+            src_entity: target,
+            dst_entity: target,
+            typ: HpModificationType::SpellDamage(100, DamageDisplayType::SingleNumber),
+        };
+        let outcome = statuses.hp_mod_is_calculated_but_not_applied_yet(damage.allow(100));
+        match outcome.typ {
+            HpModificationResultType::Absorbed => {}
+            _ => panic!("{:?}", outcome),
+        }
+
+        let mut char_state = AuthorizedCharStateComponent::default();
+        let mut hp_mod_requests = vec![];
+        let removed = statuses.update(&mut StatusUpdateParams {
+            self_char_id: target,
+            char_state: &mut char_state,
+            now: ElapsedTime(2.0),
+            hp_mod_requests: &mut hp_mod_requests,
+            area_hp_mod_requests: &mut vec![],
+            area_status_requests: &mut vec![],
+        });
+        statuses.remove_statuses(removed);
+        assert_eq!(statuses.count(), 0);
+        match hp_mod_requests.as_slice() {
+            [HpModificationRequest {
+                src_entity,
+                typ: HpModificationType::Heal(100),
+                ..
+            }] => assert_eq!(*src_entity, caster),
+            _ => panic!("{:?}", hp_mod_requests),
+        }
+    }
+}
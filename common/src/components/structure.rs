@@ -0,0 +1,148 @@
+use crate::components::char::{CharEntityId, CharOutlook, JobId, MonsterId, Team};
+use crate::scenario::{ObjectiveKind, ObjectiveStructure, Scenario};
+use std::collections::HashMap;
+
+/// A stationary objective placed from the scenario of the map.
+/// Its hp, armor and team are in its CharAttributesComponent like for any other character.
+#[derive(Component, Clone, Debug)]
+pub struct StructureComponent {
+    pub kind: ObjectiveKind,
+    /// The index of the lane it guards, None for the core
+    pub lane: Option<usize>,
+    /// Along its lane, the towers farther from the base have to fall first
+    pub distance_from_base: f32,
+    pub target: Option<CharEntityId>,
+}
+
+impl StructureComponent {
+    pub fn new(objective: &ObjectiveStructure, scenario: &Scenario) -> StructureComponent {
+        let lane = objective
+            .lane
+            .as_ref()
+            .and_then(|name| scenario.lane_index(name));
+        let distance_from_base = match lane {
+            Some(lane) => (scenario.lanes[lane].start(objective.team) - objective.pos).magnitude(),
+            None => 0.0,
+        };
+        StructureComponent {
+            kind: objective.kind,
+            lane,
+            distance_from_base,
+            target: None,
+        }
+    }
+
+    pub fn job_id(&self) -> JobId {
+        match self.kind {
+            ObjectiveKind::Tower => JobId::Tower,
+            ObjectiveKind::Inhibitor => JobId::Inhibitor,
+            ObjectiveKind::Core => JobId::Core,
+        }
+    }
+
+    pub fn outlook(&self) -> CharOutlook {
+        match self.kind {
+            ObjectiveKind::Tower | ObjectiveKind::Inhibitor => {
+                CharOutlook::Monster(MonsterId::Barricade)
+            }
+            ObjectiveKind::Core => CharOutlook::Monster(MonsterId::Emperium),
+        }
+    }
+
+    /// A tower or an inhibitor can't be damaged while a structure of its team which is farther
+    /// from the base is standing on the same lane.
+    /// The core can't be damaged until every structure of its team on at least one lane has
+    /// fallen.
+    /// `structures` contains every structure (including this one) with its team and whether it
+    /// is alive.
+    pub fn is_protected<'a>(
+        &self,
+        team: Team,
+        structures: impl Iterator<Item = (&'a StructureComponent, Team, bool)>,
+    ) -> bool {
+        let mut own_structures =
+            structures.filter(|(_structure, other_team, _is_alive)| *other_team == team);
+        return match (self.kind, self.lane) {
+            (ObjectiveKind::Core, _) => {
+                let mut lane_is_standing = HashMap::<usize, bool>::new();
+                for (structure, _team, is_alive) in own_structures {
+                    if let Some(lane) = structure.lane {
+                        *lane_is_standing.entry(lane).or_insert(false) |= is_alive;
+                    }
+                }
+                !lane_is_standing.is_empty() && lane_is_standing.values().all(|it| *it)
+            }
+            (_, Some(lane)) => own_structures.any(|(structure, _team, is_alive)| {
+                is_alive
+                    && structure.lane == Some(lane)
+                    && structure.distance_from_base > self.distance_from_base
+            }),
+            (_, None) => false,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn structure(
+        kind: ObjectiveKind,
+        lane: Option<usize>,
+        distance_from_base: f32,
+    ) -> StructureComponent {
+        StructureComponent {
+            kind,
+            lane,
+            distance_from_base,
+            target: None,
+        }
+    }
+
+    #[test]
+    fn towers_fall_from_the_front_towards_the_core() {
+        let outer_tower = structure(ObjectiveKind::Tower, Some(0), 50.0);
+        let inner_tower = structure(ObjectiveKind::Tower, Some(0), 20.0);
+        let inhibitor = structure(ObjectiveKind::Inhibitor, Some(0), 10.0);
+        let other_lane_tower = structure(ObjectiveKind::Tower, Some(1), 50.0);
+        let core = structure(ObjectiveKind::Core, None, 0.0);
+        let enemy_tower = structure(ObjectiveKind::Tower, Some(0), 80.0);
+
+        let standing = |outer_alive: bool, inner_alive: bool, inhibitor_alive: bool| {
+            vec![
+                (&outer_tower, Team::Left, outer_alive),
+                (&inner_tower, Team::Left, inner_alive),
+                (&inhibitor, Team::Left, inhibitor_alive),
+                (&other_lane_tower, Team::Left, true),
+                (&core, Team::Left, true),
+                (&enemy_tower, Team::Right, true),
+            ]
+        };
+
+        let all = standing(true, true, true);
+        assert!(!outer_tower.is_protected(Team::Left, all.iter().cloned()));
+        assert!(inner_tower.is_protected(Team::Left, all.iter().cloned()));
+        assert!(inhibitor.is_protected(Team::Left, all.iter().cloned()));
+        assert!(core.is_protected(Team::Left, all.iter().cloned()));
+        // the structures of the other team don't matter
+        assert!(!enemy_tower.is_protected(Team::Right, all.iter().cloned()));
+
+        let outer_fell = standing(false, true, true);
+        assert!(!inner_tower.is_protected(Team::Left, outer_fell.iter().cloned()));
+        assert!(inhibitor.is_protected(Team::Left, outer_fell.iter().cloned()));
+
+        let only_inhibitor = standing(false, false, true);
+        assert!(!inhibitor.is_protected(Team::Left, only_inhibitor.iter().cloned()));
+        assert!(core.is_protected(Team::Left, only_inhibitor.iter().cloned()));
+
+        // a whole lane has fallen
+        let lane_fell = standing(false, false, false);
+        assert!(!core.is_protected(Team::Left, lane_fell.iter().cloned()));
+    }
+
+    #[test]
+    fn a_core_without_lanes_is_never_protected() {
+        let core = structure(ObjectiveKind::Core, None, 0.0);
+        assert!(!core.is_protected(Team::Left, vec![(&core, Team::Left, true)].into_iter()));
+    }
+}
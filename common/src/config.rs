@@ -0,0 +1,315 @@
+use crate::char_attr::{percentage, CharAttributes, Percentage};
+use crate::components::char::JobId;
+use crate::components::skills::SkillCastingAttributes;
+use serde::{Deserialize, Serialize};
+
+/// The part of config-runtime.toml which affects the gameplay rules, so both the server
+/// and the client must see the same values
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommonConfigs {
+    pub stats: DevConfigStats,
+    pub skills: SkillsConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DevConfigStats {
+    pub minion: DevConfigStatsMinion,
+    pub player: DevConfigStatsPlayer,
+    pub structure: DevConfigStatsStructure,
+    pub sight_radius: DevConfigStatsSightRadius,
+}
+
+/// How far the characters see around them in the fog of war, in Gat cells
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DevConfigStatsSightRadius {
+    pub player: f32,
+    pub minion: f32,
+    pub structure: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DevConfigStatsStructure {
+    pub tower: CharAttributes,
+    pub inhibitor: CharAttributes,
+    pub core: CharAttributes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DevConfigStatsMinion {
+    pub melee: CharAttributes,
+    pub ranged: CharAttributes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DevConfigStatsPlayerJob {
+    #[serde(flatten)]
+    pub attributes: CharAttributes,
+    pub mounted_speedup: Percentage,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DevConfigStatsPlayer {
+    pub crusader: DevConfigStatsPlayerJob,
+    pub gunslinger: DevConfigStatsPlayerJob,
+    pub hunter: DevConfigStatsPlayerJob,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SkillConfigFireWall {
+    pub pushback_force: f32,
+    pub damage: u32,
+    pub width: u16,
+    pub duration_seconds: f32,
+    pub force_duration_seconds: f32,
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SkillConfigPyroBlastInner {
+    pub moving_speed: f32,
+    pub damage: u32,
+    pub secondary_damage: u32,
+    pub ball_size: f32,
+    pub splash_radius: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SkillConfigPyroBlast {
+    #[serde(flatten)]
+    pub inner: SkillConfigPyroBlastInner,
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SkillConfigHeal {
+    pub heal: u32,
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SkillConfigBrutalTestSkill {
+    pub damage: u32,
+    pub width: f32,
+    pub height: f32,
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SkillConfigSanctuarySkill {
+    pub heal: u32,
+    pub heal_freq_seconds: f32,
+    pub duration: f32,
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LightningSkillConfig {
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PoisonSkillConfig {
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+    pub damage: u32,
+    pub duration_seconds: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FireBombSkillConfig {
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+    pub damage: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AbsorbShieldSkillConfig {
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+    pub duration_seconds: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExoSkeletonSkillConfig {
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+    pub duration_seconds: f32,
+    pub armor: Percentage,
+    pub attack_damage: Percentage,
+    pub attack_range: Percentage,
+    pub movement_speed: Percentage,
+    pub attack_speed: Percentage,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AssaBladeDashSkillConfig {
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+    pub duration_seconds: f32,
+    pub first_damage: u32,
+    pub second_damage: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssaPhasePrismSkillConfig {
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+    pub duration_seconds: f32,
+    pub swap_duration_unit_per_second: f32,
+    pub damage: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FalconCarry {
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+    pub carry_ally_duration: f32,
+    pub carry_owner_duration: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FalconAttack {
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+    pub damage: u32,
+    pub slow: Percentage,
+    pub duration_in_seconds: f32,
+    pub slow_duration: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GazXplodiumChargeSkillConfigInner {
+    pub missile_travel_duration_seconds: f32,
+    pub detonation_duration: f32,
+    pub damage: u32,
+    pub stun_duration_seconds: f32,
+    pub explosion_area: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GazXplodiumChargeSkillConfig {
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+    #[serde(flatten)]
+    pub inner: GazXplodiumChargeSkillConfigInner,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GazTurretSkillConfig {
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+    pub turret: CharAttributes,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GazBarricadeSkillConfig {
+    #[serde(flatten)]
+    pub attributes: SkillCastingAttributes,
+    pub max_hp: i32,
+    pub armor: Percentage,
+    pub hp_regen: Percentage,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SkillsConfig {
+    pub firewall: SkillConfigFireWall,
+    pub wiz_pyroblast: SkillConfigPyroBlast,
+    pub heal: SkillConfigHeal,
+    pub brutal_test_skill: SkillConfigBrutalTestSkill,
+    pub lightning: LightningSkillConfig,
+    pub mounting: SkillCastingAttributes,
+    pub unmounting: SkillCastingAttributes,
+    pub cure: SkillCastingAttributes,
+    pub poison: PoisonSkillConfig,
+    pub firebomb: FireBombSkillConfig,
+    pub absorb_shield: AbsorbShieldSkillConfig,
+    pub exoskeleton: ExoSkeletonSkillConfig,
+    pub assa_blade_dash: AssaBladeDashSkillConfig,
+    pub assa_phase_prism: AssaPhasePrismSkillConfig,
+    pub falcon_carry: FalconCarry,
+    pub falcon_attack: FalconAttack,
+    pub gaz_xplodium_charge: GazXplodiumChargeSkillConfig,
+    pub gaz_turret: GazTurretSkillConfig,
+    pub gaz_barricade: GazBarricadeSkillConfig,
+    pub gaz_destroy_turret: SkillCastingAttributes,
+    pub sanctuary: SkillConfigSanctuarySkill,
+}
+
+pub fn get_sight_radius(job_id: JobId, stats: &DevConfigStats) -> f32 {
+    return match job_id {
+        JobId::MeleeMinion | JobId::RangedMinion => stats.sight_radius.minion,
+        JobId::Tower | JobId::Inhibitor | JobId::Core => stats.sight_radius.structure,
+        _ => stats.sight_radius.player,
+    };
+}
+
+pub fn get_base_attributes(
+    job_id: JobId,
+    stats: &DevConfigStats,
+    skills: &SkillsConfig,
+) -> CharAttributes {
+    return match job_id {
+        JobId::CRUSADER => stats.player.crusader.attributes.clone(),
+        JobId::GUNSLINGER => stats.player.gunslinger.attributes.clone(),
+        JobId::RANGER => stats.player.hunter.attributes.clone(),
+        JobId::RangedMinion => stats.minion.ranged.clone(),
+        JobId::HealingDummy => CharAttributes {
+            movement_speed: percentage(0),
+            attack_range: percentage(0),
+            attack_speed: percentage(0),
+            attack_damage: 0,
+            armor: percentage(0),
+            healing: percentage(100),
+            hp_regen: percentage(0),
+            max_hp: 1_000_000,
+            mana_regen: percentage(0),
+        },
+        JobId::TargetDummy => CharAttributes {
+            movement_speed: percentage(0),
+            attack_range: percentage(0),
+            attack_speed: percentage(0),
+            attack_damage: 0,
+            armor: percentage(0),
+            healing: percentage(100),
+            hp_regen: percentage(0),
+            max_hp: 1_000_000,
+            mana_regen: percentage(0),
+        },
+        JobId::MeleeMinion => stats.minion.melee.clone(),
+        JobId::Turret => skills.gaz_turret.turret.clone(),
+        JobId::Tower => stats.structure.tower.clone(),
+        JobId::Inhibitor => stats.structure.inhibitor.clone(),
+        JobId::Core => stats.structure.core.clone(),
+        JobId::Barricade => {
+            let configs = &skills.gaz_barricade;
+            CharAttributes {
+                movement_speed: percentage(0),
+                attack_range: percentage(0),
+                attack_speed: percentage(0),
+                attack_damage: 0,
+                armor: configs.armor,
+                healing: percentage(0),
+                hp_regen: configs.hp_regen,
+                max_hp: configs.max_hp,
+                mana_regen: percentage(10),
+            }
+        }
+        _ => CharAttributes {
+            movement_speed: percentage(100),
+            attack_range: percentage(100),
+            attack_speed: percentage(100),
+            attack_damage: 76,
+            armor: percentage(10),
+            healing: percentage(100),
+            hp_regen: percentage(100),
+            max_hp: 2000,
+            mana_regen: percentage(100),
+        },
+    };
+}
@@ -0,0 +1,164 @@
+use crate::common::Vec2;
+use crate::grf::gat::Gat;
+use crate::pathfinding::{self, Cell};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+const UNREACHABLE: u32 = std::u32::MAX;
+
+/// The cost of the cheapest path from every cell of the map to a goal.
+/// It is computed once and shared by all the characters walking towards the same goal (e.g. the
+/// minions of a team), they only have to look at the neighbouring cells to find their way.
+pub struct FlowField {
+    goal: Vec2,
+    width: usize,
+    height: usize,
+    costs: Vec<u32>,
+}
+
+impl FlowField {
+    /// None if the goal is outside of the map
+    pub fn new(gat: &Gat, goal: &Vec2) -> Option<FlowField> {
+        let goal_cell = pathfinding::cell_of(gat, goal)?;
+        let width = gat.width as usize;
+        let height = gat.height as usize;
+        let is_walkable = |cell: Cell| cell.0 < width && gat.is_walkable(cell.0, cell.1);
+        let mut costs = vec![UNREACHABLE; width * height];
+        let mut open = BinaryHeap::with_capacity(1024);
+        costs[goal_cell.1 * width + goal_cell.0] = 0;
+        open.push(Reverse((0u32, goal_cell)));
+        while let Some(Reverse((cost, cell))) = open.pop() {
+            if costs[cell.1 * width + cell.0] < cost {
+                // a cheaper way was found since it was pushed
+                continue;
+            }
+            // the steps are symmetric, so the cost from the goal is the same as towards it
+            for (neighbour, step_cost) in pathfinding::walkable_neighbours(cell, &is_walkable) {
+                let neighbour_cost = cost + step_cost;
+                let index = neighbour.1 * width + neighbour.0;
+                if neighbour_cost < costs[index] {
+                    costs[index] = neighbour_cost;
+                    open.push(Reverse((neighbour_cost, neighbour)));
+                }
+            }
+        }
+        return Some(FlowField {
+            goal: *goal,
+            width,
+            height,
+            costs,
+        });
+    }
+
+    pub fn goal(&self) -> &Vec2 {
+        &self.goal
+    }
+
+    /// None if the goal can't be reached from the cell
+    pub fn cost_of(&self, cell: Cell) -> Option<u32> {
+        if cell.0 >= self.width || cell.1 >= self.height {
+            return None;
+        }
+        let cost = self.costs[cell.1 * self.width + cell.0];
+        return if cost == UNREACHABLE {
+            None
+        } else {
+            Some(cost)
+        };
+    }
+
+    /// The normalized direction towards the cheapest neighbouring cell, or towards the goal
+    /// itself in its cell.
+    /// None if the goal has been reached or it can't be reached from `pos`.
+    pub fn direction_at(&self, pos: &Vec2) -> Option<Vec2> {
+        let (x, y) = (pos.x, -pos.y);
+        if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+            return None;
+        }
+        let cell = (x as usize, y as usize);
+        let towards = if self.cost_of(cell) == Some(0) {
+            self.goal
+        } else {
+            // a character pushed into an unreachable cell looks for a way back as well
+            let is_reachable = |cell: Cell| self.cost_of(cell).is_some();
+            let (best_neighbour, _cost) = pathfinding::walkable_neighbours(cell, &is_reachable)
+                .map(|(neighbour, _step_cost)| (neighbour, self.cost_of(neighbour).unwrap()))
+                .min_by_key(|(_neighbour, cost)| *cost)?;
+            pathfinding::center_of(best_neighbour)
+        };
+        let dir = towards - pos;
+        if dir.magnitude() < 0.01 {
+            return None;
+        }
+        return Some(dir.normalize());
+    }
+}
+
+/// Pushes `pos` away from the neighbours which are closer than `radius`, the closer they are
+/// the stronger.
+/// The length of the result is the sum of the pushes, 0 if there is nothing around.
+pub fn separation<'a>(pos: &Vec2, neighbours: impl Iterator<Item = &'a Vec2>, radius: f32) -> Vec2 {
+    let mut push = Vec2::zeros();
+    for neighbour in neighbours {
+        let away = pos - neighbour;
+        let distance = away.magnitude();
+        // 0 is the character itself
+        if distance < 0.001 || distance >= radius {
+            continue;
+        }
+        push += away / distance * ((radius - distance) / radius);
+    }
+    return push;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::v2;
+
+    #[test]
+    fn following_the_field_leads_around_walls_to_the_goal() {
+        let gat = Gat::from_rows(&[
+            "..#...", //
+            "..#.#.", //
+            "..#.#.", //
+            "....#.",
+        ]);
+        let goal = v2(5.5, -0.5);
+        let field = FlowField::new(&gat, &goal).unwrap();
+        assert_eq!(field.cost_of((5, 0)), Some(0));
+        assert_eq!(field.cost_of((2, 0)), None);
+
+        let mut pos = v2(0.5, -0.5);
+        for _ in 0..1000 {
+            match field.direction_at(&pos) {
+                Some(dir) => pos += dir * 0.1,
+                None => break,
+            }
+            let cell = pathfinding::cell_of(&gat, &pos).unwrap();
+            assert!(gat.is_walkable(cell.0, cell.1), "{:?}", pos);
+        }
+        assert!((pos - goal).magnitude() < 0.1);
+    }
+
+    #[test]
+    fn nowhere_to_go_from_unreachable_cells() {
+        let gat = Gat::from_rows(&[
+            "...#..", //
+            "...#..",
+        ]);
+        let field = FlowField::new(&gat, &v2(0.5, -0.5)).unwrap();
+        assert!(field.direction_at(&v2(5.5, -1.5)).is_none());
+        assert!(field.direction_at(&v2(6.5, -1.5)).is_none());
+        assert!(FlowField::new(&gat, &v2(6.5, -1.5)).is_none());
+    }
+
+    #[test]
+    fn separation_pushes_away_from_close_neighbours() {
+        let pos = v2(1.0, -1.0);
+        let neighbours = vec![pos, v2(1.5, -1.0), v2(5.0, -1.0)];
+        let push = separation(&pos, neighbours.iter(), 1.0);
+        assert_eq!(push, v2(-0.5, 0.0));
+        assert_eq!(separation(&pos, [pos].iter(), 1.0), Vec2::zeros());
+    }
+}
@@ -0,0 +1,175 @@
+use crate::grf::binary_reader::{BinaryReader, ParseError, ParseErrorKind};
+use serde::{Deserialize, Serialize};
+use std::ops::RangeBounds;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionFile {
+    pub actions: Vec<Action>,
+    pub sounds: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Action {
+    pub frames: Vec<ActionFrame>,
+    pub delay: u32,
+    pub duration: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActionFrame {
+    pub layers: Vec<Layer>,
+    pub sound: i32,
+    pub positions: Vec<[i32; 2]>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Layer {
+    pub pos: [i32; 2],
+    pub sprite_frame_index: i32,
+    // can be -1!!
+    pub is_mirror: bool,
+    pub scale: [f32; 2],
+    pub color: [u8; 4],
+    pub angle: i32,
+    pub spr_type: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl ActionFile {
+    pub fn remove_frames_in_every_direction<R>(&mut self, action_index: usize, range: R)
+    where
+        R: RangeBounds<usize> + Clone,
+    {
+        for i in 0..8 {
+            self.actions[action_index + i].frames.drain(range.clone());
+        }
+    }
+
+    pub fn load(mut buf: BinaryReader) -> Result<Self, ParseError> {
+        let header = buf.string(2)?;
+        if header != "AC" {
+            return Err(buf.error(ParseErrorKind::InvalidHeader(header)));
+        }
+
+        let version = buf.next_u8()? as f32 / 10.0 + buf.next_u8()? as f32;
+
+        let action_acount = buf.next_u16()? as usize;
+        buf.skip(10)?;
+
+        let mut actions: Vec<Action> = (0..action_acount)
+            .map(|_i| {
+                Ok(Action {
+                    frames: ActionFile::read_animations(&mut buf, version)?,
+                    delay: 150,
+                    duration: 0.0,
+                })
+            })
+            .collect::<Result<_, ParseError>>()?;
+        let sounds = if version >= 2.1 {
+            (0..buf.next_i32()?)
+                .map(|_i| buf.string(40))
+                .collect::<Result<_, _>>()?
+        } else {
+            vec![]
+        };
+        for a in actions.iter_mut() {
+            if version >= 2.2 {
+                a.delay = (buf.next_f32()? * 25f32) as u32;
+            }
+            a.duration = a.delay as f32 / 1000.0 * a.frames.len() as f32;
+        }
+        return Ok(ActionFile { actions, sounds });
+    }
+
+    fn read_animations(
+        buf: &mut BinaryReader,
+        version: f32,
+    ) -> Result<Vec<ActionFrame>, ParseError> {
+        let animation_count = buf.next_u32()? as usize;
+        (0..animation_count)
+            .map(|_i| {
+                buf.skip(32)?; // unknown
+                Ok(ActionFrame {
+                    layers: ActionFile::read_layers(buf, version)?,
+                    sound: if version >= 2.0 { buf.next_i32()? } else { -1 },
+                    positions: if version >= 2.3 {
+                        (0..buf.next_i32()?)
+                            .map(|_i| {
+                                buf.skip(4)?;
+                                let pos = [buf.next_i32()?, buf.next_i32()?];
+                                buf.skip(4)?;
+                                Ok(pos)
+                            })
+                            .collect::<Result<_, ParseError>>()?
+                    } else {
+                        vec![]
+                    },
+                })
+            })
+            .collect()
+    }
+
+    fn read_layers(buf: &mut BinaryReader, version: f32) -> Result<Vec<Layer>, ParseError> {
+        let layer_count = buf.next_u32()? as usize;
+        let mut layers = Vec::new();
+        for _i in 0..layer_count {
+            let pos = [buf.next_i32()?, buf.next_i32()?];
+            let sprite_frame_index = buf.next_i32()?;
+            let is_mirror = buf.next_i32()? != 0;
+            let color = if version >= 2.0 {
+                [
+                    buf.next_u8()?,
+                    buf.next_u8()?,
+                    buf.next_u8()?,
+                    buf.next_u8()?,
+                ]
+            } else {
+                [255, 255, 255, 255]
+            };
+            let scale = if version >= 2.0 {
+                let scale_0 = buf.next_f32()?;
+                [
+                    scale_0,
+                    if version <= 2.3 {
+                        scale_0
+                    } else {
+                        buf.next_f32()?
+                    },
+                ]
+            } else {
+                [1.0, 1.0]
+            };
+            let angle = if version >= 2.0 { buf.next_i32()? } else { 0 };
+            let spr_type = if version >= 2.0 { buf.next_i32()? } else { 0 };
+            let width = if version >= 2.5 { buf.next_i32()? } else { 0 };
+            let height = if version >= 2.5 { buf.next_i32()? } else { 0 };
+
+            // for head sprites, the first layer refers to sprite '-1', which is skipped anyway during rendering
+            if sprite_frame_index >= 0 {
+                layers.push(Layer {
+                    pos,
+                    sprite_frame_index,
+                    is_mirror,
+                    scale,
+                    color,
+                    angle,
+                    spr_type,
+                    width,
+                    height,
+                });
+            }
+        }
+        return Ok(layers);
+    }
+
+    /// The index of the highest sprite frame which is referred by the layers
+    pub fn max_sprite_frame_index(&self) -> Option<usize> {
+        self.actions
+            .iter()
+            .flat_map(|action| action.frames.iter())
+            .flat_map(|frame| frame.layers.iter())
+            .map(|layer| layer.sprite_frame_index as usize)
+            .max()
+    }
+}
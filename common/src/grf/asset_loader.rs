@@ -1,23 +1,18 @@
+use crate::grf::asset_source::{normalize_name, AssetSource, SourceEntry};
 use crate::grf::binary_reader::BinaryReader;
+use crate::grf::content_cache::ContentCache;
+use crate::grf::des;
 use crate::grf::gat::{BlockingRectangle, Gat};
+use crate::grf::grf_cache::{self, GrfFingerprint};
 use crate::grf::GrfEntry;
-use byteorder::LittleEndian;
-use byteorder::ReadBytesExt;
-use byteorder::WriteBytesExt;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
-use std::io::Seek;
-use std::io::SeekFrom;
-use std::io::Write;
-use std::ops::Shl;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-const GRF_HEADER_SIZE: usize = 15 + 15 + 4 * 4;
+pub(super) const GRF_HEADER_SIZE: usize = 15 + 15 + 4 * 4;
 
 // entry is a file
-#[allow(dead_code)]
-const GRF_FILELIST_TYPE_FILE: u8 = 0x01;
+pub(super) const GRF_FILELIST_TYPE_FILE: u8 = 0x01;
 
 // encryption mode 0 (header DES + periodic DES/shuffle)
 const GRF_FILELIST_TYPE_ENCRYPT_MIXED: u8 = 0x02;
@@ -25,168 +20,292 @@ const GRF_FILELIST_TYPE_ENCRYPT_MIXED: u8 = 0x02;
 // encryption mode 1 (header DES only)
 const GRF_FILELIST_TYPE_ENCRYPT_HEADER: u8 = 0x04;
 
+// the decompressed sizes come from the archive, a corrupt one must not abort the process
+const MAX_PREALLOCATED_SIZE: usize = 64 * 1024 * 1024;
+
+/// A virtual filesystem over an ordered stack of GRF archives, zip archives and plain
+/// directories. The later sources override the earlier ones, so a single file can be replaced
+/// by putting it into a directory at the end of the list.
 #[derive(Clone)]
 pub struct CommonAssetLoader {
-    entries: HashMap<String, (usize, GrfEntry)>,
-    paths: Vec<String>,
+    entries: HashMap<String, (usize, SourceEntry)>,
+    sources: Vec<AssetSource>,
+    source_paths: Vec<String>,
+    // shared by the clones, i.e. by the main and the background loader threads
+    content_cache: Arc<Mutex<ContentCache>>,
 }
 
 impl<'a> CommonAssetLoader {
-    pub fn new<P: AsRef<Path> + Clone>(paths: &[P]) -> Result<CommonAssetLoader, std::io::Error> {
-        let path_str: Vec<String> = paths
+    /// The file tables of the GRF archives are cached in `cache_path`, see `grf_cache::default_cache_path`.
+    /// At most `content_cache_bytes` of decompressed archive entries are kept in memory.
+    pub fn new<P: AsRef<Path> + Clone>(
+        paths: &[P],
+        cache_path: &Path,
+        content_cache_bytes: usize,
+    ) -> Result<CommonAssetLoader, std::io::Error> {
+        let invalid_data = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+        let source_paths: Vec<String> = paths
             .iter()
             .map(|path| path.as_ref().to_str().unwrap().to_owned())
             .collect();
+        let sources: Vec<AssetSource> = source_paths
+            .iter()
+            .map(|path| AssetSource::open(path))
+            .collect::<Result<_, _>>()
+            .map_err(invalid_data)?;
 
-        let entries = if let Ok(mut cache_file) = File::open("grf.cache") {
-            let count = cache_file.read_u32::<LittleEndian>().unwrap() as usize;
-            let mut entries = HashMap::with_capacity(count);
-            loop {
-                let len = cache_file.read_u16::<LittleEndian>();
-                if len.is_err() {
-                    break;
-                }
-                let mut name = String::from_utf8(vec![b'X'; len.unwrap() as usize]).unwrap();
-                unsafe {
-                    cache_file.read_exact(name.as_bytes_mut()).expect("");
-                }
-                let grf_index = cache_file.read_u8().unwrap() as usize;
-                let entry = GrfEntry {
-                    pack_size: cache_file.read_u32::<LittleEndian>().unwrap(),
-                    length_aligned: cache_file.read_u32::<LittleEndian>().unwrap(),
-                    real_size: cache_file.read_u32::<LittleEndian>().unwrap(),
-                    typ: cache_file.read_u8().unwrap(),
-                    offset: cache_file.read_u32::<LittleEndian>().unwrap(),
-                };
-                entries.insert(name, (grf_index, entry));
-            }
-            entries
-        } else {
-            let readers: Result<Vec<BinaryReader>, std::io::Error> = paths
-                .iter()
-                .enumerate()
-                .map(|(_i, path)| BinaryReader::new(path.clone()))
-                .collect();
-            match readers {
-                Err(e) => return Err(e),
-                Ok(readers) => {
-                    let entries: HashMap<String, (usize, GrfEntry)> = readers
-                        .into_iter()
-                        .enumerate()
-                        .map(|(file_index, buf)| {
-                            CommonAssetLoader::read_grf_entries(paths, file_index, buf)
-                        })
-                        .flatten()
-                        .collect();
-
-                    match File::create("grf.cache") {
-                        Ok(mut cache_file) => {
-                            log::info!(">>> Cache grf file content");
-                            cache_file
-                                .write_u32::<LittleEndian>(entries.len() as u32)
-                                .unwrap();
-                            for (filename, (grf_index, grf_entry)) in entries.iter() {
-                                cache_file
-                                    .write_u16::<LittleEndian>(filename.len() as u16)
-                                    .unwrap();
-                                cache_file.write(filename.as_bytes()).unwrap();
-                                cache_file.write_u8(*grf_index as u8).unwrap();
-                                cache_file
-                                    .write_u32::<LittleEndian>(grf_entry.pack_size)
-                                    .unwrap();
-                                cache_file
-                                    .write_u32::<LittleEndian>(grf_entry.length_aligned)
-                                    .unwrap();
-                                cache_file
-                                    .write_u32::<LittleEndian>(grf_entry.real_size)
-                                    .unwrap();
-                                cache_file.write_u8(grf_entry.typ).unwrap();
-                                cache_file
-                                    .write_u32::<LittleEndian>(grf_entry.offset)
-                                    .unwrap();
-                            }
-                            log::info!("<<< Cache grf file content");
-                        }
-                        Err(e) => {
-                            log::warn!("Failed to create grf cache file: {}", e);
-                        }
+        let fingerprints: Vec<GrfFingerprint> = sources
+            .iter()
+            .enumerate()
+            .filter_map(|(source_index, source)| match source {
+                AssetSource::Grf(archive) => Some(GrfFingerprint::new(source_index, &archive.path)),
+                _ => None,
+            })
+            .collect::<Result<_, _>>()?;
+        let grf_entries = match grf_cache::load(cache_path, &fingerprints) {
+            Some(entries) => entries,
+            None => {
+                let mut entries: HashMap<String, (usize, GrfEntry)> = HashMap::new();
+                for (source_index, source) in sources.iter().enumerate() {
+                    if let AssetSource::Grf(archive) = source {
+                        let grf_entries =
+                            CommonAssetLoader::read_grf_entries(paths, source_index, &archive.data)
+                                .map_err(invalid_data)?;
+                        entries.extend(grf_entries);
                     }
-                    entries
                 }
+                log::info!(">>> Cache grf file content");
+                if let Err(e) = grf_cache::save(cache_path, &fingerprints, &entries) {
+                    log::warn!(
+                        "Failed to create grf cache file '{}': {}",
+                        cache_path.display(),
+                        e
+                    );
+                }
+                log::info!("<<< Cache grf file content");
+                entries
             }
         };
+
+        // the GRF entries are already merged, only the winner is kept for every name
+        let mut entries_per_source: Vec<HashMap<String, SourceEntry>> = sources
+            .iter()
+            .map(|source| source.read_entries())
+            .collect::<Result<_, _>>()
+            .map_err(invalid_data)?;
+        for (name, (grf_index, grf_entry)) in grf_entries {
+            if let Some(source_entries) = entries_per_source.get_mut(grf_index) {
+                source_entries.insert(name, SourceEntry::Grf(grf_entry));
+            }
+        }
+        let mut entries = HashMap::new();
+        for (source_index, source_entries) in entries_per_source.into_iter().enumerate() {
+            for (name, entry) in source_entries {
+                entries.insert(name, (source_index, entry));
+            }
+        }
         Ok(CommonAssetLoader {
-            paths: path_str,
+            sources,
+            source_paths,
             entries,
+            content_cache: Arc::new(Mutex::new(ContentCache::new(content_cache_bytes))),
         })
     }
 
-    fn read_grf_entries<P: AsRef<Path> + Clone>(
+    pub(super) fn read_grf_entries<P: AsRef<Path> + Clone>(
         paths: &[P],
         file_index: usize,
-        mut buf: BinaryReader,
-    ) -> HashMap<String, (usize, GrfEntry)> {
-        log::info!(
-            "Loading {}",
-            paths[file_index].as_ref().to_str().unwrap_or("")
-        );
-        let signature = buf.string(15);
-        let _key = buf.string(15);
-        let file_table_offset = buf.next_u32();
-        let skip = buf.next_u32();
-        let file_count = buf.next_u32() - (skip + 7);
-        let version = buf.next_u32();
-
-        if signature != "Master of Magic" {
-            panic!("Incorrect signature: {}", signature);
+        data: &[u8],
+    ) -> Result<HashMap<String, (usize, GrfEntry)>, String> {
+        let path = paths[file_index].as_ref().to_str().unwrap_or("");
+        log::info!("Loading {}", path);
+        if data.len() < GRF_HEADER_SIZE {
+            return Err(format!("'{}' is too short to be a GRF file", path));
+        }
+        // the key (the next 15 bytes) is not used and it might contain anything
+        if &data[0..15] != b"Master of Magic" {
+            return Err(format!("Incorrect signature in '{}'", path));
+        }
+        let file_table_offset = read_u32(data, 30);
+        let skip = read_u32(data, 34);
+        let file_count = read_u32(data, 38).wrapping_sub(skip).wrapping_sub(7);
+        let version = read_u32(data, 42);
+
+        let table_start = GRF_HEADER_SIZE + file_table_offset as usize;
+        if table_start > data.len() {
+            return Err(format!("The file table of '{}' is out of bounds", path));
         }
+        let table = &data[table_start..];
+        return match version {
+            0x200 => CommonAssetLoader::read_grf_entries_0x200(file_index, file_count, table)
+                .map_err(|e| format!("Corrupt file table in '{}': {}", path, e)),
+            0x102 | 0x103 => {
+                CommonAssetLoader::read_grf_entries_0x103(file_index, file_count, table.to_vec())
+                    .map_err(|e| format!("Corrupt file table in '{}': {}", path, e))
+            }
+            _ => Err(format!("Unsupported version in '{}': {:#x}", path, version)),
+        };
+    }
 
-        if version != 0x200 {
-            panic!("Incorrect version: {}", version);
+    fn read_grf_entries_0x200(
+        file_index: usize,
+        file_count: u32,
+        table: &[u8],
+    ) -> Result<HashMap<String, (usize, GrfEntry)>, String> {
+        if table.len() < 8 {
+            return Err("missing table size".to_owned());
+        }
+        let pack_size = read_u32(table, 0);
+        let real_size = read_u32(table, 4);
+        if 8 + pack_size as usize > table.len() {
+            return Err(format!(
+                "the compressed table ({} bytes) is truncated",
+                pack_size
+            ));
         }
+        let data = &table[8..8 + pack_size as usize];
+        // the size comes from the archive, it is not trusted for the allocation
+        let mut out = Vec::<u8>::with_capacity((real_size as usize).min(MAX_PREALLOCATED_SIZE));
+        let mut decoder = libflate::zlib::Decoder::new(data).map_err(|e| e.to_string())?;
+        std::io::copy(&mut decoder, &mut out).map_err(|e| e.to_string())?;
 
-        buf.skip(file_table_offset);
-        let pack_size = buf.next_u32();
-        let real_size = buf.next_u32();
-        let data = buf.next(pack_size);
-        let mut out = Vec::<u8>::with_capacity(real_size as usize);
-        let mut decoder = libflate::zlib::Decoder::new(data).unwrap();
-        std::io::copy(&mut decoder, &mut out).unwrap();
-
-        let mut table_reader = BinaryReader::from_vec(out);
-        let entries: HashMap<String, (usize, GrfEntry)> = (0..file_count)
-            .map(|_i| {
-                let mut filename = String::new();
-                loop {
-                    let ch = table_reader.next_u8();
-                    if ch == 0 {
-                        break;
-                    }
-                    filename.push(ch as char);
-                }
+        // the count comes from the header, it is not trusted for the allocation
+        let mut entries = HashMap::with_capacity((file_count as usize).min(out.len() / 18));
+        let mut table_reader = 0;
+        for _i in 0..file_count {
+            let filename_len = out[table_reader..]
+                .iter()
+                .position(|ch| *ch == 0)
+                .ok_or("unterminated file name")?;
+            let filename: String = out[table_reader..table_reader + filename_len]
+                .iter()
+                .map(|ch| *ch as char)
+                .collect();
+            table_reader += filename_len + 1;
+            if table_reader + 17 > out.len() {
+                return Err(format!("the entry of '{}' is truncated", filename));
+            }
+            let entry = GrfEntry {
+                pack_size: read_u32(&out, table_reader),
+                length_aligned: read_u32(&out, table_reader + 4),
+                real_size: read_u32(&out, table_reader + 8),
+                typ: out[table_reader + 12],
+                offset: read_u32(&out, table_reader + 13),
+            };
+            table_reader += 17;
+            entries.insert(filename.to_ascii_lowercase(), (file_index, entry));
+        }
+        return Ok(entries);
+    }
+
+    /// The table is not compressed, but the file names are encrypted and the sizes are obfuscated
+    fn read_grf_entries_0x103(
+        file_index: usize,
+        file_count: u32,
+        mut table: Vec<u8>,
+    ) -> Result<HashMap<String, (usize, GrfEntry)>, String> {
+        let mut entries = HashMap::with_capacity((file_count as usize).min(table.len() / 27));
+        let mut table_reader = 0;
+        for _i in 0..file_count {
+            if table_reader + 4 > table.len() {
+                return Err("the table is truncated".to_owned());
+            }
+            let name_field_len = read_u32(&table, table_reader) as usize;
+            let entry_start = table_reader + name_field_len + 4;
+            if name_field_len < 6 || entry_start + 17 > table.len() {
+                return Err(format!("invalid entry at {}", table_reader));
+            }
+            let typ = table[entry_start + 12];
+            if typ & GRF_FILELIST_TYPE_FILE != 0 {
+                let name = &mut table[table_reader + 6..table_reader + name_field_len];
+                des::decrypt_file_name(name);
+                let filename: String = name
+                    .iter()
+                    .take_while(|ch| **ch != 0)
+                    .map(|ch| *ch as char)
+                    .collect::<String>()
+                    .to_ascii_lowercase();
+                // these are only encrypted at the beginning, everything else fully
+                let header_only = [".gnd", ".gat", ".act", ".str"]
+                    .iter()
+                    .any(|ext| filename.ends_with(ext));
+                let real_size = read_u32(&table, entry_start + 8);
                 let entry = GrfEntry {
-                    pack_size: table_reader.next_u8() as u32
-                        | (table_reader.next_u8() as u32).shl(8)
-                        | (table_reader.next_u8() as u32).shl(16)
-                        | (table_reader.next_u8() as u32).shl(24),
-                    length_aligned: table_reader.next_u8() as u32
-                        | (table_reader.next_u8() as u32).shl(8)
-                        | (table_reader.next_u8() as u32).shl(16)
-                        | (table_reader.next_u8() as u32).shl(24),
-                    real_size: table_reader.next_u8() as u32
-                        | (table_reader.next_u8() as u32).shl(8)
-                        | (table_reader.next_u8() as u32).shl(16)
-                        | (table_reader.next_u8() as u32).shl(24),
-                    typ: table_reader.next_u8(),
-                    offset: table_reader.next_u8() as u32
-                        | (table_reader.next_u8() as u32).shl(8)
-                        | (table_reader.next_u8() as u32).shl(16)
-                        | (table_reader.next_u8() as u32).shl(24),
+                    pack_size: read_u32(&table, entry_start)
+                        .wrapping_sub(real_size)
+                        .wrapping_sub(715),
+                    length_aligned: read_u32(&table, entry_start + 4).wrapping_sub(37579),
+                    real_size,
+                    typ: typ
+                        | if header_only {
+                            GRF_FILELIST_TYPE_ENCRYPT_HEADER
+                        } else {
+                            GRF_FILELIST_TYPE_ENCRYPT_MIXED
+                        },
+                    offset: read_u32(&table, entry_start + 13),
                 };
-                (filename.to_ascii_lowercase(), (file_index, entry))
+                entries.insert(filename, (file_index, entry));
+            }
+            table_reader = entry_start + 17;
+        }
+        return Ok(entries);
+    }
+
+    /// Identifies the content of every source, e.g. a bundle is valid only for the sources it
+    /// was baked from
+    pub(super) fn source_fingerprints(&self) -> Result<Vec<GrfFingerprint>, std::io::Error> {
+        return self
+            .sources
+            .iter()
+            .zip(self.source_paths.iter())
+            .enumerate()
+            .map(|(source_index, (source, path))| match source {
+                AssetSource::Dir(_root) => GrfFingerprint::of_dir(source_index, path),
+                _ => GrfFingerprint::new(source_index, path),
             })
             .collect();
-        entries
+    }
+
+    /// The roots of the directory sources, their files can be edited while the game is running
+    pub fn dir_roots(&self) -> Vec<PathBuf> {
+        self.sources
+            .iter()
+            .filter_map(|source| match source {
+                AssetSource::Dir(root) => Some(root.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Points the entry of a changed or newly created file of a directory source to that file,
+    /// unless a later source overrides it. Returns the name of the entry if it is served
+    /// from `path` after the refresh.
+    pub fn refresh_file(&mut self, path: &Path) -> Option<String> {
+        let path = path.canonicalize().ok()?;
+        let (source_index, name) = self.sources.iter().enumerate().rev().find_map(
+            |(source_index, source)| match source {
+                AssetSource::Dir(root) => {
+                    let root = root.canonicalize().ok()?;
+                    let relative_path = path.strip_prefix(root).ok()?.to_str()?;
+                    Some((source_index, normalize_name(relative_path)))
+                }
+                _ => None,
+            },
+        )?;
+        if let Some((current_source_index, _entry)) = self.entries.get(&name) {
+            if *current_source_index > source_index {
+                return None;
+            }
+        }
+        self.content_cache.lock().unwrap().remove(&name);
+        self.entries
+            .insert(name.clone(), (source_index, SourceEntry::File(path)));
+        return Some(name);
+    }
+
+    /// The names are case insensitive and both separators are accepted, this is the canonical form
+    pub fn entry_name(file_name: &str) -> String {
+        return normalize_name(file_name);
     }
 
     pub fn get_entry_names(&self) -> Vec<String> {
@@ -194,43 +313,62 @@ impl<'a> CommonAssetLoader {
     }
 
     pub fn exists(&self, file_name: &str) -> bool {
-        self.entries.get(file_name).is_some()
+        self.entries.get(&normalize_name(file_name)).is_some()
     }
 
-    pub fn get_content(&self, file_name: &str) -> Result<Vec<u8>, String> {
-        return match &self.entries.get(&file_name.to_ascii_lowercase()) {
-            Some((path_index, entry)) => {
-                return Ok(CommonAssetLoader::get_content2(
-                    &self.paths[*path_index],
-                    entry,
-                    file_name,
-                ));
+    /// The content is shared with the cache, it is not copied
+    pub fn get_content(&self, file_name: &str) -> Result<Arc<Vec<u8>>, String> {
+        return match &self.entries.get(&normalize_name(file_name)) {
+            Some((source_index, entry)) => {
+                let source = &self.sources[*source_index];
+                if let AssetSource::Dir(_root) = source {
+                    // they are not compressed, and they can be edited while the game is running
+                    return source.read(entry, file_name).map(Arc::new);
+                }
+                let key = normalize_name(file_name);
+                if let Some(content) = self.content_cache.lock().unwrap().get(&key) {
+                    return Ok(content.clone());
+                }
+                // the lock is not held during the decompression, the other threads can read meanwhile
+                let content = Arc::new(source.read(entry, file_name)?);
+                self.content_cache
+                    .lock()
+                    .unwrap()
+                    .insert(key, content.clone());
+                Ok(content)
             }
-            None => Err(format!("No entry found in GRFs '{}'", file_name)),
+            None => Err(format!(
+                "No entry found in the asset sources '{}'",
+                file_name
+            )),
         };
     }
 
-    pub(super) fn get_content2(path_to_grf: &str, entry: &GrfEntry, file_name: &str) -> Vec<u8> {
-        let mut f = File::open(path_to_grf).unwrap();
-
-        let mut buf = Vec::<u8>::with_capacity(entry.length_aligned as usize);
-        f.seek(SeekFrom::Start(
-            entry.offset as u64 + GRF_HEADER_SIZE as u64,
-        ))
-        .expect(&format!("Could not get {}", file_name));
-        f.take(entry.length_aligned as u64)
-            .read_to_end(&mut buf)
-            .expect(&format!("Could not get {}", file_name));
+    /// `archive` is the whole GRF file
+    pub(super) fn decode_entry(
+        archive: &[u8],
+        entry: &GrfEntry,
+        file_name: &str,
+    ) -> Result<Vec<u8>, String> {
+        let from = entry.offset as usize + GRF_HEADER_SIZE;
+        let to = from + entry.length_aligned as usize;
+        if to > archive.len() {
+            return Err(format!("'{}' is truncated", file_name));
+        }
+        let mut buf = archive[from..to].to_vec();
 
         if entry.typ & GRF_FILELIST_TYPE_ENCRYPT_MIXED != 0 {
-            panic!("'{}' is encrypted!", file_name);
+            des::decrypt_mixed(&mut buf, entry.pack_size);
         } else if entry.typ & GRF_FILELIST_TYPE_ENCRYPT_HEADER != 0 {
-            panic!("'{}' is encrypted!", file_name);
+            des::decrypt_header(&mut buf);
         }
-        let mut decoder = libflate::zlib::Decoder::new(buf.as_slice()).unwrap();
-        let mut out = Vec::<u8>::with_capacity(entry.real_size as usize);
-        std::io::copy(&mut decoder, &mut out).unwrap();
-        return out;
+        let mut decoder = libflate::zlib::Decoder::new(buf.as_slice())
+            .map_err(|e| format!("Could not decompress '{}': {}", file_name, e))?;
+        let mut out =
+            Vec::<u8>::with_capacity((entry.real_size as usize).min(MAX_PREALLOCATED_SIZE));
+        std::io::copy(&mut decoder, &mut out)
+            .map_err(|e| format!("Could not decompress '{}': {}", file_name, e))?;
+        return Ok(out);
     }
 
     pub fn read_dir(&self, dir_name: &str) -> Vec<String> {
@@ -243,6 +381,115 @@ impl<'a> CommonAssetLoader {
     pub fn load_gat(&self, map_name: &str) -> Result<(Gat, Vec<BlockingRectangle>), String> {
         let file_name = format!("data\\{}.gat", map_name);
         let content = self.get_content(&file_name)?;
-        return Ok(Gat::load(BinaryReader::from_vec(content), map_name));
+        return Gat::load(BinaryReader::from_shared(content, &file_name), map_name)
+            .map_err(|e| e.to_string());
+    }
+}
+
+fn read_u32(buf: &[u8], index: usize) -> u32 {
+    return buf[index] as u32
+        | (buf[index + 1] as u32) << 8
+        | (buf[index + 2] as u32) << 16
+        | (buf[index + 3] as u32) << 24;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_u32(buf: &mut Vec<u8>, value: u32) {
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn encrypted_name(name: &str) -> Vec<u8> {
+        let mut bytes = name.as_bytes().to_vec();
+        bytes.resize((name.len() / 8 + 1) * 8, 0);
+        for block in bytes.chunks_exact_mut(8) {
+            des::decrypt_block(block);
+            for byte in block.iter_mut() {
+                *byte = (*byte >> 4) | (*byte << 4);
+            }
+        }
+        return bytes;
+    }
+
+    #[test]
+    fn file_table_0x103_is_decoded() {
+        let mut table = Vec::new();
+        for (name, offset) in &[("data\\Prontera.GAT", 100), ("data\\sprite.spr", 200)] {
+            let name = encrypted_name(name);
+            write_u32(&mut table, name.len() as u32 + 6);
+            table.extend_from_slice(&[0, 0]);
+            table.extend_from_slice(&name);
+            table.extend_from_slice(&[0, 0, 0, 0]);
+            // pack_size = 10, length_aligned = 16, real_size = 30
+            write_u32(&mut table, 30 + 10 + 715);
+            write_u32(&mut table, 16 + 37579);
+            write_u32(&mut table, 30);
+            table.push(GRF_FILELIST_TYPE_FILE);
+            write_u32(&mut table, *offset);
+        }
+
+        let entries = CommonAssetLoader::read_grf_entries_0x103(3, 2, table).unwrap();
+        let (grf_index, gat) = &entries["data\\prontera.gat"];
+        assert_eq!(*grf_index, 3);
+        assert_eq!(
+            (gat.pack_size, gat.length_aligned, gat.real_size, gat.offset),
+            (10, 16, 30, 100)
+        );
+        assert_ne!(gat.typ & GRF_FILELIST_TYPE_ENCRYPT_HEADER, 0);
+        let (_grf_index, spr) = &entries["data\\sprite.spr"];
+        assert_ne!(spr.typ & GRF_FILELIST_TYPE_ENCRYPT_MIXED, 0);
+
+        assert!(CommonAssetLoader::read_grf_entries_0x103(3, 3, vec![1, 2]).is_err());
+    }
+
+    #[test]
+    fn corrupt_real_size_is_not_preallocated() {
+        let mut encoder = libflate::zlib::Encoder::new(Vec::new()).unwrap();
+        std::io::Write::write_all(&mut encoder, b"content").unwrap();
+        let compressed = encoder.finish().into_result().unwrap();
+        let mut archive = vec![0; GRF_HEADER_SIZE];
+        archive.extend_from_slice(&compressed);
+        let entry = GrfEntry {
+            pack_size: compressed.len() as u32,
+            length_aligned: compressed.len() as u32,
+            real_size: std::u32::MAX,
+            typ: GRF_FILELIST_TYPE_FILE,
+            offset: 0,
+        };
+
+        let content = CommonAssetLoader::decode_entry(&archive, &entry, "corrupt.txt").unwrap();
+        assert_eq!(content, b"content");
+    }
+
+    #[test]
+    fn files_created_in_dir_sources_are_refreshed() {
+        let root = std::env::temp_dir().join(format!("rustarok_refresh_{}", std::process::id()));
+        let sprite_dir = root.join("data").join("sprite");
+        std::fs::create_dir_all(&sprite_dir).unwrap();
+        std::fs::write(sprite_dir.join("poring.spr"), b"old").unwrap();
+        let mut loader =
+            CommonAssetLoader::new(&[&root], &root.join("grf.cache"), 1024 * 1024).unwrap();
+        assert!(!loader.exists("data\\sprite\\poring.act"));
+
+        std::fs::write(sprite_dir.join("Poring.ACT"), b"act").unwrap();
+        std::fs::write(sprite_dir.join("poring.spr"), b"new").unwrap();
+        assert_eq!(
+            loader.refresh_file(&sprite_dir.join("Poring.ACT")),
+            Some("data\\sprite\\poring.act".to_owned())
+        );
+        assert_eq!(
+            *loader.get_content("data\\sprite\\poring.act").unwrap(),
+            b"act"
+        );
+        assert_eq!(
+            *loader.get_content("data\\sprite\\poring.spr").unwrap(),
+            b"new"
+        );
+        // outside of the sources
+        assert_eq!(loader.refresh_file(&std::env::temp_dir()), None);
+
+        std::fs::remove_dir_all(&root).unwrap();
     }
 }
@@ -0,0 +1,159 @@
+use crate::grf::asset_loader::CommonAssetLoader;
+use crate::grf::GrfEntry;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// One layer of the virtual filesystem, the type is decided by the path:
+/// directories are read as they are, files with a .zip extension as zip archives,
+/// anything else as a GRF archive
+#[derive(Clone)]
+pub(super) enum AssetSource {
+    Grf(Arc<GrfArchive>),
+    /// The root of the names, i.e. the directory which contains the 'data' directory
+    Dir(PathBuf),
+    Zip(Arc<Mutex<zip::ZipArchive<File>>>),
+}
+
+/// The whole archive is mapped into memory once, and the threads read it without locking
+pub(super) struct GrfArchive {
+    pub path: String,
+    pub data: memmap::Mmap,
+}
+
+#[derive(Clone, Debug)]
+pub(super) enum SourceEntry {
+    Grf(GrfEntry),
+    File(PathBuf),
+    /// index in the zip archive
+    Zip(usize),
+}
+
+/// The lookup key of the entries: lowercase with backslash separators, as in the GRF archives
+pub(super) fn normalize_name(name: &str) -> String {
+    return name.replace('/', "\\").to_ascii_lowercase();
+}
+
+impl AssetSource {
+    pub(super) fn open(path: &str) -> Result<AssetSource, String> {
+        let fs_path = Path::new(path);
+        return if fs_path.is_dir() {
+            Ok(AssetSource::Dir(fs_path.to_path_buf()))
+        } else if path.to_ascii_lowercase().ends_with(".zip") {
+            let file =
+                File::open(fs_path).map_err(|e| format!("Could not open '{}': {}", path, e))?;
+            let archive = zip::ZipArchive::new(file)
+                .map_err(|e| format!("Could not open '{}': {}", path, e))?;
+            Ok(AssetSource::Zip(Arc::new(Mutex::new(archive))))
+        } else {
+            let file =
+                File::open(fs_path).map_err(|e| format!("Could not open '{}': {}", path, e))?;
+            // the archives must not be modified while the game is running
+            let data = unsafe { memmap::Mmap::map(&file) }
+                .map_err(|e| format!("Could not map '{}': {}", path, e))?;
+            Ok(AssetSource::Grf(Arc::new(GrfArchive {
+                path: path.to_owned(),
+                data,
+            })))
+        };
+    }
+
+    /// The entries of GRF archives are read by the loader, because they are cached
+    pub(super) fn read_entries(&self) -> Result<HashMap<String, SourceEntry>, String> {
+        let mut entries = HashMap::new();
+        match self {
+            AssetSource::Grf(_archive) => {}
+            AssetSource::Dir(root) => {
+                log::info!("Loading {}", root.display());
+                read_dir_entries(root, "", &mut entries)?;
+            }
+            AssetSource::Zip(archive) => {
+                let mut archive = archive.lock().unwrap();
+                for i in 0..archive.len() {
+                    let file = archive.by_index(i).map_err(|e| e.to_string())?;
+                    if !file.name().ends_with('/') {
+                        entries.insert(normalize_name(file.name()), SourceEntry::Zip(i));
+                    }
+                }
+            }
+        }
+        return Ok(entries);
+    }
+
+    pub(super) fn read(&self, entry: &SourceEntry, file_name: &str) -> Result<Vec<u8>, String> {
+        return match (self, entry) {
+            (AssetSource::Grf(archive), SourceEntry::Grf(grf_entry)) => {
+                CommonAssetLoader::decode_entry(&archive.data, grf_entry, file_name)
+            }
+            (AssetSource::Dir(_root), SourceEntry::File(path)) => std::fs::read(path)
+                .map_err(|e| format!("Could not read '{}': {}", path.display(), e)),
+            (AssetSource::Zip(archive), SourceEntry::Zip(index)) => {
+                let mut archive = archive.lock().unwrap();
+                let mut file = archive
+                    .by_index(*index)
+                    .map_err(|e| format!("Could not get '{}': {}", file_name, e))?;
+                let mut buf = Vec::with_capacity(file.size() as usize);
+                file.read_to_end(&mut buf)
+                    .map_err(|e| format!("Could not get '{}': {}", file_name, e))?;
+                Ok(buf)
+            }
+            _ => Err(format!(
+                "'{}' does not belong to its source: {:?}",
+                file_name, entry
+            )),
+        };
+    }
+}
+
+fn read_dir_entries(
+    dir: &Path,
+    prefix: &str,
+    entries: &mut HashMap<String, SourceEntry>,
+) -> Result<(), String> {
+    let read_dir =
+        std::fs::read_dir(dir).map_err(|e| format!("Could not read '{}': {}", dir.display(), e))?;
+    for dir_entry in read_dir {
+        let path = dir_entry
+            .map_err(|e| format!("Could not read '{}': {}", dir.display(), e))?
+            .path();
+        let file_name = match path.file_name().and_then(|it| it.to_str()) {
+            Some(file_name) => file_name,
+            None => {
+                log::warn!("Skipping '{}', its name is not valid UTF-8", path.display());
+                continue;
+            }
+        };
+        let name = if prefix.is_empty() {
+            file_name.to_owned()
+        } else {
+            format!("{}\\{}", prefix, file_name)
+        };
+        if path.is_dir() {
+            read_dir_entries(&path, &name, entries)?;
+        } else {
+            entries.insert(normalize_name(&name), SourceEntry::File(path));
+        }
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dir_entries_are_case_insensitive() {
+        let root = std::env::temp_dir().join(format!("rustarok_dir_source_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("data").join("Sprite")).unwrap();
+        std::fs::write(root.join("data").join("Sprite").join("Poring.SPR"), b"spr").unwrap();
+
+        let source = AssetSource::open(root.to_str().unwrap()).unwrap();
+        let entries = source.read_entries().unwrap();
+        let entry = &entries[&normalize_name("DATA/sprite/poring.spr")];
+        assert_eq!(source.read(entry, "poring.spr").unwrap(), b"spr");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
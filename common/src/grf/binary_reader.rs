@@ -1,29 +1,86 @@
 use encoding::types::Encoding;
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// `len` bytes were requested, but the file ended
+    UnexpectedEof {
+        len: usize,
+    },
+    InvalidHeader(String),
+    UnsupportedVersion(f32),
+    InvalidData(String),
+}
+
+/// Every asset parser returns it instead of panicking, so a corrupt file can be replaced by
+/// a placeholder
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub file_name: String,
+    /// The position of the reader when the error was detected
+    pub offset: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}' at offset {}: ", self.file_name, self.offset)?;
+        match &self.kind {
+            ParseErrorKind::UnexpectedEof { len } => {
+                write!(f, "unexpected end of file while reading {} bytes", len)
+            }
+            ParseErrorKind::InvalidHeader(header) => write!(f, "invalid header '{}'", header),
+            ParseErrorKind::UnsupportedVersion(version) => {
+                write!(f, "unsupported version {}", version)
+            }
+            ParseErrorKind::InvalidData(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
 
 pub struct BinaryReader {
-    buf: Vec<u8>,
+    buf: Arc<Vec<u8>>,
     index: usize,
+    file_name: String,
 }
 
 impl BinaryReader {
     pub fn new<P: AsRef<Path> + Clone>(path: P) -> Result<BinaryReader, std::io::Error> {
-        let mut buf = BinaryReader {
-            buf: Vec::new(),
+        let mut content = Vec::new();
+        let _read = File::open(path.clone())?.read_to_end(&mut content)?;
+        return Ok(BinaryReader::from_vec(
+            content,
+            &path.as_ref().display().to_string(),
+        ));
+    }
+
+    /// `file_name` is used only in the error messages
+    pub fn from_vec(vec: Vec<u8>, file_name: &str) -> BinaryReader {
+        return BinaryReader::from_shared(Arc::new(vec), file_name);
+    }
+
+    /// The content of the asset loader is read without copying it
+    pub fn from_shared(buf: Arc<Vec<u8>>, file_name: &str) -> BinaryReader {
+        BinaryReader {
+            buf,
             index: 0,
-        };
-        let _read = File::open(path)?.read_to_end(&mut buf.buf)?;
-        return Ok(buf);
+            file_name: file_name.to_owned(),
+        }
     }
 
-    pub fn from_vec(vec: Vec<u8>) -> BinaryReader {
-        BinaryReader { buf: vec, index: 0 }
+    pub fn file_name(&self) -> &str {
+        &self.file_name
     }
 
     pub fn as_mut_slice(&mut self) -> &mut [u8] {
-        &mut self.buf[self.index..]
+        // the shared content is copied only here
+        &mut Arc::make_mut(&mut self.buf)[self.index..]
     }
 
     pub fn tell(&self) -> usize {
@@ -34,59 +91,120 @@ impl BinaryReader {
         self.buf.len()
     }
 
-    pub fn next_u8(&mut self) -> u8 {
-        self.index += 1;
-        self.buf[self.index - 1]
+    pub fn remaining(&self) -> usize {
+        self.buf.len().saturating_sub(self.index)
+    }
+
+    pub fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            file_name: self.file_name.clone(),
+            offset: self.index,
+            kind,
+        }
     }
 
-    pub fn next_f32(&mut self) -> f32 {
-        let result = unsafe { *(self.buf.as_ptr().offset(self.index as isize) as *const f32) };
-        self.index += 4;
-        return result;
+    pub fn invalid_data<T>(&self, msg: String) -> Result<T, ParseError> {
+        Err(self.error(ParseErrorKind::InvalidData(msg)))
     }
 
-    pub fn next_i32(&mut self) -> i32 {
-        let result = unsafe { *(self.buf.as_ptr().offset(self.index as isize) as *const i32) };
-        self.index += 4;
-        return result;
+    /// The counts in the files are not trusted for allocations, a corrupt count could
+    /// request gigabytes. Fails if the remaining bytes can't contain `count` items.
+    pub fn check_count(&self, count: usize, item_size: usize) -> Result<usize, ParseError> {
+        return match count.checked_mul(item_size) {
+            Some(len) if len <= self.remaining() => Ok(count),
+            _ => Err(self.error(ParseErrorKind::UnexpectedEof {
+                len: count.saturating_mul(item_size),
+            })),
+        };
+    }
+
+    /// Reads a u32 item count, see `check_count`
+    pub fn next_count(&mut self, item_size: usize) -> Result<usize, ParseError> {
+        let count = self.next_u32()? as usize;
+        return self.check_count(count, item_size);
+    }
+
+    pub fn next_u8(&mut self) -> Result<u8, ParseError> {
+        Ok(self.next(1)?[0])
+    }
+
+    pub fn next_f32(&mut self) -> Result<f32, ParseError> {
+        let bytes = self.next(4)?;
+        Ok(f32::from_bits(u32::from_le_bytes([
+            bytes[0], bytes[1], bytes[2], bytes[3],
+        ])))
     }
 
-    pub fn next_u32(&mut self) -> u32 {
-        let result = unsafe { *(self.buf.as_ptr().offset(self.index as isize) as *const u32) };
-        self.index += 4;
-        return result;
+    pub fn next_i32(&mut self) -> Result<i32, ParseError> {
+        let bytes = self.next(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
     }
 
-    pub fn next_u16(&mut self) -> u16 {
-        let result = unsafe { *(self.buf.as_ptr().offset(self.index as isize) as *const u16) };
-        self.index += 2;
-        return result;
+    pub fn next_u32(&mut self) -> Result<u32, ParseError> {
+        let bytes = self.next(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
     }
 
-    pub fn string(&mut self, max_len: u32) -> String {
-        let i = self.index;
-        self.index += max_len as usize;
+    pub fn next_u16(&mut self) -> Result<u16, ParseError> {
+        let bytes = self.next(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn string(&mut self, max_len: u32) -> Result<String, ParseError> {
+        let start = self.index;
         let bytes: Vec<u8> = self
-            .buf
+            .next(max_len)?
             .iter()
-            .skip(i)
-            .take(max_len as usize)
             .take_while(|b| **b != 0)
             .map(|b| *b)
             .collect();
-        let decoded = encoding::all::WINDOWS_1252
+        return encoding::all::WINDOWS_1252
             .decode(&bytes, encoding::DecoderTrap::Strict)
-            .unwrap();
-        decoded
+            .map_err(|e| ParseError {
+                file_name: self.file_name.clone(),
+                offset: start,
+                kind: ParseErrorKind::InvalidData(format!("invalid string: {}", e)),
+            });
+    }
+
+    /// Strings of the newer formats (e.g. RSM 2.2) are prefixed by their length
+    pub fn string_with_len(&mut self) -> Result<String, ParseError> {
+        let len = self.next_count(1)?;
+        return self.string(len as u32);
     }
 
-    pub fn skip(&mut self, size: u32) {
-        self.index += size as usize;
+    pub fn skip(&mut self, size: u32) -> Result<(), ParseError> {
+        self.next(size).map(|_| ())
     }
 
-    pub fn next(&mut self, size: u32) -> &[u8] {
+    pub fn next(&mut self, size: u32) -> Result<&[u8], ParseError> {
         let from = self.index;
-        self.index += size as usize;
-        &self.buf[from..self.index]
+        let size = size as usize;
+        if size > self.remaining() {
+            return Err(self.error(ParseErrorKind::UnexpectedEof { len: size }));
+        }
+        self.index += size;
+        Ok(&self.buf[from..self.index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reading_past_the_end_is_an_error() {
+        let mut buf = BinaryReader::from_vec(vec![1, 0, 0, 0, 2], "test.bin");
+        assert_eq!(buf.next_u32(), Ok(1));
+        assert_eq!(
+            buf.next_u16(),
+            Err(ParseError {
+                file_name: "test.bin".to_owned(),
+                offset: 4,
+                kind: ParseErrorKind::UnexpectedEof { len: 2 },
+            })
+        );
+        assert_eq!(buf.next_u8(), Ok(2));
+        assert!(buf.check_count(std::usize::MAX, 2).is_err());
     }
 }
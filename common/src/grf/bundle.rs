@@ -0,0 +1,143 @@
+use crate::grf::asset_loader::CommonAssetLoader;
+use crate::grf::grf_cache::{self, GrfFingerprint};
+use byteorder::LittleEndian;
+use byteorder::ReadBytesExt;
+use byteorder::WriteBytesExt;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
+
+const BUNDLE_MAGIC: &[u8; 4] = b"RBDL";
+/// Has to be increased whenever the layout of the bundle or of the baked structures changes
+const BUNDLE_VERSION: u32 = 1;
+
+/// Decoded pixels of a texture, they can be uploaded as they are
+#[derive(Serialize, Deserialize)]
+pub struct BakedTexture {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// The OpenGL min/mag filter
+    pub min_mag: u32,
+    /// 4 bytes per pixel, row by row
+    pub rgba: Vec<u8>,
+}
+
+/// Writes the already decoded assets (`content`) into a bundle, so they don't have to be decoded
+/// again on every launch.
+/// The bundle remembers the asset sources it was baked from, it is ignored if any of them changes.
+pub fn save<T: Serialize>(
+    bundle_path: &Path,
+    asset_loader: &CommonAssetLoader,
+    content: &T,
+) -> Result<(), String> {
+    let fingerprints = asset_loader
+        .source_fingerprints()
+        .map_err(|e| e.to_string())?;
+    let mut buf = Vec::new();
+    write_bundle(&mut buf, &fingerprints, content)?;
+    if let Some(dir) = bundle_path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let tmp_path = bundle_path.with_extension("bundle.tmp");
+    std::fs::write(&tmp_path, &buf).map_err(|e| e.to_string())?;
+    return std::fs::rename(&tmp_path, bundle_path).map_err(|e| e.to_string());
+}
+
+/// Reads the whole bundle at once.
+/// None if it does not exist or it was baked from different asset sources.
+pub fn load<T: DeserializeOwned>(
+    bundle_path: &Path,
+    asset_loader: &CommonAssetLoader,
+) -> Result<Option<T>, String> {
+    let content = match std::fs::read(bundle_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(None);
+        }
+        Err(e) => {
+            return Err(format!("'{}': {}", bundle_path.display(), e));
+        }
+    };
+    let fingerprints = asset_loader
+        .source_fingerprints()
+        .map_err(|e| e.to_string())?;
+    return read_bundle(&content, &fingerprints)
+        .map_err(|e| format!("'{}' is corrupt: {}", bundle_path.display(), e));
+}
+
+fn write_bundle<T: Serialize>(
+    writer: &mut Vec<u8>,
+    fingerprints: &[GrfFingerprint],
+    content: &T,
+) -> Result<(), String> {
+    writer.write_all(BUNDLE_MAGIC).map_err(|e| e.to_string())?;
+    writer
+        .write_u32::<LittleEndian>(BUNDLE_VERSION)
+        .map_err(|e| e.to_string())?;
+    grf_cache::write_fingerprints(writer, fingerprints).map_err(|e| e.to_string())?;
+    return bincode::serialize_into(writer, content).map_err(|e| e.to_string());
+}
+
+fn read_bundle<T: DeserializeOwned>(
+    mut reader: &[u8],
+    fingerprints: &[GrfFingerprint],
+) -> Result<Option<T>, String> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic).map_err(|e| e.to_string())?;
+    if &magic != BUNDLE_MAGIC {
+        return Err("Not a bundle".to_owned());
+    }
+    let version = reader
+        .read_u32::<LittleEndian>()
+        .map_err(|e| e.to_string())?;
+    if version != BUNDLE_VERSION {
+        return Ok(None);
+    }
+    if !grf_cache::read_fingerprints(&mut reader, fingerprints).map_err(|e| e.to_string())? {
+        return Ok(None);
+    }
+    return bincode::deserialize(reader)
+        .map(Some)
+        .map_err(|e| e.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grf::grf_cache::fingerprint;
+
+    // the fingerprints are tested with the grf cache
+    #[test]
+    fn bundle_of_other_version_or_magic_is_rejected() {
+        let textures = vec![BakedTexture {
+            name: "data\\texture\\grass.bmp".to_owned(),
+            width: 1,
+            height: 1,
+            min_mag: 0x2600,
+            rgba: vec![1, 2, 3, 255],
+        }];
+        let mut buf = Vec::new();
+        write_bundle(&mut buf, &[fingerprint(1000)], &textures).unwrap();
+
+        let loaded: Vec<BakedTexture> = read_bundle(&buf, &[fingerprint(1000)]).unwrap().unwrap();
+        assert_eq!(loaded[0].name, "data\\texture\\grass.bmp");
+        assert_eq!(loaded[0].rgba, vec![1, 2, 3, 255]);
+        // baked by an older client
+        let mut old_version = buf.clone();
+        old_version[4..8].copy_from_slice(&(BUNDLE_VERSION - 1).to_le_bytes());
+        assert!(
+            read_bundle::<Vec<BakedTexture>>(&old_version, &[fingerprint(1000)])
+                .unwrap()
+                .is_none()
+        );
+        // the grf cache of the same sources
+        let mut other_magic = buf.clone();
+        other_magic[0..4].copy_from_slice(b"RGRC");
+        assert!(read_bundle::<Vec<BakedTexture>>(&other_magic, &[fingerprint(1000)]).is_err());
+        assert!(
+            read_bundle::<Vec<BakedTexture>>(&buf[..buf.len() - 2], &[fingerprint(1000)]).is_err()
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use lru::LruCache;
+use std::sync::Arc;
+
+/// The decompressed content of the recently used archive entries, so e.g. the textures which
+/// are shared by many models are inflated only once during a map load
+pub(super) struct ContentCache {
+    entries: LruCache<String, Arc<Vec<u8>>>,
+    budget_bytes: usize,
+    used_bytes: usize,
+}
+
+impl ContentCache {
+    pub(super) fn new(budget_bytes: usize) -> ContentCache {
+        ContentCache {
+            entries: LruCache::unbounded(),
+            budget_bytes,
+            used_bytes: 0,
+        }
+    }
+
+    pub(super) fn get(&mut self, name: &str) -> Option<Arc<Vec<u8>>> {
+        return self.entries.get(&name.to_owned()).cloned();
+    }
+
+    pub(super) fn insert(&mut self, name: String, content: Arc<Vec<u8>>) {
+        // a huge entry would evict everything else
+        if content.len() > self.budget_bytes / 4 {
+            return;
+        }
+        self.remove(&name);
+        self.used_bytes += content.len();
+        self.entries.put(name, content);
+        while self.used_bytes > self.budget_bytes {
+            match self.entries.pop_lru() {
+                Some((_name, evicted)) => self.used_bytes -= evicted.len(),
+                None => break,
+            }
+        }
+    }
+
+    pub(super) fn remove(&mut self, name: &str) {
+        if let Some(removed) = self.entries.pop(&name.to_owned()) {
+            self.used_bytes -= removed.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn least_recently_used_entries_are_evicted_over_budget() {
+        let mut cache = ContentCache::new(100);
+        cache.insert("a".to_owned(), Arc::new(vec![0; 20]));
+        cache.insert("b".to_owned(), Arc::new(vec![0; 20]));
+        cache.insert("c".to_owned(), Arc::new(vec![0; 20]));
+        cache.insert("d".to_owned(), Arc::new(vec![0; 20]));
+        assert!(cache.get("a").is_some());
+        cache.insert("e".to_owned(), Arc::new(vec![0; 25]));
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+        assert_eq!(cache.used_bytes, 85);
+        // too big to be cached
+        cache.insert("f".to_owned(), Arc::new(vec![0; 30]));
+        assert!(cache.get("f").is_none());
+    }
+}
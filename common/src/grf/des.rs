@@ -0,0 +1,295 @@
+// The GRF variant of DES: a single round with a fixed key, applied to some 8 byte blocks of the
+// compressed entries and to the file names of 0x103 archives.
+
+const MASK: [u8; 8] = [0x80, 0x40, 0x20, 0x10, 0x08, 0x04, 0x02, 0x01];
+
+const INITIAL_PERMUTATION_TABLE: [u8; 64] = [
+    58, 50, 42, 34, 26, 18, 10, 2, 60, 52, 44, 36, 28, 20, 12, 4, 62, 54, 46, 38, 30, 22, 14, 6,
+    64, 56, 48, 40, 32, 24, 16, 8, 57, 49, 41, 33, 25, 17, 9, 1, 59, 51, 43, 35, 27, 19, 11, 3, 61,
+    53, 45, 37, 29, 21, 13, 5, 63, 55, 47, 39, 31, 23, 15, 7,
+];
+
+const FINAL_PERMUTATION_TABLE: [u8; 64] = [
+    40, 8, 48, 16, 56, 24, 64, 32, 39, 7, 47, 15, 55, 23, 63, 31, 38, 6, 46, 14, 54, 22, 62, 30,
+    37, 5, 45, 13, 53, 21, 61, 29, 36, 4, 44, 12, 52, 20, 60, 28, 35, 3, 43, 11, 51, 19, 59, 27,
+    34, 2, 42, 10, 50, 18, 58, 26, 33, 1, 41, 9, 49, 17, 57, 25,
+];
+
+const TRANSPOSITION_TABLE: [u8; 32] = [
+    16, 7, 20, 21, 29, 12, 28, 17, 1, 15, 23, 26, 5, 18, 31, 10, 2, 8, 24, 14, 32, 27, 3, 9, 19,
+    13, 30, 6, 22, 11, 4, 25,
+];
+
+const SUBSTITUTION_BOX_TABLE: [[u8; 64]; 4] = [
+    [
+        0xef, 0x03, 0x41, 0xfd, 0xd8, 0x74, 0x1e, 0x47, 0x26, 0xef, 0xfb, 0x22, 0xb3, 0xd8, 0x84,
+        0x1e, 0x39, 0xac, 0xa7, 0x60, 0x62, 0xc1, 0xcd, 0xba, 0x5c, 0x96, 0x90, 0x59, 0x05, 0x3b,
+        0x7a, 0x85, 0x40, 0xfd, 0x1e, 0xc8, 0xe7, 0x8a, 0x8b, 0x21, 0xda, 0x43, 0x64, 0x9f, 0x2d,
+        0x14, 0xb1, 0x72, 0xf5, 0x5b, 0xc8, 0xb6, 0x9c, 0x37, 0x76, 0xec, 0x39, 0xa0, 0xa3, 0x05,
+        0x52, 0x6e, 0x0f, 0xd9,
+    ],
+    [
+        0xa7, 0xdd, 0x0d, 0x78, 0x9e, 0x0b, 0xe3, 0x95, 0x60, 0x36, 0x36, 0x4f, 0xf9, 0x60, 0x5a,
+        0xa3, 0x11, 0x24, 0xd2, 0x87, 0xc8, 0x52, 0x75, 0xec, 0xbb, 0xc1, 0x4c, 0xba, 0x24, 0xfe,
+        0x8f, 0x19, 0xda, 0x13, 0x66, 0xaf, 0x49, 0xd0, 0x90, 0x06, 0x8c, 0x6a, 0xfb, 0x91, 0x37,
+        0x8d, 0x0d, 0x78, 0xbf, 0x49, 0x11, 0xf4, 0x23, 0xe5, 0xce, 0x3b, 0x55, 0xbc, 0xa2, 0x57,
+        0xe8, 0x22, 0x74, 0xce,
+    ],
+    [
+        0x2c, 0xea, 0xc1, 0xbf, 0x4a, 0x24, 0x1f, 0xc2, 0x79, 0x47, 0xa2, 0x7c, 0xb6, 0xd9, 0x68,
+        0x15, 0x80, 0x56, 0x5d, 0x01, 0x33, 0xfd, 0xf4, 0xae, 0xde, 0x30, 0x07, 0x9b, 0xe5, 0x83,
+        0x9b, 0x68, 0x49, 0xb4, 0x2e, 0x83, 0x1f, 0xc2, 0xb5, 0x7c, 0xa2, 0x19, 0xd8, 0xe5, 0x7c,
+        0x2f, 0x83, 0xda, 0xf7, 0x6b, 0x90, 0xfe, 0xc4, 0x01, 0x5a, 0x97, 0x61, 0xa6, 0x3d, 0x40,
+        0x0b, 0x58, 0xe6, 0x3d,
+    ],
+    [
+        0x4d, 0xd1, 0xb2, 0x0f, 0x28, 0xbd, 0xe4, 0x78, 0xf6, 0x4a, 0x0f, 0x93, 0x8b, 0x17, 0xd1,
+        0xa4, 0x3a, 0xec, 0xc9, 0x35, 0x93, 0x56, 0x7e, 0xcb, 0x55, 0x20, 0xa0, 0xfe, 0x6c, 0x89,
+        0x17, 0x62, 0x17, 0x62, 0x4b, 0xb1, 0xb4, 0xde, 0xd1, 0x87, 0xc9, 0x14, 0x3c, 0x4a, 0x7e,
+        0xa8, 0xe2, 0x7d, 0xa0, 0x9f, 0xf6, 0x5c, 0x6a, 0x09, 0x8d, 0xf0, 0x0f, 0xe3, 0x53, 0x25,
+        0x95, 0x36, 0x28, 0xcb,
+    ],
+];
+
+fn permutate(block: &mut [u8], table: &[u8; 64]) {
+    let mut tmp = [0u8; 8];
+    for (i, from) in table.iter().enumerate() {
+        let j = (*from - 1) as usize;
+        if block[j >> 3] & MASK[j & 7] != 0 {
+            tmp[i >> 3] |= MASK[i & 7];
+        }
+    }
+    block.copy_from_slice(&tmp);
+}
+
+fn expansion(block: &[u8; 8]) -> [u8; 8] {
+    return [
+        ((block[7] << 5) | (block[4] >> 3)) & 0x3f,
+        ((block[4] << 1) | (block[5] >> 7)) & 0x3f,
+        ((block[4] << 5) | (block[5] >> 3)) & 0x3f,
+        ((block[5] << 1) | (block[6] >> 7)) & 0x3f,
+        ((block[5] << 5) | (block[6] >> 3)) & 0x3f,
+        ((block[6] << 1) | (block[7] >> 7)) & 0x3f,
+        ((block[6] << 5) | (block[7] >> 3)) & 0x3f,
+        ((block[7] << 1) | (block[4] >> 7)) & 0x3f,
+    ];
+}
+
+fn substitution(block: &[u8; 8]) -> [u8; 8] {
+    let mut tmp = [0u8; 8];
+    for (i, table) in SUBSTITUTION_BOX_TABLE.iter().enumerate() {
+        tmp[i] = (table[block[i * 2] as usize] & 0xf0) | (table[block[i * 2 + 1] as usize] & 0x0f);
+    }
+    return tmp;
+}
+
+fn transposition(block: &[u8; 8]) -> [u8; 8] {
+    let mut tmp = [0u8; 8];
+    for (i, from) in TRANSPOSITION_TABLE.iter().enumerate() {
+        let j = (*from - 1) as usize;
+        if block[j >> 3] & MASK[j & 7] != 0 {
+            tmp[(i >> 3) + 4] |= MASK[i & 7];
+        }
+    }
+    return tmp;
+}
+
+fn round_function(block: &mut [u8]) {
+    let mut right = [0u8; 8];
+    right.copy_from_slice(block);
+    let tmp = transposition(&substitution(&expansion(&right)));
+    for i in 0..4 {
+        block[i] ^= tmp[i + 4];
+    }
+}
+
+/// There is only one round, so decryption and encryption are the same
+pub fn decrypt_block(block: &mut [u8]) {
+    permutate(block, &INITIAL_PERMUTATION_TABLE);
+    round_function(block);
+    permutate(block, &FINAL_PERMUTATION_TABLE);
+}
+
+fn substitute_byte(byte: u8) -> u8 {
+    return match byte {
+        0x00 => 0x2b,
+        0x2b => 0x00,
+        0x6c => 0x80,
+        0x80 => 0x6c,
+        0x01 => 0x68,
+        0x68 => 0x01,
+        0x48 => 0x77,
+        0x77 => 0x48,
+        0x60 => 0xff,
+        0xff => 0x60,
+        0xb9 => 0xc0,
+        0xc0 => 0xb9,
+        0xfe => 0xeb,
+        0xeb => 0xfe,
+        _ => byte,
+    };
+}
+
+fn unshuffle_block(block: &mut [u8]) {
+    let src = [
+        block[0], block[1], block[2], block[3], block[4], block[5], block[6], block[7],
+    ];
+    block[0] = src[3];
+    block[1] = src[4];
+    block[2] = src[6];
+    block[3] = src[0];
+    block[4] = src[1];
+    block[5] = src[2];
+    block[6] = src[5];
+    block[7] = substitute_byte(src[7]);
+}
+
+/// Every block of the header (first 20 blocks) is encrypted, after that only every 'cycle'th,
+/// and every 7th of the remaining plain blocks is shuffled
+pub fn decrypt_mixed(data: &mut [u8], pack_size: u32) {
+    let digits = pack_size.to_string().len();
+    let cycle = if digits < 3 {
+        1
+    } else if digits < 5 {
+        digits + 1
+    } else if digits < 7 {
+        digits + 9
+    } else {
+        digits + 15
+    };
+    let mut plain_block_index = 0;
+    for (i, block) in data.chunks_exact_mut(8).enumerate() {
+        if i < 20 || i % cycle == 0 {
+            decrypt_block(block);
+            continue;
+        }
+        if plain_block_index % 7 == 0 && plain_block_index != 0 {
+            unshuffle_block(block);
+        }
+        plain_block_index += 1;
+    }
+}
+
+pub fn decrypt_header(data: &mut [u8]) {
+    for block in data.chunks_exact_mut(8).take(20) {
+        decrypt_block(block);
+    }
+}
+
+/// The file names in 0x103 archives have their nibbles swapped besides the encryption
+pub fn decrypt_file_name(data: &mut [u8]) {
+    for block in data.chunks_exact_mut(8) {
+        for byte in block.iter_mut() {
+            *byte = (*byte >> 4) | (*byte << 4);
+        }
+        decrypt_block(block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_round_decryption_is_its_own_inverse() {
+        let original = [0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0];
+        let mut block = original;
+        decrypt_block(&mut block);
+        assert_ne!(block, original);
+        decrypt_block(&mut block);
+        assert_eq!(block, original);
+    }
+
+    // The expected blocks were computed with the standard DES tables, a single round and
+    // a zero key
+
+    #[test]
+    fn block_is_decrypted_with_the_des_tables() {
+        let mut block = [0x46, 0x42, 0x02, 0x20, 0x22, 0x3c, 0x26, 0x2e];
+        decrypt_block(&mut block);
+        assert_eq!(&block, b"GRF blck");
+    }
+
+    /// 30 blocks filled with their own offsets, the last byte of the shuffled blocks is one
+    /// of the substituted bytes
+    fn encrypted_entry() -> Vec<u8> {
+        let mut data: Vec<u8> = (0..240).map(|i| i as u8).collect();
+        data[223] = 0xb9;
+        data[239] = 0x6c;
+        return data;
+    }
+
+    fn block(data: &[u8], index: usize) -> &[u8] {
+        &data[index * 8..index * 8 + 8]
+    }
+
+    #[test]
+    fn every_5th_block_is_decrypted_and_every_7th_plain_block_is_shuffled() {
+        let mut data = encrypted_entry();
+        // 4 digits
+        decrypt_mixed(&mut data, 1000);
+
+        assert_eq!(
+            block(&data, 19),
+            [0x8d, 0x99, 0xce, 0x8a, 0xc9, 0x99, 0xdb, 0xce]
+        );
+        assert_eq!(
+            block(&data, 20),
+            [0xb4, 0xa1, 0xb6, 0xb3, 0xe0, 0xb5, 0xa2, 0xb7]
+        );
+        assert_eq!(
+            block(&data, 21),
+            [0xa8, 0xa9, 0xaa, 0xab, 0xac, 0xad, 0xae, 0xaf]
+        );
+        assert_eq!(
+            block(&data, 25),
+            [0xdd, 0xc9, 0x9e, 0xda, 0x99, 0xc9, 0x8b, 0x9e]
+        );
+        assert_eq!(
+            block(&data, 28),
+            [0xe0, 0xe1, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7]
+        );
+        // the 8th plain block
+        assert_eq!(
+            block(&data, 29),
+            [0xeb, 0xec, 0xee, 0xe8, 0xe9, 0xea, 0xed, 0x80]
+        );
+    }
+
+    #[test]
+    fn every_14th_block_is_decrypted_for_5_digit_sizes() {
+        let mut data = encrypted_entry();
+        decrypt_mixed(&mut data, 10000);
+
+        assert_eq!(
+            block(&data, 20),
+            [0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7]
+        );
+        assert_eq!(
+            block(&data, 25),
+            [0xc8, 0xc9, 0xca, 0xcb, 0xcc, 0xcd, 0xce, 0xcf]
+        );
+        // the 8th plain block
+        assert_eq!(
+            block(&data, 27),
+            [0xdb, 0xdc, 0xde, 0xd8, 0xd9, 0xda, 0xdd, 0xc0]
+        );
+        assert_eq!(
+            block(&data, 28),
+            [0xf4, 0xe1, 0xf6, 0xf3, 0xa0, 0xf5, 0xe2, 0xf7]
+        );
+    }
+
+    #[test]
+    fn only_the_first_20_blocks_of_the_header_are_decrypted() {
+        let mut data = encrypted_entry();
+        decrypt_header(&mut data);
+
+        assert_eq!(
+            block(&data, 19),
+            [0x8d, 0x99, 0xce, 0x8a, 0xc9, 0x99, 0xdb, 0xce]
+        );
+        assert_eq!(block(&data, 20), block(&encrypted_entry(), 20));
+        assert_eq!(block(&data, 29), block(&encrypted_entry(), 29));
+    }
+}
@@ -1,6 +1,6 @@
 use std::fs::File;
 
-use crate::grf::binary_reader::BinaryReader;
+use crate::grf::binary_reader::{BinaryReader, ParseError, ParseErrorKind};
 use byteorder::WriteBytesExt;
 use byteorder::{LittleEndian, ReadBytesExt};
 
@@ -27,12 +27,43 @@ pub struct Gat {
 }
 
 impl Gat {
+    /// A test map from the rows of its cells, '#' is a wall, every other character is a walkable
+    /// and snipable cell
+    #[cfg(test)]
+    pub(crate) fn from_rows(rows: &[&str]) -> Gat {
+        Gat {
+            width: rows[0].len() as u32,
+            height: rows.len() as u32,
+            cells: rows
+                .iter()
+                .flat_map(|row| row.chars())
+                .map(|c| GatCell {
+                    cells: [0.0; 4],
+                    cell_type: if c == '#' {
+                        CellType::None as u8
+                    } else {
+                        CellType::Walkable as u8 | CellType::Snipable as u8
+                    },
+                })
+                .collect(),
+            version: 1.2,
+        }
+    }
+
     pub fn is_walkable(&self, x: usize, y: usize) -> bool {
         self.cells
             .get(y * self.width as usize + x)
             .map(|it| it.cell_type & CellType::Walkable as u8 != 0)
             .unwrap_or(false)
     }
+
+    /// Whether it is possible to see and shoot through the cell
+    pub fn is_snipable(&self, x: usize, y: usize) -> bool {
+        self.cells
+            .get(y * self.width as usize + x)
+            .map(|it| it.cell_type & CellType::Snipable as u8 != 0)
+            .unwrap_or(false)
+    }
 }
 
 static TYPE_TABLE: [u8; 7] = [
@@ -55,45 +86,30 @@ pub struct BlockingRectangle {
 }
 
 impl Gat {
-    pub fn load(mut buf: BinaryReader, map_name: &str) -> (Self, Vec<BlockingRectangle>) {
-        let header = buf.string(4);
-        if header != "GRAT" {
-            panic!("Invalig GAT header: {}", header);
-        }
-
-        let version = buf.next_u8() as f32 + buf.next_u8() as f32 / 10f32;
-        let width = buf.next_u32();
-        let height = buf.next_u32();
-        let cells: Vec<GatCell> = (0..width * height)
-            .map(|_i| GatCell {
-                cells: [
-                    buf.next_f32() * 0.2,
-                    buf.next_f32() * 0.2,
-                    buf.next_f32() * 0.2,
-                    buf.next_f32() * 0.2,
-                ],
-                cell_type: TYPE_TABLE[buf.next_u32() as usize],
-            })
-            .collect();
+    /// The blocking rectangles are cached into '<map_name>.cel', because merging the cells
+    /// takes a while
+    pub fn load(
+        buf: BinaryReader,
+        map_name: &str,
+    ) -> Result<(Self, Vec<BlockingRectangle>), ParseError> {
+        let gat = Gat::parse(buf)?;
         let rectangles = if let Ok(mut cache_file) = File::open(map_name.to_owned() + ".cel") {
             let mut rectangles = vec![];
-            loop {
-                let area = cache_file.read_u32::<LittleEndian>();
-                if area.is_err() {
-                    break;
-                }
-                rectangles.push(BlockingRectangle {
-                    area: area.unwrap() as i32,
-                    start_x: cache_file.read_u16::<LittleEndian>().unwrap() as i32,
-                    bottom: cache_file.read_u16::<LittleEndian>().unwrap() as i32,
-                    width: cache_file.read_u16::<LittleEndian>().unwrap() as i32,
-                    height: cache_file.read_u16::<LittleEndian>().unwrap() as i32,
-                });
+            let mut read_rectangle = || -> Result<BlockingRectangle, std::io::Error> {
+                Ok(BlockingRectangle {
+                    area: cache_file.read_u32::<LittleEndian>()? as i32,
+                    start_x: cache_file.read_u16::<LittleEndian>()? as i32,
+                    bottom: cache_file.read_u16::<LittleEndian>()? as i32,
+                    width: cache_file.read_u16::<LittleEndian>()? as i32,
+                    height: cache_file.read_u16::<LittleEndian>()? as i32,
+                })
+            };
+            while let Ok(rectangle) = read_rectangle() {
+                rectangles.push(rectangle);
             }
             rectangles
         } else {
-            let rectangles =
-                Gat::merge_cells_into_convex_rectangles(&cells, width as usize, height as usize);
+            let rectangles = gat.blocking_rectangles();
             match File::create(map_name.to_owned() + ".cel") {
                 Ok(mut cache_file) => {
                     for rectangle in rectangles.iter() {
@@ -121,14 +137,53 @@ impl Gat {
             rectangles
         };
 
-        (
-            Gat {
-                width,
-                height,
-                cells,
-                version,
-            },
-            rectangles,
+        Ok((gat, rectangles))
+    }
+
+    pub fn parse(mut buf: BinaryReader) -> Result<Gat, ParseError> {
+        let header = buf.string(4)?;
+        if header != "GRAT" {
+            return Err(buf.error(ParseErrorKind::InvalidHeader(header)));
+        }
+
+        let version = buf.next_u8()? as f32 + buf.next_u8()? as f32 / 10f32;
+        let width = buf.next_u32()?;
+        let height = buf.next_u32()?;
+        if width == 0 || height == 0 {
+            return buf.invalid_data(format!("invalid map size {}x{}", width, height));
+        }
+        let count = (width as usize)
+            .checked_mul(height as usize)
+            .unwrap_or(std::usize::MAX);
+        buf.check_count(count, 20)?;
+        let cells: Vec<GatCell> = (0..count)
+            .map(|_i| {
+                let cells = [
+                    buf.next_f32()? * 0.2,
+                    buf.next_f32()? * 0.2,
+                    buf.next_f32()? * 0.2,
+                    buf.next_f32()? * 0.2,
+                ];
+                let cell_type = match TYPE_TABLE.get(buf.next_u32()? as usize) {
+                    Some(cell_type) => *cell_type,
+                    None => return buf.invalid_data("invalid cell type".to_owned()),
+                };
+                Ok(GatCell { cells, cell_type })
+            })
+            .collect::<Result<_, ParseError>>()?;
+        Ok(Gat {
+            width,
+            height,
+            cells,
+            version,
+        })
+    }
+
+    pub fn blocking_rectangles(&self) -> Vec<BlockingRectangle> {
+        Gat::merge_cells_into_convex_rectangles(
+            &self.cells,
+            self.width as usize,
+            self.height as usize,
         )
     }
 
@@ -148,19 +203,16 @@ impl Gat {
             let largest_rect = {
                 let row_areas =
                     Gat::calc_area_of_continous_convex_cells(&non_walkable_cells, width, height);
-                row_areas
-                    .iter()
-                    .max_by(|x, y| x.area.cmp(&y.area))
-                    .unwrap()
-                    .clone()
+                match row_areas.iter().max_by(|x, y| x.area.cmp(&y.area)) {
+                    Some(largest_rect) => largest_rect.clone(),
+                    None => break,
+                }
             };
             // remove the max rectangle
             let start_y = largest_rect.bottom - (largest_rect.height - 1);
-            for x in largest_rect.start_x..=largest_rect.start_x + largest_rect.width {
+            for x in largest_rect.start_x..largest_rect.start_x + largest_rect.width {
                 for y in start_y..=largest_rect.bottom {
                     let i = (y as usize * width) + x as usize;
-                    // TODO alde cas 04
-                    // thread 'main' panicked at 'index out of bounds: the len is 57600 but the index is 57600',
                     non_walkable_cells[i] = false;
                 }
             }
@@ -349,4 +401,26 @@ mod tests {
             expected_output
         );
     }
+
+    #[test]
+    fn truncated_and_corrupt_files_are_errors() {
+        let mut content = b"GRAT\x01\x02".to_vec();
+        content.extend_from_slice(&2u32.to_le_bytes());
+        content.extend_from_slice(&1u32.to_le_bytes());
+        for cell_type in &[0u32, 1] {
+            content.extend_from_slice(&[0; 16]);
+            content.extend_from_slice(&cell_type.to_le_bytes());
+        }
+        let gat = Gat::parse(BinaryReader::from_vec(content.clone(), "test.gat")).unwrap();
+        assert!(gat.is_walkable(0, 0));
+        assert!(!gat.is_walkable(1, 0));
+
+        for len in 0..content.len() {
+            let truncated = content[0..len].to_vec();
+            assert!(Gat::parse(BinaryReader::from_vec(truncated, "test.gat")).is_err());
+        }
+        // unknown cell type
+        content[6 + 8 + 16] = 200;
+        assert!(Gat::parse(BinaryReader::from_vec(content, "test.gat")).is_err());
+    }
 }
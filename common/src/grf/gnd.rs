@@ -1,9 +1,9 @@
 use nalgebra::{Rotation3, Vector3};
-use sdl2::pixels::PixelFormatEnum;
-use sdl2::rect::Rect;
+use serde::{Deserialize, Serialize};
 
-use rustarok_common::common::v3;
-use rustarok_common::grf::binary_reader::BinaryReader;
+use crate::common::v3;
+use crate::grf::binary_reader::{BinaryReader, ParseError, ParseErrorKind};
+use crate::grf::rsw::WaterData;
 
 pub struct Gnd {
     pub version: f32,
@@ -22,6 +22,27 @@ pub struct Gnd {
     pub water_vert_count: usize,
     pub water_mesh: Vec<WaterVertex>,
     pub shadow_map: Vec<[WaterVertex; 6]>,
+    /// Since 1.8 the water is stored here instead of the RSW
+    pub water: Option<GndWater>,
+}
+
+/// The map is split into `split_width * split_height` zones, each has its own water
+pub struct GndWater {
+    pub water: WaterData,
+    pub split_width: u32,
+    pub split_height: u32,
+    pub zones: Vec<WaterData>,
+}
+
+impl GndWater {
+    fn zone_at(&self, x: u32, y: u32, map_width: u32, map_height: u32) -> &WaterData {
+        let zone_x = (x as u64 * self.split_width as u64 / map_width as u64) as usize;
+        let zone_y = (y as u64 * self.split_height as u64 / map_height as u64) as usize;
+        return self
+            .zones
+            .get(zone_y * self.split_width as usize + zone_x)
+            .unwrap_or(&self.water);
+    }
 }
 
 pub struct LightmapData {
@@ -51,6 +72,7 @@ pub struct Surface {
     pub tile_right: isize,
 }
 
+#[derive(Serialize, Deserialize)]
 #[repr(packed)]
 pub struct MeshVertex {
     pub pos: [f32; 3],
@@ -67,21 +89,35 @@ pub struct WaterVertex {
 }
 
 impl Gnd {
-    pub(super) fn load(mut buf: BinaryReader, water_level: f32, water_height: f32) -> Self {
-        let header = buf.string(4);
+    pub fn load(
+        mut buf: BinaryReader,
+        water_level: f32,
+        water_height: f32,
+    ) -> Result<Self, ParseError> {
+        let header = buf.string(4)?;
         if header != "GRGN" {
-            panic!("Invalig Gnd header: {}", header);
+            return Err(buf.error(ParseErrorKind::InvalidHeader(header)));
         }
 
-        let version = buf.next_u8() as f32 + buf.next_u8() as f32 / 10f32;
-        let width = buf.next_u32();
-        let height = buf.next_u32();
-        let zoom = buf.next_f32();
-
-        let (texture_names, texture_indices) = Gnd::load_textures(&mut buf);
-        let lightmaps = Gnd::load_lightmaps(&mut buf);
-        let tiles = Gnd::load_tiles(&mut buf, texture_names.len(), &texture_indices);
-        let surfaces = Gnd::load_surfaces(&mut buf, width, height);
+        let version = buf.next_u8()? as f32 + buf.next_u8()? as f32 / 10f32;
+        let width = buf.next_u32()?;
+        let height = buf.next_u32()?;
+        let zoom = buf.next_f32()?;
+
+        let (texture_names, texture_indices) = Gnd::load_textures(&mut buf)?;
+        let lightmaps = Gnd::load_lightmaps(&mut buf)?;
+        let tiles = Gnd::load_tiles(
+            &mut buf,
+            texture_names.len(),
+            &texture_indices,
+            lightmaps.count,
+        )?;
+        let surfaces = Gnd::load_surfaces(&mut buf, width, height, tiles.len())?;
+        let gnd_water = if version >= 1.8 {
+            Some(Gnd::load_water(&mut buf, version)?)
+        } else {
+            None
+        };
         let normals = Gnd::smooth_normal(width as usize, height as usize, &surfaces);
 
         let l_count_w = (lightmaps.count as f32).sqrt().round() as usize;
@@ -89,8 +125,8 @@ impl Gnd {
         let l_width = (l_count_w * 8).next_power_of_two();
         let l_height = (l_count_h * 8).next_power_of_two();
 
-        let mut mesh = Vec::<MeshVertex>::with_capacity((width * height * 3 * 6) as usize);
-        let mut water = Vec::<WaterVertex>::with_capacity((width * height * 3 / 2 * 6) as usize);
+        let mut mesh = Vec::<MeshVertex>::with_capacity(surfaces.len() * 3 * 6);
+        let mut water = Vec::<WaterVertex>::with_capacity(surfaces.len() * 3 / 2 * 6);
 
         let mut v = v3(0.0, 0.0, 0.0);
         let rot = Rotation3::<f32>::new(Vector3::new(180f32.to_radians(), 0.0, 0.0));
@@ -109,6 +145,13 @@ impl Gnd {
             for x in 0..width {
                 let cell_a = &surfaces[(x + y * width) as usize];
                 let h_a = cell_a.height;
+                let (water_level, water_height) = match &gnd_water {
+                    Some(gnd_water) => {
+                        let zone = gnd_water.zone_at(x, y, width, height);
+                        (zone.level, zone.wave_height)
+                    }
+                    None => (water_level, water_height),
+                };
                 let x = x as f32;
                 let y = y as f32;
 
@@ -338,7 +381,7 @@ impl Gnd {
             &lightmaps,
         );
 
-        Gnd {
+        Ok(Gnd {
             version,
             width,
             height,
@@ -355,7 +398,51 @@ impl Gnd {
             shadowmap_image,
             lightmap_image,
             shadow_map: vec![],
+            water: gnd_water,
+        })
+    }
+
+    fn load_water(buf: &mut BinaryReader, version: f32) -> Result<GndWater, ParseError> {
+        fn load_water_data(buf: &mut BinaryReader) -> Result<WaterData, ParseError> {
+            Ok(WaterData {
+                level: buf.next_f32()?,
+                typ: buf.next_i32()?,
+                wave_height: buf.next_f32()? / 5.0,
+                wave_speed: buf.next_f32()?,
+                wave_pitch: buf.next_f32()?,
+                anim_speed: buf.next_i32()?,
+                images: [0; 32],
+            })
         }
+        let water = load_water_data(buf)?;
+        let split_width = buf.next_u32()?;
+        let split_height = buf.next_u32()?;
+        let zone_count = (split_width as usize)
+            .checked_mul(split_height as usize)
+            .unwrap_or(std::usize::MAX);
+        // before 1.9 the zones have only their own level
+        let zones = if version >= 1.9 {
+            buf.check_count(zone_count, 24)?;
+            (0..zone_count)
+                .map(|_i| load_water_data(buf))
+                .collect::<Result<_, _>>()?
+        } else {
+            buf.check_count(zone_count, 4)?;
+            (0..zone_count)
+                .map(|_i| {
+                    Ok(WaterData {
+                        level: buf.next_f32()?,
+                        ..water.clone()
+                    })
+                })
+                .collect::<Result<_, ParseError>>()?
+        };
+        Ok(GndWater {
+            water,
+            split_width,
+            split_height,
+            zones,
+        })
     }
 
     fn lightmap_atlas(
@@ -377,18 +464,34 @@ impl Gnd {
         )
     }
 
-    fn load_surfaces(buf: &mut BinaryReader, width: u32, height: u32) -> Vec<Surface> {
-        (0..width * height)
-            .map(|_i| Surface {
-                height: [
-                    buf.next_f32() / 5f32,
-                    buf.next_f32() / 5f32,
-                    buf.next_f32() / 5f32,
-                    buf.next_f32() / 5f32,
-                ],
-                tile_up: buf.next_i32() as isize,
-                tile_front: buf.next_i32() as isize,
-                tile_right: buf.next_i32() as isize,
+    fn load_surfaces(
+        buf: &mut BinaryReader,
+        width: u32,
+        height: u32,
+        tile_count: usize,
+    ) -> Result<Vec<Surface>, ParseError> {
+        let count = (width as usize)
+            .checked_mul(height as usize)
+            .unwrap_or(std::usize::MAX);
+        buf.check_count(count, 28)?;
+        (0..count)
+            .map(|_i| {
+                let surface = Surface {
+                    height: [
+                        buf.next_f32()? / 5f32,
+                        buf.next_f32()? / 5f32,
+                        buf.next_f32()? / 5f32,
+                        buf.next_f32()? / 5f32,
+                    ],
+                    tile_up: buf.next_i32()? as isize,
+                    tile_front: buf.next_i32()? as isize,
+                    tile_right: buf.next_i32()? as isize,
+                };
+                let tiles = [surface.tile_up, surface.tile_front, surface.tile_right];
+                if tiles.iter().any(|&tile| tile >= tile_count as isize) {
+                    return buf.invalid_data("a surface refers to a missing tile".to_owned());
+                }
+                Ok(surface)
             })
             .collect()
     }
@@ -397,8 +500,9 @@ impl Gnd {
         buf: &mut BinaryReader,
         texture_count: usize,
         texture_indices: &Vec<usize>,
-    ) -> Vec<Tile> {
-        let count = buf.next_u32();
+        lightmap_count: u32,
+    ) -> Result<Vec<Tile>, ParseError> {
+        let count = buf.next_count(40)?;
         // Texture atlas stuff
         let atlas_cols: f32 = (texture_count as f32).sqrt().round();
         let atlas_rows: f32 = (texture_count as f32).sqrt().ceil();
@@ -411,20 +515,29 @@ impl Gnd {
 
         (0..count)
             .map(|_i| {
-                let u1 = buf.next_f32();
-                let u2 = buf.next_f32();
-                let u3 = buf.next_f32();
-                let u4 = buf.next_f32();
-                let v1 = buf.next_f32();
-                let v2 = buf.next_f32();
-                let v3 = buf.next_f32();
-                let v4 = buf.next_f32();
-                let texture = texture_indices[buf.next_u16() as usize];
+                let u1 = buf.next_f32()?;
+                let u2 = buf.next_f32()?;
+                let u3 = buf.next_f32()?;
+                let u4 = buf.next_f32()?;
+                let v1 = buf.next_f32()?;
+                let v2 = buf.next_f32()?;
+                let v3 = buf.next_f32()?;
+                let v4 = buf.next_f32()?;
+                let texture = match texture_indices.get(buf.next_u16()? as usize) {
+                    Some(texture) => *texture,
+                    None => {
+                        return buf.invalid_data("a tile refers to a missing texture".to_owned())
+                    }
+                };
+                let light = buf.next_u16()?;
+                if light as u32 >= lightmap_count {
+                    return buf.invalid_data("a tile refers to a missing lightmap".to_owned());
+                }
 
                 let u = (texture % atlas_cols as usize) as f32;
                 let v = (texture as f32 / atlas_cols).floor();
 
-                Tile {
+                Ok(Tile {
                     u1: (u + u1 * (1f32 - atlas_px_u * 2f32) + atlas_px_u) * atlas_factor_u
                         / atlas_cols,
                     u2: (u + u2 * (1f32 - atlas_px_u * 2f32) + atlas_px_u) * atlas_factor_u
@@ -442,9 +555,14 @@ impl Gnd {
                     v4: (v + v4 * (1f32 - atlas_px_v * 2f32) + atlas_px_v) * atlas_factor_v
                         / atlas_rows,
                     texture,
-                    light: buf.next_u16(),
-                    color: [buf.next_u8(), buf.next_u8(), buf.next_u8(), buf.next_u8()],
-                }
+                    light,
+                    color: [
+                        buf.next_u8()?,
+                        buf.next_u8()?,
+                        buf.next_u8()?,
+                        buf.next_u8()?,
+                    ],
+                })
             })
             .collect()
     }
@@ -622,28 +740,40 @@ impl Gnd {
         return normals;
     }
 
-    fn load_lightmaps(buf: &mut BinaryReader) -> LightmapData {
-        let count = buf.next_u32();
-        let per_cell_x = buf.next_u32();
-        let per_cell_y = buf.next_u32();
-        let size_cell = buf.next_u32();
+    fn load_lightmaps(buf: &mut BinaryReader) -> Result<LightmapData, ParseError> {
+        let count = buf.next_u32()?;
+        let per_cell_x = buf.next_u32()?;
+        let per_cell_y = buf.next_u32()?;
+        let size_cell = buf.next_u32()?;
+        // the images are built from 8x8 lightmaps
+        if (per_cell_x, per_cell_y, size_cell) != (8, 8, 1) {
+            return buf.invalid_data(format!(
+                "unsupported lightmap format: {}x{}x{}",
+                per_cell_x, per_cell_y, size_cell
+            ));
+        }
         let per_cell = per_cell_x * per_cell_y * size_cell;
+        let len = buf.check_count(count as usize, per_cell as usize * 4)? * per_cell as usize * 4;
 
-        LightmapData {
+        Ok(LightmapData {
             per_cell,
             count,
-            data: buf.next(count * per_cell * 4).to_vec(),
-        }
+            data: buf.next(len as u32)?.to_vec(),
+        })
     }
 
-    fn load_textures(buf: &mut BinaryReader) -> (Vec<String>, Vec<usize>) {
-        let count = buf.next_u32();
-        let len = buf.next_u32();
+    fn load_textures(buf: &mut BinaryReader) -> Result<(Vec<String>, Vec<usize>), ParseError> {
+        let count = buf.next_u32()?;
+        let len = buf.next_u32()?;
+        if len == 0 {
+            return buf.invalid_data("the length of the texture names is zero".to_owned());
+        }
+        buf.check_count(count as usize, len as usize)?;
 
         let mut texture_names: Vec<String> = Vec::new();
         let mut texture_indices: Vec<usize> = Vec::new();
         for _ in 0..count {
-            let name = buf.string(len);
+            let name = buf.string(len)?;
             let texture_index = texture_names
                 .iter()
                 .position(|t| *t == name)
@@ -654,31 +784,6 @@ impl Gnd {
             texture_indices.push(texture_index);
         }
 
-        (texture_names, texture_indices)
-    }
-
-    pub fn create_texture_atlas(
-        texture_surfaces: Vec<sdl2::surface::Surface>,
-    ) -> sdl2::surface::Surface<'static> {
-        let _width = (texture_surfaces.len() as f32).sqrt().round() as i32;
-        let width = ((_width * 258) as u32).next_power_of_two();
-        let height =
-            ((texture_surfaces.len() as f32).sqrt().ceil() as u32 * 258).next_power_of_two();
-        let mut surface_atlas =
-            sdl2::surface::Surface::new(width, height, PixelFormatEnum::RGB888).unwrap();
-        for (i, texture_surface) in texture_surfaces.iter().enumerate() {
-            let x = (i as i32 % _width) * 258;
-            let y = ((i as i32 / _width) as f32).floor() as i32 * 258;
-            let optimized = texture_surface
-                .convert(&surface_atlas.pixel_format())
-                .unwrap();
-            optimized
-                .blit_scaled(None, &mut surface_atlas, Rect::new(x, y, 258, 258))
-                .unwrap();
-            optimized
-                .blit_scaled(None, &mut surface_atlas, Rect::new(x + 1, y + 1, 256, 256))
-                .unwrap();
-        }
-        surface_atlas
+        Ok((texture_names, texture_indices))
     }
 }
@@ -0,0 +1,299 @@
+use crate::grf::GrfEntry;
+use byteorder::LittleEndian;
+use byteorder::ReadBytesExt;
+use byteorder::WriteBytesExt;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const CACHE_MAGIC: &[u8; 4] = b"RGRC";
+/// Has to be increased whenever the layout of the cache file changes
+const CACHE_VERSION: u32 = 1;
+
+/// Identifies the content of a GRF archive without reading it.
+/// If any of them changes (e.g. an archive was replaced or patched), the cache is rebuilt.
+/// It is used for the other sources by the asset bundles too.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct GrfFingerprint {
+    pub source_index: usize,
+    pub path: String,
+    pub size: u64,
+    pub modified_secs: u64,
+    pub modified_nanos: u32,
+}
+
+impl GrfFingerprint {
+    pub(super) fn new(source_index: usize, path: &str) -> Result<GrfFingerprint, std::io::Error> {
+        let metadata = std::fs::metadata(path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        return Ok(GrfFingerprint {
+            source_index,
+            path: path.to_owned(),
+            size: metadata.len(),
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+        });
+    }
+
+    /// The total size and the last modification of the files in the directory
+    pub(super) fn of_dir(
+        source_index: usize,
+        path: &str,
+    ) -> Result<GrfFingerprint, std::io::Error> {
+        let mut fingerprint = GrfFingerprint {
+            source_index,
+            path: path.to_owned(),
+            size: 0,
+            modified_secs: 0,
+            modified_nanos: 0,
+        };
+        let mut dirs = vec![PathBuf::from(path)];
+        while let Some(dir) = dirs.pop() {
+            for dir_entry in std::fs::read_dir(dir)? {
+                let dir_entry = dir_entry?;
+                let metadata = dir_entry.metadata()?;
+                if metadata.is_dir() {
+                    dirs.push(dir_entry.path());
+                    continue;
+                }
+                let modified = metadata
+                    .modified()?
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                fingerprint.size += metadata.len();
+                if (modified.as_secs(), modified.subsec_nanos())
+                    > (fingerprint.modified_secs, fingerprint.modified_nanos)
+                {
+                    fingerprint.modified_secs = modified.as_secs();
+                    fingerprint.modified_nanos = modified.subsec_nanos();
+                }
+            }
+        }
+        return Ok(fingerprint);
+    }
+}
+
+/// $XDG_CACHE_HOME, ~/.cache or %LOCALAPPDATA%, so the cache does not depend on the working
+/// directory. The temp directory is used if none of them is available.
+pub fn default_cache_path() -> PathBuf {
+    let cache_dir = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir);
+    return cache_dir.join("rustarok").join("grf.cache");
+}
+
+/// The configured location (grf_cache_path in config.toml) or the default one
+pub fn cache_path(configured: &Option<String>) -> PathBuf {
+    return match configured {
+        Some(path) if !path.is_empty() => PathBuf::from(path),
+        _ => default_cache_path(),
+    };
+}
+
+/// None if the cache does not exist, it is corrupt or it was built from different archives
+pub(super) fn load(
+    cache_path: &Path,
+    fingerprints: &[GrfFingerprint],
+) -> Option<HashMap<String, (usize, GrfEntry)>> {
+    let file = File::open(cache_path).ok()?;
+    return match read_cache(&mut BufReader::new(file), fingerprints) {
+        Ok(Some(entries)) => Some(entries),
+        Ok(None) => {
+            log::info!("The GRF cache is outdated, it will be rebuilt");
+            None
+        }
+        Err(e) => {
+            log::warn!(
+                "The GRF cache ('{}') is corrupt, it will be rebuilt: {}",
+                cache_path.display(),
+                e
+            );
+            None
+        }
+    };
+}
+
+fn read_cache<R: Read>(
+    reader: &mut R,
+    fingerprints: &[GrfFingerprint],
+) -> Result<Option<HashMap<String, (usize, GrfEntry)>>, std::io::Error> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != CACHE_MAGIC || reader.read_u32::<LittleEndian>()? != CACHE_VERSION {
+        return Ok(None);
+    }
+    if !read_fingerprints(reader, fingerprints)? {
+        return Ok(None);
+    }
+
+    let count = reader.read_u32::<LittleEndian>()? as usize;
+    // the count is not trusted for the allocation, the file might be truncated
+    let mut entries = HashMap::with_capacity(count.min(1 << 20));
+    for _i in 0..count {
+        let name = read_string(reader)?;
+        let source_index = reader.read_u16::<LittleEndian>()? as usize;
+        if !fingerprints
+            .iter()
+            .any(|it| it.source_index == source_index)
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("'{}' belongs to an unknown archive", name),
+            ));
+        }
+        let entry = GrfEntry {
+            pack_size: reader.read_u32::<LittleEndian>()?,
+            length_aligned: reader.read_u32::<LittleEndian>()?,
+            real_size: reader.read_u32::<LittleEndian>()?,
+            typ: reader.read_u8()?,
+            offset: reader.read_u32::<LittleEndian>()?,
+        };
+        entries.insert(name, (source_index, entry));
+    }
+    return Ok(Some(entries));
+}
+
+/// The cache is written into a temporary file first, so a crash can't leave a half written one
+pub(super) fn save(
+    cache_path: &Path,
+    fingerprints: &[GrfFingerprint],
+    entries: &HashMap<String, (usize, GrfEntry)>,
+) -> Result<(), std::io::Error> {
+    if let Some(dir) = cache_path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let tmp_path = cache_path.with_extension("cache.tmp");
+    {
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        write_cache(&mut writer, fingerprints, entries)?;
+        writer.flush()?;
+    }
+    return std::fs::rename(&tmp_path, cache_path);
+}
+
+fn write_cache<W: Write>(
+    writer: &mut W,
+    fingerprints: &[GrfFingerprint],
+    entries: &HashMap<String, (usize, GrfEntry)>,
+) -> Result<(), std::io::Error> {
+    writer.write_all(CACHE_MAGIC)?;
+    writer.write_u32::<LittleEndian>(CACHE_VERSION)?;
+    write_fingerprints(writer, fingerprints)?;
+    writer.write_u32::<LittleEndian>(entries.len() as u32)?;
+    for (name, (source_index, entry)) in entries {
+        write_string(writer, name)?;
+        writer.write_u16::<LittleEndian>(*source_index as u16)?;
+        writer.write_u32::<LittleEndian>(entry.pack_size)?;
+        writer.write_u32::<LittleEndian>(entry.length_aligned)?;
+        writer.write_u32::<LittleEndian>(entry.real_size)?;
+        writer.write_u8(entry.typ)?;
+        writer.write_u32::<LittleEndian>(entry.offset)?;
+    }
+    return Ok(());
+}
+
+/// False if the written fingerprints differ from the `expected` ones
+pub(super) fn read_fingerprints<R: Read>(
+    reader: &mut R,
+    expected: &[GrfFingerprint],
+) -> Result<bool, std::io::Error> {
+    let fingerprint_count = reader.read_u32::<LittleEndian>()? as usize;
+    if fingerprint_count != expected.len() {
+        return Ok(false);
+    }
+    for expected in expected {
+        let fingerprint = GrfFingerprint {
+            source_index: reader.read_u16::<LittleEndian>()? as usize,
+            path: read_string(reader)?,
+            size: reader.read_u64::<LittleEndian>()?,
+            modified_secs: reader.read_u64::<LittleEndian>()?,
+            modified_nanos: reader.read_u32::<LittleEndian>()?,
+        };
+        if fingerprint != *expected {
+            return Ok(false);
+        }
+    }
+    return Ok(true);
+}
+
+pub(super) fn write_fingerprints<W: Write>(
+    writer: &mut W,
+    fingerprints: &[GrfFingerprint],
+) -> Result<(), std::io::Error> {
+    writer.write_u32::<LittleEndian>(fingerprints.len() as u32)?;
+    for fingerprint in fingerprints {
+        writer.write_u16::<LittleEndian>(fingerprint.source_index as u16)?;
+        write_string(writer, &fingerprint.path)?;
+        writer.write_u64::<LittleEndian>(fingerprint.size)?;
+        writer.write_u64::<LittleEndian>(fingerprint.modified_secs)?;
+        writer.write_u32::<LittleEndian>(fingerprint.modified_nanos)?;
+    }
+    return Ok(());
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String, std::io::Error> {
+    let len = reader.read_u16::<LittleEndian>()? as usize;
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes)?;
+    return String::from_utf8(bytes)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+}
+
+fn write_string<W: Write>(writer: &mut W, value: &str) -> Result<(), std::io::Error> {
+    writer.write_u16::<LittleEndian>(value.len() as u16)?;
+    return writer.write_all(value.as_bytes());
+}
+
+/// The fingerprint of a source whose content is not relevant, only its size changes
+#[cfg(test)]
+pub(crate) fn fingerprint(size: u64) -> GrfFingerprint {
+    GrfFingerprint {
+        source_index: 1,
+        path: "data.grf".to_owned(),
+        size,
+        modified_secs: 1_565_000_000,
+        modified_nanos: 42,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_or_corrupt_cache_is_rejected() {
+        let mut entries = HashMap::new();
+        entries.insert(
+            "data\\prontera.gat".to_owned(),
+            (
+                1,
+                GrfEntry {
+                    pack_size: 1,
+                    length_aligned: 8,
+                    real_size: 3,
+                    typ: 1,
+                    offset: 100,
+                },
+            ),
+        );
+        let mut buf = Vec::new();
+        write_cache(&mut buf, &[fingerprint(1000)], &entries).unwrap();
+
+        let loaded = read_cache(&mut buf.as_slice(), &[fingerprint(1000)])
+            .unwrap()
+            .unwrap();
+        assert_eq!(loaded["data\\prontera.gat"].1.offset, 100);
+        // the archive was replaced
+        assert!(read_cache(&mut buf.as_slice(), &[fingerprint(1001)])
+            .unwrap()
+            .is_none());
+        assert!(read_cache(&mut &buf[..buf.len() - 2], &[fingerprint(1000)]).is_err());
+    }
+}
@@ -1,6 +1,18 @@
+pub mod act;
 pub mod asset_loader;
+mod asset_source;
 pub mod binary_reader;
+pub mod bundle;
+mod content_cache;
+mod des;
 pub mod gat;
+pub mod gnd;
+pub mod grf_cache;
+pub mod rsm;
+pub mod rsw;
+pub mod spr;
+pub mod str;
+pub mod writer;
 
 #[derive(Debug, Clone)]
 pub struct GrfEntry {
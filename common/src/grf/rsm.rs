@@ -0,0 +1,976 @@
+use crate::common::{v3, Mat3, Mat4, Vec3};
+use crate::grf::binary_reader::{BinaryReader, ParseError, ParseErrorKind};
+use nalgebra::{Point3, Quaternion, Rotation3, Unit, UnitQuaternion, Vector4};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug)]
+pub struct Rsm {
+    pub anim_len: i32,
+    pub shade_type: i32,
+    pub alpha: u8,
+    pub version: f32,
+    /// 0 before 2.2
+    pub fps: f32,
+    /// Since 2.3 the nodes name their own textures, they are collected here, so
+    /// `RsmNode::textures` are indices into this list in every version
+    pub texture_names: Vec<String>,
+    pub nodes: Vec<RsmNode>,
+    pub main_node_index: usize,
+    /// Models before 2.3 have only one root node, the main node
+    pub root_node_indices: Vec<usize>,
+    pub pos_key_frames: Vec<PosKeyFrame>,
+    pub volume_boxes: Vec<VolumeBox>,
+    pub bounding_box: BoundingBox,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RsmNodeVertex {
+    pub pos: [f32; 3],
+    pub normal: [f32; 3],
+    pub texcoord: [f32; 2],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min: Vec3,
+    pub max: Vec3,
+    pub range: Vec3,
+    pub center: Vec3,
+}
+
+impl BoundingBox {
+    pub fn new() -> BoundingBox {
+        BoundingBox {
+            min: v3(std::f32::INFINITY, std::f32::INFINITY, std::f32::INFINITY),
+            max: v3(
+                std::f32::NEG_INFINITY,
+                std::f32::NEG_INFINITY,
+                std::f32::NEG_INFINITY,
+            ),
+            range: v3(0.0, 0.0, 0.0),
+            center: v3(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct RsmNode {
+    pub name: String,
+    pub parent_name: String,
+    pub textures: Vec<u32>,
+    pub mat3: Mat3,
+    pub matrix: Mat4,
+    pub offset: Vec3,
+    pub pos: Vec3,
+    pub rotangle: f32,
+    pub rotaxis: Vec3,
+    pub scale: Vec3,
+    pub vertices: Vec<Vec3>,
+    pub bounding_box: BoundingBox,
+    pub mesh: Vec<RsmNodeVertex>,
+    pub texture_vertices: Vec<f32>,
+    pub faces: Vec<NodeFace>,
+    pub pos_key_frames: Vec<PosKeyFrame>,
+    pub rot_key_frames: Vec<RotKeyFrame>,
+    pub scale_key_frames: Vec<ScaleKeyFrame>,
+    pub texture_animations: Vec<TextureAnimation>,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct NodeFace {
+    pub vertex_index: [u16; 3],
+    pub texture_vertex_index: [u16; 3],
+    pub texture_id: u16,
+    pub padding: u16,
+    pub two_side: i32,
+    pub smooth_group: i32,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct PosKeyFrame {
+    pub frame: i32,
+    pub px: f32,
+    pub py: f32,
+    pub pz: f32,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct VolumeBox {
+    pub size: [f32; 3],
+    pub pos: [f32; 3],
+    pub rot: [f32; 3],
+    pub flag: i32,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct RotKeyFrame {
+    pub frame: i32,
+    pub q: [f32; 4],
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct ScaleKeyFrame {
+    pub frame: i32,
+    pub scale: [f32; 3],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureAnimationType {
+    OffsetU,
+    OffsetV,
+    ScaleU,
+    ScaleV,
+    Rotation,
+}
+
+/// Animates the texture coordinates of the faces with `texture_id`
+#[derive(Clone, Debug)]
+pub struct TextureAnimation {
+    pub texture_id: u16,
+    pub typ: TextureAnimationType,
+    pub key_frames: Vec<TextureKeyFrame>,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct TextureKeyFrame {
+    pub frame: i32,
+    pub value: f32,
+}
+
+/// The texture animations of a texture at their first key frames, the meshes are static
+struct UvTransform {
+    offset: [f32; 2],
+    scale: [f32; 2],
+    rotation: f32,
+}
+
+impl UvTransform {
+    fn first_frame(node: &RsmNode, texture_id: u16) -> UvTransform {
+        let mut uv = UvTransform {
+            offset: [0.0, 0.0],
+            scale: [1.0, 1.0],
+            rotation: 0.0,
+        };
+        for anim in node
+            .texture_animations
+            .iter()
+            .filter(|it| it.texture_id == texture_id)
+        {
+            let value = match anim.key_frames.first() {
+                Some(key_frame) => key_frame.value,
+                None => continue,
+            };
+            match anim.typ {
+                TextureAnimationType::OffsetU => uv.offset[0] = value,
+                TextureAnimationType::OffsetV => uv.offset[1] = value,
+                TextureAnimationType::ScaleU => uv.scale[0] = value,
+                TextureAnimationType::ScaleV => uv.scale[1] = value,
+                TextureAnimationType::Rotation => uv.rotation = value,
+            }
+        }
+        return uv;
+    }
+
+    fn apply(&self, u: f32, v: f32) -> [f32; 2] {
+        let u = u * self.scale[0] + self.offset[0];
+        let v = v * self.scale[1] + self.offset[1];
+        if self.rotation == 0.0 {
+            return [u, v];
+        }
+        // around the center of the texture
+        let (sin, cos) = self.rotation.sin_cos();
+        let (u, v) = (u - 0.5, v - 0.5);
+        return [u * cos - v * sin + 0.5, u * sin + v * cos + 0.5];
+    }
+}
+
+impl RsmNode {
+    fn load(
+        buf: &mut BinaryReader,
+        rsm_version: f32,
+        texture_names: &mut Vec<String>,
+    ) -> Result<Self, ParseError> {
+        let is_rsm2 = rsm_version >= 2.2;
+        let (name, parent_name) = if is_rsm2 {
+            (buf.string_with_len()?, buf.string_with_len()?)
+        } else {
+            (buf.string(40)?, buf.string(40)?)
+        };
+
+        let texture_count = buf.next_count(4)?;
+        let textures: Vec<u32> = if rsm_version >= 2.3 {
+            (0..texture_count)
+                .map(|_i| {
+                    let texture_name = buf.string_with_len()?;
+                    let index = match texture_names.iter().position(|it| *it == texture_name) {
+                        Some(index) => index,
+                        None => {
+                            texture_names.push(texture_name);
+                            texture_names.len() - 1
+                        }
+                    };
+                    Ok(index as u32)
+                })
+                .collect::<Result<_, ParseError>>()?
+        } else {
+            (0..texture_count)
+                .map(|_i| buf.next_u32())
+                .collect::<Result<_, _>>()?
+        };
+
+        let mut mat3_values = [0.0; 9];
+        for value in mat3_values.iter_mut() {
+            *value = buf.next_f32()?;
+        }
+        let mat3 = Mat3::from_row_slice(&mat3_values).transpose();
+        let offset = v3(buf.next_f32()?, buf.next_f32()?, buf.next_f32()?);
+        // RSM2 nodes are transformed only by their offset and their key frames
+        let (pos, rotangle, rotaxis, scale) = if is_rsm2 {
+            (v3(0.0, 0.0, 0.0), 0.0, v3(0.0, 0.0, 0.0), v3(1.0, 1.0, 1.0))
+        } else {
+            (
+                v3(buf.next_f32()?, buf.next_f32()?, buf.next_f32()?),
+                buf.next_f32()?,
+                v3(buf.next_f32()?, buf.next_f32()?, buf.next_f32()?),
+                v3(buf.next_f32()?, buf.next_f32()?, buf.next_f32()?),
+            )
+        };
+
+        let vertex_count = buf.next_count(12)?;
+        let vertices: Vec<Vec3> = (0..vertex_count)
+            .map(|_i| Ok(v3(buf.next_f32()?, buf.next_f32()?, buf.next_f32()?)))
+            .collect::<Result<_, ParseError>>()?;
+
+        let texture_vertices: Vec<f32> = {
+            let texture_vertex_count = buf.next_count(8)?;
+            let mut texture_vertices: Vec<f32> = vec![0.0f32; texture_vertex_count * 6];
+            for i in (0..texture_vertices.len()).step_by(6) {
+                if rsm_version >= 1.2 {
+                    texture_vertices[i + 0] = buf.next_u8()? as f32 / 255.0;
+                    texture_vertices[i + 1] = buf.next_u8()? as f32 / 255.0;
+                    texture_vertices[i + 2] = buf.next_u8()? as f32 / 255.0;
+                    texture_vertices[i + 3] = buf.next_u8()? as f32 / 255.0;
+                }
+                texture_vertices[i + 4] = buf.next_f32()? * 0.98 + 0.01;
+                texture_vertices[i + 5] = buf.next_f32()? * 0.98 + 0.01;
+            }
+            texture_vertices
+        };
+
+        let face_count = buf.next_count(20)?;
+        let mut faces: Vec<NodeFace> = Vec::with_capacity(face_count);
+        for _i in 0..face_count {
+            // RSM2 faces start with their length, they can have more smoothing groups
+            let face_len = if is_rsm2 { buf.next_i32()? } else { 0 };
+            let face = NodeFace {
+                vertex_index: [buf.next_u16()?, buf.next_u16()?, buf.next_u16()?],
+                texture_vertex_index: [buf.next_u16()?, buf.next_u16()?, buf.next_u16()?],
+                texture_id: buf.next_u16()?,
+                padding: buf.next_u16()?,
+                two_side: buf.next_i32()?,
+                smooth_group: if rsm_version >= 1.2 {
+                    buf.next_i32()?
+                } else {
+                    0
+                },
+            };
+            let valid = face
+                .vertex_index
+                .iter()
+                .all(|&i| (i as usize) < vertices.len())
+                && face
+                    .texture_vertex_index
+                    .iter()
+                    .all(|&i| (i as usize) * 6 < texture_vertices.len())
+                && (face.texture_id as usize) < textures.len()
+                && face.smooth_group >= 0
+                && face.smooth_group < 32;
+            if !valid {
+                return buf.invalid_data(format!("node '{}' has an invalid face", name));
+            }
+            // only the first smoothing group is used
+            if face_len > 24 {
+                buf.skip(face_len as u32 - 24)?;
+            }
+            faces.push(face);
+        }
+
+        let scale_key_frames = if is_rsm2 {
+            RsmNode::load_scale_key_frames(buf)?
+        } else {
+            Vec::new()
+        };
+        let pos_key_frames = if rsm_version >= 1.5 && !is_rsm2 {
+            RsmNode::load_pos_key_frames(buf)?
+        } else {
+            Vec::new()
+        };
+
+        let rot_key_frame_count = buf.next_count(20)?;
+        let rot_key_frames: Vec<RotKeyFrame> = (0..rot_key_frame_count)
+            .map(|_i| {
+                Ok(RotKeyFrame {
+                    frame: buf.next_i32()?,
+                    q: [
+                        buf.next_f32()?,
+                        buf.next_f32()?,
+                        buf.next_f32()?,
+                        buf.next_f32()?,
+                    ],
+                })
+            })
+            .collect::<Result<_, ParseError>>()?;
+
+        // in RSM2 the position key frames follow the rotation ones
+        let pos_key_frames = if is_rsm2 {
+            RsmNode::load_rsm2_pos_key_frames(buf)?
+        } else {
+            pos_key_frames
+        };
+        let texture_animations = if rsm_version >= 2.3 {
+            RsmNode::load_texture_animations(buf, textures.len())?
+        } else {
+            Vec::new()
+        };
+
+        Ok(RsmNode {
+            name,
+            parent_name,
+            textures,
+            mat3,
+            offset,
+            pos,
+            rotangle,
+            rotaxis,
+            scale,
+            vertices,
+            texture_vertices,
+            faces,
+            pos_key_frames,
+            rot_key_frames,
+            scale_key_frames,
+            texture_animations,
+            matrix: Mat4::identity(),
+            mesh: Vec::new(), // dummy
+            bounding_box: BoundingBox::new(),
+        })
+    }
+
+    fn load_pos_key_frames(buf: &mut BinaryReader) -> Result<Vec<PosKeyFrame>, ParseError> {
+        let count = buf.next_count(16)?;
+        (0..count)
+            .map(|_i| {
+                Ok(PosKeyFrame {
+                    frame: buf.next_i32()?,
+                    px: buf.next_f32()?,
+                    py: buf.next_f32()?,
+                    pz: buf.next_f32()?,
+                })
+            })
+            .collect()
+    }
+
+    fn load_rsm2_pos_key_frames(buf: &mut BinaryReader) -> Result<Vec<PosKeyFrame>, ParseError> {
+        let count = buf.next_count(20)?;
+        (0..count)
+            .map(|_i| {
+                let key_frame = PosKeyFrame {
+                    frame: buf.next_i32()?,
+                    px: buf.next_f32()?,
+                    py: buf.next_f32()?,
+                    pz: buf.next_f32()?,
+                };
+                buf.skip(4)?; // unknown
+                Ok(key_frame)
+            })
+            .collect()
+    }
+
+    fn load_scale_key_frames(buf: &mut BinaryReader) -> Result<Vec<ScaleKeyFrame>, ParseError> {
+        let count = buf.next_count(20)?;
+        (0..count)
+            .map(|_i| {
+                let key_frame = ScaleKeyFrame {
+                    frame: buf.next_i32()?,
+                    scale: [buf.next_f32()?, buf.next_f32()?, buf.next_f32()?],
+                };
+                buf.skip(4)?; // unknown
+                Ok(key_frame)
+            })
+            .collect()
+    }
+
+    fn load_texture_animations(
+        buf: &mut BinaryReader,
+        texture_count: usize,
+    ) -> Result<Vec<TextureAnimation>, ParseError> {
+        let mut texture_animations = Vec::new();
+        for _i in 0..buf.next_count(8)? {
+            let texture_id = buf.next_u32()? as usize;
+            if texture_id >= texture_count {
+                return buf.invalid_data(format!("invalid animated texture: {}", texture_id));
+            }
+            for _i in 0..buf.next_count(8)? {
+                let typ = match buf.next_i32()? {
+                    0 => TextureAnimationType::OffsetU,
+                    1 => TextureAnimationType::OffsetV,
+                    2 => TextureAnimationType::ScaleU,
+                    3 => TextureAnimationType::ScaleV,
+                    4 => TextureAnimationType::Rotation,
+                    typ => return buf.invalid_data(format!("invalid texture animation: {}", typ)),
+                };
+                let key_frame_count = buf.next_count(8)?;
+                let key_frames = (0..key_frame_count)
+                    .map(|_i| {
+                        Ok(TextureKeyFrame {
+                            frame: buf.next_i32()?,
+                            value: buf.next_f32()?,
+                        })
+                    })
+                    .collect::<Result<_, ParseError>>()?;
+                texture_animations.push(TextureAnimation {
+                    texture_id: texture_id as u16,
+                    typ,
+                    key_frames,
+                });
+            }
+        }
+        return Ok(texture_animations);
+    }
+}
+
+impl Rsm {
+    pub fn load(mut buf: BinaryReader) -> Result<Self, ParseError> {
+        let header = buf.string(4)?;
+        if header != "GRSM" {
+            return Err(buf.error(ParseErrorKind::InvalidHeader(header)));
+        }
+
+        let version = buf.next_u8()? as f32 + buf.next_u8()? as f32 / 10f32;
+        let anim_len = buf.next_i32()?;
+        let shade_type = buf.next_i32()?;
+        let alpha: u8 = if version >= 1.4 { buf.next_u8()? } else { 255 };
+        let is_rsm2 = version >= 2.2;
+        let fps = if is_rsm2 { buf.next_f32()? } else { 0.0 };
+
+        let mut texture_names: Vec<String> = Vec::new();
+        let root_node_names: Vec<String> = if version >= 2.3 {
+            let root_node_count = buf.next_count(4)?;
+            (0..root_node_count)
+                .map(|_i| buf.string_with_len())
+                .collect::<Result<_, _>>()?
+        } else if is_rsm2 {
+            let texture_count = buf.next_count(4)?;
+            for _i in 0..texture_count {
+                texture_names.push(buf.string_with_len()?);
+            }
+            vec![buf.string_with_len()?]
+        } else {
+            buf.skip(16)?; // reserved
+            let texture_count = buf.next_count(40)?;
+            for _i in 0..texture_count {
+                texture_names.push(buf.string(40)?);
+            }
+            vec![buf.string(40)?]
+        };
+
+        let (mut nodes, root_node_indices) = {
+            let node_count = buf.next_u32()? as usize;
+            let mut nodes = Vec::<RsmNode>::new();
+            for _i in 0..node_count {
+                let node = RsmNode::load(&mut buf, version, &mut texture_names)?;
+                if node
+                    .textures
+                    .iter()
+                    .any(|&it| it as usize >= texture_names.len())
+                {
+                    return buf
+                        .invalid_data(format!("node '{}' has an invalid texture", node.name));
+                }
+                nodes.push(node);
+            }
+            if nodes.is_empty() {
+                return buf.invalid_data("the model has no nodes".to_owned());
+            }
+            let mut root_node_indices: Vec<usize> = root_node_names
+                .iter()
+                .filter_map(|root_name| nodes.iter().position(|node| node.name == *root_name))
+                .collect();
+            // In some custom models, the default name don't match nodes name.
+            // So by default, assume the main node is the first one.
+            if root_node_indices.is_empty() {
+                root_node_indices.push(0);
+            }
+            (nodes, root_node_indices)
+        };
+        let main_node_index = root_node_indices[0];
+
+        let pos_key_frames = if version < 1.5 {
+            RsmNode::load_pos_key_frames(&mut buf)?
+        } else {
+            Vec::new()
+        };
+
+        // they are often missing from RSM2 files
+        let volume_box_count = if is_rsm2 && buf.remaining() < 4 {
+            0
+        } else {
+            buf.next_count(40)?
+        };
+        let volume_boxes: Vec<VolumeBox> = (0..volume_box_count)
+            .map(|_i| {
+                Ok(VolumeBox {
+                    size: [buf.next_f32()?, buf.next_f32()?, buf.next_f32()?],
+                    pos: [buf.next_f32()?, buf.next_f32()?, buf.next_f32()?],
+                    rot: [buf.next_f32()?, buf.next_f32()?, buf.next_f32()?],
+                    flag: buf.next_i32()?,
+                })
+            })
+            .collect::<Result<_, ParseError>>()?;
+
+        let is_only = nodes.len() == 1;
+        let mut visited = vec![false; nodes.len()];
+        for &root_node_index in &root_node_indices {
+            Rsm::calc_matrix_and_bounding_box_recursively(
+                root_node_index,
+                None,
+                &mut nodes,
+                &mut visited,
+                is_only,
+                is_rsm2,
+            );
+        }
+
+        let mut bbox = BoundingBox::new();
+        for i in 0..3 {
+            for node in &nodes {
+                bbox.min[i] = node.bounding_box.min[i].min(bbox.min[i]);
+                bbox.max[i] = node.bounding_box.max[i].max(bbox.max[i]);
+            }
+            bbox.range[i] = (bbox.max[i] - bbox.min[i]) / 2.0;
+            bbox.center[i] = bbox.min[i] + bbox.range[i];
+        }
+
+        Ok(Rsm {
+            anim_len,
+            shade_type,
+            alpha,
+            version,
+            fps,
+            texture_names,
+            nodes,
+            main_node_index,
+            root_node_indices,
+            pos_key_frames,
+            volume_boxes,
+            bounding_box: bbox,
+        })
+    }
+
+    pub fn is_rsm2(&self) -> bool {
+        self.version >= 2.2
+    }
+
+    /// The meshes of the nodes, grouped by their textures.
+    /// The first item of the pairs is the index of the texture in `texture_names`.
+    pub fn generate_meshes_by_texture_index(
+        &self,
+    ) -> (Vec<Vec<(usize, Vec<RsmNodeVertex>)>>, BoundingBox) {
+        let is_only = self.nodes.len() == 1;
+        let mut real_bounding_box = BoundingBox::new();
+        let mut full_model_rendering_data: Vec<Vec<(usize, Vec<RsmNodeVertex>)>> = Vec::new();
+        for node in &self.nodes {
+            let faces_by_texture_id = {
+                let mut faces_by_texture_id: HashMap<u16, Vec<&NodeFace>> = HashMap::new();
+                for face in &node.faces {
+                    faces_by_texture_id
+                        .entry(face.texture_id)
+                        .or_insert(Vec::new())
+                        .push(&face);
+                }
+                faces_by_texture_id
+            };
+            let vertices_per_texture_per_node: Vec<(usize, Vec<RsmNodeVertex>)> =
+                faces_by_texture_id
+                    .iter()
+                    .map(|(&texture_index, faces)| {
+                        // all the faces of the node whose has the same texture index (which is texture_index)
+                        let mesh =
+                            self.generate_trimesh(node, texture_index, faces.as_slice(), is_only);
+                        for v in mesh.iter() {
+                            for i in 0..3 {
+                                real_bounding_box.min[i] = v.pos[i].min(real_bounding_box.min[i]);
+                                real_bounding_box.max[i] = v.pos[i].max(real_bounding_box.max[i]);
+                            }
+                        }
+                        (node.textures[texture_index as usize] as usize, mesh)
+                    })
+                    .collect();
+            full_model_rendering_data.push(vertices_per_texture_per_node);
+        }
+        for i in 0..3 {
+            real_bounding_box.range[i] =
+                (real_bounding_box.max[i] - real_bounding_box.min[i]) / 2.0;
+            real_bounding_box.center[i] = real_bounding_box.min[i] + real_bounding_box.range[i];
+        }
+        return (full_model_rendering_data, real_bounding_box);
+    }
+
+    /// `visited` protects against the cycles in the node hierarchy of corrupt files
+    fn calc_matrix_and_bounding_box_recursively(
+        parent_node_index: usize,
+        parent_of_parent_index: Option<usize>,
+        nodes: &mut Vec<RsmNode>,
+        visited: &mut Vec<bool>,
+        is_only: bool,
+        is_rsm2: bool,
+    ) {
+        if visited[parent_node_index] {
+            return;
+        }
+        visited[parent_node_index] = true;
+        let parent_node_name_of_parent = nodes[parent_node_index].parent_name.clone();
+        {
+            let matrix = {
+                let node = &nodes[parent_node_index];
+                let parent = parent_of_parent_index.map(|i| &nodes[i]);
+                if is_rsm2 {
+                    Rsm::calc_rsm2_matrix(node, parent)
+                } else {
+                    Rsm::calc_matrix(
+                        node,
+                        &parent.map(|it| it.matrix).unwrap_or_else(Mat4::identity),
+                    )
+                }
+            };
+            let mut parent_node = &mut nodes[parent_node_index];
+            parent_node.matrix = matrix;
+            parent_node.bounding_box = Rsm::calc_bounding_box(parent_node, is_only, is_rsm2);
+        }
+
+        let parent_node_name = nodes[parent_node_index].name.clone();
+        let children_indices = nodes
+            .iter_mut()
+            .enumerate()
+            .filter(|(_i, n)| {
+                parent_node_name == n.parent_name && parent_node_name != parent_node_name_of_parent
+            })
+            .map(|(i, _n)| i)
+            .collect::<Vec<usize>>();
+        for i in children_indices {
+            Rsm::calc_matrix_and_bounding_box_recursively(
+                i,
+                Some(parent_node_index),
+                nodes,
+                visited,
+                is_only,
+                is_rsm2,
+            );
+        }
+    }
+
+    fn calc_matrix(node: &RsmNode, parent_matrix: &Mat4) -> Mat4 {
+        let mut node_matrix = parent_matrix.clone();
+
+        node_matrix.prepend_translation_mut(&node.pos);
+
+        // Dynamic or static model
+        if node.rot_key_frames.is_empty() {
+            let rotation =
+                Rotation3::from_axis_angle(&Unit::new_normalize(node.rotaxis), node.rotangle)
+                    .to_homogeneous();
+            node_matrix = node_matrix * rotation;
+        } else {
+            let quat = Quaternion::from(Vector4::from(node.rot_key_frames[0].q));
+            let rotation = UnitQuaternion::from_quaternion(quat);
+            node_matrix = node_matrix * rotation.to_homogeneous();
+        }
+        node_matrix.prepend_nonuniform_scaling_mut(&node.scale);
+        node_matrix
+    }
+
+    /// The offset and the rotation of the parent are undone, because RSM2 offsets are in
+    /// model space.
+    fn calc_rsm2_matrix(node: &RsmNode, parent: Option<&RsmNode>) -> Mat4 {
+        let parent_inverse_mat3 = parent
+            .map(|parent| parent.mat3.try_inverse().unwrap_or_else(Mat3::identity))
+            .unwrap_or_else(Mat3::identity);
+        let mut local_matrix = Mat4::identity();
+        if let Some(key_frame) = node.scale_key_frames.first() {
+            local_matrix.prepend_nonuniform_scaling_mut(&Vec3::from(key_frame.scale));
+        }
+        if let Some(key_frame) = node.rot_key_frames.first() {
+            let quat = Quaternion::from(Vector4::from(key_frame.q));
+            local_matrix = local_matrix * UnitQuaternion::from_quaternion(quat).to_homogeneous();
+        } else {
+            local_matrix = local_matrix * (node.mat3 * parent_inverse_mat3).to_homogeneous();
+        }
+
+        let translation = match (node.pos_key_frames.first(), parent) {
+            (Some(key_frame), _) => v3(key_frame.px, key_frame.py, key_frame.pz),
+            (None, Some(parent)) => parent_inverse_mat3 * (node.offset - parent.offset),
+            (None, None) => node.offset,
+        };
+        for i in 0..3 {
+            local_matrix[(i, 3)] = translation[i];
+        }
+        return match parent {
+            Some(parent) => parent.matrix * local_matrix,
+            None => local_matrix,
+        };
+    }
+
+    fn calc_bounding_box(node: &RsmNode, is_only: bool, is_rsm2: bool) -> BoundingBox {
+        let mut node_local_matrix = node.matrix.clone();
+
+        if !is_rsm2 {
+            if !is_only {
+                node_local_matrix.prepend_translation_mut(&-node.offset);
+            }
+            node_local_matrix = node_local_matrix * node.mat3.to_homogeneous();
+        }
+
+        let mut bbox = BoundingBox::new();
+
+        for vert in node.vertices.iter() {
+            let v = node_local_matrix.transform_point(&Point3::new(vert.x, vert.y, vert.z));
+            for i in 0..3 {
+                bbox.min[i] = v[i].min(bbox.min[i]);
+                bbox.max[i] = v[i].max(bbox.max[i]);
+            }
+        }
+        for i in 0..3 {
+            bbox.range[i] = (bbox.max[i] - bbox.min[i]) / 2.0;
+            bbox.center[i] = bbox.min[i] + bbox.range[i];
+        }
+        return bbox;
+    }
+
+    fn generate_trimesh(
+        &self,
+        node: &RsmNode,
+        texture_id: u16,
+        faces: &[&NodeFace],
+        is_only: bool,
+    ) -> Vec<RsmNodeVertex> {
+        let verts = &node.vertices;
+        let tverts = &node.texture_vertices;
+        let uv = UvTransform::first_frame(node, texture_id);
+
+        let matrix = if self.is_rsm2() {
+            // RSM2 models are placed by their origin, they are not centered on their bounding box
+            node.matrix
+        } else {
+            let model_bbox = &self.bounding_box;
+            let mut matrix = Mat4::identity();
+            matrix.prepend_translation_mut(&v3(
+                -model_bbox.center[0],
+                -model_bbox.max[1],
+                -model_bbox.center[2],
+            ));
+            matrix = matrix * node.matrix;
+            if !is_only {
+                matrix.prepend_translation_mut(&node.offset);
+            }
+            matrix * node.mat3.to_homogeneous()
+        };
+
+        let mesh = match self.shade_type {
+            1/*FLAT*/ => {
+                let (normals, _group_used) = Rsm::calc_flat_normals(node);
+                Rsm::generate_mesh_flat(&matrix, faces, &verts, &tverts, &uv, normals)
+            }
+            2/*SMOOTH*/ => {
+                let (normals, group_used) = Rsm::calc_flat_normals(node);
+                let normal_groups = Rsm::calc_smooth_normals(node, normals, group_used);
+                Rsm::generate_mesh_smooth(&matrix, faces, &verts, &tverts, &uv, normal_groups)
+            }
+            _/*NONE*/ => {
+                let normals = node.faces.iter().map(|_face| {
+                    v3(-1.0f32, -1.0f32, -1.0f32)
+                }).collect();
+                Rsm::generate_mesh_flat(&matrix, faces, &verts, &tverts, &uv, normals)
+            }
+        };
+        return mesh;
+    }
+
+    fn generate_mesh_flat(
+        matrix: &Mat4,
+        faces: &[&NodeFace],
+        verts: &Vec<Vec3>,
+        tverts: &Vec<f32>,
+        uv: &UvTransform,
+        normals: Vec<Vec3>,
+    ) -> Vec<RsmNodeVertex> {
+        let mut mesh: Vec<RsmNodeVertex> = Vec::with_capacity(faces.len() * 3);
+        for (face, normal) in faces.iter().zip(normals) {
+            for i in 0..3 {
+                let v = &verts[face.vertex_index[i] as usize];
+                let v = matrix.transform_point(&Point3::new(v.x, v.y, v.z));
+                let tid = face.texture_vertex_index[i] as usize * 6;
+                mesh.push(RsmNodeVertex {
+                    pos: [v[0], v[1], v[2]],
+                    normal: [normal[0], normal[1], normal[2]],
+                    texcoord: uv.apply(tverts[tid + 4], tverts[tid + 5]),
+                });
+            }
+        }
+        return mesh;
+    }
+
+    fn generate_mesh_smooth(
+        matrix: &Mat4,
+        faces: &[&NodeFace],
+        verts: &Vec<Vec3>,
+        tverts: &Vec<f32>,
+        uv: &UvTransform,
+        normal_groups: [Vec<Vec3>; 32],
+    ) -> Vec<RsmNodeVertex> {
+        let mut mesh: Vec<RsmNodeVertex> = Vec::with_capacity(faces.len() * 3);
+        for face in faces {
+            let normals = &normal_groups[face.smooth_group as usize];
+            for i in 0..3 {
+                let v = &verts[face.vertex_index[i] as usize];
+                let v = matrix.transform_point(&Point3::new(v.x, v.y, v.z));
+                let normal = &normals[face.vertex_index[i] as usize];
+                let tid = face.texture_vertex_index[i] as usize * 6;
+                mesh.push(RsmNodeVertex {
+                    pos: [v[0], v[1], v[2]],
+                    normal: [normal[0], normal[1], normal[2]],
+                    texcoord: uv.apply(tverts[tid + 4], tverts[tid + 5]),
+                });
+            }
+        }
+        return mesh;
+    }
+
+    fn calc_flat_normals(node: &RsmNode) -> (Vec<Vec3>, [bool; 32]) {
+        pub fn triangle_normal(p1: &Vec3, p2: &Vec3, p3: &Vec3) -> Vec3 {
+            (p2 - p1).cross(&(p3 - p1)).normalize()
+        }
+        let mut group_used = [false; 32];
+        let normals = node
+            .faces
+            .iter()
+            .map(|face| {
+                group_used[face.smooth_group as usize] = true;
+                triangle_normal(
+                    &node.vertices[face.vertex_index[0] as usize],
+                    &node.vertices[face.vertex_index[1] as usize],
+                    &node.vertices[face.vertex_index[2] as usize],
+                )
+            })
+            .collect();
+        return (normals, group_used);
+    }
+
+    fn calc_smooth_normals(
+        node: &RsmNode,
+        normals: Vec<Vec3>,
+        group_used: [bool; 32],
+    ) -> [Vec<Vec3>; 32] {
+        let mut group: [Vec<Vec3>; 32] = Default::default();
+        for group_index in 0..32 {
+            if !group_used[group_index] {
+                continue;
+            }
+            group[group_index].reserve(node.vertices.len());
+            for vertex_index in 0..node.vertices.len() {
+                let mut grouped_normal = v3(0.0f32, 0.0f32, 0.0f32);
+                for (face_index, face) in node.faces.iter().enumerate() {
+                    if face.smooth_group as usize == group_index
+                        && (face.vertex_index[0] == vertex_index as u16
+                            || face.vertex_index[1] == vertex_index as u16
+                            || face.vertex_index[2] == vertex_index as u16)
+                    {
+                        grouped_normal += normals[face_index];
+                    }
+                }
+                group[group_index].push(grouped_normal.normalize());
+            }
+        }
+
+        return group;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_str(content: &mut Vec<u8>, s: &str) {
+        content.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        content.extend_from_slice(s.as_bytes());
+    }
+
+    fn push_f32s(content: &mut Vec<u8>, values: &[f32]) {
+        for value in values {
+            content.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    fn push_node(content: &mut Vec<u8>, name: &str, texture_names: &[&str], uv_offset: f32) {
+        push_str(content, name);
+        push_str(content, "");
+        content.extend_from_slice(&(texture_names.len() as u32).to_le_bytes());
+        for texture_name in texture_names {
+            push_str(content, texture_name);
+        }
+        push_f32s(content, &[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]); // mat3
+        push_f32s(content, &[10.0, 0.0, 0.0]); // offset
+        content.extend_from_slice(&3u32.to_le_bytes());
+        push_f32s(content, &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+        content.extend_from_slice(&1u32.to_le_bytes());
+        content.extend_from_slice(&[255; 4]); // color
+        push_f32s(content, &[0.0, 0.0]);
+        content.extend_from_slice(&1u32.to_le_bytes());
+        content.extend_from_slice(&28i32.to_le_bytes()); // face length, with 2 smoothing groups
+        for index in &[0u16, 1, 2, 0, 0, 0, texture_names.len() as u16 - 1, 0] {
+            content.extend_from_slice(&index.to_le_bytes());
+        }
+        content.extend_from_slice(&[0; 12]); // two_side and the smoothing groups
+        content.extend_from_slice(&[0; 12]); // scale, rotation and position key frames
+                                             // one texture animation for the last texture
+        for value in &[1u32, texture_names.len() as u32 - 1, 1, 0, 1, 0] {
+            content.extend_from_slice(&value.to_le_bytes());
+        }
+        push_f32s(content, &[uv_offset]);
+    }
+
+    #[test]
+    fn rsm2_models_have_multiple_roots_and_per_node_textures() {
+        let mut content = b"GRSM\x02\x03".to_vec();
+        content.extend_from_slice(&0i32.to_le_bytes()); // anim_len
+        content.extend_from_slice(&0i32.to_le_bytes()); // shade_type
+        content.push(255);
+        push_f32s(&mut content, &[30.0]);
+        content.extend_from_slice(&2u32.to_le_bytes());
+        push_str(&mut content, "a");
+        push_str(&mut content, "b");
+        content.extend_from_slice(&2u32.to_le_bytes());
+        push_node(&mut content, "a", &["x.bmp"], 0.0);
+        push_node(&mut content, "b", &["y.bmp", "x.bmp"], 0.5);
+
+        let rsm = Rsm::load(BinaryReader::from_vec(content.clone(), "test.rsm")).unwrap();
+        assert_eq!(rsm.fps, 30.0);
+        assert_eq!(rsm.root_node_indices, vec![0, 1]);
+        assert_eq!(rsm.texture_names, vec!["x.bmp", "y.bmp"]);
+        assert_eq!(rsm.nodes[1].textures, vec![1, 0]);
+        assert!(rsm.volume_boxes.is_empty());
+        // the roots are placed by their offsets
+        assert_eq!(rsm.nodes[1].matrix[(0, 3)], 10.0);
+
+        let (meshes, _bbox) = rsm.generate_meshes_by_texture_index();
+        let (texture_index, mesh) = &meshes[1][0];
+        assert_eq!(*texture_index, 0);
+        assert_eq!(mesh[0].pos, [10.0, 0.0, 0.0]);
+        assert_eq!(mesh[0].texcoord, [0.51, 0.01]);
+
+        for len in 0..content.len() {
+            let truncated = content[0..len].to_vec();
+            assert!(Rsm::load(BinaryReader::from_vec(truncated, "test.rsm")).is_err());
+        }
+    }
+}
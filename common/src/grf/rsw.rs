@@ -0,0 +1,303 @@
+use crate::common::v3;
+use crate::grf::binary_reader::{BinaryReader, ParseError, ParseErrorKind};
+use nalgebra::Vector3;
+use std::borrow::ToOwned;
+
+#[derive(Debug)]
+pub struct GroundData {
+    pub top: i32,
+    pub bottom: i32,
+    pub left: i32,
+    pub right: i32,
+}
+
+#[derive(Debug)]
+pub struct FileData {
+    pub ini: String,
+    pub gnd: String,
+    pub gat: String,
+    pub src: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct WaterData {
+    pub level: f32,
+    pub typ: i32,
+    pub wave_height: f32,
+    pub wave_speed: f32,
+    pub wave_pitch: f32,
+    pub anim_speed: i32,
+    pub images: [i32; 32],
+}
+
+#[derive(Debug)]
+pub struct LightData {
+    pub longitude: i32,
+    pub latitude: i32,
+    pub diffuse: [f32; 3],
+    pub ambient: [f32; 3],
+    pub opacity: f32,
+    pub direction: [f32; 3],
+}
+
+#[derive(Debug)]
+pub struct Rsw {
+    pub version: f32,
+    /// 0 before 2.2
+    pub build_number: i32,
+    pub ground: GroundData,
+    /// Since 2.6 it is stored in the GND, see `Gnd::water`
+    pub water: WaterData,
+    pub file: FileData,
+    pub light: LightData,
+    pub models: Vec<RswModelInstance>,
+    pub lights: Vec<MapLight>,
+    pub sounds: Vec<MapSound>,
+    pub effects: Vec<MapEffect>,
+}
+
+#[derive(Debug)]
+pub struct RswModelInstance {
+    pub name: String,
+    pub anim_type: i32,
+    pub anim_speed: f32,
+    pub block_type: i32,
+    pub filename: String,
+    pub node_name: String,
+    pub pos: Vector3<f32>,
+    pub rot: Vector3<f32>,
+    pub scale: Vector3<f32>,
+}
+
+#[derive(Debug)]
+pub struct MapLight {
+    pub name: String,
+    pub pos: Vector3<f32>,
+    pub color: [i32; 3],
+    pub range: f32,
+}
+
+#[derive(Debug)]
+pub struct MapEffect {
+    pub name: String,
+    pub pos: Vector3<f32>,
+    pub id: i32,
+    pub delay: f32,
+    pub param: [f32; 4],
+}
+
+#[derive(Debug)]
+pub struct MapSound {
+    pub name: String,
+    pub file: String,
+    pub pos: Vector3<f32>,
+    pub vol: f32,
+    pub width: i32,
+    pub height: i32,
+    pub range: f32,
+    pub cycle: f32,
+}
+
+impl Rsw {
+    pub fn load(mut buf: BinaryReader) -> Result<Self, ParseError> {
+        let header = buf.string(4)?;
+        let version = buf.next_u8()? as f32 + buf.next_u8()? as f32 / 10f32;
+        if header != "GRSW" {
+            return Err(buf.error(ParseErrorKind::InvalidHeader(header)));
+        }
+        let build_number = if version >= 2.5 {
+            let build_number = buf.next_i32()?;
+            buf.skip(1)?; // unknown
+            build_number
+        } else if version >= 2.2 {
+            buf.next_u8()? as i32
+        } else {
+            0
+        };
+
+        let file = FileData {
+            ini: buf.string(40)?,
+            gnd: buf.string(40)?,
+            gat: buf.string(40)?,
+            src: if version >= 1.4 {
+                buf.string(40)?
+            } else {
+                "".to_owned()
+            },
+        };
+
+        let water = if version >= 2.6 {
+            Rsw::default_water(0.0)
+        } else if version >= 1.8 {
+            let water_level = buf.next_f32()?;
+            WaterData {
+                level: water_level,
+                typ: buf.next_i32()?,
+                wave_height: buf.next_f32()? / 5.0,
+                wave_speed: buf.next_f32()?,
+                wave_pitch: buf.next_f32()?,
+                anim_speed: if version >= 1.9 { buf.next_i32()? } else { 0 },
+                images: [0; 32],
+            }
+        } else {
+            Rsw::default_water(if version >= 1.3 { buf.next_f32()? } else { 0.0 })
+        };
+
+        fn calc_dir(longitude: i32, latitude: i32) -> [f32; 3] {
+            let longitude = (longitude as f32).to_radians();
+            let latitude = (latitude as f32).to_radians();
+            [
+                -longitude.cos() * latitude.sin(),
+                -latitude.cos(),
+                -longitude.sin() * latitude.sin(),
+            ]
+        }
+
+        let light = if version >= 1.5 {
+            let longitude = buf.next_i32()?;
+            let latitude = buf.next_i32()?;
+            LightData {
+                longitude,
+                latitude,
+                diffuse: [buf.next_f32()?, buf.next_f32()?, buf.next_f32()?],
+                ambient: [buf.next_f32()?, buf.next_f32()?, buf.next_f32()?],
+                opacity: if version >= 1.7 { buf.next_f32()? } else { 1.0 },
+                direction: calc_dir(longitude, latitude),
+            }
+        } else {
+            LightData {
+                longitude: 45,
+                latitude: 45,
+                diffuse: [1.0, 1.0, 1.0],
+                ambient: [0.3, 0.3, 0.3],
+                opacity: 1.0,
+                direction: calc_dir(45, 45),
+            }
+        };
+
+        let ground = if version >= 1.6 {
+            GroundData {
+                top: buf.next_i32()?,
+                bottom: buf.next_i32()?,
+                left: buf.next_i32()?,
+                right: buf.next_i32()?,
+            }
+        } else {
+            GroundData {
+                top: -500,
+                bottom: 500,
+                left: -500,
+                right: 500,
+            }
+        };
+
+        let count = buf.next_i32()?;
+        let mut models: Vec<RswModelInstance> = Vec::new();
+        let mut lights: Vec<MapLight> = Vec::new();
+        let mut sounds: Vec<MapSound> = Vec::new();
+        let mut effects: Vec<MapEffect> = Vec::new();
+        for _i in 0..count {
+            let typ = buf.next_i32()?;
+            match typ {
+                1 => models.push(RswModelInstance {
+                    name: if version >= 1.3 {
+                        buf.string(40)?
+                    } else {
+                        "".to_owned()
+                    },
+                    anim_type: if version >= 1.3 { buf.next_i32()? } else { 0 },
+                    anim_speed: if version >= 1.3 { buf.next_f32()? } else { 0.0 },
+                    block_type: if version >= 1.3 { buf.next_i32()? } else { 0 },
+                    filename: {
+                        if version >= 2.6 && build_number >= 186 {
+                            buf.skip(1)?; // unknown
+                        }
+                        buf.string(80)?
+                    },
+                    node_name: buf.string(80)?,
+                    pos: v3(
+                        buf.next_f32()? / 5.0,
+                        buf.next_f32()? / 5.0,
+                        buf.next_f32()? / 5.0,
+                    ),
+                    rot: v3(buf.next_f32()?, buf.next_f32()?, buf.next_f32()?),
+                    scale: v3(
+                        buf.next_f32()? / 5.0,
+                        buf.next_f32()? / 5.0,
+                        buf.next_f32()? / 5.0,
+                    ),
+                }),
+                2 => lights.push(MapLight {
+                    name: buf.string(80)?,
+                    pos: v3(
+                        buf.next_f32()? / 5.0,
+                        buf.next_f32()? / 5.0,
+                        buf.next_f32()? / 5.0,
+                    ),
+                    color: [buf.next_i32()?, buf.next_i32()?, buf.next_i32()?],
+                    range: buf.next_f32()?,
+                }),
+                3 => sounds.push(MapSound {
+                    name: buf.string(80)?,
+                    file: buf.string(80)?,
+                    pos: v3(
+                        buf.next_f32()? / 5.0,
+                        buf.next_f32()? / 5.0,
+                        buf.next_f32()? / 5.0,
+                    ),
+                    vol: buf.next_f32()?,
+                    width: buf.next_i32()?,
+                    height: buf.next_i32()?,
+                    range: buf.next_f32()?,
+                    cycle: if version >= 2.0 { buf.next_f32()? } else { 0.0 },
+                }),
+                4 => effects.push(MapEffect {
+                    name: buf.string(80)?,
+                    pos: v3(
+                        buf.next_f32()? / 5.0,
+                        buf.next_f32()? / 5.0,
+                        buf.next_f32()? / 5.0,
+                    ),
+                    id: buf.next_i32()?,
+                    delay: buf.next_f32()? * 10.0,
+                    param: [
+                        buf.next_f32()?,
+                        buf.next_f32()?,
+                        buf.next_f32()?,
+                        buf.next_f32()?,
+                    ],
+                }),
+                _ => return buf.invalid_data(format!("Wrong entity type: {}", typ)),
+            }
+        }
+        models.shrink_to_fit();
+        lights.shrink_to_fit();
+        effects.shrink_to_fit();
+        sounds.shrink_to_fit();
+
+        return Ok(Rsw {
+            version,
+            build_number,
+            ground,
+            water,
+            file,
+            light,
+            models,
+            lights,
+            sounds,
+            effects,
+        });
+    }
+
+    fn default_water(level: f32) -> WaterData {
+        WaterData {
+            level,
+            typ: 0,
+            wave_height: 0.2,
+            wave_speed: 2.0,
+            wave_pitch: 50.0,
+            anim_speed: 3,
+            images: [0; 32],
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::grf::binary_reader::{BinaryReader, ParseError, ParseErrorKind};
+
+pub struct SpriteFile {
+    pub frames: Vec<SprFrame>,
+}
+
+pub enum SpriteType {
+    PAL,
+    ABGR,
+}
+
+pub struct SprFrame {
+    pub typ: SpriteType,
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+impl SpriteFile {
+    pub fn read_header(buf: &mut BinaryReader) -> Result<(f32, usize, u16), ParseError> {
+        let header = buf.string(2)?;
+        let version = buf.next_u8()? as f32 / 10.0 + buf.next_u8()? as f32;
+        if header != "SP" {
+            return Err(buf.error(ParseErrorKind::InvalidHeader(header)));
+        }
+
+        let indexed_frame_count = buf.next_u16()? as usize;
+        let rgba_frame_count = if version > 1.1 { buf.next_u16()? } else { 0 };
+        return Ok((version, indexed_frame_count, rgba_frame_count));
+    }
+
+    /// `palette` overrides the palette of the file, it must have 256 RGBA colors
+    pub fn load(
+        mut buf: BinaryReader,
+        palette: Option<&[u8]>,
+        version: f32,
+        indexed_frame_count: usize,
+        rgba_frame_count: u16,
+    ) -> Result<Self, ParseError> {
+        let indexed_frames = if version < 2.1 {
+            SpriteFile::read_indexed_frames(&mut buf, indexed_frame_count)?
+        } else {
+            SpriteFile::read_indexed_frames_rle(&mut buf, indexed_frame_count)?
+        };
+
+        let rgba_frames = SpriteFile::read_rgba_frames(&mut buf, rgba_frame_count)?;
+
+        let palette = {
+            let default_palette = if version > 1.0 {
+                if buf.remaining() < 1024 {
+                    return Err(buf.error(ParseErrorKind::UnexpectedEof { len: 1024 }));
+                }
+                buf.skip((buf.remaining() - 1024) as u32)?;
+                buf.next(1024)?
+            } else {
+                &[]
+            };
+            palette.map(|it| it).unwrap_or(default_palette)
+        };
+        if !indexed_frames.is_empty() && palette.len() < 1024 {
+            return buf.invalid_data("the palette is missing".to_owned());
+        }
+
+        let mut frames = Vec::with_capacity(indexed_frames.len() + rgba_frames.len());
+        for frame in indexed_frames {
+            if frame.data.len() < frame.width * frame.height {
+                return buf.invalid_data(format!(
+                    "a frame of {}x{} has only {} pixels",
+                    frame.width,
+                    frame.height,
+                    frame.data.len()
+                ));
+            }
+            frames.push(SpriteFile::to_rgba(frame, &palette));
+        }
+        frames.extend(rgba_frames);
+
+        Ok(SpriteFile { frames })
+    }
+
+    fn read_indexed_frames(
+        buf: &mut BinaryReader,
+        indexed_frame_count: usize,
+    ) -> Result<Vec<SprFrame>, ParseError> {
+        (0..indexed_frame_count)
+            .map(|_i| {
+                let width = buf.next_u16()?;
+                let height = buf.next_u16()?;
+                Ok(SprFrame {
+                    typ: SpriteType::PAL,
+                    width: width as usize,
+                    height: height as usize,
+                    data: buf.next(width as u32 * height as u32)?.to_vec(),
+                })
+            })
+            .collect()
+    }
+
+    fn to_rgba(frame: SprFrame, pal: &[u8]) -> SprFrame {
+        let mut buf = Vec::<u8>::with_capacity(frame.width * frame.height * 4);
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                let idx1 = frame.data[(y * frame.width + x)] as usize * 4;
+                buf.push(pal[idx1 + 0]); // r
+                buf.push(pal[idx1 + 1]); // g
+                buf.push(pal[idx1 + 2]); // b
+                buf.push(if idx1 != 0 { 255 } else { 0 }); // a
+            }
+        }
+        SprFrame {
+            typ: SpriteType::ABGR,
+            data: buf,
+            ..frame
+        }
+    }
+
+    fn read_indexed_frames_rle(
+        buf: &mut BinaryReader,
+        indexed_frame_count: usize,
+    ) -> Result<Vec<SprFrame>, ParseError> {
+        (0..indexed_frame_count)
+            .map(|_i| {
+                let width = buf.next_u16()?;
+                let height = buf.next_u16()?;
+                let encoded_len = buf.next_u16()? as usize;
+                let end = encoded_len + buf.tell();
+                // a byte pair expands to at most 255 pixels, the size in the header is not trusted
+                let mut data = Vec::<u8>::with_capacity(
+                    (width as usize * height as usize).min(encoded_len * 128),
+                );
+                while buf.tell() < end {
+                    let c = buf.next_u8()?;
+                    data.push(c);
+                    if c == 0 {
+                        let count = buf.next_u8()?;
+                        if count == 0 {
+                            data.push(count);
+                        } else {
+                            for _i in 1..count {
+                                data.push(c);
+                            }
+                        }
+                    }
+                }
+                Ok(SprFrame {
+                    typ: SpriteType::PAL,
+                    width: width as usize,
+                    height: height as usize,
+                    data,
+                })
+            })
+            .collect()
+    }
+
+    fn read_rgba_frames(
+        buf: &mut BinaryReader,
+        rgba_frame_count: u16,
+    ) -> Result<Vec<SprFrame>, ParseError> {
+        (0..rgba_frame_count)
+            .map(|_i| {
+                let width = buf.next_u16()? as usize;
+                let height = buf.next_u16()? as usize;
+                let len = buf.check_count(width * height * 4, 1)?;
+                let mut data = buf.next(len as u32)?.to_vec();
+                // it seems ABGR sprites are stored upside down
+                data.reverse();
+                Ok(SprFrame {
+                    typ: SpriteType::ABGR,
+                    width,
+                    height,
+                    data,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indexed_frames_are_converted_with_the_palette() {
+        let mut content = b"SP\x00\x02".to_vec();
+        content.extend_from_slice(&[1, 0, 0, 0]); // 1 indexed, 0 rgba frame
+        content.extend_from_slice(&[2, 0, 1, 0, 1, 0]); // 2x1, palette indices 1 and 0
+        let mut palette = vec![0; 1024];
+        palette[4..8].copy_from_slice(&[10, 20, 30, 0]);
+        content.extend(palette);
+
+        let mut buf = BinaryReader::from_vec(content.clone(), "test.spr");
+        let (version, indexed_frame_count, rgba_frame_count) =
+            SpriteFile::read_header(&mut buf).unwrap();
+        assert_eq!(
+            (version, indexed_frame_count, rgba_frame_count),
+            (2.0, 1, 0)
+        );
+        let sprite =
+            SpriteFile::load(buf, None, version, indexed_frame_count, rgba_frame_count).unwrap();
+        assert_eq!(sprite.frames.len(), 1);
+        // index 0 is the transparent color
+        assert_eq!(sprite.frames[0].data, vec![10, 20, 30, 255, 0, 0, 0, 0]);
+
+        // the palette is cut off
+        let mut buf = BinaryReader::from_vec(content[..100].to_vec(), "test.spr");
+        let (version, indexed_frame_count, rgba_frame_count) =
+            SpriteFile::read_header(&mut buf).unwrap();
+        assert!(
+            SpriteFile::load(buf, None, version, indexed_frame_count, rgba_frame_count).is_err()
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use crate::grf::binary_reader::{BinaryReader, ParseError, ParseErrorKind};
+use std::collections::HashMap;
+
+pub struct StrFile {
+    pub max_key: u32,
+    pub fps: u32,
+    pub layers: Vec<StrLayer>,
+    /// The distinct texture names of the layers, `StrKeyFrame::texture_index` refers to them
+    pub texture_names: Vec<String>,
+}
+
+pub struct StrLayer {
+    pub key_frames: Vec<StrKeyFrame>,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum KeyFrameType {
+    Start,
+    End,
+}
+
+pub struct StrKeyFrame {
+    pub frame: i32,
+    pub typ: KeyFrameType,
+    pub pos: [f32; 2],
+    //    pub uv: [f32; 8], it is not used, don't store it
+    pub xy: [f32; 8],
+    pub color: [u8; 4],
+    pub angle: f32,
+    /// D3DBLEND value
+    pub src_alpha: u32,
+    /// D3DBLEND value
+    pub dst_alpha: u32,
+    pub texture_index: usize,
+    //    pub anitype: u32, not used
+    //    pub delay: f32, not used O-O
+    //    pub mtpreset: u32, not used
+}
+
+impl StrFile {
+    pub fn load(mut buf: BinaryReader) -> Result<Self, ParseError> {
+        let header = buf.string(4)?;
+        if header != "STRM" {
+            return Err(buf.error(ParseErrorKind::InvalidHeader(header)));
+        }
+        let version = buf.next_u32()?;
+        if version != 0x94 {
+            return Err(buf.error(ParseErrorKind::UnsupportedVersion(version as f32)));
+        }
+
+        let fps = buf.next_u32()?;
+        let max_key = buf.next_u32()?;
+        let layer_num = buf.next_u32()?;
+        if fps == 0 || max_key == 0 {
+            return buf.invalid_data(format!("invalid fps ({}) or max key ({})", fps, max_key));
+        }
+        buf.skip(16)?;
+
+        let mut texture_names_to_index: HashMap<String, usize> = HashMap::new();
+        let mut all_texture_names: Vec<String> = Vec::new();
+
+        let mut layers = Vec::new();
+        for _i in 0..layer_num {
+            let mut texture_names: Vec<String> = Vec::new();
+            for _i in 0..buf.next_u32()? {
+                let texture_name = buf.string(128)?;
+                if !texture_names_to_index.contains_key(&texture_name) {
+                    texture_names_to_index.insert(texture_name.clone(), all_texture_names.len());
+                    all_texture_names.push(texture_name.clone());
+                }
+                texture_names.push(texture_name);
+            }
+            let mut key_frames: Vec<StrKeyFrame> = Vec::new();
+            for _i in 0..buf.next_u32()? {
+                let frame = buf.next_i32()?;
+                let typ = if buf.next_u32()? == 0 {
+                    KeyFrameType::Start
+                } else {
+                    KeyFrameType::End
+                };
+                let pos = [buf.next_f32()?, buf.next_f32()?];
+                buf.skip(4 * 8)?; // uv
+                let mut xy = [0.0; 8];
+                for value in xy.iter_mut() {
+                    *value = buf.next_f32()?;
+                }
+                let texture_name = match texture_names.get(buf.next_f32()? as usize) {
+                    Some(texture_name) => texture_name,
+                    None => {
+                        return buf
+                            .invalid_data("a key frame refers to a missing texture".to_owned())
+                    }
+                };
+                let _anitype = buf.next_u32()?;
+                let _delay = buf.next_f32()?;
+                let angle = buf.next_f32()? / (1024.0 / 360.0);
+                let color = [
+                    buf.next_f32()? as u8,
+                    buf.next_f32()? as u8,
+                    buf.next_f32()? as u8,
+                    buf.next_f32()? as u8,
+                ];
+                let src_alpha = buf.next_u32()?;
+                let dst_alpha = buf.next_u32()?;
+                let _mtpreset = buf.next_u32()?;
+                key_frames.push(StrKeyFrame {
+                    frame,
+                    typ,
+                    pos,
+                    xy,
+                    texture_index: texture_names_to_index[texture_name],
+                    angle,
+                    color,
+                    src_alpha,
+                    dst_alpha,
+                });
+            }
+            if !key_frames.is_empty() {
+                layers.push(StrLayer { key_frames });
+            }
+        }
+        Ok(StrFile {
+            max_key,
+            fps,
+            layers,
+            texture_names: all_texture_names,
+        })
+    }
+}
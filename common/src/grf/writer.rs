@@ -0,0 +1,280 @@
+use crate::grf::asset_loader::{CommonAssetLoader, GRF_FILELIST_TYPE_FILE, GRF_HEADER_SIZE};
+use crate::grf::GrfEntry;
+use byteorder::LittleEndian;
+use byteorder::WriteBytesExt;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+enum EntrySource {
+    /// The compressed (and maybe encrypted) bytes are copied from the original archive as they are
+    Archive(GrfEntry),
+    /// Uncompressed content
+    Content(Vec<u8>),
+}
+
+struct WriterEntry {
+    // the name as it will be written, the keys of the entry map are lowercase
+    name: String,
+    source: EntrySource,
+}
+
+/// Creates 0x200 GRF archives, or patches existing ones.
+/// Nothing is written until `write` is called, which always rebuilds a compacted archive,
+/// so the space of the deleted and replaced entries is freed up.
+pub struct GrfWriter {
+    source_path: Option<PathBuf>,
+    entries: BTreeMap<String, WriterEntry>,
+}
+
+impl GrfWriter {
+    pub fn new() -> GrfWriter {
+        GrfWriter {
+            source_path: None,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Starts from the entries of an existing archive (any version the loader can read)
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<GrfWriter, String> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)
+            .map_err(|e| format!("Could not open '{}': {}", path.display(), e))?;
+        let grf_entries = CommonAssetLoader::read_grf_entries(&[path], 0, &data)?;
+        let entries = grf_entries
+            .into_iter()
+            .filter(|(_name, (_grf_index, entry))| entry.typ & GRF_FILELIST_TYPE_FILE != 0)
+            .map(|(name, (_grf_index, entry))| {
+                (
+                    name.clone(),
+                    WriterEntry {
+                        name,
+                        source: EntrySource::Archive(entry),
+                    },
+                )
+            })
+            .collect();
+        return Ok(GrfWriter {
+            source_path: Some(path.to_path_buf()),
+            entries,
+        });
+    }
+
+    pub fn entry_names(&self) -> impl Iterator<Item = &String> {
+        self.entries.values().map(|it| &it.name)
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.entries.contains_key(&name.to_ascii_lowercase())
+    }
+
+    /// Adds a new entry or replaces the existing one with the same (case insensitive) name
+    pub fn add(&mut self, name: &str, content: Vec<u8>) -> Result<(), String> {
+        if name.chars().any(|ch| ch as u32 > 0xff) || name.contains('\0') {
+            return Err(format!("'{}' can't be stored in a GRF file table", name));
+        }
+        self.entries.insert(
+            name.to_ascii_lowercase(),
+            WriterEntry {
+                name: name.to_owned(),
+                source: EntrySource::Content(content),
+            },
+        );
+        return Ok(());
+    }
+
+    /// Adds every file under `dir` recursively, with `prefix` and a backslash separated
+    /// relative path as their names (e.g. "data\\sprite\\poring.spr" for prefix "data").
+    /// Returns the number of added files.
+    pub fn add_dir<P: AsRef<Path>>(&mut self, dir: P, prefix: &str) -> Result<usize, String> {
+        let dir = dir.as_ref();
+        let read_dir = std::fs::read_dir(dir)
+            .map_err(|e| format!("Could not read '{}': {}", dir.display(), e))?;
+        let mut count = 0;
+        for dir_entry in read_dir {
+            let path = dir_entry
+                .map_err(|e| format!("Could not read '{}': {}", dir.display(), e))?
+                .path();
+            let file_name = path
+                .file_name()
+                .and_then(|it| it.to_str())
+                .ok_or_else(|| format!("Invalid file name: '{}'", path.display()))?;
+            let name = if prefix.is_empty() {
+                file_name.to_owned()
+            } else {
+                format!("{}\\{}", prefix, file_name)
+            };
+            if path.is_dir() {
+                count += self.add_dir(&path, &name)?;
+            } else {
+                let content = std::fs::read(&path)
+                    .map_err(|e| format!("Could not read '{}': {}", path.display(), e))?;
+                self.add(&name, content)?;
+                count += 1;
+            }
+        }
+        return Ok(count);
+    }
+
+    /// Returns false if there was no such entry
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.entries.remove(&name.to_ascii_lowercase()).is_some()
+    }
+
+    /// The archive is written to a temporary file first, so the source archive
+    /// can be overwritten
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("grf.tmp");
+        let result = self.write_to(&tmp_path).and_then(|()| {
+            std::fs::rename(&tmp_path, path)
+                .map_err(|e| format!("Could not create '{}': {}", path.display(), e))
+        });
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp_path);
+        }
+        return result;
+    }
+
+    fn write_to(&self, path: &Path) -> Result<(), String> {
+        let write_err = |e: std::io::Error| format!("Could not write '{}': {}", path.display(), e);
+        let mut source = match &self.source_path {
+            Some(source_path) => Some(
+                File::open(source_path)
+                    .map_err(|e| format!("Could not open '{}': {}", source_path.display(), e))?,
+            ),
+            None => None,
+        };
+        let mut out = BufWriter::new(File::create(path).map_err(write_err)?);
+        // the header is written at the end, when the offset of the file table is known
+        out.write_all(&[0; GRF_HEADER_SIZE]).map_err(write_err)?;
+
+        let mut table = Vec::<u8>::with_capacity(self.entries.len() * 64);
+        let mut offset: u32 = 0;
+        for entry in self.entries.values() {
+            let (data, grf_entry) = match &entry.source {
+                EntrySource::Archive(grf_entry) => {
+                    let source = source.as_mut().expect("Archive entries come from a source");
+                    (read_raw(source, grf_entry)?, grf_entry.clone())
+                }
+                EntrySource::Content(content) => {
+                    let compressed = compress(content)?;
+                    let grf_entry = GrfEntry {
+                        pack_size: compressed.len() as u32,
+                        length_aligned: align_to_8(compressed.len()) as u32,
+                        real_size: content.len() as u32,
+                        typ: GRF_FILELIST_TYPE_FILE,
+                        offset: 0,
+                    };
+                    (compressed, grf_entry)
+                }
+            };
+            out.write_all(&data).map_err(write_err)?;
+            let padding = grf_entry.length_aligned as usize - data.len();
+            out.write_all(&vec![0; padding]).map_err(write_err)?;
+
+            table.extend(entry.name.chars().map(|ch| ch as u8));
+            table.push(0);
+            table
+                .write_u32::<LittleEndian>(grf_entry.pack_size)
+                .unwrap();
+            table
+                .write_u32::<LittleEndian>(grf_entry.length_aligned)
+                .unwrap();
+            table
+                .write_u32::<LittleEndian>(grf_entry.real_size)
+                .unwrap();
+            table.push(grf_entry.typ);
+            table.write_u32::<LittleEndian>(offset).unwrap();
+
+            offset = offset
+                .checked_add(grf_entry.length_aligned)
+                .ok_or_else(|| "The archive would be larger than 4GB".to_owned())?;
+        }
+
+        let compressed_table = compress(&table)?;
+        out.write_u32::<LittleEndian>(compressed_table.len() as u32)
+            .map_err(write_err)?;
+        out.write_u32::<LittleEndian>(table.len() as u32)
+            .map_err(write_err)?;
+        out.write_all(&compressed_table).map_err(write_err)?;
+
+        out.seek(SeekFrom::Start(0)).map_err(write_err)?;
+        out.write_all(b"Master of Magic").map_err(write_err)?;
+        out.write_all(&[0; 15]).map_err(write_err)?;
+        out.write_u32::<LittleEndian>(offset).map_err(write_err)?;
+        out.write_u32::<LittleEndian>(0).map_err(write_err)?;
+        out.write_u32::<LittleEndian>(self.entries.len() as u32 + 7)
+            .map_err(write_err)?;
+        out.write_u32::<LittleEndian>(0x200).map_err(write_err)?;
+        out.flush().map_err(write_err)?;
+        return Ok(());
+    }
+}
+
+fn align_to_8(len: usize) -> usize {
+    (len + 7) & !7
+}
+
+fn compress(content: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = libflate::zlib::Encoder::new(Vec::with_capacity(content.len() / 2))
+        .map_err(|e| e.to_string())?;
+    encoder.write_all(content).map_err(|e| e.to_string())?;
+    return encoder.finish().into_result().map_err(|e| e.to_string());
+}
+
+fn read_raw(source: &mut File, entry: &GrfEntry) -> Result<Vec<u8>, String> {
+    let mut buf = Vec::with_capacity(entry.length_aligned as usize);
+    source
+        .seek(SeekFrom::Start(
+            entry.offset as u64 + GRF_HEADER_SIZE as u64,
+        ))
+        .map_err(|e| e.to_string())?;
+    source
+        .take(entry.length_aligned as u64)
+        .read_to_end(&mut buf)
+        .map_err(|e| e.to_string())?;
+    if buf.len() < entry.length_aligned as usize {
+        return Err(format!("An entry at {} is truncated", entry.offset));
+    }
+    return Ok(buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_archive_can_be_read_and_patched() {
+        let dir = std::env::temp_dir().join(format!("rustarok_grf_writer_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.grf");
+
+        let mut writer = GrfWriter::new();
+        writer.add("data\\Test.txt", b"hello".to_vec()).unwrap();
+        writer.add("data\\removed.txt", vec![1; 1000]).unwrap();
+        writer.write(&path).unwrap();
+
+        let mut writer = GrfWriter::open(&path).unwrap();
+        assert!(writer.remove("data\\REMOVED.txt"));
+        writer.add("data\\new.txt", b"new".to_vec()).unwrap();
+        writer.write(&path).unwrap();
+
+        let archive = std::fs::read(&path).unwrap();
+        let entries = CommonAssetLoader::read_grf_entries(&[&path], 0, &archive).unwrap();
+        let get_content = |name: &str| {
+            let (_grf_index, entry) = &entries[name];
+            CommonAssetLoader::decode_entry(&archive, entry, name).unwrap()
+        };
+        assert_eq!(get_content("data\\test.txt"), b"hello");
+        assert_eq!(get_content("data\\new.txt"), b"new");
+        assert!(!entries.contains_key("data\\removed.txt"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -16,9 +16,16 @@ extern crate hexplay;
 
 use specs;
 
+pub mod attack;
+pub mod char_attr;
 pub mod common;
 pub mod components;
+pub mod config;
+pub mod flow_field;
 pub mod grf;
 pub mod packets;
+pub mod pathfinding;
+pub mod scenario;
 pub mod serde_remote;
 pub mod systems;
+pub mod vision;
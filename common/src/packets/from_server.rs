@@ -1,9 +1,15 @@
+use crate::attack::HpModificationResultType;
+use crate::common::Vec2;
 use crate::components::char::{
     CharDir, CharEntityId, CharOutlook, CharType, JobId, ServerEntityId, Team,
 };
-use crate::components::snapshot::{CharSnapshot, WorldSnapshot};
+use crate::components::manifestation::SkillManifestationComponent;
+use crate::components::skills::Skills;
+use crate::components::snapshot::{CharSnapshot, CharSnapshotDelta, WorldSnapshot};
+use crate::components::status::StatusesComponent;
+use crate::packets::handshake::HandshakeRejectReason;
 use crate::packets::to_server::{Packet, PacketReadErr};
-use crate::packets::SocketBuffer;
+use crate::packets::{Channel, SocketBuffer, MAX_PACKET_SIZE};
 use crate::serde_remote::MyIoErrorKind;
 use serde::export::TryFrom;
 use serde::Deserialize;
@@ -13,17 +19,112 @@ use strum::EnumCount;
 use strum_macros::EnumCount;
 use strum_macros::EnumDiscriminants;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AckEntry {
     EntityState {
         id: ServerEntityId,
         char_snapshot: CharSnapshot,
     }, // entity moved out, moved in, atk speed change, status change etc
+    /// Only the changed fields compared to the baseline of the Ack
+    EntityDelta {
+        id: ServerEntityId,
+        delta: CharSnapshotDelta,
+    },
+    /// The entity was in the baseline but it does not exist anymore, both sides drop it from
+    /// their baselines
+    EntityRemoved { id: ServerEntityId },
+    /// The entity still exists but the team of the recipient does not see it anymore, it is
+    /// sent again in a NewEntity when it gets into the vision
+    EntityOutOfVision { id: ServerEntityId },
+    /// The manifestation in full, only when it differs from the baseline. The server-only
+    /// bookkeeping is left out
+    ManifestationState {
+        id: ServerEntityId,
+        manifestation: SkillManifestationComponent,
+    },
+    /// The statuses of the char, only when they differ from the baseline
+    StatusesState {
+        id: ServerEntityId,
+        statuses: StatusesComponent,
+    },
+    SkillCast {
+        caster: ServerEntityId,
+        skill: Skills,
+        caster_pos: Vec2,
+        skill_pos: Option<Vec2>,
+        char_to_skill_dir: Vec2,
+        target_entity: Option<ServerEntityId>,
+    },
+    HpModification {
+        src: ServerEntityId,
+        dst: ServerEntityId,
+        typ: HpModificationResultType,
+        dst_hp: i32,
+    },
+    /// The char has been revived with full hp, its position arrives in the snapshot
+    Respawn { id: ServerEntityId, hp: i32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerMatchStats {
+    pub id: ServerEntityId,
+    pub name: String,
+    pub team: Team,
+    pub kills: u32,
+    pub deaths: u32,
+    pub damage_done: u32,
+    pub healing_done: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MatchPhase {
+    /// Waiting for enough players
+    Lobby {
+        players: usize,
+        required_players: usize,
+    },
+    Warmup {
+        remaining_seconds: f32,
+    },
+    Running {
+        remaining_seconds: f32,
+        left_team_kills: u32,
+        right_team_kills: u32,
+    },
+    /// None means draw. The next match starts after remaining_seconds.
+    Ended {
+        winner: Option<Team>,
+        stats: Vec<PlayerMatchStats>,
+        remaining_seconds: f32,
+    },
 }
 
-#[derive(Debug, EnumDiscriminants, EnumCount, Serialize, Deserialize)]
+impl AckEntry {
+    pub fn entity_id(&self) -> Option<ServerEntityId> {
+        match self {
+            AckEntry::EntityState { id, .. } | AckEntry::EntityDelta { id, .. } => Some(*id),
+            _ => None,
+        }
+    }
+
+    /// The hp changes must not be lost together with an unreliable Ack, these entries are
+    /// sent in GameplayEvents instead
+    pub fn is_reliable_event(&self) -> bool {
+        match self {
+            AckEntry::HpModification { .. } | AckEntry::Respawn { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, EnumDiscriminants, EnumCount, Serialize, Deserialize)]
 pub enum FromServerPacket {
     LocalError(Option<MyIoErrorKind>),
+    /// It must remain the second variant, so clients with a different version can display it.
+    /// The server does not process any further packets from the client.
+    HandshakeRejected {
+        reason: HandshakeRejectReason,
+    },
     Init {
         map_name: String,
         start_x: f32,
@@ -35,6 +136,8 @@ pub enum FromServerPacket {
     Ack {
         cid: u32,
         ack_tick: u64,
+        /// the ack_tick of the snapshot the deltas were encoded against
+        baseline_tick: Option<u64>,
         entries: Vec<AckEntry>,
     },
     NewEntity {
@@ -47,6 +150,11 @@ pub enum FromServerPacket {
         max_hp: i32,
         state: CharSnapshot,
     },
+    MatchPhaseChanged(MatchPhase),
+    /// The HpModification and Respawn entries of a tick, see AckEntry::is_reliable_event
+    GameplayEvents {
+        entries: Vec<AckEntry>,
+    },
 }
 
 impl Packet for FromServerPacket {
@@ -55,13 +163,27 @@ impl Packet for FromServerPacket {
     }
 
     fn read_from(buf: &mut SocketBuffer) -> Result<Self, PacketReadErr> {
-        return match bincode::deserialize_from(buf) {
-            Ok(packet) => Ok(packet),
-            Err(e) => Err(PacketReadErr::NotEnoughBytes),
-        };
+        return Ok(bincode::config()
+            .limit(MAX_PACKET_SIZE as u64)
+            .deserialize_from(buf)?);
     }
 
     fn new_error_packet(e: Option<Error>) -> Self {
         FromServerPacket::LocalError(e.map(|it| unsafe { std::mem::transmute(it.kind()) }))
     }
+
+    // the SkillCasts in Ack are only visual effects, they can be lost together with the snapshot
+    fn channel(&self) -> Channel {
+        match self {
+            FromServerPacket::LocalError(_)
+            | FromServerPacket::HandshakeRejected { .. }
+            | FromServerPacket::Init { .. }
+            | FromServerPacket::NewEntity { .. }
+            | FromServerPacket::MatchPhaseChanged(_)
+            | FromServerPacket::GameplayEvents { .. } => Channel::ReliableOrdered,
+            FromServerPacket::Pong { .. } | FromServerPacket::Ack { .. } => {
+                Channel::UnreliableSequenced
+            }
+        }
+    }
 }
@@ -0,0 +1,157 @@
+use crate::config::SkillsConfig;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+/// Has to be increased whenever the layout of any packet or its meaning changes
+pub const PROTOCOL_VERSION: u32 = 7;
+
+/// The client can decode snapshots which were delta-compressed against an acked baseline
+pub const CAPABILITY_SNAPSHOT_DELTA: u32 = 1 << 0;
+/// The client can reassemble packets which were fragmented into multiple UDP datagrams
+pub const CAPABILITY_UDP_FRAGMENTATION: u32 = 1 << 1;
+
+pub const SUPPORTED_CAPABILITIES: u32 = CAPABILITY_SNAPSHOT_DELTA | CAPABILITY_UDP_FRAGMENTATION;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HandshakeRejectReason {
+    ProtocolVersionMismatch {
+        server_version: u32,
+        client_version: u32,
+    },
+    MissingCapabilities {
+        missing: u32,
+    },
+    /// The gameplay rules (the skills section of config-runtime.toml) differ
+    ConfigMismatch,
+    /// The map in the client's GRF files differs from the server's one
+    MapMismatch {
+        map_name: String,
+    },
+}
+
+impl Display for HandshakeRejectReason {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self {
+            HandshakeRejectReason::ProtocolVersionMismatch {
+                server_version,
+                client_version,
+            } => write!(
+                f,
+                "Incompatible version. The server uses protocol version {}, your client uses {}.",
+                server_version, client_version
+            ),
+            HandshakeRejectReason::MissingCapabilities { missing } => write!(
+                f,
+                "Your client does not support the features required by the server ({:#b}).",
+                missing
+            ),
+            HandshakeRejectReason::ConfigMismatch => write!(
+                f,
+                "Your skill configuration (config-runtime.toml) differs from the server's one."
+            ),
+            HandshakeRejectReason::MapMismatch { map_name } => write!(
+                f,
+                "Your '{}' map differs from the server's one, please check your GRF files.",
+                map_name
+            ),
+        }
+    }
+}
+
+pub fn validate_welcome(
+    protocol_version: u32,
+    capabilities: u32,
+    config_hash: u64,
+    required_capabilities: u32,
+    server_config_hash: u64,
+) -> Result<(), HandshakeRejectReason> {
+    if protocol_version != PROTOCOL_VERSION {
+        return Err(HandshakeRejectReason::ProtocolVersionMismatch {
+            server_version: PROTOCOL_VERSION,
+            client_version: protocol_version,
+        });
+    }
+    let missing = required_capabilities & !capabilities;
+    if missing != 0 {
+        return Err(HandshakeRejectReason::MissingCapabilities { missing });
+    }
+    if config_hash != server_config_hash {
+        return Err(HandshakeRejectReason::ConfigMismatch);
+    }
+    return Ok(());
+}
+
+/// FNV-1a, the std hashers are not guaranteed to produce the same result on different builds
+pub struct ContentHasher(u64);
+
+impl ContentHasher {
+    pub fn new() -> ContentHasher {
+        ContentHasher(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+pub fn skills_config_hash(skills: &SkillsConfig) -> u64 {
+    let mut hasher = ContentHasher::new();
+    // the keys of a json Value are sorted, so the encoding does not depend on the order of
+    // the fields, and the floats are printed the same way on every platform
+    let canonical = serde_json::to_value(skills)
+        .and_then(|value| serde_json::to_vec(&value))
+        .expect("The skills config can always be serialized");
+    hasher.write(&canonical);
+    return hasher.finish();
+}
+
+pub fn map_hash(map_name: &str, gat_content: &[u8]) -> u64 {
+    let mut hasher = ContentHasher::new();
+    hasher.write(map_name.as_bytes());
+    hasher.write(gat_content);
+    return hasher.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_hasher_is_fnv1a() {
+        let mut hasher = ContentHasher::new();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(map_hash("prontera", &[1, 2]), map_hash("prontera", &[1, 3]));
+    }
+
+    #[test]
+    fn welcome_validation() {
+        assert_eq!(
+            validate_welcome(PROTOCOL_VERSION, SUPPORTED_CAPABILITIES, 7, 1, 7),
+            Ok(())
+        );
+        assert_eq!(
+            validate_welcome(PROTOCOL_VERSION + 1, SUPPORTED_CAPABILITIES, 7, 1, 7),
+            Err(HandshakeRejectReason::ProtocolVersionMismatch {
+                server_version: PROTOCOL_VERSION,
+                client_version: PROTOCOL_VERSION + 1,
+            })
+        );
+        assert_eq!(
+            validate_welcome(PROTOCOL_VERSION, 0b01, 7, 0b11, 7),
+            Err(HandshakeRejectReason::MissingCapabilities { missing: 0b10 })
+        );
+        assert_eq!(
+            validate_welcome(PROTOCOL_VERSION, SUPPORTED_CAPABILITIES, 7, 1, 8),
+            Err(HandshakeRejectReason::ConfigMismatch)
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+/// How much a reordered packet is held back in addition to its normal delay,
+/// so the packets sent after it can overtake it
+const REORDER_HOLD_BACK: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct LinkConditionerConfig {
+    pub delay_ms: u32,
+    /// random extra delay between 0 and jitter_ms, it does not reorder packets
+    pub jitter_ms: u32,
+    pub loss_percent: f32,
+    pub duplication_percent: f32,
+    pub reorder_percent: f32,
+}
+
+impl LinkConditionerConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.delay_ms > 0
+            || self.jitter_ms > 0
+            || self.loss_percent > 0.0
+            || self.duplication_percent > 0.0
+            || self.reorder_percent > 0.0
+    }
+
+    /// For the transports which guarantee the delivery and the order themselves
+    pub fn delay_only(&self) -> LinkConditionerConfig {
+        LinkConditionerConfig {
+            delay_ms: self.delay_ms,
+            jitter_ms: self.jitter_ms,
+            ..Default::default()
+        }
+    }
+}
+
+/// Simulates bad network conditions in the PacketHandlerThread, separately for each direction.
+/// The UDP datagrams are conditioned one by one, the TCP packets are only delayed.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct NetworkSimulationConfig {
+    /// the same seed produces the same sequence of lost, duplicated and reordered packets
+    pub seed: u64,
+    pub incoming: LinkConditionerConfig,
+    pub outgoing: LinkConditionerConfig,
+}
+
+/// xorshift64*, the conditioner must not depend on the thread_rng to be reproducible
+struct SimulationRng(u64);
+
+impl SimulationRng {
+    fn new(seed: u64) -> SimulationRng {
+        // the state must not be zero
+        match seed ^ 0x9E37_79B9_7F4A_7C15 {
+            0 => SimulationRng(0x9E37_79B9_7F4A_7C15),
+            state => SimulationRng(state),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// in the range of [0, 100)
+    fn next_percent(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32 * 100.0
+    }
+
+    fn next_up_to(&mut self, max: u32) -> u32 {
+        if max == 0 {
+            0
+        } else {
+            (self.next_u64() % (max as u64 + 1)) as u32
+        }
+    }
+}
+
+pub struct LinkConditioner<T> {
+    config: LinkConditionerConfig,
+    rng: SimulationRng,
+    // (release_at, item), ordered by insertion
+    queue: Vec<(Instant, T)>,
+    last_in_order_release: Option<Instant>,
+}
+
+impl<T: Clone> LinkConditioner<T> {
+    pub fn new(config: LinkConditionerConfig, seed: u64) -> LinkConditioner<T> {
+        LinkConditioner {
+            config,
+            rng: SimulationRng::new(seed),
+            queue: Vec::with_capacity(64),
+            last_in_order_release: None,
+        }
+    }
+
+    pub fn set_config(&mut self, config: LinkConditionerConfig, seed: u64) {
+        self.config = config;
+        self.rng = SimulationRng::new(seed);
+    }
+
+    pub fn push(&mut self, now: Instant, item: T) {
+        if !self.config.is_enabled() {
+            self.queue.push((now, item));
+            return;
+        }
+        if self.rng.next_percent() < self.config.loss_percent {
+            return;
+        }
+        let copies = if self.rng.next_percent() < self.config.duplication_percent {
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            let delay = self.config.delay_ms + self.rng.next_up_to(self.config.jitter_ms);
+            let release_at = now + Duration::from_millis(delay as u64);
+            let release_at = if self.rng.next_percent() < self.config.reorder_percent {
+                release_at + REORDER_HOLD_BACK
+            } else {
+                // jitter alone must not let a packet overtake the previous one
+                let release_at = self
+                    .last_in_order_release
+                    .map(|last| last.max(release_at))
+                    .unwrap_or(release_at);
+                self.last_in_order_release = Some(release_at);
+                release_at
+            };
+            self.queue.push((release_at, item.clone()));
+        }
+    }
+
+    /// Moves the items whose delay has expired into `out`, in the order of their release time
+    pub fn pop_ready(&mut self, now: Instant, out: &mut Vec<T>) {
+        if self.queue.is_empty() {
+            return;
+        }
+        // stable sort, items with the same release time keep their original order
+        self.queue.sort_by_key(|it| it.0);
+        let ready_count = self
+            .queue
+            .iter()
+            .position(|it| it.0 > now)
+            .unwrap_or(self.queue.len());
+        out.extend(self.queue.drain(0..ready_count).map(|it| it.1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(config: LinkConditionerConfig, seed: u64) -> Vec<u32> {
+        let mut conditioner = LinkConditioner::new(config, seed);
+        let start = Instant::now();
+        for i in 0..100 {
+            conditioner.push(start + Duration::from_millis(i as u64), i);
+        }
+        let mut out = Vec::new();
+        conditioner.pop_ready(start + Duration::from_secs(10), &mut out);
+        out
+    }
+
+    #[test]
+    fn disabled_conditioner_passes_everything_through() {
+        let out = run(LinkConditionerConfig::default(), 1);
+        assert_eq!(out, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn same_seed_produces_same_result() {
+        let config = LinkConditionerConfig {
+            delay_ms: 20,
+            jitter_ms: 10,
+            loss_percent: 10.0,
+            duplication_percent: 10.0,
+            reorder_percent: 10.0,
+        };
+        let a = run(config.clone(), 42);
+        assert_eq!(a, run(config.clone(), 42));
+        assert_ne!(a, run(config, 43));
+    }
+
+    #[test]
+    fn delayed_packets_are_not_released_early() {
+        let config = LinkConditionerConfig {
+            delay_ms: 100,
+            ..Default::default()
+        };
+        let mut conditioner = LinkConditioner::new(config, 0);
+        let start = Instant::now();
+        conditioner.push(start, 1);
+        let mut out = Vec::new();
+        conditioner.pop_ready(start + Duration::from_millis(50), &mut out);
+        assert!(out.is_empty());
+        conditioner.pop_ready(start + Duration::from_millis(100), &mut out);
+        assert_eq!(out, vec![1]);
+    }
+}
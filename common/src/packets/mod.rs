@@ -1,19 +1,50 @@
 use crate::common::{v2, Vec2};
 use crate::grf::binary_reader::BinaryReader;
+use crate::packets::link_conditioner::{LinkConditioner, NetworkSimulationConfig};
 use crate::packets::to_server::{Packet, PacketReadErr};
+use crate::packets::udp::UdpEndpoint;
 use hexplay::{HexView, HexViewBuilder};
 use serde::export::fmt::Debug;
+use serde::Deserialize;
 use std::io::prelude::*;
 use std::io::{Error, Read};
-use std::net::TcpStream;
+use std::net::{SocketAddr, TcpStream, UdpSocket};
 use std::sync::mpsc::RecvError;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 pub mod from_server;
+pub mod handshake;
+pub mod link_conditioner;
 pub mod to_server;
+pub mod udp;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+/// Delivery guarantees of a packet, only the UDP transport makes use of it,
+/// TCP delivers everything reliably and in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// Lost packets are not resent and packets older than the last received one are dropped
+    UnreliableSequenced,
+    /// Every packet arrives exactly once and in the order they were sent
+    ReliableOrdered,
+}
+
+/// Frames bigger than this are considered malformed and the connection is closed
+pub const MAX_PACKET_SIZE: usize = 1024 * 1024;
+const INITIAL_BUFFER_SIZE: usize = 2048;
+/// size of the length prefix of the frames
+const FRAME_HEADER_SIZE: usize = 4;
+
+/// A growable buffer. The data between `os_pointer` and `user_pointer` is the outgoing data
+/// which has not been sent yet (writing), or the received data which has not been
+/// processed yet (reading).
 pub struct SocketBuffer {
-    buf: [u8; 2048],
+    buf: Vec<u8>,
     /// pointer at which the OS writes/reads the data during send/recv
     os_pointer: usize,
     /// pointer at which the application writes/reads the data during send/recv
@@ -22,16 +53,8 @@ pub struct SocketBuffer {
 
 impl Write for SocketBuffer {
     fn write(&mut self, src: &[u8]) -> Result<usize, Error> {
-        //        pub fn write_u8(&mut self, value: u8) {
-        //            self.buff[self.user_pointer] = value;
-        //            self.user_pointer += 1;
-        //        }
-        let from = self.user_pointer;
-        let len = src.len();
-        let to = self.user_pointer + len;
-        self.buf[from..to].copy_from_slice(src);
-        self.user_pointer += len;
-        Ok(len)
+        self.put(src);
+        Ok(src.len())
     }
 
     fn flush(&mut self) -> Result<(), Error> {
@@ -46,7 +69,7 @@ impl Read for SocketBuffer {
         let copy_len = full_len.min(dst.len());
         let to = from + copy_len;
 
-        dst[0..copy_len].copy_from_slice(&mut self.buf[from..to]);
+        dst[0..copy_len].copy_from_slice(&self.buf[from..to]);
         self.user_pointer += copy_len;
         return Ok(copy_len);
     }
@@ -55,7 +78,7 @@ impl Read for SocketBuffer {
 impl SocketBuffer {
     pub fn new() -> SocketBuffer {
         SocketBuffer {
-            buf: [0; 2048],
+            buf: vec![0; INITIAL_BUFFER_SIZE],
             os_pointer: 0,
             user_pointer: 0,
         }
@@ -65,11 +88,50 @@ impl SocketBuffer {
         &mut self,
         socket_stream: &mut TcpStream,
     ) -> Result<usize, std::io::Error> {
+        // keep the unprocessed (partial) frame, but make room for the new data
+        self.compact_incoming_data();
+        if self.buf.len() - self.os_pointer < INITIAL_BUFFER_SIZE / 2 {
+            let new_len = self.buf.len() * 2;
+            self.buf.resize(new_len, 0);
+        }
         let n = socket_stream.read(&mut self.buf[self.os_pointer..])?;
         self.os_pointer += n;
         Ok(n)
     }
 
+    /// Moves the unprocessed incoming data to the beginning of the buffer
+    fn compact_incoming_data(&mut self) {
+        if self.user_pointer > 0 {
+            self.buf.copy_within(self.user_pointer..self.os_pointer, 0);
+            self.os_pointer -= self.user_pointer;
+            self.user_pointer = 0;
+        }
+    }
+
+    /// Moves the unsent outgoing data to the beginning of the buffer
+    fn compact_outgoing_data(&mut self) {
+        if self.os_pointer > 0 {
+            self.buf.copy_within(self.os_pointer..self.user_pointer, 0);
+            self.user_pointer -= self.os_pointer;
+            self.os_pointer = 0;
+        }
+    }
+
+    /// Replaces the content of the buffer with the given data, e.g. with a received datagram
+    pub fn fill_from(&mut self, data: &[u8]) {
+        if self.buf.len() < data.len() {
+            self.buf.resize(data.len(), 0);
+        }
+        self.buf[0..data.len()].copy_from_slice(data);
+        self.os_pointer = data.len();
+        self.user_pointer = 0;
+    }
+
+    /// The data which was written into the buffer but has not been sent yet
+    pub fn outgoing_data(&self) -> &[u8] {
+        &self.buf[self.os_pointer..self.user_pointer]
+    }
+
     pub fn eof(&self) -> bool {
         self.os_pointer == self.user_pointer
     }
@@ -90,76 +152,136 @@ impl SocketBuffer {
             .finish();
     }
 
+    /// Sends as much as the OS accepts, the rest remains in the buffer
     #[inline]
     pub fn send_outgoing_data(
         &mut self,
         socket_stream: &mut TcpStream,
     ) -> Result<(), std::io::Error> {
-        let mut all_sent_data = 0;
         while !self.eof() {
             let sending_buf = &self.buf[self.os_pointer..self.user_pointer];
-            let sent_data = socket_stream.write(sending_buf)?;
-            self.os_pointer += sent_data;
-            all_sent_data += sent_data;
+            match socket_stream.write(sending_buf) {
+                Ok(sent_data) => self.os_pointer += sent_data,
+                Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
         }
+        self.compact_outgoing_data();
         Ok(())
     }
 
-    pub fn read_u8(&mut self) -> u8 {
-        let result = self.buf[self.user_pointer];
-        self.user_pointer += 1;
-        return result;
+    /// Writes the packet with a u32 length prefix
+    pub fn write_packet<P: Packet>(&mut self, packet: &P) {
+        let frame_start = self.user_pointer;
+        self.write_u32(0);
+        packet.write_into(self);
+        let len = (self.user_pointer - frame_start - FRAME_HEADER_SIZE) as u32;
+        self.buf[frame_start..frame_start + FRAME_HEADER_SIZE].copy_from_slice(&len.to_le_bytes());
+    }
+
+    /// Reads a length prefixed packet. If the whole frame has not arrived yet,
+    /// NotEnoughBytes is returned and the buffer remains untouched.
+    pub fn read_packet<P: Packet>(&mut self) -> Result<P, PacketReadErr> {
+        self.ensure_size(FRAME_HEADER_SIZE)?;
+        let len = self.peek_u32() as usize;
+        if len > MAX_PACKET_SIZE {
+            return Err(PacketReadErr::InvalidValues);
+        }
+        self.ensure_size(FRAME_HEADER_SIZE + len)?;
+        self.user_pointer += FRAME_HEADER_SIZE;
+        let frame_end = self.user_pointer + len;
+        // the packet must not read into the next frame
+        let os_pointer = self.os_pointer;
+        self.os_pointer = frame_end;
+        let result = P::read_from(self);
+        self.os_pointer = os_pointer;
+        let fully_consumed = self.user_pointer == frame_end;
+        self.user_pointer = frame_end;
+        return match result {
+            Ok(packet) if fully_consumed => Ok(packet),
+            // the frame was complete, so missing bytes mean a malformed packet as well
+            _ => Err(PacketReadErr::InvalidValues),
+        };
+    }
+
+    fn put(&mut self, src: &[u8]) {
+        let from = self.user_pointer;
+        let to = self.user_pointer + src.len();
+        if to > self.buf.len() {
+            let new_len = to.max(self.buf.len() * 2);
+            self.buf.resize(new_len, 0);
+        }
+        self.buf[from..to].copy_from_slice(src);
+        self.user_pointer = to;
+    }
+
+    fn take<'a>(&'a mut self, len: usize) -> Result<&'a [u8], PacketReadErr> {
+        self.ensure_size(len)?;
+        let from = self.user_pointer;
+        self.user_pointer += len;
+        return Ok(&self.buf[from..from + len]);
+    }
+
+    fn peek_u32(&self) -> u32 {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&self.buf[self.user_pointer..self.user_pointer + 4]);
+        u32::from_le_bytes(bytes)
+    }
+
+    /// Reads the u32 at `offset` bytes after the current position without consuming anything
+    pub fn peek_u32_at(&self, offset: usize) -> Option<u32> {
+        self.ensure_size(offset + 4).ok()?;
+        let from = self.user_pointer + offset;
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&self.buf[from..from + 4]);
+        return Some(u32::from_le_bytes(bytes));
+    }
+
+    /// Consumes the remaining incoming data, e.g. the undecodable rest of a packet
+    pub fn skip_remaining(&mut self) {
+        self.user_pointer = self.os_pointer;
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, PacketReadErr> {
+        Ok(self.take(1)?[0])
     }
 
-    pub fn read_f32(&mut self) -> f32 {
-        let result =
-            unsafe { *(self.buf.as_ptr().offset(self.user_pointer as isize) as *const f32) };
-        self.user_pointer += 4;
-        return result;
+    pub fn read_f32(&mut self) -> Result<f32, PacketReadErr> {
+        Ok(f32::from_bits(self.read_u32()?))
     }
 
-    pub fn read_v2(&mut self) -> Vec2 {
-        v2(self.read_f32(), self.read_f32())
+    pub fn read_v2(&mut self) -> Result<Vec2, PacketReadErr> {
+        Ok(v2(self.read_f32()?, self.read_f32()?))
     }
 
-    pub fn read_i32(&mut self) -> i32 {
-        let result =
-            unsafe { *(self.buf.as_ptr().offset(self.user_pointer as isize) as *const i32) };
-        self.user_pointer += 4;
-        return result;
+    pub fn read_i32(&mut self) -> Result<i32, PacketReadErr> {
+        Ok(self.read_u32()? as i32)
     }
 
-    pub fn read_u32(&mut self) -> u32 {
-        let result =
-            unsafe { *(self.buf.as_ptr().offset(self.user_pointer as isize) as *const u32) };
-        self.user_pointer += 4;
-        return result;
+    pub fn read_u32(&mut self) -> Result<u32, PacketReadErr> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
     }
 
-    pub fn read_u64(&mut self) -> u64 {
-        let result =
-            unsafe { *(self.buf.as_ptr().offset(self.user_pointer as isize) as *const u64) };
-        self.user_pointer += 8;
-        return result;
+    pub fn read_u64(&mut self) -> Result<u64, PacketReadErr> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
     }
 
-    pub fn read_u16(&mut self) -> u16 {
-        let result =
-            unsafe { *(self.buf.as_ptr().offset(self.user_pointer as isize) as *const u16) };
-        self.user_pointer += 2;
-        return result;
+    pub fn read_u16(&mut self) -> Result<u16, PacketReadErr> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.take(2)?);
+        Ok(u16::from_le_bytes(bytes))
     }
 
     pub fn write_u8(&mut self, value: u8) {
-        self.buf[self.user_pointer] = value;
-        self.user_pointer += 1;
+        self.put(&[value]);
     }
 
     pub fn write_f32(&mut self, value: f32) {
-        unsafe {
-            *(self.buf.as_mut_ptr().offset(self.user_pointer as isize) as *mut f32) = value;
-        }
-        self.user_pointer += 4;
+        self.write_u32(value.to_bits());
     }
 
     pub fn write_v2(&mut self, value: &Vec2) {
@@ -168,55 +290,35 @@ impl SocketBuffer {
     }
 
     pub fn write_i32(&mut self, value: i32) {
-        unsafe {
-            *(self.buf.as_mut_ptr().offset(self.user_pointer as isize) as *mut i32) = value;
-        }
-        self.user_pointer += 4;
+        self.put(&value.to_le_bytes());
     }
 
     pub fn write_u32(&mut self, value: u32) {
-        unsafe {
-            *(self.buf.as_mut_ptr().offset(self.user_pointer as isize) as *mut u32) = value;
-        }
-        self.user_pointer += 4;
+        self.put(&value.to_le_bytes());
     }
 
     pub fn write_u64(&mut self, value: u64) {
-        unsafe {
-            *(self.buf.as_mut_ptr().offset(self.user_pointer as isize) as *mut u64) = value;
-        }
-        self.user_pointer += 8;
+        self.put(&value.to_le_bytes());
     }
 
     pub fn write_u16(&mut self, value: u16) {
-        unsafe {
-            *(self.buf.as_mut_ptr().offset(self.user_pointer as isize) as *mut u16) = value;
-        }
-        self.user_pointer += 2;
+        self.put(&value.to_le_bytes());
     }
 
     pub fn read_str(&mut self) -> Result<&str, PacketReadErr> {
-        let len = self.read_u16();
+        let len = self.read_u16()?;
         if len > 1024 {
             return Err(PacketReadErr::InvalidValues);
         }
-        self.ensure_size(len as usize)?;
-        let start_index = self.user_pointer;
-        let end_index = self.user_pointer + len as usize;
-        self.user_pointer = end_index;
-        return match std::str::from_utf8(&self.buf[start_index..end_index]) {
+        return match std::str::from_utf8(self.take(len as usize)?) {
             Ok(str) => Ok(str),
             _ => Err(PacketReadErr::InvalidValues),
         };
     }
 
     pub fn write_str(&mut self, text: &str) {
-        let len = text.len();
-        self.write_u16(len as u16);
-        let start = self.user_pointer;
-        let end = self.user_pointer + text.len();
-        self.buf[start..end].clone_from_slice(text.as_bytes());
-        self.user_pointer = end;
+        self.write_u16(text.len() as u16);
+        self.put(text.as_bytes());
     }
 
     pub fn reset(&mut self) {
@@ -261,34 +363,82 @@ impl SocketId {
 
 pub struct PacketHandlerThread<I, O>
 where
-    I: Send + Packet + Debug + 'static,
-    O: Send + Packet + Debug + 'static,
+    I: Send + Packet + Clone + Debug + 'static,
+    O: Send + Packet + Clone + Debug + 'static,
 {
     incoming_channel: std::sync::mpsc::Receiver<(SocketId, I)>,
     outgoing_channel: std::sync::mpsc::Sender<NetworkTrafficHandlerMsg<O>>,
+    // UDP clients are not accepted explicitly, the thread reports them when they have finished
+    // the handshake
+    new_connections_channel: std::sync::mpsc::Receiver<(SocketId, SocketAddr)>,
     sockets: Vec<Option<()>>,
 }
 
 impl<I, O> PacketHandlerThread<I, O>
 where
-    I: Send + Packet + Debug + 'static,
-    O: Send + Packet + Debug + 'static,
+    I: Send + Packet + Clone + Debug + 'static,
+    O: Send + Packet + Clone + Debug + 'static,
 {
     pub fn start_thread(socket_capacity: usize) -> PacketHandlerThread<I, O> {
         let (send_to_incoming_ch, read_from_incoming_ch) =
             std::sync::mpsc::channel::<(SocketId, I)>();
         let (send_to_outgoing_ch, read_from_outgoing_ch) =
             std::sync::mpsc::channel::<NetworkTrafficHandlerMsg<O>>();
+        let (send_to_new_conn_ch, read_from_new_conn_ch) =
+            std::sync::mpsc::channel::<(SocketId, SocketAddr)>();
         std::thread::spawn(move || {
-            network_traffic_handler(send_to_incoming_ch, read_from_outgoing_ch)
+            network_traffic_handler(
+                send_to_incoming_ch,
+                read_from_outgoing_ch,
+                send_to_new_conn_ch,
+            )
         });
         PacketHandlerThread {
             incoming_channel: read_from_incoming_ch,
             outgoing_channel: send_to_outgoing_ch,
+            new_connections_channel: read_from_new_conn_ch,
             sockets: Vec::with_capacity(socket_capacity),
         }
     }
 
+    /// Listens for UDP clients on the given address, the new clients can be queried
+    /// by `receive_new_connections_into`
+    pub fn bind_udp(&mut self, addr: SocketAddr) -> Result<(), std::io::Error> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        self.outgoing_channel
+            .send(NetworkTrafficHandlerMsg::BindUdp(socket))
+            .unwrap();
+        Ok(())
+    }
+
+    pub fn connect_udp(&mut self, server_addr: SocketAddr) -> Result<SocketId, std::io::Error> {
+        let socket = UdpSocket::bind(SocketAddr::from(([0, 0, 0, 0], 0)))?;
+        socket.set_nonblocking(true)?;
+        let id = SocketId(self.sockets.len());
+        self.sockets.push(Some(()));
+        self.outgoing_channel
+            .send(NetworkTrafficHandlerMsg::ConnectUdp(
+                id,
+                socket,
+                server_addr,
+            ))
+            .unwrap();
+        return Ok(id);
+    }
+
+    pub fn set_network_simulation(&self, config: NetworkSimulationConfig) {
+        self.outgoing_channel
+            .send(NetworkTrafficHandlerMsg::SetNetworkSimulation(config))
+            .unwrap();
+    }
+
+    pub fn receive_new_connections_into(&self, out: &mut Vec<(SocketId, SocketAddr)>) {
+        while let Ok(id_and_addr) = self.new_connections_channel.try_recv() {
+            out.push(id_and_addr);
+        }
+    }
+
     pub fn handle_socket(&mut self, socket_stream: TcpStream) -> SocketId {
         socket_stream.set_nonblocking(true);
         socket_stream.set_nodelay(true);
@@ -330,22 +480,34 @@ where
 #[derive(Debug)]
 enum NetworkTrafficHandlerMsg<O>
 where
-    O: Send + Packet + Debug + 'static,
+    O: Send + Packet + Clone + Debug + 'static,
 {
     NewConnection(SocketId, TcpStream),
+    BindUdp(UdpSocket),
+    ConnectUdp(SocketId, UdpSocket, SocketAddr),
     RemoveConnection(SocketId),
     SendPacket(SocketId, O),
+    SetNetworkSimulation(NetworkSimulationConfig),
 }
 
 fn network_traffic_handler<I, O>(
     send_to_incoming_ch: std::sync::mpsc::Sender<(SocketId, I)>,
     read_from_outgoing_ch: std::sync::mpsc::Receiver<NetworkTrafficHandlerMsg<O>>,
+    send_to_new_conn_ch: std::sync::mpsc::Sender<(SocketId, SocketAddr)>,
 ) where
-    I: Send + Packet + Debug + 'static,
-    O: Send + Packet + Debug + 'static,
+    I: Send + Packet + Clone + Debug + 'static,
+    O: Send + Packet + Clone + Debug + 'static,
 {
     let mut sockets: Vec<Option<RemoteSocket>> = Vec::with_capacity(64);
+    let mut udp_endpoint: Option<UdpEndpoint> = None;
+    let mut network_simulation = NetworkSimulationConfig::default();
+    // only for the tcp sockets, the udp endpoint conditions the datagrams itself
+    let mut outgoing_conditioner = LinkConditioner::<(SocketId, O)>::new(Default::default(), 0);
+    let mut incoming_conditioner = LinkConditioner::<(SocketId, I)>::new(Default::default(), 0);
+    let mut outgoing_packets = Vec::with_capacity(64);
+    let mut incoming_packets = Vec::with_capacity(64);
     loop {
+        let now = Instant::now();
         let command = read_from_outgoing_ch.try_recv();
         match command {
             Ok(NetworkTrafficHandlerMsg::NewConnection(socket_id, socket_stream)) => {
@@ -356,18 +518,56 @@ fn network_traffic_handler<I, O>(
                     sockets[socket_id.0] = Some(RemoteSocket::new(socket_stream));
                 }
             }
+            Ok(NetworkTrafficHandlerMsg::BindUdp(socket)) => {
+                log::info!("listening for udp clients");
+                let mut endpoint = UdpEndpoint::new(socket, true);
+                endpoint.set_network_simulation(&network_simulation);
+                udp_endpoint = Some(endpoint);
+            }
+            Ok(NetworkTrafficHandlerMsg::ConnectUdp(socket_id, socket, server_addr)) => {
+                log::info!("adding new udp connection: {:?}", socket_id);
+                let mut endpoint = UdpEndpoint::new(socket, false);
+                endpoint.set_network_simulation(&network_simulation);
+                endpoint.add_connection(socket_id, server_addr);
+                udp_endpoint = Some(endpoint);
+            }
             Ok(NetworkTrafficHandlerMsg::RemoveConnection(socket_id)) => {
-                sockets[socket_id.0] = None;
+                if let Some(socket) = sockets.get_mut(socket_id.0) {
+                    *socket = None;
+                }
+                if let Some(udp_endpoint) = &mut udp_endpoint {
+                    udp_endpoint.remove_connection(socket_id);
+                }
             }
             Ok(NetworkTrafficHandlerMsg::SendPacket(socket_id, packet)) => {
-                if let Some(socket) = sockets[socket_id.0].as_mut() {
-                    let socket_buffer = &mut socket.out_buff;
+                if let Some(Some(_)) = sockets.get(socket_id.0) {
+                    outgoing_conditioner.push(now, (socket_id, packet));
+                } else if let Some(udp_endpoint) = &mut udp_endpoint {
                     log::trace!("Outgoing Packet: {:?}", packet);
-                    packet.write_into(socket_buffer);
+                    udp_endpoint.send_packet(socket_id, &packet);
                 }
             }
+            Ok(NetworkTrafficHandlerMsg::SetNetworkSimulation(config)) => {
+                log::info!("network simulation: {:?}", config);
+                // a tcp stream can't lose or reorder packets, only the delay is simulated on it
+                outgoing_conditioner.set_config(config.outgoing.delay_only(), config.seed);
+                // the directions must not produce the same sequence
+                incoming_conditioner
+                    .set_config(config.incoming.delay_only(), config.seed.wrapping_add(1));
+                if let Some(udp_endpoint) = &mut udp_endpoint {
+                    udp_endpoint.set_network_simulation(&config);
+                }
+                network_simulation = config;
+            }
             Err(e) => {}
         }
+        outgoing_conditioner.pop_ready(now, &mut outgoing_packets);
+        for (socket_id, packet) in outgoing_packets.drain(..) {
+            log::trace!("Outgoing Packet: {:?}", packet);
+            if let Some(Some(socket)) = sockets.get_mut(socket_id.0) {
+                socket.out_buff.write_packet(&packet);
+            }
+        }
         for i in 0..sockets.len() {
             if let Some(ref mut socket) = &mut sockets[i] {
                 if !socket.out_buff.eof() {
@@ -415,10 +615,10 @@ fn network_traffic_handler<I, O>(
 
                 let socket_id = SocketId(i);
                 while !socket.in_buff.eof() {
-                    match I::read_from(&mut socket.in_buff) {
+                    match socket.in_buff.read_packet::<I>() {
                         Ok(packet) => {
                             log::trace!("Incoming Packet: {:?}", packet);
-                            send_to_incoming_ch.send((socket_id, packet));
+                            incoming_packets.push((socket_id, packet));
                         }
                         Err(err) => {
                             match err {
@@ -428,6 +628,8 @@ fn network_traffic_handler<I, O>(
                                 PacketReadErr::InvalidValues => {
                                     log::error!("Socket({}) sent invalid values, close it", i);
                                     sockets[i] = None;
+                                    send_to_incoming_ch
+                                        .send((socket_id, I::new_error_packet(None)));
                                 }
                             }
                             continue 'sockets_loop;
@@ -440,6 +642,86 @@ fn network_traffic_handler<I, O>(
                 }
             }
         }
+        for id_and_packet in incoming_packets.drain(..) {
+            incoming_conditioner.push(now, id_and_packet);
+        }
+        incoming_conditioner.pop_ready(now, &mut incoming_packets);
+        if let Some(udp_endpoint) = &mut udp_endpoint {
+            udp_endpoint.update(
+                &mut incoming_packets,
+                &send_to_incoming_ch,
+                &send_to_new_conn_ch,
+            );
+        }
+        for id_and_packet in incoming_packets.drain(..) {
+            send_to_incoming_ch.send(id_and_packet);
+        }
         std::thread::sleep(Duration::from_millis(10));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::handshake::PROTOCOL_VERSION;
+    use crate::packets::to_server::ToServerPacket;
+
+    #[test]
+    fn partial_frame_is_not_consumed() {
+        let mut sender = SocketBuffer::new();
+        sender.write_packet(&ToServerPacket::Welcome {
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: 0,
+            config_hash: 0,
+            name: "x".repeat(INITIAL_BUFFER_SIZE * 2),
+        });
+        sender.write_packet(&ToServerPacket::Ping);
+        let data = sender.outgoing_data().to_vec();
+
+        let mut receiver = SocketBuffer::new();
+        receiver.fill_from(&data[0..10]);
+        assert!(match receiver.read_packet::<ToServerPacket>() {
+            Err(PacketReadErr::NotEnoughBytes) => true,
+            _ => false,
+        });
+        receiver.fill_from(&data);
+        assert!(match receiver.read_packet::<ToServerPacket>() {
+            Ok(ToServerPacket::Welcome { name, .. }) => name.len() == INITIAL_BUFFER_SIZE * 2,
+            _ => false,
+        });
+        assert!(match receiver.read_packet::<ToServerPacket>() {
+            Ok(ToServerPacket::Ping) => true,
+            _ => false,
+        });
+        assert!(receiver.eof());
+    }
+
+    #[test]
+    fn welcome_of_other_protocol_version_is_recognized() {
+        let mut sender = SocketBuffer::new();
+        // a future Welcome whose fields after the version can't be decoded
+        sender.write_u32(0);
+        sender.write_u32(1);
+        sender.write_u32(PROTOCOL_VERSION + 1);
+        sender.write_u8(0xFF);
+        let len = (sender.outgoing_data().len() - FRAME_HEADER_SIZE) as u32;
+        let mut data = sender.outgoing_data().to_vec();
+        data[0..FRAME_HEADER_SIZE].copy_from_slice(&len.to_le_bytes());
+        let mut ping = SocketBuffer::new();
+        ping.write_packet(&ToServerPacket::Ping);
+        data.extend_from_slice(ping.outgoing_data());
+
+        let mut receiver = SocketBuffer::new();
+        receiver.fill_from(&data);
+        assert!(match receiver.read_packet::<ToServerPacket>() {
+            Ok(ToServerPacket::IncompatibleWelcome { protocol_version }) => {
+                protocol_version == PROTOCOL_VERSION + 1
+            }
+            _ => false,
+        });
+        assert!(match receiver.read_packet::<ToServerPacket>() {
+            Ok(ToServerPacket::Ping) => true,
+            _ => false,
+        });
+    }
+}
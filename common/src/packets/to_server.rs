@@ -1,9 +1,10 @@
 use crate::components::controller::PlayerIntention;
-use crate::packets::SocketBuffer;
+use crate::packets::handshake::PROTOCOL_VERSION;
+use crate::packets::{Channel, SocketBuffer, MAX_PACKET_SIZE};
 use crate::serde_remote::MyIoErrorKind;
 use serde::Deserialize;
 use serde::Serialize;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
 use strum_macros::EnumCount;
 use strum_macros::EnumDiscriminants;
 
@@ -11,29 +12,65 @@ pub trait Packet: Sized {
     fn write_into(&self, buf: &mut SocketBuffer);
     fn read_from(buf: &mut SocketBuffer) -> Result<Self, PacketReadErr>;
     fn new_error_packet(e: Option<std::io::Error>) -> Self;
+    fn channel(&self) -> Channel;
 }
 
-#[derive(Debug, EnumDiscriminants, EnumCount, Serialize, Deserialize)]
+#[derive(Debug, Clone, EnumDiscriminants, EnumCount, Serialize, Deserialize)]
 pub enum ToServerPacket {
     LocalError(Option<MyIoErrorKind>),
+    /// It must remain the second variant with the protocol version as its first field,
+    /// so the server can tell the user if the versions differ
     Welcome {
+        protocol_version: u32,
+        capabilities: u32,
+        /// see handshake::skills_config_hash
+        config_hash: u64,
         name: String,
         //        job: JobId
     },
     Ping,
-    ReadyForGame,
+    ReadyForGame {
+        /// see handshake::map_hash, the client knows the map only after Init
+        map_hash: u64,
+    },
     Intention {
         cid: u32,
         client_tick: u64,
+        /// the client sees the other chars this much behind its own tick
+        interpolation_delay_ms: u32,
         intention: PlayerIntention,
     },
+    /// The client received the snapshot of this tick, so it can be used as a baseline
+    SnapshotAck {
+        ack_tick: u64,
+    },
+    /// Never sent, it is created instead of a Welcome from a different protocol version,
+    /// whose other fields might not be decodable
+    IncompatibleWelcome {
+        protocol_version: u32,
+    },
 }
 
+/// bincode writes the variant index as u32, followed by the fields
+const WELCOME_VARIANT_INDEX: u32 = 1;
+
+#[derive(Debug)]
 pub enum PacketReadErr {
     NotEnoughBytes,
     InvalidValues,
 }
 
+impl From<bincode::Error> for PacketReadErr {
+    fn from(e: bincode::Error) -> Self {
+        match *e {
+            bincode::ErrorKind::Io(ref io_err) if io_err.kind() == ErrorKind::UnexpectedEof => {
+                PacketReadErr::NotEnoughBytes
+            }
+            _ => PacketReadErr::InvalidValues,
+        }
+    }
+}
+
 impl Packet for ToServerPacket {
     fn write_into(&self, buf: &mut SocketBuffer) {
         bincode::serialize_into(buf, self);
@@ -71,10 +108,17 @@ impl Packet for ToServerPacket {
         //        };
     }
     fn read_from(buf: &mut SocketBuffer) -> Result<ToServerPacket, PacketReadErr> {
-        return match bincode::deserialize_from(buf) {
-            Ok(packet) => Ok(packet),
-            Err(e) => Err(PacketReadErr::NotEnoughBytes),
-        };
+        if buf.peek_u32_at(0) == Some(WELCOME_VARIANT_INDEX) {
+            if let Some(protocol_version) = buf.peek_u32_at(4) {
+                if protocol_version != PROTOCOL_VERSION {
+                    buf.skip_remaining();
+                    return Ok(ToServerPacket::IncompatibleWelcome { protocol_version });
+                }
+            }
+        }
+        return Ok(bincode::config()
+            .limit(MAX_PACKET_SIZE as u64)
+            .deserialize_from(buf)?);
         //        let packet_id = buf.read_u8() - 1;
         //        if packet_id >= ToServerPacket::count() as u8 {
         //            return Err(PacketReadErr::InvalidValues);
@@ -115,4 +159,16 @@ impl Packet for ToServerPacket {
     fn new_error_packet(e: Option<Error>) -> ToServerPacket {
         ToServerPacket::LocalError(e.map(|it| unsafe { std::mem::transmute(it.kind()) }))
     }
+
+    fn channel(&self) -> Channel {
+        match self {
+            ToServerPacket::LocalError(_)
+            | ToServerPacket::Welcome { .. }
+            | ToServerPacket::IncompatibleWelcome { .. }
+            | ToServerPacket::ReadyForGame { .. } => Channel::ReliableOrdered,
+            ToServerPacket::Ping
+            | ToServerPacket::Intention { .. }
+            | ToServerPacket::SnapshotAck { .. } => Channel::UnreliableSequenced,
+        }
+    }
 }
@@ -0,0 +1,927 @@
+use crate::packets::link_conditioner::{LinkConditioner, NetworkSimulationConfig};
+use crate::packets::to_server::Packet;
+use crate::packets::{Channel, SocketBuffer, SocketId, MAX_PACKET_SIZE};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use serde::export::fmt::Debug;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, VecDeque};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::io::{Cursor, Error, ErrorKind};
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+const RESEND_INTERVAL: Duration = Duration::from_millis(100);
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+/// Datagrams bigger than this might be fragmented or dropped by the routers
+const MAX_SAFE_DATAGRAM_SIZE: usize = 1200;
+
+/// kind (u8), reliable ack (u32), sequence (u32), fragment index (u8), fragment count (u8)
+const HEADER_SIZE: usize = 11;
+const MAX_FRAGMENT_PAYLOAD_SIZE: usize = MAX_SAFE_DATAGRAM_SIZE - HEADER_SIZE;
+const MAX_FRAGMENT_COUNT: usize = 255;
+/// Reliable packets further ahead than this are dropped instead of being buffered,
+/// the sender resends them anyway
+const RELIABLE_WINDOW_SIZE: u32 = 1024;
+/// The handshake datagrams carry only the cookie
+const COOKIE_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DatagramKind {
+    Unreliable = 0,
+    Reliable = 1,
+    /// Sent when there was no outgoing packet to piggyback the reliable ack on
+    AckOnly = 2,
+    /// The client asks for a cookie, padded to the size of the challenge so the server
+    /// can't be used for amplifying traffic
+    ConnectRequest = 3,
+    /// The server sends the cookie derived from the address of the client, without allocating
+    /// anything for it
+    ConnectChallenge = 4,
+    /// The client echoes the cookie, which proves that it owns the address
+    ConnectResponse = 5,
+}
+
+impl DatagramKind {
+    fn from_u8(value: u8) -> Option<DatagramKind> {
+        match value {
+            0 => Some(DatagramKind::Unreliable),
+            1 => Some(DatagramKind::Reliable),
+            2 => Some(DatagramKind::AckOnly),
+            3 => Some(DatagramKind::ConnectRequest),
+            4 => Some(DatagramKind::ConnectChallenge),
+            5 => Some(DatagramKind::ConnectResponse),
+            _ => None,
+        }
+    }
+}
+
+/// The datagrams with their remote address
+type DatagramConditioner = LinkConditioner<(SocketAddr, Vec<u8>)>;
+
+/// Where the client is in the connection handshake, the connections on the server side
+/// are created only after it has finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Handshake {
+    Requesting,
+    Responding { cookie: u64 },
+    Done,
+}
+
+/// Packets bigger than MAX_FRAGMENT_PAYLOAD_SIZE are sent in multiple datagrams.
+/// Unreliable fragments share the same sequence number, reliable ones have consecutive ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Fragment {
+    index: u8,
+    count: u8,
+}
+
+impl Fragment {
+    const WHOLE: Fragment = Fragment { index: 0, count: 1 };
+
+    fn is_last(&self) -> bool {
+        self.index + 1 == self.count
+    }
+}
+
+struct UnackedPacket {
+    seq: u32,
+    fragment: Fragment,
+    payload: Vec<u8>,
+    last_sent_at: Instant,
+}
+
+/// The channel states of a single remote peer.
+/// Sequence numbers are not wrapped around, at 30 packets/sec u32 lasts for years.
+struct UdpConnection {
+    addr: SocketAddr,
+    handshake: Handshake,
+    last_handshake_sent_at: Option<Instant>,
+    last_received_at: Instant,
+    next_unreliable_seq: u32,
+    last_received_unreliable_seq: Option<u32>,
+    next_reliable_seq: u32,
+    unacked_packets: VecDeque<UnackedPacket>,
+    // every reliable packet below it has been received
+    next_expected_reliable_seq: u32,
+    out_of_order_packets: BTreeMap<u32, (Fragment, Vec<u8>)>,
+    ack_pending: bool,
+    // the fragments of the packet which are being reassembled
+    unreliable_fragments: Option<(u32, Vec<Option<Vec<u8>>>)>,
+    reliable_fragments: Vec<u8>,
+}
+
+impl UdpConnection {
+    fn new(addr: SocketAddr, handshake: Handshake) -> UdpConnection {
+        UdpConnection {
+            addr,
+            handshake,
+            last_handshake_sent_at: None,
+            last_received_at: Instant::now(),
+            next_unreliable_seq: 0,
+            last_received_unreliable_seq: None,
+            next_reliable_seq: 0,
+            unacked_packets: VecDeque::with_capacity(32),
+            next_expected_reliable_seq: 0,
+            out_of_order_packets: BTreeMap::new(),
+            ack_pending: false,
+            unreliable_fragments: None,
+            reliable_fragments: Vec::new(),
+        }
+    }
+
+    /// Returns the (reassembled) payloads which can be delivered to the application in order
+    fn process_datagram(
+        &mut self,
+        kind: DatagramKind,
+        ack: u32,
+        seq: u32,
+        fragment: Fragment,
+        payload: &[u8],
+    ) -> Vec<Vec<u8>> {
+        self.last_received_at = Instant::now();
+        while self
+            .unacked_packets
+            .front()
+            .map(|it| it.seq < ack)
+            .unwrap_or(false)
+        {
+            self.unacked_packets.pop_front();
+        }
+        let mut deliverable = Vec::new();
+        if fragment.count == 0 || fragment.index >= fragment.count {
+            log::warn!("Invalid fragment from {}: {:?}", self.addr, fragment);
+            return deliverable;
+        }
+        match kind {
+            DatagramKind::AckOnly
+            | DatagramKind::ConnectRequest
+            | DatagramKind::ConnectChallenge
+            | DatagramKind::ConnectResponse => {}
+            DatagramKind::Unreliable => {
+                let is_old = self
+                    .last_received_unreliable_seq
+                    .map(|last| seq <= last)
+                    .unwrap_or(false);
+                if is_old {
+                    log::trace!("Dropping old unreliable packet {}", seq);
+                } else if let Some(payload) = self.add_unreliable_fragment(seq, fragment, payload) {
+                    self.last_received_unreliable_seq = Some(seq);
+                    deliverable.push(payload);
+                }
+            }
+            DatagramKind::Reliable => {
+                // duplicates have to be acked as well, our previous ack might have been lost
+                self.ack_pending = true;
+                if seq == self.next_expected_reliable_seq {
+                    self.next_expected_reliable_seq += 1;
+                    deliverable.extend(self.add_reliable_fragment(fragment, payload.to_vec()));
+                    while let Some((fragment, next)) = self
+                        .out_of_order_packets
+                        .remove(&self.next_expected_reliable_seq)
+                    {
+                        self.next_expected_reliable_seq += 1;
+                        deliverable.extend(self.add_reliable_fragment(fragment, next));
+                    }
+                } else if seq > self.next_expected_reliable_seq {
+                    if seq - self.next_expected_reliable_seq < RELIABLE_WINDOW_SIZE {
+                        self.out_of_order_packets
+                            .insert(seq, (fragment, payload.to_vec()));
+                    } else {
+                        log::trace!("Reliable packet {} is outside of the window", seq);
+                    }
+                }
+            }
+        }
+        return deliverable;
+    }
+
+    fn add_unreliable_fragment(
+        &mut self,
+        seq: u32,
+        fragment: Fragment,
+        payload: &[u8],
+    ) -> Option<Vec<u8>> {
+        if fragment == Fragment::WHOLE {
+            return Some(payload.to_vec());
+        }
+        let is_older_than_pending = self
+            .unreliable_fragments
+            .as_ref()
+            .map(|(pending_seq, _)| seq < *pending_seq)
+            .unwrap_or(false);
+        if is_older_than_pending {
+            return None;
+        }
+        let is_new_packet = self
+            .unreliable_fragments
+            .as_ref()
+            .map(|(pending_seq, fragments)| {
+                *pending_seq != seq || fragments.len() != fragment.count as usize
+            })
+            .unwrap_or(true);
+        if is_new_packet {
+            // an older, incomplete packet is dropped
+            self.unreliable_fragments = Some((seq, vec![None; fragment.count as usize]));
+        }
+        let (_seq, fragments) = self.unreliable_fragments.as_mut().unwrap();
+        fragments[fragment.index as usize] = Some(payload.to_vec());
+        if fragments.iter().all(|it| it.is_some()) {
+            let (_seq, fragments) = self.unreliable_fragments.take().unwrap();
+            return Some(fragments.into_iter().flat_map(|it| it.unwrap()).collect());
+        }
+        return None;
+    }
+
+    /// The reliable fragments arrive in order, so they only have to be concatenated
+    fn add_reliable_fragment(&mut self, fragment: Fragment, payload: Vec<u8>) -> Option<Vec<u8>> {
+        if fragment.index == 0 {
+            self.reliable_fragments.clear();
+        }
+        if fragment == Fragment::WHOLE {
+            return Some(payload);
+        }
+        if self.reliable_fragments.len() + payload.len() > MAX_PACKET_SIZE {
+            log::warn!("Too big reliable packet from {}, dropping it", self.addr);
+            self.reliable_fragments.clear();
+            return None;
+        }
+        self.reliable_fragments.extend_from_slice(&payload);
+        if fragment.is_last() {
+            return Some(std::mem::replace(&mut self.reliable_fragments, Vec::new()));
+        }
+        return None;
+    }
+}
+
+/// A single UDP socket multiplexing the connections to the remote peers.
+/// The server accepts new peers when they have echoed back their cookie, the client has only
+/// the server as a peer.
+pub struct UdpEndpoint {
+    socket: UdpSocket,
+    accept_new_connections: bool,
+    // randomly keyed, so the cookies can't be computed by the clients
+    cookie_secret: RandomState,
+    // the network simulation works on the datagrams, so the reliable channel can recover
+    // from the lost and reordered ones like on a real network
+    outgoing_conditioner: DatagramConditioner,
+    incoming_conditioner: DatagramConditioner,
+    tmp_datagrams: Vec<(SocketAddr, Vec<u8>)>,
+    connections: Vec<Option<UdpConnection>>,
+    recv_buf: [u8; 2048],
+    tmp_buf: SocketBuffer,
+}
+
+impl UdpEndpoint {
+    pub fn new(socket: UdpSocket, accept_new_connections: bool) -> UdpEndpoint {
+        UdpEndpoint {
+            socket,
+            accept_new_connections,
+            cookie_secret: RandomState::new(),
+            outgoing_conditioner: LinkConditioner::new(Default::default(), 0),
+            incoming_conditioner: LinkConditioner::new(Default::default(), 0),
+            tmp_datagrams: Vec::with_capacity(64),
+            connections: Vec::with_capacity(64),
+            recv_buf: [0; 2048],
+            tmp_buf: SocketBuffer::new(),
+        }
+    }
+
+    pub fn set_network_simulation(&mut self, config: &NetworkSimulationConfig) {
+        self.outgoing_conditioner
+            .set_config(config.outgoing.clone(), config.seed);
+        // the directions must not produce the same sequence
+        self.incoming_conditioner
+            .set_config(config.incoming.clone(), config.seed.wrapping_add(1));
+    }
+
+    /// The connection starts with the handshake, the packets are sent only after the server
+    /// has accepted it
+    pub fn add_connection(&mut self, socket_id: SocketId, addr: SocketAddr) {
+        self.insert_connection(socket_id, UdpConnection::new(addr, Handshake::Requesting));
+    }
+
+    fn insert_connection(&mut self, socket_id: SocketId, conn: UdpConnection) {
+        let index = socket_id.as_usize();
+        while self.connections.len() <= index {
+            self.connections.push(None);
+        }
+        self.connections[index] = Some(conn);
+    }
+
+    fn cookie_of(&self, addr: SocketAddr) -> u64 {
+        let mut hasher = self.cookie_secret.build_hasher();
+        addr.hash(&mut hasher);
+        return hasher.finish();
+    }
+
+    pub fn remove_connection(&mut self, socket_id: SocketId) {
+        if let Some(conn) = self.connections.get_mut(socket_id.as_usize()) {
+            *conn = None;
+        }
+    }
+
+    pub fn send_packet<O>(&mut self, socket_id: SocketId, packet: &O)
+    where
+        O: Packet + Debug,
+    {
+        let conn = match self
+            .connections
+            .get_mut(socket_id.as_usize())
+            .and_then(|it| it.as_mut())
+        {
+            Some(conn) => conn,
+            None => return,
+        };
+        self.tmp_buf.reset();
+        packet.write_into(&mut self.tmp_buf);
+        let payload = self.tmp_buf.outgoing_data();
+        let chunks: Vec<&[u8]> = if payload.is_empty() {
+            vec![payload]
+        } else {
+            payload.chunks(MAX_FRAGMENT_PAYLOAD_SIZE).collect()
+        };
+        if chunks.len() > MAX_FRAGMENT_COUNT {
+            log::error!("Too big packet ({} bytes): {:?}", payload.len(), packet);
+            return;
+        }
+        let count = chunks.len() as u8;
+        match packet.channel() {
+            Channel::UnreliableSequenced => {
+                let seq = conn.next_unreliable_seq;
+                conn.next_unreliable_seq += 1;
+                for (index, chunk) in chunks.into_iter().enumerate() {
+                    let fragment = Fragment {
+                        index: index as u8,
+                        count,
+                    };
+                    UdpEndpoint::send_datagram(
+                        &mut self.outgoing_conditioner,
+                        conn,
+                        DatagramKind::Unreliable,
+                        seq,
+                        fragment,
+                        chunk,
+                    );
+                }
+            }
+            Channel::ReliableOrdered => {
+                for (index, chunk) in chunks.into_iter().enumerate() {
+                    let seq = conn.next_reliable_seq;
+                    conn.next_reliable_seq += 1;
+                    let fragment = Fragment {
+                        index: index as u8,
+                        count,
+                    };
+                    UdpEndpoint::send_datagram(
+                        &mut self.outgoing_conditioner,
+                        conn,
+                        DatagramKind::Reliable,
+                        seq,
+                        fragment,
+                        chunk,
+                    );
+                    conn.unacked_packets.push_back(UnackedPacket {
+                        seq,
+                        fragment,
+                        payload: chunk.to_vec(),
+                        last_sent_at: Instant::now(),
+                    });
+                }
+            }
+        }
+        self.flush_outgoing_datagrams(Instant::now());
+    }
+
+    /// Every datagram carries the reliable ack of the connection
+    fn send_datagram(
+        outgoing: &mut DatagramConditioner,
+        conn: &mut UdpConnection,
+        kind: DatagramKind,
+        seq: u32,
+        fragment: Fragment,
+        payload: &[u8],
+    ) {
+        if conn.handshake != Handshake::Done {
+            // the server would drop it, the reliable ones are resent after the handshake
+            return;
+        }
+        let mut datagram = Vec::with_capacity(HEADER_SIZE + payload.len());
+        datagram.write_u8(kind as u8).unwrap();
+        datagram
+            .write_u32::<LittleEndian>(conn.next_expected_reliable_seq)
+            .unwrap();
+        datagram.write_u32::<LittleEndian>(seq).unwrap();
+        datagram.write_u8(fragment.index).unwrap();
+        datagram.write_u8(fragment.count).unwrap();
+        datagram.extend_from_slice(payload);
+        outgoing.push(Instant::now(), (conn.addr, datagram));
+        conn.ack_pending = false;
+    }
+
+    fn send_handshake_datagram(
+        outgoing: &mut DatagramConditioner,
+        addr: SocketAddr,
+        kind: DatagramKind,
+        cookie: u64,
+    ) {
+        let mut datagram = Vec::with_capacity(HEADER_SIZE + COOKIE_SIZE);
+        datagram.write_u8(kind as u8).unwrap();
+        datagram.write_u32::<LittleEndian>(0).unwrap();
+        datagram.write_u32::<LittleEndian>(0).unwrap();
+        datagram.write_u8(Fragment::WHOLE.index).unwrap();
+        datagram.write_u8(Fragment::WHOLE.count).unwrap();
+        datagram.write_u64::<LittleEndian>(cookie).unwrap();
+        outgoing.push(Instant::now(), (addr, datagram));
+    }
+
+    /// Sends the datagrams whose simulated delay has expired
+    fn flush_outgoing_datagrams(&mut self, now: Instant) {
+        self.outgoing_conditioner
+            .pop_ready(now, &mut self.tmp_datagrams);
+        for (addr, datagram) in self.tmp_datagrams.drain(..) {
+            match self.socket.send_to(&datagram, addr) {
+                Ok(_) => {}
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                    // treated as a lost packet, reliable ones will be resent
+                }
+                Err(e) => log::error!("Error during sending to {}: {}", addr, e),
+            }
+        }
+    }
+
+    /// The client repeats its last handshake datagram until the server accepts it
+    fn continue_handshake(
+        outgoing: &mut DatagramConditioner,
+        conn: &mut UdpConnection,
+        now: Instant,
+    ) {
+        let (kind, cookie) = match conn.handshake {
+            Handshake::Requesting => (DatagramKind::ConnectRequest, 0),
+            Handshake::Responding { cookie } => (DatagramKind::ConnectResponse, cookie),
+            Handshake::Done => return,
+        };
+        let should_send = conn
+            .last_handshake_sent_at
+            .map(|it| now.duration_since(it) >= RESEND_INTERVAL)
+            .unwrap_or(true);
+        if should_send {
+            conn.last_handshake_sent_at = Some(now);
+            UdpEndpoint::send_handshake_datagram(outgoing, conn.addr, kind, cookie);
+        }
+    }
+
+    /// Returns the connection which the datagram belongs to, if it is not a handshake datagram
+    fn process_handshake(
+        &mut self,
+        kind: DatagramKind,
+        addr: SocketAddr,
+        payload: &[u8],
+        send_to_new_conn_ch: &Sender<(SocketId, SocketAddr)>,
+    ) -> Option<SocketId> {
+        let socket_id = self.find_connection(addr);
+        let cookie = if payload.len() >= COOKIE_SIZE {
+            Cursor::new(payload).read_u64::<LittleEndian>().ok()
+        } else {
+            None
+        };
+        match kind {
+            DatagramKind::ConnectRequest => {
+                // the request without padding is ignored
+                if self.accept_new_connections && cookie.is_some() {
+                    let cookie = self.cookie_of(addr);
+                    UdpEndpoint::send_handshake_datagram(
+                        &mut self.outgoing_conditioner,
+                        addr,
+                        DatagramKind::ConnectChallenge,
+                        cookie,
+                    );
+                }
+                return None;
+            }
+            DatagramKind::ConnectChallenge => {
+                let connections = &mut self.connections;
+                let conn = socket_id
+                    .and_then(|it| connections[it.as_usize()].as_mut())
+                    .filter(|conn| conn.handshake == Handshake::Requesting);
+                if let (Some(conn), Some(cookie)) = (conn, cookie) {
+                    conn.handshake = Handshake::Responding { cookie };
+                    conn.last_handshake_sent_at = None;
+                    UdpEndpoint::continue_handshake(
+                        &mut self.outgoing_conditioner,
+                        conn,
+                        Instant::now(),
+                    );
+                }
+                return None;
+            }
+            DatagramKind::ConnectResponse => {
+                if !self.accept_new_connections || cookie != Some(self.cookie_of(addr)) {
+                    log::warn!("Invalid cookie from {}", addr);
+                    return None;
+                }
+                let socket_id = match socket_id {
+                    Some(socket_id) => socket_id,
+                    None => {
+                        let socket_id = self.next_free_socket_id();
+                        log::info!("adding new udp connection: {:?} ({})", socket_id, addr);
+                        self.insert_connection(
+                            socket_id,
+                            UdpConnection::new(addr, Handshake::Done),
+                        );
+                        if let Err(e) = send_to_new_conn_ch.send((socket_id, addr)) {
+                            log::error!("The new connection could not be reported: {}", e);
+                        }
+                        socket_id
+                    }
+                };
+                // the ack tells the client that it has been accepted
+                let conn = self.connections[socket_id.as_usize()].as_mut().unwrap();
+                conn.last_received_at = Instant::now();
+                conn.ack_pending = true;
+                return None;
+            }
+            DatagramKind::Unreliable | DatagramKind::Reliable | DatagramKind::AckOnly => {
+                let connections = &mut self.connections;
+                let conn = socket_id.and_then(|it| connections[it.as_usize()].as_mut());
+                match conn {
+                    Some(conn) => {
+                        // anything from the server means that the handshake has finished
+                        conn.handshake = Handshake::Done;
+                    }
+                    None => log::warn!("Datagram from unknown address: {}", addr),
+                }
+                return socket_id;
+            }
+        }
+    }
+
+    fn resend_unacked_packets(
+        outgoing: &mut DatagramConditioner,
+        conn: &mut UdpConnection,
+        now: Instant,
+    ) {
+        let to_resend: Vec<(u32, Fragment, Vec<u8>)> = conn
+            .unacked_packets
+            .iter_mut()
+            .filter(|it| now.duration_since(it.last_sent_at) >= RESEND_INTERVAL)
+            .map(|it| {
+                it.last_sent_at = now;
+                (it.seq, it.fragment, it.payload.clone())
+            })
+            .collect();
+        for (seq, fragment, payload) in to_resend {
+            log::trace!("Resending reliable packet {} to {}", seq, conn.addr);
+            UdpEndpoint::send_datagram(
+                outgoing,
+                conn,
+                DatagramKind::Reliable,
+                seq,
+                fragment,
+                &payload,
+            );
+        }
+    }
+
+    /// Receives the incoming datagrams into `received`, resends the unacknowledged reliable
+    /// packets and closes the timed out connections
+    pub fn update<I>(
+        &mut self,
+        received: &mut Vec<(SocketId, I)>,
+        send_to_incoming_ch: &Sender<(SocketId, I)>,
+        send_to_new_conn_ch: &Sender<(SocketId, SocketAddr)>,
+    ) where
+        I: Send + Packet + Debug + 'static,
+    {
+        let now = Instant::now();
+        loop {
+            let (len, addr) = match self.socket.recv_from(&mut self.recv_buf) {
+                Ok(len_and_addr) => len_and_addr,
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    // e.g. ICMP port unreachable on Windows, it is not fatal for the other peers
+                    log::error!("Error during udp socket reading: {}", e);
+                    break;
+                }
+            };
+            self.incoming_conditioner
+                .push(now, (addr, self.recv_buf[0..len].to_vec()));
+        }
+        let mut datagrams = std::mem::replace(&mut self.tmp_datagrams, Vec::new());
+        self.incoming_conditioner.pop_ready(now, &mut datagrams);
+        for (addr, datagram) in datagrams.drain(..) {
+            let len = datagram.len();
+            if len < HEADER_SIZE {
+                log::warn!("Too short datagram from {}", addr);
+                continue;
+            }
+            let (kind, ack, seq, fragment) = {
+                let mut header = Cursor::new(&datagram[0..HEADER_SIZE]);
+                let kind = DatagramKind::from_u8(header.read_u8().unwrap());
+                let ack = header.read_u32::<LittleEndian>().unwrap();
+                let seq = header.read_u32::<LittleEndian>().unwrap();
+                let fragment = Fragment {
+                    index: header.read_u8().unwrap(),
+                    count: header.read_u8().unwrap(),
+                };
+                match kind {
+                    Some(kind) => (kind, ack, seq, fragment),
+                    None => {
+                        log::warn!("Invalid datagram kind from {}", addr);
+                        continue;
+                    }
+                }
+            };
+            let payload = &datagram[HEADER_SIZE..len];
+            let socket_id = match self.process_handshake(kind, addr, payload, send_to_new_conn_ch) {
+                Some(socket_id) => socket_id,
+                None => continue,
+            };
+            let payloads = self.connections[socket_id.as_usize()]
+                .as_mut()
+                .unwrap()
+                .process_datagram(kind, ack, seq, fragment, payload);
+            for payload in payloads {
+                self.tmp_buf.fill_from(&payload);
+                match I::read_from(&mut self.tmp_buf) {
+                    Ok(packet) => {
+                        log::trace!("Incoming Packet: {:?}", packet);
+                        received.push((socket_id, packet));
+                    }
+                    Err(_) => {
+                        log::error!("Socket({:?}) sent an invalid packet", socket_id);
+                    }
+                }
+            }
+        }
+
+        self.tmp_datagrams = datagrams;
+
+        for i in 0..self.connections.len() {
+            let timed_out = match &mut self.connections[i] {
+                Some(conn) => {
+                    UdpEndpoint::continue_handshake(&mut self.outgoing_conditioner, conn, now);
+                    UdpEndpoint::resend_unacked_packets(&mut self.outgoing_conditioner, conn, now);
+                    if conn.ack_pending {
+                        UdpEndpoint::send_datagram(
+                            &mut self.outgoing_conditioner,
+                            conn,
+                            DatagramKind::AckOnly,
+                            0,
+                            Fragment::WHOLE,
+                            &[],
+                        );
+                    }
+                    now.duration_since(conn.last_received_at) >= CONNECTION_TIMEOUT
+                }
+                None => false,
+            };
+            if timed_out {
+                log::info!("Udp connection has timed out: {}", i);
+                self.connections[i] = None;
+                let error_packet = I::new_error_packet(Some(Error::from(ErrorKind::TimedOut)));
+                if let Err(e) = send_to_incoming_ch.send((SocketId(i), error_packet)) {
+                    log::error!("The timeout could not be reported: {}", e);
+                }
+            }
+        }
+        self.flush_outgoing_datagrams(Instant::now());
+    }
+
+    fn find_connection(&self, addr: SocketAddr) -> Option<SocketId> {
+        self.connections
+            .iter()
+            .position(|it| it.as_ref().map(|conn| conn.addr == addr).unwrap_or(false))
+            .map(SocketId)
+    }
+
+    fn next_free_socket_id(&self) -> SocketId {
+        let index = self
+            .connections
+            .iter()
+            .position(|it| it.is_none())
+            .unwrap_or(self.connections.len());
+        SocketId(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packets::link_conditioner::LinkConditionerConfig;
+    use crate::packets::to_server::ToServerPacket;
+    use std::sync::mpsc::channel;
+
+    fn conn() -> UdpConnection {
+        UdpConnection::new(SocketAddr::from(([127, 0, 0, 1], 6969)), Handshake::Done)
+    }
+
+    #[test]
+    fn reliable_packets_are_delivered_in_order() {
+        let mut conn = conn();
+        assert!(conn
+            .process_datagram(DatagramKind::Reliable, 0, 1, Fragment::WHOLE, &[1])
+            .is_empty());
+        assert_eq!(
+            conn.process_datagram(DatagramKind::Reliable, 0, 0, Fragment::WHOLE, &[0]),
+            vec![vec![0], vec![1]]
+        );
+        // duplicate
+        assert!(conn
+            .process_datagram(DatagramKind::Reliable, 0, 1, Fragment::WHOLE, &[1])
+            .is_empty());
+        assert_eq!(conn.next_expected_reliable_seq, 2);
+        assert!(conn.ack_pending);
+    }
+
+    #[test]
+    fn fragments_are_reassembled() {
+        let mut conn = conn();
+        let first = Fragment { index: 0, count: 2 };
+        let second = Fragment { index: 1, count: 2 };
+        assert!(conn
+            .process_datagram(DatagramKind::Unreliable, 0, 0, second, &[2])
+            .is_empty());
+        assert_eq!(
+            conn.process_datagram(DatagramKind::Unreliable, 0, 0, first, &[1]),
+            vec![vec![1, 2]]
+        );
+        assert!(conn
+            .process_datagram(DatagramKind::Reliable, 0, 1, second, &[4])
+            .is_empty());
+        assert_eq!(
+            conn.process_datagram(DatagramKind::Reliable, 0, 0, first, &[3]),
+            vec![vec![3, 4]]
+        );
+    }
+
+    #[test]
+    fn reliable_packets_outside_of_the_window_are_dropped() {
+        let mut conn = conn();
+        assert!(conn
+            .process_datagram(
+                DatagramKind::Reliable,
+                0,
+                RELIABLE_WINDOW_SIZE,
+                Fragment::WHOLE,
+                &[1]
+            )
+            .is_empty());
+        assert!(conn.out_of_order_packets.is_empty());
+        assert!(conn
+            .process_datagram(DatagramKind::Reliable, 0, 1, Fragment::WHOLE, &[1])
+            .is_empty());
+        assert_eq!(conn.out_of_order_packets.len(), 1);
+    }
+
+    #[test]
+    fn too_big_reliable_packets_are_dropped() {
+        let mut conn = conn();
+        let chunk = vec![0; MAX_FRAGMENT_PAYLOAD_SIZE];
+        // the last fragment never arrives, the first one is repeated instead
+        let mut delivered = Vec::new();
+        for seq in 0..(MAX_PACKET_SIZE / MAX_FRAGMENT_PAYLOAD_SIZE + 2) as u32 {
+            let fragment = Fragment {
+                index: 1,
+                count: 255,
+            };
+            delivered.extend(conn.process_datagram(
+                DatagramKind::Reliable,
+                0,
+                seq,
+                fragment,
+                &chunk,
+            ));
+            assert!(conn.reliable_fragments.len() <= MAX_PACKET_SIZE);
+        }
+        assert!(delivered.is_empty());
+    }
+
+    fn update_both(
+        client: &mut UdpEndpoint,
+        server: &mut UdpEndpoint,
+        received: &mut Vec<(SocketId, ToServerPacket)>,
+        new_conn_tx: &Sender<(SocketId, SocketAddr)>,
+    ) {
+        let (incoming_tx, _incoming_rx) = channel();
+        client.update(
+            &mut Vec::<(SocketId, ToServerPacket)>::new(),
+            &incoming_tx,
+            new_conn_tx,
+        );
+        server.update(received, &incoming_tx, new_conn_tx);
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    fn connected_endpoints(
+        new_conn_tx: &Sender<(SocketId, SocketAddr)>,
+    ) -> (UdpEndpoint, UdpEndpoint) {
+        let localhost = SocketAddr::from(([127, 0, 0, 1], 0));
+        let server_socket = UdpSocket::bind(localhost).unwrap();
+        server_socket.set_nonblocking(true).unwrap();
+        let server_addr = server_socket.local_addr().unwrap();
+        let mut server = UdpEndpoint::new(server_socket, true);
+        let client_socket = UdpSocket::bind(localhost).unwrap();
+        client_socket.set_nonblocking(true).unwrap();
+        let mut client = UdpEndpoint::new(client_socket, false);
+        client.add_connection(SocketId(0), server_addr);
+        for _ in 0..50 {
+            update_both(&mut client, &mut server, &mut Vec::new(), new_conn_tx);
+            if client.connections[0].as_ref().unwrap().handshake == Handshake::Done {
+                break;
+            }
+        }
+        return (client, server);
+    }
+
+    #[test]
+    fn connection_is_allocated_after_the_cookie_is_echoed() {
+        let (new_conn_tx, new_conn_rx) = channel();
+        let (client, server) = connected_endpoints(&new_conn_tx);
+        assert_eq!(
+            client.connections[0].as_ref().unwrap().handshake,
+            Handshake::Done
+        );
+        assert_eq!(new_conn_rx.try_iter().count(), 1);
+        assert_eq!(
+            server.connections.iter().filter(|it| it.is_some()).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn reliable_packets_survive_the_simulated_network() {
+        let (new_conn_tx, new_conn_rx) = channel();
+        let (mut client, mut server) = connected_endpoints(&new_conn_tx);
+        let server_side_id = new_conn_rx.try_recv().unwrap().0;
+        let lossy = LinkConditionerConfig {
+            delay_ms: 10,
+            jitter_ms: 10,
+            loss_percent: 30.0,
+            duplication_percent: 10.0,
+            reorder_percent: 30.0,
+        };
+        let config = NetworkSimulationConfig {
+            seed: 7,
+            incoming: lossy.clone(),
+            outgoing: lossy,
+        };
+        client.set_network_simulation(&config);
+        server.set_network_simulation(&config);
+        for map_hash in 0..20 {
+            client.send_packet(SocketId(0), &ToServerPacket::ReadyForGame { map_hash });
+        }
+        let mut received = Vec::new();
+        for _ in 0..400 {
+            update_both(&mut client, &mut server, &mut received, &new_conn_tx);
+            if received.len() >= 20 {
+                break;
+            }
+        }
+        let hashes: Vec<u64> = received
+            .iter()
+            .map(|(socket_id, packet)| {
+                assert_eq!(socket_id.0, server_side_id.0);
+                match packet {
+                    ToServerPacket::ReadyForGame { map_hash } => *map_hash,
+                    _ => panic!("Unexpected packet: {:?}", packet),
+                }
+            })
+            .collect();
+        assert_eq!(hashes, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unknown_addresses_do_not_get_a_connection() {
+        let socket = UdpSocket::bind(SocketAddr::from(([127, 0, 0, 1], 0))).unwrap();
+        let mut server = UdpEndpoint::new(socket, true);
+        let (new_conn_tx, new_conn_rx) = channel();
+        let addr = SocketAddr::from(([127, 0, 0, 1], 6969));
+        assert!(server
+            .process_handshake(DatagramKind::Reliable, addr, &[], &new_conn_tx)
+            .is_none());
+        assert!(server
+            .process_handshake(DatagramKind::ConnectResponse, addr, &[0; 8], &new_conn_tx)
+            .is_none());
+        assert!(server.connections.is_empty());
+        assert!(new_conn_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn old_unreliable_packets_are_dropped() {
+        let mut conn = conn();
+        assert_eq!(
+            conn.process_datagram(DatagramKind::Unreliable, 0, 5, Fragment::WHOLE, &[5])
+                .len(),
+            1
+        );
+        assert!(conn
+            .process_datagram(DatagramKind::Unreliable, 0, 3, Fragment::WHOLE, &[3])
+            .is_empty());
+        assert_eq!(
+            conn.process_datagram(DatagramKind::Unreliable, 0, 6, Fragment::WHOLE, &[6])
+                .len(),
+            1
+        );
+    }
+}
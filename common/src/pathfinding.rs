@@ -0,0 +1,247 @@
+use crate::common::{v2, Vec2};
+use crate::grf::gat::Gat;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// A cell of the Gat, (x, y)
+pub type Cell = (usize, usize);
+
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+/// The search gives up after this many cells and returns the path to the closest cell found so
+/// far, so an unreachable target can't freeze the simulation
+const MAX_SEARCHED_CELLS: usize = 20_000;
+/// The distance between the checked points when deciding whether a straight line is walkable
+const LINE_CHECK_STEP: f32 = 0.1;
+
+/// The world position is (x, -y) of the cell
+pub fn cell_of(gat: &Gat, pos: &Vec2) -> Option<Cell> {
+    let (x, y) = (pos.x, -pos.y);
+    if x < 0.0 || y < 0.0 || x >= gat.width as f32 || y >= gat.height as f32 {
+        return None;
+    }
+    return Some((x as usize, y as usize));
+}
+
+pub fn center_of(cell: Cell) -> Vec2 {
+    v2(cell.0 as f32 + 0.5, -(cell.1 as f32 + 0.5))
+}
+
+/// A* over the walkable cells of the Gat, the `blocked_cells` are avoided as well.
+///
+/// The returned waypoints are smoothed, a straight line between two consecutive waypoints is
+/// always walkable. The last one is `to` if it is reachable, otherwise the center of the
+/// reachable cell which is the closest to it.
+/// None if `from` or `to` is outside of the map.
+pub fn find_path(
+    gat: &Gat,
+    from: &Vec2,
+    to: &Vec2,
+    blocked_cells: &HashSet<Cell>,
+) -> Option<Vec<Vec2>> {
+    let start = cell_of(gat, from)?;
+    let goal = cell_of(gat, to)?;
+    let is_walkable = |cell: Cell| {
+        cell.0 < gat.width as usize
+            && gat.is_walkable(cell.0, cell.1)
+            && !blocked_cells.contains(&cell)
+    };
+    let heuristic = |cell: Cell| {
+        let dx = (cell.0 as i64 - goal.0 as i64).abs() as u32;
+        let dy = (cell.1 as i64 - goal.1 as i64).abs() as u32;
+        STRAIGHT_COST * (dx + dy) - (2 * STRAIGHT_COST - DIAGONAL_COST) * dx.min(dy)
+    };
+
+    // cell -> (cost from the start, previous cell)
+    let mut visited: HashMap<Cell, (u32, Cell)> = HashMap::with_capacity(1024);
+    let mut open = BinaryHeap::with_capacity(1024);
+    visited.insert(start, (0, start));
+    open.push(Reverse((heuristic(start), 0u32, start)));
+    let mut closest = (heuristic(start), start);
+    let mut searched_count = 0;
+    while let Some(Reverse((_estimated, cost, cell))) = open.pop() {
+        if cell == goal {
+            closest = (0, goal);
+            break;
+        }
+        if visited[&cell].0 < cost {
+            // a cheaper way was found since it was pushed
+            continue;
+        }
+        searched_count += 1;
+        if searched_count > MAX_SEARCHED_CELLS {
+            break;
+        }
+        let h = heuristic(cell);
+        if h < closest.0 {
+            closest = (h, cell);
+        }
+        for (neighbour, step_cost) in walkable_neighbours(cell, &is_walkable) {
+            let neighbour_cost = cost + step_cost;
+            let is_cheaper = visited
+                .get(&neighbour)
+                .map(|(known_cost, _)| neighbour_cost < *known_cost)
+                .unwrap_or(true);
+            if is_cheaper {
+                visited.insert(neighbour, (neighbour_cost, cell));
+                open.push(Reverse((
+                    neighbour_cost + heuristic(neighbour),
+                    neighbour_cost,
+                    neighbour,
+                )));
+            }
+        }
+    }
+
+    let end = closest.1;
+    let mut cells = vec![end];
+    while let Some((_cost, prev)) = visited.get(cells.last().unwrap()) {
+        if *prev == *cells.last().unwrap() {
+            break;
+        }
+        cells.push(*prev);
+    }
+    cells.reverse();
+
+    let mut points: Vec<Vec2> = Vec::with_capacity(cells.len() + 1);
+    points.push(*from);
+    points.extend(cells.iter().skip(1).map(|cell| center_of(*cell)));
+    if end == goal {
+        // the exact target instead of the center of its cell
+        if cells.len() > 1 {
+            points.pop();
+        }
+        points.push(*to);
+    }
+    return Some(smooth_path(&points, &is_walkable, gat));
+}
+
+/// The 8 surrounding cells which can be stepped on and the cost of the step, corners can't be cut
+pub(crate) fn walkable_neighbours<'a, F: Fn(Cell) -> bool>(
+    cell: Cell,
+    is_walkable: &'a F,
+) -> impl Iterator<Item = (Cell, u32)> + 'a {
+    const OFFSETS: [(i64, i64); 8] = [
+        (-1, 0),
+        (1, 0),
+        (0, -1),
+        (0, 1),
+        (-1, -1),
+        (1, -1),
+        (-1, 1),
+        (1, 1),
+    ];
+    return OFFSETS.iter().filter_map(move |(dx, dy)| {
+        let (x, y) = (cell.0 as i64 + dx, cell.1 as i64 + dy);
+        if x < 0 || y < 0 || !is_walkable((x as usize, y as usize)) {
+            return None;
+        }
+        if *dx != 0 && *dy != 0 {
+            if !is_walkable((x as usize, cell.1)) || !is_walkable((cell.0, y as usize)) {
+                return None;
+            }
+            return Some(((x as usize, y as usize), DIAGONAL_COST));
+        }
+        return Some(((x as usize, y as usize), STRAIGHT_COST));
+    });
+}
+
+/// Skips the points which can be reached in a straight line from an earlier one.
+/// The first point is the start, it is not part of the result.
+fn smooth_path<F: Fn(Cell) -> bool>(points: &[Vec2], is_walkable: &F, gat: &Gat) -> Vec<Vec2> {
+    let mut waypoints = Vec::with_capacity(points.len());
+    let mut anchor = 0;
+    while anchor + 1 < points.len() {
+        let mut next = anchor + 1;
+        while next + 1 < points.len()
+            && is_line_walkable(&points[anchor], &points[next + 1], is_walkable, gat)
+        {
+            next += 1;
+        }
+        waypoints.push(points[next]);
+        anchor = next;
+    }
+    return waypoints;
+}
+
+fn is_line_walkable<F: Fn(Cell) -> bool>(
+    from: &Vec2,
+    to: &Vec2,
+    is_walkable: &F,
+    gat: &Gat,
+) -> bool {
+    let line = to - from;
+    let step_count = (line.magnitude() / LINE_CHECK_STEP).ceil().max(1.0) as usize;
+    return (0..=step_count).all(|i| {
+        let pos = from + line * (i as f32 / step_count as f32);
+        cell_of(gat, &pos)
+            .map(|cell| is_walkable(cell))
+            .unwrap_or(false)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_walkable(gat: &Gat, from: Vec2, path: &[Vec2], blocked_cells: &HashSet<Cell>) {
+        let is_walkable =
+            |cell: Cell| gat.is_walkable(cell.0, cell.1) && !blocked_cells.contains(&cell);
+        let mut prev = from;
+        for waypoint in path {
+            assert!(
+                is_line_walkable(&prev, waypoint, &is_walkable, gat),
+                "{:?} -> {:?}",
+                prev,
+                waypoint
+            );
+            prev = *waypoint;
+        }
+    }
+
+    #[test]
+    fn straight_line_in_open_field() {
+        let gat = Gat::from_rows(&["......", "......", "......"]);
+        let path = find_path(&gat, &v2(0.5, -0.5), &v2(5.2, -2.7), &HashSet::new()).unwrap();
+        assert_eq!(path, vec![v2(5.2, -2.7)]);
+    }
+
+    #[test]
+    fn path_goes_around_walls() {
+        let gat = Gat::from_rows(&[
+            "..#...", //
+            "..#.#.", //
+            "..#.#.", //
+            "....#.",
+        ]);
+        let from = v2(0.5, -0.5);
+        let to = v2(5.5, -0.5);
+        let path = find_path(&gat, &from, &to, &HashSet::new()).unwrap();
+        assert_eq!(*path.last().unwrap(), to);
+        assert!(path.len() > 2);
+        assert_walkable(&gat, from, &path, &HashSet::new());
+    }
+
+    #[test]
+    fn blocked_cells_are_avoided() {
+        let gat = Gat::from_rows(&["......", "......", "......"]);
+        let blocked_cells: HashSet<Cell> = vec![(2, 0), (2, 1)].into_iter().collect();
+        let from = v2(0.5, -0.5);
+        let path = find_path(&gat, &from, &v2(5.5, -0.5), &blocked_cells).unwrap();
+        assert_eq!(*path.last().unwrap(), v2(5.5, -0.5));
+        assert!(path.len() > 1);
+        assert_walkable(&gat, from, &path, &blocked_cells);
+    }
+
+    #[test]
+    fn unreachable_target_leads_to_the_closest_cell() {
+        let gat = Gat::from_rows(&[
+            "...#..", //
+            "...#..", //
+            "...#..",
+        ]);
+        let path = find_path(&gat, &v2(0.5, -1.5), &v2(5.5, -1.5), &HashSet::new()).unwrap();
+        assert_eq!(path, vec![center_of((2, 1))]);
+        assert!(find_path(&gat, &v2(0.5, -1.5), &v2(6.5, -1.5), &HashSet::new()).is_none());
+    }
+}
@@ -0,0 +1,261 @@
+use crate::common::Vec2;
+use crate::components::char::Team;
+use crate::grf::gat::Gat;
+use crate::pathfinding;
+use serde::Deserialize;
+
+/// The scenarios are loaded from '<SCENARIO_DIR>/<map_name>.json'
+pub const SCENARIO_DIR: &str = "scenarios";
+
+/// Everything about a match which depends on the map: where the players spawn, where the
+/// minions walk, where the guards stand and where the objective structures are.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Scenario {
+    pub spawn_points: Vec<SpawnPoint>,
+    pub lanes: Vec<Lane>,
+    #[serde(default)]
+    pub guards: Vec<GuardPosition>,
+    pub minion_waves: MinionWaves,
+    #[serde(default)]
+    pub objectives: Vec<ObjectiveStructure>,
+}
+
+/// Where the players of a team (re)spawn
+#[derive(Debug, Deserialize, Clone)]
+pub struct SpawnPoint {
+    pub team: Team,
+    pub pos: Vec2,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct Lane {
+    pub name: String,
+    /// From the base of the Left team to the base of the Right team, the minions of the Right
+    /// team walk them in reverse order
+    pub waypoints: Vec<Vec2>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GuardPosition {
+    pub team: Team,
+    pub pos: Vec2,
+    /// the height of the guard above the ground (e.g. on a wall)
+    #[serde(default)]
+    pub y: f32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MinionWaves {
+    pub first_wave_seconds: f32,
+    pub interval_seconds: f32,
+    /// so many minions are spawned for each team on each lane in a wave
+    pub minions_per_lane: usize,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectiveKind {
+    Tower,
+    Inhibitor,
+    Core,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ObjectiveStructure {
+    pub kind: ObjectiveKind,
+    pub team: Team,
+    pub pos: Vec2,
+    /// The name of the lane it guards, None for the core
+    #[serde(default)]
+    pub lane: Option<String>,
+}
+
+impl Scenario {
+    pub fn load(map_name: &str) -> Result<Scenario, String> {
+        let path = std::path::Path::new(SCENARIO_DIR).join(format!("{}.json", map_name));
+        let content =
+            std::fs::read_to_string(&path).map_err(|e| format!("'{}': {}", path.display(), e))?;
+        return Scenario::parse(&content).map_err(|e| format!("'{}': {}", path.display(), e));
+    }
+
+    pub fn parse(content: &str) -> Result<Scenario, String> {
+        let scenario: Scenario = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        for team in &[Team::Left, Team::Right] {
+            if !scenario.spawn_points.iter().any(|it| it.team == *team) {
+                return Err(format!("There is no spawn point for {:?}", team));
+            }
+        }
+        if let Some(lane) = scenario.lanes.iter().find(|lane| lane.waypoints.is_empty()) {
+            return Err(format!("Lane '{}' has no waypoints", lane.name));
+        }
+        if let Some(objective) = scenario
+            .objectives
+            .iter()
+            .find(|objective| objective.team != Team::Left && objective.team != Team::Right)
+        {
+            return Err(format!(
+                "The {:?} at {:?} must belong to Left or Right instead of {:?}",
+                objective.kind, objective.pos, objective.team
+            ));
+        }
+        if let Some(objective) = scenario.objectives.iter().find(|objective| {
+            objective
+                .lane
+                .as_ref()
+                .map(|name| scenario.lane_index(name).is_none())
+                .unwrap_or(false)
+        }) {
+            return Err(format!(
+                "The {:?} of {:?} is on an unknown lane: {:?}",
+                objective.kind, objective.team, objective.lane
+            ));
+        }
+        return Ok(scenario);
+    }
+
+    /// The neutral teams spawn where the Left team does
+    pub fn spawn_point(&self, team: Team) -> Vec2 {
+        let spawn_point = self
+            .spawn_points
+            .iter()
+            .find(|it| it.team == team)
+            .or_else(|| self.spawn_points.iter().find(|it| it.team == Team::Left))
+            .unwrap();
+        return spawn_point.pos;
+    }
+
+    pub fn lane_index(&self, name: &str) -> Option<usize> {
+        self.lanes.iter().position(|lane| lane.name == name)
+    }
+
+    /// The positions which are not walkable on the map, e.g. after the map was modified
+    pub fn unwalkable_positions(&self, gat: &Gat) -> Vec<String> {
+        let is_walkable = |pos: &Vec2| {
+            pathfinding::cell_of(gat, pos)
+                .map(|cell| gat.is_walkable(cell.0, cell.1))
+                .unwrap_or(false)
+        };
+        let mut problems = Vec::new();
+        for spawn_point in self.spawn_points.iter() {
+            if !is_walkable(&spawn_point.pos) {
+                problems.push(format!(
+                    "The spawn point of {:?}: {:?}",
+                    spawn_point.team, spawn_point.pos
+                ));
+            }
+        }
+        for lane in self.lanes.iter() {
+            for (i, waypoint) in lane.waypoints.iter().enumerate() {
+                if !is_walkable(waypoint) {
+                    problems.push(format!(
+                        "Waypoint {} of lane '{}': {:?}",
+                        i, lane.name, waypoint
+                    ));
+                }
+            }
+        }
+        for guard in self.guards.iter() {
+            if !is_walkable(&guard.pos) {
+                problems.push(format!("A guard of {:?}: {:?}", guard.team, guard.pos));
+            }
+        }
+        for objective in self.objectives.iter() {
+            if !is_walkable(&objective.pos) {
+                problems.push(format!(
+                    "The {:?} of {:?}: {:?}",
+                    objective.kind, objective.team, objective.pos
+                ));
+            }
+        }
+        return problems;
+    }
+}
+
+impl Lane {
+    /// The index in `waypoints` of the `nth` waypoint the minions of `team` have to walk through
+    pub fn waypoint_index(&self, team: Team, nth: usize) -> Option<usize> {
+        if nth >= self.waypoints.len() {
+            return None;
+        }
+        return Some(if team == Team::Right {
+            self.waypoints.len() - 1 - nth
+        } else {
+            nth
+        });
+    }
+
+    /// The base of the team
+    pub fn start(&self, team: Team) -> Vec2 {
+        self.waypoints[self.waypoint_index(team, 0).unwrap()]
+    }
+
+    /// The `nth` of the closest waypoint, a minion which is spawned somewhere along the lane
+    /// continues from there
+    pub fn closest_waypoint(&self, team: Team, pos: &Vec2) -> usize {
+        return (0..self.waypoints.len())
+            .min_by(|a, b| {
+                let dist_a =
+                    (self.waypoints[self.waypoint_index(team, *a).unwrap()] - pos).magnitude();
+                let dist_b =
+                    (self.waypoints[self.waypoint_index(team, *b).unwrap()] - pos).magnitude();
+                dist_a.partial_cmp(&dist_b).unwrap()
+            })
+            .unwrap_or(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::v2;
+
+    const SCENARIO: &str = r#"{
+        "spawn_points": [
+            {"team": "Left", "pos": [10.0, -10.0]},
+            {"team": "Right", "pos": [90.0, -10.0]}
+        ],
+        "lanes": [
+            {"name": "top", "waypoints": [[5.0, -10.0], [50.0, -5.0], [95.0, -10.0]]}
+        ],
+        "minion_waves": {"first_wave_seconds": 2.0, "interval_seconds": 4.0, "minions_per_lane": 1},
+        "objectives": [
+            {"kind": "Tower", "team": "Left", "pos": [20.0, -10.0], "lane": "top"},
+            {"kind": "Core", "team": "Right", "pos": [94.0, -10.0]}
+        ]
+    }"#;
+
+    #[test]
+    fn lanes_are_walked_from_the_own_base() {
+        let scenario = Scenario::parse(SCENARIO).unwrap();
+        assert_eq!(scenario.spawn_point(Team::Right), v2(90.0, -10.0));
+        assert_eq!(scenario.spawn_point(Team::Neutral), v2(10.0, -10.0));
+        assert!(scenario.guards.is_empty());
+        assert_eq!(scenario.objectives[1].lane, None);
+
+        let lane = &scenario.lanes[0];
+        assert_eq!(lane.start(Team::Left), v2(5.0, -10.0));
+        assert_eq!(lane.start(Team::Right), v2(95.0, -10.0));
+        assert_eq!(lane.waypoint_index(Team::Right, 1), Some(1));
+        assert_eq!(lane.waypoint_index(Team::Right, 2), Some(0));
+        assert_eq!(lane.waypoint_index(Team::Right, 3), None);
+        assert_eq!(lane.closest_waypoint(Team::Right, &v2(80.0, -10.0)), 0);
+        assert_eq!(lane.closest_waypoint(Team::Left, &v2(80.0, -10.0)), 2);
+    }
+
+    #[test]
+    fn incomplete_scenarios_are_rejected() {
+        let without_right_spawn = SCENARIO.replace(
+            r#"{"team": "Right", "pos": [90.0, -10.0]}"#,
+            r#"{"team": "Neutral", "pos": [90.0, -10.0]}"#,
+        );
+        assert!(Scenario::parse(&without_right_spawn).is_err());
+        assert!(
+            Scenario::parse(&SCENARIO.replace(r#""lane": "top""#, r#""lane": "bottom""#)).is_err()
+        );
+        assert!(Scenario::parse(&SCENARIO.replace(
+            r#""kind": "Core", "team": "Right""#,
+            r#""kind": "Core", "team": "Neutral""#
+        ))
+        .is_err());
+        assert!(Scenario::parse("{}").is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use specs::prelude::*;
+
+use crate::attack::{
+    BasicAttackType, DamageDisplayType, HpModificationRequest, HpModificationType, WeaponType,
+};
+use crate::common::{v2_to_p2, ElapsedTime, EngineTime, Vec2};
+use crate::components::char::{
+    AuthorizedCharStateComponent, CharAttributesComponent, CharEntityId, CharState, EntityTarget,
+    Team,
+};
+use crate::components::manifestation::{
+    BasicAttackProjectileState, SkillManifestationComponent, SkillManifestationKind,
+};
+use crate::components::structure::StructureComponent;
+use crate::systems::position_history_sys::PositionHistory;
+
+/// An attack whose animation has started but it has not hit yet
+struct PendingAttack {
+    attacker: CharEntityId,
+    target: CharEntityId,
+    hits_at: ElapsedTime,
+}
+
+/// The characters attack their `OtherEntity` target once they are in range of it (they are
+/// moved there by CharacterStateUpdateSystem). The melee attacks hit in the middle of the attack
+/// animation, the ranged ones fire a projectile then. It is run only by the server, the structures
+/// are handled by StructureAiSystem.
+pub struct BasicAttackSystem {
+    pending_attacks: Vec<PendingAttack>,
+}
+
+impl BasicAttackSystem {
+    pub fn new() -> BasicAttackSystem {
+        BasicAttackSystem {
+            pending_attacks: Vec::with_capacity(64),
+        }
+    }
+
+    /// The projectile reaches its target faster from closer
+    pub fn projectile_travel_seconds(distance: f32) -> f32 {
+        return (distance * 0.05).min(0.3);
+    }
+}
+
+impl<'a> System<'a> for BasicAttackSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, CharAttributesComponent>,
+        ReadStorage<'a, AuthorizedCharStateComponent>,
+        ReadStorage<'a, StructureComponent>,
+        WriteStorage<'a, SkillManifestationComponent>,
+        ReadExpect<'a, EngineTime>,
+        Write<'a, Vec<HpModificationRequest>>,
+        Option<Read<'a, PositionHistory>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut char_attr_storage,
+            auth_char_state_storage,
+            structure_storage,
+            mut manifestation_storage,
+            time,
+            mut hp_mod_requests,
+            position_history,
+        ): Self::SystemData,
+    ) {
+        let now = time.now();
+        let living_chars: Vec<(CharEntityId, Team, Vec2)> =
+            (&entities, &char_attr_storage, &auth_char_state_storage)
+                .join()
+                .filter(|(_entity, _attrs, char_state)| char_state.state().is_alive())
+                .map(|(entity, attrs, char_state)| {
+                    (CharEntityId::new(entity), attrs.team, char_state.pos())
+                })
+                .collect();
+        let find_living = |char_id: CharEntityId| {
+            living_chars
+                .iter()
+                .find(|(other_id, _team, _pos)| *other_id == char_id)
+                .map(|(_id, team, pos)| (*team, *pos))
+        };
+
+        for (entity, attrs, char_state, _not_structure) in (
+            &entities,
+            &mut char_attr_storage,
+            &auth_char_state_storage,
+            !&structure_storage,
+        )
+            .join()
+        {
+            let target = match char_state.target {
+                Some(EntityTarget::OtherEntity(target)) => target,
+                _ => continue,
+            };
+            let attribs = &attrs.calculated_attribs;
+            // the approach of the target is decided by CharacterStateUpdateSystem
+            let is_ready = *char_state.state() == CharState::Idle
+                && attribs.attack_damage > 0
+                && attrs.attack_delay_ends_at.has_already_passed(now)
+                && attrs.cannot_control_until.has_already_passed(now);
+            if !is_ready {
+                continue;
+            }
+            let attacker = CharEntityId::new(entity);
+            let in_range = match find_living(target) {
+                Some((target_team, target_pos)) => {
+                    let range = attribs.attack_range.as_f32() * 2.0;
+                    let is_in_range = |target_pos: &Vec2| {
+                        nalgebra::distance(&v2_to_p2(&char_state.pos()), &v2_to_p2(target_pos))
+                            <= range
+                    };
+                    // the target was in range on the screen of the attacking player
+                    let seen_target_pos = position_history
+                        .as_ref()
+                        .and_then(|history| history.pos_seen_by(time.tick, attacker, target));
+                    attrs.team.can_attack(target_team)
+                        && (is_in_range(&target_pos)
+                            || seen_target_pos.map(|it| is_in_range(&it)).unwrap_or(false))
+                }
+                None => false,
+            };
+            if !in_range {
+                continue;
+            }
+            let attack_delay = 1.0 / attribs.attack_speed.as_f32();
+            attrs.attack_delay_ends_at = now.add_seconds(attack_delay);
+            self.pending_attacks.push(PendingAttack {
+                attacker,
+                target,
+                hits_at: now.add_seconds(attack_delay / 2.0),
+            });
+        }
+
+        let mut i = 0;
+        while i < self.pending_attacks.len() {
+            if self.pending_attacks[i].hits_at.has_not_passed_yet(now) {
+                i += 1;
+                continue;
+            }
+            let attack = self.pending_attacks.swap_remove(i);
+            let (attacker_pos, target_pos) =
+                match (find_living(attack.attacker), find_living(attack.target)) {
+                    (Some((_, attacker_pos)), Some((_, target_pos))) => (attacker_pos, target_pos),
+                    // the attack is interrupted if any of them died meanwhile
+                    _ => continue,
+                };
+            let attrs = match char_attr_storage.get(attack.attacker.into()) {
+                Some(attrs) => attrs,
+                None => continue,
+            };
+            let attack_damage = attrs.calculated_attribs.attack_damage as u32;
+            match BasicAttackType::for_job(attrs.job_id) {
+                BasicAttackType::MeleeSimple => {
+                    hp_mod_requests.push(HpModificationRequest {
+                        src_entity: attack.attacker,
+                        dst_entity: attack.target,
+                        typ: HpModificationType::BasicDamage(
+                            attack_damage,
+                            DamageDisplayType::SingleNumber,
+                            WeaponType::Sword,
+                        ),
+                    });
+                }
+                BasicAttackType::MeleeCombo {
+                    combo_count,
+                    base_dmg_percentage_for_each_combo,
+                } => {
+                    let dmg = base_dmg_percentage_for_each_combo.of(attack_damage as i32)
+                        * combo_count as i32;
+                    hp_mod_requests.push(HpModificationRequest {
+                        src_entity: attack.attacker,
+                        dst_entity: attack.target,
+                        typ: HpModificationType::BasicDamage(
+                            dmg as u32,
+                            DamageDisplayType::Combo(combo_count),
+                            WeaponType::Sword,
+                        ),
+                    });
+                }
+                BasicAttackType::Ranged { bullet_type } => {
+                    let distance = (target_pos - attacker_pos).magnitude();
+                    let arrives_at =
+                        now.add_seconds(BasicAttackSystem::projectile_travel_seconds(distance));
+                    entities
+                        .build_entity()
+                        .with(
+                            SkillManifestationComponent {
+                                caster_entity_id: attack.attacker,
+                                caster_team: attrs.team,
+                                pos: attacker_pos,
+                                created_at: now,
+                                // it is removed when it hits, this is only a safety net
+                                die_at: arrives_at.add_seconds(5.0),
+                                kind: SkillManifestationKind::BasicAttackProjectile(
+                                    BasicAttackProjectileState {
+                                        target_entity_id: attack.target,
+                                        start_pos: attacker_pos,
+                                        arrives_at,
+                                        damage: attack_damage,
+                                        weapon_type: bullet_type,
+                                    },
+                                ),
+                            },
+                            &mut manifestation_storage,
+                        )
+                        .build();
+                }
+            }
+        }
+    }
+}
@@ -2,12 +2,21 @@ use specs::prelude::*;
 
 use crate::common::{v2_to_p2, ElapsedTime, EngineTime, Vec2};
 use crate::components::char::{
-    AuthorizedCharStateComponent, CharDir, CharEntityId, CharState, EntityTarget, Team,
+    AuthorizedCharStateComponent, CharAttributesComponent, CharDir, CharEntityId, CharState,
+    EntityTarget, Team, WalkingPathComponent,
 };
+use crate::components::skills::FinishedSkillCast;
+use crate::components::status::StatusesComponent;
+use crate::grf::gat::Gat;
+use crate::systems::position_history_sys::PositionHistory;
 use std::collections::HashMap;
 
 pub struct CharacterStateUpdateSystem;
 
+/// The clients don't know the attributes of the characters, they predict the approach of an
+/// attack target with this range
+const DEFAULT_ATTACK_RANGE: f32 = 2.0;
+
 impl<'a> System<'a> for CharacterStateUpdateSystem {
     type SystemData = (
         Entities<'a>,
@@ -15,30 +24,44 @@ impl<'a> System<'a> for CharacterStateUpdateSystem {
         WriteStorage<'a, AuthorizedCharStateComponent>,
         ReadExpect<'a, EngineTime>,
         Write<'a, LazyUpdate>,
+        Option<Write<'a, Vec<FinishedSkillCast>>>,
+        WriteStorage<'a, WalkingPathComponent>,
+        Option<Read<'a, Gat>>,
+        ReadStorage<'a, CharAttributesComponent>,
+        ReadStorage<'a, StatusesComponent>,
+        Option<Read<'a, PositionHistory>>,
     );
 
-    fn run(&mut self, (entities, mut char_state_storage, time, mut updater): Self::SystemData) {
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut char_state_storage,
+            time,
+            mut updater,
+            mut finished_casts,
+            mut walking_path_storage,
+            gat,
+            char_attr_storage,
+            statuses_storage,
+            position_history,
+        ): Self::SystemData,
+    ) {
         let now = time.now();
+        let living_char_positions: HashMap<CharEntityId, Vec2> = (&entities, &char_state_storage)
+            .join()
+            .filter(|(_entity, char_comp)| !char_comp.state().is_dead())
+            .map(|(entity, char_comp)| (CharEntityId::new(entity), char_comp.pos()))
+            .collect();
+        // the paths of the stuck characters are planned around the others
+        let char_positions: Vec<Vec2> = if gat.is_some() {
+            living_char_positions.values().cloned().collect()
+        } else {
+            vec![]
+        };
 
-        // TODO: HACK
-        // I can't get the position of the target entity inside the loop because
-        // char_state storage is borrowed as mutable already
-        // TODO2
-        //        let all_char_data = {
-        //            let mut char_positions = HashMap::<CharEntityId, (Vec2, Team)>::new();
-        //            for (char_entity_id, char_comp) in (&entities, &char_state_storage).join() {
-        //                // TODO2
-        //                //                if char_comp.state().is_dead() {
-        //                //                    continue;
-        //                //                }
-        //                let char_entity_id = CharEntityId::new(char_entity_id);
-        //                char_positions.insert(char_entity_id, (char_comp.pos(), char_comp.team));
-        //            }
-        //            char_positions
-        //        };
-
-        for (char_entity_id, char_comp) in (&entities, &mut char_state_storage).join() {
-            let char_entity_id = CharEntityId::new(char_entity_id);
+        for (entity, char_comp) in (&entities, &mut char_state_storage).join() {
+            let char_entity_id = CharEntityId::new(entity);
             // pakold külön componensbe augy a dolgokat, hogy innen be tudjam álltiani a
             // target et None-ra ha az halott, meg a fenti position hack se kelllejn
             // TODO2
@@ -85,7 +108,28 @@ impl<'a> System<'a> for CharacterStateUpdateSystem {
             //                continue;
             //            }
 
+            if char_comp.state().is_dead() {
+                continue;
+            }
+
             let char_pos = char_comp.pos();
+            if let CharState::CastingSkill(casting_info) = char_comp.state().clone() {
+                if casting_info.cast_ends.has_already_passed(now) {
+                    log::debug!("Skill cast has finished: {:?}", casting_info.skill);
+                    if let Some(finished_casts) = &mut finished_casts {
+                        finished_casts.push(FinishedSkillCast {
+                            skill: casting_info.skill,
+                            caster_entity_id: char_entity_id,
+                            caster_pos: char_pos,
+                            skill_pos: casting_info.target_area_pos,
+                            char_to_skill_dir: casting_info.char_to_skill_dir_when_casted,
+                            target_entity: casting_info.target_entity,
+                        });
+                    }
+                    char_comp.set_state(CharState::Idle, char_comp.dir());
+                }
+                continue;
+            }
             // TODO: why clone?
             // TODO2
             //            match char_comp.state().clone() {
@@ -195,9 +239,37 @@ impl<'a> System<'a> for CharacterStateUpdateSystem {
                     //                            )
                     //                        }
                     //                    } else {
+                    let walking_path = match &gat {
+                        Some(gat) => {
+                            if walking_path_storage.get(entity).is_none() {
+                                walking_path_storage
+                                    .insert(entity, WalkingPathComponent::default())
+                                    .expect("");
+                            }
+                            walking_path_storage.get_mut(entity).map(|walking_path| {
+                                (&**gat, walking_path, char_positions.as_slice())
+                            })
+                        }
+                        None => None,
+                    };
+                    let attack_range = char_attr_storage
+                        .get(entity)
+                        .map(|attrs| attrs.calculated_attribs.attack_range.as_f32() * 2.0)
+                        .unwrap_or(DEFAULT_ATTACK_RANGE);
+                    let seen_target_pos = match (&position_history, target) {
+                        (Some(history), EntityTarget::OtherEntity(target_entity)) => {
+                            history.pos_seen_by(time.tick, char_entity_id, *target_entity)
+                        }
+                        _ => None,
+                    };
                     CharacterStateUpdateSystem::act_based_on_target(
-                        now, //                        &all_char_data,
-                        char_comp, target,
+                        now,
+                        &living_char_positions,
+                        attack_range,
+                        seen_target_pos,
+                        char_comp,
+                        target,
+                        walking_path,
                     )
                 //                    }
                 } else {
@@ -209,11 +281,13 @@ impl<'a> System<'a> for CharacterStateUpdateSystem {
 
         // TODO: into a system
         // apply moving physics here, so that the prev loop does not have to borrow physics_storage
-        for char_comp in (&mut char_state_storage).join() {
+        for (entity, char_comp) in (&entities, &mut char_state_storage).join() {
             if let CharState::Walking(target_pos) = char_comp.state() {
-                if true
-                /*TODO2 char_comp.can_move(now)*/
-                {
+                let can_move = statuses_storage
+                    .get(entity)
+                    .map(|statuses| statuses.can_move())
+                    .unwrap_or(true);
+                if can_move {
                     // it is possible that the character is pushed away but stayed in WALKING state (e.g. because of she blocked the attack)
                     let dir = (target_pos - char_comp.pos()).normalize();
                     // 100% movement speed = 5 units/second
@@ -262,67 +336,81 @@ impl CharacterStateUpdateSystem {
 
     fn act_based_on_target(
         now: ElapsedTime,
-        //        char_positions: &HashMap<CharEntityId, (Vec2, Team)>,
+        living_char_positions: &HashMap<CharEntityId, Vec2>,
+        attack_range: f32,
+        // where the target is on the screen of the player, see PositionHistory::pos_seen_by
+        seen_target_pos: Option<Vec2>,
         char_comp: &mut AuthorizedCharStateComponent,
         target: &EntityTarget,
+        // without a map the characters walk in a straight line
+        walking_path: Option<(&Gat, &mut WalkingPathComponent, &[Vec2])>,
     ) {
         let char_pos = char_comp.pos();
         match target {
             EntityTarget::OtherEntity(target_entity) => {
-                // TODO2
-                //                let target_pos = char_positions.get(target_entity);
-                //                if let Some((target_pos, _team)) = target_pos {
-                //                    let distance = nalgebra::distance(
-                //                        &nalgebra::Point::from(char_pos),
-                //                        &v2_to_p2(&target_pos),
-                //                    );
-                //
-                //                    if distance <= char_comp.calculated_attribs().attack_range.as_f32() * 2.0 {
-                //                        if char_comp.attack_delay_ends_at.has_already_passed(now) {
-                //                            let attack_anim_duration =
-                //                                1.0 / char_comp.calculated_attribs().attack_speed.as_f32();
-                //                            let damage_occurs_at = now.add_seconds(attack_anim_duration / 2.0);
-                //                            let new_state = CharState::Attacking {
-                //                                damage_occurs_at,
-                //                                target: *target_entity,
-                //                                basic_attack: char_comp.basic_attack_type.clone(),
-                //                            };
-                //                            char_comp.set_state(
-                //                                new_state,
-                //                                CharDir::determine_dir(target_pos, &char_pos),
-                //                            );
-                //                            let attack_anim_duration = ElapsedTime(
-                //                                1.0 / char_comp.calculated_attribs().attack_speed.as_f32(),
-                //                            );
-                //                            char_comp.attack_delay_ends_at = now.add(attack_anim_duration);
-                //                        } else {
-                //                            char_comp.set_state(CharState::Idle, char_comp.dir());
-                //                        }
-                //                    } else {
-                //                        //                     move closer
-                //                        char_comp.set_state(
-                //                            CharState::Walking(*target_pos),
-                //                            CharDir::determine_dir(target_pos, &char_pos),
-                //                        );
-                //                    }
-                //                } else {
-                char_comp.set_state(CharState::Idle, char_comp.dir());
-                char_comp.target = None;
-                //                }
+                match living_char_positions.get(target_entity) {
+                    Some(target_pos) => {
+                        let is_in_range = |pos: &Vec2| {
+                            nalgebra::distance(&nalgebra::Point::from(char_pos), &v2_to_p2(pos))
+                                <= attack_range
+                        };
+                        if is_in_range(target_pos)
+                            || seen_target_pos.map(|it| is_in_range(&it)).unwrap_or(false)
+                        {
+                            // in range, the attacks are done by BasicAttackSystem
+                            char_comp.set_state(
+                                CharState::Idle,
+                                CharDir::determine_dir(target_pos, &char_pos),
+                            );
+                            return;
+                        }
+                        let next_pos = match walking_path {
+                            Some((gat, walking_path, char_positions)) => walking_path
+                                .next_waypoint(gat, &char_pos, target_pos, now, char_positions),
+                            None => Some(*target_pos),
+                        };
+                        match next_pos {
+                            Some(next_pos) => char_comp.set_state(
+                                CharState::Walking(next_pos),
+                                CharDir::determine_dir(&next_pos, &char_pos),
+                            ),
+                            None => char_comp.set_state(CharState::Idle, char_comp.dir()),
+                        }
+                    }
+                    None => {
+                        // the target has died or disappeared
+                        char_comp.set_state(CharState::Idle, char_comp.dir());
+                        char_comp.target = None;
+                    }
+                }
             }
-            EntityTarget::Pos(target_pos) => {
+            EntityTarget::Pos(target_pos) | EntityTarget::PosInStraightLine(target_pos) => {
+                let walking_path = match target {
+                    EntityTarget::Pos(_) => walking_path,
+                    _ => None,
+                };
                 let distance =
                     nalgebra::distance(&nalgebra::Point::from(char_pos), &v2_to_p2(target_pos));
-                if distance <= 0.2 {
-                    // stop
-                    char_comp.set_state(CharState::Idle, char_comp.dir());
-                    char_comp.target = None;
-                } else {
+                let next_pos =
+                    if distance <= 0.2 {
+                        None
+                    } else {
+                        match walking_path {
+                            Some((gat, walking_path, char_positions)) => walking_path
+                                .next_waypoint(gat, &char_pos, target_pos, now, char_positions),
+                            None => Some(*target_pos),
+                        }
+                    };
+                if let Some(next_pos) = next_pos {
                     // move closer
                     char_comp.set_state(
-                        CharState::Walking(*target_pos),
-                        CharDir::determine_dir(target_pos, &char_pos),
+                        CharState::Walking(next_pos),
+                        CharDir::determine_dir(&next_pos, &char_pos),
                     );
+                } else {
+                    // stop, the target was reached or it is as close as possible
+                    char_comp.set_state(CharState::Idle, char_comp.dir());
+                    char_comp.target = None;
                 }
             }
             EntityTarget::PosWhileAttacking(_pos, _current_target) => {}
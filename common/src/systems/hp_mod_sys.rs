@@ -0,0 +1,125 @@
+use specs::prelude::*;
+
+use crate::attack::{HpModificationRequest, HpModificationResult, HpModificationType};
+use crate::common::EngineTime;
+use crate::components::char::{AuthorizedCharStateComponent, CharAttributesComponent, CharState};
+use crate::components::status::StatusesComponent;
+use crate::components::structure::StructureComponent;
+
+/// Validates and applies the hp modification requests. It is run only by the server, the
+/// clients receive the results.
+pub struct HpModificationSystem {
+    hp_mod_requests: Vec<HpModificationRequest>,
+}
+
+impl HpModificationSystem {
+    pub fn new() -> HpModificationSystem {
+        HpModificationSystem {
+            hp_mod_requests: Vec::with_capacity(128),
+        }
+    }
+}
+
+impl<'a> System<'a> for HpModificationSystem {
+    type SystemData = (
+        WriteStorage<'a, CharAttributesComponent>,
+        WriteStorage<'a, AuthorizedCharStateComponent>,
+        ReadStorage<'a, StructureComponent>,
+        WriteStorage<'a, StatusesComponent>,
+        ReadExpect<'a, EngineTime>,
+        Write<'a, Vec<HpModificationRequest>>,
+        Write<'a, Vec<HpModificationResult>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut char_attr_storage,
+            mut auth_char_state_storage,
+            structure_storage,
+            mut statuses_storage,
+            time,
+            mut hp_mod_requests,
+            mut hp_mod_results,
+        ): Self::SystemData,
+    ) {
+        self.hp_mod_requests.clear();
+        std::mem::swap(&mut self.hp_mod_requests, &mut hp_mod_requests);
+
+        for hp_mod_req in self.hp_mod_requests.drain(..) {
+            log::trace!("Process hp_mod_req {:?}", hp_mod_req);
+            let src_team = match char_attr_storage.get(hp_mod_req.src_entity.into()) {
+                Some(src_attrs) => src_attrs.team,
+                None => continue,
+            };
+            let dst_entity = hp_mod_req.dst_entity;
+            // the protection is checked before the storages are borrowed mutably
+            let is_protected_structure = match hp_mod_req.typ {
+                HpModificationType::Heal(_) => false,
+                _ => match (
+                    structure_storage.get(dst_entity.into()),
+                    char_attr_storage.get(dst_entity.into()),
+                ) {
+                    (Some(structure), Some(dst_attrs)) => structure.is_protected(
+                        dst_attrs.team,
+                        (
+                            &structure_storage,
+                            &char_attr_storage,
+                            &auth_char_state_storage,
+                        )
+                            .join()
+                            .map(|(other, other_attrs, other_state)| {
+                                (other, other_attrs.team, other_state.state().is_alive())
+                            }),
+                    ),
+                    _ => false,
+                },
+            };
+            let (dst_attrs, dst_auth_state) = match (
+                char_attr_storage.get_mut(dst_entity.into()),
+                auth_char_state_storage.get_mut(dst_entity.into()),
+            ) {
+                (Some(dst_attrs), Some(dst_auth_state)) => (dst_attrs, dst_auth_state),
+                _ => continue,
+            };
+            let is_valid = dst_auth_state.state().is_alive()
+                && match hp_mod_req.typ {
+                    HpModificationType::Heal(_) => src_team.can_support(dst_attrs.team),
+                    _ => src_team.can_attack(dst_attrs.team),
+                };
+            if !is_valid {
+                log::warn!("Invalid hp_mod_req: {:?}", hp_mod_req);
+                continue;
+            }
+            if is_protected_structure {
+                log::debug!("Structure is protected {:?}", dst_entity);
+                hp_mod_results.push(hp_mod_req.blocked().with_dst_hp(dst_attrs.hp));
+                continue;
+            }
+
+            let result = hp_mod_req.apply_armor_calc(&dst_attrs.calculated_attribs);
+            // the statuses of the target can alter the outcome, e.g. absorb it
+            let result = match statuses_storage.get_mut(dst_entity.into()) {
+                Some(statuses) => statuses.hp_mod_is_calculated_but_not_applied_yet(result),
+                None => result,
+            };
+            if result.interrupts_target() {
+                dst_attrs
+                    .cannot_control_until
+                    .run_at_least_until_seconds(time.now(), 0.1);
+                dst_auth_state.set_receiving_damage();
+            }
+            dst_attrs.hp = result.apply_on_hp(dst_attrs.hp, dst_attrs.calculated_attribs.max_hp);
+            if dst_attrs.hp <= 0 {
+                log::debug!("Entity has died {:?}", dst_entity);
+                dst_auth_state.set_state(CharState::Dead, dst_auth_state.dir());
+                dst_auth_state.target = None;
+                if let Some(statuses) = statuses_storage.get_mut(dst_entity.into()) {
+                    statuses.remove_all();
+                }
+            }
+            // the target might be modified more times in this tick, its final hp is not enough
+            hp_mod_results.push(result.with_dst_hp(dst_attrs.hp));
+        }
+    }
+}
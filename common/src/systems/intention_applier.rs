@@ -1,147 +1,251 @@
-use crate::common::{v2, EngineTime};
-use crate::components::char::{AuthorizedCharStateComponent, EntityTarget};
+use crate::common::{v2, ElapsedTime, EngineTime, Vec2};
+use crate::components::char::{
+    AuthorizedCharStateComponent, CastingSkillData, CharAttributesComponent, CharDir, CharEntityId,
+    CharState, EntityTarget, Team,
+};
 use crate::components::controller::{ControllerComponent, PlayerIntention};
+use crate::components::skills::{SkillTargetType, Skills};
+use crate::components::status::StatusesComponent;
+use crate::config::CommonConfigs;
+use crate::systems::position_history_sys::PositionHistory;
 use specs::prelude::*;
 
 pub struct NextActionApplierSystem;
 
 impl<'a> System<'a> for NextActionApplierSystem {
     type SystemData = (
+        Entities<'a>,
         WriteStorage<'a, AuthorizedCharStateComponent>,
+        WriteStorage<'a, CharAttributesComponent>,
         ReadStorage<'a, ControllerComponent>,
+        ReadStorage<'a, StatusesComponent>,
         ReadExpect<'a, EngineTime>,
-        //        ReadExpect<'a, DevConfig>,
+        Option<Read<'a, CommonConfigs>>,
+        Option<Read<'a, PositionHistory>>,
     );
 
-    fn run(&mut self, (mut char_state_storage, controller_storage, time): Self::SystemData) {
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut char_state_storage,
+            mut char_attr_storage,
+            controller_storage,
+            statuses_storage,
+            time,
+            configs,
+            position_history,
+        ): Self::SystemData,
+    ) {
         let now = time.now();
         for controller in (&controller_storage).join() {
+            let rewind = match (&position_history, controller.rewind_to_tick) {
+                (Some(history), Some(tick)) => Some((&**history, tick)),
+                _ => None,
+            };
+            // the casting target has to be resolved before borrowing the caster mutably
+            let cast_target = match controller.intention {
+                Some(PlayerIntention::Casting(skill, is_self_cast, target_pos)) => {
+                    NextActionApplierSystem::find_cast_target(
+                        &entities,
+                        &char_state_storage,
+                        &char_attr_storage,
+                        controller.controlled_entity,
+                        skill,
+                        is_self_cast,
+                        &target_pos,
+                        rewind,
+                    )
+                }
+                _ => None,
+            };
             let char_state = char_state_storage.get_mut(controller.controlled_entity.into());
 
             // the controlled character might have been removed due to death etc
             if let Some(char_state) = char_state {
-                // TODO2
-                //                if char_state.statuses.can_be_controlled() == false {
-                //                    continue;
-                //                }
+                if char_state.state().is_dead() {
+                    continue;
+                }
+                let statuses = statuses_storage.get(controller.controlled_entity.into());
+                if !statuses.map(|it| it.can_be_controlled()).unwrap_or(true) {
+                    continue;
+                }
                 match controller.intention {
                     Some(PlayerIntention::MoveTo(pos)) => {
-                        char_state.target = Some(EntityTarget::Pos(v2(pos.x, -65.0)))
+                        // the path towards it is planned by CharacterStateUpdateSystem
+                        char_state.target = Some(EntityTarget::Pos(pos))
                     }
                     Some(PlayerIntention::Attack(target_entity_id)) => {
+                        // the range is checked against the rewound position of the target as
+                        // well, see PositionHistory::pos_seen_by
                         char_state.target = Some(EntityTarget::OtherEntity(target_entity_id));
                     }
                     Some(PlayerIntention::MoveTowardsMouse(pos)) => {
-                        char_state.target = Some(EntityTarget::Pos(v2(pos.x, -65.0)));
+                        char_state.target = Some(EntityTarget::Pos(pos));
+                    }
+                    Some(PlayerIntention::Steer(pos)) => {
+                        char_state.target = Some(EntityTarget::PosInStraightLine(pos));
                     }
                     Some(PlayerIntention::AttackTowards(pos)) => {
                         char_state.target =
                             Some(EntityTarget::PosWhileAttacking(v2(pos.x, -65.0), None));
                     }
-                    None => {} // TODO2
-                               //                    Some(PlayerIntention::Casting(skill, is_self_cast, mouse_world_pos)) => {
-                               //                        NextActionApplierSystem::try_cast_skill(
-                               //                            skill,
-                               //                            now,
-                               //                            &dev_configs,
-                               //                            char_state,
-                               //                            &mouse_world_pos,
-                               //                            &controller.entities_below_cursor,
-                               //                            controller.controlled_entity,
-                               //                            is_self_cast,
-                               //                        )
-                               //                    }
+                    Some(PlayerIntention::Casting(skill, is_self_cast, target_pos)) => {
+                        // only the side which owns the gameplay configs can decide about casting
+                        if let (Some(configs), Some(char_attrs)) = (
+                            &configs,
+                            char_attr_storage.get_mut(controller.controlled_entity.into()),
+                        ) {
+                            NextActionApplierSystem::try_cast_skill(
+                                skill,
+                                now,
+                                configs,
+                                char_state,
+                                char_attrs,
+                                statuses,
+                                &target_pos,
+                                cast_target,
+                                controller.controlled_entity,
+                                is_self_cast,
+                            );
+                        }
+                    }
+                    None => {}
                 };
             }
         }
     }
 }
 
-//impl NextActionApplierSystem {
-//    pub fn try_cast_skill(
-//        skill: Skills,
-//        now: ElapsedTime,
-//        configs: &DevConfig,
-//        char_state: &mut CharacterStateComponent,
-//        mouse_world_pos: &Vec2,
-//        entities_below_cursor: &EntitiesBelowCursor,
-//        self_char_id: CharEntityId,
-//        is_self_cast: bool,
-//    ) -> bool {
-//        if char_state
-//            .skill_cast_allowed_at
-//            .entry(skill)
-//            .or_insert(ElapsedTime(0.0))
-//            .has_not_passed_yet(now)
-//        {
-//            return true;
-//        }
-//        let skill_def = skill.get_definition();
-//        let skill_cast_attrs = skill.get_cast_attributes(configs, char_state);
-//        let (target_pos, target_entity) = if is_self_cast {
-//            (char_state.pos(), Some(self_char_id))
-//        } else {
-//            let target_entity = match skill_def.get_skill_target_type() {
-//                SkillTargetType::AnyEntity => entities_below_cursor.get_enemy_or_friend(),
-//                SkillTargetType::NoTarget => None,
-//                SkillTargetType::Area => None,
-//                SkillTargetType::Directional => None,
-//                SkillTargetType::OnlyAllyButNoSelf => {
-//                    entities_below_cursor.get_friend_except(self_char_id)
-//                }
-//                SkillTargetType::OnlyAllyAndSelf => entities_below_cursor.get_friend(),
-//                SkillTargetType::OnlyEnemy => entities_below_cursor.get_enemy(),
-//            };
-//            (*mouse_world_pos, target_entity)
-//        };
-//        let distance = (char_state.pos() - target_pos).magnitude();
-//        let allowed = Skills::is_casting_allowed_based_on_target(
-//            skill_def.get_skill_target_type(),
-//            skill_cast_attrs.casting_range,
-//            self_char_id,
-//            target_entity,
-//            distance,
-//        );
-//        let can_move = char_state.can_cast(now);
-//        if allowed && can_move {
-//            log::debug!("Casting request for '{:?}' was allowed", skill);
-//            let casting_time_seconds = skill_cast_attrs.casting_time;
-//            let (target_pos, dir_vector) = Skills::limit_vector_into_range(
-//                &char_state.pos(),
-//                &target_pos,
-//                skill_cast_attrs.casting_range,
-//            );
-//            let new_state = CharState::CastingSkill(CastingSkillData {
-//                target_entity,
-//                cast_started: now,
-//                cast_ends: now.add(casting_time_seconds),
-//                can_move: false,
-//                skill,
-//                target_area_pos: match skill_def.get_skill_target_type() {
-//                    SkillTargetType::Area | SkillTargetType::Directional => Some(target_pos),
-//                    _ => None,
-//                },
-//                char_to_skill_dir_when_casted: dir_vector,
-//            });
-//            let dir = if is_self_cast || target_entity.map(|it| it == self_char_id).is_some() {
-//                // skill on self, don't change direction
-//                char_state.dir()
-//            } else {
-//                let char_pos = char_state.pos();
-//                NextActionApplierSystem::determine_dir(&target_pos, &char_pos)
-//            };
-//            char_state.set_state(new_state, dir);
-//            *char_state.skill_cast_allowed_at.get_mut(&skill).unwrap() =
-//                now.add(skill_cast_attrs.cast_delay);
-//            return false;
-//        } else {
-//            log::debug!(
-//                "Casting request for '{:?}' was rejected, allowed: {}, can_cast: {}",
-//                skill,
-//                allowed,
-//                can_move
-//            );
-//            return !can_move; // try to repeat casting only when it was interrupted, but not when the target was invalid
-//        }
-//    }
-//}
+impl NextActionApplierSystem {
+    fn find_cast_target(
+        entities: &Entities,
+        char_state_storage: &WriteStorage<AuthorizedCharStateComponent>,
+        char_attr_storage: &WriteStorage<CharAttributesComponent>,
+        self_char_id: CharEntityId,
+        skill: Skills,
+        is_self_cast: bool,
+        target_pos: &Vec2,
+        rewind: Option<(&PositionHistory, u64)>,
+    ) -> Option<CharEntityId> {
+        if is_self_cast {
+            return Some(self_char_id);
+        }
+        let self_team = char_attr_storage.get(self_char_id.into())?.team;
+        let team_filter = |team: Team| match skill.get_skill_target_type() {
+            SkillTargetType::AnyEntity => true,
+            SkillTargetType::NoTarget | SkillTargetType::Area | SkillTargetType::Directional => {
+                false
+            }
+            SkillTargetType::OnlyAllyButNoSelf | SkillTargetType::OnlyAllyAndSelf => {
+                self_team.can_support(team)
+            }
+            SkillTargetType::OnlyEnemy => self_team.can_attack(team),
+        };
+        const MAX_TARGET_DISTANCE_FROM_CURSOR: f32 = 1.5;
+        let mut closest: Option<(CharEntityId, f32)> = None;
+        for (entity_id, char_state, char_attrs) in
+            (entities, char_state_storage, char_attr_storage).join()
+        {
+            let entity_id = CharEntityId::new(entity_id);
+            if char_state.state().is_dead()
+                || !team_filter(char_attrs.team)
+                || (entity_id == self_char_id
+                    && skill.get_skill_target_type() == SkillTargetType::OnlyAllyButNoSelf)
+            {
+                continue;
+            }
+            // the player aimed at where the target was on her screen
+            let pos = rewind
+                .and_then(|(history, tick)| history.get_pos(tick, entity_id))
+                .unwrap_or(char_state.pos());
+            let distance = (pos - target_pos).magnitude();
+            if distance <= MAX_TARGET_DISTANCE_FROM_CURSOR
+                && closest.map(|it| distance < it.1).unwrap_or(true)
+            {
+                closest = Some((entity_id, distance));
+            }
+        }
+        return closest.map(|it| it.0);
+    }
+
+    /// Returns true if the casting was accepted
+    pub fn try_cast_skill(
+        skill: Skills,
+        now: ElapsedTime,
+        configs: &CommonConfigs,
+        char_state: &mut AuthorizedCharStateComponent,
+        char_attrs: &mut CharAttributesComponent,
+        statuses: Option<&StatusesComponent>,
+        mouse_world_pos: &Vec2,
+        target_entity: Option<CharEntityId>,
+        self_char_id: CharEntityId,
+        is_self_cast: bool,
+    ) -> bool {
+        if char_attrs.is_skill_on_cooldown(skill, now) {
+            log::debug!("Casting request for '{:?}' was rejected, cooldown", skill);
+            return false;
+        }
+        let is_mounted = statuses.map(|it| it.is_mounted()).unwrap_or(false);
+        let skill_cast_attrs = skill.get_cast_attributes(&configs.skills, is_mounted);
+        let target_pos = if is_self_cast {
+            char_state.pos()
+        } else {
+            *mouse_world_pos
+        };
+        let distance = (char_state.pos() - target_pos).magnitude();
+        let allowed = Skills::is_casting_allowed_based_on_target(
+            skill.get_skill_target_type(),
+            skill_cast_attrs.casting_range,
+            self_char_id,
+            target_entity,
+            distance,
+        );
+        let can_cast = match char_state.state() {
+            CharState::Idle | CharState::Walking(_) => true,
+            CharState::CastingSkill(_) | CharState::Dead => false,
+        } && char_attrs.cannot_control_until.has_already_passed(now)
+            && statuses.map(|it| it.can_cast()).unwrap_or(true);
+        if allowed && can_cast {
+            log::debug!("Casting request for '{:?}' was allowed", skill);
+            let (target_pos, dir_vector) = Skills::limit_vector_into_range(
+                &char_state.pos(),
+                &target_pos,
+                skill_cast_attrs.casting_range,
+            );
+            let new_state = CharState::CastingSkill(CastingSkillData {
+                target_entity,
+                cast_started: now,
+                cast_ends: now.add(skill_cast_attrs.casting_time),
+                can_move: false,
+                skill,
+                target_area_pos: match skill.get_skill_target_type() {
+                    SkillTargetType::Area | SkillTargetType::Directional => Some(target_pos),
+                    _ => None,
+                },
+                char_to_skill_dir_when_casted: dir_vector,
+            });
+            let dir = if is_self_cast || target_entity.map(|it| it == self_char_id).unwrap_or(false)
+            {
+                // skill on self, don't change direction
+                char_state.dir()
+            } else {
+                CharDir::determine_dir(&target_pos, &char_state.pos())
+            };
+            char_state.target = None;
+            char_state.set_state(new_state, dir);
+            char_attrs
+                .skill_cast_allowed_at
+                .insert(skill, now.add(skill_cast_attrs.cast_delay));
+            return true;
+        } else {
+            log::debug!(
+                "Casting request for '{:?}' was rejected, allowed: {}, can_cast: {}",
+                skill,
+                allowed,
+                can_cast
+            );
+            return false;
+        }
+    }
+}
@@ -1,2 +1,8 @@
+pub mod attack_sys;
 pub mod char_state_sys;
+pub mod hp_mod_sys;
 pub mod intention_applier;
+pub mod position_history_sys;
+pub mod skill_sys;
+pub mod status_sys;
+pub mod structure_ai_sys;
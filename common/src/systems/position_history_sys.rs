@@ -0,0 +1,143 @@
+use crate::common::{EngineTime, Vec2, MAX_SECONDS_ALLOWED_FOR_SINGLE_FRAME};
+use crate::components::char::{AuthorizedCharStateComponent, CharEntityId};
+use specs::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+/// The positions of the chars in the last ticks, so the intentions of laggy clients can be
+/// validated against the world they saw when they made them
+pub struct PositionHistory {
+    // (tick, positions), the newest is at the back
+    ticks: VecDeque<(u64, HashMap<CharEntityId, Vec2>)>,
+    max_rewind_ticks: u64,
+    // how many ticks behind the present the player who controls the char sees the world
+    lags: HashMap<CharEntityId, u64>,
+}
+
+impl PositionHistory {
+    pub fn new(max_rewind_ms: u32) -> PositionHistory {
+        let max_rewind_ticks = PositionHistory::ticks_from_ms(max_rewind_ms);
+        PositionHistory {
+            ticks: VecDeque::with_capacity(max_rewind_ticks as usize + 1),
+            max_rewind_ticks,
+            lags: HashMap::new(),
+        }
+    }
+
+    pub fn ticks_from_ms(ms: u32) -> u64 {
+        (ms as f32 / 1000.0 / MAX_SECONDS_ALLOWED_FOR_SINGLE_FRAME) as u64
+    }
+
+    pub fn record(&mut self, tick: u64, positions: HashMap<CharEntityId, Vec2>) {
+        while self.ticks.len() > self.max_rewind_ticks as usize {
+            self.ticks.pop_front();
+        }
+        self.ticks.push_back((tick, positions));
+    }
+
+    /// The tick the world should be rewound to, it can't be older than the configured maximum
+    pub fn rewind_tick(&self, now: u64, requested_tick: u64) -> u64 {
+        return requested_tick
+            .min(now)
+            .max(now.saturating_sub(self.max_rewind_ticks));
+    }
+
+    /// None if the char did not exist at that tick or the tick is not in the history
+    pub fn get_pos(&self, tick: u64, char_id: CharEntityId) -> Option<Vec2> {
+        let (_tick, positions) = self.ticks.iter().rev().find(|(t, _)| *t == tick)?;
+        return positions.get(&char_id).cloned();
+    }
+
+    /// Updated by every intention of the player controlling `char_id`
+    pub fn set_lag(&mut self, char_id: CharEntityId, lag_ticks: u64) {
+        self.lags.insert(char_id, lag_ticks);
+    }
+
+    /// When the player controlling `char_id` leaves the game or the char is deleted
+    pub fn remove_lag(&mut self, char_id: CharEntityId) {
+        self.lags.remove(&char_id);
+    }
+
+    /// Where `char_id` was on the screen of the player controlling `observer`, so the attacks
+    /// and areas of the player hit what they saw. None if the observer is not a laggy player.
+    pub fn pos_seen_by(
+        &self,
+        now: u64,
+        observer: CharEntityId,
+        char_id: CharEntityId,
+    ) -> Option<Vec2> {
+        let lag = *self.lags.get(&observer)?;
+        return self.get_pos(self.rewind_tick(now, now.saturating_sub(lag)), char_id);
+    }
+}
+
+pub struct PositionHistorySystem;
+
+impl<'a> System<'a> for PositionHistorySystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, AuthorizedCharStateComponent>,
+        ReadExpect<'a, EngineTime>,
+        WriteExpect<'a, PositionHistory>,
+    );
+
+    fn run(&mut self, (entities, char_state_storage, time, mut history): Self::SystemData) {
+        let positions = (&entities, &char_state_storage)
+            .join()
+            .map(|(entity_id, char_state)| (CharEntityId::new(entity_id), char_state.pos()))
+            .collect();
+        history.record(time.tick, positions);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::v2;
+
+    #[test]
+    fn rewind_is_capped() {
+        // 10 ticks at 30 Hz
+        let mut history = PositionHistory::new(340);
+        let mut world = World::new();
+        let char_id = CharEntityId::new(world.create_entity().build());
+        for tick in 0..20 {
+            let mut positions = HashMap::new();
+            positions.insert(char_id, v2(tick as f32, 0.0));
+            history.record(tick, positions);
+        }
+        assert_eq!(history.rewind_tick(19, 15), 15);
+        assert_eq!(history.rewind_tick(19, 2), 9);
+        assert_eq!(history.rewind_tick(19, 25), 19);
+        assert_eq!(history.get_pos(15, char_id), Some(v2(15.0, 0.0)));
+        assert_eq!(history.get_pos(2, char_id), None);
+    }
+
+    #[test]
+    fn positions_are_seen_with_the_lag_of_the_observer() {
+        let mut history = PositionHistory::new(340);
+        let mut world = World::new();
+        let (observer, char_id) = (
+            CharEntityId::new(world.create_entity().build()),
+            CharEntityId::new(world.create_entity().build()),
+        );
+        for tick in 0..20 {
+            let mut positions = HashMap::new();
+            positions.insert(char_id, v2(tick as f32, 0.0));
+            history.record(tick, positions);
+        }
+        assert_eq!(history.pos_seen_by(19, observer, char_id), None);
+        history.set_lag(observer, 4);
+        assert_eq!(
+            history.pos_seen_by(19, observer, char_id),
+            Some(v2(15.0, 0.0))
+        );
+        // the lag can't be bigger than the history
+        history.set_lag(observer, 100);
+        assert_eq!(
+            history.pos_seen_by(19, observer, char_id),
+            Some(v2(9.0, 0.0))
+        );
+        history.remove_lag(observer);
+        assert_eq!(history.pos_seen_by(19, observer, char_id), None);
+    }
+}
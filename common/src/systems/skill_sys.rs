@@ -0,0 +1,168 @@
+use specs::prelude::*;
+use std::collections::HashMap;
+
+use crate::attack::HpModificationRequest;
+use crate::common::{EngineTime, Vec2};
+use crate::components::char::{
+    AuthorizedCharStateComponent, CharAttributesComponent, CharEntityId, Team,
+};
+use crate::components::manifestation::{
+    AreaHpModificationRequest, PushRequest, SkillManifestationComponent,
+    SkillManifestationUpdateParams, SkillManifestationUpdateResult, PUSH_DISPLACEMENT,
+};
+use crate::components::skills::{FinishedSkillCast, FinishedSkillCastParams};
+use crate::components::status::{
+    ApplyStatusInAreaRequest, ApplyStatusRequest, RemoveStatusRequest, StatusesComponent,
+};
+use crate::config::CommonConfigs;
+use crate::grf::gat::Gat;
+use crate::pathfinding;
+
+/// Finishes the skill casts, then spawns, updates and removes the skill manifestations (areas,
+/// projectiles) and applies the pushes they caused. It is run only by the server, the clients
+/// receive the manifestations in the snapshots.
+pub struct SkillManifestationSystem {
+    pushes: Vec<PushRequest>,
+}
+
+impl SkillManifestationSystem {
+    pub fn new() -> SkillManifestationSystem {
+        SkillManifestationSystem {
+            pushes: Vec::with_capacity(32),
+        }
+    }
+
+    /// Where the push moves the character, None if it would end up on an unwalkable cell
+    fn pushed_pos(gat: Option<&Gat>, pos: &Vec2, force: &Vec2) -> Option<Vec2> {
+        let new_pos = pos + force * PUSH_DISPLACEMENT;
+        return match gat {
+            Some(gat) => match pathfinding::cell_of(gat, &new_pos) {
+                Some((x, y)) if gat.is_walkable(x, y) => Some(new_pos),
+                _ => None,
+            },
+            None => Some(new_pos),
+        };
+    }
+}
+
+impl<'a> System<'a> for SkillManifestationSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, SkillManifestationComponent>,
+        WriteStorage<'a, AuthorizedCharStateComponent>,
+        WriteStorage<'a, CharAttributesComponent>,
+        ReadStorage<'a, StatusesComponent>,
+        ReadExpect<'a, EngineTime>,
+        ReadExpect<'a, CommonConfigs>,
+        Option<Read<'a, Gat>>,
+        // they are also sent to the clients, so they are not consumed here
+        Read<'a, Vec<FinishedSkillCast>>,
+        Write<'a, Vec<HpModificationRequest>>,
+        Write<'a, Vec<AreaHpModificationRequest>>,
+        Write<'a, Vec<ApplyStatusRequest>>,
+        Write<'a, Vec<RemoveStatusRequest>>,
+        Write<'a, Vec<ApplyStatusInAreaRequest>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut manifestation_storage,
+            mut auth_char_state_storage,
+            mut char_attr_storage,
+            statuses_storage,
+            time,
+            configs,
+            gat,
+            finished_casts,
+            mut hp_mod_requests,
+            mut area_hp_mod_requests,
+            mut apply_status_requests,
+            mut remove_status_requests,
+            mut area_status_requests,
+        ): Self::SystemData,
+    ) {
+        let now = time.now();
+        for finished_cast in finished_casts.iter() {
+            let caster_entity = finished_cast.caster_entity_id.into();
+            let caster_attrs = match char_attr_storage.get(caster_entity) {
+                Some(caster_attrs) => caster_attrs,
+                None => continue,
+            };
+            let manifestation = finished_cast.finish(&mut FinishedSkillCastParams {
+                now,
+                caster_team: caster_attrs.team,
+                caster_job_id: caster_attrs.job_id,
+                caster_is_mounted: statuses_storage
+                    .get(caster_entity)
+                    .map(|statuses| statuses.is_mounted())
+                    .unwrap_or(false),
+                configs: &configs,
+                hp_mod_requests: &mut hp_mod_requests,
+                apply_status_requests: &mut apply_status_requests,
+                remove_status_requests: &mut remove_status_requests,
+            });
+            if let Some(manifestation) = manifestation {
+                entities
+                    .build_entity()
+                    .with(manifestation, &mut manifestation_storage)
+                    .build();
+            }
+        }
+
+        let living_chars: HashMap<CharEntityId, (Vec2, Team)> =
+            (&entities, &auth_char_state_storage, &char_attr_storage)
+                .join()
+                .filter(|(_entity, char_state, _attrs)| char_state.state().is_alive())
+                .map(|(entity, char_state, attrs)| {
+                    (CharEntityId::new(entity), (char_state.pos(), attrs.team))
+                })
+                .collect();
+        for (entity, manifestation) in (&entities, &mut manifestation_storage).join() {
+            let result = manifestation.update(&mut SkillManifestationUpdateParams {
+                now,
+                dt: time.dt(),
+                chars: &living_chars,
+                hp_mod_requests: &mut hp_mod_requests,
+                area_hp_mod_requests: &mut area_hp_mod_requests,
+                area_status_requests: &mut area_status_requests,
+                pushes: &mut self.pushes,
+            });
+            if let SkillManifestationUpdateResult::RemoveIt = result {
+                if let Err(e) = entities.delete(entity) {
+                    log::warn!("Could not remove the skill manifestation: {:?}", e);
+                }
+            }
+        }
+
+        for push in self.pushes.drain(..) {
+            let dst_entity = push.dst_entity.into();
+            let allow_push = statuses_storage
+                .get(dst_entity)
+                .map(|statuses| statuses.allow_push())
+                .unwrap_or(true);
+            if !allow_push {
+                continue;
+            }
+            if let (Some(char_state), Some(attrs)) = (
+                auth_char_state_storage.get_mut(dst_entity),
+                char_attr_storage.get_mut(dst_entity),
+            ) {
+                if char_state.state().is_dead() {
+                    continue;
+                }
+                if let Some(new_pos) = SkillManifestationSystem::pushed_pos(
+                    gat.as_ref().map(|it| &**it),
+                    &char_state.pos(),
+                    &push.force,
+                ) {
+                    char_state.set_pos(new_pos);
+                }
+                attrs
+                    .cannot_control_until
+                    .run_at_least_until_seconds(now, push.duration);
+            }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use specs::prelude::*;
+
+use crate::attack::{HpModificationRequest, HpModificationType};
+use crate::common::{EngineTime, Vec2};
+use crate::components::char::{
+    AuthorizedCharStateComponent, CharAttributesComponent, CharEntityId, StatusNature, Team,
+};
+use crate::components::manifestation::AreaHpModificationRequest;
+use crate::components::status::{
+    ApplyStatusInAreaRequest, ApplyStatusRequest, RemoveStatusRequest, RemoveStatusRequestPayload,
+    StatusUpdateParams, StatusesComponent,
+};
+use crate::systems::position_history_sys::PositionHistory;
+
+/// Applies, updates and removes the statuses of the characters and recalculates their attributes
+/// from them. It also resolves the area hp modifications into the ones of the affected
+/// characters. It is run only by the server, the clients receive the statuses in the snapshots.
+pub struct StatusSystem;
+
+impl StatusSystem {
+    /// The characters in the area who can be affected by something with `nature`
+    fn chars_in_area<'b>(
+        living_chars: &'b [(CharEntityId, Team, Vec2)],
+        caster_team: Team,
+        nature: StatusNature,
+        contains: impl Fn(&Vec2) -> bool + 'b,
+        except: Option<CharEntityId>,
+    ) -> impl Iterator<Item = CharEntityId> + 'b {
+        living_chars
+            .iter()
+            .filter(move |(char_id, team, pos)| {
+                Some(*char_id) != except
+                    && caster_team.is_compatible(nature, *team)
+                    && contains(pos)
+            })
+            .map(|(char_id, _team, _pos)| *char_id)
+    }
+
+    /// The areas of the players hit the characters where they were on the screen of the
+    /// caster, see PositionHistory::pos_seen_by
+    fn chars_seen_by(
+        living_chars: &[(CharEntityId, Team, Vec2)],
+        position_history: Option<&PositionHistory>,
+        tick: u64,
+        observer: CharEntityId,
+    ) -> Vec<(CharEntityId, Team, Vec2)> {
+        living_chars
+            .iter()
+            .map(|(char_id, team, pos)| {
+                let seen_pos = position_history
+                    .and_then(|history| history.pos_seen_by(tick, observer, *char_id))
+                    .unwrap_or(*pos);
+                (*char_id, *team, seen_pos)
+            })
+            .collect()
+    }
+}
+
+impl<'a> System<'a> for StatusSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, StatusesComponent>,
+        WriteStorage<'a, AuthorizedCharStateComponent>,
+        WriteStorage<'a, CharAttributesComponent>,
+        ReadExpect<'a, EngineTime>,
+        Write<'a, Vec<ApplyStatusRequest>>,
+        Write<'a, Vec<RemoveStatusRequest>>,
+        Write<'a, Vec<ApplyStatusInAreaRequest>>,
+        Write<'a, Vec<HpModificationRequest>>,
+        Write<'a, Vec<AreaHpModificationRequest>>,
+        Option<Read<'a, PositionHistory>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut statuses_storage,
+            mut auth_char_state_storage,
+            mut char_attr_storage,
+            time,
+            mut apply_status_requests,
+            mut remove_status_requests,
+            mut area_status_requests,
+            mut hp_mod_requests,
+            mut area_hp_mod_requests,
+            position_history,
+        ): Self::SystemData,
+    ) {
+        let now = time.now();
+        let living_chars: Vec<(CharEntityId, Team, Vec2)> =
+            (&entities, &char_attr_storage, &auth_char_state_storage)
+                .join()
+                .filter(|(_entity, _attrs, char_state)| char_state.state().is_alive())
+                .map(|(entity, attrs, char_state)| {
+                    (CharEntityId::new(entity), attrs.team, char_state.pos())
+                })
+                .collect();
+
+        // the ones created during the update are processed in the next tick
+        let area_requests = std::mem::replace(&mut *area_status_requests, Vec::new());
+        for area_request in area_requests {
+            let seen_chars = StatusSystem::chars_seen_by(
+                &living_chars,
+                position_history.as_ref().map(|it| &**it),
+                time.tick,
+                area_request.source_entity_id,
+            );
+            let targets = StatusSystem::chars_in_area(
+                &seen_chars,
+                area_request.caster_team,
+                area_request.status.nature(),
+                |pos| area_request.shape.contains_char(&area_request.pos, pos),
+                area_request.except,
+            );
+            for target_entity_id in targets {
+                apply_status_requests.push(ApplyStatusRequest {
+                    source_entity_id: area_request.source_entity_id,
+                    target_entity_id,
+                    status: area_request.status.clone(),
+                });
+            }
+        }
+
+        for request in apply_status_requests.drain(..) {
+            let is_alive = auth_char_state_storage
+                .get(request.target_entity_id.into())
+                .map(|it| it.state().is_alive())
+                .unwrap_or(false);
+            if !is_alive {
+                continue;
+            }
+            if let Some(statuses) = statuses_storage.get_mut(request.target_entity_id.into()) {
+                statuses.add(request.status);
+            }
+        }
+
+        for request in remove_status_requests.drain(..) {
+            if let Some(statuses) = statuses_storage.get_mut(request.target_entity_id.into()) {
+                match request.payload {
+                    RemoveStatusRequestPayload::ByNature(nature) => {
+                        statuses.remove_by_nature(nature)
+                    }
+                    RemoveStatusRequestPayload::ByDiscr(discr) => statuses.remove(discr),
+                }
+            }
+        }
+
+        for (entity, statuses, char_state, attrs) in (
+            &entities,
+            &mut statuses_storage,
+            &mut auth_char_state_storage,
+            &mut char_attr_storage,
+        )
+            .join()
+        {
+            if char_state.state().is_dead() {
+                continue;
+            }
+            let finished_statuses = statuses.update(&mut StatusUpdateParams {
+                self_char_id: CharEntityId::new(entity),
+                char_state,
+                now,
+                hp_mod_requests: &mut hp_mod_requests,
+                area_hp_mod_requests: &mut area_hp_mod_requests,
+                area_status_requests: &mut area_status_requests,
+            });
+            if finished_statuses != 0 {
+                statuses.remove_statuses(finished_statuses);
+            }
+            attrs.calculated_attribs = attrs.base_attributes.apply(statuses.calc_attributes());
+            attrs.hp = attrs.hp.min(attrs.calculated_attribs.max_hp);
+        }
+
+        for area_hp_mod in area_hp_mod_requests.drain(..) {
+            let src_team = match char_attr_storage.get(area_hp_mod.src_entity.into()) {
+                Some(src_attrs) => src_attrs.team,
+                None => continue,
+            };
+            let nature = match area_hp_mod.typ {
+                HpModificationType::Heal(_) => StatusNature::Supportive,
+                _ => StatusNature::Harmful,
+            };
+            let seen_chars = StatusSystem::chars_seen_by(
+                &living_chars,
+                position_history.as_ref().map(|it| &**it),
+                time.tick,
+                area_hp_mod.src_entity,
+            );
+            let targets = StatusSystem::chars_in_area(
+                &seen_chars,
+                src_team,
+                nature,
+                |pos| area_hp_mod.shape.contains_char(&area_hp_mod.pos, pos),
+                area_hp_mod.except,
+            );
+            for dst_entity in targets {
+                hp_mod_requests.push(HpModificationRequest {
+                    src_entity: area_hp_mod.src_entity,
+                    dst_entity,
+                    typ: area_hp_mod.typ,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::v2;
+
+    #[test]
+    fn only_the_compatible_chars_in_the_area_are_affected() {
+        let mut world = World::new();
+        let caster = CharEntityId::new(world.create_entity().build());
+        let ally = CharEntityId::new(world.create_entity().build());
+        let enemy = CharEntityId::new(world.create_entity().build());
+        let far_enemy = CharEntityId::new(world.create_entity().build());
+        let chars = vec![
+            (caster, Team::Left, v2(0.0, 0.0)),
+            (ally, Team::Left, v2(1.0, 0.0)),
+            (enemy, Team::Right, v2(0.0, 1.0)),
+            (far_enemy, Team::Right, v2(10.0, 0.0)),
+        ];
+        let in_area = |nature: StatusNature, except: Option<CharEntityId>| -> Vec<CharEntityId> {
+            StatusSystem::chars_in_area(
+                &chars,
+                Team::Left,
+                nature,
+                |pos| pos.magnitude() < 2.0,
+                except,
+            )
+            .collect()
+        };
+        assert_eq!(in_area(StatusNature::Harmful, None), vec![enemy]);
+        assert_eq!(in_area(StatusNature::Supportive, None), vec![caster, ally]);
+        assert_eq!(in_area(StatusNature::Supportive, Some(caster)), vec![ally]);
+    }
+
+    #[test]
+    fn areas_hit_the_chars_where_the_caster_saw_them() {
+        let mut world = World::new();
+        let caster = CharEntityId::new(world.create_entity().build());
+        let enemy = CharEntityId::new(world.create_entity().build());
+        let mut history = PositionHistory::new(340);
+        for tick in 0..20 {
+            let mut positions = std::collections::HashMap::new();
+            positions.insert(enemy, v2(tick as f32, 0.0));
+            history.record(tick, positions);
+        }
+        let chars = vec![
+            (caster, Team::Left, v2(0.0, 0.0)),
+            (enemy, Team::Right, v2(19.0, 0.0)),
+        ];
+        assert_eq!(
+            StatusSystem::chars_seen_by(&chars, Some(&history), 19, caster),
+            chars
+        );
+        history.set_lag(caster, 4);
+        assert_eq!(
+            StatusSystem::chars_seen_by(&chars, Some(&history), 19, caster)[1],
+            (enemy, Team::Right, v2(15.0, 0.0))
+        );
+        assert_eq!(StatusSystem::chars_seen_by(&chars, None, 19, caster), chars);
+    }
+}
@@ -0,0 +1,149 @@
+use specs::prelude::*;
+
+use crate::attack::{DamageDisplayType, HpModificationRequest, HpModificationType, WeaponType};
+use crate::common::{v2_to_p2, EngineTime, Vec2};
+use crate::components::char::{
+    AuthorizedCharStateComponent, CharAttributesComponent, CharEntityId, Team,
+};
+use crate::components::structure::StructureComponent;
+
+/// The towers and the core shoot at the enemies around them. It is run only by the server,
+/// since the structures can't be controlled by anyone.
+pub struct StructureAiSystem;
+
+impl StructureAiSystem {
+    /// The target of a stationary attacker: the current one as long as it is in range, otherwise
+    /// the closest enemy in range.
+    /// `living_chars` are the id, team and position of the characters which are alive.
+    pub fn choose_target(
+        current_target: Option<CharEntityId>,
+        center: &Vec2,
+        radius: f32,
+        self_team: Team,
+        living_chars: impl Iterator<Item = (CharEntityId, Team, Vec2)>,
+    ) -> Option<CharEntityId> {
+        let mut closest = None;
+        let mut closest_distance = 2000.0;
+        let center = v2_to_p2(center);
+        for (char_id, team, pos) in living_chars {
+            if !team.is_enemy_to(self_team) || (pos.x - center.x).abs() > radius {
+                continue;
+            }
+            let distance = nalgebra::distance(&center, &v2_to_p2(&pos));
+            if distance > radius {
+                continue;
+            }
+            if current_target == Some(char_id) {
+                return current_target;
+            }
+            if distance < closest_distance {
+                closest_distance = distance;
+                closest = Some(char_id);
+            }
+        }
+        return closest;
+    }
+}
+
+impl<'a> System<'a> for StructureAiSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, StructureComponent>,
+        WriteStorage<'a, CharAttributesComponent>,
+        ReadStorage<'a, AuthorizedCharStateComponent>,
+        ReadExpect<'a, EngineTime>,
+        Write<'a, Vec<HpModificationRequest>>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut structure_storage,
+            mut char_attr_storage,
+            auth_char_state_storage,
+            time,
+            mut hp_mod_requests,
+        ): Self::SystemData,
+    ) {
+        let now = time.now();
+        let living_chars: Vec<(CharEntityId, Team, Vec2)> =
+            (&entities, &char_attr_storage, &auth_char_state_storage)
+                .join()
+                .filter(|(_entity, _attrs, char_state)| char_state.state().is_alive())
+                .map(|(entity, attrs, char_state)| {
+                    (CharEntityId::new(entity), attrs.team, char_state.pos())
+                })
+                .collect();
+
+        for (entity, structure, attrs, char_state) in (
+            &entities,
+            &mut structure_storage,
+            &mut char_attr_storage,
+            &auth_char_state_storage,
+        )
+            .join()
+        {
+            let attribs = &attrs.calculated_attribs;
+            if char_state.state().is_dead() || attribs.attack_damage == 0 {
+                structure.target = None;
+                continue;
+            }
+            let radius = attribs.attack_range.as_f32() * 2.0;
+            structure.target = StructureAiSystem::choose_target(
+                structure.target,
+                &char_state.pos(),
+                radius,
+                attrs.team,
+                living_chars.iter().cloned(),
+            );
+            if let Some(target) = structure.target {
+                if attrs.attack_delay_ends_at.has_already_passed(now) {
+                    hp_mod_requests.push(HpModificationRequest {
+                        src_entity: CharEntityId::new(entity),
+                        dst_entity: target,
+                        typ: HpModificationType::BasicDamage(
+                            attribs.attack_damage as u32,
+                            DamageDisplayType::SingleNumber,
+                            WeaponType::Arrow,
+                        ),
+                    });
+                    attrs.attack_delay_ends_at =
+                        now.add_seconds(1.0 / attribs.attack_speed.as_f32());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::v2;
+
+    #[test]
+    fn the_current_target_is_kept_while_it_is_in_range() {
+        let mut world = World::new();
+        let far_enemy = CharEntityId::new(world.create_entity().build());
+        let close_enemy = CharEntityId::new(world.create_entity().build());
+        let ally = CharEntityId::new(world.create_entity().build());
+        let chars = vec![
+            (far_enemy, Team::Right, v2(8.0, 0.0)),
+            (close_enemy, Team::Right, v2(3.0, 0.0)),
+            (ally, Team::Left, v2(1.0, 0.0)),
+        ];
+        let choose = |current_target, radius| {
+            StructureAiSystem::choose_target(
+                current_target,
+                &v2(0.0, 0.0),
+                radius,
+                Team::Left,
+                chars.iter().cloned(),
+            )
+        };
+        assert_eq!(choose(None, 10.0), Some(close_enemy));
+        assert_eq!(choose(Some(far_enemy), 10.0), Some(far_enemy));
+        assert_eq!(choose(Some(far_enemy), 5.0), Some(close_enemy));
+        assert_eq!(choose(Some(ally), 2.0), None);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::common::Vec2;
+use crate::grf::gat::Gat;
+use crate::pathfinding::{self, Cell};
+
+/// The cells of the map which are seen by the characters of a team.
+/// The non-snipable cells block the sight, though the blocking cells themselves are seen.
+pub struct TeamVision {
+    width: usize,
+    height: usize,
+    visible: Vec<bool>,
+    /// The cells which have ever been visible, they remain explored after the team lost sight
+    /// of them
+    explored: Vec<bool>,
+}
+
+impl TeamVision {
+    pub fn new(gat: &Gat) -> TeamVision {
+        let width = gat.width as usize;
+        let height = gat.height as usize;
+        TeamVision {
+            width,
+            height,
+            visible: vec![false; width * height],
+            explored: vec![false; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Hides every cell, the vision is rebuilt from the characters after it in every frame
+    pub fn clear(&mut self) {
+        for it in self.visible.iter_mut() {
+            *it = false;
+        }
+    }
+
+    /// Makes visible the cells within `sight_radius` around `pos` which are in line of sight
+    pub fn reveal(&mut self, gat: &Gat, pos: &Vec2, sight_radius: f32) {
+        let center = match pathfinding::cell_of(gat, pos) {
+            Some(cell) => cell,
+            None => return,
+        };
+        let radius = sight_radius.max(0.0).ceil() as usize;
+        let radius_sq = sight_radius * sight_radius;
+        let min_x = center.0.saturating_sub(radius);
+        let max_x = (center.0 + radius).min(self.width - 1);
+        let min_y = center.1.saturating_sub(radius);
+        let max_y = (center.1 + radius).min(self.height - 1);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let index = y * self.width + x;
+                if self.visible[index] {
+                    continue;
+                }
+                let dx = x as f32 - center.0 as f32;
+                let dy = y as f32 - center.1 as f32;
+                if dx * dx + dy * dy > radius_sq {
+                    continue;
+                }
+                if is_in_line_of_sight(gat, center, (x, y)) {
+                    self.visible[index] = true;
+                    self.explored[index] = true;
+                }
+            }
+        }
+    }
+
+    pub fn is_visible(&self, pos: &Vec2) -> bool {
+        let (x, y) = (pos.x, -pos.y);
+        if x < 0.0 || y < 0.0 {
+            return false;
+        }
+        return self.is_cell_visible((x as usize, y as usize));
+    }
+
+    pub fn is_cell_visible(&self, cell: Cell) -> bool {
+        self.index_of(cell)
+            .map(|index| self.visible[index])
+            .unwrap_or(false)
+    }
+
+    pub fn is_cell_explored(&self, cell: Cell) -> bool {
+        self.index_of(cell)
+            .map(|index| self.explored[index])
+            .unwrap_or(false)
+    }
+
+    fn index_of(&self, cell: Cell) -> Option<usize> {
+        if cell.0 >= self.width || cell.1 >= self.height {
+            return None;
+        }
+        return Some(cell.1 * self.width + cell.0);
+    }
+}
+
+/// Walks the cells between `from` and `to` with Bresenham's line algorithm, all of them have to
+/// be snipable
+fn is_in_line_of_sight(gat: &Gat, from: Cell, to: Cell) -> bool {
+    let (mut x, mut y) = (from.0 as i32, from.1 as i32);
+    let (to_x, to_y) = (to.0 as i32, to.1 as i32);
+    let dx = (to_x - x).abs();
+    let dy = -(to_y - y).abs();
+    let step_x = if x < to_x { 1 } else { -1 };
+    let step_y = if y < to_y { 1 } else { -1 };
+    let mut err = dx + dy;
+    loop {
+        if x == to_x && y == to_y {
+            return true;
+        }
+        // the character can see out of the cell it stands on even if it is not snipable
+        let is_start = x == from.0 as i32 && y == from.1 as i32;
+        if !is_start && !gat.is_snipable(x as usize, y as usize) {
+            return false;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += step_x;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += step_y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::v2;
+
+    #[test]
+    fn walls_block_the_sight_but_are_seen() {
+        let gat = Gat::from_rows(&[
+            "......", //
+            "..#...", //
+            "......",
+        ]);
+        let mut vision = TeamVision::new(&gat);
+        vision.reveal(&gat, &v2(0.5, -1.5), 10.0);
+        assert!(vision.is_cell_visible((1, 1)));
+        assert!(vision.is_cell_visible((2, 1)));
+        assert!(!vision.is_cell_visible((3, 1)));
+        assert!(!vision.is_cell_visible((5, 1)));
+        // around the corner of the wall
+        assert!(vision.is_cell_visible((4, 0)));
+        assert!(vision.is_visible(&v2(4.5, -2.5)));
+        assert!(!vision.is_visible(&v2(4.5, -1.5)));
+        // outside of the map
+        assert!(!vision.is_visible(&v2(-0.5, -1.5)));
+        assert!(!vision.is_cell_visible((6, 1)));
+    }
+
+    #[test]
+    fn explored_cells_remain_explored_after_losing_sight() {
+        let gat = Gat::from_rows(&["...................."]);
+        let mut vision = TeamVision::new(&gat);
+        vision.reveal(&gat, &v2(0.5, -0.5), 5.0);
+        assert!(vision.is_cell_visible((5, 0)));
+        assert!(!vision.is_cell_visible((6, 0)));
+
+        vision.clear();
+        vision.reveal(&gat, &v2(19.5, -0.5), 5.0);
+        assert!(!vision.is_cell_visible((5, 0)));
+        assert!(vision.is_cell_explored((5, 0)));
+        assert!(!vision.is_cell_explored((6, 0)));
+        assert!(vision.is_cell_visible((14, 0)));
+    }
+}
@@ -0,0 +1,389 @@
+use nalgebra::Vector3;
+use rustarok_common::grf::act::ActionFile;
+use rustarok_common::grf::asset_loader::CommonAssetLoader;
+use rustarok_common::grf::binary_reader::{BinaryReader, ParseError};
+use rustarok_common::grf::gat::Gat;
+use rustarok_common::grf::gnd::Gnd;
+use rustarok_common::grf::grf_cache;
+use rustarok_common::grf::rsm::{BoundingBox, Rsm};
+use rustarok_common::grf::rsw::Rsw;
+use rustarok_common::grf::spr::SpriteFile;
+use rustarok_common::grf::str::{KeyFrameType, StrFile};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Arc;
+
+const USAGE: &str = "Usage:
+    rustarok-assets list <source> [pattern]                   prints the entries matching the glob pattern (e.g. 'data/sprite/**/*.spr')
+    rustarok-assets dump <source> <name>                      prints the parsed content of a spr/act/rsm/gnd/rsw/str/gat entry as JSON
+    rustarok-assets extract <source> <name.spr> <dir> [pal]   writes the frames of a sprite into <dir> as PNG files
+
+<source> is a GRF archive, a zip archive or a directory which contains the 'data' directory,
+the same as the entries of grf_paths in config.toml.
+The names are case insensitive, '/' and '\\' are both accepted as separators.";
+
+// the file table of the archive is cached next to the one of the game, not to invalidate it
+const CACHE_FILE_NAME: &str = "assets_tool.cache";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|it| it.as_str()).collect();
+    let result = match args.as_slice() {
+        ["list", source] => open(source).and_then(|loader| list(&loader, "**")),
+        ["list", source, pattern] => open(source).and_then(|loader| list(&loader, pattern)),
+        ["dump", source, name] => open(source).and_then(|loader| dump(&loader, name)),
+        ["extract", source, name, dir] => {
+            open(source).and_then(|loader| extract(&loader, name, dir, None))
+        }
+        ["extract", source, name, dir, palette] => {
+            open(source).and_then(|loader| extract(&loader, name, dir, Some(palette)))
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn open(source: &str) -> Result<CommonAssetLoader, String> {
+    let cache_path = grf_cache::default_cache_path().with_file_name(CACHE_FILE_NAME);
+    return CommonAssetLoader::new(&[source], &cache_path, 0)
+        .map_err(|e| format!("Could not open '{}': {}", source, e));
+}
+
+fn list(loader: &CommonAssetLoader, pattern: &str) -> Result<(), String> {
+    let pattern = glob::Pattern::new(&pattern.replace('\\', "/"))
+        .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+    let options = glob::MatchOptions {
+        case_sensitive: false,
+        require_literal_separator: false,
+        require_literal_leading_dot: false,
+    };
+    let mut names: Vec<String> = loader
+        .get_entry_names()
+        .into_iter()
+        .filter(|name| pattern.matches_with(&name.replace('\\', "/"), options))
+        .collect();
+    names.sort();
+    for name in names {
+        println!("{}", name);
+    }
+    return Ok(());
+}
+
+fn dump(loader: &CommonAssetLoader, name: &str) -> Result<(), String> {
+    let content = loader.get_content(name)?;
+    let buf = BinaryReader::from_shared(content, name);
+    let extension = Path::new(&name.replace('\\', "/"))
+        .extension()
+        .and_then(|it| it.to_str())
+        .map(|it| it.to_ascii_lowercase())
+        .unwrap_or_default();
+    let json = match extension.as_str() {
+        "spr" => dump_spr(buf),
+        "act" => ActionFile::load(buf).map(|it| dump_act(&it)),
+        "rsm" => Rsm::load(buf).map(|it| dump_rsm(&it)),
+        // the water level does not matter, only the counts of the water vertices are printed
+        "gnd" => Gnd::load(buf, 0.0, 1.0).map(|it| dump_gnd(&it)),
+        "rsw" => Rsw::load(buf).map(|it| dump_rsw(&it)),
+        "str" => StrFile::load(buf).map(|it| dump_str(&it)),
+        // Gat::load would read and write the '<map name>.cel' cache of the game, a dump must not
+        "gat" => Gat::parse(buf).map(|it| dump_gat(&it)),
+        _ => return Err(format!("'{}' is not a supported asset type", name)),
+    }
+    .map_err(|e| e.to_string())?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json).map_err(|e| e.to_string())?
+    );
+    return Ok(());
+}
+
+fn extract(
+    loader: &CommonAssetLoader,
+    name: &str,
+    dir: &str,
+    palette_name: Option<&str>,
+) -> Result<(), String> {
+    let palette = match palette_name {
+        Some(palette_name) => {
+            let palette = loader.get_content(palette_name)?;
+            if palette.len() < 1024 {
+                return Err(format!("'{}' is not a palette", palette_name));
+            }
+            Some(palette)
+        }
+        None => None,
+    };
+    let sprite = load_spr(
+        BinaryReader::from_shared(loader.get_content(name)?, name),
+        &palette,
+    )
+    .map_err(|e| e.to_string())?;
+
+    std::fs::create_dir_all(dir).map_err(|e| format!("Could not create '{}': {}", dir, e))?;
+    let normalized_name = name.replace('\\', "/");
+    let stem = Path::new(&normalized_name)
+        .file_stem()
+        .and_then(|it| it.to_str())
+        .unwrap_or("frame");
+    let mut count = 0;
+    for (index, frame) in sprite.frames.iter().enumerate() {
+        if frame.width == 0 || frame.height == 0 {
+            println!("Frame {} is empty, it was skipped", index);
+            continue;
+        }
+        let path = Path::new(dir).join(format!("{}_{}.png", stem, index));
+        let write_err =
+            |e: &dyn std::fmt::Display| format!("Could not write '{}': {}", path.display(), e);
+        let file = File::create(&path).map_err(|e| write_err(&e))?;
+        let mut encoder = png::Encoder::new(
+            BufWriter::new(file),
+            frame.width as u32,
+            frame.height as u32,
+        );
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| write_err(&e))?;
+        writer
+            .write_image_data(&frame.data)
+            .map_err(|e| write_err(&e))?;
+        count += 1;
+    }
+    println!("{} frames were written into '{}'", count, dir);
+    return Ok(());
+}
+
+fn load_spr(
+    mut buf: BinaryReader,
+    palette: &Option<Arc<Vec<u8>>>,
+) -> Result<SpriteFile, ParseError> {
+    let (version, indexed_frame_count, rgba_frame_count) = SpriteFile::read_header(&mut buf)?;
+    return SpriteFile::load(
+        buf,
+        palette.as_ref().map(|it| &it[0..1024]),
+        version,
+        indexed_frame_count,
+        rgba_frame_count,
+    );
+}
+
+fn vec3(v: &Vector3<f32>) -> Value {
+    json!([v.x, v.y, v.z])
+}
+
+fn bounding_box(bbox: &BoundingBox) -> Value {
+    json!({
+        "min": vec3(&bbox.min),
+        "max": vec3(&bbox.max),
+        "center": vec3(&bbox.center),
+    })
+}
+
+fn dump_spr(mut buf: BinaryReader) -> Result<Value, ParseError> {
+    let (version, indexed_frame_count, rgba_frame_count) = SpriteFile::read_header(&mut buf)?;
+    let sprite = SpriteFile::load(buf, None, version, indexed_frame_count, rgba_frame_count)?;
+    Ok(json!({
+        "version": version,
+        "indexed_frame_count": indexed_frame_count,
+        "rgba_frame_count": rgba_frame_count,
+        // the indexed frames come first, they are converted to RGBA by the loader
+        "frames": sprite.frames.iter().enumerate().map(|(index, frame)| json!({
+            "type": if index < indexed_frame_count { "indexed" } else { "rgba" },
+            "width": frame.width,
+            "height": frame.height,
+        })).collect::<Vec<_>>(),
+    }))
+}
+
+fn dump_act(act: &ActionFile) -> Value {
+    json!({
+        "sounds": act.sounds,
+        "actions": act.actions.iter().map(|action| json!({
+            "delay": action.delay,
+            "duration": action.duration,
+            "frames": action.frames.iter().map(|frame| json!({
+                "sound": frame.sound,
+                "positions": frame.positions,
+                "layers": frame.layers.iter().map(|layer| json!({
+                    "pos": layer.pos,
+                    "sprite_frame_index": layer.sprite_frame_index,
+                    "is_mirror": layer.is_mirror,
+                    "scale": layer.scale,
+                    "color": layer.color,
+                    "angle": layer.angle,
+                    "spr_type": layer.spr_type,
+                    "width": layer.width,
+                    "height": layer.height,
+                })).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn dump_rsm(rsm: &Rsm) -> Value {
+    json!({
+        "version": rsm.version,
+        "anim_len": rsm.anim_len,
+        "shade_type": rsm.shade_type,
+        "alpha": rsm.alpha,
+        "fps": rsm.fps,
+        "texture_names": rsm.texture_names,
+        "root_nodes": rsm.root_node_indices.iter().map(|&i| &rsm.nodes[i].name).collect::<Vec<_>>(),
+        "bounding_box": bounding_box(&rsm.bounding_box),
+        "pos_key_frame_count": rsm.pos_key_frames.len(),
+        "volume_box_count": rsm.volume_boxes.len(),
+        "nodes": rsm.nodes.iter().map(|node| json!({
+            "name": node.name,
+            "parent_name": node.parent_name,
+            "textures": node.textures,
+            "offset": vec3(&node.offset),
+            "pos": vec3(&node.pos),
+            "rotangle": node.rotangle,
+            "rotaxis": vec3(&node.rotaxis),
+            "scale": vec3(&node.scale),
+            "bounding_box": bounding_box(&node.bounding_box),
+            "vertex_count": node.vertices.len(),
+            "face_count": node.faces.len(),
+            "pos_key_frame_count": node.pos_key_frames.len(),
+            "rot_key_frame_count": node.rot_key_frames.len(),
+            "scale_key_frame_count": node.scale_key_frames.len(),
+            "texture_animation_count": node.texture_animations.len(),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn dump_gnd(gnd: &Gnd) -> Value {
+    json!({
+        "version": gnd.version,
+        "width": gnd.width,
+        "height": gnd.height,
+        "zoom": gnd.zoom,
+        "texture_names": gnd.texture_names,
+        "lightmap_count": gnd.lightmaps.count,
+        "lightmap_per_cell": gnd.lightmaps.per_cell,
+        "tile_count": gnd.tiles.len(),
+        "surface_count": gnd.surfaces.len(),
+        "mesh_vertex_count": gnd.mesh.len(),
+        "water_vertex_count": gnd.water_vert_count,
+        "water": gnd.water.as_ref().map(|water| json!({
+            "level": water.water.level,
+            "split_width": water.split_width,
+            "split_height": water.split_height,
+            "zone_levels": water.zones.iter().map(|zone| zone.level).collect::<Vec<_>>(),
+        })),
+    })
+}
+
+fn dump_rsw(rsw: &Rsw) -> Value {
+    json!({
+        "version": rsw.version,
+        "build_number": rsw.build_number,
+        "files": {
+            "ini": rsw.file.ini,
+            "gnd": rsw.file.gnd,
+            "gat": rsw.file.gat,
+            "src": rsw.file.src,
+        },
+        "ground": {
+            "top": rsw.ground.top,
+            "bottom": rsw.ground.bottom,
+            "left": rsw.ground.left,
+            "right": rsw.ground.right,
+        },
+        "water": {
+            "level": rsw.water.level,
+            "type": rsw.water.typ,
+            "wave_height": rsw.water.wave_height,
+            "wave_speed": rsw.water.wave_speed,
+            "wave_pitch": rsw.water.wave_pitch,
+            "anim_speed": rsw.water.anim_speed,
+        },
+        "light": {
+            "longitude": rsw.light.longitude,
+            "latitude": rsw.light.latitude,
+            "diffuse": rsw.light.diffuse,
+            "ambient": rsw.light.ambient,
+            "opacity": rsw.light.opacity,
+        },
+        "models": rsw.models.iter().map(|model| json!({
+            "name": model.name,
+            "filename": model.filename,
+            "node_name": model.node_name,
+            "anim_type": model.anim_type,
+            "anim_speed": model.anim_speed,
+            "block_type": model.block_type,
+            "pos": vec3(&model.pos),
+            "rot": vec3(&model.rot),
+            "scale": vec3(&model.scale),
+        })).collect::<Vec<_>>(),
+        "lights": rsw.lights.iter().map(|light| json!({
+            "name": light.name,
+            "pos": vec3(&light.pos),
+            "color": light.color,
+            "range": light.range,
+        })).collect::<Vec<_>>(),
+        "sounds": rsw.sounds.iter().map(|sound| json!({
+            "name": sound.name,
+            "file": sound.file,
+            "pos": vec3(&sound.pos),
+            "vol": sound.vol,
+            "width": sound.width,
+            "height": sound.height,
+            "range": sound.range,
+            "cycle": sound.cycle,
+        })).collect::<Vec<_>>(),
+        "effects": rsw.effects.iter().map(|effect| json!({
+            "name": effect.name,
+            "pos": vec3(&effect.pos),
+            "id": effect.id,
+            "delay": effect.delay,
+            "param": effect.param,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn dump_str(str_file: &StrFile) -> Value {
+    json!({
+        "fps": str_file.fps,
+        "max_key": str_file.max_key,
+        "texture_names": str_file.texture_names,
+        "layers": str_file.layers.iter().map(|layer| json!({
+            "key_frames": layer.key_frames.iter().map(|key_frame| json!({
+                "frame": key_frame.frame,
+                "type": match key_frame.typ {
+                    KeyFrameType::Start => "start",
+                    KeyFrameType::End => "end",
+                },
+                "pos": key_frame.pos,
+                "xy": key_frame.xy,
+                "color": key_frame.color,
+                "angle": key_frame.angle,
+                "src_alpha": key_frame.src_alpha,
+                "dst_alpha": key_frame.dst_alpha,
+                "texture_index": key_frame.texture_index,
+            })).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+    })
+}
+
+fn dump_gat(gat: &Gat) -> Value {
+    let blocking_rectangles = gat.blocking_rectangles();
+    let walkable_cell_count = (0..gat.height as usize)
+        .flat_map(|y| (0..gat.width as usize).map(move |x| (x, y)))
+        .filter(|(x, y)| gat.is_walkable(*x, *y))
+        .count();
+    json!({
+        "version": gat.version,
+        "width": gat.width,
+        "height": gat.height,
+        "walkable_cell_count": walkable_cell_count,
+        "blocking_rectangle_count": blocking_rectangles.len(),
+    })
+}
@@ -0,0 +1,93 @@
+use rustarok_common::grf::writer::GrfWriter;
+use std::path::Path;
+
+const USAGE: &str = "Usage:
+    rustarok-grf create <archive.grf> <dir>             creates an archive from the content of <dir>
+    rustarok-grf add <archive.grf> <name> <file>        adds or replaces <name> (e.g. data\\sprite\\poring.spr)
+    rustarok-grf add-dir <archive.grf> <dir> [prefix]   adds or replaces every file under <dir>
+    rustarok-grf delete <archive.grf> <name>...         deletes the given entries
+    rustarok-grf compact <archive.grf>                  rebuilds the archive without the unused space
+    rustarok-grf list <archive.grf>                     prints the names of the entries
+
+The paths inside <dir> become the entry names, so <dir> should contain the 'data' directory.
+Every modification rebuilds and compacts the archive.";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|it| it.as_str()).collect();
+    let result = match args.as_slice() {
+        ["create", archive, dir] => create(archive, dir),
+        ["add", archive, name, file] => add(archive, name, file),
+        ["add-dir", archive, dir] => add_dir(archive, dir, ""),
+        ["add-dir", archive, dir, prefix] => add_dir(archive, dir, prefix),
+        _ if args.len() > 2 && args[0] == "delete" => delete(args[1], &args[2..]),
+        ["compact", archive] => GrfWriter::open(archive).and_then(|writer| writer.write(archive)),
+        ["list", archive] => list(archive),
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn create(archive: &str, dir: &str) -> Result<(), String> {
+    let mut writer = GrfWriter::new();
+    let count = writer.add_dir(dir, "")?;
+    writer.write(archive)?;
+    println!("{} files were written into '{}'", count, archive);
+    return Ok(());
+}
+
+fn add(archive: &str, name: &str, file: &str) -> Result<(), String> {
+    let mut writer = open_or_new(archive)?;
+    let content = std::fs::read(file).map_err(|e| format!("Could not read '{}': {}", file, e))?;
+    let replaced = writer.exists(name);
+    writer.add(name, content)?;
+    writer.write(archive)?;
+    println!(
+        "'{}' was {}",
+        name,
+        if replaced { "replaced" } else { "added" }
+    );
+    return Ok(());
+}
+
+fn add_dir(archive: &str, dir: &str, prefix: &str) -> Result<(), String> {
+    let mut writer = open_or_new(archive)?;
+    let count = writer.add_dir(dir, prefix)?;
+    writer.write(archive)?;
+    println!("{} files were added or replaced", count);
+    return Ok(());
+}
+
+fn delete(archive: &str, names: &[&str]) -> Result<(), String> {
+    let mut writer = GrfWriter::open(archive)?;
+    for name in names {
+        if !writer.remove(name) {
+            return Err(format!("'{}' is not in the archive", name));
+        }
+    }
+    writer.write(archive)?;
+    println!("{} entries were deleted", names.len());
+    return Ok(());
+}
+
+fn list(archive: &str) -> Result<(), String> {
+    let writer = GrfWriter::open(archive)?;
+    for name in writer.entry_names() {
+        println!("{}", name);
+    }
+    return Ok(());
+}
+
+fn open_or_new(archive: &str) -> Result<GrfWriter, String> {
+    return if Path::new(archive).exists() {
+        GrfWriter::open(archive)
+    } else {
+        Ok(GrfWriter::new())
+    };
+}
@@ -16,31 +16,62 @@
 extern crate specs_derive;
 
 mod components;
+mod match_lifecycle;
 
 use specs;
 use strum;
 
+use crate::match_lifecycle::{MatchConfig, MatchEvent, MatchLifecycle};
 use log::LevelFilter;
 use notify::Watcher;
+use rustarok_common::attack::{HpModificationRequest, HpModificationResult};
 use rustarok_common::common::{
-    measure_time, v2, EngineTime, MAX_DURATION_ALLOWED_FOR_SINGLE_FRAME,
+    measure_time, EngineTime, Vec2, MAX_DURATION_ALLOWED_FOR_SINGLE_FRAME,
     MAX_SECONDS_ALLOWED_FOR_SINGLE_FRAME,
 };
 use rustarok_common::components::char::{
-    AuthorizedCharStateComponent, CharEntityId, CharOutlook, CharType, ControllerEntityId, JobId,
-    ServerEntityId, Sex, Team,
+    AuthorizedCharStateComponent, CharAttributesComponent, CharEntityId, CharOutlook, CharState,
+    CharType, ControllerEntityId, JobId, ServerEntityId, Sex, Team, WalkingPathComponent,
 };
 use rustarok_common::components::controller::ControllerComponent;
 use rustarok_common::components::job_ids::JobSpriteId;
-use rustarok_common::components::snapshot::CharSnapshot;
+use rustarok_common::components::manifestation::{
+    AreaHpModificationRequest, SkillManifestationComponent,
+};
+use rustarok_common::components::skills::FinishedSkillCast;
+use rustarok_common::components::snapshot::{
+    encode_world_snapshot, CharSnapshot, SnapshotHistory, WorldSnapshot,
+};
+use rustarok_common::components::status::{
+    ApplyStatusInAreaRequest, ApplyStatusRequest, RemoveStatusRequest, StatusesComponent,
+};
+use rustarok_common::components::structure::StructureComponent;
+use rustarok_common::config::{get_base_attributes, get_sight_radius, CommonConfigs};
 use rustarok_common::grf::asset_loader::CommonAssetLoader;
-use rustarok_common::packets::from_server::{AckEntry, FromServerPacket};
+use rustarok_common::grf::binary_reader::BinaryReader;
+use rustarok_common::grf::gat::Gat;
+use rustarok_common::grf::grf_cache;
+use rustarok_common::packets::from_server::{AckEntry, FromServerPacket, MatchPhase};
+use rustarok_common::packets::handshake::{
+    map_hash, skills_config_hash, validate_welcome, HandshakeRejectReason,
+    CAPABILITY_SNAPSHOT_DELTA, CAPABILITY_UDP_FRAGMENTATION, PROTOCOL_VERSION,
+};
+use rustarok_common::packets::link_conditioner::NetworkSimulationConfig;
 use rustarok_common::packets::to_server::ToServerPacket;
-use rustarok_common::packets::{PacketHandlerThread, SocketId};
+use rustarok_common::packets::{PacketHandlerThread, SocketId, Transport};
+use rustarok_common::scenario::{ObjectiveKind, Scenario};
+use rustarok_common::systems::attack_sys::BasicAttackSystem;
 use rustarok_common::systems::char_state_sys::CharacterStateUpdateSystem;
+use rustarok_common::systems::hp_mod_sys::HpModificationSystem;
 use rustarok_common::systems::intention_applier::NextActionApplierSystem;
+use rustarok_common::systems::position_history_sys::{PositionHistory, PositionHistorySystem};
+use rustarok_common::systems::skill_sys::SkillManifestationSystem;
+use rustarok_common::systems::status_sys::StatusSystem;
+use rustarok_common::systems::structure_ai_sys::StructureAiSystem;
+use rustarok_common::vision::TeamVision;
 use serde::Deserialize;
 use specs::prelude::*;
+use std::collections::HashSet;
 use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::str::FromStr;
 use std::time::{Duration, Instant};
@@ -51,10 +82,15 @@ pub const SIMULATION_FREQ: u64 = 30;
 pub struct AppConfig {
     pub map_name: String,
     pub log_level: String,
-    pub start_pos_x: f32,
-    pub start_pos_y: f32,
     pub grf_paths: Vec<String>,
+    pub grf_cache_path: Option<String>,
+    pub asset_cache_size_mb: usize,
     pub server_port: u16,
+    pub transport: Transport,
+    pub network_simulation: NetworkSimulationConfig,
+    /// intentions are validated against the world at most this much in the past
+    pub max_lag_compensation_ms: u32,
+    pub match_config: MatchConfig,
 }
 
 impl AppConfig {
@@ -65,6 +101,12 @@ impl AppConfig {
     }
 }
 
+fn load_common_configs() -> Result<CommonConfigs, config::ConfigError> {
+    let mut s = config::Config::new();
+    s.merge(config::File::with_name("config-runtime"))?;
+    return s.try_into();
+}
+
 fn bind_server(port: u16) -> TcpListener {
     let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], port))).unwrap();
     listener
@@ -92,28 +134,33 @@ struct RemoteClient {
     last_action_tick: u64,
     last_command_id: u32,
     name: String,
+    // packets other than Welcome are ignored until the handshake succeeds
+    handshake_accepted: bool,
+    // the snapshots sent to the client, the acknowledged ones are used as delta baselines
+    sent_snapshots: SnapshotHistory,
+    acked_snapshot_tick: Option<u64>,
+    // the chars the client has received in a NewEntity and the manifestations it has been sent,
+    // they are forgotten when they leave the vision of its team
+    known_entities: HashSet<ServerEntityId>,
 }
 
 // only the server must implement it
 fn to_server_id(id: CharEntityId) -> ServerEntityId {
-    unsafe { std::mem::transmute(id) }
+    ServerEntityId::new(id)
 }
 
-fn init_connection(
-    world: &mut specs::World,
-    socket_handler: &mut PacketHandlerThread<ToServerPacket, FromServerPacket>,
-    incoming_conn: (TcpStream, SocketAddr),
-    configs: &AppConfig,
-) -> RemoteClient {
-    let socket_id = socket_handler.handle_socket(incoming_conn.0);
-
+fn init_connection(socket_id: SocketId, sock_addr: SocketAddr) -> RemoteClient {
     RemoteClient {
         socket_id,
         controller_id: None,
-        sock_addr: incoming_conn.1,
+        sock_addr,
         last_action_tick: 0,
         last_command_id: 0,
         name: "unknown".to_owned(),
+        handshake_accepted: false,
+        sent_snapshots: SnapshotHistory::new(),
+        acked_snapshot_tick: None,
+        known_entities: HashSet::new(),
     }
 }
 
@@ -135,26 +182,106 @@ fn main() {
     );
     log::info!(">>> Loading GRF files");
     let (elapsed, asset_loader) = measure_time(|| {
-        CommonAssetLoader::new(config.grf_paths.as_slice())
-            .expect("Could not open grf files. Please configure them in 'config.toml'")
+        CommonAssetLoader::new(
+            config.grf_paths.as_slice(),
+            &grf_cache::cache_path(&config.grf_cache_path),
+            config.asset_cache_size_mb * 1024 * 1024,
+        )
+        .expect("Could not open grf files. Please configure them in 'config.toml'")
     });
     log::info!("<<< GRF loading: {}ms", elapsed.as_millis());
 
+    let common_configs =
+        load_common_configs().expect("Could not load gameplay config file ('config-runtime.toml')");
+    let config_hash = skills_config_hash(&common_configs.skills);
+    // the server is headless, only the walkability data of the map is needed
+    let (map_content_hash, gat) = {
+        let gat_content = asset_loader
+            .get_content(&format!("data\\{}.gat", config.map_name))
+            .expect("Could not load the map, please check 'map_name' in 'config.toml'");
+        let map_content_hash = map_hash(&config.map_name, &gat_content);
+        let gat_name = format!("data\\{}.gat", config.map_name);
+        let gat = Gat::parse(BinaryReader::from_shared(gat_content, &gat_name))
+            .unwrap_or_else(|e| panic!("Could not load the map: {}", e));
+        (map_content_hash, gat)
+    };
+    let scenario = Scenario::load(&config.map_name)
+        .unwrap_or_else(|e| panic!("Could not load the scenario of the map: {}", e));
+    for problem in scenario.unwalkable_positions(&gat) {
+        log::warn!("Not walkable: {}", problem);
+    }
+    let mut match_lifecycle = MatchLifecycle::new(config.match_config.clone());
+    let required_capabilities = match config.transport {
+        Transport::Tcp => CAPABILITY_SNAPSHOT_DELTA,
+        Transport::Udp => CAPABILITY_SNAPSHOT_DELTA | CAPABILITY_UDP_FRAGMENTATION,
+    };
+
     let mut ecs_world = create_ecs_world();
     ecs_world.add_resource(EngineTime::new());
+    ecs_world.add_resource(common_configs);
+    ecs_world.add_resource(Vec::<FinishedSkillCast>::with_capacity(64));
+    ecs_world.add_resource(Vec::<HpModificationRequest>::with_capacity(128));
+    ecs_world.add_resource(Vec::<HpModificationResult>::with_capacity(128));
+    ecs_world.add_resource(Vec::<AreaHpModificationRequest>::with_capacity(32));
+    ecs_world.add_resource(Vec::<ApplyStatusRequest>::with_capacity(32));
+    ecs_world.add_resource(Vec::<RemoveStatusRequest>::with_capacity(32));
+    ecs_world.add_resource(Vec::<ApplyStatusInAreaRequest>::with_capacity(32));
+    ecs_world.add_resource(PositionHistory::new(config.max_lag_compensation_ms));
+    let mut team_visions = [
+        (Team::Left, TeamVision::new(&gat)),
+        (Team::Right, TeamVision::new(&gat)),
+    ];
+    ecs_world.add_resource(gat);
+    let structures = spawn_structures(&mut ecs_world, &scenario);
     let mut ecs_dispatcher = specs::DispatcherBuilder::new()
         .with(NextActionApplierSystem, "char_control", &[])
         .with(CharacterStateUpdateSystem, "char_state", &["char_control"])
+        .with(
+            SkillManifestationSystem::new(),
+            "skill_manifestation",
+            &["char_state"],
+        )
+        .with(
+            BasicAttackSystem::new(),
+            "basic_attack",
+            &["skill_manifestation"],
+        )
+        .with(
+            StatusSystem,
+            "status",
+            &["skill_manifestation", "basic_attack"],
+        )
+        .with(StructureAiSystem, "structure_ai", &["status"])
+        .with(
+            HpModificationSystem::new(),
+            "hp_mod",
+            &["status", "structure_ai"],
+        )
+        .with(PositionHistorySystem, "position_history", &["char_state"])
         .build();
 
     let mut packet_handler_thread =
         PacketHandlerThread::<ToServerPacket, FromServerPacket>::start_thread(64);
-
-    let mut socket_listener = bind_server(config.server_port);
-    log::info!("bind socket on port {}", config.server_port);
+    packet_handler_thread.set_network_simulation(config.network_simulation.clone());
+
+    let mut socket_listener = match config.transport {
+        Transport::Tcp => Some(bind_server(config.server_port)),
+        Transport::Udp => {
+            packet_handler_thread
+                .bind_udp(SocketAddr::from(([127, 0, 0, 1], config.server_port)))
+                .expect("Could not bind udp socket");
+            None
+        }
+    };
+    log::info!(
+        "bind {:?} socket on port {}",
+        config.transport,
+        config.server_port
+    );
 
     log::info!("waiting for incoming connections...");
     let mut tmp_vec = Vec::with_capacity(256);
+    let mut new_connections = Vec::with_capacity(8);
     const MAX_PLAYER_NUM: usize = 64;
 
     let mut remote_clients = Vec::<Option<RemoteClient>>::with_capacity(64);
@@ -171,14 +298,15 @@ fn main() {
         let start = Instant::now();
         let tick = ecs_world.read_resource::<EngineTime>().tick;
 
-        if let Some(connecting_client) = accept_connection(&mut socket_listener) {
-            log::info!("{} has joined", connecting_client.1);
-            let remote_client = init_connection(
-                &mut ecs_world,
-                &mut packet_handler_thread,
-                connecting_client,
-                &config,
-            );
+        if let Some(socket_listener) = &mut socket_listener {
+            if let Some((stream, sock_addr)) = accept_connection(socket_listener) {
+                new_connections.push((packet_handler_thread.handle_socket(stream), sock_addr));
+            }
+        }
+        packet_handler_thread.receive_new_connections_into(&mut new_connections);
+        for (socket_id, sock_addr) in new_connections.drain(..) {
+            log::info!("{} has joined", sock_addr);
+            let remote_client = init_connection(socket_id, sock_addr);
             if remote_client.socket_id.as_usize() >= remote_clients.len() {
                 remote_clients.push(Some(remote_client));
             } else {
@@ -197,40 +325,137 @@ fn main() {
                     } else {
                         log::debug!("Client({:?}) has been disconnected", socket_id);
                     }
+                    match_lifecycle.take_pending_team(socket_id.as_usize());
                     let remote_client = remote_clients[socket_id.as_usize()].as_ref().unwrap();
                     if let Some(controller_id) = remote_client.controller_id {
+                        let controlled_entity = ecs_world
+                            .read_storage::<ControllerComponent>()
+                            .get(controller_id.into())
+                            .map(|controller| controller.controlled_entity);
+                        if let Some(controlled_entity) = controlled_entity {
+                            match_lifecycle.remove_player(controlled_entity);
+                            ecs_world
+                                .write_resource::<PositionHistory>()
+                                .remove_lag(controlled_entity);
+                            // it disappears from the snapshots of the others as well
+                            if let Err(e) = ecs_world.delete_entity(controlled_entity.into()) {
+                                log::error!("The char of the client could not be deleted: {}", e);
+                            }
+                        }
                         ecs_world.delete_entity(controller_id.into());
-                        remote_clients[socket_id.as_usize()] = None;
                     }
+                    remote_clients[socket_id.as_usize()] = None;
                 }
-                ToServerPacket::Welcome { name } => {
-                    log::info!("{} welcomed ^^", name);
+                ToServerPacket::Welcome {
+                    protocol_version,
+                    capabilities,
+                    config_hash: client_config_hash,
+                    name,
+                } => {
                     let remote_client = remote_clients[socket_id.as_usize()].as_mut().unwrap();
+                    if remote_client.handshake_accepted {
+                        log::warn!("{} has sent a second Welcome", remote_client.name);
+                        continue;
+                    }
+                    if let Err(reason) = validate_welcome(
+                        protocol_version,
+                        capabilities,
+                        client_config_hash,
+                        required_capabilities,
+                        config_hash,
+                    ) {
+                        log::info!("{} has been rejected: {:?}", name, reason);
+                        packet_handler_thread
+                            .send(socket_id, FromServerPacket::HandshakeRejected { reason });
+                        continue;
+                    }
+                    log::info!("{} welcomed ^^", name);
+                    // the team is assigned here so Init can contain the spawn point of the team
+                    let team = match_lifecycle.assign_team(socket_id.as_usize());
                     remote_client.name = name;
+                    remote_client.handshake_accepted = true;
                     packet_handler_thread.send(
                         socket_id,
                         FromServerPacket::Init {
                             //    let map_name = "bat_a01"; // battle ground
-                            map_name: "prontera".to_string(),
-                            start_x: config.start_pos_x,
-                            start_y: config.start_pos_y,
+                            map_name: config.map_name.clone(),
+                            start_x: scenario.spawn_point(team).x,
+                            start_y: scenario.spawn_point(team).y,
                         },
                     );
                 }
+                ToServerPacket::IncompatibleWelcome { protocol_version } => {
+                    let reason = HandshakeRejectReason::ProtocolVersionMismatch {
+                        server_version: PROTOCOL_VERSION,
+                        client_version: protocol_version,
+                    };
+                    log::info!("Client({:?}) has been rejected: {:?}", socket_id, reason);
+                    packet_handler_thread
+                        .send(socket_id, FromServerPacket::HandshakeRejected { reason });
+                }
+                _ if !remote_clients[socket_id.as_usize()]
+                    .as_ref()
+                    .unwrap()
+                    .handshake_accepted =>
+                {
+                    log::debug!(
+                        "Client({:?}) has sent a packet before the handshake",
+                        socket_id
+                    );
+                }
                 ToServerPacket::Ping => packet_handler_thread
                     .send(socket_id, FromServerPacket::Pong { server_tick: tick }),
-                ToServerPacket::ReadyForGame => {
-                    let (char_id, char_snapshot) = {
+                ToServerPacket::ReadyForGame {
+                    map_hash: client_map_hash,
+                } => {
+                    if client_map_hash != map_content_hash {
+                        let remote_client = remote_clients[socket_id.as_usize()].as_mut().unwrap();
+                        log::info!("{} has been rejected: map mismatch", remote_client.name);
+                        remote_client.handshake_accepted = false;
+                        packet_handler_thread.send(
+                            socket_id,
+                            FromServerPacket::HandshakeRejected {
+                                reason: HandshakeRejectReason::MapMismatch {
+                                    map_name: config.map_name.clone(),
+                                },
+                            },
+                        );
+                        continue;
+                    }
+                    let team = match match_lifecycle.take_pending_team(socket_id.as_usize()) {
+                        Some(team) => team,
+                        None => {
+                            let team = match_lifecycle.assign_team(socket_id.as_usize());
+                            match_lifecycle.take_pending_team(socket_id.as_usize());
+                            team
+                        }
+                    };
+                    {
                         let remote_client = remote_clients[socket_id.as_usize()].as_mut().unwrap();
-                        log::info!("{} is ready to play", remote_client.name);
+                        log::info!("{} is ready to play in team {:?}", remote_client.name, team);
+                        let base_attributes = {
+                            let configs = ecs_world.read_resource::<CommonConfigs>();
+                            get_base_attributes(JobId::CRUSADER, &configs.stats, &configs.skills)
+                        };
                         let char_id = ecs_world
                             .create_entity()
-                            .with(AuthorizedCharStateComponent::new(v2(
-                                config.start_pos_x,
-                                config.start_pos_y,
-                            )))
+                            .with(AuthorizedCharStateComponent::new(
+                                scenario.spawn_point(team),
+                            ))
+                            .with(CharAttributesComponent::new(
+                                team,
+                                JobId::CRUSADER,
+                                base_attributes,
+                            ))
+                            .with(StatusesComponent::new())
                             .build();
                         let char_id = CharEntityId::from(char_id);
+                        match_lifecycle.add_player(
+                            char_id,
+                            to_server_id(char_id),
+                            remote_client.name.clone(),
+                            team,
+                        );
                         let network_player_id = ecs_world
                             .create_entity()
                             .with(ControllerComponent::new(char_id))
@@ -249,84 +474,30 @@ fn main() {
                             FromServerPacket::Ack {
                                 cid: 0,
                                 ack_tick: 0,
+                                baseline_tick: None,
                                 entries: vec![AckEntry::EntityState {
                                     id: to_server_id(char_id),
-                                    char_snapshot: char_snapshot.clone(),
+                                    char_snapshot,
                                 }],
                             },
                         );
 
-                        // send her the player list
-                        {
-                            for (other_char_id, other_char_state) in
-                                (&ecs_world.entities(), &auth_char_storage).join()
-                            {
-                                let other_char_id = CharEntityId::new(other_char_id);
-                                if other_char_id == char_id {
-                                    continue;
-                                }
-                                let other_char_snapshot = CharSnapshot::from(char_state);
-                                packet_handler_thread.send(
-                                    remote_client.socket_id,
-                                    FromServerPacket::NewEntity {
-                                        id: to_server_id(other_char_id),
-                                        name: "???".to_owned(),
-                                        team: Team::Left,
-                                        typ: CharType::Player,
-                                        outlook: CharOutlook::Player {
-                                            job_sprite_id: JobSpriteId::from_job_id(
-                                                JobId::CRUSADER,
-                                            ),
-                                            head_index: 0,
-                                            sex: Sex::Male,
-                                        },
-                                        job_id: JobId::CRUSADER,
-                                        max_hp: 100,
-                                        state: other_char_snapshot.clone(),
-                                    },
-                                );
-                            }
-                        }
-
-                        (char_id, char_snapshot)
-                    };
-
-                    // inform others about this player
-                    {
-                        let remote_client = remote_clients[socket_id.as_usize()].as_ref().unwrap();
-                        for other_remote_client in remote_clients.iter() {
-                            if let Some(other_client) = other_remote_client {
-                                if let Some(other_id) = other_client.controller_id {
-                                    if other_id != remote_client.controller_id.unwrap() {
-                                        // not self
-                                        packet_handler_thread.send(
-                                            other_client.socket_id,
-                                            FromServerPacket::NewEntity {
-                                                id: to_server_id(char_id),
-                                                name: remote_client.name.clone(),
-                                                team: Team::Left,
-                                                typ: CharType::Player,
-                                                outlook: CharOutlook::Player {
-                                                    job_sprite_id: JobSpriteId::from_job_id(
-                                                        JobId::CRUSADER,
-                                                    ),
-                                                    head_index: 0,
-                                                    sex: Sex::Male,
-                                                },
-                                                job_id: JobId::CRUSADER,
-                                                max_hp: 100,
-                                                state: char_snapshot.clone(),
-                                            },
-                                        );
-                                    }
-                                }
-                            }
-                        }
+                        // the other entities are sent in NewEntity packets when they get into
+                        // the vision of their team, see send_new_entities
+                        remote_client.known_entities.insert(to_server_id(char_id));
                     }
+                    packet_handler_thread.send(
+                        socket_id,
+                        FromServerPacket::MatchPhaseChanged(
+                            match_lifecycle
+                                .current_phase(ecs_world.read_resource::<EngineTime>().now()),
+                        ),
+                    );
                 }
                 ToServerPacket::Intention {
                     cid,
                     client_tick,
+                    interpolation_delay_ms,
                     intention,
                 } => {
                     let remote_client = remote_clients[socket_id.as_usize()].as_mut().unwrap();
@@ -336,60 +507,216 @@ fn main() {
                         let controller: &mut ControllerComponent =
                             controller_storage.get_mut(controller_id.into()).unwrap();
                         controller.intention = Some(intention);
+                        // client_tick is in the timeline of the Acks of this client, which
+                        // advances together with the server's tick, and the other chars
+                        // are rendered interpolation_delay_ms behind it
+                        let client_tick_on_server = tick
+                            .saturating_sub(
+                                remote_client.last_action_tick.saturating_sub(client_tick),
+                            )
+                            .saturating_sub(PositionHistory::ticks_from_ms(interpolation_delay_ms));
+                        let mut position_history = ecs_world.write_resource::<PositionHistory>();
+                        let rewind_tick = position_history.rewind_tick(tick, client_tick_on_server);
+                        controller.rewind_to_tick = Some(rewind_tick);
+                        position_history.set_lag(controller.controlled_entity, tick - rewind_tick);
                         remote_client.last_command_id = cid;
                         log::debug!("client tick: {}, cid: {}", client_tick, cid);
                     } else {
                         // TODO: close connection
                     }
                 }
+                ToServerPacket::SnapshotAck { ack_tick } => {
+                    let remote_client = remote_clients[socket_id.as_usize()].as_mut().unwrap();
+                    if remote_client
+                        .acked_snapshot_tick
+                        .map(|it| it < ack_tick)
+                        .unwrap_or(true)
+                    {
+                        remote_client.acked_snapshot_tick = Some(ack_tick);
+                        remote_client.sent_snapshots.remove_older_than(ack_tick);
+                    }
+                }
             }
         }
 
         ecs_dispatcher.dispatch(&mut ecs_world.res);
         ecs_world.maintain();
 
+        let mut gameplay_entries = collect_gameplay_events(&mut ecs_world, &mut match_lifecycle);
+        let now = ecs_world.read_resource::<EngineTime>().now();
+        for char_id in match_lifecycle.take_respawns(now) {
+            if let Some(team) = match_lifecycle.team_of(char_id) {
+                gameplay_entries.extend(revive_char(
+                    &ecs_world,
+                    char_id,
+                    scenario.spawn_point(team),
+                ));
+            }
+        }
+        if let Some(match_event) = match_lifecycle.update(now) {
+            if match_event == MatchEvent::ResetWorld {
+                for (char_id, team) in match_lifecycle.players() {
+                    gameplay_entries.extend(revive_char(
+                        &ecs_world,
+                        char_id,
+                        scenario.spawn_point(team),
+                    ));
+                }
+                for (char_id, pos) in structures.iter() {
+                    gameplay_entries.extend(revive_char(&ecs_world, *char_id, *pos));
+                }
+            }
+            let phase = match_lifecycle.current_phase(now);
+            if let MatchPhase::Ended { winner, .. } = &phase {
+                log::info!("Match ended, winner: {:?}", winner);
+            }
+            for remote_client in remote_clients.iter() {
+                if let Some(remote_client) = remote_client {
+                    if remote_client.controller_id.is_some() {
+                        packet_handler_thread.send(
+                            remote_client.socket_id,
+                            FromServerPacket::MatchPhaseChanged(phase.clone()),
+                        );
+                    }
+                }
+            }
+        }
+
+        let (reliable_events, gameplay_entries): (Vec<AckEntry>, Vec<AckEntry>) = gameplay_entries
+            .into_iter()
+            .partition(|entry| entry.is_reliable_event());
+
+        for (team, vision) in team_visions.iter_mut() {
+            update_team_vision(&ecs_world, *team, vision);
+        }
+        // A team receives only its own characters, the structures and what its vision reveals,
+        // so a modified client can't show the enemies hiding in the fog of war
+        let (world_snapshot, team_snapshots) = {
+            let mut world_snapshot = WorldSnapshot::default();
+            let mut team_snapshots = [WorldSnapshot::default(), WorldSnapshot::default()];
+            let auth_char_storage = ecs_world.read_storage::<AuthorizedCharStateComponent>();
+            let char_attr_storage = ecs_world.read_storage::<CharAttributesComponent>();
+            let structure_storage = ecs_world.read_storage::<StructureComponent>();
+            let status_storage = ecs_world.read_storage::<StatusesComponent>();
+            let manifestation_storage = ecs_world.read_storage::<SkillManifestationComponent>();
+            for (char_id, char_state) in (&ecs_world.entities(), &auth_char_storage).join() {
+                let server_id = to_server_id(CharEntityId::from(char_id));
+                let statuses = status_storage.get(char_id);
+                world_snapshot.set_char(server_id, char_state);
+                if let Some(statuses) = statuses {
+                    world_snapshot.statuses.insert(server_id, statuses.clone());
+                }
+                let char_team = char_attr_storage.get(char_id).map(|it| it.team);
+                let is_structure = structure_storage.get(char_id).is_some();
+                for ((team, vision), team_snapshot) in
+                    team_visions.iter().zip(team_snapshots.iter_mut())
+                {
+                    if is_structure
+                        || char_team == Some(*team)
+                        || vision.is_visible(&char_state.pos())
+                    {
+                        team_snapshot.set_char(server_id, char_state);
+                        if let Some(statuses) = statuses {
+                            team_snapshot.statuses.insert(server_id, statuses.clone());
+                        }
+                    }
+                }
+            }
+            for (entity, manifestation) in (&ecs_world.entities(), &manifestation_storage).join() {
+                let server_id = to_server_id(CharEntityId::from(entity));
+                world_snapshot
+                    .manifestations
+                    .insert(server_id, manifestation.clone());
+                for ((team, vision), team_snapshot) in
+                    team_visions.iter().zip(team_snapshots.iter_mut())
+                {
+                    if manifestation.caster_team == *team || vision.is_visible(&manifestation.pos) {
+                        team_snapshot
+                            .manifestations
+                            .insert(server_id, manifestation.clone());
+                    }
+                }
+            }
+            (world_snapshot, team_snapshots)
+        };
+
         for remote_client in remote_clients.iter_mut() {
             if let Some(remote_client) = remote_client {
                 if let Some(controller_id) = remote_client.controller_id {
                     let controller_storage = ecs_world.read_storage::<ControllerComponent>();
                     let controller = controller_storage.get(controller_id.into()).unwrap();
-                    let auth_char_storage =
-                        ecs_world.read_storage::<AuthorizedCharStateComponent>();
-                    let char_state = auth_char_storage
-                        .get(controller.controlled_entity.into())
-                        .unwrap();
-                    log::debug!(
-                        "ack_tick: {}, x: {}, y: {}",
-                        remote_client.last_action_tick,
-                        char_state.pos().x,
-                        char_state.pos().y,
-                    );
+                    let controlled_id = to_server_id(controller.controlled_entity);
+                    if let Some(char_snapshot) = world_snapshot.get_char(controlled_id) {
+                        log::debug!(
+                            "ack_tick: {}, x: {}, y: {}",
+                            remote_client.last_action_tick,
+                            char_snapshot.state.pos().x,
+                            char_snapshot.state.pos().y,
+                        );
+                    }
 
-                    let mut entries = vec![AckEntry::EntityState {
-                        id: to_server_id(controller.controlled_entity),
-                        char_snapshot: CharSnapshot::from(char_state),
-                    }];
-                    for (other_char_id, other_char_state) in
-                        (&ecs_world.entities(), &auth_char_storage).join()
+                    let team_index = match_lifecycle
+                        .team_of(controller.controlled_entity)
+                        .and_then(|team| team_visions.iter().position(|(it, _)| *it == team));
+                    let (visible_snapshot, vision) = match team_index {
+                        Some(index) => (&team_snapshots[index], Some(&team_visions[index].1)),
+                        None => (&world_snapshot, None),
+                    };
+                    // the baseline might have been dropped already, then full states are sent
+                    let (baseline_tick, baseline) = match remote_client
+                        .acked_snapshot_tick
+                        .and_then(|tick| Some((tick, remote_client.sent_snapshots.get(tick)?)))
                     {
-                        let other_char_id = CharEntityId::from(other_char_id);
-                        if other_char_id == controller.controlled_entity.into() {
-                            continue;
-                        }
-                        entries.push(AckEntry::EntityState {
-                            id: to_server_id(other_char_id),
-                            char_snapshot: CharSnapshot::from(other_char_state),
-                        })
-                    }
+                        Some((tick, baseline)) => (Some(tick), Some(baseline)),
+                        None => (None, None),
+                    };
+                    let (mut entries, sent_snapshot) = encode_world_snapshot(
+                        visible_snapshot,
+                        baseline,
+                        controlled_id,
+                        &world_snapshot,
+                        &remote_client.known_entities,
+                    );
+                    remote_client
+                        .sent_snapshots
+                        .push(remote_client.last_action_tick, sent_snapshot);
+                    // after the encoding, which needs the entities known from the previous ticks
+                    send_new_entities(
+                        &ecs_world,
+                        &match_lifecycle,
+                        &packet_handler_thread,
+                        remote_client,
+                        visible_snapshot,
+                    );
+                    entries.extend(
+                        gameplay_entries
+                            .iter()
+                            .filter(|it| is_seen_by(it, visible_snapshot, vision))
+                            .cloned(),
+                    );
                     packet_handler_thread.send(
                         remote_client.socket_id,
                         FromServerPacket::Ack {
                             cid: remote_client.last_command_id,
                             ack_tick: remote_client.last_action_tick,
+                            baseline_tick,
                             entries,
                         },
                     );
                     remote_client.last_action_tick += 1;
+                    let seen_events: Vec<AckEntry> = reliable_events
+                        .iter()
+                        .filter(|it| is_seen_by(it, visible_snapshot, vision))
+                        .cloned()
+                        .collect();
+                    if !seen_events.is_empty() {
+                        packet_handler_thread.send(
+                            remote_client.socket_id,
+                            FromServerPacket::GameplayEvents {
+                                entries: seen_events,
+                            },
+                        );
+                    }
                 }
             }
         }
@@ -405,9 +732,222 @@ fn main() {
     }
 }
 
+/// Rebuilds the vision of the team from the sight of its living characters
+fn update_team_vision(ecs_world: &specs::World, team: Team, vision: &mut TeamVision) {
+    let gat = ecs_world.read_resource::<Gat>();
+    let configs = ecs_world.read_resource::<CommonConfigs>();
+    let auth_char_storage = ecs_world.read_storage::<AuthorizedCharStateComponent>();
+    let char_attr_storage = ecs_world.read_storage::<CharAttributesComponent>();
+    vision.clear();
+    for (char_state, attrs) in (&auth_char_storage, &char_attr_storage).join() {
+        if attrs.team != team || char_state.state().is_dead() {
+            continue;
+        }
+        let sight_radius = get_sight_radius(attrs.job_id, &configs.stats);
+        vision.reveal(&gat, &char_state.pos(), sight_radius);
+    }
+}
+
+/// Puts the char back to the given position with full hp
+fn revive_char(ecs_world: &specs::World, char_id: CharEntityId, pos: Vec2) -> Option<AckEntry> {
+    let mut auth_char_storage = ecs_world.write_storage::<AuthorizedCharStateComponent>();
+    let mut char_attr_storage = ecs_world.write_storage::<CharAttributesComponent>();
+    let char_state = auth_char_storage.get_mut(char_id.into())?;
+    let char_attrs = char_attr_storage.get_mut(char_id.into())?;
+    char_attrs.hp = char_attrs.calculated_attribs.max_hp;
+    char_state.set_pos(pos);
+    char_state.set_state(CharState::Idle, char_state.dir());
+    char_state.target = None;
+    if let Some(statuses) = ecs_world
+        .write_storage::<StatusesComponent>()
+        .get_mut(char_id.into())
+    {
+        statuses.remove_all();
+    }
+    // otherwise they would continue what they were doing before their death
+    for controller in (&mut ecs_world.write_storage::<ControllerComponent>()).join() {
+        if controller.controlled_entity == char_id {
+            controller.intention = None;
+        }
+    }
+    return Some(AckEntry::Respawn {
+        id: to_server_id(char_id),
+        hp: char_attrs.hp,
+    });
+}
+
+/// Creates the towers, inhibitors and cores of the scenario, they are revived at the same
+/// positions when a new match starts
+fn spawn_structures(
+    ecs_world: &mut specs::World,
+    scenario: &Scenario,
+) -> Vec<(CharEntityId, Vec2)> {
+    let mut structures = Vec::with_capacity(scenario.objectives.len());
+    for objective in scenario.objectives.iter() {
+        let structure = StructureComponent::new(objective, scenario);
+        let base_attributes = {
+            let configs = ecs_world.read_resource::<CommonConfigs>();
+            get_base_attributes(structure.job_id(), &configs.stats, &configs.skills)
+        };
+        let char_id = ecs_world
+            .create_entity()
+            .with(AuthorizedCharStateComponent::new(objective.pos))
+            .with(CharAttributesComponent::new(
+                objective.team,
+                structure.job_id(),
+                base_attributes,
+            ))
+            .with(StatusesComponent::new())
+            .with(structure)
+            .build();
+        structures.push((CharEntityId::from(char_id), objective.pos));
+    }
+    log::info!("{} structures have been placed", structures.len());
+    return structures;
+}
+
+/// Sends the entities which got into the vision of the team of the client for the first time or
+/// since they left it
+fn send_new_entities(
+    ecs_world: &specs::World,
+    match_lifecycle: &MatchLifecycle,
+    packet_handler_thread: &PacketHandlerThread<ToServerPacket, FromServerPacket>,
+    remote_client: &mut RemoteClient,
+    visible_snapshot: &WorldSnapshot,
+) {
+    remote_client.known_entities.retain(|id| {
+        visible_snapshot.chars.contains_key(id) || visible_snapshot.manifestations.contains_key(id)
+    });
+    remote_client
+        .known_entities
+        .extend(visible_snapshot.manifestations.keys().cloned());
+    let char_attr_storage = ecs_world.read_storage::<CharAttributesComponent>();
+    let structure_storage = ecs_world.read_storage::<StructureComponent>();
+    for (id, char_snapshot) in &visible_snapshot.chars {
+        if remote_client.known_entities.contains(id) {
+            continue;
+        }
+        let char_id = id.to_char_id();
+        let char_attrs = match char_attr_storage.get(char_id.into()) {
+            Some(char_attrs) => char_attrs,
+            None => continue,
+        };
+        let new_entity_packet = match structure_storage.get(char_id.into()) {
+            Some(structure) => FromServerPacket::NewEntity {
+                id: *id,
+                name: format!("{:?}", structure.kind),
+                team: char_attrs.team,
+                typ: CharType::Structure,
+                outlook: structure.outlook(),
+                job_id: structure.job_id(),
+                max_hp: char_attrs.calculated_attribs.max_hp,
+                state: char_snapshot.clone(),
+            },
+            None => FromServerPacket::NewEntity {
+                id: *id,
+                name: match_lifecycle.name_of(char_id).unwrap_or("???").to_owned(),
+                team: char_attrs.team,
+                typ: CharType::Player,
+                outlook: CharOutlook::Player {
+                    job_sprite_id: JobSpriteId::from_job_id(JobId::CRUSADER),
+                    head_index: 0,
+                    sex: Sex::Male,
+                },
+                job_id: JobId::CRUSADER,
+                max_hp: char_attrs.calculated_attribs.max_hp,
+                state: char_snapshot.clone(),
+            },
+        };
+        packet_handler_thread.send(remote_client.socket_id, new_entity_packet);
+        remote_client.known_entities.insert(*id);
+    }
+}
+
+/// The gameplay events of the hidden entities are not sent, they would reveal them
+fn is_seen_by(
+    entry: &AckEntry,
+    visible_snapshot: &WorldSnapshot,
+    vision: Option<&TeamVision>,
+) -> bool {
+    let is_visible = |pos: &Vec2| vision.map(|it| it.is_visible(pos)).unwrap_or(true);
+    match entry {
+        AckEntry::SkillCast {
+            caster,
+            caster_pos,
+            skill_pos,
+            ..
+        } => {
+            visible_snapshot.chars.contains_key(caster)
+                || is_visible(caster_pos)
+                || skill_pos.as_ref().map(is_visible).unwrap_or(false)
+        }
+        AckEntry::HpModification { dst, .. } => visible_snapshot.chars.contains_key(dst),
+        AckEntry::Respawn { id, .. } => visible_snapshot.chars.contains_key(id),
+        _ => true,
+    }
+}
+
+/// Converts the outcomes of the current tick into Ack entries which are sent to every client
+fn collect_gameplay_events(
+    ecs_world: &mut specs::World,
+    match_lifecycle: &mut MatchLifecycle,
+) -> Vec<AckEntry> {
+    let mut entries = Vec::new();
+    let finished_casts = std::mem::replace(
+        &mut *ecs_world.write_resource::<Vec<FinishedSkillCast>>(),
+        Vec::with_capacity(64),
+    );
+    for finished_cast in finished_casts {
+        entries.push(AckEntry::SkillCast {
+            caster: to_server_id(finished_cast.caster_entity_id),
+            skill: finished_cast.skill,
+            caster_pos: finished_cast.caster_pos,
+            skill_pos: finished_cast.skill_pos,
+            char_to_skill_dir: finished_cast.char_to_skill_dir,
+            target_entity: finished_cast.target_entity.map(to_server_id),
+        });
+    }
+
+    let hp_mod_results = std::mem::replace(
+        &mut *ecs_world.write_resource::<Vec<HpModificationResult>>(),
+        Vec::with_capacity(128),
+    );
+    let char_attr_storage = ecs_world.read_storage::<CharAttributesComponent>();
+    let structure_storage = ecs_world.read_storage::<StructureComponent>();
+    for hp_mod_result in hp_mod_results {
+        let dst_attrs = char_attr_storage.get(hp_mod_result.dst_entity.into());
+        let dst_hp = hp_mod_result.dst_hp;
+        match_lifecycle.on_hp_modification(
+            &hp_mod_result,
+            ecs_world.read_resource::<EngineTime>().now(),
+        );
+        let is_core = structure_storage
+            .get(hp_mod_result.dst_entity.into())
+            .map(|it| it.kind == ObjectiveKind::Core)
+            .unwrap_or(false);
+        if is_core && dst_hp <= 0 {
+            if let Some(dst_attrs) = dst_attrs {
+                match_lifecycle.on_core_destroyed(dst_attrs.team);
+            }
+        }
+        entries.push(AckEntry::HpModification {
+            src: to_server_id(hp_mod_result.src_entity),
+            dst: to_server_id(hp_mod_result.dst_entity),
+            typ: hp_mod_result.typ,
+            dst_hp,
+        });
+    }
+    return entries;
+}
+
 pub fn create_ecs_world() -> specs::World {
     let mut ecs_world = specs::World::new();
     ecs_world.register::<AuthorizedCharStateComponent>();
+    ecs_world.register::<CharAttributesComponent>();
     ecs_world.register::<ControllerComponent>();
+    ecs_world.register::<WalkingPathComponent>();
+    ecs_world.register::<StructureComponent>();
+    ecs_world.register::<StatusesComponent>();
+    ecs_world.register::<SkillManifestationComponent>();
     ecs_world
 }
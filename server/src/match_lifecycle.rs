@@ -0,0 +1,458 @@
+use rustarok_common::attack::{HpModificationResult, HpModificationResultType, HpModificationType};
+use rustarok_common::common::ElapsedTime;
+use rustarok_common::components::char::{CharEntityId, ServerEntityId, Team};
+use rustarok_common::packets::from_server::{MatchPhase, PlayerMatchStats};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MatchConfig {
+    /// the warmup starts when this many players are in the game
+    pub min_players: usize,
+    pub warmup_seconds: f32,
+    /// the first team which reaches it or destroys the enemy core wins, otherwise the team with
+    /// more kills when the time is up
+    pub kills_to_win: u32,
+    pub time_limit_seconds: f32,
+    pub respawn_seconds: f32,
+    /// the next match starts after the stats were shown for this long
+    pub restart_seconds: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    Lobby,
+    Warmup {
+        ends_at: ElapsedTime,
+    },
+    Running {
+        ends_at: ElapsedTime,
+    },
+    Ended {
+        winner: Option<Team>,
+        restart_at: ElapsedTime,
+    },
+}
+
+/// What the main loop has to do with the world after MatchLifecycle::update
+#[derive(Debug, PartialEq)]
+pub enum MatchEvent {
+    PhaseChanged,
+    /// The phase has changed and every char has to be revived at its team's start position
+    ResetWorld,
+}
+
+pub struct MatchLifecycle {
+    config: MatchConfig,
+    phase: Phase,
+    players: HashMap<CharEntityId, PlayerMatchStats>,
+    /// the teams of the welcomed clients which are still loading the map, indexed by their socket
+    pending_teams: HashMap<usize, Team>,
+    left_team_kills: u32,
+    right_team_kills: u32,
+    /// the team which has lost its core
+    destroyed_core: Option<Team>,
+    respawns: Vec<(CharEntityId, ElapsedTime)>,
+}
+
+impl MatchLifecycle {
+    pub fn new(config: MatchConfig) -> MatchLifecycle {
+        MatchLifecycle {
+            config,
+            phase: Phase::Lobby,
+            players: HashMap::with_capacity(64),
+            pending_teams: HashMap::with_capacity(16),
+            left_team_kills: 0,
+            right_team_kills: 0,
+            destroyed_core: None,
+            respawns: Vec::with_capacity(16),
+        }
+    }
+
+    /// The team with fewer players, Left in case of equality. The clients which have not joined
+    /// the game yet are counted as well, their team is kept until take_pending_team
+    pub fn assign_team(&mut self, socket_index: usize) -> Team {
+        self.pending_teams.remove(&socket_index);
+        let teams: Vec<Team> = self
+            .players
+            .values()
+            .map(|it| it.team)
+            .chain(self.pending_teams.values().cloned())
+            .collect();
+        let left_count = teams.iter().filter(|it| **it == Team::Left).count();
+        let right_count = teams.len() - left_count;
+        let team = if right_count < left_count {
+            Team::Right
+        } else {
+            Team::Left
+        };
+        self.pending_teams.insert(socket_index, team);
+        return team;
+    }
+
+    /// It must be called when the client joins the game or disconnects
+    pub fn take_pending_team(&mut self, socket_index: usize) -> Option<Team> {
+        self.pending_teams.remove(&socket_index)
+    }
+
+    pub fn add_player(
+        &mut self,
+        char_id: CharEntityId,
+        id: ServerEntityId,
+        name: String,
+        team: Team,
+    ) {
+        self.players.insert(
+            char_id,
+            PlayerMatchStats {
+                id,
+                name,
+                team,
+                kills: 0,
+                deaths: 0,
+                damage_done: 0,
+                healing_done: 0,
+            },
+        );
+    }
+
+    pub fn remove_player(&mut self, char_id: CharEntityId) {
+        self.players.remove(&char_id);
+        self.respawns.retain(|(id, _)| *id != char_id);
+    }
+
+    pub fn players(&self) -> Vec<(CharEntityId, Team)> {
+        self.players
+            .iter()
+            .map(|(char_id, stats)| (*char_id, stats.team))
+            .collect()
+    }
+
+    pub fn name_of(&self, char_id: CharEntityId) -> Option<&str> {
+        self.players.get(&char_id).map(|it| it.name.as_str())
+    }
+
+    pub fn team_of(&self, char_id: CharEntityId) -> Option<Team> {
+        self.players.get(&char_id).map(|it| it.team)
+    }
+
+    pub fn is_running(&self) -> bool {
+        match self.phase {
+            Phase::Running { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Only the events of a running match are counted, but the dead are revived in every phase
+    pub fn on_hp_modification(&mut self, result: &HpModificationResult, now: ElapsedTime) {
+        let value = match result.typ {
+            HpModificationResultType::Ok(typ) => typ,
+            HpModificationResultType::Blocked | HpModificationResultType::Absorbed => return,
+        };
+        let is_damage = match value {
+            HpModificationType::BasicDamage(..)
+            | HpModificationType::SpellDamage(..)
+            | HpModificationType::Poison(..) => true,
+            HpModificationType::Heal(..) => false,
+        };
+        let is_kill = is_damage && result.dst_hp <= 0;
+        if is_kill && self.players.contains_key(&result.dst_entity) {
+            self.respawns.push((
+                result.dst_entity,
+                now.add_seconds(self.config.respawn_seconds),
+            ));
+        }
+        if !self.is_running() {
+            return;
+        }
+        if let Some(src) = self.players.get_mut(&result.src_entity) {
+            match value {
+                HpModificationType::BasicDamage(dmg, ..)
+                | HpModificationType::SpellDamage(dmg, ..)
+                | HpModificationType::Poison(dmg) => src.damage_done += dmg,
+                HpModificationType::Heal(heal) => src.healing_done += heal,
+            }
+            // only the players count, the structures are not kills
+            if is_kill
+                && result.src_entity != result.dst_entity
+                && self.players.contains_key(&result.dst_entity)
+            {
+                src.kills += 1;
+                match src.team {
+                    Team::Left => self.left_team_kills += 1,
+                    Team::Right => self.right_team_kills += 1,
+                    _ => {}
+                }
+            }
+        }
+        if is_kill {
+            if let Some(dst) = self.players.get_mut(&result.dst_entity) {
+                dst.deaths += 1;
+            }
+        }
+    }
+
+    /// The match ends with the defeat of the team at the next update
+    pub fn on_core_destroyed(&mut self, team: Team) {
+        if self.is_running() {
+            log::info!("The core of {:?} has been destroyed", team);
+            self.destroyed_core = Some(team);
+        }
+    }
+
+    /// The chars whose respawn time has come
+    pub fn take_respawns(&mut self, now: ElapsedTime) -> Vec<CharEntityId> {
+        let (ready, waiting): (Vec<_>, Vec<_>) = self
+            .respawns
+            .drain(..)
+            .partition(|(_id, respawn_at)| respawn_at.has_already_passed(now));
+        self.respawns = waiting;
+        return ready.into_iter().map(|(id, _)| id).collect();
+    }
+
+    pub fn update(&mut self, now: ElapsedTime) -> Option<MatchEvent> {
+        let next_phase = match self.phase {
+            Phase::Lobby if self.players.len() >= self.config.min_players => Phase::Warmup {
+                ends_at: now.add_seconds(self.config.warmup_seconds),
+            },
+            Phase::Warmup { .. } if self.players.len() < self.config.min_players => Phase::Lobby,
+            Phase::Warmup { ends_at } if ends_at.has_already_passed(now) => {
+                self.reset_stats();
+                Phase::Running {
+                    ends_at: now.add_seconds(self.config.time_limit_seconds),
+                }
+            }
+            Phase::Running { ends_at } => {
+                let kills_to_win = self.config.kills_to_win;
+                if self.destroyed_core.is_some()
+                    || self.left_team_kills >= kills_to_win
+                    || self.right_team_kills >= kills_to_win
+                    || ends_at.has_already_passed(now)
+                {
+                    self.end(now)
+                } else {
+                    return None;
+                }
+            }
+            Phase::Ended { restart_at, .. } if restart_at.has_already_passed(now) => {
+                self.reset_stats();
+                self.respawns.clear();
+                self.phase = Phase::Lobby;
+                log::info!("Match restarted");
+                return Some(MatchEvent::ResetWorld);
+            }
+            _ => return None,
+        };
+        log::info!("Match phase: {:?}", next_phase);
+        self.phase = next_phase;
+        // the players are put back to their start positions when the fight begins
+        return if let Phase::Running { .. } = next_phase {
+            Some(MatchEvent::ResetWorld)
+        } else {
+            Some(MatchEvent::PhaseChanged)
+        };
+    }
+
+    fn end(&self, now: ElapsedTime) -> Phase {
+        let winner = if let Some(loser) = self.destroyed_core {
+            match loser {
+                Team::Left => Some(Team::Right),
+                Team::Right => Some(Team::Left),
+                _ => None,
+            }
+        } else if self.left_team_kills > self.right_team_kills {
+            Some(Team::Left)
+        } else if self.right_team_kills > self.left_team_kills {
+            Some(Team::Right)
+        } else {
+            None
+        };
+        Phase::Ended {
+            winner,
+            restart_at: now.add_seconds(self.config.restart_seconds),
+        }
+    }
+
+    fn reset_stats(&mut self) {
+        self.left_team_kills = 0;
+        self.right_team_kills = 0;
+        self.destroyed_core = None;
+        for stats in self.players.values_mut() {
+            stats.kills = 0;
+            stats.deaths = 0;
+            stats.damage_done = 0;
+            stats.healing_done = 0;
+        }
+    }
+
+    pub fn current_phase(&self, now: ElapsedTime) -> MatchPhase {
+        let remaining = |at: ElapsedTime| (at.as_f32() - now.as_f32()).max(0.0);
+        return match self.phase {
+            Phase::Lobby => MatchPhase::Lobby {
+                players: self.players.len(),
+                required_players: self.config.min_players,
+            },
+            Phase::Warmup { ends_at } => MatchPhase::Warmup {
+                remaining_seconds: remaining(ends_at),
+            },
+            Phase::Running { ends_at } => MatchPhase::Running {
+                remaining_seconds: remaining(ends_at),
+                left_team_kills: self.left_team_kills,
+                right_team_kills: self.right_team_kills,
+            },
+            Phase::Ended { winner, restart_at } => {
+                let mut stats: Vec<PlayerMatchStats> = self.players.values().cloned().collect();
+                stats.sort_by(|a, b| b.kills.cmp(&a.kills).then(a.deaths.cmp(&b.deaths)));
+                MatchPhase::Ended {
+                    winner,
+                    stats,
+                    remaining_seconds: remaining(restart_at),
+                }
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::prelude::*;
+
+    fn config() -> MatchConfig {
+        MatchConfig {
+            min_players: 2,
+            warmup_seconds: 1.0,
+            kills_to_win: 1,
+            time_limit_seconds: 100.0,
+            respawn_seconds: 1.0,
+            restart_seconds: 1.0,
+        }
+    }
+
+    #[test]
+    fn match_goes_through_every_phase() {
+        let mut world = World::new();
+        let left = CharEntityId::new(world.create_entity().build());
+        let right = CharEntityId::new(world.create_entity().build());
+        let mut lifecycle = MatchLifecycle::new(config());
+        let now = ElapsedTime(0.0);
+
+        assert_eq!(lifecycle.assign_team(0), Team::Left);
+        assert_eq!(lifecycle.take_pending_team(0), Some(Team::Left));
+        lifecycle.add_player(
+            left,
+            ServerEntityId::from_u64(1),
+            "left".to_owned(),
+            Team::Left,
+        );
+        assert_eq!(lifecycle.assign_team(1), Team::Right);
+        assert_eq!(lifecycle.take_pending_team(1), Some(Team::Right));
+        // not enough players
+        assert_eq!(lifecycle.update(now), None);
+        lifecycle.add_player(
+            right,
+            ServerEntityId::from_u64(2),
+            "right".to_owned(),
+            Team::Right,
+        );
+        assert_eq!(lifecycle.update(now), Some(MatchEvent::PhaseChanged));
+        assert_eq!(
+            lifecycle.update(now.add_seconds(2.0)),
+            Some(MatchEvent::ResetWorld)
+        );
+        assert!(lifecycle.is_running());
+
+        lifecycle.on_hp_modification(
+            &HpModificationResult {
+                src_entity: left,
+                dst_entity: right,
+                dst_hp: 0,
+                typ: HpModificationResultType::Ok(HpModificationType::Poison(30)),
+            },
+            now.add_seconds(2.0),
+        );
+        assert_eq!(
+            lifecycle.update(now.add_seconds(2.0)),
+            Some(MatchEvent::PhaseChanged)
+        );
+        match lifecycle.current_phase(now.add_seconds(2.0)) {
+            MatchPhase::Ended { winner, stats, .. } => {
+                assert_eq!(winner, Some(Team::Left));
+                assert_eq!(stats[0].kills, 1);
+                assert_eq!(stats[0].damage_done, 30);
+                assert_eq!(stats[1].deaths, 1);
+            }
+            _ => panic!(),
+        }
+        assert_eq!(lifecycle.take_respawns(now.add_seconds(3.5)), vec![right]);
+        assert_eq!(
+            lifecycle.update(now.add_seconds(4.0)),
+            Some(MatchEvent::ResetWorld)
+        );
+        assert!(!lifecycle.is_running());
+    }
+
+    #[test]
+    fn clients_still_loading_the_map_are_counted_in_the_team_assignment() {
+        let mut lifecycle = MatchLifecycle::new(config());
+
+        assert_eq!(lifecycle.assign_team(0), Team::Left);
+        assert_eq!(lifecycle.assign_team(1), Team::Right);
+        assert_eq!(lifecycle.assign_team(2), Team::Left);
+        // a repeated assignment does not count the client twice
+        assert_eq!(lifecycle.assign_team(2), Team::Left);
+
+        assert_eq!(lifecycle.take_pending_team(0), Some(Team::Left));
+        assert_eq!(lifecycle.take_pending_team(0), None);
+        assert_eq!(lifecycle.assign_team(3), Team::Right);
+    }
+
+    #[test]
+    fn destroying_the_core_ends_the_match() {
+        let mut world = World::new();
+        let left = CharEntityId::new(world.create_entity().build());
+        let right = CharEntityId::new(world.create_entity().build());
+        let right_tower = CharEntityId::new(world.create_entity().build());
+        let mut lifecycle = MatchLifecycle::new(config());
+        let now = ElapsedTime(0.0);
+        lifecycle.add_player(
+            left,
+            ServerEntityId::from_u64(1),
+            "left".to_owned(),
+            Team::Left,
+        );
+        lifecycle.add_player(
+            right,
+            ServerEntityId::from_u64(2),
+            "right".to_owned(),
+            Team::Right,
+        );
+        // ignored outside of a running match
+        lifecycle.on_core_destroyed(Team::Left);
+        lifecycle.update(now);
+        lifecycle.update(now.add_seconds(2.0));
+        assert!(lifecycle.is_running());
+
+        // a destroyed structure is not a kill
+        lifecycle.on_hp_modification(
+            &HpModificationResult {
+                src_entity: left,
+                dst_entity: right_tower,
+                dst_hp: 0,
+                typ: HpModificationResultType::Ok(HpModificationType::Poison(30)),
+            },
+            now.add_seconds(2.0),
+        );
+        assert_eq!(lifecycle.update(now.add_seconds(2.0)), None);
+
+        lifecycle.on_core_destroyed(Team::Left);
+        assert_eq!(
+            lifecycle.update(now.add_seconds(3.0)),
+            Some(MatchEvent::PhaseChanged)
+        );
+        match lifecycle.current_phase(now.add_seconds(3.0)) {
+            MatchPhase::Ended { winner, .. } => assert_eq!(winner, Some(Team::Right)),
+            _ => panic!(),
+        }
+    }
+}